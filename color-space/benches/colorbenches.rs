@@ -1,4 +1,4 @@
-use color_space::{palette, LinearRGB};
+use color_space::{BuiltinPalette, LinearRGB};
 use criterion::{criterion_group, criterion_main, Bencher, Criterion, Throughput};
 use image::Rgb;
 
@@ -15,13 +15,16 @@ fn bench_color_space(c: &mut Criterion) {
             b.iter(|| {
                 speeds
                     .iter()
-                    .map(|s| std::hint::black_box(palette(*s)))
+                    .map(|s| std::hint::black_box(BuiltinPalette::Classic.color(*s)))
                     .collect::<Vec<_>>()
             })
         },
     );
 
-    let colors: Vec<LinearRGB> = speeds.into_iter().map(palette).collect();
+    let colors: Vec<LinearRGB> = speeds
+        .into_iter()
+        .map(|s| BuiltinPalette::Classic.color(s))
+        .collect();
     let colors_ref: &[LinearRGB] = &colors;
     group.bench_with_input(
         "linear<f64> to srgb<u8> conversion",
@@ -36,6 +39,19 @@ fn bench_color_space(c: &mut Criterion) {
         },
     );
 
+    group.bench_with_input(
+        "linear<f64> to srgb<u8> exact conversion",
+        colors_ref,
+        |b: &mut Bencher, colors: &[LinearRGB]| {
+            b.iter(|| {
+                colors
+                    .iter()
+                    .map(|color| std::hint::black_box(color.to_srgb_exact()))
+                    .collect::<Vec<_>>()
+            })
+        },
+    );
+
     group.finish();
 }
 
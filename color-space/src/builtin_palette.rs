@@ -0,0 +1,279 @@
+//! The palettes that are built into the program and can be chosen by name.
+
+use core::fmt;
+use core::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::{srgb_to_linear_rgb, GradientFormat, GradientPalette, LinearRGB};
+
+/// The default gradient of Ultra Fractal, as a gradient file.
+const ULTRA_FRACTAL_DEFAULT: &str = "Default {
+gradient:
+  title=\"Default\" smooth=no
+  index=0 color=6555392
+  index=64 color=13331232
+  index=168 color=16777197
+  index=257 color=43775
+  index=343 color=512
+}
+";
+
+/// The colors of the viridis color map of matplotlib in the sRGB color space,
+/// at evenly spaced points from its start to its end.
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 45, 123],
+    [59, 82, 139],
+    [44, 114, 142],
+    [33, 144, 140],
+    [39, 173, 129],
+    [93, 200, 99],
+    [170, 220, 50],
+    [253, 231, 37],
+];
+
+/// A palette that is built into the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum BuiltinPalette {
+    /// The palette of the program before there were other palettes. As the escape speed increases from 0 to 1
+    /// the color transitions as
+    ///
+    /// black -> brown -> orange -> yellow -> cyan -> blue -> dark blue -> black.
+    #[default]
+    Classic,
+    /// Glows from black far outside the set through red, orange and yellow to white close to it.
+    Fire,
+    /// Glows from black far outside the set through blue and cyan to white close to it.
+    Ice,
+    /// Goes from black far outside the set to white close to it.
+    /// Unlike grayscale images it is stored in color and can be exported like the other palettes.
+    Grayscale,
+    /// The default gradient of Ultra Fractal: dark blue, light blue, white, orange and black,
+    /// once around from far outside the set to next to it.
+    UltraFractalDefault,
+    /// The perceptually uniform viridis color map of matplotlib,
+    /// from dark purple far outside the set to yellow close to it.
+    Viridis,
+}
+
+impl BuiltinPalette {
+    pub const ALL: [Self; 6] = [
+        Self::Classic,
+        Self::Fire,
+        Self::Ice,
+        Self::Grayscale,
+        Self::UltraFractalDefault,
+        Self::Viridis,
+    ];
+
+    /// Determines the color of a pixel in linear RGB color space
+    /// from the given escape speed.
+    /// Points inside the set, whose escape speed is 0, are black with every palette but the Ultra Fractal gradient.
+    #[must_use]
+    pub fn color(self, escape_speed: f64) -> LinearRGB {
+        let srgb = match self {
+            Self::Classic => return classic(escape_speed),
+            Self::UltraFractalDefault => {
+                return ultra_fractal_default().color(closeness(escape_speed))
+            }
+            _ if escape_speed <= 0.0 => return LinearRGB::default(),
+            Self::Fire => {
+                let closeness = closeness(escape_speed);
+                [
+                    3.0 * closeness,
+                    3.0 * closeness - 1.0,
+                    3.0 * closeness - 2.0,
+                ]
+            }
+            Self::Ice => {
+                let closeness = closeness(escape_speed);
+                [
+                    3.0 * closeness - 2.0,
+                    3.0 * closeness - 1.0,
+                    3.0 * closeness,
+                ]
+            }
+            Self::Grayscale => [closeness(escape_speed); 3],
+            Self::Viridis => {
+                let position = closeness(escape_speed) * (VIRIDIS.len() - 1) as f64;
+                // The closeness is at most 1, so the index is within the color map.
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let index = (position as usize).min(VIRIDIS.len() - 2);
+                let t = position - index as f64;
+                [0, 1, 2].map(|channel| {
+                    let from = f64::from(VIRIDIS[index][channel]);
+                    let to = f64::from(VIRIDIS[index + 1][channel]);
+                    (from + t * (to - from)) / f64::from(u8::MAX)
+                })
+            }
+        };
+        srgb.map(|channel| srgb_to_linear_rgb(channel.clamp(0.0, 1.0)))
+            .into()
+    }
+}
+
+/// Returns how close to the set a point with the given escape speed is, from 0 far outside it to 1 next to it.
+/// Most of the exterior escapes after a small share of the maximum number of iterations,
+/// so the square root spreads the escape speeds close to 1 over more of the range.
+fn closeness(escape_speed: f64) -> f64 {
+    (1.0 - escape_speed).clamp(0.0, 1.0).sqrt()
+}
+
+/// Returns the default gradient of Ultra Fractal, which is only parsed the first time it is needed.
+fn ultra_fractal_default() -> &'static GradientPalette {
+    static GRADIENT: OnceLock<GradientPalette> = OnceLock::new();
+    GRADIENT.get_or_init(|| {
+        GradientPalette::parse(ULTRA_FRACTAL_DEFAULT, GradientFormat::UltraFractal)
+            .expect("the default gradient of Ultra Fractal is a valid gradient")
+    })
+}
+
+/// Determines the color of a pixel in linear RGB color space with the classic palette of the program.
+/// The color map that this function uses was taken from the python code in
+/// [this](https://preshing.com/20110926/high-resolution-mandelbrot-in-obfuscated-python/) blog post.
+///
+/// # Note
+/// The function has not been tested for inputs outside the range \[0, 1\]
+/// and makes no guarantees about the output in that case.
+#[inline]
+fn classic(escape_speed: f64) -> LinearRGB {
+    let third_power = escape_speed * escape_speed * escape_speed;
+    let ninth_power = third_power * third_power * third_power;
+    let eighteenth_power = ninth_power * ninth_power;
+    let thirty_sixth_power = eighteenth_power * eighteenth_power;
+
+    [
+        255.0_f64.powf(-2.0 * ninth_power * thirty_sixth_power) * escape_speed,
+        14.0 / 51.0 * escape_speed - 176.0 / 51.0 * eighteenth_power + 701.0 / 255.0 * ninth_power,
+        16.0 / 51.0 * escape_speed + ninth_power
+            - 190.0 / 51.0
+                * thirty_sixth_power
+                * thirty_sixth_power
+                * eighteenth_power
+                * ninth_power,
+    ]
+    .map(srgb_to_linear_rgb)
+    .into()
+}
+
+impl fmt::Display for BuiltinPalette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Classic => write!(f, "classic"),
+            Self::Fire => write!(f, "fire"),
+            Self::Ice => write!(f, "ice"),
+            Self::Grayscale => write!(f, "grayscale"),
+            Self::UltraFractalDefault => write!(f, "ultra-fractal-default"),
+            Self::Viridis => write!(f, "viridis"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBuiltinPaletteError(String);
+
+impl fmt::Display for ParseBuiltinPaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown palette \"{}\", expected one of {}",
+            self.0,
+            BuiltinPalette::ALL
+                .map(|palette| format!("\"{palette}\""))
+                .join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ParseBuiltinPaletteError {}
+
+impl FromStr for BuiltinPalette {
+    type Err = ParseBuiltinPaletteError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase().replace(['_', ' '], "-");
+        match name.as_str() {
+            "classic" => Ok(Self::Classic),
+            "fire" => Ok(Self::Fire),
+            "ice" => Ok(Self::Ice),
+            "grayscale" | "greyscale" => Ok(Self::Grayscale),
+            "ultra-fractal-default" => Ok(Self::UltraFractalDefault),
+            "viridis" => Ok(Self::Viridis),
+            _ => Err(ParseBuiltinPaletteError(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_builtin_palette {
+    use super::*;
+
+    #[test]
+    fn check_parsing() {
+        for palette in BuiltinPalette::ALL {
+            assert_eq!(palette.to_string().parse(), Ok(palette));
+        }
+        assert_eq!(
+            " Ultra_Fractal_Default".parse(),
+            Ok(BuiltinPalette::UltraFractalDefault)
+        );
+        assert_eq!("greyscale".parse(), Ok(BuiltinPalette::Grayscale));
+        assert!("magma".parse::<BuiltinPalette>().is_err());
+    }
+
+    #[test]
+    fn check_colors() {
+        let srgb =
+            |palette: BuiltinPalette, escape_speed| palette.color(escape_speed).to_srgb_exact();
+
+        // The glowing palettes are black far outside the set and white next to it.
+        for palette in [
+            BuiltinPalette::Fire,
+            BuiltinPalette::Ice,
+            BuiltinPalette::Grayscale,
+        ] {
+            assert_eq!(srgb(palette, 1.0), [0, 0, 0], "{palette}");
+            assert_eq!(srgb(palette, 1e-9), [255, 255, 255], "{palette}");
+        }
+        // Only the Ultra Fractal gradient does not color the inside of the set black.
+        for palette in BuiltinPalette::ALL {
+            assert_eq!(
+                srgb(palette, 0.0) == [0, 0, 0],
+                palette != BuiltinPalette::UltraFractalDefault,
+                "{palette}"
+            );
+        }
+        let [r, g, b] = srgb(BuiltinPalette::Fire, 0.7);
+        assert!(r > g && g > b, "{:?}", [r, g, b]);
+        let [r, g, b] = srgb(BuiltinPalette::Ice, 0.7);
+        assert!(b > g && g > r, "{:?}", [r, g, b]);
+
+        assert_eq!(srgb(BuiltinPalette::Viridis, 1.0), VIRIDIS[0]);
+        assert_eq!(srgb(BuiltinPalette::Viridis, 1e-9), VIRIDIS[8]);
+
+        assert_eq!(srgb(BuiltinPalette::UltraFractalDefault, 1.0), [0, 7, 100]);
+        let closeness: f64 = 168.0 / 400.0;
+        assert_eq!(
+            srgb(
+                BuiltinPalette::UltraFractalDefault,
+                1.0 - closeness * closeness
+            ),
+            [237, 255, 255]
+        );
+        assert_eq!(
+            srgb(BuiltinPalette::UltraFractalDefault, 1.0),
+            srgb(BuiltinPalette::UltraFractalDefault, 0.0)
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn check_deprecated_aliases() {
+        assert_eq!(crate::Palette::Classic, crate::Palette::default());
+        assert_eq!(crate::palette(0.3), BuiltinPalette::Classic.color(0.3));
+    }
+}
@@ -0,0 +1,487 @@
+//! A palette defined by three independent curves, one for each color channel,
+//! written in a tiny expression language.
+//!
+//! A curve palette is written as `r=<expr>, g=<expr>, b=<expr>` where each expression
+//! is a function of the escape speed `s`, e.g. `r=s^0.7, g=sin(3.1*s), b=1-s`.
+//! The expressions support the operators `+`, `-`, `*`, `/` and `^`, parentheses,
+//! the constants `pi` and `e`, and the functions
+//! `sin`, `cos`, `tan`, `exp`, `ln`, `log2`, `sqrt`, `abs`, `floor`, `fract`, `min` and `max`.
+//! The value of each curve is interpreted as a channel value in the sRGB color space
+//! and is clamped to the range \[0, 1\] when it is quantized.
+//! The expressions are parsed by the [shared expression parser](crate::parse_expression).
+
+use core::fmt;
+use core::str::FromStr;
+
+use crate::expression::{
+    parse_expression, BinaryOperator, ExpressionLanguage, ParseExpressionError,
+    ParseExpressionErrorKind, MAX_NESTING_DEPTH,
+};
+use crate::{srgb_to_linear_rgb, LinearRGB};
+
+/// A palette made from three curves that map the escape speed
+/// to the red, green and blue channels of a color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurvePalette {
+    /// The text that the palette was parsed from.
+    source: String,
+    red: Expression,
+    green: Expression,
+    blue: Expression,
+}
+
+impl CurvePalette {
+    /// Evaluates the curves at the given escape speed and returns the resulting color.
+    #[must_use]
+    pub fn color(&self, escape_speed: f64) -> LinearRGB {
+        [&self.red, &self.green, &self.blue]
+            .map(|curve| srgb_to_linear_rgb(curve.evaluate(escape_speed)))
+            .into()
+    }
+}
+
+/// Displays the text that the palette was parsed from.
+impl fmt::Display for CurvePalette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// Serializes the text that the palette was parsed from.
+#[cfg(feature = "serde")]
+impl serde::Serialize for CurvePalette {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CurvePalette {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl FromStr for CurvePalette {
+    type Err = ParseCurveError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut red = None;
+        let mut green = None;
+        let mut blue = None;
+
+        let mut offset = 0;
+        for definition in split_top_level(s) {
+            let (channel, expression) = definition.split_once('=').ok_or(ParseCurveError::new(
+                ParseCurveErrorKind::MissingEquals,
+                offset,
+            ))?;
+
+            let slot = match channel.trim() {
+                "r" => &mut red,
+                "g" => &mut green,
+                "b" => &mut blue,
+                other => {
+                    return Err(ParseCurveError::new(
+                        ParseCurveErrorKind::UnknownChannel(other.to_owned()),
+                        offset,
+                    ))
+                }
+            };
+
+            if slot.is_some() {
+                return Err(ParseCurveError::new(
+                    ParseCurveErrorKind::DuplicateChannel(channel.trim().to_owned()),
+                    offset,
+                ));
+            }
+
+            let expression_offset = offset + channel.len() + 1;
+            *slot = Some(
+                parse_expression::<Curves>(expression)
+                    .map_err(|e| ParseCurveError::from(e).shifted(expression_offset))?,
+            );
+
+            // Skip past the definition and the comma.
+            offset += definition.len() + 1;
+        }
+
+        match (red, green, blue) {
+            (Some(red), Some(green), Some(blue)) => Ok(Self {
+                source: s.to_owned(),
+                red,
+                green,
+                blue,
+            }),
+            (None, _, _) => Err(ParseCurveError::new(
+                ParseCurveErrorKind::MissingChannel('r'),
+                s.len(),
+            )),
+            (_, None, _) => Err(ParseCurveError::new(
+                ParseCurveErrorKind::MissingChannel('g'),
+                s.len(),
+            )),
+            (_, _, None) => Err(ParseCurveError::new(
+                ParseCurveErrorKind::MissingChannel('b'),
+                s.len(),
+            )),
+        }
+    }
+}
+
+/// Splits the input at every comma that is not inside parentheses.
+fn split_top_level(s: &str) -> impl Iterator<Item = &str> {
+    let mut depth: usize = 0;
+    let mut start = 0;
+    let mut parts = Vec::new();
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(&s[start..]);
+    parts.into_iter()
+}
+
+/// The abstract syntax tree of a curve expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    Constant(f64),
+    EscapeSpeed,
+    Negate(Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+    Function(Function, Vec<Expression>),
+}
+
+impl Expression {
+    fn evaluate(&self, s: f64) -> f64 {
+        match self {
+            Self::Constant(c) => *c,
+            Self::EscapeSpeed => s,
+            Self::Negate(e) => -e.evaluate(s),
+            Self::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.evaluate(s), rhs.evaluate(s));
+                match op {
+                    BinaryOperator::Add => lhs + rhs,
+                    BinaryOperator::Subtract => lhs - rhs,
+                    BinaryOperator::Multiply => lhs * rhs,
+                    BinaryOperator::Divide => lhs / rhs,
+                    BinaryOperator::Power => lhs.powf(rhs),
+                }
+            }
+            Self::Function(function, arguments) => {
+                let x = arguments[0].evaluate(s);
+                match function {
+                    Function::Sin => x.sin(),
+                    Function::Cos => x.cos(),
+                    Function::Tan => x.tan(),
+                    Function::Exp => x.exp(),
+                    Function::Ln => x.ln(),
+                    Function::Log2 => x.log2(),
+                    Function::Sqrt => x.sqrt(),
+                    Function::Abs => x.abs(),
+                    Function::Floor => x.floor(),
+                    Function::Fract => x.fract(),
+                    Function::Min => x.min(arguments[1].evaluate(s)),
+                    Function::Max => x.max(arguments[1].evaluate(s)),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Exp,
+    Ln,
+    Log2,
+    Sqrt,
+    Abs,
+    Floor,
+    Fract,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sin" => Some(Self::Sin),
+            "cos" => Some(Self::Cos),
+            "tan" => Some(Self::Tan),
+            "exp" => Some(Self::Exp),
+            "ln" => Some(Self::Ln),
+            "log2" => Some(Self::Log2),
+            "sqrt" => Some(Self::Sqrt),
+            "abs" => Some(Self::Abs),
+            "floor" => Some(Self::Floor),
+            "fract" => Some(Self::Fract),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            _ => None,
+        }
+    }
+
+    const fn arity(self) -> usize {
+        match self {
+            Self::Min | Self::Max => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// The language of curve expressions, a function of the escape speed `s` over the real numbers.
+struct Curves;
+
+impl ExpressionLanguage for Curves {
+    type Expression = Expression;
+    type Function = Function;
+
+    fn constant(value: f64) -> Expression {
+        Expression::Constant(value)
+    }
+
+    fn variable(name: &str) -> Option<Expression> {
+        match name {
+            "s" => Some(Expression::EscapeSpeed),
+            "pi" => Some(Expression::Constant(core::f64::consts::PI)),
+            "e" => Some(Expression::Constant(core::f64::consts::E)),
+            _ => None,
+        }
+    }
+
+    fn function(name: &str) -> Option<Function> {
+        Function::from_name(name)
+    }
+
+    fn arity(function: Function) -> usize {
+        function.arity()
+    }
+
+    fn negate(expression: Expression) -> Expression {
+        Expression::Negate(Box::new(expression))
+    }
+
+    fn binary(operator: BinaryOperator, lhs: Expression, rhs: Expression) -> Expression {
+        Expression::Binary(operator, Box::new(lhs), Box::new(rhs))
+    }
+
+    fn call(function: Function, arguments: Vec<Expression>) -> Expression {
+        Expression::Function(function, arguments)
+    }
+}
+
+/// An error that occured while parsing a [`CurvePalette`].
+/// Contains the byte position in the input where the error was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCurveError {
+    kind: ParseCurveErrorKind,
+    position: usize,
+}
+
+impl ParseCurveError {
+    const fn new(kind: ParseCurveErrorKind, position: usize) -> Self {
+        Self { kind, position }
+    }
+
+    const fn shifted(mut self, offset: usize) -> Self {
+        self.position += offset;
+        self
+    }
+
+    #[must_use]
+    pub const fn kind(&self) -> &ParseCurveErrorKind {
+        &self.kind
+    }
+
+    /// The byte position in the input where the error was found.
+    #[must_use]
+    pub const fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl From<ParseExpressionError> for ParseCurveError {
+    fn from(error: ParseExpressionError) -> Self {
+        let (kind, position) = error.into_parts();
+        let kind = match kind {
+            ParseExpressionErrorKind::UnexpectedCharacter(c) => {
+                ParseCurveErrorKind::UnexpectedCharacter(c)
+            }
+            ParseExpressionErrorKind::UnexpectedEnd => ParseCurveErrorKind::UnexpectedEnd,
+            ParseExpressionErrorKind::Expected { expected, found } => {
+                ParseCurveErrorKind::Expected { expected, found }
+            }
+            ParseExpressionErrorKind::InvalidNumber(n) => ParseCurveErrorKind::InvalidNumber(n),
+            ParseExpressionErrorKind::UnknownVariable(v) => ParseCurveErrorKind::UnknownVariable(v),
+            ParseExpressionErrorKind::UnknownFunction(name) => {
+                ParseCurveErrorKind::UnknownFunction(name)
+            }
+            ParseExpressionErrorKind::WrongArgumentCount {
+                function,
+                expected,
+                found,
+            } => ParseCurveErrorKind::WrongArgumentCount {
+                function,
+                expected,
+                found,
+            },
+            ParseExpressionErrorKind::TooDeeplyNested => ParseCurveErrorKind::TooDeeplyNested,
+        };
+        Self::new(kind, position)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseCurveErrorKind {
+    MissingEquals,
+    UnknownChannel(String),
+    DuplicateChannel(String),
+    MissingChannel(char),
+    UnexpectedCharacter(char),
+    UnexpectedEnd,
+    Expected {
+        expected: char,
+        found: Option<char>,
+    },
+    InvalidNumber(String),
+    UnknownVariable(String),
+    UnknownFunction(String),
+    WrongArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// The expression is nested more than [`MAX_NESTING_DEPTH`] levels deep.
+    TooDeeplyNested,
+}
+
+impl fmt::Display for ParseCurveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at position {}: ", self.position)?;
+        match &self.kind {
+            ParseCurveErrorKind::MissingEquals => {
+                write!(f, "every curve must be given in the form \"channel=expression\"")
+            }
+            ParseCurveErrorKind::UnknownChannel(c) => {
+                write!(f, "unknown channel \"{c}\", expected one of r, g or b")
+            }
+            ParseCurveErrorKind::DuplicateChannel(c) => {
+                write!(f, "the curve for channel {c} is given more than once")
+            }
+            ParseCurveErrorKind::MissingChannel(c) => {
+                write!(f, "no curve was given for channel {c}")
+            }
+            ParseCurveErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character '{c}'"),
+            ParseCurveErrorKind::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            ParseCurveErrorKind::Expected { expected, found } => match found {
+                Some(c) => write!(f, "expected '{expected}' but found '{c}'"),
+                None => write!(f, "expected '{expected}' but the expression ended"),
+            },
+            ParseCurveErrorKind::InvalidNumber(n) => write!(f, "\"{n}\" is not a valid number"),
+            ParseCurveErrorKind::UnknownVariable(v) => write!(
+                f,
+                "unknown variable \"{v}\", the only variable is s and the only constants are pi and e"
+            ),
+            ParseCurveErrorKind::UnknownFunction(name) => write!(f, "unknown function \"{name}\""),
+            ParseCurveErrorKind::WrongArgumentCount {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "the function {function} takes {expected} argument(s) but was given {found}"
+            ),
+            ParseCurveErrorKind::TooDeeplyNested => write!(
+                f,
+                "the expression is nested more than {MAX_NESTING_DEPTH} levels deep"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseCurveError {}
+
+#[cfg(test)]
+mod test_curves {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn evaluate(source: &str, s: f64) -> f64 {
+        parse_expression::<Curves>(source).unwrap().evaluate(s)
+    }
+
+    #[test]
+    fn check_operator_precedence() {
+        assert_relative_eq!(evaluate("1 + 2 * 3", 0.0), 7.0);
+        assert_relative_eq!(evaluate("(1 + 2) * 3", 0.0), 9.0);
+        assert_relative_eq!(evaluate("2^3^2", 0.0), 512.0);
+        assert_relative_eq!(evaluate("-2^2", 0.0), -4.0);
+        assert_relative_eq!(evaluate("1 - s - s", 0.25), 0.5);
+        assert_relative_eq!(evaluate("max(s, 0.5) / 2", 0.25), 0.25);
+        assert_relative_eq!(evaluate("sin(pi*s)", 0.5), 1.0);
+    }
+
+    #[test]
+    fn check_palette_parsing() {
+        let palette: CurvePalette = "r=s^0.7, g=sin(3.1*s), b=1-s".parse().unwrap();
+        assert_eq!(palette.color(0.0), LinearRGB::new(0.0, 0.0, 1.0));
+        // The channels may be given in any order.
+        assert!("b=s, r=s, g=s".parse::<CurvePalette>().is_ok());
+        // and functions with several arguments may be used.
+        assert!("r=min(s, 0.5), g=max(s, 0.2), b=s"
+            .parse::<CurvePalette>()
+            .is_ok());
+    }
+
+    #[test]
+    fn check_error_messages() {
+        let error = "r=s, g=s*, b=s".parse::<CurvePalette>().unwrap_err();
+        assert_eq!(error.kind(), &ParseCurveErrorKind::UnexpectedEnd);
+        assert_eq!(error.position(), 9);
+
+        let error = "r=s, g=x, b=s".parse::<CurvePalette>().unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &ParseCurveErrorKind::UnknownVariable("x".to_owned())
+        );
+        assert_eq!(error.position(), 7);
+
+        let error = "r=s, g=s".parse::<CurvePalette>().unwrap_err();
+        assert_eq!(error.kind(), &ParseCurveErrorKind::MissingChannel('b'));
+
+        let error = "r=s, r=s, b=s".parse::<CurvePalette>().unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &ParseCurveErrorKind::DuplicateChannel("r".to_owned())
+        );
+
+        let error = "r=min(s), g=s, b=s".parse::<CurvePalette>().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ParseCurveErrorKind::WrongArgumentCount { .. }
+        ));
+
+        let error = "r=(s, g=s, b=s".parse::<CurvePalette>().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ParseCurveErrorKind::Expected { expected: ')', .. }
+        ));
+
+        let error = format!("r=s, g=s, b={}", "-".repeat(1000) + "s")
+            .parse::<CurvePalette>()
+            .unwrap_err();
+        assert_eq!(error.kind(), &ParseCurveErrorKind::TooDeeplyNested);
+        assert_eq!(error.position(), 12 + MAX_NESTING_DEPTH);
+    }
+}
@@ -0,0 +1,443 @@
+//! The parser of the small expression languages that curve palettes and custom iteration formulas are written in.
+//!
+//! The languages share their syntax: the operators `+`, `-`, `*`, `/` and `^`, parentheses, numbers,
+//! names of variables and constants, and calls of functions with one or more arguments.
+//! What the names mean and what the parsed expression is made of is decided by an [`ExpressionLanguage`].
+
+use core::fmt;
+
+/// How deeply parentheses, function calls, negations and exponents may be nested in an expression.
+/// Deeper expressions are rejected with [`ParseExpressionErrorKind::TooDeeplyNested`]
+/// instead of overflowing the stack of the parser.
+pub const MAX_NESTING_DEPTH: usize = 100;
+
+/// A language that expressions can be parsed into with [`parse_expression`].
+pub trait ExpressionLanguage {
+    /// The parsed expression.
+    type Expression;
+    /// The functions that can be called in the language.
+    type Function: Copy;
+
+    /// A number literal.
+    fn constant(value: f64) -> Self::Expression;
+
+    /// Looks up a variable or named constant, returns `None` if there is none with the name.
+    fn variable(name: &str) -> Option<Self::Expression>;
+
+    /// Looks up a function, returns `None` if there is none with the name.
+    fn function(name: &str) -> Option<Self::Function>;
+
+    /// How many arguments the function takes.
+    fn arity(function: Self::Function) -> usize;
+
+    fn negate(expression: Self::Expression) -> Self::Expression;
+
+    fn binary(
+        operator: BinaryOperator,
+        lhs: Self::Expression,
+        rhs: Self::Expression,
+    ) -> Self::Expression;
+
+    /// Calls a function with as many arguments as its [`arity`](Self::arity).
+    fn call(function: Self::Function, arguments: Vec<Self::Expression>) -> Self::Expression;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+/// Parses the entire source as one expression in the given language.
+///
+/// # Errors
+/// Returns an error if the source is not a valid expression,
+/// or if it is nested more than [`MAX_NESTING_DEPTH`] levels deep.
+pub fn parse_expression<L: ExpressionLanguage>(
+    source: &str,
+) -> Result<L::Expression, ParseExpressionError> {
+    Parser::<L>::new(source).parse()
+}
+
+/// A recursive descent parser for expressions with the grammar
+///
+/// ```text
+/// expression = term (("+" | "-") term)*
+/// term       = unary (("*" | "/") unary)*
+/// unary      = "-" unary | power
+/// power      = atom ("^" unary)?
+/// atom       = number | name | name "(" expression ("," expression)* ")" | "(" expression ")"
+/// ```
+struct Parser<'a, L> {
+    source: &'a str,
+    position: usize,
+    /// How many levels of nesting the parser is in, every recursion passes through `unary`.
+    depth: usize,
+    language: core::marker::PhantomData<L>,
+}
+
+impl<'a, L: ExpressionLanguage> Parser<'a, L> {
+    const fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            position: 0,
+            depth: 0,
+            language: core::marker::PhantomData,
+        }
+    }
+
+    /// Parses the entire source as one expression.
+    fn parse(mut self) -> Result<L::Expression, ParseExpressionError> {
+        let expression = self.expression()?;
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok(expression),
+            Some(c) => Err(self.error(ParseExpressionErrorKind::UnexpectedCharacter(c))),
+        }
+    }
+
+    fn expression(&mut self) -> Result<L::Expression, ParseExpressionError> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek_token() {
+                Some('+') => BinaryOperator::Add,
+                Some('-') => BinaryOperator::Subtract,
+                _ => return Ok(lhs),
+            };
+            self.position += 1;
+            lhs = L::binary(op, lhs, self.term()?);
+        }
+    }
+
+    fn term(&mut self) -> Result<L::Expression, ParseExpressionError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek_token() {
+                Some('*') => BinaryOperator::Multiply,
+                Some('/') => BinaryOperator::Divide,
+                _ => return Ok(lhs),
+            };
+            self.position += 1;
+            lhs = L::binary(op, lhs, self.unary()?);
+        }
+    }
+
+    fn unary(&mut self) -> Result<L::Expression, ParseExpressionError> {
+        if self.depth == MAX_NESTING_DEPTH {
+            return Err(self.error(ParseExpressionErrorKind::TooDeeplyNested));
+        }
+        self.depth += 1;
+        let expression = if self.peek_token() == Some('-') {
+            self.position += 1;
+            self.unary().map(L::negate)
+        } else {
+            self.power()
+        };
+        self.depth -= 1;
+        expression
+    }
+
+    fn power(&mut self) -> Result<L::Expression, ParseExpressionError> {
+        let base = self.atom()?;
+        if self.peek_token() == Some('^') {
+            self.position += 1;
+            // Exponentiation is right associative, so we parse the exponent with `unary`.
+            Ok(L::binary(BinaryOperator::Power, base, self.unary()?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<L::Expression, ParseExpressionError> {
+        match self.peek_token() {
+            None => Err(self.error(ParseExpressionErrorKind::UnexpectedEnd)),
+            Some('(') => {
+                self.position += 1;
+                let expression = self.expression()?;
+                self.expect(')')?;
+                Ok(expression)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => self.name(),
+            Some(c) => Err(self.error(ParseExpressionErrorKind::UnexpectedCharacter(c))),
+        }
+    }
+
+    fn number(&mut self) -> Result<L::Expression, ParseExpressionError> {
+        let start = self.position;
+        let length = self.source[start..]
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(self.source.len() - start);
+        self.position += length;
+        let literal = &self.source[start..self.position];
+        literal.parse().map(L::constant).map_err(|_| {
+            ParseExpressionError::new(
+                ParseExpressionErrorKind::InvalidNumber(literal.to_owned()),
+                start,
+            )
+        })
+    }
+
+    fn name(&mut self) -> Result<L::Expression, ParseExpressionError> {
+        let start = self.position;
+        let length = self.source[start..]
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(self.source.len() - start);
+        self.position += length;
+        let name = &self.source[start..self.position];
+
+        if self.peek_token() == Some('(') {
+            let function = L::function(name).ok_or(ParseExpressionError::new(
+                ParseExpressionErrorKind::UnknownFunction(name.to_owned()),
+                start,
+            ))?;
+            self.position += 1;
+
+            let mut arguments = vec![self.expression()?];
+            while self.peek_token() == Some(',') {
+                self.position += 1;
+                arguments.push(self.expression()?);
+            }
+            self.expect(')')?;
+
+            if arguments.len() == L::arity(function) {
+                Ok(L::call(function, arguments))
+            } else {
+                Err(ParseExpressionError::new(
+                    ParseExpressionErrorKind::WrongArgumentCount {
+                        function: name.to_owned(),
+                        expected: L::arity(function),
+                        found: arguments.len(),
+                    },
+                    start,
+                ))
+            }
+        } else {
+            L::variable(name).ok_or(ParseExpressionError::new(
+                ParseExpressionErrorKind::UnknownVariable(name.to_owned()),
+                start,
+            ))
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseExpressionError> {
+        match self.peek_token() {
+            Some(c) if c == expected => {
+                self.position += 1;
+                Ok(())
+            }
+            found => Err(self.error(ParseExpressionErrorKind::Expected { expected, found })),
+        }
+    }
+
+    /// Skips whitespace and returns the next character without consuming it.
+    fn peek_token(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.peek()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.position..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.position += c.len_utf8();
+        }
+    }
+
+    const fn error(&self, kind: ParseExpressionErrorKind) -> ParseExpressionError {
+        ParseExpressionError::new(kind, self.position)
+    }
+}
+
+/// An error that occured while parsing an expression.
+/// Contains the byte position in the input where the error was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseExpressionError {
+    kind: ParseExpressionErrorKind,
+    position: usize,
+}
+
+impl ParseExpressionError {
+    const fn new(kind: ParseExpressionErrorKind, position: usize) -> Self {
+        Self { kind, position }
+    }
+
+    #[must_use]
+    pub const fn kind(&self) -> &ParseExpressionErrorKind {
+        &self.kind
+    }
+
+    /// The byte position in the input where the error was found.
+    #[must_use]
+    pub const fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Splits the error into its kind and position.
+    #[must_use]
+    pub fn into_parts(self) -> (ParseExpressionErrorKind, usize) {
+        (self.kind, self.position)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseExpressionErrorKind {
+    UnexpectedCharacter(char),
+    UnexpectedEnd,
+    Expected {
+        expected: char,
+        found: Option<char>,
+    },
+    InvalidNumber(String),
+    UnknownVariable(String),
+    UnknownFunction(String),
+    WrongArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// The expression is nested more than [`MAX_NESTING_DEPTH`] levels deep.
+    TooDeeplyNested,
+}
+
+impl fmt::Display for ParseExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at position {}: ", self.position)?;
+        match &self.kind {
+            ParseExpressionErrorKind::UnexpectedCharacter(c) => {
+                write!(f, "unexpected character '{c}'")
+            }
+            ParseExpressionErrorKind::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            ParseExpressionErrorKind::Expected { expected, found } => match found {
+                Some(c) => write!(f, "expected '{expected}' but found '{c}'"),
+                None => write!(f, "expected '{expected}' but the expression ended"),
+            },
+            ParseExpressionErrorKind::InvalidNumber(n) => {
+                write!(f, "\"{n}\" is not a valid number")
+            }
+            ParseExpressionErrorKind::UnknownVariable(v) => write!(f, "unknown variable \"{v}\""),
+            ParseExpressionErrorKind::UnknownFunction(name) => {
+                write!(f, "unknown function \"{name}\"")
+            }
+            ParseExpressionErrorKind::WrongArgumentCount {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "the function {function} takes {expected} argument(s) but was given {found}"
+            ),
+            ParseExpressionErrorKind::TooDeeplyNested => write!(
+                f,
+                "the expression is nested more than {MAX_NESTING_DEPTH} levels deep"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseExpressionError {}
+
+#[cfg(test)]
+mod test_expression {
+    use super::*;
+
+    /// Integer arithmetic with the variable `x` = 1, which is enough to exercise the parser.
+    struct Integers;
+
+    impl ExpressionLanguage for Integers {
+        type Expression = i64;
+        type Function = ();
+
+        #[allow(clippy::cast_possible_truncation)]
+        fn constant(value: f64) -> i64 {
+            value as i64
+        }
+
+        fn variable(name: &str) -> Option<i64> {
+            (name == "x").then_some(1)
+        }
+
+        fn function(name: &str) -> Option<()> {
+            (name == "max").then_some(())
+        }
+
+        fn arity((): ()) -> usize {
+            2
+        }
+
+        fn negate(expression: i64) -> i64 {
+            -expression
+        }
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        fn binary(operator: BinaryOperator, lhs: i64, rhs: i64) -> i64 {
+            match operator {
+                BinaryOperator::Add => lhs + rhs,
+                BinaryOperator::Subtract => lhs - rhs,
+                BinaryOperator::Multiply => lhs * rhs,
+                BinaryOperator::Divide => lhs / rhs,
+                BinaryOperator::Power => lhs.pow(rhs as u32),
+            }
+        }
+
+        fn call((): (), arguments: Vec<i64>) -> i64 {
+            arguments[0].max(arguments[1])
+        }
+    }
+
+    #[test]
+    fn check_parsing() {
+        assert_eq!(parse_expression::<Integers>("1 + 2 * 3"), Ok(7));
+        assert_eq!(parse_expression::<Integers>("(1 + 2) * 3"), Ok(9));
+        assert_eq!(parse_expression::<Integers>("2^3^2"), Ok(512));
+        assert_eq!(parse_expression::<Integers>("-2^2"), Ok(-4));
+        assert_eq!(parse_expression::<Integers>("max(x, -x) - x - x"), Ok(-1));
+
+        let error = parse_expression::<Integers>("max(x)").unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ParseExpressionErrorKind::WrongArgumentCount { .. }
+        ));
+        let error = parse_expression::<Integers>("1 + y").unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &ParseExpressionErrorKind::UnknownVariable("y".to_owned())
+        );
+        assert_eq!(error.position(), 4);
+    }
+
+    #[test]
+    fn check_nesting_limit() {
+        let nested = |depth: usize| format!("{}x{}", "(".repeat(depth - 1), ")".repeat(depth - 1));
+        assert_eq!(
+            parse_expression::<Integers>(&nested(MAX_NESTING_DEPTH)),
+            Ok(1)
+        );
+        assert_eq!(
+            parse_expression::<Integers>(&nested(MAX_NESTING_DEPTH + 1))
+                .unwrap_err()
+                .kind(),
+            &ParseExpressionErrorKind::TooDeeplyNested
+        );
+
+        // Every way of nesting counts towards the limit, and deep expressions are rejected without overflowing the stack.
+        for deep in [
+            "(".repeat(100_000),
+            "-".repeat(100_000),
+            "max(x, ".repeat(100_000),
+            "x^".repeat(100_000),
+        ] {
+            assert_eq!(
+                parse_expression::<Integers>(&deep).unwrap_err().kind(),
+                &ParseExpressionErrorKind::TooDeeplyNested,
+                "{}",
+                &deep[..8]
+            );
+        }
+    }
+}
@@ -0,0 +1,675 @@
+//! Palettes made from the color gradients of other fractal programs.
+//!
+//! Two file formats are supported:
+//!  - Ultra Fractal gradients (`.ugr`), where each gradient is written as
+//!    `name { gradient: title="name" index=0 color=8716288 index=200 color=255 ... }`.
+//!    The indices run from 0 to 399 around the gradient, and each color is the decimal integer
+//!    `red + 256 * green + 65536 * blue`. A file can contain several gradients, only the first one is used.
+//!  - Fractint color maps (`.map`), with one `red green blue` triplet of integers from 0 to 255 per line,
+//!    optionally followed by a comment. The colors are spread evenly around the gradient.
+//!
+//! Both formats describe cyclic gradients: after the last color the gradient blends back into the first one.
+//! The colors are interpolated linearly in the sRGB color space, like Ultra Fractal does for gradients
+//! that are not smoothed.
+
+use core::fmt;
+use core::num::NonZeroU32;
+use core::str::FromStr;
+
+use crate::{quantize_srgb, srgb_to_linear_rgb, ColorMap, LinearRGB};
+
+/// The number of indices around an Ultra Fractal gradient.
+const UGR_INDICES: u32 = 400;
+
+/// The number of colors that are written to a Fractint color map.
+const MAP_COLORS: u32 = 256;
+
+/// A cyclic palette made from colors at positions along a gradient,
+/// usually loaded from a gradient file, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GradientPalette {
+    /// The name of the gradient.
+    name: String,
+    /// The number of positions around the gradient.
+    period: u32,
+    /// The positions in \[0, `period`) and 8 bit sRGB colors of the control points,
+    /// sorted by position. Never empty.
+    stops: Vec<(u32, [u8; 3])>,
+}
+
+impl GradientPalette {
+    /// Parses a gradient in the given format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text is not a valid gradient in that format.
+    pub fn parse(text: &str, format: GradientFormat) -> Result<Self, ParseGradientError> {
+        match format {
+            GradientFormat::UltraFractal => parse_ugr(text),
+            GradientFormat::Fractint => parse_map(text),
+        }
+    }
+
+    /// Samples the given palette at `stops` evenly spaced escape speeds from 0 up to, but not including, 1
+    /// and creates a gradient from the resulting colors.
+    /// This makes it possible to export any palette to a gradient file.
+    #[must_use]
+    pub fn sampled(palette: &impl ColorMap, stops: NonZeroU32, name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            period: stops.get(),
+            stops: (0..stops.get())
+                .map(|i| {
+                    let escape_speed = f64::from(i) / f64::from(stops.get());
+                    (i, palette.color(escape_speed).to_srgb_exact())
+                })
+                .collect(),
+        }
+    }
+
+    /// The name of the gradient.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Determines the color at the given escape speed.
+    /// The gradient repeats with a period of 1.
+    #[must_use]
+    pub fn color(&self, escape_speed: f64) -> LinearRGB {
+        let [r, g, b] = self.srgb(escape_speed).map(srgb_to_linear_rgb);
+        LinearRGB::new(r, g, b)
+    }
+
+    /// Determines the color at the given escape speed in the sRGB color space.
+    fn srgb(&self, escape_speed: f64) -> [f64; 3] {
+        let position = escape_speed.rem_euclid(1.0) * f64::from(self.period);
+        // The index of the first stop after the position.
+        let next = self
+            .stops
+            .partition_point(|&(index, _)| f64::from(index) <= position);
+
+        let len = self.stops.len();
+        let (before, after) = (self.stops[(next + len - 1) % len], self.stops[next % len]);
+        // Unwrap the positions of the stops around the cycle so that before <= position <= after.
+        let mut start = f64::from(before.0);
+        let mut end = f64::from(after.0);
+        if next == 0 {
+            start -= f64::from(self.period);
+        }
+        if next == len {
+            end += f64::from(self.period);
+        }
+
+        let t = if end > start {
+            (position - start) / (end - start)
+        } else {
+            0.0
+        };
+        [0, 1, 2].map(|channel| {
+            let from = f64::from(before.1[channel]) / f64::from(u8::MAX);
+            let to = f64::from(after.1[channel]) / f64::from(u8::MAX);
+            from + t * (to - from)
+        })
+    }
+
+    /// Writes the gradient in the given format.
+    #[must_use]
+    pub fn write(&self, format: GradientFormat) -> String {
+        let mut text = String::new();
+        match format {
+            GradientFormat::UltraFractal => {
+                // Ultra Fractal does not allow quotes in titles.
+                let title = self.name.replace('"', "'");
+                text.push_str(&format!(
+                    "{} {{\ngradient:\n  title=\"{title}\" smooth=no\n",
+                    ugr_identifier(&self.name)
+                ));
+                let mut previous = None;
+                for &(position, [r, g, b]) in &self.stops {
+                    let index =
+                        u64::from(position) * u64::from(UGR_INDICES) / u64::from(self.period);
+                    // Stops that end up at the same index would only make a hard edge in the gradient.
+                    if previous == Some(index) {
+                        continue;
+                    }
+                    previous = Some(index);
+                    let color = u32::from(r) | u32::from(g) << 8 | u32::from(b) << 16;
+                    text.push_str(&format!("  index={index} color={color}\n"));
+                }
+                text.push_str("}\n");
+            }
+            GradientFormat::Fractint => {
+                for i in 0..MAP_COLORS {
+                    let [r, g, b] = self
+                        .srgb(f64::from(i) / f64::from(MAP_COLORS))
+                        .map(quantize_srgb);
+                    text.push_str(&format!("{r} {g} {b}\n"));
+                }
+            }
+        }
+        text
+    }
+}
+
+/// The fields of a [`GradientPalette`] as they are serialized.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "GradientPalette")]
+struct GradientFields {
+    name: String,
+    period: u32,
+    stops: Vec<(u32, [u8; 3])>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for GradientPalette {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GradientFields {
+            name: self.name.clone(),
+            period: self.period,
+            stops: self.stops.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Checks that the gradient has stops, and that they are sorted by position and within its period.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for GradientPalette {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let GradientFields {
+            name,
+            period,
+            stops,
+        } = GradientFields::deserialize(deserializer)?;
+        if stops.is_empty() {
+            return Err(serde::de::Error::custom("the gradient has no colors"));
+        }
+        if stops.windows(2).any(|pair| pair[0].0 > pair[1].0)
+            || stops.iter().any(|&(position, _)| position >= period)
+        {
+            return Err(serde::de::Error::custom(format!(
+                "the positions of the colors of the gradient must be sorted and below its period of {period}"
+            )));
+        }
+        Ok(Self {
+            name,
+            period,
+            stops,
+        })
+    }
+}
+
+/// Displays the name of the gradient.
+impl fmt::Display for GradientPalette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// Turns the name of a gradient into a name that Ultra Fractal accepts in front of its braces.
+fn ugr_identifier(name: &str) -> String {
+    let identifier: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    if identifier.is_empty() {
+        "gradient".to_owned()
+    } else {
+        identifier
+    }
+}
+
+fn parse_ugr(text: &str) -> Result<GradientPalette, ParseGradientError> {
+    let open = text.find('{').ok_or(ParseGradientError::new(
+        ParseGradientErrorKind::Missing("{"),
+        1,
+    ))?;
+    let mut name = text[..open]
+        .lines()
+        .last()
+        .unwrap_or_default()
+        .trim()
+        .to_owned();
+
+    let mut line = line_number(text, open);
+    let mut in_gradient = false;
+    let mut closed = false;
+    let mut index = None;
+    let mut stops = Vec::new();
+    'lines: for contents in text[open + 1..].split('\n') {
+        for token in tokens(contents) {
+            if token == "}" {
+                closed = true;
+                break 'lines;
+            }
+            if let Some(section) = token.strip_suffix(':') {
+                in_gradient = section == "gradient";
+                continue;
+            }
+            if !in_gradient {
+                continue;
+            }
+            let Some((key, value)) = token.split_once('=') else {
+                continue;
+            };
+            match key {
+                "title" => name = value.trim_matches('"').to_owned(),
+                "index" => {
+                    if index.is_some() {
+                        return Err(ParseGradientError::new(
+                            ParseGradientErrorKind::Missing("color"),
+                            line,
+                        ));
+                    }
+                    let parsed: i64 = value.parse().map_err(|_| {
+                        ParseGradientError::new(
+                            ParseGradientErrorKind::InvalidNumber(value.to_owned()),
+                            line,
+                        )
+                    })?;
+                    // Rotated gradients can have indices outside the range of the gradient.
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    let wrapped = parsed.rem_euclid(i64::from(UGR_INDICES)) as u32;
+                    index = Some(wrapped);
+                }
+                "color" => {
+                    let index = index.take().ok_or(ParseGradientError::new(
+                        ParseGradientErrorKind::Missing("index"),
+                        line,
+                    ))?;
+                    let color: u32 = value
+                        .parse()
+                        .ok()
+                        .filter(|&color| color <= 0xFF_FF_FF)
+                        .ok_or_else(|| {
+                            ParseGradientError::new(
+                                ParseGradientErrorKind::InvalidNumber(value.to_owned()),
+                                line,
+                            )
+                        })?;
+                    let [r, g, b, _] = color.to_le_bytes();
+                    stops.push((index, [r, g, b]));
+                }
+                _ => {}
+            }
+        }
+        line += 1;
+    }
+
+    if !closed {
+        return Err(ParseGradientError::new(
+            ParseGradientErrorKind::Missing("}"),
+            line - 1,
+        ));
+    }
+    if index.is_some() {
+        return Err(ParseGradientError::new(
+            ParseGradientErrorKind::Missing("color"),
+            line,
+        ));
+    }
+    if stops.is_empty() {
+        return Err(ParseGradientError::new(ParseGradientErrorKind::Empty, line));
+    }
+    // The sort is stable, so stops at the same index keep their order and make a hard edge.
+    stops.sort_by_key(|&(index, _)| index);
+
+    Ok(GradientPalette {
+        name,
+        period: UGR_INDICES,
+        stops,
+    })
+}
+
+/// Splits a line of an Ultra Fractal gradient into its whitespace separated tokens,
+/// without splitting quoted values.
+fn tokens(line: &str) -> impl Iterator<Item = &str> {
+    let mut rest = line.trim();
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let mut in_quotes = false;
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| {
+                if c == '"' {
+                    in_quotes = !in_quotes;
+                }
+                c.is_whitespace() && !in_quotes
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        let token = &rest[..end];
+        rest = rest[end..].trim_start();
+        Some(token)
+    })
+}
+
+fn parse_map(text: &str) -> Result<GradientPalette, ParseGradientError> {
+    let mut colors = Vec::new();
+    for (line, contents) in text.lines().enumerate() {
+        let line = line + 1;
+        let mut channels = contents.split_whitespace();
+        let Some(first) = channels.next() else {
+            continue;
+        };
+        let mut color = [0; 3];
+        for (channel, value) in color
+            .iter_mut()
+            .zip(core::iter::once(first).chain(channels))
+        {
+            *channel = value.parse().map_err(|_| {
+                ParseGradientError::new(
+                    ParseGradientErrorKind::InvalidNumber(value.to_owned()),
+                    line,
+                )
+            })?;
+        }
+        if contents.split_whitespace().count() < 3 {
+            return Err(ParseGradientError::new(
+                ParseGradientErrorKind::Missing("three color channels"),
+                line,
+            ));
+        }
+        colors.push(color);
+    }
+
+    if colors.is_empty() {
+        return Err(ParseGradientError::new(ParseGradientErrorKind::Empty, 1));
+    }
+    Ok(GradientPalette {
+        name: String::new(),
+        period: u32::try_from(colors.len()).unwrap_or(u32::MAX),
+        stops: (0..).zip(colors).collect(),
+    })
+}
+
+/// The line number, starting from 1, of the given byte position in the text.
+fn line_number(text: &str, position: usize) -> usize {
+    text[..position].matches('\n').count() + 1
+}
+
+/// The file formats that gradients can be read from and written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GradientFormat {
+    /// Ultra Fractal gradients, usually stored in `.ugr` files.
+    UltraFractal,
+    /// Fractint color maps, usually stored in `.map` files.
+    Fractint,
+}
+
+impl GradientFormat {
+    pub const ALL: [Self; 2] = [Self::UltraFractal, Self::Fractint];
+
+    /// Determines the format from the extension of a file name,
+    /// `ugr` or `gradient` for Ultra Fractal gradients and `map` for Fractint color maps.
+    #[must_use]
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "ugr" | "gradient" => Some(Self::UltraFractal),
+            "map" => Some(Self::Fractint),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GradientFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UltraFractal => write!(f, "ugr"),
+            Self::Fractint => write!(f, "map"),
+        }
+    }
+}
+
+impl FromStr for GradientFormat {
+    type Err = ParseGradientFormatError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_extension(s).ok_or_else(|| ParseGradientFormatError(s.to_owned()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseGradientFormatError(String);
+
+impl fmt::Display for ParseGradientFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown gradient format \"{}\", expected one of {}",
+            self.0,
+            GradientFormat::ALL
+                .map(|format| format.to_string())
+                .join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ParseGradientFormatError {}
+
+/// An error that occured while parsing a [`GradientPalette`].
+/// Contains the line in the input, starting from 1, where the error was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseGradientError {
+    kind: ParseGradientErrorKind,
+    line: usize,
+}
+
+impl ParseGradientError {
+    const fn new(kind: ParseGradientErrorKind, line: usize) -> Self {
+        Self { kind, line }
+    }
+
+    #[must_use]
+    pub const fn kind(&self) -> &ParseGradientErrorKind {
+        &self.kind
+    }
+
+    /// The line in the input, starting from 1, where the error was found.
+    #[must_use]
+    pub const fn line(&self) -> usize {
+        self.line
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseGradientErrorKind {
+    /// The gradient contains no colors.
+    Empty,
+    /// The named part of the gradient was expected but not found.
+    Missing(&'static str),
+    InvalidNumber(String),
+}
+
+impl fmt::Display for ParseGradientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "on line {}: ", self.line)?;
+        match &self.kind {
+            ParseGradientErrorKind::Empty => write!(f, "the gradient contains no colors"),
+            ParseGradientErrorKind::Missing(what) => write!(f, "expected {what}"),
+            ParseGradientErrorKind::InvalidNumber(number) => {
+                write!(f, "\"{number}\" is not a valid number")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseGradientError {}
+
+#[cfg(test)]
+mod test_gradient {
+    use super::*;
+    use crate::{BuiltinPalette, Palette};
+    use std::sync::Arc;
+
+    const SEAHORSE: &str = include_str!("../test_data/seahorse.ugr");
+    const FIRESTRM: &str = include_str!("../test_data/firestrm.map");
+
+    fn srgb(palette: &GradientPalette, escape_speed: f64) -> [u8; 3] {
+        palette.color(escape_speed).to_srgb_exact()
+    }
+
+    #[test]
+    fn check_ultra_fractal() {
+        let palette = GradientPalette::parse(SEAHORSE, GradientFormat::UltraFractal).unwrap();
+        // Only the first gradient in the file is used, and it is named by its title.
+        assert_eq!(palette.name(), "Seahorse");
+        assert_eq!(palette.stops.len(), 5);
+        // index=0 color=8781824 is 0x860000 in BGR order, a dark blue.
+        assert_eq!(srgb(&palette, 0.0), [0, 0, 134]);
+        assert_eq!(srgb(&palette, 100.0 / 400.0), [255, 255, 255]);
+        // Halfway between the white at index 100 and the orange at index 200.
+        assert_eq!(srgb(&palette, 150.0 / 400.0), [255, 200, 128]);
+        // The gradient wraps around from the last stop at index 350 to the first one.
+        assert_eq!(srgb(&palette, 375.0 / 400.0), [0, 0, 67]);
+        assert_eq!(srgb(&palette, 1.0), srgb(&palette, 0.0));
+    }
+
+    #[test]
+    fn check_fractint() {
+        let palette = GradientPalette::parse(FIRESTRM, GradientFormat::Fractint).unwrap();
+        assert_eq!(palette.stops.len(), 8);
+        assert_eq!(srgb(&palette, 0.0), [0, 0, 0]);
+        assert_eq!(srgb(&palette, 0.5), [255, 255, 0]);
+        assert_eq!(srgb(&palette, 0.5625), [255, 255, 100]);
+
+        // Color maps are exported with 256 colors, and maps with that many colors survive the round trip.
+        let exported = palette.write(GradientFormat::Fractint);
+        assert_eq!(exported.lines().count(), 256);
+        assert_eq!(exported.lines().nth(128), Some("255 255 0"));
+        let reimported = GradientPalette::parse(&exported, GradientFormat::Fractint).unwrap();
+        assert_eq!(
+            reimported.write(GradientFormat::Fractint),
+            palette.write(GradientFormat::Fractint)
+        );
+    }
+
+    #[test]
+    fn check_round_trip() {
+        let palette = GradientPalette::parse(SEAHORSE, GradientFormat::UltraFractal).unwrap();
+        let exported = palette.write(GradientFormat::UltraFractal);
+        assert_eq!(
+            GradientPalette::parse(&exported, GradientFormat::UltraFractal),
+            Ok(palette)
+        );
+
+        // Sampling a gradient at its own stops recreates it.
+        let palette = GradientPalette::parse(FIRESTRM, GradientFormat::Fractint).unwrap();
+        let sampled = GradientPalette::sampled(
+            &Palette::Gradient(Arc::new(palette.clone())),
+            NonZeroU32::new(8).unwrap(),
+            "",
+        );
+        assert_eq!(sampled, palette);
+
+        let classic = GradientPalette::sampled(
+            &BuiltinPalette::Classic,
+            NonZeroU32::new(400).unwrap(),
+            "classic",
+        );
+        for i in 0..400 {
+            let escape_speed = f64::from(i) / 400.0;
+            assert_eq!(
+                srgb(&classic, escape_speed),
+                BuiltinPalette::Classic.color(escape_speed).to_srgb_exact()
+            );
+        }
+    }
+
+    #[test]
+    fn check_errors() {
+        let kind = |text: &str, format| GradientPalette::parse(text, format).unwrap_err();
+        use GradientFormat::{Fractint, UltraFractal};
+        assert_eq!(
+            kind("", UltraFractal).kind(),
+            &ParseGradientErrorKind::Missing("{")
+        );
+        assert_eq!(
+            kind("a {\ngradient:\n index=0 color=0\n", UltraFractal).kind(),
+            &ParseGradientErrorKind::Missing("}")
+        );
+        assert_eq!(
+            kind("a {\ngradient:\n}", UltraFractal).kind(),
+            &ParseGradientErrorKind::Empty
+        );
+        let error = kind(
+            "a {\ngradient:\n index=0 color=0\n index=5 color=red\n}",
+            UltraFractal,
+        );
+        assert_eq!(
+            error.kind(),
+            &ParseGradientErrorKind::InvalidNumber("red".to_owned())
+        );
+        assert_eq!(error.line(), 4);
+        assert_eq!(
+            kind("a {\ngradient:\n index=0 index=1 color=0\n}", UltraFractal).kind(),
+            &ParseGradientErrorKind::Missing("color")
+        );
+
+        assert_eq!(
+            kind("\n\n", Fractint).kind(),
+            &ParseGradientErrorKind::Empty
+        );
+        let error = kind("0 0 0\n255 255\n", Fractint);
+        assert_eq!(
+            error.kind(),
+            &ParseGradientErrorKind::Missing("three color channels")
+        );
+        assert_eq!(error.line(), 2);
+        assert_eq!(
+            kind("0 0 256", Fractint).kind(),
+            &ParseGradientErrorKind::InvalidNumber("256".to_owned())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn check_serde() {
+        let gradient = GradientPalette::parse(SEAHORSE, GradientFormat::UltraFractal).unwrap();
+        for palette in [
+            Palette::default(),
+            Palette::Builtin(BuiltinPalette::UltraFractalDefault),
+            Palette::Gradient(Arc::new(gradient)),
+            Palette::Curves(Arc::new("r=s, g=s^2, b=1-s".parse().unwrap())),
+        ] {
+            let json = serde_json::to_string(&palette).unwrap();
+            assert_eq!(serde_json::from_str::<Palette>(&json).unwrap(), palette);
+        }
+        assert_eq!(
+            serde_json::to_string(&Palette::default()).unwrap(),
+            "\"classic\""
+        );
+        assert_eq!(
+            serde_json::from_str::<Palette>("\"ultra-fractal-default\"").unwrap(),
+            Palette::Builtin(BuiltinPalette::UltraFractalDefault)
+        );
+
+        let gradient = |period, stops| {
+            serde_json::from_str::<GradientPalette>(&format!(
+                r#"{{"name":"","period":{period},"stops":{stops}}}"#
+            ))
+        };
+        assert!(gradient(4, "[[0,[0,0,0]],[3,[255,0,0]]]").is_ok());
+        assert!(gradient(4, "[]").is_err());
+        assert!(gradient(4, "[[3,[0,0,0]],[0,[255,0,0]]]").is_err());
+        assert!(gradient(4, "[[4,[0,0,0]]]").is_err());
+        // The errors say what is wrong with the palette.
+        let error = |json| {
+            serde_json::from_str::<Palette>(json)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(error(r#"{"curves":"r=s"}"#).contains("no curve was given for channel g"));
+        assert!(error(r#""magma""#).contains("unknown palette \"magma\""));
+        assert!(error(r#"{"spline":"r=s"}"#).contains("unknown variant `spline`"));
+        assert!(error("3").contains("the name of a built-in palette"));
+    }
+}
@@ -1,39 +1,159 @@
 #![forbid(unsafe_code)]
 
-/// Determines the color of a pixel in linear RGB color space.
-/// The color map that this function uses was taken from the python code in
-/// [this](https://preshing.com/20110926/high-resolution-mandelbrot-in-obfuscated-python/) blog post.
-///
-/// As the input increases from 0 to 1 the color transitions as
-///
-/// black -> brown -> orange -> yellow -> cyan -> blue -> dark blue -> black.
+use std::sync::Arc;
+
+/// Maps escape speeds to colors. Implemented by every kind of palette,
+/// so that code that only needs the colors of a palette can take any of them.
+pub trait ColorMap {
+    /// Determines the color of a pixel in linear RGB color space
+    /// from the given escape speed.
+    fn color(&self, escape_speed: f64) -> LinearRGB;
+}
+
+/// The palette that is used to map escape speeds to colors.
 ///
-/// # Note
-/// The function has not been tested for inputs outside the range \[0, 1\]
-/// and makes no guarantees about the output in that case.
+/// Built-in palettes are serialized as just their name, and the other palettes as a map
+/// from the kind of palette to its definition, e.g. `{"curves": "r=s, g=s, b=s"}`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Palette {
+    /// A palette defined by one curve per color channel, see [`CurvePalette`].
+    Curves(Arc<CurvePalette>),
+    /// A gradient, usually loaded from the gradient file of another fractal program, see [`GradientPalette`].
+    Gradient(Arc<GradientPalette>),
+    /// One of the palettes that are built into the program.
+    #[cfg_attr(feature = "serde", serde(untagged))]
+    Builtin(BuiltinPalette),
+}
+
+impl Palette {
+    /// Determines the color of a pixel in linear RGB color space
+    /// from the given escape speed.
+    #[inline]
+    #[must_use]
+    pub fn color(&self, escape_speed: f64) -> LinearRGB {
+        match self {
+            Self::Builtin(builtin) => builtin.color(escape_speed),
+            Self::Curves(curves) => curves.color(escape_speed),
+            Self::Gradient(gradient) => gradient.color(escape_speed),
+        }
+    }
+
+    /// Returns the palette as a [`ColorMap`], so that renderers can look up colors
+    /// without matching on the kind of palette for every pixel.
+    #[must_use]
+    pub fn color_map(&self) -> Arc<dyn ColorMap + Send + Sync> {
+        match self {
+            Self::Builtin(builtin) => Arc::new(*builtin),
+            Self::Curves(curves) => Arc::clone(curves) as _,
+            Self::Gradient(gradient) => Arc::clone(gradient) as _,
+        }
+    }
+
+    /// The classic built-in palette.
+    #[deprecated(note = "use `Palette::Builtin(BuiltinPalette::Classic)` instead")]
+    #[allow(non_upper_case_globals)]
+    pub const Classic: Self = Self::Builtin(BuiltinPalette::Classic);
+}
+
+/// The kinds of palettes that are serialized as a map from their kind to their definition.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(rename = "Palette", rename_all = "snake_case")]
+enum TaggedPalette {
+    Curves(Arc<CurvePalette>),
+    Gradient(Arc<GradientPalette>),
+}
+
+/// Deserializes the name of a built-in palette or a map with another kind of palette.
+/// Unlike an untagged enum this reports what is wrong with the palette instead of only
+/// that it matches none of the kinds.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Palette {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PaletteVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for PaletteVisitor {
+            type Value = Palette;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("the name of a built-in palette, or a map with curves or a gradient")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<Palette, E> {
+                name.parse().map(Palette::Builtin).map_err(E::custom)
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Palette, A::Error> {
+                use serde::Deserialize;
+                Ok(
+                    match TaggedPalette::deserialize(serde::de::value::MapAccessDeserializer::new(
+                        map,
+                    ))? {
+                        TaggedPalette::Curves(curves) => Palette::Curves(curves),
+                        TaggedPalette::Gradient(gradient) => Palette::Gradient(gradient),
+                    },
+                )
+            }
+        }
+
+        deserializer.deserialize_any(PaletteVisitor)
+    }
+}
+
+/// Determines the color of a pixel in linear RGB color space with the classic palette.
+#[deprecated(note = "use `BuiltinPalette::Classic.color` instead")]
 #[inline]
+#[must_use]
 pub fn palette(escape_speed: f64) -> LinearRGB {
-    let third_power = escape_speed * escape_speed * escape_speed;
-    let ninth_power = third_power * third_power * third_power;
-    let eighteenth_power = ninth_power * ninth_power;
-    let thirty_sixth_power = eighteenth_power * eighteenth_power;
-
-    [
-        255.0_f64.powf(-2.0 * ninth_power * thirty_sixth_power) * escape_speed,
-        14.0 / 51.0 * escape_speed - 176.0 / 51.0 * eighteenth_power + 701.0 / 255.0 * ninth_power,
-        16.0 / 51.0 * escape_speed + ninth_power
-            - 190.0 / 51.0
-                * thirty_sixth_power
-                * thirty_sixth_power
-                * eighteenth_power
-                * ninth_power,
-    ]
-    .map(srgb_to_linear_rgb)
-    .into()
+    BuiltinPalette::Classic.color(escape_speed)
 }
 
-/// Converts a point in the sRGB color space to a linear RGB triplet.
-fn srgb_to_linear_rgb(c: f64) -> f64 {
+/// The classic built-in palette.
+impl Default for Palette {
+    fn default() -> Self {
+        Self::Builtin(BuiltinPalette::Classic)
+    }
+}
+
+impl From<BuiltinPalette> for Palette {
+    fn from(builtin: BuiltinPalette) -> Self {
+        Self::Builtin(builtin)
+    }
+}
+
+impl ColorMap for Palette {
+    fn color(&self, escape_speed: f64) -> LinearRGB {
+        Palette::color(self, escape_speed)
+    }
+}
+
+impl ColorMap for BuiltinPalette {
+    fn color(&self, escape_speed: f64) -> LinearRGB {
+        BuiltinPalette::color(*self, escape_speed)
+    }
+}
+
+impl ColorMap for CurvePalette {
+    fn color(&self, escape_speed: f64) -> LinearRGB {
+        CurvePalette::color(self, escape_speed)
+    }
+}
+
+impl ColorMap for GradientPalette {
+    fn color(&self, escape_speed: f64) -> LinearRGB {
+        GradientPalette::color(self, escape_speed)
+    }
+}
+
+/// Converts a channel of a point in the sRGB color space, in the range \[0.0, 1.0\],
+/// to the same channel of a linear RGB triplet.
+#[must_use]
+pub fn srgb_to_linear_rgb(c: f64) -> f64 {
     if c <= 0.04045 {
         c / 12.92
     } else {
@@ -56,9 +176,29 @@ fn quantize_srgb(srgb: f64) -> u8 {
     (f64::from(u8::MAX) * srgb.clamp(0.0, 1.0)).round() as u8
 }
 
+mod builtin_palette;
+pub use builtin_palette::{BuiltinPalette, ParseBuiltinPaletteError};
+
+mod curves;
+pub use curves::{CurvePalette, ParseCurveError, ParseCurveErrorKind};
+
+mod expression;
+pub use expression::{
+    parse_expression, BinaryOperator, ExpressionLanguage, ParseExpressionError,
+    ParseExpressionErrorKind, MAX_NESTING_DEPTH,
+};
+
+mod gradient;
+pub use gradient::{
+    GradientFormat, GradientPalette, ParseGradientError, ParseGradientErrorKind,
+    ParseGradientFormatError,
+};
+
 mod linear_rgb;
 pub use linear_rgb::LinearRGB;
 
+mod quantization;
+
 mod pixel;
 pub use pixel::Pixel;
 
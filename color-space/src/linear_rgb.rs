@@ -1,3 +1,4 @@
+use crate::quantization::quantize_linear_rgb;
 use crate::{linear_rgb_to_srgb, quantize_srgb, srgb_to_linear_rgb};
 use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 use image::{Luma, Rgb, Rgba};
@@ -17,6 +18,30 @@ impl LinearRGB {
     pub const fn new(r: f64, g: f64, b: f64) -> Self {
         Self { r, g, b }
     }
+
+    /// Converts a color given by its 8 bit sRGB channels to linear RGB.
+    #[must_use]
+    pub fn from_srgb(srgb: [u8; 3]) -> Self {
+        let [r, g, b] = srgb.map(|c| srgb_to_linear_rgb(f64::from(c) / f64::from(u8::MAX)));
+        Self::new(r, g, b)
+    }
+
+    /// Converts the color to 8 bit sRGB channels, the inverse of [`from_srgb`](Self::from_srgb).
+    /// Clamps the color channels to the range \[0, 1\] before conversion.
+    ///
+    /// The channels are looked up in a table rather than computed with the sRGB transfer function,
+    /// which is several times faster. Channels within rounding error of halfway between
+    /// two values may be rounded the other way than [`to_srgb_exact`](Self::to_srgb_exact) does.
+    #[must_use]
+    pub fn to_srgb(self) -> [u8; 3] {
+        [self.r, self.g, self.b].map(quantize_linear_rgb)
+    }
+
+    /// Works like [`to_srgb`](Self::to_srgb), but computes every channel with the sRGB transfer function.
+    #[must_use]
+    pub fn to_srgb_exact(self) -> [u8; 3] {
+        [self.r, self.g, self.b].map(|c| quantize_srgb(linear_rgb_to_srgb(c)))
+    }
 }
 
 impl Add for LinearRGB {
@@ -85,9 +110,7 @@ impl From<LinearRGB> for Rgb<u8> {
     /// Clamps the color channels to the range \[0, 1\] before conversion.
     #[inline]
     fn from(linear_rgb: LinearRGB) -> Self {
-        [linear_rgb.r, linear_rgb.g, linear_rgb.b]
-            .map(|c| quantize_srgb(linear_rgb_to_srgb(c)))
-            .into()
+        linear_rgb.to_srgb().into()
     }
 }
 
@@ -116,17 +139,16 @@ impl From<[f64; 3]> for LinearRGB {
 impl From<LinearRGB> for Luma<u8> {
     #[inline]
     fn from(linear_rgb: LinearRGB) -> Self {
-        Luma::from([quantize_srgb(linear_rgb_to_srgb(
+        Luma::from([quantize_linear_rgb(
             linear_rgb.r * 0.2126 + linear_rgb.g * 0.7152 + linear_rgb.b * 0.0722,
-        ))])
+        )])
     }
 }
 
 impl From<LinearRGB> for Rgba<u8> {
     #[inline]
     fn from(linear_rgb: LinearRGB) -> Self {
-        let [r, g, b] = [linear_rgb.r, linear_rgb.g, linear_rgb.b]
-            .map(|c| quantize_srgb(linear_rgb_to_srgb(c)));
+        let [r, g, b] = linear_rgb.to_srgb();
 
         [r, g, b, 255].into()
     }
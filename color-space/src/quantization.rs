@@ -0,0 +1,109 @@
+//! Fast conversion of linear RGB channels to 8 bit sRGB values.
+//!
+//! Converting a channel with `linear_rgb_to_srgb` calls `powf`, which dominates the time it takes
+//! to quantize the millions of pixels of a large image. Since the result is one of only 256 values
+//! it can be looked up instead: the linear values where the quantized value steps up are computed once,
+//! and a table of evenly spaced linear values gives the quantized value of the step that every value
+//! starts in. The table is fine enough that at most one step starts within every entry, so a single
+//! comparison with the start of the next step gives the result.
+//!
+//! The result only differs from quantizing the output of `linear_rgb_to_srgb` when a value lies
+//! within rounding error of the start of a step, and then by one.
+
+use std::sync::OnceLock;
+
+use crate::srgb_to_linear_rgb;
+
+/// The number of entries in the table of evenly spaced linear values.
+/// The steps are the closest together at the bottom of the range, where the sRGB transfer function
+/// is a line with a slope of 12.92, so they are 1 / (255 * 12.92) ≈ 1 / 3294 apart.
+const ENTRIES: usize = 4096;
+
+struct Tables {
+    /// The smallest linear value that is quantized to `k + 1`, for every k.
+    step_starts: [f64; u8::MAX as usize],
+    /// The quantized value at the start of every entry.
+    entry_values: [u8; ENTRIES],
+}
+
+impl Tables {
+    fn new() -> Self {
+        // A value is rounded up to k + 1 from halfway between k and k + 1.
+        let step_starts =
+            core::array::from_fn(|k| srgb_to_linear_rgb((k as f64 + 0.5) / f64::from(u8::MAX)));
+        let entry_values = core::array::from_fn(|entry| {
+            let start = entry as f64 / ENTRIES as f64;
+            // There are at most 255 steps, so the count fits in a u8.
+            #[allow(clippy::cast_possible_truncation)]
+            let value = step_starts
+                .iter()
+                .take_while(|&&step_start| step_start <= start)
+                .count() as u8;
+            value
+        });
+        Self {
+            step_starts,
+            entry_values,
+        }
+    }
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+/// Converts a channel of a linear RGB triplet to an 8 bit sRGB value.
+/// Clamps the input to the range \[0.0, 1.0\] before the conversion, and maps NaN to 0.
+#[inline]
+pub(crate) fn quantize_linear_rgb(c: f64) -> u8 {
+    let tables = TABLES.get_or_init(Tables::new);
+    // The clamped value is in [0, ENTRIES], and NaN is cast to 0.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let entry = ((c.clamp(0.0, 1.0) * ENTRIES as f64) as usize).min(ENTRIES - 1);
+    let value = tables.entry_values[entry];
+    match tables.step_starts.get(usize::from(value)) {
+        Some(&next_step_start) if c >= next_step_start => value + 1,
+        _ => value,
+    }
+}
+
+#[cfg(test)]
+mod test_quantization {
+    use super::*;
+    use crate::{linear_rgb_to_srgb, quantize_srgb};
+
+    fn quantize_linear_rgb_exact(c: f64) -> u8 {
+        quantize_srgb(linear_rgb_to_srgb(c))
+    }
+
+    #[test]
+    fn check_against_exact() {
+        let tables = Tables::new();
+        // At most one step starts within every entry of the table.
+        for pair in tables.entry_values.windows(2) {
+            assert!(pair[1] - pair[0] <= 1);
+        }
+
+        const SAMPLES: u32 = 1_000_000;
+        let mut differing = 0;
+        for i in 0..=SAMPLES {
+            let c = f64::from(i) / f64::from(SAMPLES);
+            let (fast, exact) = (quantize_linear_rgb(c), quantize_linear_rgb_exact(c));
+            assert!(fast.abs_diff(exact) <= 1, "{c}: {fast} vs {exact}");
+            if fast != exact {
+                differing += 1;
+            }
+        }
+        // Only values that are within rounding error of the start of a step can differ.
+        assert!(differing < 10, "{differing}");
+
+        // Every 8 bit value survives a round trip through linear RGB.
+        for value in 0..=u8::MAX {
+            let linear = srgb_to_linear_rgb(f64::from(value) / f64::from(u8::MAX));
+            assert_eq!(quantize_linear_rgb(linear), value);
+        }
+
+        for (c, expected) in [(-0.5, 0), (1.5, 255), (f64::NAN, 0), (f64::INFINITY, 255)] {
+            assert_eq!(quantize_linear_rgb(c), expected);
+            assert_eq!(quantize_linear_rgb_exact(c), expected);
+        }
+    }
+}
@@ -1,6 +1,11 @@
 use image::ColorType;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum SupportedColorType {
     Rgba8,
     Rgb8,
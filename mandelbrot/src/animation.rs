@@ -0,0 +1,408 @@
+//! Rendering every frame of an animation to its own image, and resuming an animation
+//! whose rendering was interrupted.
+//!
+//! After every saved frame the number of finished frames is recorded in a manifest next to the frames,
+//! together with the settings and keyframes of the animation. Rendering the animation again with the same
+//! settings continues after the last finished frame. The view of a frame only depends on its index,
+//! and the renderer gives the same image for the same view no matter how its work is scheduled,
+//! so the resumed frames are identical to the ones of an uninterrupted render.
+//!
+//! Frames whose view is the same as that of the frame before them, such as the frames of an animation
+//! that only cycles the colors of the palette, are colored from the escape data of that frame,
+//! see [`FrameCache`].
+
+use core::fmt;
+use core::str::FromStr;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use image::DynamicImage;
+use mandellib::{
+    colorize, compute, render, Animation, Coloring, EscapeBuffer, Frame, GrayscaleMode,
+    ReconstructionFilter, RenderParameters, RenderQuality,
+};
+
+use crate::batch::suffixed_path;
+use crate::command_line_interface::Cli;
+
+/// The progress of an animation, which is saved after every frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    /// The number of frames from the start of the animation that have been saved.
+    pub finished_frames: u64,
+    /// The arguments that every frame is rendered with apart from its view.
+    pub arguments: Vec<String>,
+    pub animation: Animation,
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "# The progress of an animation rendered by mandelbrot. Delete this file to render the animation from the start."
+        )?;
+        writeln!(f, "finished {}", self.finished_frames)?;
+        writeln!(f, "arguments {}", self.arguments.join("\t"))?;
+        for line in self.animation.to_string().lines() {
+            writeln!(f, "keyframe {line}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Manifest {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut finished_frames = None;
+        let mut arguments = Vec::new();
+        let mut keyframes = String::new();
+        for line in s.lines().filter(|line| !line.starts_with('#')) {
+            match line.split_once(' ') {
+                Some(("finished", count)) => {
+                    finished_frames = Some(
+                        count
+                            .parse()
+                            .map_err(|_| format!("\"{count}\" is not a number of frames"))?,
+                    );
+                }
+                Some(("arguments", line)) => {
+                    arguments = line.split('\t').map(str::to_owned).collect();
+                }
+                Some(("keyframe", keyframe)) => {
+                    keyframes.push_str(keyframe);
+                    keyframes.push('\n');
+                }
+                _ => return Err(format!("\"{line}\" is not part of an animation manifest")),
+            }
+        }
+        Ok(Self {
+            finished_frames: finished_frames.ok_or("the number of finished frames is missing")?,
+            arguments,
+            animation: keyframes.parse().map_err(|e| format!("{e}"))?,
+        })
+    }
+}
+
+/// The escape data of the latest frame of an animation, so that the next frame is colored from it
+/// instead of being computed again if it only differs from it in its palette offset.
+#[derive(Debug, Default)]
+pub struct FrameCache {
+    /// The recorded arguments of the frame that the escape data is of, apart from its palette offset.
+    view: Vec<String>,
+    buffer: Option<EscapeBuffer>,
+}
+
+impl FrameCache {
+    /// Returns a cache for the frames of the animation, which only keeps the escape data of a frame
+    /// if the animation has frames that show the same view as the frame before them.
+    pub fn new(animation: &Animation) -> Option<Self> {
+        animation
+            .keyframes()
+            .windows(2)
+            .any(|pair| {
+                (pair[0].real_center, pair[0].imag_center, pair[0].zoom)
+                    == (pair[1].real_center, pair[1].imag_center, pair[1].zoom)
+            })
+            .then(Self::default)
+    }
+
+    /// Renders the frame that the arguments describe, by coloring the escape data of the previous frame
+    /// if it shows the same view, or by computing its escape data and keeping it for the next frame.
+    /// Frames whose colors can not be worked out from their escape data alone are rendered as usual.
+    pub fn render(
+        &mut self,
+        args: &Cli,
+        render_parameters: &RenderParameters,
+        draw_region: Frame,
+    ) -> DynamicImage {
+        if !is_recolorable(render_parameters) {
+            return render(render_parameters, draw_region, args.verbose);
+        }
+        let view: Vec<String> = args
+            .recorded_arguments()
+            .into_iter()
+            .filter(|argument| !argument.starts_with("--palette-offset="))
+            .collect();
+        if self.view != view || self.buffer.is_none() {
+            if args.verbose {
+                _ = write!(io::stdout(), "\rComputing escape data");
+            }
+            self.buffer = Some(compute(render_parameters, draw_region));
+            self.view = view;
+        }
+        let buffer = self
+            .buffer
+            .as_ref()
+            .expect("the escape data was just computed");
+        colorize(buffer, &Coloring::from(render_parameters))
+    }
+}
+
+/// Whether the image rendered with the parameters is the same as its escape data colored with them.
+/// The escape data is colored by averaging the samples of every pixel and equalizes its own escape speeds,
+/// so renders with other reconstruction filters, draft qualities or equalized grayscale would change.
+pub fn is_recolorable(render_parameters: &RenderParameters) -> bool {
+    render_parameters.reconstruction_filter == ReconstructionFilter::Box
+        && render_parameters.quality == RenderQuality::Full
+        && render_parameters.grayscale_mode != GrayscaleMode::Equalized
+}
+
+/// Returns the path of the manifest of the animation whose frames are saved next to the output path:
+/// "out.png" gives "out.manifest.txt".
+pub fn manifest_path(output_path: &Path) -> PathBuf {
+    output_path.with_extension("manifest.txt")
+}
+
+/// Returns the path that the frame with the given index is saved at, based on the output path of the program:
+/// "out.png" becomes "out_042.png" for frame 42 of an animation with at most a thousand frames.
+/// The indices are padded with zeros so that the frames are listed in order.
+pub fn frame_path(output_path: &Path, frame: u64, frame_count: u64) -> PathBuf {
+    let width = frame_count.saturating_sub(1).max(1).ilog10() as usize + 1;
+    suffixed_path(output_path, &format!("{frame:0width$}"))
+}
+
+/// Calls `render` with the settings of every frame of the animation that has not been saved yet, in order,
+/// and records the progress in the manifest next to the output path of `args` after every frame.
+/// Every frame is saved next to the output path with its index appended to the name, see [`frame_path`].
+///
+/// # Errors
+/// Returns an error if a frame can not be rendered, or if the manifest can not be read or written.
+/// A manifest of an animation with other settings or keyframes is an error rather than being overwritten,
+/// since its frames would be mixed with the new ones.
+pub fn render_animation(
+    args: &Cli,
+    animation: &Animation,
+    mut render: impl FnMut(&Cli) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let output_path = Path::new(&args.output_path);
+    let manifest_path = manifest_path(output_path);
+    let mut manifest = Manifest {
+        finished_frames: 0,
+        // The view and palette offset of every frame are given by the keyframes.
+        arguments: args
+            .recorded_arguments()
+            .into_iter()
+            .filter(|argument| {
+                ![
+                    "--real-center=",
+                    "--imag-center=",
+                    "--zoom-level=",
+                    "--palette-offset=",
+                ]
+                .iter()
+                .any(|prefix| argument.starts_with(prefix))
+            })
+            .collect(),
+        animation: animation.clone(),
+    };
+    match fs::read_to_string(&manifest_path) {
+        Ok(text) => {
+            let saved: Manifest = text.parse().map_err(|e| {
+                format!(
+                    "could not read the manifest {}: {e}",
+                    manifest_path.display()
+                )
+            })?;
+            if saved.arguments != manifest.arguments || saved.animation != manifest.animation {
+                return Err(format!(
+                    "{} is the manifest of an animation with other settings or keyframes. Delete it to render this animation from the start",
+                    manifest_path.display()
+                )
+                .into());
+            }
+            manifest.finished_frames = saved.finished_frames;
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => {
+            return Err(format!("could not open {}: {e}", manifest_path.display()).into());
+        }
+    }
+
+    let frame_count = animation.frame_count();
+    if args.verbose && manifest.finished_frames > 0 {
+        _ = writeln!(
+            io::stdout(),
+            "Resuming the animation at frame {} of {frame_count}",
+            manifest.finished_frames + 1
+        );
+    }
+    for frame in manifest.finished_frames..frame_count {
+        let keyframe = animation
+            .keyframe_at(frame)
+            .expect("the frame is part of the animation");
+        let path = frame_path(output_path, frame, frame_count);
+        // The frame is saved under another name until it is done, so that an interrupted render
+        // never leaves a broken frame behind under the name of a finished one.
+        let unfinished_path = suffixed_path(&path, "unfinished");
+
+        let mut job_arguments = manifest.arguments.clone();
+        job_arguments.extend([
+            format!("--real-center={}", keyframe.real_center),
+            format!("--imag-center={}", keyframe.imag_center),
+            format!("--zoom-level={}", keyframe.zoom),
+            format!("--palette-offset={}", keyframe.palette_offset),
+            format!("--output-path={}", unfinished_path.display()),
+        ]);
+        if args.auto_iterations {
+            job_arguments.push("--auto-iterations".to_owned());
+        }
+        let mut job =
+            Cli::try_parse_from(core::iter::once("mandelbrot".to_owned()).chain(job_arguments))?;
+        job.resolve_auto_iterations();
+
+        render(&job)?;
+        fs::rename(&unfinished_path, &path)?;
+        manifest.finished_frames = frame + 1;
+        save_manifest(&manifest, &manifest_path)?;
+        if args.verbose {
+            _ = writeln!(
+                io::stdout(),
+                "Saved frame {} of {frame_count} as {}",
+                frame + 1,
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the manifest at the path with the given one, such that an interruption
+/// leaves either the old or the new manifest.
+fn save_manifest(manifest: &Manifest, path: &Path) -> io::Result<()> {
+    let unfinished_path = path.with_extension("unfinished");
+    fs::write(&unfinished_path, manifest.to_string())?;
+    fs::rename(unfinished_path, path)
+}
+
+#[cfg(test)]
+mod test_animation {
+    use super::*;
+
+    /// Renders the animation with a render function that writes the view of every frame to its file,
+    /// and fails at the given frame.
+    fn render_views(
+        args: &Cli,
+        animation: &Animation,
+        fail_at: Option<u64>,
+    ) -> (Result<(), Box<dyn Error>>, Vec<String>) {
+        let mut rendered = Vec::new();
+        let result = render_animation(args, animation, |job| {
+            if fail_at == Some(rendered.len() as u64) {
+                return Err("out of memory".into());
+            }
+            let view = format!(
+                "{} {} {} {} {}",
+                job.real_center,
+                job.imag_center,
+                job.zoom_level,
+                job.max_iterations,
+                job.palette_offset
+            );
+            fs::write(&job.output_path, &view)?;
+            rendered.push(view);
+            Ok(())
+        });
+        (result, rendered)
+    }
+
+    #[test]
+    fn check_resume() {
+        let directory =
+            std::env::temp_dir().join(format!("mandelbrot_animation_test_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let output_path = directory.join("zoom.png");
+        let args = Cli::parse_from([
+            "mandelbrot",
+            "--auto-iterations",
+            "--resolution=30x20",
+            "-o",
+            output_path.to_str().unwrap(),
+        ]);
+        let animation: Animation = "0 -0.75 0 0\n4 -0.745 0.1 12".parse().unwrap();
+
+        let (result, uninterrupted) = render_views(&args, &animation, None);
+        result.unwrap();
+        assert_eq!(uninterrupted.len(), 5);
+        // The iterations follow the zoom.
+        assert_ne!(uninterrupted[0], uninterrupted[4]);
+        assert_eq!(
+            fs::read_to_string(directory.join("zoom_4.png")).unwrap(),
+            uninterrupted[4]
+        );
+
+        // Rendering it again does nothing, since every frame is done.
+        let (result, rendered) = render_views(&args, &animation, None);
+        result.unwrap();
+        assert!(rendered.is_empty());
+
+        fs::remove_file(manifest_path(&output_path)).unwrap();
+        let (result, rendered) = render_views(&args, &animation, Some(2));
+        assert!(result.is_err());
+        assert_eq!(rendered, uninterrupted[..2]);
+        let (result, rendered) = render_views(&args, &animation, None);
+        result.unwrap();
+        assert_eq!(rendered, uninterrupted[2..]);
+
+        // Other keyframes would mix two animations.
+        let other: Animation = "0 -0.75 0 0\n4 -0.745 0.1 13".parse().unwrap();
+        assert!(render_views(&args, &other, None).0.is_err());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn check_frame_path() {
+        let output_path = Path::new("renders/zoom.png");
+        assert_eq!(
+            frame_path(output_path, 42, 1000),
+            Path::new("renders/zoom_042.png")
+        );
+        assert_eq!(
+            frame_path(output_path, 7, 1001),
+            Path::new("renders/zoom_0007.png")
+        );
+        assert_eq!(
+            frame_path(output_path, 0, 1),
+            Path::new("renders/zoom_0.png")
+        );
+    }
+
+    #[test]
+    fn check_palette_cycling() {
+        let animation: Animation = "0 -0.75 0 0\n2 -0.75 0 0 0.5\n3 -0.7 0 1 0.5"
+            .parse::<Animation>()
+            .unwrap()
+            .with_palette_cycling(0.0, 0.125)
+            .unwrap();
+        let mut cache = FrameCache::new(&animation).unwrap();
+        let mut images = Vec::new();
+        for frame in 0..animation.frame_count() {
+            let keyframe = animation.keyframe_at(frame).unwrap();
+            let job = Cli::parse_from([
+                "mandelbrot".to_owned(),
+                "--resolution=30x20".to_owned(),
+                format!("--real-center={}", keyframe.real_center),
+                format!("--zoom-level={}", keyframe.zoom),
+                format!("--palette-offset={}", keyframe.palette_offset),
+            ]);
+            let (render_parameters, draw_region) = crate::render_settings(&job).unwrap();
+            // Frames with the same view as the one before them are colored from its escape data.
+            let image = cache.render(&job, &render_parameters, draw_region);
+            assert_eq!(
+                image,
+                render(&render_parameters, draw_region, false),
+                "{frame}"
+            );
+            images.push(image);
+        }
+        assert_ne!(images[0], images[1]);
+        assert!(cache.view.contains(&"--real-center=-0.7".to_owned()));
+
+        // Animations that never stay at a view do not keep the escape data of their frames.
+        let zoom: Animation = "0 -0.75 0 0\n4 -0.745 0.1 12 3".parse().unwrap();
+        assert!(FrameCache::new(&zoom).is_none());
+    }
+}
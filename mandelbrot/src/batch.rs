@@ -0,0 +1,318 @@
+//! A mode that renders views that are sent to it from other programs, so that a view can be found
+//! in the viewer and rendered in the background at a higher resolution.
+//!
+//! The program listens on a local socket that only the user who started it can connect to, see [`local_socket`](crate::local_socket).
+//! A client connects to it and sends one view per line,
+//! as the arguments that render it separated by tabs, e.g.
+//! "--real-center=-0.75\t--zoom-level=3\t--resolution=3840x2160".
+//! Each line is answered with "queued PATH", where PATH is where the image will be saved,
+//! or with "error: " followed by why the view was rejected.
+//! The views are rendered one at a time in the order they were received.
+
+use core::fmt;
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use clap::Parser;
+
+use crate::command_line_interface::Cli;
+use crate::local_socket::{Connection, LocalListener};
+
+/// The recorded arguments that name files, which a view can not contain,
+/// since the server would read them with the permissions of its own user.
+const PATH_ARGUMENTS: [&str; 3] = ["--palette-file", "--watermark", "--mask"];
+
+#[derive(Debug)]
+pub enum ParseJobError {
+    InvalidArguments(clap::Error),
+    Subcommand,
+    /// The view contains an argument that names a file on the machine that renders it.
+    PathArgument(&'static str),
+}
+
+impl fmt::Display for ParseJobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // The rendered error also contains the usage of the program, which is not useful to the client.
+            Self::InvalidArguments(e) => {
+                let message = e.to_string();
+                let first_line = message.lines().next().unwrap_or_default();
+                write!(
+                    f,
+                    "{}",
+                    first_line.strip_prefix("error: ").unwrap_or(first_line)
+                )
+            }
+            Self::Subcommand => write!(f, "a view can not contain a subcommand"),
+            Self::PathArgument(argument) => {
+                write!(f, "a view can not refer to files with {argument}")
+            }
+        }
+    }
+}
+
+impl Error for ParseJobError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidArguments(e) => Some(e),
+            Self::Subcommand | Self::PathArgument(_) => None,
+        }
+    }
+}
+
+/// Parses a line of tab separated arguments into the settings of the view that they describe.
+/// Only the arguments that determine what the image looks like are kept,
+/// so that a client can not decide where or how the image is saved,
+/// and views with arguments that name files are rejected, so that a client can not make the server read them.
+pub fn parse_job(line: &str) -> Result<Cli, ParseJobError> {
+    let arguments = line
+        .trim_end_matches(['\r', '\n'])
+        .split('\t')
+        .filter(|argument| !argument.is_empty());
+    let given = Cli::try_parse_from(core::iter::once("mandelbrot").chain(arguments))
+        .map_err(ParseJobError::InvalidArguments)?;
+    if given.command.is_some() {
+        return Err(ParseJobError::Subcommand);
+    }
+    let recorded = given.recorded_arguments();
+    if let Some(argument) = PATH_ARGUMENTS.into_iter().find(|argument| {
+        recorded
+            .iter()
+            .any(|recorded| recorded.starts_with(&format!("{argument}=")))
+    }) {
+        return Err(ParseJobError::PathArgument(argument));
+    }
+    Cli::try_parse_from(core::iter::once("mandelbrot".to_owned()).chain(recorded))
+        .map_err(ParseJobError::InvalidArguments)
+}
+
+/// Returns the path of the image with the given number, based on the output path of the program:
+/// "out.png" becomes "out_3.png".
+pub fn numbered_path(output_path: &Path, number: u32) -> PathBuf {
+    suffixed_path(output_path, &number.to_string())
+}
+
+/// Returns the output path of the program with the suffix appended to the name of the file:
+/// "out.png" becomes "out_suffix.png".
+pub fn suffixed_path(output_path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = output_path.file_stem().unwrap_or_default().to_owned();
+    file_name.push(format!("_{suffix}"));
+    if let Some(extension) = output_path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    output_path.with_file_name(file_name)
+}
+
+/// Accepts views from clients and decides where their images are saved.
+#[derive(Debug)]
+pub struct JobQueue {
+    output_path: PathBuf,
+    verbose: bool,
+    last_number: u32,
+}
+
+impl JobQueue {
+    pub fn new(output_path: PathBuf, verbose: bool) -> Self {
+        Self {
+            output_path,
+            verbose,
+            last_number: 0,
+        }
+    }
+
+    /// Returns the next numbered path that is not already taken by a file,
+    /// so that images from earlier sessions are not overwritten.
+    fn next_path(&mut self) -> PathBuf {
+        loop {
+            self.last_number += 1;
+            let path = numbered_path(&self.output_path, self.last_number);
+            if !path.exists() {
+                return path;
+            }
+        }
+    }
+
+    /// Reads views from `input` until it ends, answers each of them on `output`,
+    /// and sends the views that could be parsed to `jobs`.
+    pub fn handle_connection(
+        &mut self,
+        input: impl BufRead,
+        mut output: impl Write,
+        jobs: &Sender<Cli>,
+    ) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match parse_job(&line) {
+                Ok(mut job) => {
+                    let path = self.next_path();
+                    writeln!(output, "queued {}", path.display())?;
+                    job.output_path = path.to_string_lossy().into_owned();
+                    job.verbose = self.verbose;
+                    if jobs.send(job).is_err() {
+                        // Nothing is rendered anymore, so there is no point in accepting more views.
+                        return Ok(());
+                    }
+                }
+                Err(e) => writeln!(output, "error: {e}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Listens on the local socket at `socket` for views and calls `render` with the settings of each of them in turn.
+/// The images are saved next to the output path of `args` with increasing numbers.
+/// Runs until the program is stopped.
+pub fn listen(
+    args: &Cli,
+    socket: &Path,
+    mut render: impl FnMut(&Cli) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let listener = LocalListener::bind(socket)
+        .map_err(|e| format!("could not listen on {}: {e}", socket.display()))?;
+    _ = writeln!(
+        io::stdout(),
+        "Listening for views on {}",
+        listener.path().display()
+    );
+
+    let (sender, receiver) = mpsc::channel();
+    let mut queue = JobQueue::new(PathBuf::from(&args.output_path), args.verbose);
+    thread::spawn(move || {
+        loop {
+            // A client that fails to connect or disconnects early does not stop the others.
+            let Ok(stream) = listener.accept() else {
+                continue;
+            };
+            let Ok(reader) = stream.try_clone() else {
+                continue;
+            };
+            _ = queue.handle_connection(BufReader::new(reader), stream, &sender);
+        }
+    });
+
+    for job in receiver {
+        match render(&job) {
+            Ok(()) => _ = writeln!(io::stdout(), "Saved image as {}", job.output_path),
+            Err(e) => _ = writeln!(io::stderr(), "could not render {}: {e}", job.output_path),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_batch {
+    use super::*;
+
+    #[test]
+    fn check_parse_job() {
+        let job =
+            parse_job("--real-center=-1.5\t--zoom-level=3\t--resolution\t30x20\t--grayscale\n")
+                .unwrap();
+        assert_eq!(job.real_center.to_f64(), -1.5);
+        assert_eq!(job.zoom_level.level(), 3.0);
+        assert_eq!(job.resolution.to_string(), "30x20");
+        assert!(job.grayscale);
+
+        // Arguments that do not change the image are dropped.
+        let job = parse_job("--contours=5\t--contours-svg=/tmp/lines.svg\t-o\t/tmp/x.png").unwrap();
+        assert!(job.contours.is_none());
+        assert!(job.contours_svg.is_none());
+        assert_eq!(job.output_path, Cli::parse_from(["mandelbrot"]).output_path);
+
+        assert!(matches!(
+            parse_job("--bogus"),
+            Err(ParseJobError::InvalidArguments(_))
+        ));
+        assert!(matches!(parse_job("repl"), Err(ParseJobError::Subcommand)));
+
+        // A client can not make the server read its files.
+        for argument in [
+            "--palette-file=/etc/passwd.map",
+            "--watermark=/home/user/.ssh/id_rsa",
+            "--mask\t/etc/shadow",
+        ] {
+            assert!(
+                matches!(parse_job(argument), Err(ParseJobError::PathArgument(_))),
+                "{argument}"
+            );
+        }
+    }
+
+    #[test]
+    fn check_numbered_path() {
+        assert_eq!(
+            numbered_path(Path::new("renders/out.png"), 3),
+            Path::new("renders/out_3.png")
+        );
+        assert_eq!(numbered_path(Path::new("out"), 12), Path::new("out_12"));
+    }
+
+    #[test]
+    fn check_connection() {
+        let mut queue = JobQueue::new(PathBuf::from("batch_test_view.png"), false);
+        let (sender, receiver) = mpsc::channel();
+        let input = "--zoom-level=2\n\n--max-iterations=0\n--zoom-level=5\t--ssaa=1\n";
+        let mut output = Vec::new();
+        queue
+            .handle_connection(input.as_bytes(), &mut output, &sender)
+            .unwrap();
+        drop(sender);
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "queued batch_test_view_1.png");
+        assert!(lines[1].starts_with("error: "));
+        assert_eq!(lines[2], "queued batch_test_view_2.png");
+
+        let jobs: Vec<(f64, String)> = receiver
+            .iter()
+            .map(|job| (job.zoom_level.level(), job.output_path))
+            .collect();
+        assert_eq!(
+            jobs,
+            [
+                (2.0, "batch_test_view_1.png".to_owned()),
+                (5.0, "batch_test_view_2.png".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn check_stopped_renderer() {
+        // The renderer stops after a few views while a client keeps sending them,
+        // which must not keep the connection from being handled to the end.
+        for accepted in [0, 1, 10, 100] {
+            let mut queue = JobQueue::new(PathBuf::from("batch_test_stopped.png"), false);
+            let (sender, receiver) = mpsc::channel();
+            let renderer = thread::spawn(move || {
+                receiver
+                    .iter()
+                    .take(accepted)
+                    .map(|job: Cli| job.output_path)
+                    .collect::<Vec<_>>()
+            });
+            let input = "--zoom-level=2\n".repeat(1000);
+            let mut output = Vec::new();
+            queue
+                .handle_connection(input.as_bytes(), &mut output, &sender)
+                .unwrap();
+            drop(sender);
+
+            let rendered = renderer.join().unwrap();
+            let expected: Vec<String> = (1..=accepted)
+                .map(|number| format!("batch_test_stopped_{number}.png"))
+                .collect();
+            assert_eq!(rendered, expected);
+            assert!(String::from_utf8(output).unwrap().lines().count() >= accepted);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Listing the bookmarks that were saved in the viewer, and rendering every bookmark with a given tag.
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use mandellib::{Bookmark, BookmarkLibrary};
+
+use crate::batch::{parse_job, suffixed_path};
+use crate::command_line_interface::Cli;
+
+/// Reads the bookmarks in the file at the path.
+/// A file that does not exist holds no bookmarks, since none have been saved yet.
+pub fn load_library(path: &Path) -> Result<BookmarkLibrary, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => text
+            .parse()
+            .map_err(|e| format!("could not read the bookmarks in {}: {e}", path.display())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BookmarkLibrary::default()),
+        Err(e) => Err(format!("could not open {}: {e}", path.display())),
+    }
+}
+
+/// Writes the name, tags, notes and command of every bookmark that has the tag, if one is given,
+/// and matches the search query to `output`.
+pub fn list(
+    library: &BookmarkLibrary,
+    tag: Option<&str>,
+    query: &str,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let bookmarks = library
+        .search(query)
+        .filter(|bookmark| tag.is_none_or(|tag| bookmark.has_tag(tag)));
+    for bookmark in bookmarks {
+        write!(output, "{}", bookmark.name)?;
+        if !bookmark.tags.is_empty() {
+            write!(output, " [{}]", bookmark.tags.join(", "))?;
+        }
+        writeln!(output)?;
+        for line in bookmark.notes.lines() {
+            writeln!(output, "    {line}")?;
+        }
+        writeln!(output, "    mandelbrot {}", bookmark.arguments.join(" "))?;
+    }
+    Ok(())
+}
+
+/// Returns the path that the image of the bookmark is saved at, based on the output path of the program:
+/// "out.png" becomes "out_seahorse_valley.png" for the bookmark "Seahorse valley".
+pub fn bookmark_path(output_path: &Path, bookmark: &Bookmark) -> PathBuf {
+    let mut suffix = String::new();
+    for c in bookmark.name.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            suffix.push(c);
+        } else if !suffix.is_empty() && !suffix.ends_with('_') {
+            suffix.push('_');
+        }
+    }
+    suffixed_path(output_path, suffix.trim_end_matches('_'))
+}
+
+/// Calls `render` with the settings of every bookmark that has the tag in turn.
+/// The images are saved next to the output path of `args` with the names of the bookmarks appended.
+/// A bookmark that can not be rendered does not stop the others from being rendered.
+pub fn render_tagged(
+    args: &Cli,
+    library: &BookmarkLibrary,
+    tag: &str,
+    mut render: impl FnMut(&Cli) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut rendered = 0;
+    let mut failed = 0;
+    for bookmark in library.with_tag(tag) {
+        let result = parse_job(&bookmark.arguments.join("\t"))
+            .map_err(Box::<dyn Error>::from)
+            .and_then(|mut job| {
+                job.output_path = bookmark_path(Path::new(&args.output_path), bookmark)
+                    .to_string_lossy()
+                    .into_owned();
+                job.verbose = args.verbose;
+                render(&job).map(|()| job.output_path)
+            });
+        match result {
+            Ok(path) => {
+                rendered += 1;
+                _ = writeln!(io::stdout(), "Saved \"{}\" as {path}", bookmark.name);
+            }
+            Err(e) => {
+                failed += 1;
+                _ = writeln!(io::stderr(), "could not render \"{}\": {e}", bookmark.name);
+            }
+        }
+    }
+
+    match (rendered, failed) {
+        (0, 0) => Err(format!("there are no bookmarks with the tag \"{tag}\"").into()),
+        (_, 0) => Ok(()),
+        _ => Err(format!("{failed} of the bookmarks could not be rendered").into()),
+    }
+}
+
+#[cfg(test)]
+mod test_bookmarks {
+    use super::*;
+    use clap::Parser;
+
+    const LIBRARY: &str = "bookmark Seahorse valley
+arguments --real-center=-0.745\t--zoom-level=150\t--resolution=30x20
+tags spiral
+note Double spirals.
+
+bookmark Broken
+arguments --zoom-level=nothing
+tags spiral
+
+bookmark Elephant valley
+arguments --real-center=0.275
+tags elephant
+";
+
+    #[test]
+    fn check_list() {
+        let library: BookmarkLibrary = LIBRARY.parse().unwrap();
+        let mut output = Vec::new();
+        list(&library, Some("spiral"), "seahorse", &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Seahorse valley [spiral]\n    Double spirals.\n    mandelbrot --real-center=-0.745 --zoom-level=150 --resolution=30x20\n"
+        );
+
+        let mut output = Vec::new();
+        list(&library, None, "", &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap().lines().count(), 7);
+    }
+
+    #[test]
+    fn check_bookmark_path() {
+        let bookmark = Bookmark {
+            name: "  Seahorse valley, deep (2)! ".to_owned(),
+            ..Bookmark::default()
+        };
+        assert_eq!(
+            bookmark_path(Path::new("renders/out.png"), &bookmark),
+            Path::new("renders/out_seahorse_valley_deep_2.png")
+        );
+    }
+
+    #[test]
+    fn check_render_tagged() {
+        let library: BookmarkLibrary = LIBRARY.parse().unwrap();
+        let args = Cli::parse_from(["mandelbrot", "-o", "tagged.png", "--zoom-level=7"]);
+
+        let mut jobs = Vec::new();
+        let result = render_tagged(&args, &library, "Spiral", |job| {
+            jobs.push((job.output_path.clone(), job.zoom_level.level()));
+            Ok(())
+        });
+        // The broken bookmark is reported but does not stop the others.
+        assert!(result.is_err());
+        assert_eq!(jobs, [("tagged_seahorse_valley.png".to_owned(), 150.0)]);
+
+        let mut jobs = Vec::new();
+        render_tagged(&args, &library, "elephant", |job| {
+            jobs.push((job.real_center.to_f64(), job.zoom_level.level()));
+            Ok(())
+        })
+        .unwrap();
+        // The view is that of the bookmark and not of the command line.
+        assert_eq!(jobs, [(0.275, 0.0)]);
+
+        assert!(render_tagged(&args, &library, "none", |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn check_missing_library() {
+        assert_eq!(
+            load_library(Path::new("this_bookmark_file_does_not_exist.txt")),
+            Ok(BookmarkLibrary::default())
+        );
+    }
+}
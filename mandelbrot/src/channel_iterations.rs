@@ -0,0 +1,66 @@
+use core::fmt;
+use core::num::{NonZeroU32, ParseIntError};
+use core::str::FromStr;
+
+/// The maximum number of iterations of the red, green and blue channels of a Nebulabrot,
+/// that can be parsed from a string on the form "RED,GREEN,BLUE".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelIterations([NonZeroU32; 3]);
+
+impl ChannelIterations {
+    pub const fn get(&self) -> [NonZeroU32; 3] {
+        self.0
+    }
+}
+
+impl fmt::Display for ChannelIterations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [red, green, blue] = self.0;
+        write!(f, "{red},{green},{blue}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseChannelIterationsError {
+    InvalidFormat,
+    InvalidValue(ParseIntError),
+}
+
+impl fmt::Display for ParseChannelIterationsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormat => write!(
+                f,
+                "the iterations must be given in the format RED,GREEN,BLUE"
+            ),
+            Self::InvalidValue(e) => write!(f, "an iteration count could not be parsed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseChannelIterationsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidValue(e) => Some(e),
+            Self::InvalidFormat => None,
+        }
+    }
+}
+
+impl FromStr for ChannelIterations {
+    type Err = ParseChannelIterationsError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(',');
+        let mut next_channel = || match parts.next() {
+            Some(s) => s.trim().parse().map_err(Self::Err::InvalidValue),
+            None => Err(Self::Err::InvalidFormat),
+        };
+
+        let channels = [next_channel()?, next_channel()?, next_channel()?];
+        if parts.next().is_some() {
+            Err(Self::Err::InvalidFormat)
+        } else {
+            Ok(Self(channels))
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Saving the channels of an image as images of their own next to it, with `--channels`.
+
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use image::DynamicImage;
+use mandellib::{
+    colorize, compute, separate_channels, Coloring, Frame, ImageChannel, RenderParameters,
+};
+
+use crate::animation::is_recolorable;
+use crate::command_line_interface::Cli;
+
+/// The text in the suffix template of `--channel-suffix` that is replaced by the name of the channel.
+const CHANNEL_PLACEHOLDER: &str = "{channel}";
+
+/// Returns the path that the given channel of the image at the output path is saved at:
+/// the template with the name of the channel filled in is appended to the name of the file,
+/// so "out.png" and the template "_{channel}" give "out_red.png" for the red channel.
+pub fn channel_path(output_path: &Path, template: &str, channel: ImageChannel) -> PathBuf {
+    let mut file_name = output_path.file_stem().unwrap_or_default().to_owned();
+    file_name.push(template.replace(CHANNEL_PLACEHOLDER, &channel.to_string()));
+    if let Some(extension) = output_path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    output_path.with_file_name(file_name)
+}
+
+/// Computes the escape data of the image once, saves every channel of `--channels` next to the output path
+/// and returns the image colored from the same data.
+///
+/// # Errors
+/// Returns an error if the suffix template does not contain the name of the channel,
+/// if the image would not be the same as a normal render when colored from its escape data,
+/// or if a channel can not be saved.
+pub fn render_and_save_channels(
+    args: &Cli,
+    render_parameters: &RenderParameters,
+    draw_region: Frame,
+    output_path: &Path,
+) -> Result<DynamicImage, Box<dyn Error>> {
+    if !args.channel_suffix.contains(CHANNEL_PLACEHOLDER) {
+        return Err(format!(
+            "the channel suffix \"{}\" must contain \"{CHANNEL_PLACEHOLDER}\", or every channel would be saved at the same path",
+            args.channel_suffix
+        )
+        .into());
+    }
+    if !is_recolorable(render_parameters) {
+        return Err("the channels can only be separated from images rendered with the box filter, in full quality and without equalized grayscale".into());
+    }
+
+    if args.verbose {
+        _ = write!(io::stdout(), "\rComputing escape data");
+    }
+    let buffer = compute(render_parameters, draw_region);
+    let image = colorize(&buffer, &Coloring::from(render_parameters));
+    if args.verbose {
+        _ = write!(io::stdout(), "\rSaving channels");
+    }
+    for (&channel, channel_image) in
+        args.channels
+            .iter()
+            .zip(separate_channels(&buffer, &image, &args.channels))
+    {
+        let path = channel_path(output_path, &args.channel_suffix, channel);
+        channel_image.save(&path).map_err(|e| {
+            format!(
+                "could not save the {channel} channel at {}: {e}",
+                path.display()
+            )
+        })?;
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod test_channels {
+    use super::*;
+
+    #[test]
+    fn check_channel_path() {
+        assert_eq!(
+            channel_path(Path::new("images/out.png"), "_{channel}", ImageChannel::Red),
+            Path::new("images/out_red.png")
+        );
+        assert_eq!(
+            channel_path(
+                Path::new("out.tiff"),
+                ".{channel}.pass",
+                ImageChannel::EscapeSpeed
+            ),
+            Path::new("out.escape.pass.tiff")
+        );
+    }
+}
@@ -1,11 +1,33 @@
-use core::num::{NonZeroU32, NonZeroU8, NonZeroUsize};
+use core::num::{NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::thread;
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+use color_space::{BuiltinPalette, CurvePalette};
+use mandellib::{
+    auto_max_iterations, default_render_server_socket, BulbChecks, CpuLimit, CustomFormula,
+    DarkFrameThreshold, EscapeRadius, EscapeSpeedRange, Exponent, Formula, Frame, GrayscaleMode,
+    HeightScale, HeightfieldSettings, ImageChannel, InteriorShading, LowDiscrepancySequence,
+    Nebulabrot, Newton, Polynomial, PreciseReal, RayAngle, ReconstructionFilter, RenderQuality,
+    RenderingProfile, SamplePlacement, SlopeShading, SlopeShadingError, Smoothing, SsaaCutoff,
+    VarianceThreshold, WatermarkError, WatermarkPosition, Zoom, DEFAULT_BOOKMARKS_FILE,
+    DEFAULT_SMOOTHING_OFFSET,
+};
 
-use crate::resolution::Resolution;
+use crate::{
+    channel_iterations::ChannelIterations, hex_color::HexColor, print_preset::DEFAULT_PRESETS_FILE,
+    resolution::Resolution,
+};
+
+/// The number of orbits that are traced per pixel of a Buddhabrot or Nebulabrot
+/// if `--orbit-samples` is not given.
+const DEFAULT_ORBIT_SAMPLES_PER_PIXEL: u64 = 100;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+// Later occurrences of an argument override earlier ones, which lets `--seed-from`
+// place the recorded arguments before the ones given on the command line.
+#[command(author, version, about, long_about = None, args_override_self = true)]
 /// Renders a supersampled image of the Mandelbrot set to an image file.
 /// It is possible to change which part of the set is rendered, how zoomed in the image is,
 /// the number of iterations to use, as well as a few other things.
@@ -16,27 +38,27 @@ pub struct Cli {
         long,
         value_name = "RE(CENTER)",
         allow_negative_numbers = true,
-        default_value_t = -0.75
+        default_value = "-0.75"
     )]
     /// The real part of the center point of the image
-    pub real_center: f64,
+    pub real_center: PreciseReal,
 
     #[arg(
         short,
         long,
         value_name = "IM(CENTER)",
         allow_negative_numbers = true,
-        default_value_t = 0.0
+        default_value = "0.0"
     )]
     /// The imaginary part of the center point of the image
-    pub imag_center: f64,
+    pub imag_center: PreciseReal,
 
-    #[arg(short, long, default_value_t = 0.0, allow_negative_numbers = true)]
+    #[arg(short, long, default_value_t = Zoom::NONE, allow_negative_numbers = true)]
     /// A real number describing how far in to zoom on the given center point.
     /// This number works on an exponential scale where 0 means no zoom
     /// and every time it is increased by 1 the vertical and horizontal
     /// distances covered by the image are halved
-    pub zoom_level: f64,
+    pub zoom_level: Zoom,
 
     #[arg(
         short = 'p',
@@ -58,20 +80,176 @@ pub struct Cli {
     /// If this is set to 1, supersampling is turned off
     pub ssaa: NonZeroU8,
 
+    #[arg(
+        long,
+        value_name = "NAME",
+        overrides_with_all = ["resolution", "ssaa"],
+        conflicts_with_all = ["tile_size", "stitch"]
+    )]
+    /// Render for printing with a preset that chooses the resolution and SSAA factor from a paper size
+    /// and DPI, and saves the image with the DPI, bit depth and color profile of the preset.
+    /// The built-in presets are named "print-<PAPER>-<DPI>dpi", e.g. "print-a2-300dpi", where the paper
+    /// is one of a0 to a6, letter and tabloid in landscape, or in portrait if "-portrait" is appended.
+    /// Other presets are read from `--presets-file`. The DPI is only recorded in PNG images.
+    /// Whichever of this and `--resolution` or `--ssaa` is given last is used
+    pub preset: Option<String>,
+
+    #[arg(long, value_name = "FILE", default_value = DEFAULT_PRESETS_FILE)]
+    /// The file that user-defined print presets are read from.
+    /// Each preset starts with a line "preset <NAME>" followed by the lines "size <WIDTH>x<HEIGHT>"
+    /// in millimeters, and optionally "dpi <DPI>", "ssaa <SQRT(SSAA_FACTOR)>", "bit-depth <8 or 16>"
+    /// and "icc-profile <FILE>"
+    pub presets_file: PathBuf,
+
     #[arg(
         short,
         long,
-        default_value_t = const {NonZeroU32::new(255).expect("255 is not 0")},
+        default_value_t = const {NonZeroU64::new(255).expect("255 is not 0")},
     )]
     /// The maximum number of iterations for each pixel sample
-    pub max_iterations: NonZeroU32,
+    pub max_iterations: NonZeroU64,
+
+    #[arg(long, overrides_with = "max_iterations")]
+    /// Choose the maximum number of iterations from the zoom level and resolution,
+    /// with more iterations the smaller the pixels are. The chosen number is recorded in the image.
+    /// Whichever of this and `--max-iterations` is given last is used
+    pub auto_iterations: bool,
+
+    #[arg(short, long, default_value_t = Formula::Mandelbrot)]
+    /// The fractal to render. Either "mandelbrot", which iterates z -> z^d + c,
+    /// "mandelbar" (also known as the tricorn), which iterates z -> conj(z)^d + c,
+    /// or one of "burning-ship", "celtic", "perpendicular-mandelbrot", "perpendicular-burning-ship"
+    /// and "buffalo", which iterate z -> z^2 + c with the absolute value of parts of z^2 and ignore the exponent.
+    /// A comma separated list of these, e.g. "mandelbrot,mandelbrot,burning-ship",
+    /// gives a hybrid fractal that applies them in turn
+    pub fractal: Formula,
+
+    #[arg(short, long, default_value_t = Exponent::TWO)]
+    /// The exponent d in the iteration function z -> z^d + c.
+    /// The default of 2 gives the Mandelbrot set, while larger values give Multibrot sets
+    pub exponent: Exponent,
+
+    #[arg(long, conflicts_with_all = ["fractal", "exponent", "formula"])]
+    /// Render the Mandelbrot set with perturbation theory, which allows zooming
+    /// far beyond the precision limit of around 2^45 of the normal renderer.
+    /// All the digits of the center point are used
+    pub perturbation: bool,
+
+    #[arg(long, value_name = "FORMULA", conflicts_with_all = ["fractal", "exponent"])]
+    /// Render the fractal given by iterating a custom formula from z = 0,
+    /// e.g. "z = z^2 + c" or "z = conj(z)^2 + c".
+    /// The formula can use the variables z and c, the operators +, -, *, / and ^, parentheses,
+    /// the constants i, pi and e, and the functions conj, re, im, abs, exp, ln, sqrt, sin and cos.
+    /// Custom formulas are interpreted and render much slower than the built-in fractals
+    pub formula: Option<CustomFormula>,
 
     #[arg(long)]
     /// Output the image in grayscale by mapping escape speed to brightness
     pub grayscale: bool,
 
+    #[arg(long, value_name = "MODE", requires = "grayscale", default_value_t = GrayscaleMode::Linear)]
+    /// How escape speeds are mapped to brightness in grayscale images. Either "linear",
+    /// which leaves most of the exterior close to white, "equalized", which gives every brightness
+    /// to equally many pixels, or "log", which darkens by the logarithm of the iteration count.
+    /// Equalized images can not be split into tiles, since every tile would be equalized on its own
+    pub grayscale_mode: GrayscaleMode,
+
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["grayscale", "palette_curves", "palette_file"])]
+    /// Color the image with one of the built-in palettes: "classic", "fire", "ice", "grayscale",
+    /// "ultra-fractal-default" or "viridis". The palette of the rendering profile is used if it is not given
+    pub palette: Option<BuiltinPalette>,
+
+    #[arg(long, value_name = "CURVES", conflicts_with = "grayscale")]
+    /// Color the image with three curves that map the escape speed s to
+    /// the red, green and blue channels, e.g. "r=s^0.7, g=sin(3.1*s), b=1-s".
+    /// The curves can use the operators +, -, *, / and ^, parentheses, the constants pi and e,
+    /// and the functions sin, cos, tan, exp, ln, log2, sqrt, abs, floor, fract, min and max
+    pub palette_curves: Option<CurvePalette>,
+
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["grayscale", "palette_curves"])]
+    /// Color the image with a gradient from an Ultra Fractal ".ugr" file or a Fractint ".map" file.
+    /// Only the first gradient in a file with several gradients is used
+    pub palette_file: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE", conflicts_with = "orbit_density")]
+    /// Write the palette to an Ultra Fractal ".ugr" file or a Fractint ".map" file
+    /// instead of rendering an image, so that it can be used in other fractal programs
+    pub export_palette: Option<PathBuf>,
+
+    #[arg(long, value_name = "SHADING", default_value_t = InteriorShading::Flat)]
+    /// How to color the points inside the set. Either "flat", which colors them all the same,
+    /// "multiplier", which shades them by how strongly their orbits are attracted to a cycle,
+    /// "distance", which shades them by their estimated distance to the boundary of the set,
+    /// "magnitude", which shades them by how far from the origin their orbits end up,
+    /// or "atom-domain", which shades them by the iteration at which their orbits come closest to the origin,
+    /// which usually is the period of the component they are in.
+    /// Only the Mandelbrot and Multibrot sets are shaded by "multiplier" and "distance"
+    pub interior: InteriorShading,
+
+    #[arg(long, conflicts_with = "grayscale")]
+    /// Make everything outside the set transparent, so that only its inside is visible.
+    /// The edge of the set is smoothed by making pixels that are partly inside it partly transparent.
+    /// The output file must support transparency, like png
+    pub interior_only: bool,
+
+    #[arg(long, value_name = "RRGGBB", requires = "interior_only")]
+    /// Color the inside of the set with this color in hexadecimal form when `--interior-only` is used
+    pub interior_color: Option<HexColor>,
+
+    #[arg(long, conflicts_with = "interior_only")]
+    /// Darken the points outside the set whose orbits escape below the real axis.
+    /// This binary decomposition splits the bands of the exterior into cells along the external rays
+    pub binary_decomposition: bool,
+
+    #[arg(
+        long,
+        value_name = "RED,GREEN,BLUE",
+        group = "orbit_density",
+        conflicts_with_all = ["perturbation", "formula", "fractal", "palette", "palette_curves", "palette_file", "interior", "interior_only", "binary_decomposition", "mask", "tile_size"],
+    )]
+    /// Render a Nebulabrot instead of the set: the density of the orbits of points outside the set,
+    /// where each color channel only counts the orbits that escape within the given number of iterations.
+    /// The brightness of each channel is normalized to its densest pixel
+    pub nebulabrot: Option<ChannelIterations>,
+
+    #[arg(
+        long,
+        group = "orbit_density",
+        conflicts_with_all = ["perturbation", "formula", "fractal", "palette", "palette_curves", "palette_file", "interior", "interior_only", "binary_decomposition", "mask", "tile_size"],
+    )]
+    /// Render a Buddhabrot instead of the set: the density of the orbits of points outside the set
+    /// that escape within the maximum number of iterations
+    pub buddhabrot: bool,
+
+    #[arg(
+        long,
+        value_name = "POLYNOMIAL",
+        conflicts_with_all = ["orbit_density", "perturbation", "formula", "fractal", "exponent", "interior", "interior_only", "binary_decomposition", "mask", "tile_size"],
+    )]
+    /// Render the Newton fractal of the given polynomial instead of the set, e.g. "z^3 - 1".
+    /// Every point is colored by the root that Newton's method converges to from it,
+    /// and darkened the more iterations it takes to get there
+    pub newton: Option<Polynomial>,
+
+    #[arg(long, value_name = "SHADING", requires = "newton", default_value_t = Newton::DEFAULT_SHADING)]
+    /// How quickly the colors of a Newton fractal darken with the number of iterations.
+    /// A point that takes n iterations to converge gets a brightness of e^(-SHADING * n)
+    pub newton_shading: f64,
+
+    #[arg(long, value_name = "SAMPLES", requires = "orbit_density")]
+    /// The number of orbits to trace for `--nebulabrot` and `--buddhabrot`.
+    /// More orbits give a less noisy image. Defaults to 100 per pixel
+    pub orbit_samples: Option<NonZeroU64>,
+
+    #[arg(long, value_name = "SEQUENCE", requires = "orbit_density", default_value_t = LowDiscrepancySequence::R2)]
+    /// The low discrepancy sequence that the traced orbits start from for `--nebulabrot` and `--buddhabrot`:
+    /// "r2", "halton" or "sobol". Sobol converges the fastest when the number of orbits is a power of two
+    pub orbit_sequence: LowDiscrepancySequence,
+
     #[arg(short, long, default_value_t = String::from("mandelbrot_set.png"))]
     /// The path at which to save the resulting image.
+    /// If it ends in ".svg" the boundary of the set is traced into vector paths instead,
+    /// see `--boundary-iterations`.
     /// Supports saving as png
     #[cfg_attr(feature = "jpg", doc = ", jpg")]
     #[cfg_attr(feature = "webp", doc = ", webp")]
@@ -84,14 +262,779 @@ pub struct Cli {
     #[cfg_attr(feature = "tga", doc = ", and tga")]
     pub output_path: String,
 
+    #[arg(long, value_name = "FILE")]
+    /// Only compute the pixels where the given mask image is white.
+    /// The mask must have the same aspect ratio as the output image,
+    /// but is resized to its resolution if needed.
+    /// The rest of the pixels are filled with the color given by `--mask-fill`
+    pub mask: Option<PathBuf>,
+
+    #[arg(long, requires = "mask")]
+    /// Compute the pixels where the mask is black instead of where it is white
+    pub invert_mask: bool,
+
+    #[arg(long, value_name = "RRGGBB", requires = "mask", default_value_t = HexColor::BLACK)]
+    /// The color of the pixels outside the mask in hexadecimal form
+    pub mask_fill: HexColor,
+
+    #[arg(long, value_name = "WIDTHxHEIGHT", conflicts_with = "mask")]
+    /// Split the image into tiles of this size and save each tile to its own file
+    /// named like the output file, but with "_tile_<COLUMN>_<ROW>" added to the name.
+    /// The tiles can be rendered on different machines and combined with `--stitch`
+    pub tile_size: Option<Resolution>,
+
+    #[arg(
+        long,
+        value_name = "PIXELS",
+        requires = "tile_size",
+        default_value_t = 0
+    )]
+    /// Render this many extra pixels on every side of each tile.
+    /// This lets post-processing filters that depend on neighbouring pixels
+    /// be applied to each tile without visible seams, since the overlap is trimmed when stitching
+    pub tile_overlap: u32,
+
+    #[arg(long, value_name = "INDEX", requires = "tile_size")]
+    /// Only render the tile with this index, counted row by row from 0 at the top left tile
+    pub tile: Option<usize>,
+
+    #[arg(long, requires = "tile_size", conflicts_with = "tile")]
+    /// Instead of rendering, combine the tiles rendered with the same resolution,
+    /// tile size, overlap and output path into the output image
+    pub stitch: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["orbit_density", "newton", "mask", "tile_size", "watchdog", "contours", "equipotentials", "rays", "slope_shading", "mesh", "watermark"],
+    )]
+    /// Render the image a band of rows at a time and write every band to the output file
+    /// as soon as it is done, so that the whole image is never held in memory.
+    /// This makes very large images possible, but they can only be saved as PNG
+    /// and can not have anything drawn over them
+    pub stream: bool,
+
+    #[arg(long, value_name = "PERIOD", default_value_t = BulbChecks::Period2)]
+    /// Skip iterating the points in the largest bulbs of the Mandelbrot set up to this period, 2, 3 or 4,
+    /// since they are known to be inside it. Higher periods speed up views of those bulbs
+    /// but slow down other views slightly. Does not change the image
+    pub bulb_checks: BulbChecks,
+
+    #[arg(long)]
+    /// Iterate every point, including the ones in the main cardioid and the bulbs of --bulb-checks
+    /// that are known to be inside the set. Slower, but does not change the image
+    pub disable_shortcuts: bool,
+
+    #[arg(long)]
+    /// Compute both halves of views that contain the real axis, instead of mirroring one half onto the other.
+    /// Slower, and only changes the image slightly where the SSAA stops at the first sample
+    /// that is far enough from the set, since that sample is not mirrored
+    pub disable_mirroring: bool,
+
+    #[arg(long)]
+    /// Stop iterating points whose orbits contract like the orbits of points inside the set,
+    /// and take them to be inside it. Much faster for views with a lot of the inside of the set,
+    /// but a few points right outside it are taken to be inside as well.
+    /// Only used by the mandelbrot and mandelbar fractals
+    pub derivative_bailout: bool,
+
+    #[arg(long, default_value_t = RenderQuality::Full)]
+    /// "full" computes every pixel, while "draft" renders the image on a coarse grid
+    /// and only refines the parts where neighbouring pixels differ.
+    /// Drafts are much faster for views with a lot of the inside of the set, but can miss small details.
+    /// The experimental "quadtree" splits the image into cells where their corners differ and interpolates the rest,
+    /// which also skips smooth gradients outside the set, but does not supersample the interpolated pixels
+    pub quality: RenderQuality,
+
+    #[arg(long, value_name = "PLACEMENT", default_value_t = SamplePlacement::Grid)]
+    /// Where the SSAA samples are placed within each pixel. "grid" places them on a uniform grid,
+    /// while "gradient" spreads them across the edges that cross the pixel, which smooths edges
+    /// with fewer samples at the cost of one extra sample per pixel.
+    /// "r2", "halton" and "sobol" take them from a low discrepancy sequence, whose samples never line up
+    /// with an edge the way the rows of a grid do. Sobol works best when the SSAA factor is a power of two.
+    /// "jittered" moves every sample of the grid to a random point in its cell given by `--seed`,
+    /// which turns moiré into fine noise.
+    /// Compare the two with the tune-ssaa subcommand
+    pub sample_placement: SamplePlacement,
+
+    #[arg(long, value_name = "SEED", default_value_t = 0)]
+    /// Seed the pseudo-random numbers of the render: where "jittered" `--sample-placement` puts the samples,
+    /// and how far the traced orbits of `--nebulabrot` and `--buddhabrot` are shifted.
+    /// The same seed always gives the same image, and images with different seeds have independent noise.
+    /// 0 does not shift the orbits
+    pub seed: u64,
+
+    #[arg(long, value_name = "THRESHOLD")]
+    /// Take the first few SSAA samples of every pixel, and the rest of them only if the variance
+    /// of their escape speeds is above this threshold, e.g. 1e-6. Lower thresholds supersample more pixels.
+    /// Without it supersampling stops at the first sample that is far from the set
+    pub adaptive_ssaa: Option<VarianceThreshold>,
+
+    #[arg(long, value_name = "SPEED", default_value_t = SsaaCutoff::DEFAULT)]
+    /// Stop supersampling a pixel at the first sample whose escape speed is above this cutoff, between 0 and 1.
+    /// Raise it if the region that is not supersampled clips into the set, which can happen at low resolutions
+    pub ssaa_cutoff: SsaaCutoff,
+
+    #[arg(long, conflicts_with_all = ["ssaa_cutoff", "adaptive_ssaa"])]
+    /// Take every SSAA sample of every pixel, even far from the set
+    pub full_ssaa: bool,
+
+    #[arg(long)]
+    /// Color the pixels whose supersampling was stopped early orange/brown,
+    /// to see which parts of the image are not fully supersampled
+    pub show_ssaa_region: bool,
+
+    #[arg(
+        long = "filter",
+        value_name = "FILTER",
+        default_value_t = ReconstructionFilter::Box
+    )]
+    /// How the SSAA samples are combined into pixels. "box" averages the samples of every pixel,
+    /// while "tent" and "gaussian" weight them by their distance to the center of the pixel
+    /// and let them count for the neighbouring pixels as well, which smooths edges with the same
+    /// number of samples. Both take around twice as long as "box"
+    pub reconstruction_filter: ReconstructionFilter,
+
+    #[arg(long, value_name = "RADIUS", default_value_t = EscapeRadius::DEFAULT)]
+    /// Stop iterating a point once its orbit leaves the disk with this radius, between 2 and 1e9.
+    /// A smaller radius is slightly faster and a larger one makes the coloring more accurate.
+    /// Custom formulas and perturbed renders use their own radius
+    pub escape_radius: EscapeRadius,
+
+    #[arg(long, value_name = "SMOOTHING", default_value_t = Smoothing::Classic)]
+    /// How the iteration counts of escaping points are turned into colors. "classic" smooths them
+    /// and shifts them by the smoothing offset, "normalized" uses the standard normalized iteration count
+    /// n + 1 - log_d(ln|z|), which suits analysis, and "banded" does not smooth them, for a retro look
+    pub smoothing: Smoothing,
+
+    #[arg(
+        long,
+        value_name = "OFFSET",
+        allow_negative_numbers = true,
+        default_value_t = DEFAULT_SMOOTHING_OFFSET
+    )]
+    /// Subtracted from the smoothed iteration count of every escaping point,
+    /// which shifts the colors of the palette along the bands of the image. Only used by classic smoothing
+    pub smoothing_offset: f64,
+
+    #[arg(
+        long,
+        value_name = "OFFSET",
+        allow_negative_numbers = true,
+        default_value_t = 0.0
+    )]
+    /// Shift the colors of the palette along the bands of the image by this share of the palette,
+    /// wrapping around at its end. Animations cycle the palette by changing it between frames.
+    /// Ignored by grayscale images
+    pub palette_offset: f64,
+
+    #[arg(long, value_name = "MIN,MAX", default_value_t = EscapeSpeedRange::FULL)]
+    /// Stretch the palette over only this range of escape speeds, between 0 and 1, e.g. "0.2,0.9".
+    /// Faster and slower escaping points are colored like the ends of the range, which brings out
+    /// detail in images whose escape speeds are bunched together
+    pub escape_speed_range: EscapeSpeedRange,
+
+    #[arg(long, value_name = "PROFILE")]
+    /// Render with the defaults of the given rendering profile, e.g. "v1". The defaults of a profile
+    /// never change, so that images can be rendered identically by later versions of the program.
+    /// The smoothing, smoothing offset, escape radius and palette are those of the profile unless they are given.
+    /// Images record the profile they were rendered with. Defaults to the latest profile
+    pub profile: Option<RenderingProfile>,
+
+    #[arg(
+        long,
+        value_name = "ITERATIONS",
+        conflicts_with_all = ["orbit_density", "newton", "tile_size"]
+    )]
+    /// Draw contour lines of the smoothed iteration count over the image at every multiple of this
+    /// many iterations that points in the view escape after, at most 10000 of them.
+    /// Each line connects the points that escape after the same number of iterations
+    pub contours: Option<NonZeroU64>,
+
+    #[arg(long, value_name = "RRGGBB", requires = "contours", default_value_t = HexColor::WHITE)]
+    /// The color of the contour lines in hexadecimal form
+    pub contour_color: HexColor,
+
+    #[arg(long, value_name = "FILE", requires = "contours")]
+    /// Save the contour lines as an SVG image at this path instead of drawing them over the image
+    pub contours_svg: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "LEVELS",
+        value_delimiter = ',',
+        conflicts_with_all = ["orbit_density", "newton", "tile_size"]
+    )]
+    /// Draw equipotential lines over the image at these comma separated levels of the potential,
+    /// e.g. "1,0.1,0.01". The potential is close to the natural logarithm of the distance to the origin
+    /// far from the set and goes to zero at it, so smaller levels give lines that hug the set closer
+    pub equipotentials: Vec<f64>,
+
+    #[arg(
+        long,
+        value_name = "RRGGBB",
+        requires = "equipotentials",
+        default_value_t = HexColor::BLACK
+    )]
+    /// The color of the equipotential lines in hexadecimal form
+    pub equipotential_color: HexColor,
+
+    #[arg(
+        long,
+        value_name = "ANGLES",
+        value_delimiter = ',',
+        conflicts_with_all = ["orbit_density", "newton", "tile_size"]
+    )]
+    /// Draw the external rays with these angles over the image, given as comma separated fractions
+    /// of a turn such as "1/3,2/3" or decimal numbers such as "0.25".
+    /// Rays with rational angles land on the set, and which of them land together shows
+    /// how its components are connected. Only works for the Mandelbrot and Multibrot sets
+    pub rays: Vec<RayAngle>,
+
+    #[arg(long, value_name = "RRGGBB", requires = "rays", default_value_t = HexColor::WHITE)]
+    /// The color of the external rays in hexadecimal form
+    pub ray_color: HexColor,
+
+    #[arg(long, conflicts_with_all = ["orbit_density", "newton", "tile_size"])]
+    /// Light the outside of the set as if it was a surface whose height grows with the escape speed,
+    /// which gives the image an embossed look
+    pub slope_shading: bool,
+
+    #[arg(long, value_name = "DEGREES", requires = "slope_shading", allow_negative_numbers = true, default_value_t = SlopeShading::DEFAULT_AZIMUTH)]
+    /// The direction that the light of `--slope-shading` comes from,
+    /// counter-clockwise from the positive real axis. The default lights the image from the top left
+    pub light_azimuth: f64,
+
+    #[arg(long, value_name = "DEGREES", requires = "slope_shading", default_value_t = SlopeShading::DEFAULT_ELEVATION)]
+    /// The angle of the light of `--slope-shading` above the image, above 0 and at most 90.
+    /// Lower lights give deeper shadows
+    pub light_elevation: f64,
+
+    #[arg(long, value_name = "RELIEF", requires = "slope_shading", default_value_t = SlopeShading::DEFAULT_RELIEF)]
+    /// How steep the slopes of `--slope-shading` are
+    pub slope_relief: f64,
+
+    #[arg(long, value_name = "STRENGTH", requires = "slope_shading", default_value_t = SlopeShading::DEFAULT_STRENGTH)]
+    /// How much `--slope-shading` changes the colors of the image, from 0 to 1
+    pub slope_strength: f64,
+
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["orbit_density", "newton", "tile_size"])]
+    /// Also save a heightfield of the image as a 3D mesh at this path, for 3D printing or raytracing.
+    /// The height of every pixel grows with its smoothed iteration count, and the mesh is closed
+    /// into a solid. The format is given by the extension, either ".obj" or ".stl"
+    pub mesh: Option<PathBuf>,
+
+    #[arg(long, value_name = "RELIEF", requires = "mesh", default_value_t = HeightfieldSettings::default().relief)]
+    /// The height of the set above the lowest points of the mesh, as a fraction of its width
+    pub mesh_relief: f64,
+
+    #[arg(long, value_name = "SCALE", requires = "mesh", default_value_t = HeightScale::default())]
+    /// How the iteration counts are mapped to heights in the mesh. Either "log",
+    /// which brings out the bands of the exterior, or "linear", which leaves most of them close to the floor
+    pub mesh_scale: HeightScale,
+
+    #[arg(long, value_name = "FILE", conflicts_with = "tile_size")]
+    /// Composite the given image, e.g. a logo or signature, onto the output before it is saved.
+    /// Transparent parts of the image are left out
+    pub watermark: Option<PathBuf>,
+
+    #[arg(
+        long = "watermark-pos",
+        value_name = "POSITION",
+        requires = "watermark",
+        default_value_t = WatermarkPosition::BottomRight
+    )]
+    /// Where to place the watermark: "tl", "tr", "bl", "br" or "center"
+    pub watermark_position: WatermarkPosition,
+
+    #[arg(
+        long,
+        value_name = "OPACITY",
+        requires = "watermark",
+        default_value_t = 0.5,
+        value_parser = parse_watermark_opacity
+    )]
+    /// How opaque the watermark is, from 0 to 1
+    pub watermark_opacity: f64,
+
+    #[arg(long, value_name = "ITERATIONS")]
+    /// When the output path ends in ".svg", trace the boundary between the points that escape
+    /// within this many iterations and those that do not. Defaults to the maximum number of iterations,
+    /// which traces the boundary of the set itself
+    pub boundary_iterations: Option<NonZeroU64>,
+
+    #[arg(long, value_name = "PIXELS", default_value_t = 0.5)]
+    /// Simplify the paths of SVG output by leaving out the points that are closer than this
+    /// to the lines between the remaining ones
+    pub simplify: f64,
+
+    #[arg(long, value_name = "FACTOR", conflicts_with_all = ["orbit_density", "newton", "mask", "tile_size"])]
+    /// Time how long every tile or band of the image takes to render, and report the ones that take
+    /// more than this many times the median time per pixel together with the regions of the complex plane
+    /// that they cover. Must be at least 1
+    pub watchdog: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "CHANNELS",
+        value_delimiter = ',',
+        conflicts_with_all = ["orbit_density", "newton", "mask", "tile_size", "watchdog", "stream"]
+    )]
+    /// Also save these comma separated channels of the image as grayscale images of their own,
+    /// for compositing in an image editor: "red", "green", "blue" and "alpha" of the colors,
+    /// "escape" for the escape speeds as 16 bit levels, and "interior" for the share of every pixel inside the set.
+    /// They are computed in the same pass as the image, and saved next to it, see `--channel-suffix`.
+    /// Needs the box filter, full quality and a grayscale mode other than equalized
+    pub channels: Vec<ImageChannel>,
+
+    #[arg(long, value_name = "TEMPLATE", requires = "channels", default_value_t = String::from("_{channel}"))]
+    /// Appended to the name of the output file to get the names of the files of `--channels`,
+    /// with "{channel}" replaced by the name of the channel. The default saves the red channel of "out.png" as "out_red.png"
+    pub channel_suffix: String,
+
+    #[arg(long, value_name = "FILE", requires = "watchdog")]
+    /// Save a heat map of the time per pixel of every tile or band of the image at this path,
+    /// going from black through red and yellow to white for the slowest one
+    pub heat_map: Option<PathBuf>,
+
     #[arg(short, long)]
     /// Print extra information and show the progress of the rendering process
     pub verbose: bool,
 
+    #[arg(short, long)]
+    /// Do not print warnings about settings that are probably a mistake, such as too few iterations
+    /// for the zoom level or a view that is entirely inside the set
+    pub quiet: bool,
+
+    #[arg(long, value_name = "FRACTION", default_value_t = DarkFrameThreshold::DEFAULT)]
+    /// Warn after rendering if more than this fraction, between 0 and 1, of the image reached the maximum number
+    /// of iterations, which colors it like the inside of the set, and suggest doubling the iterations
+    pub dark_frame_threshold: DarkFrameThreshold,
+
+    #[arg(long)]
+    /// Ring the terminal bell with a message when the image has been saved,
+    /// so that a long render is not forgotten about. If the program is compiled
+    /// with the `notify` feature a desktop notification is shown as well
+    pub notify: bool,
+
     #[arg(short, long)]
     /// The number of parallel jobs to dispatch. If this is not set the program
     /// will let the parallelism library decide.
     pub jobs: Option<NonZeroUsize>,
+
+    #[arg(long, value_name = "PERCENT")]
+    /// Only use this percentage of the processor, or of the jobs if they are given,
+    /// so that a long render can run in the background without the fans at full speed.
+    /// Fewer jobs are dispatched, and they take breaks to stay under the limit
+    pub max_cpu: Option<CpuLimit>,
+
+    #[arg(long, value_name = "IMAGE")]
+    /// Render with the settings recorded in a PNG image that was rendered by this program.
+    /// Any other arguments that are given override the recorded ones,
+    /// e.g. to render the same view at a higher resolution
+    pub seed_from: Option<PathBuf>,
+
+    #[cfg(feature = "trace")]
+    #[arg(long, value_name = "PATH")]
+    /// Record how long each part of the rendering takes and write it to this file.
+    /// The trace can be opened in chrome://tracing or https://ui.perfetto.dev
+    pub trace: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Instead of saving an image, find the lowest SSAA factor that renders the image
+    /// with the given quality. The image is rendered with a high SSAA factor as a reference,
+    /// and then with increasing SSAA factors until one is similar enough to the reference
+    TuneSsaa(TuneSsaaArgs),
+    /// Instead of rendering once, read commands from stdin that change the view and render it,
+    /// e.g. "center -0.75 0.1", "zoom 12", "iters 2000" and "render out.png".
+    /// The other arguments give the starting settings. Type "help" to list the commands
+    Repl,
+    /// Instead of rendering once, listen for views sent from the viewer and render them one at a time.
+    /// Each image is saved next to the output path with an increasing number appended to its name,
+    /// e.g. "mandelbrot_set_1.png"
+    Batch(BatchArgs),
+    /// Instead of rendering the view given by the arguments, list or render the views
+    /// that were bookmarked in the viewer
+    Bookmarks(BookmarksArgs),
+    /// Instead of rendering one view, render every frame of an animation that moves through the keyframes
+    /// in a file. Each frame is saved next to the output path with its index appended to its name,
+    /// e.g. "mandelbrot_set_042.png". An interrupted animation continues where it stopped
+    /// when it is rendered again with the same arguments
+    Animate(AnimateArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct AnimateArgs {
+    /// A file with one keyframe per line: its frame index followed by the real and imaginary parts
+    /// of its center and its zoom level, e.g. "0 -0.75 0 0" and "600 -0.745 0.1 30".
+    /// A fifth value gives the palette offset at the keyframe, which changes linearly between keyframes
+    /// and is added to `--palette-offset`. The first keyframe must be at frame 0
+    pub keyframes: PathBuf,
+
+    #[arg(
+        long,
+        value_name = "CYCLES",
+        allow_negative_numbers = true,
+        default_value_t = 0.0
+    )]
+    /// Cycle the colors of the palette by this share of the palette every frame,
+    /// on top of the palette offsets of the keyframes. Frames that only differ from the previous one
+    /// in their palette offset are colored from its escape data instead of being computed again
+    pub palette_cycle_rate: f64,
+}
+
+#[derive(Args, Debug)]
+pub struct BatchArgs {
+    #[arg(long, value_name = "SOCKET", default_value_os_t = default_render_server_socket())]
+    /// The local socket to listen for views on, which only the user who runs the program can connect to.
+    /// On Windows this is the name of a named pipe, like "\\.\pipe\mandelbrot-batch"
+    pub listen: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct BookmarksArgs {
+    #[arg(long, value_name = "FILE", default_value = DEFAULT_BOOKMARKS_FILE)]
+    /// The file that the viewer saves the bookmarks in
+    pub file: PathBuf,
+
+    #[command(subcommand)]
+    pub action: BookmarksAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BookmarksAction {
+    /// List the name, tags, notes and command of the bookmarks, or only of those with the given tag
+    List {
+        tag: Option<String>,
+
+        #[arg(long, value_name = "WORDS", default_value = "")]
+        /// Only list the bookmarks whose name, notes or tags contain every one of the words
+        search: String,
+    },
+    /// Render every bookmark with the given tag. Each image is saved next to the output path
+    /// with the name of the bookmark appended to its name, e.g. "mandelbrot_set_seahorse_valley.png"
+    Render { tag: String },
+}
+
+#[derive(Args, Debug)]
+pub struct TuneSsaaArgs {
+    #[arg(
+        long,
+        value_name = "SQRT(SSAA_FACTOR)",
+        default_value_t = const {NonZeroU8::new(12).expect("12 is not 0")},
+    )]
+    /// The SSAA factor of the reference render
+    pub reference_ssaa: NonZeroU8,
+
+    #[arg(long, value_name = "DECIBELS", default_value_t = 40.0)]
+    /// The lowest acceptable peak signal-to-noise ratio compared to the reference
+    pub target_psnr: f64,
+
+    #[arg(long, value_name = "INDEX", default_value_t = 0.99)]
+    /// The lowest acceptable structural similarity index compared to the reference, at most 1
+    pub target_ssim: f64,
+}
+
+impl Cli {
+    /// Sets the maximum number of iterations from the view if `--auto-iterations` is given.
+    pub fn resolve_auto_iterations(&mut self) {
+        if self.auto_iterations {
+            let aspect_ratio = f64::from(self.resolution.x_resolution().get())
+                / f64::from(self.resolution.y_resolution().get());
+            let frame = Frame::from_zoom(0.0, 0.0, self.zoom_level, aspect_ratio);
+            self.max_iterations = auto_max_iterations(frame, self.resolution.y_resolution());
+        }
+    }
+
+    /// Returns the rendering profile whose defaults the image is rendered with.
+    pub fn rendering_profile(&self) -> RenderingProfile {
+        self.profile.unwrap_or(RenderingProfile::LATEST)
+    }
+
+    /// Returns the number of threads to render with and the share of the time that each of them works,
+    /// from `--jobs` and `--max-cpu`. Zero threads lets the parallelism library decide.
+    pub fn render_threads(&self) -> (usize, CpuLimit) {
+        match self.max_cpu {
+            None => (self.jobs.map_or(0, NonZeroUsize::get), CpuLimit::NONE),
+            Some(limit) => {
+                let cores = self
+                    .jobs
+                    .or_else(|| thread::available_parallelism().ok())
+                    .unwrap_or(NonZeroUsize::MIN);
+                let (threads, per_thread) = limit.split(cores);
+                (threads.get(), per_thread)
+            }
+        }
+    }
+
+    /// Returns the largest SSAA factor that the image will be rendered with.
+    pub fn max_ssaa(&self) -> NonZeroU8 {
+        match self.command {
+            Some(Command::TuneSsaa(ref tune)) => self.ssaa.max(tune.reference_ssaa),
+            Some(
+                Command::Repl | Command::Batch(_) | Command::Bookmarks(_) | Command::Animate(_),
+            )
+            | None => self.ssaa,
+        }
+    }
+
+    /// Returns the slope shading to apply to the image, if any.
+    pub fn slope_shading(&self) -> Result<Option<SlopeShading>, SlopeShadingError> {
+        if !self.slope_shading {
+            return Ok(None);
+        }
+        SlopeShading::new(
+            self.light_azimuth,
+            self.light_elevation,
+            self.slope_relief,
+            self.slope_strength,
+        )
+        .map(Some)
+    }
+
+    /// Returns the settings of the heightfield mesh to save along with the image.
+    pub fn heightfield_settings(&self) -> HeightfieldSettings {
+        HeightfieldSettings {
+            relief: self.mesh_relief,
+            scale: self.mesh_scale,
+            ..HeightfieldSettings::default()
+        }
+    }
+
+    /// Returns the settings of the Newton fractal to render instead of the set, if any.
+    pub fn newton(&self) -> Option<Newton> {
+        self.newton.clone().map(|polynomial| Newton {
+            polynomial,
+            shading: self.newton_shading,
+        })
+    }
+
+    /// Returns the settings of the Buddhabrot or Nebulabrot to render instead of the set, if any.
+    pub fn nebulabrot(&self) -> Option<Nebulabrot> {
+        let channel_max_iterations = match (self.nebulabrot, self.buddhabrot) {
+            (Some(channels), _) => channels.get(),
+            // The orbits of the Buddhabrot are stored, so they can not be as long as the iterations
+            // of the set. No one would wait for orbits of more than u32::MAX iterations anyway.
+            (None, true) => {
+                [NonZeroU32::try_from(self.max_iterations).unwrap_or(NonZeroU32::MAX); 3]
+            }
+            (None, false) => return None,
+        };
+        let samples = self.orbit_samples.unwrap_or_else(|| {
+            let pixels = u64::from(self.resolution.x_resolution().get())
+                * u64::from(self.resolution.y_resolution().get());
+            NonZeroU64::new(pixels.saturating_mul(DEFAULT_ORBIT_SAMPLES_PER_PIXEL))
+                .expect("the resolution is not zero")
+        });
+        Some(Nebulabrot {
+            sequence: self.orbit_sequence,
+            ..Nebulabrot::new(channel_max_iterations, samples)
+        })
+    }
+
+    /// Returns the arguments that determine what the rendered image looks like,
+    /// such that parsing them gives an image identical to the one rendered with these settings.
+    /// Arguments that only affect where or how the image is saved are not included.
+    pub fn recorded_arguments(&self) -> Vec<String> {
+        let mut arguments = vec![
+            format!("--real-center={}", self.real_center),
+            format!("--imag-center={}", self.imag_center),
+            format!("--zoom-level={}", self.zoom_level),
+            format!("--resolution={}", self.resolution),
+            format!("--ssaa={}", self.ssaa),
+            // The resolved number of iterations is recorded rather than `--auto-iterations`,
+            // so that rendering the image again at another resolution gives the same image.
+            format!("--max-iterations={}", self.max_iterations),
+            // The profile is always recorded, so that the image is rendered with the same defaults
+            // by later versions of the program, whose latest profile may be another one.
+            format!("--profile={}", self.rendering_profile()),
+        ];
+        // These are only recorded when they differ from their defaults,
+        // since they conflict with `--perturbation` and `--formula`.
+        if self.fractal != Formula::default() {
+            arguments.push(format!("--fractal={}", self.fractal));
+        }
+        if self.exponent != Exponent::default() {
+            arguments.push(format!("--exponent={}", self.exponent));
+        }
+        if self.perturbation {
+            arguments.push("--perturbation".to_owned());
+        }
+        if let Some(ref formula) = self.formula {
+            arguments.push(format!("--formula={formula}"));
+        }
+        if self.grayscale {
+            arguments.push("--grayscale".to_owned());
+            if self.grayscale_mode != GrayscaleMode::default() {
+                arguments.push(format!("--grayscale-mode={}", self.grayscale_mode));
+            }
+        }
+        if let Some(palette) = self.palette {
+            arguments.push(format!("--palette={palette}"));
+        }
+        if let Some(ref curves) = self.palette_curves {
+            arguments.push(format!("--palette-curves={curves}"));
+        }
+        if let Some(ref palette_file) = self.palette_file {
+            arguments.push(format!("--palette-file={}", palette_file.display()));
+        }
+        if self.interior != InteriorShading::default() {
+            arguments.push(format!("--interior={}", self.interior));
+        }
+        if self.interior_only {
+            arguments.push("--interior-only".to_owned());
+            if let Some(color) = self.interior_color {
+                arguments.push(format!("--interior-color={color}"));
+            }
+        }
+        if self.binary_decomposition {
+            arguments.push("--binary-decomposition".to_owned());
+        }
+        if let Some(channels) = self.nebulabrot {
+            arguments.push(format!("--nebulabrot={channels}"));
+        }
+        if self.buddhabrot {
+            arguments.push("--buddhabrot".to_owned());
+        }
+        if let Some(ref polynomial) = self.newton {
+            arguments.push(format!("--newton={polynomial}"));
+            if self.newton_shading != Newton::DEFAULT_SHADING {
+                arguments.push(format!("--newton-shading={}", self.newton_shading));
+            }
+        }
+        if let Some(samples) = self.orbit_samples {
+            arguments.push(format!("--orbit-samples={samples}"));
+        }
+        if self.orbit_sequence != LowDiscrepancySequence::default() {
+            arguments.push(format!("--orbit-sequence={}", self.orbit_sequence));
+        }
+        if self.escape_radius != EscapeRadius::default() {
+            arguments.push(format!("--escape-radius={}", self.escape_radius));
+        }
+        if self.smoothing != Smoothing::default() {
+            arguments.push(format!("--smoothing={}", self.smoothing));
+        }
+        if self.smoothing_offset != DEFAULT_SMOOTHING_OFFSET {
+            arguments.push(format!("--smoothing-offset={}", self.smoothing_offset));
+        }
+        if self.palette_offset != 0.0 {
+            arguments.push(format!("--palette-offset={}", self.palette_offset));
+        }
+        if self.escape_speed_range != EscapeSpeedRange::FULL {
+            arguments.push(format!("--escape-speed-range={}", self.escape_speed_range));
+        }
+        if self.derivative_bailout {
+            arguments.push("--derivative-bailout".to_owned());
+        }
+        if self.quality != RenderQuality::default() {
+            arguments.push(format!("--quality={}", self.quality));
+        }
+        if self.sample_placement != SamplePlacement::default() {
+            arguments.push(format!("--sample-placement={}", self.sample_placement));
+        }
+        if self.seed != 0 {
+            arguments.push(format!("--seed={}", self.seed));
+        }
+        if let Some(threshold) = self.adaptive_ssaa {
+            arguments.push(format!("--adaptive-ssaa={threshold}"));
+        }
+        if self.ssaa_cutoff != SsaaCutoff::default() {
+            arguments.push(format!("--ssaa-cutoff={}", self.ssaa_cutoff));
+        }
+        if self.full_ssaa {
+            arguments.push("--full-ssaa".to_owned());
+        }
+        if self.show_ssaa_region {
+            arguments.push("--show-ssaa-region".to_owned());
+        }
+        if self.reconstruction_filter != ReconstructionFilter::default() {
+            arguments.push(format!("--filter={}", self.reconstruction_filter));
+        }
+        // The SVG path is not recorded, since the contours are not part of the image in that case.
+        if let Some(spacing) = self.contours {
+            if self.contours_svg.is_none() {
+                arguments.push(format!("--contours={spacing}"));
+                arguments.push(format!("--contour-color={}", self.contour_color));
+            }
+        }
+        if !self.equipotentials.is_empty() {
+            let levels: Vec<String> = self.equipotentials.iter().map(f64::to_string).collect();
+            arguments.push(format!("--equipotentials={}", levels.join(",")));
+            arguments.push(format!(
+                "--equipotential-color={}",
+                self.equipotential_color
+            ));
+        }
+        if !self.rays.is_empty() {
+            let angles: Vec<String> = self.rays.iter().map(RayAngle::to_string).collect();
+            arguments.push(format!("--rays={}", angles.join(",")));
+            arguments.push(format!("--ray-color={}", self.ray_color));
+        }
+        if self.slope_shading {
+            arguments.push("--slope-shading".to_owned());
+            arguments.push(format!("--light-azimuth={}", self.light_azimuth));
+            arguments.push(format!("--light-elevation={}", self.light_elevation));
+            arguments.push(format!("--slope-relief={}", self.slope_relief));
+            arguments.push(format!("--slope-strength={}", self.slope_strength));
+        }
+        if let Some(ref watermark) = self.watermark {
+            arguments.push(format!("--watermark={}", watermark.display()));
+            arguments.push(format!("--watermark-pos={}", self.watermark_position));
+            arguments.push(format!("--watermark-opacity={}", self.watermark_opacity));
+        }
+        if let Some(ref mask) = self.mask {
+            arguments.push(format!("--mask={}", mask.display()));
+            arguments.push(format!("--mask-fill={}", self.mask_fill));
+            if self.invert_mask {
+                arguments.push("--invert-mask".to_owned());
+            }
+        }
+        arguments
+    }
+
+    /// Parses the recorded arguments followed by the given command line, without its `--seed-from` argument.
+    /// The given command line includes the program name.
+    pub fn parse_with_recorded(
+        recorded: Vec<String>,
+        command_line: impl IntoIterator<Item = OsString>,
+    ) -> Result<Self, clap::Error> {
+        let mut command_line = command_line.into_iter();
+        let program_name = command_line.next().unwrap_or_default();
+
+        let mut given = Vec::new();
+        while let Some(argument) = command_line.next() {
+            if argument == "--seed-from" {
+                // Also skip the value.
+                command_line.next();
+            } else if !argument
+                .to_str()
+                .is_some_and(|argument| argument.starts_with("--seed-from="))
+            {
+                given.push(argument);
+            }
+        }
+
+        Self::try_parse_from(
+            core::iter::once(program_name)
+                .chain(recorded.into_iter().map(OsString::from))
+                .chain(given),
+        )
+    }
+}
+
+/// Parses a watermark opacity, so that values outside of \[0, 1\] are rejected
+/// together with the other invalid arguments instead of after the image has been rendered.
+fn parse_watermark_opacity(s: &str) -> Result<f64, String> {
+    let opacity: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if (0.0..=1.0).contains(&opacity) {
+        Ok(opacity)
+    } else {
+        Err(WatermarkError::InvalidOpacity(opacity).to_string())
+    }
 }
 
 #[cfg(test)]
@@ -103,4 +1046,437 @@ mod test_cli {
         use clap::CommandFactory;
         Cli::command().debug_assert();
     }
+
+    #[test]
+    fn check_recorded_arguments() {
+        let original = Cli::parse_from([
+            "mandelbrot",
+            "-r",
+            "-0.7436438870371587047521",
+            "-i=0.1318259043",
+            "-z",
+            "20.5",
+            "--formula",
+            "z = conj(z)^3 + c",
+            "--palette-curves",
+            "r=s, g=s^2, b=1-s",
+            "--interior",
+            "distance",
+            "--quality",
+            "draft",
+            "--sample-placement",
+            "gradient",
+            "--adaptive-ssaa",
+            "1e-7",
+            "--ssaa-cutoff",
+            "0.99",
+            "--show-ssaa-region",
+            "--filter",
+            "tent",
+            "--derivative-bailout",
+            "--contours",
+            "25",
+            "--rays",
+            "1/7,0.25",
+            "--equipotentials",
+            "0.5,0.05",
+            "--watermark",
+            "logo.png",
+            "--watermark-pos",
+            "tl",
+            "-p",
+            "300x200",
+            "-o",
+            "somewhere.png",
+        ]);
+        let recorded = original.recorded_arguments();
+
+        // Rerendering at a higher resolution keeps the rest of the settings.
+        let rerender = Cli::parse_with_recorded(
+            recorded,
+            [
+                "mandelbrot",
+                "--seed-from",
+                "somewhere.png",
+                "-p",
+                "3000x2000",
+            ]
+            .map(OsString::from),
+        )
+        .unwrap();
+        assert_eq!(rerender.real_center, original.real_center);
+        assert_eq!(rerender.imag_center, original.imag_center);
+        assert_eq!(rerender.zoom_level, original.zoom_level);
+        assert_eq!(rerender.formula, original.formula);
+        assert_eq!(rerender.palette_curves, original.palette_curves);
+        assert_eq!(rerender.interior, InteriorShading::Distance);
+        assert_eq!(rerender.quality, RenderQuality::Draft);
+        assert_eq!(rerender.sample_placement, SamplePlacement::Gradient);
+        assert_eq!(rerender.adaptive_ssaa, "1e-7".parse().ok());
+        assert_eq!(rerender.ssaa_cutoff, "0.99".parse().unwrap());
+        assert!(rerender.show_ssaa_region);
+        assert_eq!(rerender.reconstruction_filter, ReconstructionFilter::Tent);
+        assert!(rerender.derivative_bailout);
+        assert_eq!(rerender.contours, NonZeroU64::new(25));
+        assert_eq!(rerender.contour_color, HexColor::WHITE);
+        assert_eq!(rerender.equipotentials, [0.5, 0.05]);
+        assert_eq!(rerender.equipotential_color, HexColor::BLACK);
+        assert_eq!(
+            rerender.rays,
+            ["1/7".parse().unwrap(), "1/4".parse().unwrap()]
+        );
+        assert_eq!(rerender.watermark, Some(PathBuf::from("logo.png")));
+        assert_eq!(rerender.watermark_position, WatermarkPosition::TopLeft);
+        assert_eq!(rerender.watermark_opacity, 0.5);
+        for opacity in ["2", "-0.1", "NaN", "opaque"] {
+            assert!(Cli::try_parse_from([
+                "mandelbrot",
+                "--watermark",
+                "logo.png",
+                "--watermark-opacity",
+                opacity
+            ])
+            .is_err());
+        }
+        assert_eq!(rerender.resolution, Resolution::new(3000, 2000).unwrap());
+        assert_eq!(rerender.seed_from, None);
+        assert_eq!(rerender.output_path, "mandelbrot_set.png");
+    }
+
+    #[test]
+    fn check_hybrid_fractal() {
+        let args = Cli::parse_from(["mandelbrot", "-f", "Mandelbrot, Burning Ship"]);
+        let recorded = args.recorded_arguments();
+        assert!(recorded.contains(&"--fractal=mandelbrot,burning-ship".to_owned()));
+        let rerender =
+            Cli::parse_with_recorded(recorded, ["mandelbrot"].map(OsString::from)).unwrap();
+        assert_eq!(rerender.fractal, args.fractal);
+    }
+
+    #[test]
+    fn check_newton_arguments() {
+        let args = Cli::parse_from([
+            "mandelbrot",
+            "--newton",
+            "z^3-2z + 2",
+            "--newton-shading",
+            "0.25",
+        ]);
+        let recorded = args.recorded_arguments();
+        assert!(recorded.contains(&"--newton=z^3 - 2z + 2".to_owned()));
+        let rerender =
+            Cli::parse_with_recorded(recorded, ["mandelbrot"].map(OsString::from)).unwrap();
+        assert_eq!(rerender.newton(), args.newton());
+        assert_eq!(rerender.newton().unwrap().shading, 0.25);
+
+        assert_eq!(Cli::parse_from(["mandelbrot"]).newton(), None);
+        assert!(
+            Cli::try_parse_from(["mandelbrot", "--newton", "z^3 - 1", "--buddhabrot"]).is_err()
+        );
+        assert!(
+            Cli::try_parse_from(["mandelbrot", "--newton", "z^3 - 1", "--contours", "5"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["mandelbrot", "--newton", "7"]).is_err());
+    }
+
+    #[test]
+    fn check_mesh_arguments() {
+        let args = Cli::parse_from([
+            "mandelbrot",
+            "--mesh",
+            "set.stl",
+            "--mesh-relief",
+            "0.3",
+            "--mesh-scale",
+            "linear",
+        ]);
+        let settings = args.heightfield_settings();
+        assert_eq!(settings.relief, 0.3);
+        assert_eq!(settings.scale, HeightScale::Linear);
+        // The mesh is saved next to the image, so it is not a setting of the render.
+        assert!(!args
+            .recorded_arguments()
+            .iter()
+            .any(|argument| argument.starts_with("--mesh")));
+
+        assert!(Cli::try_parse_from(["mandelbrot", "--mesh-relief", "0.3"]).is_err());
+        assert!(Cli::try_parse_from(["mandelbrot", "--mesh", "set.obj", "--buddhabrot"]).is_err());
+    }
+
+    #[test]
+    fn check_slope_shading_arguments() {
+        assert_eq!(Cli::parse_from(["mandelbrot"]).slope_shading(), Ok(None));
+
+        let args = Cli::parse_from([
+            "mandelbrot",
+            "--slope-shading",
+            "--light-azimuth",
+            "-45",
+            "--slope-strength=0.5",
+        ]);
+        let shading = SlopeShading::new(
+            -45.0,
+            SlopeShading::DEFAULT_ELEVATION,
+            SlopeShading::DEFAULT_RELIEF,
+            0.5,
+        );
+        assert_eq!(args.slope_shading(), shading.map(Some));
+        let rerender =
+            Cli::parse_with_recorded(args.recorded_arguments(), [OsString::from("mandelbrot")])
+                .unwrap();
+        assert_eq!(rerender.slope_shading(), shading.map(Some));
+
+        let args = Cli::parse_from(["mandelbrot", "--slope-shading", "--light-elevation=0"]);
+        assert!(args.slope_shading().is_err());
+        assert!(Cli::try_parse_from(["mandelbrot", "--slope-relief", "3"]).is_err());
+        assert!(Cli::try_parse_from(["mandelbrot", "--slope-shading", "--buddhabrot"]).is_err());
+    }
+
+    #[test]
+    fn check_watchdog_arguments() {
+        let args = Cli::parse_from(["mandelbrot", "--watchdog=4", "--heat-map", "heat.png"]);
+        assert_eq!(args.watchdog, Some(4.0));
+        // The watchdog does not change the image, so it is not recorded.
+        assert!(!args
+            .recorded_arguments()
+            .iter()
+            .any(|argument| argument.contains("watchdog") || argument.contains("heat")));
+        assert!(Cli::try_parse_from(["mandelbrot", "--heat-map", "heat.png"]).is_err());
+        assert!(
+            Cli::try_parse_from(["mandelbrot", "--watchdog=4", "--newton", "z^3 - 1"]).is_err()
+        );
+    }
+
+    #[test]
+    fn check_preset_arguments() {
+        let args = Cli::try_parse_from(["mandelbrot", "--preset", "print-a2-300dpi"]).unwrap();
+        assert_eq!(args.preset.as_deref(), Some("print-a2-300dpi"));
+        assert_eq!(args.presets_file, PathBuf::from(DEFAULT_PRESETS_FILE));
+
+        // Whichever of the preset and the resolution or SSAA factor is given last is used.
+        let args =
+            Cli::try_parse_from(["mandelbrot", "--preset", "print-a2-300dpi", "-s", "2"]).unwrap();
+        assert_eq!(args.preset, None);
+        assert_eq!(args.ssaa.get(), 2);
+        let args =
+            Cli::try_parse_from(["mandelbrot", "-p", "300x200", "--preset", "print-a2-300dpi"])
+                .unwrap();
+        assert!(args.preset.is_some());
+
+        assert!(Cli::try_parse_from([
+            "mandelbrot",
+            "--preset",
+            "print-a2-300dpi",
+            "--tile-size",
+            "100x100"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn check_palette_file() {
+        let args = Cli::parse_from(["mandelbrot", "--palette-file", "gradients/seahorse.ugr"]);
+        let recorded = args.recorded_arguments();
+        assert!(recorded.contains(&"--palette-file=gradients/seahorse.ugr".to_owned()));
+        let rerender =
+            Cli::parse_with_recorded(recorded, ["mandelbrot"].map(OsString::from)).unwrap();
+        assert_eq!(rerender.palette_file, args.palette_file);
+
+        assert!(Cli::try_parse_from([
+            "mandelbrot",
+            "--palette-file",
+            "seahorse.ugr",
+            "--palette-curves",
+            "r=s, g=s, b=s",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn check_palette() {
+        let args = Cli::parse_from(["mandelbrot", "--palette", "ultra_fractal_default"]);
+        assert_eq!(args.palette, Some(BuiltinPalette::UltraFractalDefault));
+        let recorded = args.recorded_arguments();
+        assert!(recorded.contains(&"--palette=ultra-fractal-default".to_owned()));
+        let rerender =
+            Cli::parse_with_recorded(recorded, ["mandelbrot"].map(OsString::from)).unwrap();
+        assert_eq!(rerender.palette, args.palette);
+
+        assert!(Cli::try_parse_from(["mandelbrot", "--palette", "magma"]).is_err());
+        assert!(Cli::try_parse_from(["mandelbrot", "--palette", "fire", "--grayscale"]).is_err());
+        assert!(Cli::try_parse_from([
+            "mandelbrot",
+            "--palette",
+            "fire",
+            "--palette-file",
+            "seahorse.ugr",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn check_auto_iterations() {
+        let mut args = Cli::parse_from(["mandelbrot", "--auto-iterations", "-z", "20"]);
+        args.resolve_auto_iterations();
+        let iterations = args.max_iterations;
+        assert!(iterations.get() > 1000, "{iterations}");
+        let recorded = args.recorded_arguments();
+        assert!(recorded.contains(&format!("--max-iterations={iterations}")));
+        assert!(!recorded
+            .iter()
+            .any(|argument| argument == "--auto-iterations"));
+
+        // The last of the two options wins, so a seeded render can choose again.
+        let mut rerender = Cli::parse_with_recorded(
+            recorded,
+            ["mandelbrot", "-p", "300x200", "--auto-iterations"].map(OsString::from),
+        )
+        .unwrap();
+        rerender.resolve_auto_iterations();
+        assert!(rerender.max_iterations < iterations);
+        let mut args = Cli::parse_from(["mandelbrot", "--auto-iterations", "-m", "42"]);
+        args.resolve_auto_iterations();
+        assert_eq!(args.max_iterations.get(), 42);
+    }
+
+    #[test]
+    fn check_nebulabrot_arguments() {
+        let args = Cli::parse_from(["mandelbrot", "--nebulabrot", "2000,200,20", "-p", "30x20"]);
+        let nebulabrot = args.nebulabrot().unwrap();
+        assert_eq!(
+            nebulabrot.channel_max_iterations.map(NonZeroU32::get),
+            [2000, 200, 20]
+        );
+        assert_eq!(
+            nebulabrot.samples.get(),
+            30 * 20 * DEFAULT_ORBIT_SAMPLES_PER_PIXEL
+        );
+        let rerender =
+            Cli::parse_with_recorded(args.recorded_arguments(), [OsString::from("mandelbrot")])
+                .unwrap();
+        assert_eq!(rerender.nebulabrot(), Some(nebulabrot));
+
+        let args = Cli::parse_from([
+            "mandelbrot",
+            "--buddhabrot",
+            "-m",
+            "500",
+            "--orbit-samples",
+            "9",
+        ]);
+        assert_eq!(
+            args.nebulabrot(),
+            Some(Nebulabrot::buddhabrot(
+                NonZeroU32::new(500).unwrap(),
+                NonZeroU64::new(9).unwrap()
+            ))
+        );
+
+        assert!(
+            Cli::try_parse_from(["mandelbrot", "--buddhabrot", "--nebulabrot", "1,2,3"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["mandelbrot", "--orbit-samples", "9"]).is_err());
+        assert!(Cli::try_parse_from(["mandelbrot", "--orbit-sequence", "sobol"]).is_err());
+
+        let args = Cli::parse_from([
+            "mandelbrot",
+            "--buddhabrot",
+            "--orbit-sequence=Sobol",
+            "--seed=7",
+        ]);
+        let buddhabrot = args.nebulabrot().unwrap();
+        assert_eq!(buddhabrot.sequence, LowDiscrepancySequence::Sobol);
+        let rerender =
+            Cli::parse_with_recorded(args.recorded_arguments(), [OsString::from("mandelbrot")])
+                .unwrap();
+        assert_eq!(rerender.nebulabrot(), Some(buddhabrot));
+        assert_eq!(rerender.seed, 7);
+        assert!(Cli::try_parse_from(["mandelbrot", "--nebulabrot", "1,2"]).is_err());
+        assert!(Cli::try_parse_from(["mandelbrot", "--nebulabrot", "1,2,0"]).is_err());
+    }
+
+    #[test]
+    fn check_max_cpu() {
+        assert_eq!(
+            Cli::parse_from(["mandelbrot"]).render_threads(),
+            (0, CpuLimit::NONE)
+        );
+        let args = Cli::parse_from(["mandelbrot", "--max-cpu=25%", "-j", "4"]);
+        assert_eq!(args.render_threads(), (1, CpuLimit::NONE));
+        // The limit does not change the image, so it is not recorded.
+        assert!(!args
+            .recorded_arguments()
+            .iter()
+            .any(|argument| argument.contains("cpu")));
+        assert!(Cli::try_parse_from(["mandelbrot", "--max-cpu", "0"]).is_err());
+    }
+
+    #[test]
+    fn check_rendering_profile() {
+        let args = Cli::parse_from(["mandelbrot"]);
+        assert_eq!(args.rendering_profile(), RenderingProfile::LATEST);
+        let recorded = args.recorded_arguments();
+        assert!(recorded.contains(&format!("--profile={}", RenderingProfile::LATEST)));
+        let rerender = Cli::parse_with_recorded(recorded, [OsString::from("mandelbrot")]).unwrap();
+        assert_eq!(rerender.profile, Some(RenderingProfile::LATEST));
+
+        let args = Cli::parse_from(["mandelbrot", "--profile", "V1"]);
+        assert_eq!(args.rendering_profile(), RenderingProfile::V1);
+        assert!(Cli::try_parse_from(["mandelbrot", "--profile", "v0"]).is_err());
+    }
+
+    #[test]
+    fn check_escape_speed_range() {
+        let args = Cli::parse_from(["mandelbrot"]);
+        assert_eq!(args.escape_speed_range, EscapeSpeedRange::FULL);
+        assert!(!args
+            .recorded_arguments()
+            .iter()
+            .any(|argument| argument.starts_with("--escape-speed-range=")));
+
+        let args = Cli::parse_from(["mandelbrot", "--escape-speed-range", "0.2,0.9"]);
+        let recorded = args.recorded_arguments();
+        assert!(recorded.contains(&"--escape-speed-range=0.2,0.9".to_owned()));
+        let rerender = Cli::parse_with_recorded(recorded, [OsString::from("mandelbrot")]).unwrap();
+        assert_eq!(rerender.escape_speed_range, EscapeSpeedRange::new(0.2, 0.9));
+        assert!(Cli::try_parse_from(["mandelbrot", "--escape-speed-range", "0.9,0.2"]).is_err());
+    }
+
+    #[test]
+    fn check_smoothing() {
+        let args = Cli::parse_from(["mandelbrot"]);
+        assert_eq!(args.smoothing, Smoothing::Classic);
+        assert!(!args
+            .recorded_arguments()
+            .iter()
+            .any(|argument| argument.starts_with("--smoothing=")));
+
+        let args = Cli::parse_from(["mandelbrot", "--smoothing", "none"]);
+        assert_eq!(args.smoothing, Smoothing::Banded);
+        let recorded = args.recorded_arguments();
+        assert!(recorded.contains(&"--smoothing=banded".to_owned()));
+        let rerender = Cli::parse_with_recorded(recorded, [OsString::from("mandelbrot")]).unwrap();
+        assert_eq!(rerender.smoothing, Smoothing::Banded);
+        assert!(Cli::try_parse_from(["mandelbrot", "--smoothing", "smooth"]).is_err());
+    }
+
+    #[test]
+    fn check_channels() {
+        let args = Cli::parse_from(["mandelbrot", "--channels", "r,escape,interior"]);
+        assert_eq!(
+            args.channels,
+            [
+                ImageChannel::Red,
+                ImageChannel::EscapeSpeed,
+                ImageChannel::Interior
+            ]
+        );
+        assert_eq!(args.channel_suffix, "_{channel}");
+        assert!(Cli::try_parse_from(["mandelbrot", "--channel-suffix", "_{channel}"]).is_err());
+        assert!(
+            Cli::try_parse_from(["mandelbrot", "--channels", "red", "--newton", "z^3 - 1"])
+                .is_err()
+        );
+        assert!(Cli::try_parse_from(["mandelbrot", "--channels", "angle"]).is_err());
+    }
 }
@@ -0,0 +1,91 @@
+use core::fmt;
+use core::num::ParseIntError;
+use core::str::FromStr;
+
+use image::Rgb;
+
+/// An sRGB color that can be parsed from a hexadecimal string on the form "RRGGBB".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexColor(Rgb<u8>);
+
+impl HexColor {
+    pub const BLACK: Self = Self(Rgb([0, 0, 0]));
+    pub const WHITE: Self = Self(Rgb([255, 255, 255]));
+
+    pub const fn rgb(&self) -> Rgb<u8> {
+        self.0
+    }
+}
+
+impl fmt::Display for HexColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b] = self.0 .0;
+        write!(f, "{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseHexColorError {
+    InvalidFormat,
+    InvalidValue(ParseIntError),
+}
+
+impl fmt::Display for ParseHexColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormat => write!(f, "the color must be given in the format RRGGBB"),
+            Self::InvalidValue(e) => write!(f, "the color could not be parsed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseHexColorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidValue(e) => Some(e),
+            Self::InvalidFormat => None,
+        }
+    }
+}
+
+impl FromStr for HexColor {
+    type Err = ParseHexColorError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_prefix('#').unwrap_or(s);
+
+        if s.len() != 6 || !s.is_ascii() {
+            return Err(Self::Err::InvalidFormat);
+        }
+
+        let mut channels = [0; 3];
+        for (i, channel) in channels.iter_mut().enumerate() {
+            *channel =
+                u8::from_str_radix(&s[2 * i..2 * i + 2], 16).map_err(Self::Err::InvalidValue)?;
+        }
+
+        Ok(Self(Rgb(channels)))
+    }
+}
+
+#[cfg(test)]
+mod test_hex_color {
+    use super::*;
+
+    #[test]
+    fn check_parsing() {
+        assert_eq!(
+            "ff8000".parse::<HexColor>().unwrap().rgb(),
+            Rgb([255, 128, 0])
+        );
+        assert_eq!(
+            "#0000FF".parse::<HexColor>().unwrap().rgb(),
+            Rgb([0, 0, 255])
+        );
+        assert_eq!(
+            "fff".parse::<HexColor>(),
+            Err(ParseHexColorError::InvalidFormat)
+        );
+        assert!("gg0000".parse::<HexColor>().is_err());
+        assert_eq!(HexColor::BLACK.to_string(), "000000");
+    }
+}
@@ -0,0 +1,388 @@
+//! A socket that only the user who runs the program can connect to, which batch mode listens on.
+//!
+//! On Unix this is a Unix domain socket whose file can only be read and written by its owner.
+//! On Windows it is a named pipe that only its owner has access to and that rejects remote clients.
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// A connection from a client to a [`LocalListener`].
+pub trait Connection: Read + Write + Sized {
+    /// Returns another handle to the same connection, so that it can be read from and written to at once.
+    fn try_clone(&self) -> io::Result<Self>;
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::fs::{self, DirBuilder, Permissions};
+    use std::io;
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::process;
+
+    use super::Connection;
+
+    /// How long a client may take to send a line before it is disconnected,
+    /// so that a client that stops responding does not keep other clients from connecting.
+    const CLIENT_TIMEOUT: core::time::Duration = core::time::Duration::from_secs(10);
+
+    impl Connection for UnixStream {
+        fn try_clone(&self) -> io::Result<Self> {
+            UnixStream::try_clone(self)
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct LocalListener {
+        listener: UnixListener,
+        path: PathBuf,
+    }
+
+    impl LocalListener {
+        pub fn bind(path: &Path) -> io::Result<Self> {
+            match fs::symlink_metadata(path) {
+                Ok(metadata) if !metadata.file_type().is_socket() => {
+                    // Anything else at the path is left alone, since it may be a file the user cares about.
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{} already exists and is not a socket", path.display()),
+                    ));
+                }
+                Ok(_) => {
+                    if UnixStream::connect(path).is_ok() {
+                        return Err(io::Error::new(
+                            io::ErrorKind::AddrInUse,
+                            format!("another process is already listening on {}", path.display()),
+                        ));
+                    }
+                    // The socket was left behind by a process that did not shut down cleanly.
+                    fs::remove_file(path)?;
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+
+            let parent = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            // The default socket is in a directory of its own when there is no runtime directory,
+            // which only the user may enter so that no other user can put a socket at its path.
+            match DirBuilder::new().mode(0o700).create(parent) {
+                Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+                _ => {}
+            }
+            // Whoever owns the directory, or can write to it without the sticky bit, can replace the socket.
+            // Shared directories like the temporary one belong to root.
+            let directory = fs::metadata(parent)?;
+            if directory.uid() != 0 {
+                mandellib::check_render_server_owner(parent)?;
+            }
+            if directory.mode() & 0o022 != 0 && directory.mode() & 0o1000 == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!(
+                        "other users can replace the socket in {}, since they can write to it",
+                        parent.display()
+                    ),
+                ));
+            }
+
+            // The socket is created in a directory that only the user can enter and given its permissions there,
+            // before it is moved to its path, so that no other user can connect to it in the meantime.
+            let private_directory = parent.join(format!(".mandelbrot-batch-{}", process::id()));
+            DirBuilder::new().mode(0o700).create(&private_directory)?;
+            let staged_path = private_directory.join("socket");
+            let bound = UnixListener::bind(&staged_path).and_then(|listener| {
+                fs::set_permissions(&staged_path, Permissions::from_mode(0o600))?;
+                fs::rename(&staged_path, path)?;
+                Ok(listener)
+            });
+            _ = fs::remove_file(&staged_path);
+            fs::remove_dir(&private_directory)?;
+
+            Ok(Self {
+                listener: bound?,
+                path: path.to_owned(),
+            })
+        }
+
+        pub fn accept(&self) -> io::Result<UnixStream> {
+            let (stream, _) = self.listener.accept()?;
+            stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+            Ok(stream)
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+    }
+
+    impl Drop for LocalListener {
+        fn drop(&mut self) {
+            _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::ffi::OsStr;
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle};
+    use std::path::{Path, PathBuf};
+    use std::ptr;
+    use std::sync::Mutex;
+
+    use windows_sys::Win32::Foundation::{
+        GetLastError, LocalFree, ERROR_ACCESS_DENIED, ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE,
+    };
+    use windows_sys::Win32::Security::Authorization::{
+        ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+    };
+    use windows_sys::Win32::Security::SECURITY_ATTRIBUTES;
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX,
+    };
+    use windows_sys::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+        PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    use super::Connection;
+
+    /// Gives full access to the owner of the pipe and no access to anyone else.
+    const OWNER_ONLY: &str = "D:P(A;;GA;;;OW)";
+
+    const BUFFER_SIZE: u32 = 4096;
+
+    impl Connection for File {
+        fn try_clone(&self) -> io::Result<Self> {
+            File::try_clone(self)
+        }
+    }
+
+    fn wide(text: &OsStr) -> Vec<u16> {
+        text.encode_wide().chain(core::iter::once(0)).collect()
+    }
+
+    /// Creates an instance of the named pipe that only its owner can open.
+    /// The first instance fails to be created if another process already has a pipe with the name.
+    fn create_instance(name: &[u16], first: bool) -> io::Result<OwnedHandle> {
+        let sddl = wide(OsStr::new(OWNER_ONLY));
+        let mut security_descriptor = ptr::null_mut();
+        // SAFETY: the string is null terminated and the descriptor is written to a valid pointer.
+        if unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                sddl.as_ptr(),
+                SDDL_REVISION_1,
+                &mut security_descriptor,
+                ptr::null_mut(),
+            )
+        } == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        let attributes = SECURITY_ATTRIBUTES {
+            nLength: u32::try_from(core::mem::size_of::<SECURITY_ATTRIBUTES>())
+                .expect("the attributes are a few bytes large"),
+            lpSecurityDescriptor: security_descriptor,
+            bInheritHandle: 0,
+        };
+        let open_mode = if first {
+            PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE
+        } else {
+            PIPE_ACCESS_DUPLEX
+        };
+        // SAFETY: the name is null terminated and the attributes outlive the call.
+        let pipe = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                open_mode,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                BUFFER_SIZE,
+                BUFFER_SIZE,
+                0,
+                &attributes,
+            )
+        };
+        let error = io::Error::last_os_error();
+        // SAFETY: the descriptor was allocated by `ConvertStringSecurityDescriptorToSecurityDescriptorW`
+        // and the pipe keeps its own copy of it.
+        unsafe { LocalFree(security_descriptor) };
+        if pipe == INVALID_HANDLE_VALUE {
+            return Err(
+                if first && error.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32) {
+                    io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        "another process is already listening on the pipe",
+                    )
+                } else {
+                    error
+                },
+            );
+        }
+        // SAFETY: the handle was just created and is owned by nothing else.
+        Ok(unsafe { OwnedHandle::from_raw_handle(pipe) })
+    }
+
+    #[derive(Debug)]
+    pub struct LocalListener {
+        name: Vec<u16>,
+        path: PathBuf,
+        /// The instance of the pipe that the next client connects to.
+        /// There is always one, so that clients can connect while earlier ones are handled.
+        waiting: Mutex<OwnedHandle>,
+    }
+
+    impl LocalListener {
+        pub fn bind(path: &Path) -> io::Result<Self> {
+            let name = wide(path.as_os_str());
+            let waiting = Mutex::new(create_instance(&name, true)?);
+            Ok(Self {
+                name,
+                path: path.to_owned(),
+                waiting,
+            })
+        }
+
+        pub fn accept(&self) -> io::Result<File> {
+            let mut waiting = self
+                .waiting
+                .lock()
+                .map_err(|_| io::Error::other("the pipe is poisoned"))?;
+            // SAFETY: the handle is a valid pipe that was not opened for overlapped operations.
+            let connected = unsafe { ConnectNamedPipe(waiting.as_raw_handle(), ptr::null_mut()) };
+            // A client that connects between the creation of the pipe and the call is already connected.
+            // SAFETY: `GetLastError` only reads the error code of the calling thread.
+            if connected == 0 && unsafe { GetLastError() } != ERROR_PIPE_CONNECTED {
+                return Err(io::Error::last_os_error());
+            }
+            let next = create_instance(&self.name, false)?;
+            Ok(File::from(core::mem::replace(&mut *waiting, next)))
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+    }
+}
+
+/// Listens for connections on a socket that only the current user can connect to, see the [module documentation](self).
+#[derive(Debug)]
+pub struct LocalListener(imp::LocalListener);
+
+impl LocalListener {
+    /// Creates the socket at the given path. On Windows the path must be a pipe name like `\\.\pipe\name`.
+    ///
+    /// # Errors
+    /// Returns an error if the socket can not be created,
+    /// e.g. because another process is already listening on it.
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        imp::LocalListener::bind(path).map(Self)
+    }
+
+    /// Waits for the next client to connect.
+    ///
+    /// # Errors
+    /// Returns an error if the client could not be connected.
+    pub fn accept(&self) -> io::Result<impl Connection> {
+        self.0.accept()
+    }
+
+    pub fn path(&self) -> &Path {
+        self.0.path()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test_local_socket {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    #[test]
+    fn check_owner_only_socket() {
+        let directory =
+            std::env::temp_dir().join(format!("mandelbrot-socket-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("batch.sock");
+        let listener = LocalListener::bind(&path).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // Only the socket is left in the directory.
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.write_all(b"hello\n").unwrap();
+        let connection = listener.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(connection).read_line(&mut line).unwrap();
+        assert_eq!(line, "hello\n");
+
+        assert_eq!(
+            LocalListener::bind(&path).unwrap_err().kind(),
+            io::ErrorKind::AddrInUse
+        );
+
+        drop(listener);
+        assert!(!path.exists());
+        std::fs::remove_dir(&directory).unwrap();
+    }
+
+    #[test]
+    fn check_only_stale_sockets_are_replaced() {
+        let directory = std::env::temp_dir().join(format!(
+            "mandelbrot-stale-socket-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let notes = directory.join("notes.txt");
+        std::fs::write(&notes, "do not delete").unwrap();
+        assert_eq!(
+            LocalListener::bind(&notes).unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+        assert_eq!(std::fs::read_to_string(&notes).unwrap(), "do not delete");
+        std::fs::remove_file(&notes).unwrap();
+
+        // A socket that nothing listens on any more is replaced.
+        let path = directory.join("batch.sock");
+        drop(UnixListener::bind(&path).unwrap());
+        let listener = LocalListener::bind(&path).unwrap();
+        UnixStream::connect(&path).unwrap();
+        drop(listener);
+        std::fs::remove_dir(&directory).unwrap();
+    }
+
+    #[test]
+    fn check_socket_directory() {
+        let directory = std::env::temp_dir().join(format!(
+            "mandelbrot-socket-directory-test-{}",
+            std::process::id()
+        ));
+        // A missing directory is created for the socket, which only the user can enter.
+        let path = directory.join("socket");
+        let listener = LocalListener::bind(&path).unwrap();
+        let mode = std::fs::metadata(&directory).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        drop(listener);
+
+        // Other users could replace the socket in a directory that they can write to,
+        // unless it has the sticky bit like the temporary directory.
+        std::fs::set_permissions(&directory, std::fs::Permissions::from_mode(0o777)).unwrap();
+        assert_eq!(
+            LocalListener::bind(&path).unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+        std::fs::set_permissions(&directory, std::fs::Permissions::from_mode(0o1777)).unwrap();
+        drop(LocalListener::bind(&path).unwrap());
+        std::fs::remove_dir(&directory).unwrap();
+    }
+}
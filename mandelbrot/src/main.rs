@@ -1,84 +1,726 @@
 use std::{
+    env,
     error::Error,
+    fs,
     io::{self, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
-use core::str;
+use core::{num::NonZeroU32, str, time::Duration};
+use std::time::Instant;
 
 use clap::Parser;
-use color_space::SupportedColorType;
+use color_space::{GradientFormat, GradientPalette, Palette, SupportedColorType};
 use rayon::ThreadPoolBuilder;
 
-use crate::command_line_interface::Cli;
+use crate::{
+    animation::FrameCache,
+    command_line_interface::{BookmarksAction, Cli, Command, TuneSsaaArgs},
+    print_preset::PrintPreset,
+    tiling::TileGrid,
+    tune_ssaa::QualityTarget,
+};
 
-use mandellib::{render, Frame, RenderParameters};
+use mandellib::{
+    contour_lines, contours_to_svg, dark_frame_warning, draw_contours, draw_external_rays,
+    external_rays, render, render_rows, render_tile, render_warnings, try_render_masked,
+    try_render_nebulabrot, try_render_newton, try_render_timed, Animation, EscapeRadius,
+    EscapeSpeedField, Exterior, Formula, Frame, Mask, Mesh, MeshFormat, PerturbedMandelbrot,
+    RenderParameters, Smoothing, Watermark, Zoom, DEFAULT_SMOOTHING_OFFSET,
+};
 
+mod animation;
+mod batch;
+mod bookmarks;
+mod channel_iterations;
+mod channels;
 mod command_line_interface;
+mod hex_color;
+mod local_socket;
+mod metadata;
+mod notification;
+mod print_preset;
+mod quality;
+mod repl;
 mod resolution;
+mod tiling;
+mod tune_ssaa;
+mod watchdog;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Cli::parse();
+    let mut args = match args.seed_from {
+        Some(ref image_path) => {
+            Cli::parse_with_recorded(metadata::read_arguments(image_path)?, env::args_os())
+                .unwrap_or_else(|e| e.exit())
+        }
+        None => args,
+    };
+    let preset = match args.preset {
+        Some(ref name) => Some(
+            print_preset::find(name, &print_preset::load_presets(&args.presets_file)?)
+                .ok_or_else(|| {
+                    format!(
+                        "there is no print preset called \"{name}\" in {} or among the built-in ones, which are named like \"print-a2-300dpi\"",
+                        args.presets_file.display()
+                    )
+                })?,
+        ),
+        None => None,
+    };
+    // The preset is applied before the iterations are chosen, since they depend on the resolution.
+    if let Some(ref preset) = preset {
+        args.resolution = preset.resolution();
+        args.ssaa = preset.ssaa;
+    }
+    args.resolve_auto_iterations();
+
+    // The trace is written to the file when this guard is dropped at the end of `main`.
+    #[cfg(feature = "trace")]
+    let _trace_guard = args.trace.as_ref().map(|path| {
+        use tracing_subscriber::layer::SubscriberExt;
+        let (chrome_layer, guard) = tracing_chrome::ChromeLayerBuilder::new()
+            .file(path)
+            .include_args(true)
+            .build();
+        _ = tracing::subscriber::set_global_default(
+            tracing_subscriber::registry().with(chrome_layer),
+        );
+        guard
+    });
+
+    let out_path = PathBuf::from(&args.output_path);
+
+    if args.stitch {
+        if let Some(tile_size) = args.tile_size {
+            let grid = TileGrid::new(args.resolution, tile_size, args.tile_overlap);
+            tiling::stitch(&grid, &out_path)?.save(&out_path)?;
+            if args.verbose {
+                _ = writeln!(
+                    io::stdout(),
+                    "Saved stitched image as {}",
+                    out_path.display()
+                );
+            }
+            return Ok(());
+        }
+    }
+
+    let (render_parameters, draw_region) = render_settings(&args)?;
+
+    if let Some(ref palette_path) = args.export_palette {
+        return export_palette(&render_parameters.palette, palette_path);
+    }
 
+    if args.verbose {
+        _ = give_user_feedback(&args, &render_parameters);
+    }
+
+    ThreadPoolBuilder::new()
+        // Zero lets the parallelism library decide.
+        .num_threads(args.render_threads().0)
+        .thread_name(mandellib::worker_thread_name)
+        .build_global()?;
+
+    match args.command {
+        Some(Command::TuneSsaa(ref tune)) => {
+            return tune_ssaa(&render_parameters, draw_region, tune)
+        }
+        Some(Command::Repl) => {
+            return repl::run(args, io::stdin().lock(), io::stdout(), |args| {
+                let (render_parameters, draw_region) = render_settings(args)?;
+                render_and_save(args, &render_parameters, draw_region, preset.as_ref(), None)
+            })
+        }
+        Some(Command::Batch(ref batch)) => {
+            return batch::listen(&args, &batch.listen, |job| {
+                let (render_parameters, draw_region) = render_settings(job)?;
+                render_and_save(job, &render_parameters, draw_region, preset.as_ref(), None)
+            })
+        }
+        Some(Command::Animate(ref animate)) => {
+            let animation = fs::read_to_string(&animate.keyframes)?
+                .parse::<Animation>()
+                .map_err(|e| {
+                    format!(
+                        "could not read the keyframes in {}: {e}",
+                        animate.keyframes.display()
+                    )
+                })?
+                .with_palette_cycling(args.palette_offset, animate.palette_cycle_rate)?;
+            let mut frame_cache = FrameCache::new(&animation);
+            let started = Instant::now();
+            animation::render_animation(&args, &animation, |job| {
+                let (render_parameters, draw_region) = render_settings(job)?;
+                render_and_save(
+                    job,
+                    &render_parameters,
+                    draw_region,
+                    preset.as_ref(),
+                    frame_cache.as_mut(),
+                )
+            })?;
+            if args.notify {
+                notify_finished(
+                    &format!("Saved the frames of {}", out_path.display()),
+                    started.elapsed(),
+                );
+            }
+            return Ok(());
+        }
+        Some(Command::Bookmarks(ref bookmarks)) => {
+            let library = bookmarks::load_library(&bookmarks.file)?;
+            return match bookmarks.action {
+                BookmarksAction::List {
+                    ref tag,
+                    ref search,
+                } => Ok(bookmarks::list(
+                    &library,
+                    tag.as_deref(),
+                    search,
+                    io::stdout().lock(),
+                )?),
+                BookmarksAction::Render { ref tag } => {
+                    bookmarks::render_tagged(&args, &library, tag, |job| {
+                        let (render_parameters, draw_region) = render_settings(job)?;
+                        render_and_save(job, &render_parameters, draw_region, preset.as_ref(), None)
+                    })
+                }
+            };
+        }
+        None => (),
+    }
+
+    if let Some(tile_size) = args.tile_size {
+        let grid = TileGrid::new(args.resolution, tile_size, args.tile_overlap);
+        let tiles = match args.tile {
+            Some(index) => vec![grid.tile(index).ok_or_else(|| {
+                format!(
+                    "there is no tile with index {index}, the image is split into {} tiles",
+                    grid.tile_count()
+                )
+            })?],
+            None => grid.tiles().collect(),
+        };
+        print_warnings(&args, &render_parameters, draw_region);
+        let started = Instant::now();
+        for tile in tiles {
+            let _tile_span =
+                tracing::info_span!("tile", column = tile.column, row = tile.row).entered();
+            let img = render_tile(&render_parameters, draw_region, tile.padded)?;
+            let tile_path = tile.path(&out_path);
+            img.save(&tile_path)?;
+            if args.verbose {
+                _ = writeln!(io::stdout(), "\rSaved tile as {}", tile_path.display());
+            }
+        }
+        if args.notify {
+            notify_finished(
+                &format!("Saved the tiles of {}", out_path.display()),
+                started.elapsed(),
+            );
+        }
+        return Ok(());
+    }
+
+    render_and_save(
+        &args,
+        &render_parameters,
+        draw_region,
+        preset.as_ref(),
+        None,
+    )
+}
+
+/// Returns the parameters to render the image described by the arguments with,
+/// and the region of the complex plane that it shows.
+fn render_settings(args: &Cli) -> Result<(RenderParameters, Frame), Box<dyn Error>> {
     let x_resolution = args.resolution.x_resolution();
     let y_resolution = args.resolution.y_resolution();
 
-    let zoom = 2.0_f64.powf(args.zoom_level);
-
-    let imag_distance = 8.0 / (3.0 * zoom);
-    let real_distance =
-        f64::from(x_resolution.get()) / f64::from(y_resolution.get()) * imag_distance;
+    let aspect_ratio = f64::from(x_resolution.get()) / f64::from(y_resolution.get());
 
-    let draw_region = Frame::new(
-        args.real_center,
-        args.imag_center,
-        real_distance,
-        imag_distance,
-    );
+    // Perturbed renders are done in coordinates relative to the center point.
+    let draw_region = if args.perturbation {
+        Frame::from_zoom(0.0, 0.0, args.zoom_level, aspect_ratio)
+    } else {
+        Frame::from_zoom(
+            args.real_center.to_f64(),
+            args.imag_center.to_f64(),
+            args.zoom_level,
+            aspect_ratio,
+        )
+    };
 
-    let render_parameters = RenderParameters::try_new(
+    let mut render_parameters = RenderParameters::try_new(
         x_resolution,
         y_resolution,
         args.max_iterations,
         args.ssaa,
         if args.grayscale {
             SupportedColorType::L8
+        } else if args.interior_only {
+            SupportedColorType::Rgba8
         } else {
             SupportedColorType::Rgb8
         },
     )?;
+    render_parameters.formula = if args.perturbation {
+        // The precision must be enough for the most closely spaced samples.
+        let pixel_size = draw_region.imag_distance
+            / f64::from(y_resolution.get())
+            / f64::from(args.max_ssaa().get());
+        Formula::Custom(Arc::new(PerturbedMandelbrot::new(
+            &args.real_center,
+            &args.imag_center,
+            pixel_size,
+            args.max_iterations,
+        )))
+    } else {
+        match args.formula {
+            Some(ref formula) => Formula::Custom(Arc::new(formula.clone())),
+            None => args.fractal.clone(),
+        }
+    };
+    render_parameters.exponent = args.exponent;
+    render_parameters.interior_shading = args.interior;
+    render_parameters.bulb_checks = args.bulb_checks;
+    render_parameters.shortcuts = !args.disable_shortcuts;
+    render_parameters.mirroring = !args.disable_mirroring;
+    render_parameters.derivative_bailout = args.derivative_bailout;
+    render_parameters.cpu_limit = args.render_threads().1;
+    render_parameters.quality = args.quality;
+    render_parameters.sample_placement = args.sample_placement;
+    render_parameters.seed = args.seed;
+    render_parameters.adaptive_supersampling = args.adaptive_ssaa;
+    render_parameters.ssaa_cutoff = (!args.full_ssaa).then_some(args.ssaa_cutoff);
+    render_parameters.show_ssaa_region = args.show_ssaa_region;
+    render_parameters.reconstruction_filter = args.reconstruction_filter;
+    render_parameters.grayscale_mode = args.grayscale_mode;
+    // Like in the recorded arguments, values that equal the current defaults count as not given,
+    // and are replaced by those of the profile.
+    args.rendering_profile().apply(&mut render_parameters);
+    if args.escape_radius != EscapeRadius::DEFAULT {
+        render_parameters.escape_radius = args.escape_radius;
+    }
+    if args.smoothing != Smoothing::default() {
+        render_parameters.smoothing = args.smoothing;
+    }
+    if args.smoothing_offset != DEFAULT_SMOOTHING_OFFSET {
+        render_parameters.smoothing_offset = args.smoothing_offset;
+    }
+    render_parameters.palette_offset = args.palette_offset;
+    render_parameters.escape_speed_range = args.escape_speed_range;
+    if args.interior_only {
+        render_parameters.exterior = Exterior::Transparent {
+            interior_color: args.interior_color.map(|color| color.rgb().0),
+        };
+    }
+    if args.binary_decomposition {
+        render_parameters.exterior = Exterior::BinaryDecomposition;
+    }
+    if let Some(palette) = args.palette {
+        render_parameters.palette = Palette::Builtin(palette);
+    }
+    if let Some(ref curves) = args.palette_curves {
+        render_parameters.palette = Palette::Curves(Arc::new(curves.clone()));
+    }
+    if let Some(ref palette_path) = args.palette_file {
+        let format = gradient_format(palette_path)?;
+        let gradient =
+            GradientPalette::parse(&fs::read_to_string(palette_path)?, format).map_err(|e| {
+                format!(
+                    "could not read the gradient in {}: {e}",
+                    palette_path.display()
+                )
+            })?;
+        render_parameters.palette = Palette::Gradient(Arc::new(gradient));
+    }
 
-    if args.verbose {
-        _ = give_user_feedback(&args, &render_parameters);
+    Ok((render_parameters, draw_region))
+}
+
+/// Determines the format of a gradient file from its extension.
+fn gradient_format(path: &Path) -> Result<GradientFormat, Box<dyn Error>> {
+    path.extension()
+        .and_then(|extension| GradientFormat::from_extension(&extension.to_string_lossy()))
+        .ok_or_else(|| {
+            format!(
+                "can not tell the format of the gradient file {}, it should end in \".ugr\" or \".map\"",
+                path.display()
+            )
+            .into()
+        })
+}
+
+/// Writes the palette to a gradient file in the format given by its extension.
+fn export_palette(palette: &Palette, path: &Path) -> Result<(), Box<dyn Error>> {
+    let format = gradient_format(path)?;
+    let gradient = match palette {
+        // Gradients are written as they are rather than sampled, so that no stops are lost.
+        Palette::Gradient(gradient) => GradientPalette::clone(gradient),
+        _ => {
+            let stops = match format {
+                GradientFormat::UltraFractal => 400,
+                GradientFormat::Fractint => 256,
+            };
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            GradientPalette::sampled(palette, NonZeroU32::new(stops).unwrap(), name)
+        }
+    };
+    fs::write(path, gradient.write(format))?;
+    Ok(())
+}
+
+/// Renders the image described by the arguments and saves it at the output path,
+/// with the arguments recorded in it and encoded for the print preset, if any.
+/// Frames of animations are rendered with the escape data of the previous frame in the frame cache, if any.
+fn render_and_save(
+    args: &Cli,
+    render_parameters: &RenderParameters,
+    draw_region: Frame,
+    preset: Option<&PrintPreset>,
+    frame_cache: Option<&mut FrameCache>,
+) -> Result<(), Box<dyn Error>> {
+    let started = Instant::now();
+    let out_path = PathBuf::from(&args.output_path);
+
+    if out_path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"))
+    {
+        if args.newton.is_some() {
+            return Err("Newton fractals have no boundary to trace into an SVG image".into());
+        }
+        return save_boundary(args, render_parameters, draw_region, &out_path);
+    }
+    print_warnings(args, render_parameters, draw_region);
+    if args.stream {
+        return stream_and_save(
+            args,
+            render_parameters,
+            draw_region,
+            preset,
+            &out_path,
+            started,
+        );
+    }
+
+    let slope_shading = args.slope_shading()?;
+    if let Some(factor) = args.watchdog {
+        if !(1.0..).contains(&factor) {
+            return Err(
+                format!("the watchdog factor must be at least 1, but it was {factor}").into(),
+            );
+        }
+    }
+
+    let mut img = if let Some(nebulabrot) = args.nebulabrot() {
+        try_render_nebulabrot(render_parameters, draw_region, &nebulabrot, args.verbose)?
+    } else if let Some(newton) = args.newton() {
+        try_render_newton(render_parameters, draw_region, &newton, args.verbose)?
+    } else if let Some(ref mask_path) = args.mask {
+        let mask = Mask::new(
+            &image::open(mask_path)?,
+            render_parameters.x_resolution,
+            render_parameters.y_resolution,
+            args.mask_fill.rgb(),
+            args.invert_mask,
+        )?;
+        try_render_masked(render_parameters, draw_region, &mask, args.verbose)?
+    } else if !args.channels.is_empty() {
+        channels::render_and_save_channels(args, render_parameters, draw_region, &out_path)?
+    } else if let Some(factor) = args.watchdog {
+        let (img, timings) = try_render_timed(render_parameters, draw_region, args.verbose)?;
+        if args.verbose {
+            _ = writeln!(io::stdout());
+        }
+        if let Some(ref heat_map_path) = args.heat_map {
+            timings.heat_map().save(heat_map_path)?;
+        }
+        watchdog::write_report(&timings, factor, io::stdout().lock())?;
+        img
+    } else if let Some(frame_cache) = frame_cache {
+        frame_cache.render(args, render_parameters, draw_region)
+    } else {
+        render(render_parameters, draw_region, args.verbose)
+    };
+    print_dark_frame_warning(args, render_parameters, draw_region);
+
+    // The escape speeds are only computed once for the shading, all the lines that are traced
+    // over the image and the mesh.
+    let escape_field = (slope_shading.is_some()
+        || args.contours.is_some()
+        || !args.equipotentials.is_empty()
+        || args.mesh.is_some())
+    .then(|| {
+        if args.verbose {
+            _ = write!(io::stdout(), "\rComputing escape speeds");
+        }
+        EscapeSpeedField::new(render_parameters, draw_region)
+    });
+    // The image is shaded first so that the lines are drawn over it in their own colors.
+    if let (Some(shading), Some(field)) = (slope_shading, &escape_field) {
+        shading.shade(&mut img, field);
+    }
+    if let (Some(mesh_path), Some(field)) = (&args.mesh, &escape_field) {
+        let format = mesh_path
+            .extension()
+            .and_then(|extension| MeshFormat::from_extension(&extension.to_string_lossy()))
+            .ok_or_else(|| {
+                format!(
+                    "can not tell the format of the mesh file {}, it should end in \".obj\" or \".stl\"",
+                    mesh_path.display()
+                )
+            })?;
+        Mesh::heightfield(field, &args.heightfield_settings())
+            .write(io::BufWriter::new(fs::File::create(mesh_path)?), format)?;
+    }
+    if let (Some(spacing), Some(field)) = (args.contours, &escape_field) {
+        let contours = field.contour_lines(&field.contour_levels(spacing)?);
+        let color = args.contour_color.rgb().0;
+        match args.contours_svg {
+            Some(ref svg_path) => fs::write(
+                svg_path,
+                contours_to_svg(&contours, img.width(), img.height(), color, args.simplify),
+            )?,
+            None => draw_contours(&mut img, &contours, color),
+        }
+    }
+    if let Some(field) = escape_field.filter(|_| !args.equipotentials.is_empty()) {
+        let equipotentials = field.equipotentials(&args.equipotentials);
+        draw_contours(&mut img, &equipotentials, args.equipotential_color.rgb().0);
     }
 
-    if let Some(jobs) = args.jobs {
-        ThreadPoolBuilder::new()
-            .num_threads(jobs.into())
-            .build_global()?;
+    if !args.rays.is_empty() {
+        if args.verbose {
+            _ = write!(io::stdout(), "\rTracing external rays");
+        }
+        let rays = external_rays(render_parameters, draw_region, &args.rays)?;
+        draw_external_rays(&mut img, &rays, draw_region, args.ray_color.rgb().0);
     }
 
-    let img = render(render_parameters, draw_region, args.verbose);
+    if let Some(ref logo_path) = args.watermark {
+        Watermark::new(
+            image::open(logo_path)?,
+            args.watermark_position,
+            args.watermark_opacity,
+        )?
+        .stamp(&mut img);
+    }
 
     if args.verbose {
         _ = write!(io::stdout(), "\rEncoding and saving image");
     }
 
-    let out_path = PathBuf::from(args.output_path);
+    let recorded_arguments = args.recorded_arguments();
+    metadata::save_with_arguments(&img, &out_path, &recorded_arguments, preset)?;
+    report_saved(args, &out_path, &recorded_arguments, started);
+
+    Ok(())
+}
+
+/// The number of rows of the image that are rendered at a time with `--stream`.
+const STREAM_BAND_ROWS: NonZeroU32 = NonZeroU32::new(64).unwrap();
+
+/// Renders the image a band of rows at a time and writes every band to the PNG file at the output path
+/// as soon as it is done, so that the whole image is never held in memory.
+fn stream_and_save(
+    args: &Cli,
+    render_parameters: &RenderParameters,
+    draw_region: Frame,
+    preset: Option<&PrintPreset>,
+    out_path: &Path,
+    started: Instant,
+) -> Result<(), Box<dyn Error>> {
+    if !out_path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+    {
+        return Err(format!(
+            "streamed images can only be saved as PNG, but the output path is {}",
+            out_path.display()
+        )
+        .into());
+    }
+
+    let bands = render_rows(render_parameters, draw_region, STREAM_BAND_ROWS)?;
+    let band_count = bands.len();
+    let recorded_arguments = args.recorded_arguments();
+    metadata::stream_with_arguments(
+        bands.enumerate().map(|(index, band)| {
+            if args.verbose {
+                _ = write!(
+                    io::stdout(),
+                    "\rRendered band {} of {band_count}",
+                    index + 1
+                );
+                _ = io::stdout().flush();
+            }
+            band
+        }),
+        (
+            render_parameters.x_resolution.into(),
+            render_parameters.y_resolution.into(),
+        ),
+        render_parameters.color_type,
+        out_path,
+        &recorded_arguments,
+        preset,
+    )?;
+    report_saved(args, out_path, &recorded_arguments, started);
 
-    img.save(&out_path)?;
+    Ok(())
+}
 
+/// Tells the user where the image was saved and how to render it again if they asked for it,
+/// and notifies them that it is done if they asked for that.
+fn report_saved(args: &Cli, out_path: &Path, recorded_arguments: &[String], started: Instant) {
     if args.verbose {
         _ = writeln!(
             io::stdout(),
             "\rSaved image as {}                       ",
             out_path.display()
         );
+        _ = writeln!(
+            io::stdout(),
+            "It can be rendered again with: {}",
+            recorded_arguments.join(" ")
+        );
+    }
+    if args.notify {
+        notify_finished(&format!("Saved {}", out_path.display()), started.elapsed());
     }
+}
 
+/// Tells the user that what was done has finished after the elapsed time.
+/// The image is already saved, so failing to do so is only reported.
+fn notify_finished(what: &str, elapsed: Duration) {
+    if let Err(e) = notification::notify(&notification::finished_message(what, elapsed)) {
+        _ = writeln!(io::stderr(), "{e}");
+    }
+}
+
+/// Traces the boundary between the points that escape within `--boundary-iterations` iterations
+/// and those that do not, and saves it as an SVG image at the given path.
+fn save_boundary(
+    args: &Cli,
+    render_parameters: &RenderParameters,
+    draw_region: Frame,
+    out_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let max_iterations = render_parameters.max_iterations;
+    let level = args.boundary_iterations.unwrap_or(max_iterations);
+    if level > max_iterations {
+        return Err(format!(
+            "the boundary can not be traced at {level} iterations, since points are only iterated {max_iterations} times"
+        )
+        .into());
+    }
+
+    if args.verbose {
+        _ = write!(io::stdout(), "\rTracing the boundary");
+    }
+    let contours = contour_lines(render_parameters, draw_region, &[level.get() as f64]);
+    fs::write(
+        out_path,
+        contours_to_svg(
+            &contours,
+            render_parameters.x_resolution.into(),
+            render_parameters.y_resolution.into(),
+            [0, 0, 0],
+            args.simplify,
+        ),
+    )?;
+
+    if args.verbose {
+        _ = writeln!(
+            io::stdout(),
+            "\rSaved boundary as {}       ",
+            out_path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Finds and prints the cheapest SSAA factor that renders the image with the quality given by `tune`.
+fn tune_ssaa(
+    render_parameters: &RenderParameters,
+    draw_region: Frame,
+    tune: &TuneSsaaArgs,
+) -> Result<(), Box<dyn Error>> {
+    writeln!(
+        io::stdout(),
+        "Rendering a reference with {} samples per pixel",
+        u16::from(tune.reference_ssaa.get()).pow(2)
+    )?;
+    writeln!(io::stdout(), "SSAA  samples  time (s)  PSNR (dB)  SSIM")?;
+    let target = QualityTarget {
+        psnr: tune.target_psnr,
+        ssim: tune.target_ssim,
+    };
+    let cheapest = tune_ssaa::find_cheapest_ssaa(
+        render_parameters,
+        draw_region,
+        tune.reference_ssaa,
+        target,
+        |measurement| {
+            _ = writeln!(
+                io::stdout(),
+                "{:>4}  {:>7}  {:>8.2}  {:>9.2}  {:.4}",
+                measurement.ssaa,
+                u16::from(measurement.ssaa.get()).pow(2),
+                measurement.render_time.as_secs_f64(),
+                measurement.psnr,
+                measurement.ssim,
+            );
+        },
+    )?;
+
+    match cheapest {
+        Some(measurement) => writeln!(
+            io::stdout(),
+            "Recommended: --ssaa {}, the cheapest setting with a PSNR of at least {} dB and an SSIM of at least {}",
+            measurement.ssaa, target.psnr, target.ssim
+        )?,
+        None => writeln!(
+            io::stdout(),
+            "No SSAA factor below {} reaches the target quality, use it or lower the target",
+            tune.reference_ssaa
+        )?,
+    }
     Ok(())
 }
 
+/// Print the warnings about the settings of the image to stderr, unless the user asked not to.
+/// Newton fractals, Buddhabrots and Nebulabrots are not checked, since the warnings are about the Mandelbrot set.
+fn print_warnings(args: &Cli, render_parameters: &RenderParameters, draw_region: Frame) {
+    if args.quiet || args.newton.is_some() || args.nebulabrot().is_some() {
+        return;
+    }
+    for warning in render_warnings(render_parameters, draw_region) {
+        _ = writeln!(io::stderr(), "warning: {warning}");
+    }
+}
+
+/// Print a warning to stderr if most of the rendered image reached the maximum number of iterations,
+/// unless the user asked not to. Checked in the same cases as [`print_warnings`].
+fn print_dark_frame_warning(args: &Cli, render_parameters: &RenderParameters, draw_region: Frame) {
+    if args.quiet || args.newton.is_some() || args.nebulabrot().is_some() {
+        return;
+    }
+    if let Some(warning) =
+        dark_frame_warning(render_parameters, draw_region, args.dark_frame_threshold)
+    {
+        _ = writeln!(io::stderr(), "warning: {warning}");
+    }
+}
+
 /// Output some basic information about what the program will be rendering.
 fn give_user_feedback(args: &Cli, rparams: &RenderParameters) -> Result<(), Box<dyn Error>> {
     let mut header = Vec::with_capacity(80);
@@ -98,11 +740,11 @@ fn give_user_feedback(args: &Cli, rparams: &RenderParameters) -> Result<(), Box<
         u32::from(rparams.x_resolution),
         rparams.y_resolution,
     )?;
-    if args.zoom_level > 0.0 {
+    if args.zoom_level > Zoom::NONE {
         write!(
             &mut header,
             " zoomed by a factor of {}",
-            2.0_f64.powf(args.zoom_level)
+            args.zoom_level.factor()
         )?;
     }
     write!(&mut header, " ----")?;
@@ -0,0 +1,385 @@
+//! Records the settings that an image was rendered with in the image file itself,
+//! so that the exact same image can be rendered again with `--seed-from`.
+//!
+//! The settings are stored as the command line arguments that reproduce them,
+//! one per line, in an iTXt chunk of the PNG file. Other file formats are saved without them.
+//!
+//! Images rendered with a print preset are also saved with its bit depth, DPI and color profile.
+
+use std::borrow::Cow;
+
+use core::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use color_space::SupportedColorType;
+use image::{DynamicImage, ImageError};
+use mandellib::{RenderError, RowBand};
+
+use crate::print_preset::PrintPreset;
+
+/// The keyword of the PNG text chunk that the arguments are stored in.
+const ARGUMENTS_KEYWORD: &str = "Mandelbrot arguments";
+
+/// Saves the image to the given path. If it is saved as a PNG the given arguments are stored in it.
+/// If a print preset is given the image is saved with its bit depth and color profile,
+/// and PNG images also record its DPI.
+pub fn save_with_arguments(
+    image: &DynamicImage,
+    path: &Path,
+    arguments: &[String],
+    preset: Option<&PrintPreset>,
+) -> Result<(), MetadataError> {
+    let image = match preset {
+        Some(preset) if preset.sixteen_bit => Cow::Owned(sixteen_bit(image)),
+        _ => Cow::Borrowed(image),
+    };
+    let icc_profile = read_icc_profile(preset)?;
+
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => (),
+        #[cfg(feature = "tiff")]
+        Some("tif" | "tiff") => return save_tiff(&image, path, icc_profile),
+        _ if icc_profile.is_some() => return Err(MetadataError::NoIccProfileSupport),
+        _ => return image.save(path).map_err(MetadataError::Image),
+    }
+
+    let (color_type, bit_depth) = match *image {
+        DynamicImage::ImageLuma8(_) => (png::ColorType::Grayscale, png::BitDepth::Eight),
+        DynamicImage::ImageRgb8(_) => (png::ColorType::Rgb, png::BitDepth::Eight),
+        DynamicImage::ImageRgba8(_) => (png::ColorType::Rgba, png::BitDepth::Eight),
+        DynamicImage::ImageLuma16(_) => (png::ColorType::Grayscale, png::BitDepth::Sixteen),
+        DynamicImage::ImageRgb16(_) => (png::ColorType::Rgb, png::BitDepth::Sixteen),
+        DynamicImage::ImageRgba16(_) => (png::ColorType::Rgba, png::BitDepth::Sixteen),
+        // The renderer and `sixteen_bit` only produce the above color types.
+        _ => return image.save(path).map_err(MetadataError::Image),
+    };
+
+    let mut writer = png_writer(
+        path,
+        (image.width(), image.height()),
+        (color_type, bit_depth),
+        icc_profile,
+        arguments,
+        preset,
+    )?;
+    writer
+        .write_image_data(&png_data(&image, bit_depth))
+        .map_err(MetadataError::Encoding)?;
+    writer.finish().map_err(MetadataError::Encoding)
+}
+
+/// Saves the image made up of the bands of rows, which come from the top down, as a PNG at the given path
+/// with the given arguments stored in it. Each band is written as soon as it has been rendered,
+/// so that the whole image is never held in memory.
+/// If a print preset is given the image is saved with its bit depth, DPI and color profile.
+pub fn stream_with_arguments(
+    bands: impl Iterator<Item = Result<RowBand, RenderError>>,
+    resolution: (u32, u32),
+    color_type: SupportedColorType,
+    path: &Path,
+    arguments: &[String],
+    preset: Option<&PrintPreset>,
+) -> Result<(), MetadataError> {
+    let color_type = match color_type {
+        SupportedColorType::L8 => png::ColorType::Grayscale,
+        SupportedColorType::Rgb8 => png::ColorType::Rgb,
+        SupportedColorType::Rgba8 => png::ColorType::Rgba,
+    };
+    let sixteen_bit_output = preset.is_some_and(|preset| preset.sixteen_bit);
+    let bit_depth = if sixteen_bit_output {
+        png::BitDepth::Sixteen
+    } else {
+        png::BitDepth::Eight
+    };
+    let mut writer = png_writer(
+        path,
+        resolution,
+        (color_type, bit_depth),
+        read_icc_profile(preset)?,
+        arguments,
+        preset,
+    )?;
+    let mut stream = writer.stream_writer().map_err(MetadataError::Encoding)?;
+    for band in bands {
+        let band = band.map_err(MetadataError::Render)?.image;
+        let band = if sixteen_bit_output {
+            sixteen_bit(&band)
+        } else {
+            band
+        };
+        stream
+            .write_all(&png_data(&band, bit_depth))
+            .map_err(MetadataError::Io)?;
+    }
+    // Fails if the bands did not add up to the whole image.
+    stream.finish().map_err(MetadataError::Encoding)?;
+    writer.finish().map_err(MetadataError::Encoding)
+}
+
+/// Reads the ICC profile of the print preset, if it has one.
+fn read_icc_profile(preset: Option<&PrintPreset>) -> Result<Option<Vec<u8>>, MetadataError> {
+    preset
+        .and_then(|preset| preset.icc_profile.as_ref())
+        .map(|profile_path| {
+            fs::read(profile_path).map_err(|e| MetadataError::IccProfile(profile_path.clone(), e))
+        })
+        .transpose()
+}
+
+/// Creates the PNG file at the path and writes everything but the image data to it:
+/// the header, the color profile, the DPI of the print preset and the arguments.
+fn png_writer(
+    path: &Path,
+    (width, height): (u32, u32),
+    (color_type, bit_depth): (png::ColorType, png::BitDepth),
+    icc_profile: Option<Vec<u8>>,
+    arguments: &[String],
+    preset: Option<&PrintPreset>,
+) -> Result<png::Writer<BufWriter<File>>, MetadataError> {
+    let file = BufWriter::new(File::create(path).map_err(MetadataError::Io)?);
+    let mut info = png::Info::with_size(width, height);
+    let has_icc_profile = icc_profile.is_some();
+    info.icc_profile = icc_profile.map(Cow::Owned);
+    let mut encoder = png::Encoder::with_info(file, info).map_err(MetadataError::Encoding)?;
+    encoder.set_color(color_type);
+    encoder.set_depth(bit_depth);
+    if let Some(preset) = preset {
+        let dots_per_meter = preset.dots_per_meter();
+        encoder.set_pixel_dims(Some(png::PixelDimensions {
+            xppu: dots_per_meter,
+            yppu: dots_per_meter,
+            unit: png::Unit::Meter,
+        }));
+        if !has_icc_profile {
+            encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        }
+    }
+    encoder
+        .add_text_chunk(
+            "Software".to_owned(),
+            format!("mandelbrot {}", env!("CARGO_PKG_VERSION")),
+        )
+        .map_err(MetadataError::Encoding)?;
+    encoder
+        .add_itxt_chunk(ARGUMENTS_KEYWORD.to_owned(), arguments.join("\n"))
+        .map_err(MetadataError::Encoding)?;
+    encoder.write_header().map_err(MetadataError::Encoding)
+}
+
+/// Returns the pixels of the image in the byte order of PNG files.
+fn png_data(image: &DynamicImage, bit_depth: png::BitDepth) -> Cow<'_, [u8]> {
+    match bit_depth {
+        // PNG stores 16-bit samples in big-endian order.
+        png::BitDepth::Sixteen => Cow::Owned(
+            image
+                .as_bytes()
+                .chunks_exact(2)
+                .flat_map(|sample| u16::from_ne_bytes([sample[0], sample[1]]).to_be_bytes())
+                .collect(),
+        ),
+        _ => Cow::Borrowed(image.as_bytes()),
+    }
+}
+
+/// Returns the image with 16 bits per channel and the same channels.
+fn sixteen_bit(image: &DynamicImage) -> DynamicImage {
+    match image {
+        DynamicImage::ImageLuma8(_) => DynamicImage::ImageLuma16(image.to_luma16()),
+        DynamicImage::ImageRgba8(_) => DynamicImage::ImageRgba16(image.to_rgba16()),
+        _ => DynamicImage::ImageRgb16(image.to_rgb16()),
+    }
+}
+
+/// Saves the image as a TIFF with the given ICC profile embedded in it.
+#[cfg(feature = "tiff")]
+fn save_tiff(
+    image: &DynamicImage,
+    path: &Path,
+    icc_profile: Option<Vec<u8>>,
+) -> Result<(), MetadataError> {
+    use image::{codecs::tiff::TiffEncoder, ImageEncoder};
+
+    let file = BufWriter::new(File::create(path).map_err(MetadataError::Io)?);
+    let mut encoder = TiffEncoder::new(file);
+    if let Some(profile) = icc_profile {
+        encoder
+            .set_icc_profile(profile)
+            .map_err(|e| MetadataError::Image(ImageError::Unsupported(e)))?;
+    }
+    encoder
+        .write_image(
+            image.as_bytes(),
+            image.width(),
+            image.height(),
+            image.color().into(),
+        )
+        .map_err(MetadataError::Image)
+}
+
+/// Reads the arguments stored in a PNG file by [`save_with_arguments`].
+pub fn read_arguments(path: &Path) -> Result<Vec<String>, MetadataError> {
+    let file = BufReader::new(File::open(path).map_err(MetadataError::Io)?);
+    let reader = png::Decoder::new(file)
+        .read_info()
+        .map_err(MetadataError::Decoding)?;
+    let chunk = reader
+        .info()
+        .utf8_text
+        .iter()
+        .find(|chunk| chunk.keyword == ARGUMENTS_KEYWORD)
+        .ok_or_else(|| MetadataError::NoArguments(path.to_owned()))?;
+    let text = chunk.get_text().map_err(MetadataError::Decoding)?;
+    Ok(text.lines().map(str::to_owned).collect())
+}
+
+#[derive(Debug)]
+pub enum MetadataError {
+    Io(io::Error),
+    Image(ImageError),
+    Encoding(png::EncodingError),
+    Decoding(png::DecodingError),
+    /// A band of a streamed image could not be rendered.
+    Render(RenderError),
+    NoArguments(PathBuf),
+    /// The ICC profile at the path could not be read.
+    IccProfile(PathBuf, io::Error),
+    /// An ICC profile was given for a file format that it can not be embedded in.
+    NoIccProfileSupport,
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Image(e) => write!(f, "{e}"),
+            Self::Encoding(e) => write!(f, "could not encode the image: {e}"),
+            Self::Decoding(e) => write!(f, "could not read the image: {e}"),
+            Self::Render(e) => write!(f, "could not render the image: {e}"),
+            Self::NoArguments(path) => write!(
+                f,
+                "{} does not contain the settings it was rendered with",
+                path.display()
+            ),
+            Self::IccProfile(path, e) => {
+                write!(f, "could not read the ICC profile {}: {e}", path.display())
+            }
+            Self::NoIccProfileSupport => write!(
+                f,
+                "ICC profiles can only be embedded in PNG and TIFF images"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MetadataError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Image(e) => Some(e),
+            Self::Encoding(e) => Some(e),
+            Self::Decoding(e) => Some(e),
+            Self::Render(e) => Some(e),
+            Self::IccProfile(_, e) => Some(e),
+            Self::NoArguments(_) | Self::NoIccProfileSupport => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_metadata {
+    use super::*;
+
+    #[test]
+    fn check_arguments_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "mandelbrot_metadata_test_{}.png",
+            std::process::id()
+        ));
+        let image = DynamicImage::new_rgb8(3, 2);
+        let arguments = ["--formula".to_owned(), "z^2 + sin(c) * ℯ".to_owned()];
+        save_with_arguments(&image, &path, &arguments, None).unwrap();
+
+        assert_eq!(read_arguments(&path).unwrap(), arguments);
+        assert_eq!(image::open(&path).unwrap(), image);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn check_print_preset() {
+        let path = std::env::temp_dir().join(format!(
+            "mandelbrot_print_preset_test_{}.png",
+            std::process::id()
+        ));
+        let image = DynamicImage::ImageRgb8(image::RgbImage::from_fn(3, 2, |x, y| {
+            image::Rgb([40 * x as u8, 100 * y as u8, 255])
+        }));
+        let preset = PrintPreset {
+            sixteen_bit: true,
+            ..PrintPreset::built_in("print-a4-300dpi").unwrap()
+        };
+        save_with_arguments(&image, &path, &[], Some(&preset)).unwrap();
+
+        let reader = png::Decoder::new(BufReader::new(File::open(&path).unwrap()))
+            .read_info()
+            .unwrap();
+        let info = reader.info();
+        assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+        assert_eq!(info.pixel_dims.unwrap().xppu, 11811);
+        assert!(info.srgb.is_some());
+        assert_eq!(
+            image::open(&path).unwrap(),
+            DynamicImage::ImageRgb16(image.to_rgb16())
+        );
+
+        let missing_profile = PrintPreset {
+            icc_profile: Some(PathBuf::from("no_such_profile.icc")),
+            ..preset
+        };
+        assert!(matches!(
+            save_with_arguments(&image, &path, &[], Some(&missing_profile)),
+            Err(MetadataError::IccProfile(..))
+        ));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn check_streamed_image() {
+        use mandellib::{render, render_rows, Frame, RenderParameters};
+
+        let path =
+            std::env::temp_dir().join(format!("mandelbrot_stream_test_{}.png", std::process::id()));
+        let params = RenderParameters::try_new(
+            20.try_into().unwrap(),
+            13.try_into().unwrap(),
+            64.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+        let arguments = ["--resolution=20x13".to_owned()];
+        let bands = render_rows(&params, frame, 4.try_into().unwrap()).unwrap();
+        stream_with_arguments(bands, (20, 13), params.color_type, &path, &arguments, None).unwrap();
+
+        assert_eq!(read_arguments(&path).unwrap(), arguments);
+        assert_eq!(image::open(&path).unwrap(), render(&params, frame, false));
+
+        // The bands must make up the whole image.
+        let bands = render_rows(&params, frame, 4.try_into().unwrap())
+            .unwrap()
+            .take(2);
+        assert!(matches!(
+            stream_with_arguments(bands, (20, 13), params.color_type, &path, &[], None),
+            Err(MetadataError::Encoding(_))
+        ));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+//! Telling the user that a render has finished, since renders that take hours are easy to forget about.
+//!
+//! The terminal bell is rung along with a message, and if the program is compiled
+//! with the `notify` feature a desktop notification with the message is shown as well.
+
+use core::time::Duration;
+use std::io::{self, Write};
+
+/// Rings the terminal bell and prints the message, and shows it in a desktop notification
+/// if the program is compiled with the `notify` feature.
+pub fn notify(message: &str) -> Result<(), String> {
+    // The bell character makes most terminals play a sound or flash their window.
+    writeln!(io::stdout(), "\x07{message}")
+        .map_err(|e| format!("could not ring the terminal bell: {e}"))?;
+
+    #[cfg(feature = "notify")]
+    notify_rust::Notification::new()
+        .appname("mandelbrot")
+        .summary("Render finished")
+        .body(message)
+        .show()
+        .map_err(|e| format!("could not show a desktop notification: {e}"))?;
+
+    Ok(())
+}
+
+/// Returns a message that says that what was done finished after the elapsed time,
+/// e.g. "Saved out.png after 1 h 2 min 5 s".
+pub fn finished_message(what: &str, elapsed: Duration) -> String {
+    // Rounded first, so that e.g. 59.99 seconds is written as a minute rather than as 60.0 seconds.
+    let tenths = (elapsed.as_millis() + 50) / 100;
+    let seconds = tenths / 10;
+    let time = if seconds < 60 {
+        format!("{seconds}.{} s", tenths % 10)
+    } else if seconds < 60 * 60 {
+        format!("{} min {} s", seconds / 60, seconds % 60)
+    } else {
+        format!(
+            "{} h {} min {} s",
+            seconds / (60 * 60),
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    };
+    format!("{what} after {time}")
+}
+
+#[cfg(test)]
+mod test_notification {
+    use super::*;
+
+    #[test]
+    fn check_finished_message() {
+        let message = |millis| finished_message("Saved out.png", Duration::from_millis(millis));
+        assert_eq!(message(1_240), "Saved out.png after 1.2 s");
+        assert_eq!(message(59_990), "Saved out.png after 1 min 0 s");
+        assert_eq!(message(125_000), "Saved out.png after 2 min 5 s");
+        assert_eq!(message(3_725_000), "Saved out.png after 1 h 2 min 5 s");
+    }
+}
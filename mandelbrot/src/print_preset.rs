@@ -0,0 +1,398 @@
+//! Presets that render an image for printing at a physical size, so that the resolution,
+//! supersampling and encoding of a poster do not have to be worked out by hand.
+//!
+//! The built-in presets are named "print-<PAPER>-<DPI>dpi", e.g. "print-a2-300dpi",
+//! and print in landscape unless "-portrait" is appended to the name.
+//! Other presets are read from a text file with one field per line:
+//!
+//! ```text
+//! # Lines starting with a '#' and empty lines are ignored.
+//! preset gallery-poster
+//! size 600x400
+//! dpi 200
+//! ssaa 4
+//! bit-depth 16
+//! icc-profile /home/me/profiles/FOGRA39.icc
+//! ```
+//!
+//! The size is given in millimeters and must be given for every preset,
+//! while the other fields default to 300 dpi, an SSAA factor of 3, 8 bits per channel and sRGB.
+
+use core::fmt;
+use core::num::{NonZeroU32, NonZeroU8};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::resolution::Resolution;
+
+/// The file that `--preset` reads user-defined presets from by default.
+pub const DEFAULT_PRESETS_FILE: &str = "mandelbrot_print_presets.txt";
+
+const MILLIMETERS_PER_INCH: f64 = 25.4;
+const DEFAULT_DPI: NonZeroU32 = NonZeroU32::new(300).unwrap();
+const DEFAULT_SSAA: NonZeroU8 = NonZeroU8::new(3).unwrap();
+
+/// The paper sizes of the built-in presets, with their long and short sides in millimeters.
+const PAPER_SIZES: [(&str, f64, f64); 9] = [
+    ("a0", 1189.0, 841.0),
+    ("a1", 841.0, 594.0),
+    ("a2", 594.0, 420.0),
+    ("a3", 420.0, 297.0),
+    ("a4", 297.0, 210.0),
+    ("a5", 210.0, 148.0),
+    ("a6", 148.0, 105.0),
+    ("letter", 279.4, 215.9),
+    ("tabloid", 431.8, 279.4),
+];
+
+/// A physical print size and resolution along with the settings that an image for it is rendered and saved with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrintPreset {
+    pub name: String,
+    /// The width of the print in millimeters.
+    pub width_mm: f64,
+    /// The height of the print in millimeters.
+    pub height_mm: f64,
+    pub dpi: NonZeroU32,
+    pub ssaa: NonZeroU8,
+    /// Whether the image is saved with 16 bits per channel instead of 8.
+    /// The colors are still computed with 8 bits per channel, but some print workflows require 16-bit files.
+    pub sixteen_bit: bool,
+    /// The ICC profile to embed in the image. Images without one are marked as sRGB.
+    pub icc_profile: Option<PathBuf>,
+}
+
+impl PrintPreset {
+    /// Returns the built-in preset with the given name, if there is one.
+    pub fn built_in(name: &str) -> Option<Self> {
+        let rest = name.strip_prefix("print-")?;
+        let (rest, portrait) = match rest.strip_suffix("-portrait") {
+            Some(rest) => (rest, true),
+            None => (rest, false),
+        };
+        let (paper, dpi) = rest.split_once('-')?;
+        let dpi = dpi.strip_suffix("dpi")?.parse().ok()?;
+        let &(_, long_side, short_side) = PAPER_SIZES
+            .iter()
+            .find(|(paper_name, ..)| paper_name.eq_ignore_ascii_case(paper))?;
+        let (width_mm, height_mm) = if portrait {
+            (short_side, long_side)
+        } else {
+            (long_side, short_side)
+        };
+        Some(Self {
+            name: name.to_owned(),
+            width_mm,
+            height_mm,
+            dpi,
+            ssaa: DEFAULT_SSAA,
+            sixteen_bit: false,
+            icc_profile: None,
+        })
+    }
+
+    /// Returns the resolution that covers the print with the DPI of the preset.
+    pub fn resolution(&self) -> Resolution {
+        let pixels = |millimeters: f64| {
+            // Prints too large for a u32 saturate, and can not be rendered anyway.
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let pixels =
+                (millimeters / MILLIMETERS_PER_INCH * f64::from(self.dpi.get())).round() as u32;
+            pixels.max(1)
+        };
+        Resolution::new(pixels(self.width_mm), pixels(self.height_mm))
+            .expect("the resolution is at least 1 by 1")
+    }
+
+    /// Returns the number of pixels per meter, which is how PNG images record their DPI.
+    pub fn dots_per_meter(&self) -> u32 {
+        // Only DPIs above a hundred million saturate, which no printer comes close to.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let dots = (f64::from(self.dpi.get()) * 1000.0 / MILLIMETERS_PER_INCH).round() as u32;
+        dots
+    }
+}
+
+/// Returns the preset with the given name, looking among the user-defined presets first
+/// so that they can replace the built-in ones.
+pub fn find(name: &str, user_presets: &[PrintPreset]) -> Option<PrintPreset> {
+    user_presets
+        .iter()
+        .find(|preset| preset.name == name)
+        .cloned()
+        .or_else(|| PrintPreset::built_in(name))
+}
+
+/// Reads the presets in the file at the path.
+/// A file that does not exist holds no presets, since the user has not defined any.
+pub fn load_presets(path: &Path) -> Result<Vec<PrintPreset>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse_presets(&text)
+            .map_err(|e| format!("could not read the presets in {}: {e}", path.display())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("could not open {}: {e}", path.display())),
+    }
+}
+
+/// Parses presets in the format described in the [module documentation](self).
+pub fn parse_presets(text: &str) -> Result<Vec<PrintPreset>, ParsePresetsError> {
+    // The presets along with the line that they start on, so that a missing size can be reported there.
+    let mut presets: Vec<(usize, PrintPreset)> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let (field, value) = trimmed.split_once([' ', '\t']).unwrap_or((trimmed, ""));
+        let value = value.trim();
+        let invalid_value = || {
+            ParsePresetsError::new(
+                ParsePresetsErrorKind::InvalidValue {
+                    field: field.to_owned(),
+                    value: value.to_owned(),
+                },
+                line_number,
+            )
+        };
+
+        if field == "preset" {
+            if value.is_empty() {
+                return Err(ParsePresetsError::new(
+                    ParsePresetsErrorKind::EmptyName,
+                    line_number,
+                ));
+            }
+            presets.push((
+                line_number,
+                PrintPreset {
+                    name: value.to_owned(),
+                    // A size of zero marks that it has not been given yet.
+                    width_mm: 0.0,
+                    height_mm: 0.0,
+                    dpi: DEFAULT_DPI,
+                    ssaa: DEFAULT_SSAA,
+                    sixteen_bit: false,
+                    icc_profile: None,
+                },
+            ));
+            continue;
+        }
+
+        let Some((_, preset)) = presets.last_mut() else {
+            return Err(ParsePresetsError::new(
+                ParsePresetsErrorKind::OutsidePreset,
+                line_number,
+            ));
+        };
+        match field {
+            "size" => {
+                let (width, height) = value.split_once('x').ok_or_else(invalid_value)?;
+                let millimeters = |side: &str| {
+                    side.trim()
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|side| side.is_finite() && *side > 0.0)
+                        .ok_or_else(invalid_value)
+                };
+                preset.width_mm = millimeters(width)?;
+                preset.height_mm = millimeters(height)?;
+            }
+            "dpi" => preset.dpi = value.parse().map_err(|_| invalid_value())?,
+            "ssaa" => preset.ssaa = value.parse().map_err(|_| invalid_value())?,
+            "bit-depth" => {
+                preset.sixteen_bit = match value {
+                    "8" => false,
+                    "16" => true,
+                    _ => return Err(invalid_value()),
+                }
+            }
+            "icc-profile" => {
+                if value.is_empty() {
+                    return Err(invalid_value());
+                }
+                preset.icc_profile = Some(PathBuf::from(value));
+            }
+            _ => {
+                return Err(ParsePresetsError::new(
+                    ParsePresetsErrorKind::UnknownField(field.to_owned()),
+                    line_number,
+                ))
+            }
+        }
+    }
+
+    presets
+        .into_iter()
+        .map(|(line_number, preset)| {
+            if preset.width_mm > 0.0 {
+                Ok(preset)
+            } else {
+                Err(ParsePresetsError::new(
+                    ParsePresetsErrorKind::MissingSize,
+                    line_number,
+                ))
+            }
+        })
+        .collect()
+}
+
+/// An error that occured while parsing print presets.
+/// Contains the line in the input, starting from 1, where the error was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePresetsError {
+    kind: ParsePresetsErrorKind,
+    line: usize,
+}
+
+impl ParsePresetsError {
+    const fn new(kind: ParsePresetsErrorKind, line: usize) -> Self {
+        Self { kind, line }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsePresetsErrorKind {
+    /// A preset was started without a name.
+    EmptyName,
+    /// A field was given before the first preset.
+    OutsidePreset,
+    UnknownField(String),
+    InvalidValue {
+        field: String,
+        value: String,
+    },
+    /// The preset starting on the line has no size.
+    MissingSize,
+}
+
+impl fmt::Display for ParsePresetsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "on line {}: ", self.line)?;
+        match &self.kind {
+            ParsePresetsErrorKind::EmptyName => write!(f, "the preset has no name"),
+            ParsePresetsErrorKind::OutsidePreset => {
+                write!(f, "expected \"preset\" followed by a name")
+            }
+            ParsePresetsErrorKind::UnknownField(field) => {
+                write!(f, "\"{field}\" is not a field of a preset")
+            }
+            ParsePresetsErrorKind::InvalidValue { field, value } => {
+                write!(f, "\"{value}\" is not a valid {field}")?;
+                match field.as_str() {
+                    "size" => write!(f, ", it must be given in millimeters as WIDTHxHEIGHT"),
+                    "bit-depth" => write!(f, ", it must be 8 or 16"),
+                    _ => Ok(()),
+                }
+            }
+            ParsePresetsErrorKind::MissingSize => write!(f, "the preset has no size"),
+        }
+    }
+}
+
+impl std::error::Error for ParsePresetsError {}
+
+#[cfg(test)]
+mod test_print_preset {
+    use super::*;
+
+    #[test]
+    fn check_built_in_presets() {
+        let a2 = PrintPreset::built_in("print-a2-300dpi").unwrap();
+        assert_eq!(a2.resolution(), Resolution::new(7016, 4961).unwrap());
+        assert_eq!(a2.ssaa, DEFAULT_SSAA);
+        assert_eq!(a2.dots_per_meter(), 11811);
+
+        let a4 = PrintPreset::built_in("print-A4-150dpi-portrait").unwrap();
+        assert_eq!((a4.width_mm, a4.height_mm), (210.0, 297.0));
+        assert_eq!(a4.resolution(), Resolution::new(1240, 1754).unwrap());
+
+        for name in [
+            "print-a7-300dpi",
+            "print-a2-300",
+            "print-a2-0dpi",
+            "a2-300dpi",
+            "print-a2-300dpi-sideways",
+        ] {
+            assert_eq!(PrintPreset::built_in(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn check_parsing() {
+        let presets = parse_presets(
+            "# Presets for the gallery
+preset gallery-poster
+size 600x400
+dpi 200
+ssaa 4
+bit-depth 16
+icc-profile /profiles/FOGRA39.icc
+
+preset print-a2-300dpi
+size 594 x 420
+",
+        )
+        .unwrap();
+        assert_eq!(presets.len(), 2);
+        let poster = &presets[0];
+        assert_eq!(poster.name, "gallery-poster");
+        assert_eq!(poster.resolution(), Resolution::new(4724, 3150).unwrap());
+        assert_eq!(poster.ssaa.get(), 4);
+        assert!(poster.sixteen_bit);
+        assert_eq!(
+            poster.icc_profile.as_deref(),
+            Some(Path::new("/profiles/FOGRA39.icc"))
+        );
+
+        // User-defined presets replace the built-in ones with the same name.
+        let a2 = find("print-a2-300dpi", &presets).unwrap();
+        assert_eq!(a2.dpi, DEFAULT_DPI);
+        assert!(!a2.sixteen_bit && a2.icc_profile.is_none());
+        assert!(find("print-a3-300dpi", &presets).is_some());
+        assert_eq!(find("poster", &presets), None);
+        assert_eq!(parse_presets(""), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn check_errors() {
+        let error = |text: &str| parse_presets(text).unwrap_err();
+        let invalid = |field: &str, value: &str, line| {
+            ParsePresetsError::new(
+                ParsePresetsErrorKind::InvalidValue {
+                    field: field.to_owned(),
+                    value: value.to_owned(),
+                },
+                line,
+            )
+        };
+        assert_eq!(
+            error("size 10x10"),
+            ParsePresetsError::new(ParsePresetsErrorKind::OutsidePreset, 1)
+        );
+        assert_eq!(
+            error("preset"),
+            ParsePresetsError::new(ParsePresetsErrorKind::EmptyName, 1)
+        );
+        assert_eq!(
+            error("preset a\nsize 10x10\ncolor red"),
+            ParsePresetsError::new(ParsePresetsErrorKind::UnknownField("color".to_owned()), 3)
+        );
+        // A missing size is reported on the line that the preset starts on.
+        assert_eq!(
+            error("preset a\nsize 10x10\n\npreset b\ndpi 600"),
+            ParsePresetsError::new(ParsePresetsErrorKind::MissingSize, 4)
+        );
+
+        assert_eq!(error("preset a\nsize 10"), invalid("size", "10", 2));
+        assert_eq!(error("preset a\nsize 10x-5"), invalid("size", "10x-5", 2));
+        assert_eq!(error("preset a\nsize 10x10\ndpi 0"), invalid("dpi", "0", 3));
+        let bit_depth = error("preset a\nsize 10x10\nbit-depth 12");
+        assert_eq!(bit_depth, invalid("bit-depth", "12", 3));
+        assert_eq!(
+            bit_depth.to_string(),
+            "on line 3: \"12\" is not a valid bit-depth, it must be 8 or 16"
+        );
+    }
+}
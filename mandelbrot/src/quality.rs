@@ -0,0 +1,150 @@
+//! Measures of how similar two images of the same resolution are.
+
+use core::fmt;
+
+use image::{DynamicImage, GenericImageView};
+
+/// The width and height of the windows that the structural similarity is computed in.
+const SSIM_WINDOW: u32 = 8;
+
+/// Returns the peak signal-to-noise ratio in decibels of `image` compared to `reference`,
+/// computed over all color channels. Identical images give infinity.
+pub fn psnr(reference: &DynamicImage, image: &DynamicImage) -> Result<f64, CompareError> {
+    check_resolutions(reference, image)?;
+    let reference = reference.to_rgb8();
+    let image = image.to_rgb8();
+
+    let squared_error: u64 = reference
+        .as_raw()
+        .iter()
+        .zip(image.as_raw())
+        .map(|(&a, &b)| u64::from(a.abs_diff(b)).pow(2))
+        .sum();
+    // Counts are converted to floats since they are only used in ratios.
+    #[allow(clippy::cast_precision_loss)]
+    let mean_squared_error = squared_error as f64 / reference.as_raw().len() as f64;
+
+    Ok(10.0 * (255.0_f64.powi(2) / mean_squared_error).log10())
+}
+
+/// Returns the mean structural similarity index of the luma of `image` compared to `reference`,
+/// computed in non-overlapping windows of 8 by 8 pixels. Identical images give 1.
+pub fn ssim(reference: &DynamicImage, image: &DynamicImage) -> Result<f64, CompareError> {
+    /// Stabilize the division for windows with low mean and variance.
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    check_resolutions(reference, image)?;
+    let reference = reference.to_luma8();
+    let image = image.to_luma8();
+    let (width, height) = reference.dimensions();
+
+    let mut total = 0.0;
+    let mut windows = 0_u32;
+    for window_y in (0..height).step_by(SSIM_WINDOW as usize) {
+        for window_x in (0..width).step_by(SSIM_WINDOW as usize) {
+            // The windows at the right and bottom edges are smaller if the window size
+            // does not divide the resolution.
+            let window_width = SSIM_WINDOW.min(width - window_x);
+            let window_height = SSIM_WINDOW.min(height - window_y);
+            let pixels = (window_y..window_y + window_height)
+                .flat_map(|y| (window_x..window_x + window_width).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    (
+                        f64::from(reference.get_pixel(x, y).0[0]),
+                        f64::from(image.get_pixel(x, y).0[0]),
+                    )
+                });
+
+            let count = f64::from(window_width * window_height);
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) =
+                (0.0, 0.0, 0.0, 0.0, 0.0);
+            for (a, b) in pixels {
+                sum_a += a;
+                sum_b += b;
+                sum_aa += a * a;
+                sum_bb += b * b;
+                sum_ab += a * b;
+            }
+            let (mean_a, mean_b) = (sum_a / count, sum_b / count);
+            let variance_a = sum_aa / count - mean_a * mean_a;
+            let variance_b = sum_bb / count - mean_b * mean_b;
+            let covariance = sum_ab / count - mean_a * mean_b;
+
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (variance_a + variance_b + C2));
+            windows += 1;
+        }
+    }
+
+    Ok(total / f64::from(windows))
+}
+
+fn check_resolutions(reference: &DynamicImage, image: &DynamicImage) -> Result<(), CompareError> {
+    if reference.dimensions() == image.dimensions() {
+        Ok(())
+    } else {
+        Err(CompareError::ResolutionMismatch {
+            reference: reference.dimensions(),
+            image: image.dimensions(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareError {
+    ResolutionMismatch {
+        reference: (u32, u32),
+        image: (u32, u32),
+    },
+}
+
+impl fmt::Display for CompareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ResolutionMismatch { reference, image } => write!(
+                f,
+                "can not compare a {}x{} image to a reference image of {}x{}",
+                image.0, image.1, reference.0, reference.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompareError {}
+
+#[cfg(test)]
+mod test_quality {
+    use super::*;
+    use image::{GrayImage, Luma, RgbImage};
+
+    #[test]
+    fn check_metrics() {
+        let gradient = DynamicImage::ImageLuma8(GrayImage::from_fn(20, 12, |x, y| {
+            Luma([u8::try_from(10 * x + y).unwrap()])
+        }));
+        assert_eq!(psnr(&gradient, &gradient), Ok(f64::INFINITY));
+        assert!((ssim(&gradient, &gradient).unwrap() - 1.0).abs() < 1e-12);
+
+        // An error of one in every channel of every pixel gives a PSNR of 20 log10(255).
+        let black = DynamicImage::ImageRgb8(RgbImage::new(5, 3));
+        let almost_black = DynamicImage::ImageRgb8(RgbImage::from_pixel(5, 3, image::Rgb([1; 3])));
+        let expected = 20.0 * 255.0_f64.log10();
+        assert!((psnr(&black, &almost_black).unwrap() - expected).abs() < 1e-12);
+
+        // Noise lowers the structural similarity more than a small change in brightness.
+        let noisy = DynamicImage::ImageLuma8(GrayImage::from_fn(20, 12, |x, y| {
+            Luma([u8::try_from(10 * x + y).unwrap() ^ u8::try_from((x * 7 + y * 3) % 16).unwrap()])
+        }));
+        let brighter = gradient.brighten(2);
+        assert!(ssim(&gradient, &noisy).unwrap() < ssim(&gradient, &brighter).unwrap());
+
+        assert_eq!(
+            psnr(&gradient, &black),
+            Err(CompareError::ResolutionMismatch {
+                reference: (20, 12),
+                image: (5, 3)
+            })
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! An interactive mode that reads commands from stdin which change the view and render it,
+//! so that a view can be iterated on without retyping the whole command line.
+
+use core::fmt;
+use core::num::{NonZeroU64, ParseFloatError, ParseIntError};
+use core::str::FromStr;
+use std::error::Error;
+use std::io::{BufRead, Write};
+
+use mandellib::{ParsePreciseRealError, PreciseReal, Zoom};
+
+use crate::{
+    command_line_interface::Cli,
+    resolution::{ParseResolutionError, Resolution},
+};
+
+/// The usage and description of every command, in the order they are listed by "help".
+const COMMANDS: [(&str, &str); 8] = [
+    ("center RE IM", "move the center of the image to RE + IM*i"),
+    (
+        "zoom LEVEL",
+        "set the zoom level, where each step halves the size of the view",
+    ),
+    ("iters N", "set the maximum number of iterations"),
+    ("resolution X_RESxY_RES", "set the resolution of the image"),
+    (
+        "render [PATH]",
+        "render the image and save it at PATH, or at the last used path",
+    ),
+    (
+        "show",
+        "print the command line that renders the current view",
+    ),
+    ("help", "list the commands"),
+    ("quit", "end the session"),
+];
+
+/// A command that is read from stdin in the interactive mode.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplCommand {
+    Center {
+        real: PreciseReal,
+        imag: PreciseReal,
+    },
+    Zoom(Zoom),
+    Iterations(NonZeroU64),
+    Resolution(Resolution),
+    Render(Option<String>),
+    Show,
+    Help,
+    Quit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseReplCommandError {
+    UnknownCommand(String),
+    WrongArgumentCount { usage: &'static str },
+    InvalidCenter(ParsePreciseRealError),
+    InvalidZoom(ParseFloatError),
+    NonFiniteZoom,
+    InvalidIterations(ParseIntError),
+    InvalidResolution(ParseResolutionError),
+}
+
+impl fmt::Display for ParseReplCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownCommand(command) => write!(
+                f,
+                "unknown command \"{command}\", type \"help\" to list the commands"
+            ),
+            Self::WrongArgumentCount { usage } => write!(f, "usage: {usage}"),
+            Self::InvalidCenter(e) => write!(f, "the center could not be parsed: {e}"),
+            Self::InvalidZoom(e) => write!(f, "the zoom level could not be parsed: {e}"),
+            Self::NonFiniteZoom => write!(f, "the zoom level must be finite"),
+            Self::InvalidIterations(e) => {
+                write!(f, "the number of iterations could not be parsed: {e}")
+            }
+            Self::InvalidResolution(e) => write!(f, "the resolution could not be parsed: {e}"),
+        }
+    }
+}
+
+impl Error for ParseReplCommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidCenter(e) => Some(e),
+            Self::InvalidZoom(e) => Some(e),
+            Self::InvalidIterations(e) => Some(e),
+            Self::InvalidResolution(e) => Some(e),
+            Self::UnknownCommand(_) | Self::WrongArgumentCount { .. } | Self::NonFiniteZoom => None,
+        }
+    }
+}
+
+impl FromStr for ReplCommand {
+    type Err = ParseReplCommandError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let arguments: Vec<&str> = words.collect();
+        let usage = |index: usize| ParseReplCommandError::WrongArgumentCount {
+            usage: COMMANDS[index].0,
+        };
+
+        match (command.to_ascii_lowercase().as_str(), arguments.as_slice()) {
+            ("center", [real, imag]) => Ok(Self::Center {
+                real: real.parse().map_err(Self::Err::InvalidCenter)?,
+                imag: imag.parse().map_err(Self::Err::InvalidCenter)?,
+            }),
+            ("center", _) => Err(usage(0)),
+            ("zoom", [level]) => {
+                let level: f64 = level.parse().map_err(Self::Err::InvalidZoom)?;
+                Zoom::try_from(level)
+                    .map(Self::Zoom)
+                    .map_err(|_| Self::Err::NonFiniteZoom)
+            }
+            ("zoom", _) => Err(usage(1)),
+            ("iters", [iterations]) => Ok(Self::Iterations(
+                iterations.parse().map_err(Self::Err::InvalidIterations)?,
+            )),
+            ("iters", _) => Err(usage(2)),
+            ("resolution", [resolution]) => Ok(Self::Resolution(
+                resolution.parse().map_err(Self::Err::InvalidResolution)?,
+            )),
+            ("resolution", _) => Err(usage(3)),
+            ("render", []) => Ok(Self::Render(None)),
+            ("render", [path]) => Ok(Self::Render(Some((*path).to_owned()))),
+            ("render", _) => Err(usage(4)),
+            ("show", []) => Ok(Self::Show),
+            ("show", _) => Err(usage(5)),
+            ("help", []) => Ok(Self::Help),
+            ("help", _) => Err(usage(6)),
+            ("quit" | "exit", []) => Ok(Self::Quit),
+            ("quit" | "exit", _) => Err(usage(7)),
+            _ => Err(Self::Err::UnknownCommand(command.to_owned())),
+        }
+    }
+}
+
+/// Reads commands from `input` until it ends or the "quit" command is given,
+/// starting from the settings in `args`. The settings that the commands change are kept between commands,
+/// and the "render" command calls `render` with the current settings.
+///
+/// Commands that can not be parsed or fail to render print an error to `output`
+/// and do not end the session.
+pub fn run(
+    mut args: Cli,
+    mut input: impl BufRead,
+    mut output: impl Write,
+    mut render: impl FnMut(&Cli) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    writeln!(output, "Type \"help\" to list the commands")?;
+    let mut line = String::new();
+    loop {
+        write!(output, "> ")?;
+        output.flush()?;
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let command = match line.parse() {
+            Ok(command) => command,
+            Err(e) => {
+                writeln!(output, "error: {e}")?;
+                continue;
+            }
+        };
+        match command {
+            ReplCommand::Center { real, imag } => {
+                args.real_center = real;
+                args.imag_center = imag;
+            }
+            ReplCommand::Zoom(level) => {
+                args.zoom_level = level;
+                args.resolve_auto_iterations();
+            }
+            ReplCommand::Iterations(iterations) => {
+                // An explicit number of iterations replaces the automatic one.
+                args.auto_iterations = false;
+                args.max_iterations = iterations;
+            }
+            ReplCommand::Resolution(resolution) => {
+                args.resolution = resolution;
+                args.resolve_auto_iterations();
+            }
+            ReplCommand::Render(path) => {
+                if let Some(path) = path {
+                    args.output_path = path;
+                }
+                match render(&args) {
+                    Ok(()) => writeln!(output, "Saved image as {}", args.output_path)?,
+                    Err(e) => writeln!(output, "error: {e}")?,
+                }
+            }
+            ReplCommand::Show => writeln!(output, "{}", args.recorded_arguments().join(" "))?,
+            ReplCommand::Help => {
+                for (usage, description) in COMMANDS {
+                    writeln!(output, "{usage:<24}{description}")?;
+                }
+            }
+            ReplCommand::Quit => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_repl {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn check_parsing() {
+        assert_eq!(
+            "center -0.75 0.1".parse(),
+            Ok(ReplCommand::Center {
+                real: "-0.75".parse().unwrap(),
+                imag: "0.1".parse().unwrap()
+            })
+        );
+        assert_eq!(
+            "  ZOOM 12 ".parse(),
+            Ok(ReplCommand::Zoom(12.0.try_into().unwrap()))
+        );
+        assert_eq!(
+            "render out.png".parse(),
+            Ok(ReplCommand::Render(Some("out.png".to_owned())))
+        );
+        assert_eq!(
+            "iters".parse::<ReplCommand>(),
+            Err(ParseReplCommandError::WrongArgumentCount { usage: "iters N" })
+        );
+        assert_eq!(
+            "help me".parse::<ReplCommand>(),
+            Err(ParseReplCommandError::WrongArgumentCount { usage: "help" })
+        );
+        assert_eq!(
+            "zoom inf".parse::<ReplCommand>(),
+            Err(ParseReplCommandError::NonFiniteZoom)
+        );
+        assert!(matches!(
+            "iters 0".parse::<ReplCommand>(),
+            Err(ParseReplCommandError::InvalidIterations(_))
+        ));
+        assert!(matches!(
+            "fly away".parse::<ReplCommand>(),
+            Err(ParseReplCommandError::UnknownCommand(_))
+        ));
+    }
+
+    #[test]
+    fn check_session() {
+        let input = "center -1 0.5\nzoom 3\n\niters 50\nrender a.png\nbogus\nzoom x\nrender\nquit\nrender never.png\n";
+        let mut output = Vec::new();
+        let mut renders = Vec::new();
+        run(
+            Cli::parse_from(["mandelbrot"]),
+            input.as_bytes(),
+            &mut output,
+            |args| {
+                renders.push((
+                    args.real_center.to_f64(),
+                    args.imag_center.to_f64(),
+                    args.zoom_level.level(),
+                    args.max_iterations.get(),
+                    args.output_path.clone(),
+                ));
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        // The settings are kept between renders, and nothing is read after "quit".
+        assert_eq!(
+            renders,
+            [
+                (-1.0, 0.5, 3.0, 50, "a.png".to_owned()),
+                (-1.0, 0.5, 3.0, 50, "a.png".to_owned()),
+            ]
+        );
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("error:").count(), 2);
+        assert_eq!(output.matches("Saved image as a.png").count(), 2);
+    }
+}
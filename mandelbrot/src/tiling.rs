@@ -0,0 +1,297 @@
+use core::fmt;
+use std::path::{Path, PathBuf};
+
+use image::{DynamicImage, GenericImage, GenericImageView, ImageError};
+use mandellib::PixelRect;
+
+use crate::resolution::Resolution;
+
+/// Splits an image into tiles of a given size, where each tile is padded with
+/// `overlap` guard pixels on every side that is not at the edge of the image.
+/// The tiles in the last column and row are smaller if the tile size does not divide the resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileGrid {
+    resolution: Resolution,
+    tile_size: Resolution,
+    overlap: u32,
+}
+
+impl TileGrid {
+    pub const fn new(resolution: Resolution, tile_size: Resolution, overlap: u32) -> Self {
+        Self {
+            resolution,
+            tile_size,
+            overlap,
+        }
+    }
+
+    pub const fn columns(&self) -> u32 {
+        self.resolution
+            .x_resolution()
+            .get()
+            .div_ceil(self.tile_size.x_resolution().get())
+    }
+
+    pub const fn rows(&self) -> u32 {
+        self.resolution
+            .y_resolution()
+            .get()
+            .div_ceil(self.tile_size.y_resolution().get())
+    }
+
+    pub fn tile_count(&self) -> usize {
+        usize::try_from(u64::from(self.columns()) * u64::from(self.rows())).unwrap_or(usize::MAX)
+    }
+
+    /// Returns the tile with the given index, counted row by row from the top left tile,
+    /// or `None` if there is no such tile.
+    pub fn tile(&self, index: usize) -> Option<Tile> {
+        let index = u32::try_from(index).ok()?;
+        let (column, row) = (index % self.columns(), index / self.columns());
+        if row >= self.rows() {
+            return None;
+        }
+
+        let (x_resolution, y_resolution) = (
+            self.resolution.x_resolution().get(),
+            self.resolution.y_resolution().get(),
+        );
+        let (tile_width, tile_height) = (
+            self.tile_size.x_resolution().get(),
+            self.tile_size.y_resolution().get(),
+        );
+
+        let core = PixelRect {
+            x: column * tile_width,
+            y: row * tile_height,
+            width: tile_width.min(x_resolution - column * tile_width),
+            height: tile_height.min(y_resolution - row * tile_height),
+        };
+
+        let left = core.x.saturating_sub(self.overlap);
+        let top = core.y.saturating_sub(self.overlap);
+        let right = (core.x + core.width)
+            .saturating_add(self.overlap)
+            .min(x_resolution);
+        let bottom = (core.y + core.height)
+            .saturating_add(self.overlap)
+            .min(y_resolution);
+
+        Some(Tile {
+            column,
+            row,
+            core,
+            padded: PixelRect {
+                x: left,
+                y: top,
+                width: right - left,
+                height: bottom - top,
+            },
+        })
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = Tile> + '_ {
+        (0..self.tile_count()).map_while(|index| self.tile(index))
+    }
+}
+
+/// One tile in a [`TileGrid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub column: u32,
+    pub row: u32,
+    /// The part of the image that this tile contributes to the stitched image.
+    pub core: PixelRect,
+    /// The part of the image that is rendered for this tile:
+    /// the core plus the overlap on every side, clamped to the image.
+    pub padded: PixelRect,
+}
+
+impl Tile {
+    /// Cuts the core of the tile out of an image of the padded tile.
+    pub fn trim(&self, padded_image: &DynamicImage) -> DynamicImage {
+        padded_image.crop_imm(
+            self.core.x - self.padded.x,
+            self.core.y - self.padded.y,
+            self.core.width,
+            self.core.height,
+        )
+    }
+
+    /// Returns the path that the tile is saved to when the full image would be saved to `output_path`,
+    /// e.g. "mandelbrot_set_tile_2_0.png" for the tile in the third column of the first row of "mandelbrot_set.png".
+    pub fn path(&self, output_path: &Path) -> PathBuf {
+        let mut file_name = output_path.file_stem().unwrap_or_default().to_owned();
+        file_name.push(format!("_tile_{}_{}", self.column, self.row));
+        if let Some(extension) = output_path.extension() {
+            file_name.push(".");
+            file_name.push(extension);
+        }
+        output_path.with_file_name(file_name)
+    }
+}
+
+/// Loads the tiles of the image at `output_path` and stitches them together,
+/// trimming the overlap of each tile.
+pub fn stitch(grid: &TileGrid, output_path: &Path) -> Result<DynamicImage, StitchError> {
+    let tiles = grid
+        .tiles()
+        .map(|tile| {
+            let path = tile.path(output_path);
+            image::open(&path)
+                .map(|image| (tile, image))
+                .map_err(|error| StitchError::Image { path, error })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    stitch_images(grid, tiles, output_path)
+}
+
+fn stitch_images(
+    grid: &TileGrid,
+    tiles: Vec<(Tile, DynamicImage)>,
+    output_path: &Path,
+) -> Result<DynamicImage, StitchError> {
+    let color_type = tiles
+        .first()
+        .map_or(image::ColorType::Rgb8, |(_, image)| image.color());
+    let mut stitched = DynamicImage::new(
+        grid.resolution.x_resolution().get(),
+        grid.resolution.y_resolution().get(),
+        color_type,
+    );
+
+    for (tile, image) in tiles {
+        if image.dimensions() != (tile.padded.width, tile.padded.height) {
+            return Err(StitchError::SizeMismatch {
+                path: tile.path(output_path),
+                expected: (tile.padded.width, tile.padded.height),
+                found: image.dimensions(),
+            });
+        }
+        stitched
+            .copy_from(&tile.trim(&image), tile.core.x, tile.core.y)
+            .map_err(|error| StitchError::Image {
+                path: tile.path(output_path),
+                error,
+            })?;
+    }
+
+    Ok(stitched)
+}
+
+#[derive(Debug)]
+pub enum StitchError {
+    Image {
+        path: PathBuf,
+        error: ImageError,
+    },
+    SizeMismatch {
+        path: PathBuf,
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+}
+
+impl fmt::Display for StitchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Image { path, error } => {
+                write!(f, "could not stitch in the tile {}: {error}", path.display())
+            }
+            Self::SizeMismatch {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "the tile {} is {}x{} pixels, but it should be {}x{} pixels with the given tile size and overlap",
+                path.display(),
+                found.0,
+                found.1,
+                expected.0,
+                expected.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StitchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Image { error, .. } => Some(error),
+            Self::SizeMismatch { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_tiling {
+    use super::*;
+    use color_space::SupportedColorType;
+    use mandellib::{render, render_tile, Frame, RenderParameters};
+
+    #[test]
+    fn check_tiles_cover_image() {
+        let grid = TileGrid::new(
+            Resolution::new(50, 30).unwrap(),
+            Resolution::new(16, 16).unwrap(),
+            4,
+        );
+        assert_eq!((grid.columns(), grid.rows()), (4, 2));
+        let last = grid.tile(grid.tile_count() - 1).unwrap();
+        assert_eq!(
+            last.core,
+            PixelRect {
+                x: 48,
+                y: 16,
+                width: 2,
+                height: 14
+            }
+        );
+        assert_eq!(
+            last.padded,
+            PixelRect {
+                x: 44,
+                y: 12,
+                width: 6,
+                height: 18
+            }
+        );
+        assert!(grid.tile(grid.tile_count()).is_none());
+        assert_eq!(
+            grid.tile(5).unwrap().path(Path::new("out/image.png")),
+            Path::new("out/image_tile_1_1.png")
+        );
+    }
+
+    #[test]
+    fn check_stitching_is_seamless() {
+        let resolution = Resolution::new(48, 36).unwrap();
+        for (center_real, center_imag) in [(-0.75, 0.0), (-1.5, 0.0), (-0.5, 0.55), (-0.2, -0.7)] {
+            let frame = Frame::new(center_real, center_imag, 1.2, 0.9);
+            let params = RenderParameters::try_new(
+                resolution.x_resolution(),
+                resolution.y_resolution(),
+                255.try_into().unwrap(),
+                2.try_into().unwrap(),
+                SupportedColorType::Rgb8,
+            )
+            .unwrap();
+            let full = render(&params, frame, false);
+
+            let grid = TileGrid::new(resolution, Resolution::new(20, 14).unwrap(), 3);
+            let tiles = grid
+                .tiles()
+                .map(|tile| (tile, render_tile(&params, frame, tile.padded).unwrap()))
+                .collect();
+            let stitched = stitch_images(&grid, tiles, Path::new("test.png")).unwrap();
+
+            let differing = full
+                .pixels()
+                .zip(stitched.pixels())
+                .filter(|(a, b)| a != b)
+                .count();
+            assert_eq!(differing, 0, "center {center_real} + {center_imag}i");
+        }
+    }
+}
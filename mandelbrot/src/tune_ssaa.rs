@@ -0,0 +1,136 @@
+//! Finds the cheapest supersampling factor that renders an image with a given quality,
+//! by comparing renders with increasing SSAA factors against a reference render.
+
+use core::num::NonZeroU8;
+use std::time::{Duration, Instant};
+
+use image::DynamicImage;
+use mandellib::{render, Frame, ReconstructionFilter, RenderParameters, SamplePlacement};
+
+use crate::quality::{psnr, ssim, CompareError};
+
+/// The quality of a render with a given SSAA factor compared to the reference render.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub ssaa: NonZeroU8,
+    pub render_time: Duration,
+    pub psnr: f64,
+    pub ssim: f64,
+}
+
+/// The quality that a render must reach for its SSAA factor to be recommended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityTarget {
+    pub psnr: f64,
+    pub ssim: f64,
+}
+
+impl Measurement {
+    pub fn meets(&self, target: QualityTarget) -> bool {
+        self.psnr >= target.psnr && self.ssim >= target.ssim
+    }
+}
+
+/// Renders the reference image with `reference_ssaa`, and then renders and measures
+/// SSAA factors from 1 and up until one meets the target, calling `on_measurement` for each of them.
+/// Returns the measurement of the cheapest SSAA factor that meets the target, or `None`
+/// if none of the factors below the reference do.
+pub fn find_cheapest_ssaa(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    reference_ssaa: NonZeroU8,
+    target: QualityTarget,
+    mut on_measurement: impl FnMut(&Measurement),
+) -> Result<Option<Measurement>, CompareError> {
+    // The reference is always sampled on a uniform grid, box filtered and not adaptively supersampled,
+    // so that other placements of the samples, filters and thresholds are measured against the same image.
+    // It never shows where supersampling stopped, since that is not part of the image.
+    let mut reference_parameters = render_parameters.clone();
+    reference_parameters.sample_placement = SamplePlacement::Grid;
+    reference_parameters.reconstruction_filter = ReconstructionFilter::Box;
+    reference_parameters.adaptive_supersampling = None;
+    reference_parameters.show_ssaa_region = false;
+    let reference = render_with_ssaa(&reference_parameters, render_region, reference_ssaa).0;
+
+    for ssaa in (1..reference_ssaa.get()).filter_map(NonZeroU8::new) {
+        let (image, render_time) = render_with_ssaa(render_parameters, render_region, ssaa);
+        let measurement = Measurement {
+            ssaa,
+            render_time,
+            psnr: psnr(&reference, &image)?,
+            ssim: ssim(&reference, &image)?,
+        };
+        on_measurement(&measurement);
+        // More samples per pixel only make the render slower, so the first factor
+        // that meets the target is the cheapest one.
+        if measurement.meets(target) {
+            return Ok(Some(measurement));
+        }
+    }
+
+    Ok(None)
+}
+
+fn render_with_ssaa(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    ssaa: NonZeroU8,
+) -> (DynamicImage, Duration) {
+    let mut render_parameters = render_parameters.clone();
+    render_parameters.sqrt_samples_per_pixel = ssaa;
+    let start = Instant::now();
+    let image = render(&render_parameters, render_region, false);
+    (image, start.elapsed())
+}
+
+#[cfg(test)]
+mod test_tune_ssaa {
+    use super::*;
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_tuning() {
+        let params = RenderParameters::try_new(
+            60.try_into().unwrap(),
+            40.try_into().unwrap(),
+            100.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+        let reference_ssaa = NonZeroU8::new(6).unwrap();
+
+        let mut measurements = Vec::new();
+        let cheapest = find_cheapest_ssaa(
+            &params,
+            frame,
+            reference_ssaa,
+            QualityTarget {
+                psnr: f64::INFINITY,
+                ssim: 2.0,
+            },
+            |measurement| measurements.push(*measurement),
+        )
+        .unwrap();
+        // No render can be better than identical to the reference, so every factor is tried.
+        assert_eq!(cheapest, None);
+        assert_eq!(measurements.len(), 5);
+        // Closer to the reference the quality improves.
+        assert!(measurements[0].psnr < measurements[4].psnr);
+        assert!(measurements[0].ssim < measurements[4].ssim);
+
+        let cheapest = find_cheapest_ssaa(
+            &params,
+            frame,
+            reference_ssaa,
+            QualityTarget {
+                psnr: 0.0,
+                ssim: -1.0,
+            },
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(cheapest.map(|measurement| measurement.ssaa.get()), Some(1));
+    }
+}
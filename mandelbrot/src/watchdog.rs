@@ -0,0 +1,96 @@
+//! Reporting the tiles and bands of an image that were unusually slow to render.
+
+use std::io::{self, Write};
+
+use mandellib::RenderTimings;
+
+/// Writes the median time per pixel of the render and the tiles or bands that took more than `factor`
+/// times as long per pixel as the median to `output`, slowest first.
+/// Each of them is written with its position in the image and the region of the complex plane it covers,
+/// so that the region can be rendered on its own with `--real-center` and `--imag-center`.
+pub fn write_report(
+    timings: &RenderTimings,
+    factor: f64,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let median = timings.median_seconds_per_pixel();
+    writeln!(
+        output,
+        "Median time per pixel: {:.3} µs over {} tiles or bands",
+        median * 1e6,
+        timings.tiles.len()
+    )?;
+
+    let slow = timings.slow_tiles(factor);
+    if slow.is_empty() {
+        return writeln!(
+            output,
+            "No tile or band took more than {factor} times as long per pixel"
+        );
+    }
+    writeln!(
+        output,
+        "{} took more than {factor} times as long per pixel:",
+        if slow.len() == 1 {
+            "1 tile or band".to_owned()
+        } else {
+            format!("{} tiles or bands", slow.len())
+        }
+    )?;
+    for tile in slow {
+        writeln!(
+            output,
+            "  {:.1}x  {}x{} pixels at ({}, {}), centered on {}{:+}i with a size of {:e} by {:e}",
+            tile.seconds_per_pixel() / median,
+            tile.width,
+            tile.height,
+            tile.x,
+            tile.y,
+            tile.region.center_real,
+            tile.region.center_imag,
+            tile.region.real_distance,
+            tile.region.imag_distance,
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_watchdog {
+    use super::*;
+    use core::time::Duration;
+    use mandellib::{Frame, TileTime};
+
+    #[test]
+    fn check_report() {
+        let band = |x: u32, micros: u64| TileTime {
+            x,
+            y: 0,
+            width: 1,
+            height: 10,
+            region: Frame::new(f64::from(x) - 0.5, -0.25, 1.0, 2.0),
+            duration: Duration::from_micros(micros),
+        };
+        let timings = RenderTimings {
+            x_resolution: 3,
+            y_resolution: 10,
+            tiles: vec![band(0, 10), band(1, 50), band(2, 10)],
+        };
+
+        let mut output = Vec::new();
+        write_report(&timings, 4.0, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Median time per pixel: 1.000 µs over 3 tiles or bands
+1 tile or band took more than 4 times as long per pixel:
+  5.0x  1x10 pixels at (1, 0), centered on 0.5-0.25i with a size of 1e0 by 2e0
+"
+        );
+
+        let mut output = Vec::new();
+        write_report(&timings, 6.0, &mut output).unwrap();
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .ends_with("No tile or band took more than 6 times as long per pixel\n"));
+    }
+}
@@ -1,6 +1,7 @@
 use color_space::SupportedColorType;
 use criterion::{criterion_group, criterion_main, Criterion};
-use mandellib::{render, Frame, RenderParameters};
+use image::{DynamicImage, RgbImage};
+use mandellib::{parallel_rotate270, render, Frame, RenderParameters, Scheduling, Zoom};
 
 fn get_inputs(
     y_res: u32,
@@ -8,7 +9,7 @@ fn get_inputs(
     zoom: Option<f64>,
     re: Option<f64>,
     im: Option<f64>,
-    miters: Option<u32>,
+    miters: Option<u64>,
     grayscale: Option<bool>,
 ) -> (RenderParameters, Frame) {
     let aspect_ratio = 1.5;
@@ -32,10 +33,9 @@ fn get_inputs(
 
     let center_real = re.unwrap_or(-0.75);
     let center_imag = im.unwrap_or(0.0);
-    let distance_imag = 8.0 / (3.0 * 2.0_f64.powf(zoom.unwrap_or(0.0)));
-    let distance_real = aspect_ratio * distance_imag;
+    let zoom = zoom.map_or(Zoom::NONE, |level| level.try_into().unwrap());
 
-    let frame = Frame::new(center_real, center_imag, distance_real, distance_imag);
+    let frame = Frame::from_zoom(center_real, center_imag, zoom, aspect_ratio);
 
     (params, frame)
 }
@@ -45,47 +45,47 @@ fn fast(c: &mut Criterion) {
 
     let (params, frame) = get_inputs(480, None, None, None, None, None, None);
     group.bench_function(
-        &format!(
+        format!(
             "{}x{} render of full set",
             params.x_resolution, params.y_resolution
         ),
-        |b| b.iter(|| render(params, frame, false)),
+        |b| b.iter(|| render(&params, frame, false)),
     );
 
     let (params, frame) = get_inputs(720, None, None, None, None, None, None);
     group.bench_function(
-        &format!(
+        format!(
             "{}x{} render of full set",
             params.x_resolution, params.y_resolution
         ),
-        |b| b.iter(|| render(params, frame, false)),
+        |b| b.iter(|| render(&params, frame, false)),
     );
 
     let (params, frame) = get_inputs(1080, None, None, None, None, None, None);
     group.bench_function(
-        &format!(
+        format!(
             "{}x{} render of full set",
             params.x_resolution, params.y_resolution
         ),
-        |b| b.iter(|| render(params, frame, false)),
+        |b| b.iter(|| render(&params, frame, false)),
     );
 
     let (params, frame) = get_inputs(1080, None, None, None, None, None, Some(true));
     group.bench_function(
-        &format!(
+        format!(
             "{}x{} grayscale render of full set",
             params.x_resolution, params.y_resolution
         ),
-        |b| b.iter(|| render(params, frame, false)),
+        |b| b.iter(|| render(&params, frame, false)),
     );
 
     let (params, frame) = get_inputs(1080, Some(1), None, None, None, None, None);
     group.bench_function(
-        &format!(
+        format!(
             "{}x{} render  of full set without SSAA",
             params.x_resolution, params.y_resolution
         ),
-        |b| b.iter(|| render(params, frame, false)),
+        |b| b.iter(|| render(&params, frame, false)),
     );
 }
 
@@ -95,11 +95,11 @@ fn slow(c: &mut Criterion) {
 
     let (params, frame) = get_inputs(2160, None, None, None, None, None, None);
     group.bench_function(
-        &format!(
+        format!(
             "{}x{} render of full set",
             params.x_resolution, params.y_resolution
         ),
-        |b| b.iter(|| render(params, frame, false)),
+        |b| b.iter(|| render(&params, frame, false)),
     );
 
     let zoom = 12.0;
@@ -114,13 +114,86 @@ fn slow(c: &mut Criterion) {
     );
 
     group.bench_function(
-        &format!(
+        format!(
             "{}x{}, {} iterations, zoomed by 2^{}: 'Mandelsun'",
             params.x_resolution, params.y_resolution, params.max_iterations, zoom
         ),
-        |b| b.iter(|| render(params, frame, false)),
+        |b| b.iter(|| render(&params, frame, false)),
     );
 }
 
-criterion_group!(benches, fast, slow);
+fn scheduling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scheduling");
+    group.sample_size(10);
+
+    let zoom = 12.0;
+    let frames = [
+        (
+            "full set",
+            get_inputs(720, None, None, None, None, None, None),
+        ),
+        (
+            "off-axis view",
+            get_inputs(720, None, None, None, Some(0.3), None, None),
+        ),
+        (
+            "deep zoom",
+            get_inputs(
+                720,
+                None,
+                Some(zoom),
+                Some(-0.2345),
+                Some(-0.7178),
+                Some(1000),
+                None,
+            ),
+        ),
+    ];
+
+    let mut thread_counts = vec![1, 2, 4, rayon::current_num_threads()];
+    thread_counts.sort_unstable();
+    thread_counts.dedup();
+
+    for (name, (params, frame)) in frames {
+        for threads in &thread_counts {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(*threads)
+                .build()
+                .unwrap();
+            for scheduling in [Scheduling::Bands, Scheduling::Tiles, Scheduling::Interlaced] {
+                let params = RenderParameters {
+                    scheduling,
+                    ..params.clone()
+                };
+                group.bench_function(
+                    format!(
+                        "{}x{} {name} in {scheduling:?} on {threads} threads",
+                        params.x_resolution, params.y_resolution
+                    ),
+                    |b| b.iter(|| pool.install(|| render(&params, frame, false))),
+                );
+            }
+        }
+    }
+}
+
+fn rotation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rotation");
+    group.sample_size(10);
+
+    // Images are rendered rotated, so this is the layout of a 4K and an 8K render before rotation.
+    for (x_res, y_res) in [(3840, 2160), (7680, 4320)] {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(y_res, x_res, |x, y| {
+            image::Rgb([x as u8, y as u8, (x ^ y) as u8])
+        }));
+        group.bench_function(format!("{x_res}x{y_res} single-threaded rotation"), |b| {
+            b.iter(|| image.rotate270())
+        });
+        group.bench_function(format!("{x_res}x{y_res} parallel rotation"), |b| {
+            b.iter(|| parallel_rotate270(&image))
+        });
+    }
+}
+
+criterion_group!(benches, fast, slow, scheduling, rotation);
 criterion_main!(benches);
@@ -0,0 +1,104 @@
+//! The family of quadratic fractals that take the absolute value of parts of z while squaring it.
+//!
+//! All of them iterate z -> z^2 + c, written as x + iy -> (x^2 - y^2 + c_re) + i(2xy + c_im),
+//! except that the absolute value of the real part of z^2, or of some of the factors
+//! of its imaginary part, is taken. Which ones is all that tells them apart.
+
+use core::fmt;
+
+use crate::Float;
+
+/// A quadratic fractal that takes the absolute value of parts of z^2, see [`Formula::Abs`](crate::Formula::Abs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AbsVariant {
+    /// z -> (|x| + i|y|)^2 + c.
+    BurningShip,
+    /// The Mandelbrot set with the absolute value of the real part of z^2.
+    Celtic,
+    /// The Mandelbrot set with the imaginary part of z^2 replaced by -2|x|y.
+    PerpendicularMandelbrot,
+    /// The Mandelbrot set with the imaginary part of z^2 replaced by -2x|y|.
+    PerpendicularBurningShip,
+    /// The Burning Ship with the absolute value of the real part of z^2, like the Celtic.
+    Buffalo,
+}
+
+impl AbsVariant {
+    pub const ALL: [Self; 5] = [
+        Self::BurningShip,
+        Self::Celtic,
+        Self::PerpendicularMandelbrot,
+        Self::PerpendicularBurningShip,
+        Self::Buffalo,
+    ];
+
+    /// Computes the next value of z = x + iy.
+    #[inline]
+    pub(crate) fn step<F: Float>(self, x: F, y: F, c_re: F, c_im: F) -> (F, F) {
+        let real = x * x - y * y;
+        let real = match self {
+            Self::Celtic | Self::Buffalo => real.abs(),
+            Self::BurningShip | Self::PerpendicularMandelbrot | Self::PerpendicularBurningShip => {
+                real
+            }
+        };
+        let imag = match self {
+            Self::BurningShip | Self::Buffalo => F::from_f64(2.0) * (x * y).abs(),
+            Self::Celtic => F::from_f64(2.0) * x * y,
+            Self::PerpendicularMandelbrot => F::from_f64(-2.0) * x.abs() * y,
+            Self::PerpendicularBurningShip => F::from_f64(-2.0) * x * y.abs(),
+        };
+        (real + c_re, imag + c_im)
+    }
+
+    /// Returns true if the fractal is symmetric under complex conjugation.
+    /// That is the case when flipping the sign of y flips the sign of the imaginary part of the step.
+    pub(crate) fn is_conjugation_symmetric(self) -> bool {
+        match self {
+            Self::Celtic | Self::PerpendicularMandelbrot => true,
+            Self::BurningShip | Self::PerpendicularBurningShip | Self::Buffalo => false,
+        }
+    }
+}
+
+impl fmt::Display for AbsVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BurningShip => write!(f, "burning-ship"),
+            Self::Celtic => write!(f, "celtic"),
+            Self::PerpendicularMandelbrot => write!(f, "perpendicular-mandelbrot"),
+            Self::PerpendicularBurningShip => write!(f, "perpendicular-burning-ship"),
+            Self::Buffalo => write!(f, "buffalo"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_abs_variant {
+    use super::*;
+
+    #[test]
+    fn check_steps() {
+        let (x, y, c_re, c_im) = (-0.5, 0.75, 0.25, -0.125);
+        let (mandelbrot_re, mandelbrot_im) = (x * x - y * y + c_re, 2.0 * x * y + c_im);
+        assert!(mandelbrot_re < c_re && mandelbrot_im < c_im);
+
+        let flipped_re = c_re - (mandelbrot_re - c_re);
+        let flipped_im = c_im - (mandelbrot_im - c_im);
+        for (variant, expected) in [
+            (AbsVariant::BurningShip, (mandelbrot_re, flipped_im)),
+            (AbsVariant::Celtic, (flipped_re, mandelbrot_im)),
+            (
+                AbsVariant::PerpendicularMandelbrot,
+                (mandelbrot_re, mandelbrot_im),
+            ),
+            (
+                AbsVariant::PerpendicularBurningShip,
+                (mandelbrot_re, flipped_im),
+            ),
+            (AbsVariant::Buffalo, (flipped_re, flipped_im)),
+        ] {
+            assert_eq!(variant.step(x, y, c_re, c_im), expected, "{variant}");
+        }
+    }
+}
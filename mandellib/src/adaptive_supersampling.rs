@@ -0,0 +1,317 @@
+//! Deciding how many samples a pixel needs, either from how much its first few samples disagree
+//! or from how far its samples are from the set.
+//!
+//! A pixel whose first samples have nearly the same escape speed lies in a smooth part of the image,
+//! and more samples would barely change its color. A pixel that straddles the edge of the set
+//! or a thin filament has samples with very different escape speeds, and gets the full sample budget.
+
+use core::fmt;
+use core::num::ParseFloatError;
+use core::str::FromStr;
+
+/// The number of samples of a pixel whose variance decides whether it is supersampled any further.
+pub(crate) const PILOT_SAMPLES: u16 = 4;
+
+/// Pixels whose first samples have a variance in escape speed of at most this threshold are not supersampled further.
+/// Is known to be finite and non-negative.
+///
+/// Escape speeds are between 0 and 1, so a variance of 0 supersamples every pixel whose samples differ at all,
+/// while a variance of 0.25 or more never supersamples anything beyond the first samples.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{InvalidVarianceThresholdError, VarianceThreshold};
+/// assert_eq!("1e-6".parse(), Ok(VarianceThreshold::DEFAULT));
+/// assert_eq!(
+///     VarianceThreshold::try_from(-1.0),
+///     Err(InvalidVarianceThresholdError::OutOfRange)
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VarianceThreshold(f64);
+
+impl VarianceThreshold {
+    /// A threshold that gives images closer to fully supersampled ones than stopping far from the set does
+    /// in typical views, while taking about as long or less.
+    pub const DEFAULT: Self = Self(1e-6);
+
+    #[must_use]
+    pub const fn get(&self) -> f64 {
+        self.0
+    }
+}
+
+impl Default for VarianceThreshold {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl fmt::Display for VarianceThreshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<f64> for VarianceThreshold {
+    type Error = InvalidVarianceThresholdError;
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        if value.is_finite() && value >= 0.0 {
+            Ok(Self(value))
+        } else {
+            Err(InvalidVarianceThresholdError::OutOfRange)
+        }
+    }
+}
+
+impl From<VarianceThreshold> for f64 {
+    fn from(value: VarianceThreshold) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidVarianceThresholdError {
+    OutOfRange,
+    InvalidValue(ParseFloatError),
+}
+
+impl fmt::Display for InvalidVarianceThresholdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange => write!(
+                f,
+                "the variance threshold must be a finite number that is not negative"
+            ),
+            Self::InvalidValue(e) => write!(f, "the variance threshold could not be parsed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidVarianceThresholdError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidValue(e) => Some(e),
+            Self::OutOfRange => None,
+        }
+    }
+}
+
+impl FromStr for VarianceThreshold {
+    type Err = InvalidVarianceThresholdError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<f64>()
+            .map_err(InvalidVarianceThresholdError::InvalidValue)?
+            .try_into()
+    }
+}
+
+/// Supersampling of a pixel stops at the first sample whose escape speed is above this cutoff,
+/// since such samples are far from the set where the image is smooth.
+/// Is known to be between 0 and 1.
+///
+/// For low enough resolutions the region that is not supersampled begins clipping into the fractal,
+/// which a higher cutoff avoids at the cost of a slower render.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{InvalidSsaaCutoffError, SsaaCutoff};
+/// assert_eq!("0.963".parse(), Ok(SsaaCutoff::DEFAULT));
+/// assert_eq!(SsaaCutoff::try_from(1.5), Err(InvalidSsaaCutoffError::OutOfRange));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SsaaCutoff(f64);
+
+impl SsaaCutoff {
+    /// A cutoff that does not visibly clip into the set at typical image resolutions.
+    pub const DEFAULT: Self = Self(0.963);
+
+    #[must_use]
+    pub const fn get(&self) -> f64 {
+        self.0
+    }
+}
+
+impl Default for SsaaCutoff {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl fmt::Display for SsaaCutoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<f64> for SsaaCutoff {
+    type Error = InvalidSsaaCutoffError;
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        if (0.0..=1.0).contains(&value) {
+            Ok(Self(value))
+        } else {
+            Err(InvalidSsaaCutoffError::OutOfRange)
+        }
+    }
+}
+
+impl From<SsaaCutoff> for f64 {
+    fn from(value: SsaaCutoff) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidSsaaCutoffError {
+    OutOfRange,
+    InvalidValue(ParseFloatError),
+}
+
+impl fmt::Display for InvalidSsaaCutoffError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange => write!(f, "the SSAA cutoff must be between 0 and 1"),
+            Self::InvalidValue(e) => write!(f, "the SSAA cutoff could not be parsed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidSsaaCutoffError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidValue(e) => Some(e),
+            Self::OutOfRange => None,
+        }
+    }
+}
+
+impl FromStr for SsaaCutoff {
+    type Err = InvalidSsaaCutoffError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<f64>()
+            .map_err(InvalidSsaaCutoffError::InvalidValue)?
+            .try_into()
+    }
+}
+
+/// The running mean and variance of the escape speeds of the samples of a pixel.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SampleVariance {
+    count: u16,
+    mean: f64,
+    /// The sum of the squared differences from the mean.
+    squared_deviations: f64,
+}
+
+impl SampleVariance {
+    pub(crate) fn add(&mut self, escape_speed: f64) {
+        self.count += 1;
+        let deviation = escape_speed - self.mean;
+        self.mean += deviation / f64::from(self.count);
+        self.squared_deviations += deviation * (escape_speed - self.mean);
+    }
+
+    pub(crate) const fn count(&self) -> u16 {
+        self.count
+    }
+
+    /// The variance of the samples so far, or 0 if there are none.
+    pub(crate) fn get(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.squared_deviations / f64::from(self.count)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_adaptive_supersampling {
+    use super::*;
+    use crate::{render, Frame, RenderParameters};
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_variance() {
+        let mut variance = SampleVariance::default();
+        for escape_speed in [0.2, 0.4, 0.4, 0.6] {
+            variance.add(escape_speed);
+        }
+        assert_eq!(variance.count(), 4);
+        assert!((variance.get() - 0.02).abs() < 1e-15);
+    }
+
+    #[test]
+    fn check_adaptive_is_closer_to_full_supersampling() {
+        let mut params = RenderParameters::try_new(
+            90.try_into().unwrap(),
+            60.try_into().unwrap(),
+            200.try_into().unwrap(),
+            4.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+        params.adaptive_supersampling = Some(VarianceThreshold::try_from(0.0).unwrap());
+        let reference = render(&params, frame, false);
+
+        // The number of color channels that differ visibly from the reference.
+        let differences = |params: &RenderParameters| {
+            render(params, frame, false)
+                .as_bytes()
+                .iter()
+                .zip(reference.as_bytes())
+                .filter(|(a, b)| a.abs_diff(**b) > 3)
+                .count()
+        };
+
+        // The pixels that are only sampled a few times are in smooth parts of the image,
+        // so they are closer to their fully supersampled colors than with the fixed cutoff.
+        params.adaptive_supersampling = Some(VarianceThreshold::DEFAULT);
+        let adaptive = differences(&params);
+        params.adaptive_supersampling = None;
+        let cutoff = differences(&params);
+        assert!(adaptive < cutoff, "{adaptive} >= {cutoff}");
+
+        // A threshold no variance can exceed only takes the first samples of every pixel.
+        params.adaptive_supersampling = Some(VarianceThreshold::try_from(1.0).unwrap());
+        assert!(differences(&params) > adaptive);
+    }
+
+    #[test]
+    fn check_ssaa_cutoff() {
+        let mut params = RenderParameters::try_new(
+            90.try_into().unwrap(),
+            60.try_into().unwrap(),
+            200.try_into().unwrap(),
+            3.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+        let restricted = render(&params, frame, false);
+
+        // Without the cutoff the pixels far from the set are supersampled as well, and their colors change a little.
+        params.ssaa_cutoff = None;
+        let full = render(&params, frame, false);
+        assert_ne!(restricted, full);
+        // No escape speed is above the largest cutoff.
+        params.ssaa_cutoff = Some(SsaaCutoff::try_from(1.0).unwrap());
+        assert_eq!(render(&params, frame, false), full);
+
+        // Most of the overview is far from the set, and is recolored to show that it is not fully supersampled.
+        params.show_ssaa_region = true;
+        assert_eq!(render(&params, frame, false), full);
+        params.ssaa_cutoff = Some(SsaaCutoff::DEFAULT);
+        let shown = render(&params, frame, false);
+        let recolored = shown
+            .as_bytes()
+            .chunks_exact(3)
+            .zip(restricted.as_bytes().chunks_exact(3))
+            .filter(|(shown, restricted)| shown != restricted)
+            .count();
+        assert!(recolored > 90 * 60 / 2, "{recolored}");
+    }
+}
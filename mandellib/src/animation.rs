@@ -0,0 +1,422 @@
+//! Animations that move the view through a list of keyframes, e.g. a zoom into a point of the set.
+//!
+//! The view of every frame is worked out from its index alone, so any frame of an animation
+//! can be rendered on its own and comes out identical to the same frame of a full render of it.
+
+use core::fmt;
+use core::str::FromStr;
+
+use crate::{InvalidZoomError, Zoom};
+
+/// The view at a given frame of an animation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    /// The index of the frame, counted from 0.
+    pub frame: u64,
+    pub real_center: f64,
+    pub imag_center: f64,
+    pub zoom: Zoom,
+    /// The offset of the palette at the frame, see [`RenderParameters::palette_offset`](crate::RenderParameters::palette_offset).
+    pub palette_offset: f64,
+}
+
+/// The keyframes of an animation, which starts at the first keyframe and ends at the last one.
+///
+/// The zoom level changes linearly between keyframes, so the view shrinks by the same factor every frame.
+/// The center moves such that the center of the next keyframe approaches the center of the view
+/// at a steady pace on the screen, rather than racing past it when zooming in.
+/// The palette offset also changes linearly, so keyframes with offsets that differ by a few whole numbers
+/// cycle the colors of the palette through the image that many times in between them.
+///
+/// It can be written as text with one keyframe per line, consisting of its frame index,
+/// the real and imaginary parts of its center, its zoom level and optionally its palette offset,
+/// which is 0 if it is left out. Empty lines and lines starting with `#` are ignored.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{Animation, Zoom};
+/// let animation: Animation = "# Into the seahorse valley
+/// 0 -0.75 0 0
+/// 100 -0.745 0.1 20 4"
+///     .parse()
+///     .unwrap();
+/// assert_eq!(animation.frame_count(), 101);
+///
+/// let middle = animation.keyframe_at(50).unwrap();
+/// assert_eq!(middle.zoom, Zoom::try_from(10.0).unwrap());
+/// // The view has almost arrived at the center when it is zoomed in halfway.
+/// assert!((middle.real_center + 0.745).abs() < 1e-5);
+/// // The palette cycles twice on the way there.
+/// assert_eq!(middle.palette_offset, 2.0);
+///
+/// assert_eq!(animation.keyframe_at(100), Some(animation.keyframes()[1]));
+/// assert_eq!(animation.keyframe_at(101), None);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    keyframes: Vec<Keyframe>,
+}
+
+impl Animation {
+    /// # Errors
+    /// Returns an error if there are no keyframes, if the first one is not at frame 0,
+    /// or if their frames are not strictly increasing.
+    pub fn new(keyframes: Vec<Keyframe>) -> Result<Self, InvalidAnimationError> {
+        match keyframes.first() {
+            None => return Err(InvalidAnimationError::NoKeyframes),
+            Some(first) if first.frame != 0 => {
+                return Err(InvalidAnimationError::FirstFrame(first.frame))
+            }
+            Some(_) => (),
+        }
+        if let Some(pair) = keyframes
+            .windows(2)
+            .find(|pair| pair[0].frame >= pair[1].frame)
+        {
+            return Err(InvalidAnimationError::Unordered(pair[1].frame));
+        }
+        Ok(Self { keyframes })
+    }
+
+    #[must_use]
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Returns the animation with the palette offset of every frame increased by `palette_offset`,
+    /// and by a further `cycles_per_frame` for every frame since the start of the animation.
+    /// This cycles the colors of the palette at a steady rate on top of the offsets of the keyframes.
+    ///
+    /// # Errors
+    /// Returns an error if the offset or the rate is not finite.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::Animation;
+    /// let animation: Animation = "0 -0.75 0 0\n100 -0.75 0 0 1".parse().unwrap();
+    /// let cycling = animation.with_palette_cycling(0.5, 0.01).unwrap();
+    /// assert_eq!(cycling.keyframe_at(0).unwrap().palette_offset, 0.5);
+    /// assert_eq!(cycling.keyframe_at(50).unwrap().palette_offset, 1.5);
+    /// assert_eq!(cycling.keyframe_at(100).unwrap().palette_offset, 2.5);
+    /// ```
+    pub fn with_palette_cycling(
+        mut self,
+        palette_offset: f64,
+        cycles_per_frame: f64,
+    ) -> Result<Self, InvalidAnimationError> {
+        if !(palette_offset.is_finite() && cycles_per_frame.is_finite()) {
+            return Err(InvalidAnimationError::PaletteCycling);
+        }
+        // The offsets are interpolated linearly between keyframes, so adding a linear function of the frame
+        // to the keyframes adds it to every frame in between them as well.
+        for keyframe in &mut self.keyframes {
+            // Frame indices are exact in an f64 for any animation that could be rendered.
+            #[allow(clippy::cast_precision_loss)]
+            let frame = keyframe.frame as f64;
+            keyframe.palette_offset += palette_offset + cycles_per_frame * frame;
+        }
+        Ok(self)
+    }
+
+    /// The number of frames in the animation, which ends at the frame of the last keyframe.
+    #[must_use]
+    pub fn frame_count(&self) -> u64 {
+        self.keyframes.last().map_or(0, |last| last.frame + 1)
+    }
+
+    /// Returns the view at the given frame, or `None` if the animation has ended before it.
+    #[must_use]
+    pub fn keyframe_at(&self, frame: u64) -> Option<Keyframe> {
+        // The index of the first keyframe after the frame.
+        let next = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.frame <= frame);
+        let start = self.keyframes[next.checked_sub(1)?];
+        if start.frame == frame {
+            return Some(start);
+        }
+        let end = self.keyframes.get(next)?;
+
+        // Frame indices are exact in an f64 for any animation that could be rendered.
+        #[allow(clippy::cast_precision_loss)]
+        let t = (frame - start.frame) as f64 / (end.frame - start.frame) as f64;
+        let start_level = start.zoom.level();
+        let level_change = end.zoom.level() - start_level;
+        let level = start_level + t * level_change;
+        // The share of the way from the start to the end center that the view has moved,
+        // which is the share of the change in the size of the view that has happened.
+        let progress = if level_change == 0.0 {
+            t
+        } else {
+            (1.0 - (-t * level_change).exp2()) / (1.0 - (-level_change).exp2())
+        };
+        Some(Keyframe {
+            frame,
+            real_center: start.real_center + progress * (end.real_center - start.real_center),
+            imag_center: start.imag_center + progress * (end.imag_center - start.imag_center),
+            zoom: Zoom::try_from(level).expect("between the levels of two keyframes"),
+            palette_offset: start.palette_offset + t * (end.palette_offset - start.palette_offset),
+        })
+    }
+}
+
+impl fmt::Display for Animation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for keyframe in &self.keyframes {
+            write!(
+                f,
+                "{} {} {} {}",
+                keyframe.frame, keyframe.real_center, keyframe.imag_center, keyframe.zoom
+            )?;
+            if keyframe.palette_offset != 0.0 {
+                write!(f, " {}", keyframe.palette_offset)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Animation {
+    type Err = ParseAnimationError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keyframes = Vec::new();
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |kind| ParseAnimationError::new(kind, index + 1);
+
+            let values: Vec<&str> = line.split_whitespace().collect();
+            let (view, palette_offset) = match values.as_slice() {
+                [view @ .., palette_offset] if view.len() == 4 => (view, Some(*palette_offset)),
+                view => (view, None),
+            };
+            let &[frame, real_center, imag_center, zoom] = view else {
+                return Err(error(ParseAnimationErrorKind::ValueCount(values.len())));
+            };
+            let coordinate = |value: &str| {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|value| value.is_finite())
+                    .ok_or_else(|| error(ParseAnimationErrorKind::Coordinate(value.to_owned())))
+            };
+            keyframes.push(Keyframe {
+                frame: frame
+                    .parse()
+                    .map_err(|_| error(ParseAnimationErrorKind::Frame(frame.to_owned())))?,
+                real_center: coordinate(real_center)?,
+                imag_center: coordinate(imag_center)?,
+                zoom: zoom
+                    .parse()
+                    .map_err(|e| error(ParseAnimationErrorKind::Zoom(e)))?,
+                palette_offset: palette_offset.map_or(Ok(0.0), coordinate)?,
+            });
+        }
+        // Mistakes in the order of the keyframes are reported on the last line.
+        let last_line = s.lines().count();
+        Self::new(keyframes)
+            .map_err(|e| ParseAnimationError::new(ParseAnimationErrorKind::Invalid(e), last_line))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidAnimationError {
+    NoKeyframes,
+    /// The first keyframe is at the given frame instead of frame 0.
+    FirstFrame(u64),
+    /// The keyframe at the given frame comes after a keyframe at the same or a later frame.
+    Unordered(u64),
+    /// The palette offset or cycling rate given to [`Animation::with_palette_cycling`] was not finite.
+    PaletteCycling,
+}
+
+impl fmt::Display for InvalidAnimationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoKeyframes => write!(f, "the animation has no keyframes"),
+            Self::FirstFrame(frame) => write!(
+                f,
+                "the first keyframe must be at frame 0, but it is at frame {frame}"
+            ),
+            Self::Unordered(frame) => write!(
+                f,
+                "the keyframe at frame {frame} must come after the keyframes of the frames before it"
+            ),
+            Self::PaletteCycling => write!(
+                f,
+                "the palette offset and the rate that the palette cycles at must be finite"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InvalidAnimationError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseAnimationError {
+    kind: ParseAnimationErrorKind,
+    line: usize,
+}
+
+impl ParseAnimationError {
+    const fn new(kind: ParseAnimationErrorKind, line: usize) -> Self {
+        Self { kind, line }
+    }
+
+    #[must_use]
+    pub const fn kind(&self) -> &ParseAnimationErrorKind {
+        &self.kind
+    }
+
+    /// The line that the error is on, counted from 1.
+    #[must_use]
+    pub const fn line(&self) -> usize {
+        self.line
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseAnimationErrorKind {
+    /// A keyframe had the given number of values instead of four or five.
+    ValueCount(usize),
+    Frame(String),
+    Coordinate(String),
+    Zoom(InvalidZoomError),
+    Invalid(InvalidAnimationError),
+}
+
+impl fmt::Display for ParseAnimationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "on line {}: ", self.line)?;
+        match &self.kind {
+            ParseAnimationErrorKind::ValueCount(count) => write!(
+                f,
+                "expected a frame, the real and imaginary parts of the center, a zoom level and optionally a palette offset, but got {count} values"
+            ),
+            ParseAnimationErrorKind::Frame(frame) => {
+                write!(f, "\"{frame}\" is not a frame index")
+            }
+            ParseAnimationErrorKind::Coordinate(coordinate) => {
+                write!(f, "\"{coordinate}\" is not a finite number")
+            }
+            ParseAnimationErrorKind::Zoom(e) => write!(f, "{e}"),
+            ParseAnimationErrorKind::Invalid(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseAnimationError {}
+
+#[cfg(test)]
+mod test_animation {
+    use super::*;
+
+    #[test]
+    fn check_interpolation() {
+        let keyframe = |frame, real_center, imag_center, level| Keyframe {
+            frame,
+            real_center,
+            imag_center,
+            zoom: Zoom::try_from(level).unwrap(),
+            palette_offset: 0.0,
+        };
+        let keyframes = vec![
+            keyframe(0, -0.75, 0.0, 0.0),
+            keyframe(10, -0.5, 0.5, 0.0),
+            keyframe(30, -0.1, 0.9, 40.0),
+        ];
+        let animation = Animation::new(keyframes.clone()).unwrap();
+        assert_eq!(animation.frame_count(), 31);
+        for keyframe in &keyframes {
+            assert_eq!(animation.keyframe_at(keyframe.frame), Some(*keyframe));
+        }
+
+        // Without zooming the center moves linearly.
+        assert_eq!(
+            animation.keyframe_at(5),
+            Some(keyframe(5, -0.625, 0.25, 0.0))
+        );
+
+        // While zooming the distance to the end center shrinks with the view.
+        let mut last_distance = f64::INFINITY;
+        for frame in 11..30 {
+            let view = animation.keyframe_at(frame).unwrap();
+            assert!((view.zoom.level() - 2.0 * (frame - 10) as f64).abs() < 1e-12);
+            let distance = (view.real_center + 0.1).hypot(view.imag_center - 0.9);
+            let screens = distance / view.zoom.imag_distance();
+            assert!(screens < last_distance, "{frame}");
+            last_distance = screens;
+        }
+        assert_eq!(animation.keyframe_at(31), None);
+
+        // The palette cycles at a steady rate on top of the offsets of the keyframes.
+        let mut keyframes = keyframes;
+        keyframes[1].palette_offset = 2.0;
+        let animation = Animation::new(keyframes)
+            .unwrap()
+            .with_palette_cycling(0.25, 0.125)
+            .unwrap();
+        let offsets: Vec<f64> = [0, 5, 10, 20, 30]
+            .into_iter()
+            .map(|frame| animation.keyframe_at(frame).unwrap().palette_offset)
+            .collect();
+        assert_eq!(offsets, [0.25, 1.875, 3.5, 3.75, 4.0]);
+        assert_eq!(
+            animation.clone().with_palette_cycling(0.0, f64::NAN),
+            Err(InvalidAnimationError::PaletteCycling)
+        );
+    }
+
+    #[test]
+    fn check_parsing() {
+        let text = "# Keyframes\n0 -0.75 0 0\n\n  100 -0.745 0.1 20.5 -1.5\n";
+        let animation: Animation = text.parse().unwrap();
+        assert_eq!(animation.keyframes()[0].palette_offset, 0.0);
+        assert_eq!(animation.keyframes()[1].palette_offset, -1.5);
+        assert_eq!(animation.to_string().parse(), Ok(animation));
+
+        let error = |text: &str| text.parse::<Animation>().unwrap_err();
+        assert_eq!(
+            error("0 0 0 0\n5 0 0").kind(),
+            &ParseAnimationErrorKind::ValueCount(3)
+        );
+        assert_eq!(error("0 0 0 0\n5 0 0").line(), 2);
+        assert_eq!(
+            error("0 0 0 0 1 2").kind(),
+            &ParseAnimationErrorKind::ValueCount(6)
+        );
+        assert_eq!(
+            error("0 0 0 0 inf").kind(),
+            &ParseAnimationErrorKind::Coordinate("inf".to_owned())
+        );
+        assert_eq!(
+            error("-1 0 0 0").kind(),
+            &ParseAnimationErrorKind::Frame("-1".to_owned())
+        );
+        assert_eq!(
+            error("0 NaN 0 0").kind(),
+            &ParseAnimationErrorKind::Coordinate("NaN".to_owned())
+        );
+        assert!(matches!(
+            error("0 0 0 deep").kind(),
+            ParseAnimationErrorKind::Zoom(_)
+        ));
+        assert_eq!(
+            error("# nothing").kind(),
+            &ParseAnimationErrorKind::Invalid(InvalidAnimationError::NoKeyframes)
+        );
+        assert_eq!(
+            error("3 0 0 0").kind(),
+            &ParseAnimationErrorKind::Invalid(InvalidAnimationError::FirstFrame(3))
+        );
+        assert_eq!(
+            error("0 0 0 0\n8 0 0 1\n8 0 0 2").kind(),
+            &ParseAnimationErrorKind::Invalid(InvalidAnimationError::Unordered(8))
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Choosing the maximum number of iterations from the view.
+
+use core::num::{NonZeroU32, NonZeroU64};
+
+use crate::Frame;
+
+/// The number of iterations per octave of zoom depth raised to [`DEPTH_EXPONENT`].
+const ITERATIONS_PER_DEPTH: f64 = 10.0;
+
+/// How fast the number of iterations grows with the depth of the zoom.
+/// The details that appear when zooming in need more iterations the deeper they are,
+/// so the growth is faster than linear.
+const DEPTH_EXPONENT: f64 = 1.5;
+
+/// The smallest number of iterations that is chosen. Fewer iterations than this
+/// blur the outline of the set even in small images.
+const MIN_ITERATIONS: NonZeroU64 = NonZeroU64::new(100).unwrap();
+
+/// Returns a maximum number of iterations that resolves the details of the given frame
+/// when it is rendered with the given vertical resolution.
+///
+/// The number grows with the depth of the zoom, measured as the number of times the distance
+/// between adjacent pixels has been halved from 1, so zooming in or rendering a larger image
+/// both give more iterations. A full view of the set in 1080p gets 255 iterations.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{auto_max_iterations, Frame, Zoom};
+/// # use core::num::NonZeroU32;
+/// let y_resolution = NonZeroU32::new(1080).unwrap();
+/// let overview = Frame::from_zoom(-0.75, 0.0, Zoom::NONE, 16.0 / 9.0);
+/// assert_eq!(auto_max_iterations(overview, y_resolution).get(), 255);
+///
+/// let deep = Frame::from_zoom(-0.75, 0.1, Zoom::try_from(30.0).unwrap(), 16.0 / 9.0);
+/// assert!(auto_max_iterations(deep, y_resolution).get() > 2000);
+/// ```
+#[must_use]
+pub fn auto_max_iterations(frame: Frame, y_resolution: NonZeroU32) -> NonZeroU64 {
+    let pixel_size = frame.imag_distance.abs() / f64::from(y_resolution.get());
+    let depth = -pixel_size.log2();
+    if !depth.is_finite() || depth <= 0.0 {
+        return MIN_ITERATIONS;
+    }
+    // Saturates at u64::MAX for absurdly deep frames.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let iterations = (ITERATIONS_PER_DEPTH * depth.powf(DEPTH_EXPONENT)).round() as u64;
+    NonZeroU64::new(iterations).map_or(MIN_ITERATIONS, |iterations| iterations.max(MIN_ITERATIONS))
+}
+
+#[cfg(test)]
+mod test_auto_iterations {
+    use super::*;
+    use crate::Zoom;
+
+    #[test]
+    fn check_growth() {
+        let y_resolution = NonZeroU32::new(1000).unwrap();
+        let mut previous = NonZeroU64::MIN;
+        for level in [0.0, 5.0, 20.0, 45.0, 200.0] {
+            let frame = Frame::from_zoom(-0.75, 0.0, Zoom::try_from(level).unwrap(), 1.5);
+            let iterations = auto_max_iterations(frame, y_resolution);
+            assert!(iterations > previous, "{iterations} at zoom {level}");
+            previous = iterations;
+        }
+
+        // A larger image of the same view resolves smaller details.
+        let frame = Frame::from_zoom(-0.75, 0.0, Zoom::try_from(10.0).unwrap(), 1.5);
+        assert!(
+            auto_max_iterations(frame, NonZeroU32::new(4000).unwrap())
+                > auto_max_iterations(frame, y_resolution)
+        );
+
+        // Thumbnails and degenerate frames still get enough iterations to show the set.
+        for frame in [
+            Frame::new(0.0, 0.0, 3.0, 2.0),
+            Frame::new(0.0, 0.0, 0.0, 0.0),
+            Frame::new(0.0, 0.0, f64::NAN, f64::NAN),
+        ] {
+            assert_eq!(auto_max_iterations(frame, NonZeroU32::MIN), MIN_ITERATIONS);
+        }
+    }
+}
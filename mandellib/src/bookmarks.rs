@@ -0,0 +1,353 @@
+//! A library of saved views that can be annotated, tagged, searched and rendered again.
+//!
+//! Each bookmark stores the command line arguments of `mandelbrot` that render its view,
+//! the same arguments that the viewer sends to a render server, together with a name,
+//! free-text notes and tags. The library is stored as text with one field per line:
+//!
+//! ```text
+//! # Lines starting with a '#' and empty lines are ignored.
+//! bookmark Seahorse valley
+//! arguments --real-center=-0.745
+//! arguments --imag-center=0.1
+//! arguments --zoom-level=150
+//! tags seahorse spiral
+//! note The tails curl into double spirals.
+//! note Looks best with at least 2000 iterations.
+//! ```
+//!
+//! Several arguments on the same line are separated by tabs, and tags are separated by spaces.
+//! Every `note` line adds one line to the notes of the bookmark above it.
+
+use core::fmt;
+use core::str::FromStr;
+
+/// The file that the viewer and `mandelbrot bookmarks` read and write bookmarks in by default.
+pub const DEFAULT_BOOKMARKS_FILE: &str = "mandelbrot_bookmarks.txt";
+
+/// A saved view along with the notes and tags that the user has given it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bookmark {
+    pub name: String,
+    /// The command line arguments of `mandelbrot` that render the view, e.g. "--zoom-level=3".
+    pub arguments: Vec<String>,
+    /// Free text, possibly spanning several lines.
+    pub notes: String,
+    /// Single words that group bookmarks together.
+    pub tags: Vec<String>,
+}
+
+impl Bookmark {
+    /// Splits text into tags at whitespace and commas, dropping empty and repeated tags.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::Bookmark;
+    /// assert_eq!(Bookmark::parse_tags("deep, spiral  Deep"), ["deep", "spiral"]);
+    /// ```
+    #[must_use]
+    pub fn parse_tags(text: &str) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in text.split(|c: char| c.is_whitespace() || c == ',') {
+            if !tag.is_empty() && !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.to_owned());
+            }
+        }
+        tags
+    }
+
+    /// Returns whether the bookmark has the given tag, ignoring case.
+    #[must_use]
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag.trim()))
+    }
+
+    /// Returns whether every word of the query occurs in the name, notes or tags of the bookmark,
+    /// ignoring case. An empty query matches every bookmark.
+    #[must_use]
+    pub fn matches(&self, query: &str) -> bool {
+        let name = self.name.to_lowercase();
+        let notes = self.notes.to_lowercase();
+        query.split_whitespace().all(|word| {
+            let word = word.to_lowercase();
+            name.contains(&word)
+                || notes.contains(&word)
+                || self
+                    .tags
+                    .iter()
+                    .any(|tag| tag.to_lowercase().contains(&word))
+        })
+    }
+
+    /// Returns the value of the argument with the given long name if the bookmark stores it,
+    /// e.g. `argument("zoom-level")` returns "3" for "--zoom-level=3".
+    #[must_use]
+    pub fn argument(&self, name: &str) -> Option<&str> {
+        self.arguments.iter().find_map(|argument| {
+            argument
+                .strip_prefix("--")?
+                .strip_prefix(name)?
+                .strip_prefix('=')
+        })
+    }
+}
+
+/// A collection of bookmarks in the order that they were added.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BookmarkLibrary {
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl BookmarkLibrary {
+    /// Returns the bookmarks that have the given tag.
+    pub fn with_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Bookmark> {
+        self.bookmarks.iter().filter(move |b| b.has_tag(tag))
+    }
+
+    /// Returns the bookmarks that match the query, see [`Bookmark::matches`].
+    pub fn search<'a>(&'a self, query: &'a str) -> impl Iterator<Item = &'a Bookmark> {
+        self.bookmarks.iter().filter(move |b| b.matches(query))
+    }
+
+    /// Returns every tag that is used by a bookmark once, sorted alphabetically and in lowercase.
+    #[must_use]
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .bookmarks
+            .iter()
+            .flat_map(|b| b.tags.iter().map(|tag| tag.to_lowercase()))
+            .collect();
+        tags.sort_unstable();
+        tags.dedup();
+        tags
+    }
+}
+
+impl FromStr for BookmarkLibrary {
+    type Err = ParseBookmarksError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bookmarks: Vec<Bookmark> = Vec::new();
+        for (index, line) in s.lines().enumerate() {
+            let line_number = index + 1;
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let (field, value) = trimmed.split_once([' ', '\t']).unwrap_or((trimmed, ""));
+            if field == "bookmark" {
+                let name = value.trim();
+                if name.is_empty() {
+                    return Err(ParseBookmarksError::new(
+                        ParseBookmarksErrorKind::EmptyName,
+                        line_number,
+                    ));
+                }
+                bookmarks.push(Bookmark {
+                    name: name.to_owned(),
+                    ..Bookmark::default()
+                });
+                continue;
+            }
+
+            let Some(bookmark) = bookmarks.last_mut() else {
+                return Err(ParseBookmarksError::new(
+                    ParseBookmarksErrorKind::OutsideBookmark,
+                    line_number,
+                ));
+            };
+            match field {
+                "arguments" => bookmark.arguments.extend(
+                    value
+                        .trim_end_matches('\r')
+                        .split('\t')
+                        .filter(|argument| !argument.is_empty())
+                        .map(str::to_owned),
+                ),
+                "tags" => bookmark.tags.extend(Bookmark::parse_tags(value)),
+                "note" => {
+                    if !bookmark.notes.is_empty() {
+                        bookmark.notes.push('\n');
+                    }
+                    bookmark.notes.push_str(value.trim_end_matches('\r'));
+                }
+                _ => {
+                    return Err(ParseBookmarksError::new(
+                        ParseBookmarksErrorKind::UnknownField(field.to_owned()),
+                        line_number,
+                    ))
+                }
+            }
+        }
+        Ok(Self { bookmarks })
+    }
+}
+
+impl fmt::Display for BookmarkLibrary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, bookmark) in self.bookmarks.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            // Names can not span several lines in the file.
+            writeln!(f, "bookmark {}", bookmark.name.replace(['\r', '\n'], " "))?;
+            if !bookmark.arguments.is_empty() {
+                writeln!(f, "arguments {}", bookmark.arguments.join("\t"))?;
+            }
+            if !bookmark.tags.is_empty() {
+                writeln!(f, "tags {}", bookmark.tags.join(" "))?;
+            }
+            if !bookmark.notes.is_empty() {
+                for line in bookmark.notes.lines() {
+                    writeln!(f, "note {line}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An error that occured while parsing a [`BookmarkLibrary`].
+/// Contains the line in the input, starting from 1, where the error was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBookmarksError {
+    kind: ParseBookmarksErrorKind,
+    line: usize,
+}
+
+impl ParseBookmarksError {
+    const fn new(kind: ParseBookmarksErrorKind, line: usize) -> Self {
+        Self { kind, line }
+    }
+
+    #[must_use]
+    pub const fn kind(&self) -> &ParseBookmarksErrorKind {
+        &self.kind
+    }
+
+    /// The line in the input, starting from 1, where the error was found.
+    #[must_use]
+    pub const fn line(&self) -> usize {
+        self.line
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseBookmarksErrorKind {
+    /// A bookmark was started without a name.
+    EmptyName,
+    /// A field was given before the first bookmark.
+    OutsideBookmark,
+    UnknownField(String),
+}
+
+impl fmt::Display for ParseBookmarksError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "on line {}: ", self.line)?;
+        match &self.kind {
+            ParseBookmarksErrorKind::EmptyName => write!(f, "the bookmark has no name"),
+            ParseBookmarksErrorKind::OutsideBookmark => {
+                write!(f, "expected \"bookmark\" followed by a name")
+            }
+            ParseBookmarksErrorKind::UnknownField(field) => {
+                write!(f, "\"{field}\" is not a field of a bookmark")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseBookmarksError {}
+
+#[cfg(test)]
+mod test_bookmarks {
+    use super::*;
+
+    const LIBRARY: &str = "# My favourite places
+bookmark Seahorse valley
+arguments --real-center=-0.745\t--imag-center=0.1\t--zoom-level=150
+tags seahorse Spiral
+note The tails curl into double spirals.
+note Looks best with at least 2000 iterations.
+
+bookmark Elephant valley
+arguments --real-center=0.275\t--zoom-level=40
+tags elephant, spiral
+";
+
+    #[test]
+    fn check_parsing() {
+        let library: BookmarkLibrary = LIBRARY.parse().unwrap();
+        assert_eq!(library.bookmarks.len(), 2);
+
+        let seahorse = &library.bookmarks[0];
+        assert_eq!(seahorse.name, "Seahorse valley");
+        assert_eq!(seahorse.arguments.len(), 3);
+        assert_eq!(seahorse.argument("real-center"), Some("-0.745"));
+        assert_eq!(seahorse.argument("zoom-level"), Some("150"));
+        assert_eq!(seahorse.argument("zoom"), None);
+        assert_eq!(seahorse.tags, ["seahorse", "Spiral"]);
+        assert_eq!(
+            seahorse.notes,
+            "The tails curl into double spirals.\nLooks best with at least 2000 iterations."
+        );
+        assert_eq!(library.bookmarks[1].tags, ["elephant", "spiral"]);
+
+        assert_eq!(library.to_string().parse::<BookmarkLibrary>(), Ok(library));
+        assert_eq!("".parse(), Ok(BookmarkLibrary::default()));
+    }
+
+    #[test]
+    fn check_filtering() {
+        let library: BookmarkLibrary = LIBRARY.parse().unwrap();
+        let names = |bookmarks: Vec<&Bookmark>| -> Vec<String> {
+            bookmarks.into_iter().map(|b| b.name.clone()).collect()
+        };
+
+        assert_eq!(
+            names(library.with_tag("SPIRAL").collect()),
+            ["Seahorse valley", "Elephant valley"]
+        );
+        assert_eq!(
+            names(library.with_tag("elephant").collect()),
+            ["Elephant valley"]
+        );
+        assert!(library.with_tag("spir").next().is_none());
+
+        assert_eq!(library.search("").count(), 2);
+        assert_eq!(
+            names(library.search("valley DOUBLE").collect()),
+            ["Seahorse valley"]
+        );
+        assert_eq!(
+            names(library.search("eleph").collect()),
+            ["Elephant valley"]
+        );
+        assert!(library.search("seahorse elephant").next().is_none());
+
+        assert_eq!(library.tags(), ["elephant", "seahorse", "spiral"]);
+    }
+
+    #[test]
+    fn check_errors() {
+        let error = "tags deep".parse::<BookmarkLibrary>().unwrap_err();
+        assert_eq!(error.kind(), &ParseBookmarksErrorKind::OutsideBookmark);
+        assert_eq!(error.line(), 1);
+
+        let error = "bookmark A\n\nbookmark  "
+            .parse::<BookmarkLibrary>()
+            .unwrap_err();
+        assert_eq!(error.kind(), &ParseBookmarksErrorKind::EmptyName);
+        assert_eq!(error.line(), 3);
+
+        let error = "bookmark A\nnotes x"
+            .parse::<BookmarkLibrary>()
+            .unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &ParseBookmarksErrorKind::UnknownField("notes".to_owned())
+        );
+        assert_eq!(
+            error.to_string(),
+            "on line 2: \"notes\" is not a field of a bookmark"
+        );
+    }
+}
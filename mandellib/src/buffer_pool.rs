@@ -0,0 +1,99 @@
+//! Reusing the temporary buffers that the render threads need for every band,
+//! instead of allocating and freeing one for each of the thousands of bands of an image.
+//!
+//! With many threads the allocator is otherwise contended by all of them at once,
+//! which makes the render slower and its time vary more from run to run.
+
+use core::cell::RefCell;
+use std::collections::HashMap;
+use std::thread::LocalKey;
+
+/// The largest number of buffers of each length that a pool keeps.
+/// A thread only ever needs one at a time, but the segments of a band can have a few different lengths.
+const MAX_BUFFERS_PER_LENGTH: usize = 2;
+
+/// The largest number of different lengths that a pool keeps buffers of. Pools that would keep more
+/// are emptied, so that renders at many different resolutions do not hold on to the memory of all of them.
+const MAX_LENGTHS: usize = 8;
+
+/// Buffers that are no longer in use, kept by their lengths.
+pub(crate) struct BufferPool<T> {
+    buffers: HashMap<usize, Vec<Vec<T>>>,
+}
+
+impl<T: Clone> BufferPool<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            buffers: HashMap::new(),
+        }
+    }
+
+    /// Returns a buffer of the given length that is filled with `value`, reusing one from the pool if it has one.
+    fn take(&mut self, length: usize, value: T) -> Vec<T> {
+        match self.buffers.get_mut(&length).and_then(Vec::pop) {
+            Some(mut buffer) => {
+                buffer.fill(value);
+                buffer
+            }
+            None => vec![value; length],
+        }
+    }
+
+    /// Keeps the buffer for later, unless the pool is full.
+    fn give_back(&mut self, buffer: Vec<T>) {
+        if !self.buffers.contains_key(&buffer.len()) && self.buffers.len() >= MAX_LENGTHS {
+            self.buffers.clear();
+        }
+        let buffers = self.buffers.entry(buffer.len()).or_default();
+        if buffers.len() < MAX_BUFFERS_PER_LENGTH {
+            buffers.push(buffer);
+        }
+    }
+
+    /// Calls `f` with a buffer of the given length that is filled with `value`,
+    /// taken from the pool of the current thread, and returns the buffer to the pool afterwards.
+    pub(crate) fn with_buffer<R>(
+        pool: &'static LocalKey<RefCell<Self>>,
+        length: usize,
+        value: T,
+        f: impl FnOnce(&mut [T]) -> R,
+    ) -> R {
+        // The pool is not borrowed while `f` runs, so `f` can take buffers of its own from it.
+        let mut buffer = pool.with_borrow_mut(|pool| pool.take(length, value));
+        let result = f(&mut buffer);
+        pool.with_borrow_mut(|pool| pool.give_back(buffer));
+        result
+    }
+}
+
+#[cfg(test)]
+mod test_buffer_pool {
+    use super::*;
+
+    thread_local! {
+        static POOL: RefCell<BufferPool<u32>> = RefCell::new(BufferPool::new());
+    }
+
+    #[test]
+    fn check_reuse() {
+        let first = BufferPool::with_buffer(&POOL, 100, 0, |buffer| {
+            buffer[3] = 7;
+            buffer.as_ptr()
+        });
+        // The same memory is handed out again, filled anew.
+        let second = BufferPool::with_buffer(&POOL, 100, 1, |buffer| {
+            assert!(buffer.iter().all(|&value| value == 1));
+            // Nested buffers of the same length are different ones.
+            let nested = BufferPool::with_buffer(&POOL, 100, 2, |nested| nested.as_ptr());
+            assert_ne!(nested, buffer.as_ptr());
+            buffer.as_ptr()
+        });
+        assert_eq!(first, second);
+
+        // Too many different lengths empty the pool.
+        for length in 0..=MAX_LENGTHS {
+            BufferPool::with_buffer(&POOL, length, 0, |_| ());
+        }
+        POOL.with_borrow(|pool| assert!(pool.buffers.len() <= MAX_LENGTHS));
+    }
+}
@@ -0,0 +1,146 @@
+//! Checks for whether a point lies in one of the larger hyperbolic components of period 3 or 4
+//! of the Mandelbrot set, so that it can be labeled as inside the set without being iterated.
+//!
+//! Unlike the main cardioid and period 2 bulb these components have no simple closed form,
+//! so each one is approximated by a disc around its center that lies inside it.
+//! The radii were found by following rays out from the center until the multiplier
+//! of the attracting cycle reached 1, and then shrinking the shortest ray by 10%.
+
+use core::fmt;
+use core::str::FromStr;
+
+/// Discs given as `(center_real, center_imag, radius)` that lie inside hyperbolic components of period 3.
+/// Only the components in the upper half plane are listed, the rest are their mirror images.
+const PERIOD_3_DISCS: [(f64, f64, f64); 2] = [
+    // The 1/3 bulb of the main cardioid.
+    (-0.122_561_166_876_653_6, 0.744_861_766_619_744_2, 0.0828),
+    // The cardioid of the largest minibrot on the real axis.
+    (-1.754_877_666_246_692_7, 0.0, 0.004_58),
+];
+
+/// Like [`PERIOD_3_DISCS`], but for components of period 4.
+const PERIOD_4_DISCS: [(f64, f64, f64); 4] = [
+    // The 1/4 bulb of the main cardioid.
+    (0.282_271_390_766_913_9, 0.530_060_617_578_525_3, 0.0381),
+    // The 1/2 bulb of the period 2 bulb.
+    (-1.310_702_641_336_832_8, 0.0, 0.0516),
+    // The cardioids of two minibrots.
+    (-1.940_799_806_529_484_7, 0.0, 0.000_233),
+    (-0.156_520_166_833_755_08, 1.032_247_108_922_831_8, 0.001_98),
+];
+
+/// Which hyperbolic components of the Mandelbrot set points are checked against before they are iterated.
+/// Points in the checked components are inside the set, so checking more components
+/// saves the full iteration budget on those points at the cost of a few more comparisons for all other points.
+///
+/// The main cardioid and period 2 bulb are always checked.
+/// Only has an effect on the Mandelbrot set with the exponent 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BulbChecks {
+    /// Only check the main cardioid and period 2 bulb.
+    #[default]
+    Period2,
+    /// Also check the largest components of period 3.
+    Period3,
+    /// Also check the largest components of period 3 and 4.
+    Period4,
+}
+
+impl BulbChecks {
+    /// All the bulb checks, in order of how many components they check.
+    pub const ALL: [Self; 3] = [Self::Period2, Self::Period3, Self::Period4];
+
+    /// Returns true if c lies in one of the checked components of period 3 or more.
+    pub(crate) fn contains(self, c_re: f64, c_im: f64) -> bool {
+        let in_any = |discs: &[(f64, f64, f64)]| {
+            discs.iter().any(|&(center_re, center_im, radius)| {
+                let (d_re, d_im) = (c_re - center_re, c_im.abs() - center_im);
+                d_re * d_re + d_im * d_im <= radius * radius
+            })
+        };
+        match self {
+            Self::Period2 => false,
+            Self::Period3 => in_any(&PERIOD_3_DISCS),
+            Self::Period4 => in_any(&PERIOD_3_DISCS) || in_any(&PERIOD_4_DISCS),
+        }
+    }
+}
+
+impl fmt::Display for BulbChecks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Period2 => write!(f, "2"),
+            Self::Period3 => write!(f, "3"),
+            Self::Period4 => write!(f, "4"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBulbChecksError(String);
+
+impl fmt::Display for ParseBulbChecksError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "can not check bulbs up to period \"{}\", expected 2, 3 or 4",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseBulbChecksError {}
+
+impl FromStr for BulbChecks {
+    type Err = ParseBulbChecksError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "2" => Ok(Self::Period2),
+            "3" => Ok(Self::Period3),
+            "4" => Ok(Self::Period4),
+            _ => Err(ParseBulbChecksError(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_bulbs {
+    use super::*;
+    use crate::{Complex, Exponent};
+    use core::num::NonZeroU64;
+
+    #[test]
+    fn check_discs_are_inside_the_set() {
+        const MAXITERS: NonZeroU64 = NonZeroU64::new(100_000).unwrap();
+        for (center_re, center_im, radius) in PERIOD_3_DISCS.into_iter().chain(PERIOD_4_DISCS) {
+            let center = Complex::new(center_re, center_im);
+            for step in 0..64 {
+                let angle = f64::from(step) * core::f64::consts::TAU / 64.0;
+                // Just inside the edge, so that rounding does not put the point outside the disc.
+                let c = center
+                    + Complex::new(angle.cos(), angle.sin()) * Complex::from(0.999_999 * radius);
+                assert!(BulbChecks::Period4.contains(c.re, c.im));
+                assert!(BulbChecks::Period4.contains(c.re, -c.im));
+                assert_eq!(
+                    crate::iterate(c.re, c.im, Exponent::TWO, MAXITERS).0,
+                    MAXITERS.get(),
+                    "{c:?} at the edge of a disc escapes"
+                );
+            }
+        }
+
+        // The 1/4 bulb is only checked when asked for.
+        assert!(!BulbChecks::Period3.contains(0.282, -0.53));
+        assert!(BulbChecks::Period4.contains(0.282, -0.53));
+        assert!(!BulbChecks::Period2.contains(-0.122, 0.745));
+        assert!(!BulbChecks::Period4.contains(0.0, 1.0));
+    }
+
+    #[test]
+    fn check_parsing() {
+        for checks in BulbChecks::ALL {
+            assert_eq!(checks.to_string().parse(), Ok(checks));
+        }
+        assert!("5".parse::<BulbChecks>().is_err());
+    }
+}
@@ -0,0 +1,245 @@
+//! Separating an image into one grayscale image per channel, for compositing in image editors.
+//!
+//! Besides the color channels of the image, the escape data that it was colored from can be exported,
+//! so that an editor can color or mask the image by it.
+
+use core::fmt;
+use core::str::FromStr;
+
+use image::{DynamicImage, GenericImageView, GrayImage, ImageBuffer, Luma};
+
+use crate::EscapeBuffer;
+
+/// A channel of an image, or of the escape data that it was colored from, see [`separate_channels`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageChannel {
+    Red,
+    Green,
+    Blue,
+    /// The opacity of the image, which is fully opaque unless it has an alpha channel.
+    Alpha,
+    /// The mean escape speed of the samples of every pixel that escaped, from black close to the set
+    /// to white far from it, as a 16 bit image. Pixels whose samples are all inside the set are black.
+    EscapeSpeed,
+    /// The share of the samples of every pixel that are inside the set, from black outside it to white inside it.
+    Interior,
+}
+
+impl ImageChannel {
+    pub const ALL: [Self; 6] = [
+        Self::Red,
+        Self::Green,
+        Self::Blue,
+        Self::Alpha,
+        Self::EscapeSpeed,
+        Self::Interior,
+    ];
+}
+
+impl fmt::Display for ImageChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Red => write!(f, "red"),
+            Self::Green => write!(f, "green"),
+            Self::Blue => write!(f, "blue"),
+            Self::Alpha => write!(f, "alpha"),
+            Self::EscapeSpeed => write!(f, "escape"),
+            Self::Interior => write!(f, "interior"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseImageChannelError(String);
+
+impl fmt::Display for ParseImageChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown channel \"{}\", expected \"red\", \"green\", \"blue\", \"alpha\", \"escape\" or \"interior\"",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseImageChannelError {}
+
+impl FromStr for ImageChannel {
+    type Err = ParseImageChannelError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "red" | "r" => Ok(Self::Red),
+            "green" | "g" => Ok(Self::Green),
+            "blue" | "b" => Ok(Self::Blue),
+            "alpha" | "a" => Ok(Self::Alpha),
+            "escape" | "escape-speed" => Ok(Self::EscapeSpeed),
+            "interior" => Ok(Self::Interior),
+            _ => Err(ParseImageChannelError(s.to_owned())),
+        }
+    }
+}
+
+/// Returns a grayscale image of each of the given channels, in the same order,
+/// of an image that was colored from the escape data in the buffer, e.g. by [`colorize`](crate::colorize).
+/// The color channels of grayscale images are all the same.
+///
+/// # Panics
+/// Panics if the image and the buffer do not have the same size.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{colorize, compute, separate_channels, Coloring, Frame, ImageChannel, RenderParameters};
+/// # use color_space::SupportedColorType;
+/// let params = RenderParameters::try_new(
+///     30.try_into().unwrap(),
+///     20.try_into().unwrap(),
+///     100.try_into().unwrap(),
+///     2.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// let buffer = compute(&params, Frame::new(-0.75, 0.0, 3.0, 2.0));
+/// let image = colorize(&buffer, &Coloring::from(&params));
+/// let channels = separate_channels(&buffer, &image, &[ImageChannel::Green, ImageChannel::EscapeSpeed]);
+/// assert_eq!(channels[0].to_luma8().get_pixel(0, 0).0[0], image.to_rgb8().get_pixel(0, 0).0[1]);
+/// assert!(channels[1].as_luma16().is_some());
+/// ```
+#[must_use]
+pub fn separate_channels(
+    buffer: &EscapeBuffer,
+    image: &DynamicImage,
+    channels: &[ImageChannel],
+) -> Vec<DynamicImage> {
+    assert_eq!(
+        (buffer.width(), buffer.height()),
+        (image.width() as usize, image.height() as usize),
+        "the image must have the size of the escape data it was colored from"
+    );
+    let (width, height) = image.dimensions();
+    let rgba = channels
+        .iter()
+        .any(|channel| {
+            matches!(
+                channel,
+                ImageChannel::Red | ImageChannel::Green | ImageChannel::Blue | ImageChannel::Alpha
+            )
+        })
+        .then(|| image.to_rgba8());
+    let rgba_channel = |index: usize| {
+        let rgba = rgba.as_ref().expect("the color channels were converted");
+        DynamicImage::ImageLuma8(GrayImage::from_fn(width, height, |x, y| {
+            Luma([rgba.get_pixel(x, y).0[index]])
+        }))
+    };
+
+    channels
+        .iter()
+        .map(|channel| match channel {
+            ImageChannel::Red => rgba_channel(0),
+            ImageChannel::Green => rgba_channel(1),
+            ImageChannel::Blue => rgba_channel(2),
+            ImageChannel::Alpha => rgba_channel(3),
+            ImageChannel::EscapeSpeed => {
+                // The escape speeds are clamped to [0, 1] first, so they fit in a u16.
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let levels: Vec<u16> = buffer
+                    .mean_escape_speeds()
+                    .into_iter()
+                    .map(|speed| {
+                        (speed.unwrap_or(0.0).clamp(0.0, 1.0) * f64::from(u16::MAX)).round() as u16
+                    })
+                    .collect();
+                DynamicImage::ImageLuma16(
+                    ImageBuffer::from_raw(width, height, levels)
+                        .expect("there is an escape speed for every pixel"),
+                )
+            }
+            ImageChannel::Interior => {
+                // The share is between 0 and 1, so the level fits in a u8.
+                #[allow(
+                    clippy::cast_possible_truncation,
+                    clippy::cast_sign_loss,
+                    clippy::cast_precision_loss
+                )]
+                let levels: Vec<u8> = buffer
+                    .pixels()
+                    .map(|samples| {
+                        let inside = samples.iter().filter(|sample| sample.is_inside()).count();
+                        (inside as f64 / samples.len() as f64 * f64::from(u8::MAX)).round() as u8
+                    })
+                    .collect();
+                DynamicImage::ImageLuma8(
+                    GrayImage::from_raw(width, height, levels)
+                        .expect("there is a share for every pixel"),
+                )
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test_channels {
+    use super::*;
+    use crate::{colorize, compute, Coloring, EscapeSample, Frame, RenderParameters};
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_parsing() {
+        for channel in ImageChannel::ALL {
+            assert_eq!(channel.to_string().parse(), Ok(channel));
+        }
+        assert_eq!(" R".parse(), Ok(ImageChannel::Red));
+        assert!("angle".parse::<ImageChannel>().is_err());
+    }
+
+    #[test]
+    fn check_channels() {
+        let mut params = RenderParameters::try_new(
+            30.try_into().unwrap(),
+            20.try_into().unwrap(),
+            100.try_into().unwrap(),
+            2.try_into().unwrap(),
+            SupportedColorType::Rgba8,
+        )
+        .unwrap();
+        let buffer = compute(&params, Frame::new(-0.75, 0.0, 3.0, 2.0));
+        let image = colorize(&buffer, &Coloring::from(&params));
+        let channels = separate_channels(&buffer, &image, &ImageChannel::ALL);
+        let rgba = image.to_rgba8();
+        let escape = channels[4].as_luma16().unwrap();
+        let interior = channels[5].as_luma8().unwrap();
+        for (x, y, pixel) in rgba.enumerate_pixels() {
+            for (index, channel) in channels[..4].iter().enumerate() {
+                assert_eq!(
+                    channel.as_luma8().unwrap().get_pixel(x, y).0[0],
+                    pixel.0[index]
+                );
+            }
+            let samples = buffer.pixel(x as usize, y as usize);
+            match interior.get_pixel(x, y).0[0] {
+                u8::MAX => assert!(samples.iter().all(EscapeSample::is_inside)),
+                0 => assert!(escape.get_pixel(x, y).0[0] > 0),
+                _ => {}
+            }
+        }
+        assert!(interior.pixels().any(|level| level.0[0] == u8::MAX));
+        assert!(escape.pixels().any(|level| level.0[0] == 0));
+
+        // The color channels of grayscale images are the same.
+        params.color_type = SupportedColorType::L8;
+        let image = colorize(&buffer, &Coloring::from(&params));
+        let channels = separate_channels(
+            &buffer,
+            &image,
+            &[ImageChannel::Red, ImageChannel::Blue, ImageChannel::Alpha],
+        );
+        assert_eq!(channels[0], image);
+        assert_eq!(channels[1], image);
+        assert!(channels[2]
+            .as_luma8()
+            .unwrap()
+            .pixels()
+            .all(|level| level.0[0] == u8::MAX));
+    }
+}
@@ -0,0 +1,152 @@
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+/// A minimal complex number type, used by formulas that
+/// can not be written in terms of separate real and imaginary parts.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub const ZERO: Self = Self::new(0.0, 0.0);
+    pub const I: Self = Self::new(0.0, 1.0);
+
+    #[must_use]
+    pub const fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    #[must_use]
+    pub fn mag_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    #[must_use]
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    #[must_use]
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    #[must_use]
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    #[must_use]
+    pub fn exp(self) -> Self {
+        let (sin, cos) = self.im.sin_cos();
+        let r = self.re.exp();
+        Self::new(r * cos, r * sin)
+    }
+
+    #[must_use]
+    pub fn ln(self) -> Self {
+        Self::new(self.abs().ln(), self.arg())
+    }
+
+    #[must_use]
+    pub fn sqrt(self) -> Self {
+        let r = self.abs().sqrt();
+        let (sin, cos) = (0.5 * self.arg()).sin_cos();
+        Self::new(r * cos, r * sin)
+    }
+
+    #[must_use]
+    pub fn sin(self) -> Self {
+        Self::new(
+            self.re.sin() * self.im.cosh(),
+            self.re.cos() * self.im.sinh(),
+        )
+    }
+
+    #[must_use]
+    pub fn cos(self) -> Self {
+        Self::new(
+            self.re.cos() * self.im.cosh(),
+            -self.re.sin() * self.im.sinh(),
+        )
+    }
+
+    /// Raises the number to an integer power by repeated squaring.
+    #[must_use]
+    pub fn powi(self, exponent: i32) -> Self {
+        let mut result = Self::new(1.0, 0.0);
+        let mut base = self;
+        let mut n = exponent.unsigned_abs();
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            n >>= 1;
+        }
+        if exponent < 0 {
+            Self::new(1.0, 0.0) / result
+        } else {
+            result
+        }
+    }
+
+    /// Raises the number to a complex power using the principal branch of the logarithm.
+    #[must_use]
+    pub fn powc(self, exponent: Self) -> Self {
+        if self == Self::ZERO {
+            Self::ZERO
+        } else {
+            (exponent * self.ln()).exp()
+        }
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Self {
+        Self::new(re, 0.0)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self::Output {
+        let denominator = rhs.mag_sqr();
+        Self::new(
+            (self.re * rhs.re + self.im * rhs.im) / denominator,
+            (self.im * rhs.re - self.re * rhs.im) / denominator,
+        )
+    }
+}
+
+impl Neg for Complex {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Self::new(-self.re, -self.im)
+    }
+}
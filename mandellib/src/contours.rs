@@ -0,0 +1,776 @@
+//! Contour lines of the smoothed iteration count, i.e. the curves along which points
+//! escape after the same, fractional, number of iterations.
+//! They are traced with marching squares over a grid of samples taken at the centers of the pixels,
+//! and can be drawn over a rendered image or written as an SVG file.
+//! Since the potential of a point only depends on its smoothed iteration count,
+//! the equipotential lines of the set are traced the same way.
+//!
+//! The contour at the maximum number of iterations traces the boundary of the set,
+//! which gives scalable line art of it when it is simplified and written as an SVG file.
+
+use core::fmt::{self, Write};
+use core::num::NonZeroU64;
+use std::collections::{BTreeMap, HashMap};
+
+use image::{DynamicImage, GenericImage, Rgba};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    iterations_to_f64, potential_in_precision, Escape, Frame, RenderParameters, Smoothing,
+};
+
+/// The largest number of levels that [`EscapeSpeedField::contour_levels`] returns.
+/// Every level is traced over the whole image, and more lines than this would cover most images anyway.
+pub const MAX_CONTOUR_LEVELS: u64 = 10_000;
+
+/// A straight line between two points in pixel coordinates.
+pub type Segment = [(f64, f64); 2];
+
+/// The contour line of a single level of the smoothed iteration count, or of the potential.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contour {
+    /// The smoothed iteration count, or the potential, along the line.
+    pub level: f64,
+    /// The line as unordered segments between points in pixel coordinates,
+    /// measured from the top left corner of the image.
+    pub segments: Vec<Segment>,
+}
+
+/// Traces the contour lines of the given levels of the smoothed iteration count
+/// in the image described by the render parameters and region.
+/// Points inside the set count as having been iterated the maximum number of iterations,
+/// so the contour at that level separates them from the points that escape.
+/// Levels without any contour line in the image are left out of the result,
+/// and the rest are returned in increasing order.
+///
+/// Computes an [`EscapeSpeedField`] of the image, which should be done once and kept instead
+/// if several sets of contour lines are traced over the same image.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{contour_lines, Frame, RenderParameters};
+/// # use color_space::SupportedColorType;
+/// let params = RenderParameters::try_new(
+///     60.try_into().unwrap(),
+///     40.try_into().unwrap(),
+///     100.try_into().unwrap(),
+///     1.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// let contours = contour_lines(&params, Frame::new(-0.75, 0.0, 3.0, 2.0), &[5.0, 10.0]);
+/// assert_eq!(contours.len(), 2);
+/// assert!(contours.iter().all(|contour| !contour.segments.is_empty()));
+/// ```
+#[must_use]
+pub fn contour_lines(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    levels: &[f64],
+) -> Vec<Contour> {
+    EscapeSpeedField::new(render_parameters, render_region).contour_lines(levels)
+}
+
+/// The escape speeds at the centers of the pixels of an image, kept after they are computed
+/// so that any number of contour lines can be traced over the image without iterating its points again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EscapeSpeedField {
+    width: usize,
+    height: usize,
+    /// The smoothed iteration count at the center of every pixel, row by row from the top left.
+    /// Points inside the set count as having been iterated the maximum number of iterations.
+    smoothed_iterations: Vec<f64>,
+    max_iterations: f64,
+    /// How much the magnitude of z is raised to by each iteration, which relates
+    /// the smoothed iteration count to the potential.
+    degree: f64,
+    smoothing_offset: f64,
+}
+
+impl EscapeSpeedField {
+    /// Computes the escape speed at the center of every pixel of the image described
+    /// by the render parameters and region.
+    /// The iteration counts are always smoothed, [`Smoothing::Banded`] renders get the contours of [`Smoothing::Classic`].
+    #[must_use]
+    pub fn new(render_parameters: &RenderParameters, render_region: Frame) -> Self {
+        let smoothed_parameters;
+        let render_parameters = if render_parameters.smoothing == Smoothing::Banded {
+            smoothed_parameters = RenderParameters {
+                smoothing: Smoothing::Classic,
+                ..render_parameters.clone()
+            };
+            &smoothed_parameters
+        } else {
+            render_parameters
+        };
+        let degree = render_parameters.formula.degree(render_parameters.exponent);
+        let x_resolution = u32::from(render_parameters.x_resolution);
+        let y_resolution = u32::from(render_parameters.y_resolution);
+        let real_delta = render_region.real_distance / f64::from(x_resolution);
+        let imag_delta = render_region.imag_distance / f64::from(y_resolution);
+        let start_real =
+            render_region.center_real - (render_region.real_distance - real_delta) / 2.0;
+        let start_imag =
+            render_region.center_imag + (render_region.imag_distance - imag_delta) / 2.0;
+        let max_iterations = iterations_to_f64(render_parameters.max_iterations.get());
+
+        let smoothed_iterations = (0..y_resolution)
+            .into_par_iter()
+            .flat_map_iter(|y| {
+                let c_im = start_imag - f64::from(y) * imag_delta;
+                (0..x_resolution).map(move |x| {
+                    let c_re = start_real + f64::from(x) * real_delta;
+                    let escape = potential_in_precision(c_re, c_im, render_parameters);
+                    match escape {
+                        Escape::Escaped { escape_speed, .. } => {
+                            max_iterations * (1.0 - escape_speed)
+                        }
+                        Escape::Inside(_) => max_iterations,
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            width: usize::from(render_parameters.x_resolution),
+            height: usize::from(render_parameters.y_resolution),
+            smoothed_iterations,
+            max_iterations,
+            degree,
+            smoothing_offset: render_parameters
+                .smoothing
+                .offset(render_parameters.smoothing_offset, degree)
+                .expect("the iteration counts are smoothed"),
+        }
+    }
+
+    /// The number of pixels along the real axis.
+    #[must_use]
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The number of pixels along the imaginary axis.
+    #[must_use]
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The smoothed iteration count at the center of every pixel, row by row from the top left.
+    /// Points inside the set count as having been iterated the maximum number of iterations.
+    #[must_use]
+    pub fn smoothed_iterations(&self) -> &[f64] {
+        &self.smoothed_iterations
+    }
+
+    /// The maximum number of iterations that the points were iterated.
+    #[must_use]
+    pub const fn max_iterations(&self) -> f64 {
+        self.max_iterations
+    }
+
+    /// Returns the multiples of `spacing` that lie between the smallest and largest smoothed iteration counts
+    /// of the points that escape, in increasing order. If some points are inside the set and the maximum number
+    /// of iterations is a multiple of `spacing`, it is included as well, so that the boundary of the set is traced.
+    ///
+    /// # Errors
+    /// Returns an error if there would be more than [`MAX_CONTOUR_LEVELS`] levels.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::{EscapeSpeedField, Frame, RenderParameters};
+    /// # use color_space::SupportedColorType;
+    /// let params = RenderParameters::try_new(
+    ///     60.try_into().unwrap(),
+    ///     40.try_into().unwrap(),
+    ///     100.try_into().unwrap(),
+    ///     1.try_into().unwrap(),
+    ///     SupportedColorType::Rgb8,
+    /// )
+    /// .unwrap();
+    /// let field = EscapeSpeedField::new(&params, Frame::new(-0.75, 0.0, 3.0, 2.0));
+    /// let levels = field.contour_levels(10.try_into().unwrap()).unwrap();
+    /// assert_eq!(levels.last(), Some(&100.0));
+    /// assert!(levels.iter().all(|level| level % 10.0 == 0.0));
+    /// ```
+    pub fn contour_levels(&self, spacing: NonZeroU64) -> Result<Vec<f64>, TooManyContoursError> {
+        let (mut lowest, mut highest) = (f64::INFINITY, f64::NEG_INFINITY);
+        let mut any_inside = false;
+        for &iterations in &self.smoothed_iterations {
+            if iterations < self.max_iterations {
+                lowest = lowest.min(iterations);
+                highest = highest.max(iterations);
+            } else {
+                any_inside = true;
+            }
+        }
+        // Contours more than 2^53 iterations apart are rounded, which can not be seen.
+        #[allow(clippy::cast_precision_loss)]
+        let spacing = spacing.get() as f64;
+        let mut levels = Vec::new();
+        if lowest <= highest {
+            let first = (lowest / spacing).ceil().max(1.0);
+            let last = (highest / spacing).floor();
+            if last - first + 1.0 > MAX_CONTOUR_LEVELS as f64 {
+                return Err(TooManyContoursError {
+                    levels: last - first + 1.0,
+                });
+            }
+            // There are at most `MAX_CONTOUR_LEVELS` of them, so they fit in a `u64`.
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let multiples = first as u64..=last as u64;
+            #[allow(clippy::cast_precision_loss)]
+            levels.extend(multiples.map(|k| k as f64 * spacing));
+        }
+        if any_inside && self.max_iterations % spacing == 0.0 {
+            if levels.len() as u64 == MAX_CONTOUR_LEVELS {
+                return Err(TooManyContoursError {
+                    levels: MAX_CONTOUR_LEVELS as f64 + 1.0,
+                });
+            }
+            levels.push(self.max_iterations);
+        }
+        Ok(levels)
+    }
+
+    /// Traces the contour lines of the given levels of the smoothed iteration count,
+    /// like [`contour_lines`].
+    #[must_use]
+    pub fn contour_lines(&self, levels: &[f64]) -> Vec<Contour> {
+        let mut contours: Vec<Contour> = self
+            .trace(levels)
+            .into_iter()
+            .map(|(index, segments)| Contour {
+                level: levels[index],
+                segments,
+            })
+            .collect();
+        contours.sort_by(|a, b| a.level.total_cmp(&b.level));
+        contours
+    }
+
+    /// Traces the equipotential lines of the given levels of the potential, i.e. the curves
+    /// that the electric field lines around the set, if it was a charged conductor, cross at right angles.
+    /// The potential is the limit of ln|z_n| / d^(n - 1), where z_1 = c and d is the degree of the formula.
+    /// It is 0 on the set and close to ln|c| far from it, so the lines are circles far from the set that wrap ever closer around it
+    /// for smaller levels. Levels that are not positive or that are too close to the set to be told apart
+    /// from it within the maximum number of iterations have no line, and are left out of the result.
+    /// The rest are returned in increasing order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::{EscapeSpeedField, Frame, RenderParameters};
+    /// # use color_space::SupportedColorType;
+    /// let params = RenderParameters::try_new(
+    ///     60.try_into().unwrap(),
+    ///     40.try_into().unwrap(),
+    ///     100.try_into().unwrap(),
+    ///     1.try_into().unwrap(),
+    ///     SupportedColorType::Rgb8,
+    /// )
+    /// .unwrap();
+    /// let field = EscapeSpeedField::new(&params, Frame::new(-0.75, 0.0, 3.0, 2.0));
+    /// let lines = field.equipotentials(&[0.5, 0.1, -1.0]);
+    /// assert_eq!(lines.iter().map(|line| line.level).collect::<Vec<_>>(), [0.1, 0.5]);
+    /// ```
+    #[must_use]
+    pub fn equipotentials(&self, potentials: &[f64]) -> Vec<Contour> {
+        let levels: Vec<f64> = potentials
+            .iter()
+            .map(|&potential| self.smoothed_iterations_at(potential))
+            .collect();
+        let mut contours: Vec<Contour> = self
+            .trace(&levels)
+            .into_iter()
+            .filter(|(index, _)| levels[*index] < self.max_iterations)
+            .map(|(index, segments)| Contour {
+                level: potentials[index],
+                segments,
+            })
+            .collect();
+        contours.sort_by(|a, b| a.level.total_cmp(&b.level));
+        contours
+    }
+
+    /// Returns the smoothed iteration count of the points with the given potential.
+    /// Each iteration multiplies the logarithm of |z| by the degree, and the smoothed iteration count
+    /// is the fractional number of iterations it takes for ln|z|^2 to reach 1,
+    /// shifted by the smoothing offset.
+    fn smoothed_iterations_at(&self, potential: f64) -> f64 {
+        self.smoothing_offset + 1.0 - (2.0 * potential).ln() / self.degree.ln()
+    }
+
+    /// Returns the segments of the contour lines of the given levels of the smoothed iteration count,
+    /// by the index of their level. Levels without any segments are left out.
+    fn trace(&self, levels: &[f64]) -> BTreeMap<usize, Vec<Segment>> {
+        let (width, height) = (self.width, self.height);
+        let field = &self.smoothed_iterations;
+
+        // Every row of cells between two rows of samples is traced on its own.
+        let rows: Vec<Vec<(usize, Segment)>> = (0..height.saturating_sub(1))
+            .into_par_iter()
+            .map(|y| {
+                let mut segments = Vec::new();
+                for x in 0..width - 1 {
+                    let corners = [
+                        field[y * width + x],
+                        field[y * width + x + 1],
+                        field[(y + 1) * width + x + 1],
+                        field[(y + 1) * width + x],
+                    ];
+                    for (index, &level) in levels.iter().enumerate() {
+                        trace_cell(corners, level, |[(x0, y0), (x1, y1)]| {
+                            // The samples are taken at the centers of the pixels.
+                            let offset =
+                                |(cx, cy): (f64, f64)| (x as f64 + cx + 0.5, y as f64 + cy + 0.5);
+                            segments.push((index, [offset((x0, y0)), offset((x1, y1))]));
+                        });
+                    }
+                }
+                segments
+            })
+            .collect();
+
+        let mut contours: BTreeMap<usize, Vec<Segment>> = BTreeMap::new();
+        for (index, segment) in rows.into_iter().flatten() {
+            contours.entry(index).or_default().push(segment);
+        }
+        contours
+    }
+}
+
+/// Calls `segment` with the segments of the contour line of the given level through a square cell
+/// with the given values at its corners, in the order top left, top right, bottom right and bottom left.
+/// The end points of the segments are given relative to the top left corner of the cell, whose sides are 1.
+fn trace_cell(corners: [f64; 4], level: f64, mut segment: impl FnMut(Segment)) {
+    let case = corners.iter().enumerate().fold(0, |case, (i, &value)| {
+        case | (usize::from(value >= level) << i)
+    });
+    if case == 0 || case == 0b1111 {
+        return;
+    }
+
+    // The point on each side where the value crosses the level,
+    // with the sides in the order top, right, bottom and left.
+    // Every side is interpolated from its top or left end, so that the cells on both sides of it
+    // compute exactly the same point and their segments can be joined into paths.
+    let crossing = |side: usize| {
+        let (from, to) = match side {
+            0 => (0, 1),
+            1 => (1, 2),
+            2 => (3, 2),
+            _ => (0, 3),
+        };
+        let t = (level - corners[from]) / (corners[to] - corners[from]);
+        match side {
+            0 => (t, 0.0),
+            1 => (1.0, t),
+            2 => (t, 1.0),
+            _ => (0.0, t),
+        }
+    };
+    // Both ends of a segment are at the same corner if its value is exactly the level,
+    // as it is for points inside the set on the contour at the maximum number of iterations.
+    let mut connect = |from: usize, to: usize| {
+        let (start, end) = (crossing(from), crossing(to));
+        if start != end {
+            segment([start, end]);
+        }
+    };
+
+    match case {
+        0b0001 | 0b1110 => connect(3, 0),
+        0b0010 | 0b1101 => connect(0, 1),
+        0b0100 | 0b1011 => connect(1, 2),
+        0b1000 | 0b0111 => connect(2, 3),
+        0b0011 | 0b1100 => connect(3, 1),
+        0b0110 | 0b1001 => connect(0, 2),
+        // In the saddle cases the value at the center decides which corners are connected.
+        0b0101 | 0b1010 => {
+            let center_is_above = corners.iter().sum::<f64>() / 4.0 >= level;
+            if (case == 0b0101) == center_is_above {
+                connect(0, 1);
+                connect(2, 3);
+            } else {
+                connect(3, 0);
+                connect(1, 2);
+            }
+        }
+        _ => unreachable!("the case only has four bits and the trivial cases are handled above"),
+    }
+}
+
+impl Contour {
+    /// Joins the segments of the contour line into paths of connected points.
+    /// Paths that are closed loops end with their first point.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::Contour;
+    /// let contour = Contour {
+    ///     level: 1.0,
+    ///     segments: vec![[(1.0, 0.0), (0.0, 1.0)], [(2.0, 1.0), (1.0, 0.0)], [(5.0, 5.0), (6.0, 6.0)]],
+    /// };
+    /// let paths = contour.paths();
+    /// assert_eq!(paths.len(), 2);
+    /// assert!(paths.contains(&vec![(2.0, 1.0), (1.0, 0.0), (0.0, 1.0)]));
+    /// ```
+    #[must_use]
+    pub fn paths(&self) -> Vec<Vec<(f64, f64)>> {
+        let key = |(x, y): (f64, f64)| (x.to_bits(), y.to_bits());
+        // The segments that end at each point, of which there are at most two.
+        let mut ends: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+        for (index, segment) in self.segments.iter().enumerate() {
+            for &point in segment {
+                ends.entry(key(point)).or_default().push(index);
+            }
+        }
+
+        let mut used = vec![false; self.segments.len()];
+        // Follows the unused segments from the last point of the path until it ends or closes.
+        let extend = |path: &mut Vec<(f64, f64)>, used: &mut [bool]| {
+            while let Some(&next) = path
+                .last()
+                .and_then(|&point| ends.get(&key(point)))
+                .and_then(|segments| segments.iter().find(|&&segment| !used[segment]))
+            {
+                used[next] = true;
+                let [a, b] = self.segments[next];
+                path.push(
+                    if key(a) == key(*path.last().expect("the path is not empty")) {
+                        b
+                    } else {
+                        a
+                    },
+                );
+            }
+        };
+
+        let mut paths = Vec::new();
+        for index in 0..self.segments.len() {
+            if used[index] {
+                continue;
+            }
+            used[index] = true;
+            let [a, b] = self.segments[index];
+            let mut forward = vec![a, b];
+            extend(&mut forward, &mut used);
+            // Paths that do not close must also be followed in the other direction.
+            let mut backward = vec![a];
+            extend(&mut backward, &mut used);
+            backward.reverse();
+            backward.extend_from_slice(&forward[1..]);
+            paths.push(backward);
+        }
+        paths
+    }
+}
+
+/// Simplifies the path with the Douglas-Peucker algorithm by removing points that are closer
+/// than `tolerance` to the line between the points that are kept. The ends of the path are always kept.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::simplify_path;
+/// let path = [(0.0, 0.0), (1.0, 0.1), (2.0, -0.1), (3.0, 0.0), (3.0, 2.0)];
+/// assert_eq!(simplify_path(&path, 0.5), [(0.0, 0.0), (3.0, 0.0), (3.0, 2.0)]);
+/// assert_eq!(simplify_path(&path, 0.0), path);
+/// ```
+#[must_use]
+pub fn simplify_path(path: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    if path.len() < 3 {
+        return path.to_vec();
+    }
+
+    let mut keep = vec![false; path.len()];
+    keep[0] = true;
+    keep[path.len() - 1] = true;
+    let mut stack = vec![(0, path.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let farthest = (first + 1..last)
+            .map(|i| (i, distance_to_line(path[i], path[first], path[last])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance {
+                keep[i] = true;
+                stack.push((first, i));
+                stack.push((i, last));
+            }
+        }
+    }
+
+    path.iter()
+        .zip(keep)
+        .filter_map(|(&point, keep)| keep.then_some(point))
+        .collect()
+}
+
+/// Returns the distance from `point` to the line through `a` and `b`,
+/// or to `a` if they are the same point, as they are at the ends of a closed path.
+fn distance_to_line(point: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx.hypot(dy);
+    if length == 0.0 {
+        (point.0 - a.0).hypot(point.1 - a.1)
+    } else {
+        (dx * (a.1 - point.1) - dy * (a.0 - point.0)).abs() / length
+    }
+}
+
+/// Draws the contour lines on the image with the given color. The lines are one pixel wide.
+pub fn draw_contours(image: &mut DynamicImage, contours: &[Contour], color: [u8; 3]) {
+    for &[start, end] in contours.iter().flat_map(|contour| &contour.segments) {
+        draw_line(image, start, end, color);
+    }
+}
+
+/// Draws a one pixel wide line between two points in pixel coordinates on the image.
+/// The parts of the line outside the image are skipped, so the points may be far outside it.
+pub(crate) fn draw_line(
+    image: &mut DynamicImage,
+    start: (f64, f64),
+    end: (f64, f64),
+    color: [u8; 3],
+) {
+    let (width, height) = (f64::from(image.width()), f64::from(image.height()));
+    let Some(((x0, y0), (x1, y1))) = clip_line(start, end, width, height) else {
+        return;
+    };
+    let [r, g, b] = color;
+    // Step along the segment at most half a pixel at a time so that the line has no gaps.
+    let steps = ((x1 - x0).abs().max((y1 - y0).abs()) * 2.0).ceil().max(1.0);
+    for step in 0..=steps as u32 {
+        let t = f64::from(step) / steps;
+        let (x, y) = (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+        if (0.0..width).contains(&x) && (0.0..height).contains(&y) {
+            // The point is inside the image, so it fits in a u32.
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            image.put_pixel(x as u32, y as u32, Rgba([r, g, b, u8::MAX]));
+        }
+    }
+}
+
+/// Returns the part of the line between the points that is inside the rectangle
+/// from the origin to `(width, height)`, or `None` if it misses the rectangle.
+fn clip_line(
+    (x0, y0): (f64, f64),
+    (x1, y1): (f64, f64),
+    width: f64,
+    height: f64,
+) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    // The line is `start + t * (dx, dy)` for t in [0, 1], and each edge of the rectangle
+    // limits t from one side.
+    let (mut first, mut last) = (0.0_f64, 1.0_f64);
+    for (direction, distance) in [(-dx, x0), (dx, width - x0), (-dy, y0), (dy, height - y0)] {
+        if direction == 0.0 {
+            if distance < 0.0 {
+                return None;
+            }
+        } else if direction < 0.0 {
+            first = first.max(distance / direction);
+        } else {
+            last = last.min(distance / direction);
+        }
+    }
+    (first <= last).then_some((
+        (x0 + dx * first, y0 + dy * first),
+        (x0 + dx * last, y0 + dy * last),
+    ))
+}
+
+/// Writes the contour lines as an SVG image with the given size in pixels,
+/// with one path element per level drawn with the given color.
+/// The lines are simplified with [`simplify_path`] and the given tolerance in pixels first.
+#[must_use]
+pub fn contours_to_svg(
+    contours: &[Contour],
+    width: u32,
+    height: u32,
+    color: [u8; 3],
+    tolerance: f64,
+) -> String {
+    let [r, g, b] = color;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n\
+         <g fill=\"none\" stroke=\"#{r:02x}{g:02x}{b:02x}\" stroke-width=\"1\">\n"
+    );
+    for contour in contours {
+        _ = write!(svg, "<path data-level=\"{}\" d=\"", contour.level);
+        for path in contour.paths() {
+            let path = simplify_path(&path, tolerance);
+            let closed = path.len() > 2 && path.first() == path.last();
+            let points = if closed {
+                &path[..path.len() - 1]
+            } else {
+                &path[..]
+            };
+            for (i, (x, y)) in points.iter().enumerate() {
+                _ = write!(svg, "{}{x:.2} {y:.2}", if i == 0 { 'M' } else { 'L' });
+            }
+            if closed {
+                svg.push('Z');
+            }
+        }
+        svg.push_str("\"/>\n");
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// The spacing of the contour lines would give more than [`MAX_CONTOUR_LEVELS`] levels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TooManyContoursError {
+    /// The number of levels that the spacing would give.
+    pub levels: f64,
+}
+
+impl fmt::Display for TooManyContoursError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the contour spacing gives {} levels in this view, but at most {MAX_CONTOUR_LEVELS} can be traced, use a larger spacing",
+            self.levels
+        )
+    }
+}
+
+impl std::error::Error for TooManyContoursError {}
+
+#[cfg(test)]
+mod test_contours {
+    use super::*;
+
+    #[test]
+    fn check_cell_cases() {
+        let trace = |corners| {
+            let mut segments = Vec::new();
+            trace_cell(corners, 0.5, |segment| segments.push(segment));
+            segments
+        };
+        assert!(trace([0.0; 4]).is_empty());
+        assert!(trace([1.0; 4]).is_empty());
+        // Only the top left corner is above the level.
+        assert_eq!(trace([1.0, 0.0, 0.0, 0.0]), [[(0.0, 0.5), (0.5, 0.0)]]);
+        // The left half is above the level, so the line is vertical.
+        assert_eq!(trace([1.0, 0.0, 0.0, 1.0]), [[(0.5, 0.0), (0.5, 1.0)]]);
+        // A saddle gives two segments.
+        assert_eq!(trace([1.0, 0.0, 1.0, 0.0]).len(), 2);
+    }
+
+    #[test]
+    fn check_paths_of_traced_contour() {
+        let params = RenderParameters::try_new(
+            60.try_into().unwrap(),
+            40.try_into().unwrap(),
+            100.try_into().unwrap(),
+            1.try_into().unwrap(),
+            color_space::SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let contours = contour_lines(&params, Frame::new(-0.75, 0.0, 3.0, 2.0), &[10.0, 100.0]);
+        assert_eq!(contours.len(), 2);
+        for contour in contours {
+            let paths = contour.paths();
+            // Every segment is used once, and joining two segments shares a point.
+            let points: usize = paths.iter().map(Vec::len).sum();
+            assert_eq!(points, contour.segments.len() + paths.len());
+            // A path can only end where it leaves the grid of samples at the centers of the pixels.
+            let on_edge = |(x, y): (f64, f64)| x == 0.5 || x == 59.5 || y == 0.5 || y == 39.5;
+            for path in &paths {
+                let (first, last) = (path[0], path[path.len() - 1]);
+                assert!(first == last || (on_edge(first) && on_edge(last)));
+                // Simplifying a path keeps its ends.
+                let simplified = simplify_path(path, 0.5);
+                assert_eq!(
+                    (simplified[0], simplified[simplified.len() - 1]),
+                    (first, last)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn check_contour_levels() {
+        let params = RenderParameters::try_new(
+            60.try_into().unwrap(),
+            40.try_into().unwrap(),
+            1_000_000_000.try_into().unwrap(),
+            1.try_into().unwrap(),
+            color_space::SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        // The view is outside the set, so every point escapes quickly.
+        let field = EscapeSpeedField::new(&params, Frame::new(2.0, 1.5, 2.0, 1.0));
+        let levels = field.contour_levels(NonZeroU64::MIN).unwrap();
+        assert!(!levels.is_empty() && levels.len() < 10, "{levels:?}");
+        assert!(levels.windows(2).all(|pair| pair[1] - pair[0] == 1.0));
+
+        // The spacing is larger than any of the iteration counts.
+        let spacing = NonZeroU64::new(500_000_000).unwrap();
+        assert_eq!(field.contour_levels(spacing), Ok(Vec::new()));
+
+        // Only the iteration counts that occur in the field are counted.
+        let field = EscapeSpeedField {
+            width: 2,
+            height: 1,
+            smoothed_iterations: vec![0.5, 20_000.5],
+            ..field
+        };
+        assert_eq!(
+            field.contour_levels(NonZeroU64::MIN),
+            Err(TooManyContoursError { levels: 20_000.0 })
+        );
+        assert_eq!(
+            field
+                .contour_levels(NonZeroU64::new(2).unwrap())
+                .map(|levels| levels.len()),
+            Ok(10_000)
+        );
+    }
+
+    #[test]
+    fn check_equipotentials() {
+        let mut params = RenderParameters::try_new(
+            81.try_into().unwrap(),
+            81.try_into().unwrap(),
+            100.try_into().unwrap(),
+            1.try_into().unwrap(),
+            color_space::SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        // The smoothed iteration count is only exact for large escape radii.
+        params.escape_radius = crate::EscapeRadius::try_from(1e6).unwrap();
+        // The potential does not depend on how the iteration counts are smoothed.
+        for smoothing in Smoothing::ALL {
+            params.smoothing = smoothing;
+            // The pixels are one unit wide, with the origin at the center of the middle one.
+            let field = EscapeSpeedField::new(&params, Frame::new(0.0, 0.0, 81.0, 81.0));
+            let lines = field.equipotentials(&[30.0_f64.ln()]);
+            assert_eq!(lines.len(), 1);
+            // Far from the set the potential is ln|c + 1/2| to a good approximation,
+            // so the line is a circle.
+            for &(x, y) in lines[0].segments.iter().flatten() {
+                let radius = (x - 40.5 + 0.5).hypot(40.5 - y);
+                assert!((radius - 30.0).abs() < 0.02, "{smoothing}: {radius}");
+            }
+        }
+    }
+
+    #[test]
+    fn check_svg() {
+        let contours = [Contour {
+            level: 3.0,
+            segments: vec![[(0.5, 1.0), (1.5, 2.25)]],
+        }];
+        let svg = contours_to_svg(&contours, 4, 3, [255, 0, 0], 0.0);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("stroke=\"#ff0000\""));
+        assert!(svg.contains("<path data-level=\"3\" d=\"M0.50 1.00L1.50 2.25\"/>"));
+    }
+}
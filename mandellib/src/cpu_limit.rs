@@ -0,0 +1,201 @@
+//! Keeping long renders from using all of the processor, so that they can run in the background
+//! on a laptop without its fans at full speed.
+//!
+//! The limit is kept by the render threads taking a break after every unit of work,
+//! long enough that they only work for the given share of the time.
+//! Programs that build their own thread pool can also use fewer threads, see [`CpuLimit::split`].
+
+use core::fmt;
+use core::num::{NonZeroUsize, ParseFloatError};
+use core::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The share of the time, in percent, that the threads rendering an image spend working.
+/// Is known to be between [`CpuLimit::MIN`] and 100.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CpuLimit(f64);
+
+impl CpuLimit {
+    /// The threads work all the time.
+    pub const NONE: Self = Self(100.0);
+
+    /// The smallest limit, at which the threads rest 99 times as long as they work.
+    pub const MIN: f64 = 1.0;
+
+    #[must_use]
+    pub const fn percent(&self) -> f64 {
+        self.0
+    }
+
+    /// Splits a limit on the share of `cores` processor cores that a render uses into the
+    /// number of threads to render with and the limit of each of those threads,
+    /// so that no more threads than needed are busy.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::CpuLimit;
+    /// # use core::num::NonZeroUsize;
+    /// // Half of 8 cores is 4 threads that work all the time.
+    /// let limit = CpuLimit::try_from(50.0).unwrap();
+    /// let (threads, per_thread) = limit.split(NonZeroUsize::new(8).unwrap());
+    /// assert_eq!((threads.get(), per_thread), (4, CpuLimit::NONE));
+    ///
+    /// // 30 % of 4 cores is 1.2 cores, which is 2 threads that work 60 % of the time.
+    /// let limit = CpuLimit::try_from(30.0).unwrap();
+    /// let (threads, per_thread) = limit.split(NonZeroUsize::new(4).unwrap());
+    /// assert_eq!(threads.get(), 2);
+    /// assert!((per_thread.percent() - 60.0).abs() < 1e-9);
+    /// ```
+    #[must_use]
+    pub fn split(self, cores: NonZeroUsize) -> (NonZeroUsize, Self) {
+        let busy_cores = cores.get() as f64 * self.0 / 100.0;
+        // The share is at most 100 %, so the number of threads is at most the number of cores.
+        // Each of them works at least as large a share as the whole render, so it is at least the minimum.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let threads = NonZeroUsize::new(busy_cores.ceil() as usize).unwrap_or(NonZeroUsize::MIN);
+        let per_thread = (100.0 * busy_cores / threads.get() as f64).clamp(Self::MIN, 100.0);
+        (threads, Self(per_thread))
+    }
+
+    /// Runs `work`, and then sleeps long enough for the thread to only have worked
+    /// for the share of the time given by the limit.
+    pub(crate) fn throttle<T>(self, work: impl FnOnce() -> T) -> T {
+        if self.0 >= 100.0 {
+            return work();
+        }
+        let start = Instant::now();
+        let result = work();
+        let rest = start.elapsed().as_secs_f64() * (100.0 / self.0 - 1.0);
+        thread::sleep(Duration::try_from_secs_f64(rest).unwrap_or(Duration::MAX));
+        result
+    }
+}
+
+impl Default for CpuLimit {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+impl fmt::Display for CpuLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<f64> for CpuLimit {
+    type Error = InvalidCpuLimitError;
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        if (Self::MIN..=100.0).contains(&value) {
+            Ok(Self(value))
+        } else {
+            Err(InvalidCpuLimitError::OutOfRange)
+        }
+    }
+}
+
+impl From<CpuLimit> for f64 {
+    fn from(value: CpuLimit) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidCpuLimitError {
+    OutOfRange,
+    InvalidValue(ParseFloatError),
+}
+
+impl fmt::Display for InvalidCpuLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange => write!(
+                f,
+                "the CPU limit must be a percentage between {} and 100",
+                CpuLimit::MIN
+            ),
+            Self::InvalidValue(e) => write!(f, "the CPU limit could not be parsed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidCpuLimitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidValue(e) => Some(e),
+            Self::OutOfRange => None,
+        }
+    }
+}
+
+impl FromStr for CpuLimit {
+    type Err = InvalidCpuLimitError;
+    /// Parses a percentage, with or without a trailing "%".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        s.strip_suffix('%')
+            .unwrap_or(s)
+            .trim_end()
+            .parse::<f64>()
+            .map_err(InvalidCpuLimitError::InvalidValue)?
+            .try_into()
+    }
+}
+
+#[cfg(test)]
+mod test_cpu_limit {
+    use super::*;
+    use crate::{render, Frame, RenderParameters};
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_parsing() {
+        assert_eq!("100".parse(), Ok(CpuLimit::NONE));
+        assert_eq!("25 %".parse(), Ok(CpuLimit(25.0)));
+        assert_eq!(
+            "0".parse::<CpuLimit>(),
+            Err(InvalidCpuLimitError::OutOfRange)
+        );
+        assert_eq!(
+            "0.5".parse::<CpuLimit>(),
+            Err(InvalidCpuLimitError::OutOfRange)
+        );
+        assert_eq!(
+            "150".parse::<CpuLimit>(),
+            Err(InvalidCpuLimitError::OutOfRange)
+        );
+        assert!(matches!(
+            "half".parse::<CpuLimit>(),
+            Err(InvalidCpuLimitError::InvalidValue(_))
+        ));
+    }
+
+    #[test]
+    fn check_throttle() {
+        let start = Instant::now();
+        CpuLimit(25.0).throttle(|| thread::sleep(Duration::from_millis(20)));
+        // Working for a quarter of the time means resting three times as long as the work took.
+        assert!(start.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[test]
+    fn check_smallest_limit() {
+        let limit = CpuLimit::try_from(CpuLimit::MIN).unwrap();
+        assert_eq!(limit.split(NonZeroUsize::MIN), (NonZeroUsize::MIN, limit));
+
+        let mut params = RenderParameters::try_new(
+            8.try_into().unwrap(),
+            8.try_into().unwrap(),
+            50.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let frame = Frame::new(-0.75, 0.0, 3.0, 3.0);
+        let full_speed = render(&params, frame, false);
+        params.cpu_limit = limit;
+        assert_eq!(render(&params, frame, false), full_speed);
+    }
+}
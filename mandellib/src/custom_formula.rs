@@ -0,0 +1,504 @@
+//! Iteration formulas written in a small expression language over the complex numbers.
+//!
+//! A formula is written as an expression in the variables `z` and `c`,
+//! optionally preceded by `z =`, e.g. `z = z^2 + c` or `z = conj(z)^2 + c`.
+//! The expressions support the operators `+`, `-`, `*`, `/` and `^`, parentheses,
+//! the imaginary unit `i`, the constants `pi` and `e`, and the functions
+//! `conj`, `re`, `im`, `abs`, `exp`, `ln`, `sqrt`, `sin` and `cos`.
+//! The formula is interpreted, so it renders a lot slower than the built-in formulas.
+//! The expressions are parsed by the same parser as the curve palettes of [`color_space`].
+
+use core::fmt;
+use core::str::FromStr;
+
+use color_space::{
+    parse_expression, BinaryOperator, ExpressionLanguage, ParseExpressionError,
+    ParseExpressionErrorKind, MAX_NESTING_DEPTH,
+};
+
+use crate::{Complex, Fractal, Symmetry};
+
+/// An iteration formula z -> f(z, c) given as an expression.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{Complex, CustomFormula, Exponent, Formula, Fractal};
+/// # use core::num::NonZeroU64;
+/// const MAXITERS: NonZeroU64 = NonZeroU64::new(100).unwrap();
+/// let formula: CustomFormula = "z = z^2 + c".parse().unwrap();
+/// for (c_re, c_im) in [(-0.5, 0.2), (0.3, 0.6), (-1.8, 0.01)] {
+///     assert_eq!(
+///         formula.iterate(Complex::new(c_re, c_im), MAXITERS).0,
+///         Formula::Mandelbrot.iterate(c_re, c_im, Exponent::TWO, MAXITERS).0,
+///     );
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CustomFormula {
+    source: String,
+    expression: Expression,
+    degree: f64,
+    symmetry: Symmetry,
+}
+
+impl CustomFormula {
+    /// The formula as it was written.
+    #[must_use]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+}
+
+impl Fractal for CustomFormula {
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        self.expression.evaluate(z, c)
+    }
+
+    /// A formula is symmetric under conjugation if it does not contain `i` or `im`,
+    /// no rotational symmetry is detected.
+    fn symmetry(&self) -> Symmetry {
+        self.symmetry
+    }
+
+    /// The degree of the formula as a polynomial in z.
+    /// If the formula is not a polynomial in z of degree larger than one this is 2.
+    fn degree(&self) -> f64 {
+        self.degree
+    }
+}
+
+// Two formulas are considered equal if they were written the same way.
+impl PartialEq for CustomFormula {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for CustomFormula {}
+
+impl fmt::Display for CustomFormula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl FromStr for CustomFormula {
+    type Err = ParseCustomFormulaError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (expression, offset) = match s.split_once('=') {
+            Some((variable, expression)) if variable.trim() == "z" => {
+                (expression, variable.len() + 1)
+            }
+            Some((variable, _)) => {
+                return Err(ParseCustomFormulaError::new(
+                    ParseCustomFormulaErrorKind::InvalidAssignment(variable.trim().to_owned()),
+                    0,
+                ))
+            }
+            None => (s, 0),
+        };
+
+        let expression = parse_expression::<Formulas>(expression)
+            .map_err(|e| ParseCustomFormulaError::from(e).shifted(offset))?;
+
+        let degree = expression
+            .degree()
+            .filter(|&degree| degree > 1.0)
+            .unwrap_or(2.0);
+
+        let symmetry = Symmetry {
+            conjugation: expression.commutes_with_conjugation(),
+            rotational_order: 1,
+        };
+
+        Ok(Self {
+            source: s.trim().to_owned(),
+            expression,
+            degree,
+            symmetry,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    Constant(Complex),
+    Z,
+    C,
+    Negate(Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+    /// Raising to a constant integer power is common enough to get its own fast path.
+    IntegerPower(Box<Expression>, i32),
+    Function(Function, Box<Expression>),
+}
+
+impl Expression {
+    fn evaluate(&self, z: Complex, c: Complex) -> Complex {
+        match self {
+            Self::Constant(k) => *k,
+            Self::Z => z,
+            Self::C => c,
+            Self::Negate(e) => -e.evaluate(z, c),
+            Self::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.evaluate(z, c), rhs.evaluate(z, c));
+                match op {
+                    BinaryOperator::Add => lhs + rhs,
+                    BinaryOperator::Subtract => lhs - rhs,
+                    BinaryOperator::Multiply => lhs * rhs,
+                    BinaryOperator::Divide => lhs / rhs,
+                    BinaryOperator::Power => lhs.powc(rhs),
+                }
+            }
+            Self::IntegerPower(base, exponent) => base.evaluate(z, c).powi(*exponent),
+            Self::Function(function, argument) => {
+                let x = argument.evaluate(z, c);
+                match function {
+                    Function::Conj => x.conj(),
+                    Function::Re => x.re.into(),
+                    Function::Im => x.im.into(),
+                    Function::Abs => x.abs().into(),
+                    Function::Exp => x.exp(),
+                    Function::Ln => x.ln(),
+                    Function::Sqrt => x.sqrt(),
+                    Function::Sin => x.sin(),
+                    Function::Cos => x.cos(),
+                }
+            }
+        }
+    }
+
+    /// Returns the degree of the expression if it is a polynomial in z (and its conjugate).
+    fn degree(&self) -> Option<f64> {
+        match self {
+            Self::Constant(_) | Self::C => Some(0.0),
+            Self::Z => Some(1.0),
+            Self::Negate(e) => e.degree(),
+            Self::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.degree()?, rhs.degree()?);
+                match op {
+                    BinaryOperator::Add | BinaryOperator::Subtract => Some(lhs.max(rhs)),
+                    BinaryOperator::Multiply => Some(lhs + rhs),
+                    BinaryOperator::Divide if rhs == 0.0 => Some(lhs),
+                    BinaryOperator::Divide | BinaryOperator::Power => {
+                        (lhs == 0.0 && rhs == 0.0).then_some(0.0)
+                    }
+                }
+            }
+            Self::IntegerPower(base, exponent) => {
+                let degree = base.degree()?;
+                (*exponent >= 0).then(|| degree * f64::from(*exponent))
+            }
+            Self::Function(Function::Conj, argument) => argument.degree(),
+            Self::Function(_, argument) => (argument.degree()? == 0.0).then_some(0.0),
+        }
+    }
+
+    /// Returns true if f(conj(z), conj(c)) = conj(f(z, c)),
+    /// which makes the fractal symmetric under mirroring in the real axis.
+    fn commutes_with_conjugation(&self) -> bool {
+        match self {
+            Self::Constant(k) => k.im == 0.0,
+            Self::Z | Self::C => true,
+            Self::Negate(e) | Self::IntegerPower(e, _) => e.commutes_with_conjugation(),
+            Self::Binary(_, lhs, rhs) => {
+                lhs.commutes_with_conjugation() && rhs.commutes_with_conjugation()
+            }
+            Self::Function(Function::Im, _) => false,
+            Self::Function(_, argument) => argument.commutes_with_conjugation(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Conj,
+    Re,
+    Im,
+    Abs,
+    Exp,
+    Ln,
+    Sqrt,
+    Sin,
+    Cos,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "conj" => Some(Self::Conj),
+            "re" => Some(Self::Re),
+            "im" => Some(Self::Im),
+            "abs" => Some(Self::Abs),
+            "exp" => Some(Self::Exp),
+            "ln" => Some(Self::Ln),
+            "sqrt" => Some(Self::Sqrt),
+            "sin" => Some(Self::Sin),
+            "cos" => Some(Self::Cos),
+            _ => None,
+        }
+    }
+}
+
+/// The language of formula expressions, a function of `z` and `c` over the complex numbers.
+struct Formulas;
+
+impl ExpressionLanguage for Formulas {
+    type Expression = Expression;
+    type Function = Function;
+
+    fn constant(value: f64) -> Expression {
+        Expression::Constant(value.into())
+    }
+
+    fn variable(name: &str) -> Option<Expression> {
+        match name {
+            "z" => Some(Expression::Z),
+            "c" => Some(Expression::C),
+            "i" => Some(Expression::Constant(Complex::I)),
+            "pi" => Some(Expression::Constant(core::f64::consts::PI.into())),
+            "e" => Some(Expression::Constant(core::f64::consts::E.into())),
+            _ => None,
+        }
+    }
+
+    fn function(name: &str) -> Option<Function> {
+        Function::from_name(name)
+    }
+
+    /// Every function takes a single argument.
+    fn arity(_: Function) -> usize {
+        1
+    }
+
+    fn negate(expression: Expression) -> Expression {
+        Expression::Negate(Box::new(expression))
+    }
+
+    fn binary(operator: BinaryOperator, lhs: Expression, rhs: Expression) -> Expression {
+        match (operator, integer_value(&rhs)) {
+            (BinaryOperator::Power, Some(n)) => Expression::IntegerPower(Box::new(lhs), n),
+            _ => Expression::Binary(operator, Box::new(lhs), Box::new(rhs)),
+        }
+    }
+
+    fn call(function: Function, mut arguments: Vec<Expression>) -> Expression {
+        Expression::Function(function, Box::new(arguments.swap_remove(0)))
+    }
+}
+
+/// Returns the value of the expression if it is a constant small integer,
+/// possibly negated.
+fn integer_value(expression: &Expression) -> Option<i32> {
+    match expression {
+        Expression::Constant(k) if k.im == 0.0 && k.re.fract() == 0.0 && k.re.abs() <= 64.0 => {
+            // The value is checked to be a small integer above.
+            #[allow(clippy::cast_possible_truncation)]
+            Some(k.re as i32)
+        }
+        Expression::Negate(e) => integer_value(e).map(|n| -n),
+        _ => None,
+    }
+}
+
+/// An error that occured while parsing a [`CustomFormula`].
+/// Contains the byte position in the input where the error was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCustomFormulaError {
+    kind: ParseCustomFormulaErrorKind,
+    position: usize,
+}
+
+impl ParseCustomFormulaError {
+    const fn new(kind: ParseCustomFormulaErrorKind, position: usize) -> Self {
+        Self { kind, position }
+    }
+
+    const fn shifted(mut self, offset: usize) -> Self {
+        self.position += offset;
+        self
+    }
+
+    #[must_use]
+    pub const fn kind(&self) -> &ParseCustomFormulaErrorKind {
+        &self.kind
+    }
+
+    /// The byte position in the input where the error was found.
+    #[must_use]
+    pub const fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl From<ParseExpressionError> for ParseCustomFormulaError {
+    fn from(error: ParseExpressionError) -> Self {
+        let (kind, position) = error.into_parts();
+        let kind = match kind {
+            ParseExpressionErrorKind::UnexpectedCharacter(c) => {
+                ParseCustomFormulaErrorKind::UnexpectedCharacter(c)
+            }
+            ParseExpressionErrorKind::UnexpectedEnd => ParseCustomFormulaErrorKind::UnexpectedEnd,
+            ParseExpressionErrorKind::Expected { expected, found } => {
+                ParseCustomFormulaErrorKind::Expected { expected, found }
+            }
+            ParseExpressionErrorKind::InvalidNumber(n) => {
+                ParseCustomFormulaErrorKind::InvalidNumber(n)
+            }
+            ParseExpressionErrorKind::UnknownVariable(v) => {
+                ParseCustomFormulaErrorKind::UnknownVariable(v)
+            }
+            ParseExpressionErrorKind::UnknownFunction(name) => {
+                ParseCustomFormulaErrorKind::UnknownFunction(name)
+            }
+            ParseExpressionErrorKind::WrongArgumentCount {
+                function,
+                expected,
+                found,
+            } => ParseCustomFormulaErrorKind::WrongArgumentCount {
+                function,
+                expected,
+                found,
+            },
+            ParseExpressionErrorKind::TooDeeplyNested => {
+                ParseCustomFormulaErrorKind::TooDeeplyNested
+            }
+        };
+        Self::new(kind, position)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseCustomFormulaErrorKind {
+    InvalidAssignment(String),
+    UnexpectedCharacter(char),
+    UnexpectedEnd,
+    Expected {
+        expected: char,
+        found: Option<char>,
+    },
+    InvalidNumber(String),
+    UnknownVariable(String),
+    UnknownFunction(String),
+    WrongArgumentCount {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    /// The expression is nested more than [`MAX_NESTING_DEPTH`] levels deep.
+    TooDeeplyNested,
+}
+
+impl fmt::Display for ParseCustomFormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at position {}: ", self.position)?;
+        match &self.kind {
+            ParseCustomFormulaErrorKind::InvalidAssignment(v) => {
+                write!(f, "the formula must assign to z, not \"{v}\"")
+            }
+            ParseCustomFormulaErrorKind::UnexpectedCharacter(c) => {
+                write!(f, "unexpected character '{c}'")
+            }
+            ParseCustomFormulaErrorKind::UnexpectedEnd => {
+                write!(f, "unexpected end of expression")
+            }
+            ParseCustomFormulaErrorKind::Expected { expected, found } => match found {
+                Some(c) => write!(f, "expected '{expected}' but found '{c}'"),
+                None => write!(f, "expected '{expected}' but the expression ended"),
+            },
+            ParseCustomFormulaErrorKind::InvalidNumber(n) => {
+                write!(f, "\"{n}\" is not a valid number")
+            }
+            ParseCustomFormulaErrorKind::UnknownVariable(v) => write!(
+                f,
+                "unknown variable \"{v}\", the only variables are z and c and the only constants are i, pi and e"
+            ),
+            ParseCustomFormulaErrorKind::UnknownFunction(name) => {
+                write!(f, "unknown function \"{name}\"")
+            }
+            ParseCustomFormulaErrorKind::WrongArgumentCount {
+                function,
+                expected,
+                found,
+            } => write!(
+                f,
+                "the function {function} takes {expected} argument(s) but was given {found}"
+            ),
+            ParseCustomFormulaErrorKind::TooDeeplyNested => write!(
+                f,
+                "the expression is nested more than {MAX_NESTING_DEPTH} levels deep"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseCustomFormulaError {}
+
+#[cfg(test)]
+mod test_custom_formula {
+    use super::*;
+    use crate::{Exponent, Formula};
+    use core::num::NonZeroU64;
+
+    #[test]
+    fn check_against_built_in_formulas() {
+        let max_iterations = NonZeroU64::new(255).unwrap();
+        let mandelbar: CustomFormula = "z = conj(z)^2 + c".parse().unwrap();
+        let multibrot: CustomFormula = "z^3 + c".parse().unwrap();
+        for (c_re, c_im) in [(0.3, 0.5), (-1.2, 0.4), (0.1, -0.9), (-0.1, 0.1)] {
+            assert_eq!(
+                mandelbar
+                    .iterate(Complex::new(c_re, c_im), max_iterations)
+                    .0,
+                Formula::Mandelbar
+                    .iterate(c_re, c_im, Exponent::TWO, max_iterations)
+                    .0
+            );
+            assert_eq!(
+                multibrot
+                    .iterate(Complex::new(c_re, c_im), max_iterations)
+                    .0,
+                Formula::Mandelbrot
+                    .iterate(c_re, c_im, 3.try_into().unwrap(), max_iterations)
+                    .0
+            );
+        }
+    }
+
+    #[test]
+    fn check_analysis() {
+        let formula: CustomFormula = "z = z*z*conj(z) - 2*z/3 + c".parse().unwrap();
+        assert_eq!(formula.degree(), 3.0);
+        assert!(formula.symmetry().conjugation);
+
+        let formula: CustomFormula = "exp(z) + i*c".parse().unwrap();
+        assert_eq!(formula.degree(), 2.0);
+        assert!(!formula.symmetry().conjugation);
+    }
+
+    #[test]
+    fn check_error_messages() {
+        let error = "w = z^2 + c".parse::<CustomFormula>().unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &ParseCustomFormulaErrorKind::InvalidAssignment("w".to_owned())
+        );
+
+        let error = "z = z^2 + x".parse::<CustomFormula>().unwrap_err();
+        assert_eq!(
+            error.kind(),
+            &ParseCustomFormulaErrorKind::UnknownVariable("x".to_owned())
+        );
+        assert_eq!(error.position(), 10);
+
+        let error = "z = conj(z, c)".parse::<CustomFormula>().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ParseCustomFormulaErrorKind::WrongArgumentCount { .. }
+        ));
+
+        let deep = format!("z = {}z{} + c", "(".repeat(1000), ")".repeat(1000));
+        let error = deep.parse::<CustomFormula>().unwrap_err();
+        assert_eq!(error.kind(), &ParseCustomFormulaErrorKind::TooDeeplyNested);
+    }
+}
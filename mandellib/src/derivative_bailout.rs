@@ -0,0 +1,155 @@
+//! Finding the points inside the set from how their orbits contract.
+//!
+//! The orbit of a point inside a hyperbolic component of the set is attracted to a cycle,
+//! so orbits that start close to it move closer together with every iteration, and the derivative
+//! of z_n with respect to z_1 shrinks geometrically. Once it is tiny the orbit is taken to be attracted
+//! to a cycle, which finds most of the interior after a few dozen iterations instead of after
+//! the maximum number of iterations.
+//!
+//! The orbits of some points right outside the set also contract for a while before they escape,
+//! e.g. when they pass close to 0 or follow a cycle of a nearby component for many iterations,
+//! so a few of them are taken to be inside. That is why the check must be enabled with
+//! [`RenderParameters::derivative_bailout`](crate::RenderParameters::derivative_bailout).
+
+use core::num::NonZeroU64;
+
+use crate::{complex_powi, EscapeRadius, Exponent, Float};
+
+/// The squared magnitude of the derivative of an orbit below which it is taken to be attracted to a cycle.
+const DERIVATIVE_BAILOUT_SQR: f64 = 1e-24;
+
+/// Iterates z -> z^d + c, or z -> conj(z)^d + c if `conjugate` is true, and returns the same
+/// as [`iterate_with_escape_radius`](crate::iterate_with_escape_radius) and the Mandelbar formula do,
+/// except that the iteration stops as soon as the orbit has contracted enough to be taken to be inside the set.
+/// Such points get the maximum number of iterations and a final z of NaN,
+/// like the points that are skipped by the cardioid and bulb checks.
+pub(crate) fn iterate_with_derivative_bailout<F: Float>(
+    c_re: F,
+    c_im: F,
+    conjugate: bool,
+    exponent: Exponent,
+    max_iterations: NonZeroU64,
+    escape_radius: EscapeRadius,
+) -> (u64, F, F) {
+    let max_iterations = max_iterations.get();
+    let bailout_sqr = escape_radius.bailout_sqr();
+    let derivative_bailout_sqr = F::from_f64(DERIVATIVE_BAILOUT_SQR);
+    let degree = exponent.get();
+    let sign = F::from_f64(if conjugate { -1.0 } else { 1.0 });
+
+    let mut z_re = c_re;
+    let mut z_im = c_im;
+    let mut mag_sqr = c_re * c_re + c_im * c_im;
+    // |dz_n/dz_1|^2
+    let mut derivative_sqr = F::from_f64(1.0);
+
+    let mut iterations = 1;
+
+    while iterations < max_iterations && mag_sqr <= bailout_sqr {
+        // |d/dz (z^d + c)| = d |z|^(d - 1), and the same holds for conj(z)^d + c.
+        let mut factor = F::from_f64(f64::from(degree) * f64::from(degree));
+        for _ in 1..degree {
+            factor *= mag_sqr;
+        }
+        derivative_sqr *= factor;
+        if derivative_sqr < derivative_bailout_sqr {
+            return (max_iterations, F::NAN, F::NAN);
+        }
+
+        if exponent == Exponent::TWO {
+            // The same operations as in the loops for the exponent 2,
+            // so that escaping points get exactly the same result.
+            let z_re_sqr = z_re * z_re;
+            let z_im_sqr = z_im * z_im;
+            z_im = F::from_f64(2.0) * sign * z_re * z_im + c_im;
+            z_re = z_re_sqr - z_im_sqr + c_re;
+        } else {
+            let (w_re, w_im) = complex_powi(z_re, z_im, degree);
+            z_re = w_re + c_re;
+            z_im = sign * w_im + c_im;
+        }
+        mag_sqr = z_re * z_re + z_im * z_im;
+        iterations += 1;
+    }
+
+    if mag_sqr > bailout_sqr {
+        for _ in 0..escape_radius.smoothing_iterations() {
+            let (w_re, w_im) = complex_powi(z_re, z_im, degree);
+            z_re = w_re + c_re;
+            z_im = sign * w_im + c_im;
+        }
+    }
+
+    (iterations, z_re, z_im)
+}
+
+#[cfg(test)]
+mod test_derivative_bailout {
+    use super::*;
+    use crate::{iterate_with_escape_radius, Formula};
+
+    #[test]
+    fn check_bailout() {
+        let max_iterations = NonZeroU64::new(10_000).unwrap();
+        let radius = EscapeRadius::DEFAULT;
+        // Points in the main cardioid, the period 2 bulb and a period 3 bulb of the Mandelbrot set,
+        // and around the origin of the cubic Multibrot set.
+        for (exponent, interior) in [
+            (
+                Exponent::TWO,
+                [(-0.1_f64, 0.1), (-1.0, 0.05), (-0.12, 0.75)],
+            ),
+            (
+                Exponent::try_from(3).unwrap(),
+                [(-0.1, 0.1), (0.0, 0.0), (0.1, -0.2)],
+            ),
+        ] {
+            // Points inside the set are found before the maximum number of iterations,
+            // which can not be seen from the result, but the final value of z is not known.
+            for (c_re, c_im) in interior {
+                let (iterations, z_re, z_im) = iterate_with_derivative_bailout(
+                    c_re,
+                    c_im,
+                    false,
+                    exponent,
+                    max_iterations,
+                    radius,
+                );
+                assert_eq!(iterations, max_iterations.get());
+                assert!(z_re.is_nan() && z_im.is_nan(), "{c_re} + {c_im}i");
+            }
+
+            // Points far from the set escape exactly like they do without the check.
+            for (c_re, c_im) in [(1.0, 1.0), (-2.1, 0.0), (0.5, -1.2)] {
+                assert_eq!(
+                    iterate_with_derivative_bailout(
+                        c_re,
+                        c_im,
+                        false,
+                        exponent,
+                        max_iterations,
+                        radius
+                    ),
+                    iterate_with_escape_radius(c_re, c_im, exponent, max_iterations, radius)
+                );
+                assert_eq!(
+                    iterate_with_derivative_bailout(
+                        c_re,
+                        c_im,
+                        true,
+                        exponent,
+                        max_iterations,
+                        radius
+                    ),
+                    Formula::Mandelbar.iterate_with_escape_radius(
+                        c_re,
+                        c_im,
+                        exponent,
+                        max_iterations,
+                        radius
+                    )
+                );
+            }
+        }
+    }
+}
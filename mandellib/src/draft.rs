@@ -0,0 +1,225 @@
+//! A fast way of rendering previews by successive rectangle subdivision, also known as guessing.
+//! The image is first computed on a coarse grid, which is then refined by halving the distance
+//! between its points until every pixel is known. A new point between points that all have the same
+//! color is assumed to have that color as well, and is only computed if they disagree.
+//!
+//! Large areas of a single color, most importantly the inside of the set where every point
+//! costs the full number of iterations, are then only computed along their edges.
+//! Features that are thinner than the distance between the points of the grid can be missed entirely.
+
+use core::fmt;
+use core::str::FromStr;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{pixel_color, Colorizer, PixelGrid, RenderParameters};
+
+/// The distance in pixels between the points of the coarsest grid. Must be a power of two.
+const CELL_SIZE: usize = 8;
+
+/// How carefully the pixels of an image are computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RenderQuality {
+    /// Compute every pixel.
+    #[default]
+    Full,
+    /// Only compute the pixels at the edges of areas of a single color and fill in the rest.
+    /// Much faster for views that contain a lot of the inside of the set,
+    /// but small features can be missing. Has no effect on renders with a [`Mask`](crate::Mask).
+    Draft,
+    /// Experimental. Recursively split the image into cells wherever the escape speeds at their corners differ,
+    /// and interpolate the escape speeds of the cells whose corners agree.
+    /// Faster than a draft for views with smooth gradients outside the set, but the interpolated pixels are
+    /// not supersampled and small features can be missing. Has no effect on renders with a [`Mask`](crate::Mask).
+    QuadTree,
+}
+
+impl RenderQuality {
+    pub const ALL: [Self; 3] = [Self::Full, Self::Draft, Self::QuadTree];
+}
+
+impl fmt::Display for RenderQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => write!(f, "full"),
+            Self::Draft => write!(f, "draft"),
+            Self::QuadTree => write!(f, "quadtree"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRenderQualityError(String);
+
+impl fmt::Display for ParseRenderQualityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown render quality \"{}\", expected \"full\", \"draft\" or \"quadtree\"",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseRenderQualityError {}
+
+impl FromStr for RenderQuality {
+    type Err = ParseRenderQualityError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "full" => Ok(Self::Full),
+            "draft" => Ok(Self::Draft),
+            "quadtree" | "quad-tree" => Ok(Self::QuadTree),
+            _ => Err(ParseRenderQualityError(s.to_owned())),
+        }
+    }
+}
+
+/// Fills the rotated `image` by guessing, where each band is `band_length` pixels long.
+/// The pixels sample the same points as they would in a full render,
+/// but the image is never mirrored since most of the pixels that would be mirrored are guessed anyway.
+pub(crate) fn color_by_guessing(
+    render_parameters: &RenderParameters,
+    grid: &PixelGrid,
+    colorizer: &Colorizer,
+    image: &mut [u8],
+    band_length: usize,
+) {
+    let bytes_per_pixel = usize::from(render_parameters.color_type.bytes_per_pixel());
+    if band_length == 0 {
+        return;
+    }
+    let bands = image.len() / (band_length * bytes_per_pixel);
+
+    let mut step = CELL_SIZE;
+    loop {
+        // The points on this level of the grid that are not on the coarser one,
+        // and their colors. They are computed from the coarser level only,
+        // so the bands can be handled in parallel and the colors written afterwards.
+        let known: &[u8] = image;
+        let updates: Vec<(usize, [u8; 4])> = (0..bands.div_ceil(step))
+            .into_par_iter()
+            .flat_map_iter(|band_step| {
+                let band_index = band_step * step;
+                (0..band_length)
+                    .step_by(step)
+                    .filter_map(move |pixel_index| {
+                        let offset = (band_index * band_length + pixel_index) * bytes_per_pixel;
+                        let color = if step == CELL_SIZE {
+                            None
+                        } else {
+                            guess(
+                                known,
+                                (band_index, pixel_index),
+                                (bands, band_length),
+                                step,
+                                bytes_per_pixel,
+                            )?
+                        };
+                        let mut bytes = [0; 4];
+                        match color {
+                            Some(color) => bytes[..bytes_per_pixel].copy_from_slice(color),
+                            None => {
+                                let grid_index = grid.grid_index(pixel_index);
+                                let color = pixel_color(
+                                    grid,
+                                    (band_index, grid_index),
+                                    None,
+                                    colorizer,
+                                    render_parameters,
+                                );
+                                bytes[..bytes_per_pixel].copy_from_slice(color.as_raw());
+                            }
+                        }
+                        Some((offset, bytes))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        for (offset, color) in updates {
+            image[offset..offset + bytes_per_pixel].copy_from_slice(&color[..bytes_per_pixel]);
+        }
+
+        if step == 1 {
+            return;
+        }
+        step /= 2;
+    }
+}
+
+/// Returns `None` if the point at `(band_index, pixel_index)` is on the coarser level of the grid
+/// with twice the given step and thus already known. Otherwise returns the color of the points
+/// around it on the coarser level if they all have the same color, and `Some(None)` if they disagree
+/// or some of them are outside the image, in which case the point must be computed.
+fn guess(
+    image: &[u8],
+    (band_index, pixel_index): (usize, usize),
+    (bands, band_length): (usize, usize),
+    step: usize,
+    bytes_per_pixel: usize,
+) -> Option<Option<&[u8]>> {
+    let coarse = 2 * step;
+    let band_offset = if band_index % coarse == 0 { 0 } else { step };
+    let pixel_offset = if pixel_index % coarse == 0 { 0 } else { step };
+    if band_offset == 0 && pixel_offset == 0 {
+        return None;
+    }
+
+    let mut neighbors = [-1, 1].into_iter().flat_map(|band_sign| {
+        [-1, 1].into_iter().map(move |pixel_sign| {
+            let band = band_index.checked_add_signed(band_sign * band_offset as isize)?;
+            let pixel = pixel_index.checked_add_signed(pixel_sign * pixel_offset as isize)?;
+            (band < bands && pixel < band_length).then(|| {
+                let offset = (band * band_length + pixel) * bytes_per_pixel;
+                &image[offset..offset + bytes_per_pixel]
+            })
+        })
+    });
+
+    let first = neighbors.next().flatten();
+    Some(first.filter(|first| neighbors.all(|neighbor| neighbor == Some(*first))))
+}
+
+#[cfg(test)]
+mod test_draft {
+    use super::*;
+    use crate::{render, Frame};
+
+    fn parameters(quality: RenderQuality) -> RenderParameters {
+        RenderParameters::test_image(quality, 1)
+    }
+
+    #[test]
+    fn check_draft_is_close_to_full_render() {
+        for frame in [
+            Frame::new(-0.75, 0.0, 3.0, 2.0),
+            Frame::new(-0.2, 0.3, 0.9, 0.6),
+            // Entirely inside the main cardioid.
+            Frame::new(-0.1, 0.1, 0.15, 0.1),
+        ] {
+            let full = render(&parameters(RenderQuality::Full), frame, false).into_rgb8();
+            let draft = render(&parameters(RenderQuality::Draft), frame, false).into_rgb8();
+            assert_eq!(full.dimensions(), draft.dimensions());
+            let different = full
+                .pixels()
+                .zip(draft.pixels())
+                .filter(|(full, draft)| full != draft)
+                .count();
+            assert!(
+                different * 100 < full.len() / 3,
+                "{different} pixels differ in {frame:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn check_parsing() {
+        for quality in RenderQuality::ALL {
+            assert_eq!(quality.to_string().parse(), Ok(quality));
+        }
+        assert_eq!(" Draft".parse(), Ok(RenderQuality::Draft));
+        assert_eq!("quad-tree".parse(), Ok(RenderQuality::QuadTree));
+        assert!("best".parse::<RenderQuality>().is_err());
+    }
+}
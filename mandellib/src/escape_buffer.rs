@@ -0,0 +1,641 @@
+//! Rendering in two stages: computing how fast the samples of every pixel escape,
+//! which is where almost all of the time goes, and coloring them.
+//!
+//! [`render`](crate::render) runs both stages on one sample at a time without storing anything
+//! in between. [`compute`] instead keeps the escape data of every sample in an [`EscapeBuffer`],
+//! which [`colorize`] can then turn into an image with any [`Coloring`] without iterating a single point again.
+
+use core::num::{NonZeroU64, NonZeroUsize};
+use core::ops::Range;
+use std::sync::Arc;
+
+use color_space::{ColorMap, LinearRGB, Palette, Pixel, SupportedColorType};
+use image::{DynamicImage, ImageBuffer, Luma, Rgb, Rgba};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use rayon::slice::{ParallelSlice, ParallelSliceMut};
+
+use crate::grayscale::GrayscaleCurve;
+use crate::interior::BINARY_DECOMPOSITION_DARKENING;
+use crate::progress::NoProgress;
+use crate::sample_placement::{sample_offsets, GradientField};
+use crate::{
+    check_frame, potential_in_precision, sample_escapes, Escape, EscapeSpeedRange, Exterior, Frame,
+    GrayscaleMode, PixelGrid, Progress, RenderError, RenderParameters,
+};
+
+/// What is known about a sample point once it has been iterated, before it is colored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EscapeSample {
+    /// The point escaped with the given escape speed, from 0 close to the set to 1 far from it.
+    Escaped {
+        escape_speed: f64,
+        /// Whether the last point of the orbit was below the real axis,
+        /// which is what [`Exterior::BinaryDecomposition`] darkens by.
+        below_real_axis: bool,
+    },
+    /// The point is inside the set. The brightness is given if it is shaded by an
+    /// [`InteriorShading`](crate::InteriorShading) other than the flat one.
+    Inside { brightness: Option<f64> },
+}
+
+impl EscapeSample {
+    #[must_use]
+    pub const fn is_inside(&self) -> bool {
+        matches!(self, Self::Inside { .. })
+    }
+
+    /// Returns the escape speed of the sample, or 0 if it is inside the set.
+    #[must_use]
+    pub const fn escape_speed(&self) -> f64 {
+        match self {
+            Self::Escaped { escape_speed, .. } => *escape_speed,
+            Self::Inside { .. } => 0.0,
+        }
+    }
+}
+
+/// The settings of [`RenderParameters`] that decide how escape speeds are turned into colors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coloring {
+    pub color_type: SupportedColorType,
+    pub palette: Palette,
+    pub escape_speed_range: EscapeSpeedRange,
+    pub grayscale_mode: GrayscaleMode,
+    pub exterior: Exterior,
+    pub palette_offset: f64,
+}
+
+impl From<&RenderParameters> for Coloring {
+    fn from(render_parameters: &RenderParameters) -> Self {
+        Self {
+            color_type: render_parameters.color_type,
+            palette: render_parameters.palette.clone(),
+            escape_speed_range: render_parameters.escape_speed_range,
+            grayscale_mode: render_parameters.grayscale_mode,
+            exterior: render_parameters.exterior,
+            palette_offset: render_parameters.palette_offset,
+        }
+    }
+}
+
+impl Coloring {
+    /// Returns the color of the points inside the set if the exterior of the image is transparent.
+    /// The color is `None` if the interior is colored as usual.
+    fn transparent_exterior(&self) -> Option<Option<LinearRGB>> {
+        match self.exterior {
+            Exterior::Transparent { interior_color } => (self.color_type
+                == SupportedColorType::Rgba8)
+                .then(|| interior_color.map(LinearRGB::from_srgb)),
+            Exterior::Opaque | Exterior::BinaryDecomposition => None,
+        }
+    }
+}
+
+/// A [`Coloring`] together with what is worked out about the whole view to apply it.
+pub(crate) struct Colorizer {
+    coloring: Coloring,
+    color_map: Arc<dyn ColorMap + Send + Sync>,
+    grayscale_curve: GrayscaleCurve,
+    transparent_exterior: Option<Option<LinearRGB>>,
+}
+
+impl Colorizer {
+    pub(crate) fn new(coloring: Coloring, grayscale_curve: GrayscaleCurve) -> Self {
+        Self {
+            transparent_exterior: coloring.transparent_exterior(),
+            color_map: coloring.palette.color_map(),
+            coloring,
+            grayscale_curve,
+        }
+    }
+
+    /// Returns the color of a sample in linear RGB.
+    pub(crate) fn color(&self, sample: &EscapeSample) -> LinearRGB {
+        let is_inside = sample.is_inside();
+        let escape_speed = sample.escape_speed();
+        let interior_brightness = match *sample {
+            EscapeSample::Inside { brightness } => brightness,
+            EscapeSample::Escaped { .. } => None,
+        };
+        let palette_position = self.coloring.escape_speed_range.normalize(escape_speed);
+
+        // This branch will be the same for all samples of an image,
+        // so the branch predictor should not have any issues with it.
+        // This reasoning has been verified with benchmarks.
+        let color = match (
+            self.transparent_exterior,
+            self.coloring.color_type,
+            interior_brightness,
+        ) {
+            // Transparent samples do not contribute to the color.
+            (Some(_), _, _) if !is_inside => LinearRGB::default(),
+            (Some(Some(interior_color)), _, _) => interior_color,
+            (_, _, Some(brightness)) => LinearRGB::new(brightness, brightness, brightness),
+            (_, SupportedColorType::Rgb8 | SupportedColorType::Rgba8, None) => self
+                .color_map
+                .color(self.cycled(palette_position, is_inside)),
+            (_, SupportedColorType::L8, None) => {
+                let brightness = self
+                    .grayscale_curve
+                    .brightness(escape_speed, palette_position);
+                LinearRGB::new(brightness, brightness, brightness)
+            }
+        };
+        // Points whose orbits escape below the real axis are darkened,
+        // which splits every band of the exterior in two along the external rays
+        // with angles of the form k / 2^n.
+        match sample {
+            EscapeSample::Escaped {
+                below_real_axis: true,
+                ..
+            } if self.coloring.exterior == Exterior::BinaryDecomposition => {
+                color * BINARY_DECOMPOSITION_DARKENING
+            }
+            _ => color,
+        }
+    }
+
+    /// Offsets the position in the palette of an escaping sample by the palette offset,
+    /// wrapping around at its end. The points inside the set keep their color.
+    fn cycled(&self, palette_position: f64, is_inside: bool) -> f64 {
+        if is_inside || self.coloring.palette_offset == 0.0 {
+            palette_position
+        } else {
+            (palette_position + self.coloring.palette_offset).rem_euclid(1.0)
+        }
+    }
+
+    /// Converts the weighted sum of the colors of the samples of a pixel to an sRGB value.
+    /// `weight` is the total weight of the samples and `inside_weight` the weight of those inside the set.
+    pub(crate) fn pixel(&self, mut color: LinearRGB, weight: f64, inside_weight: f64) -> Pixel<u8> {
+        if self.transparent_exterior.is_some() {
+            // The color is the average of the samples inside the set,
+            // and the opacity is the share of the weight that is inside it.
+            if inside_weight > 0.0 {
+                color /= inside_weight;
+            }
+            // The share is at most one, so the result fits in a u8.
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let alpha = (f64::from(u8::MAX) * inside_weight / weight).round() as u8;
+            let mut pixel = Pixel::Rgba(color.into());
+            if let Pixel::Rgba(ref mut rgba) = pixel {
+                rgba.0[3] = alpha;
+            }
+            return pixel;
+        }
+
+        // Divide by the weight of the samples
+        color /= weight;
+        // and convert to sRGB color space in the correct format.
+        match self.coloring.color_type {
+            SupportedColorType::L8 => Pixel::Luma(color.into()),
+            SupportedColorType::Rgb8 => Pixel::Rgb(color.into()),
+            SupportedColorType::Rgba8 => Pixel::Rgba(color.into()),
+        }
+    }
+}
+
+/// The escape data of every sample of every pixel of an image, see [`compute`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EscapeBuffer {
+    width: usize,
+    height: usize,
+    max_iterations: NonZeroU64,
+    /// The index in `samples` of the first sample of every pixel, row by row from the top left,
+    /// followed by the number of samples.
+    pixel_starts: Vec<usize>,
+    samples: Vec<EscapeSample>,
+}
+
+impl EscapeBuffer {
+    /// The number of pixels along the real axis.
+    #[must_use]
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The number of pixels along the imaginary axis.
+    #[must_use]
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The maximum number of iterations that the points were iterated.
+    #[must_use]
+    pub const fn max_iterations(&self) -> NonZeroU64 {
+        self.max_iterations
+    }
+
+    /// Returns the samples of the pixel in the given column and row, counted from the top left.
+    /// The sample closest to the center of the pixel comes first, and pixels far from the set
+    /// have fewer samples than those close to it, since they are not supersampled.
+    ///
+    /// # Panics
+    /// Panics if the pixel is outside the image.
+    #[must_use]
+    pub fn pixel(&self, x: usize, y: usize) -> &[EscapeSample] {
+        assert!(
+            x < self.width && y < self.height,
+            "the pixel is outside the image"
+        );
+        &self.samples[self.sample_indices(y * self.width + x)]
+    }
+
+    /// Returns the indices in `samples` of the samples of the pixel with the given index,
+    /// counted row by row from the top left.
+    pub(crate) fn sample_indices(&self, pixel: usize) -> Range<usize> {
+        self.pixel_starts[pixel]..self.pixel_starts[pixel + 1]
+    }
+
+    /// Returns the samples of every pixel, row by row from the top left.
+    pub fn pixels(&self) -> impl Iterator<Item = &[EscapeSample]> {
+        self.pixel_starts
+            .windows(2)
+            .map(|bounds| &self.samples[bounds[0]..bounds[1]])
+    }
+
+    /// Returns the mean escape speed of the samples of every pixel that escaped, row by row from
+    /// the top left, or `None` for the pixels whose samples are all inside the set.
+    #[must_use]
+    pub fn mean_escape_speeds(&self) -> Vec<Option<f64>> {
+        self.pixels()
+            .map(|samples| {
+                let (sum, count) = samples
+                    .iter()
+                    .filter(|sample| !sample.is_inside())
+                    .fold((0.0, 0.0), |(sum, count), sample| {
+                        (sum + sample.escape_speed(), count + 1.0)
+                    });
+                (count > 0.0).then(|| sum / count)
+            })
+            .collect()
+    }
+
+    /// Counts the escape speeds of the samples closest to the centers of the pixels
+    /// in `bins` bins of equal width that cover the range \[0, 1\], like
+    /// [`escape_speed_histogram`](crate::escape_speed_histogram) does for an image that has not been computed.
+    /// Points inside the set are not counted.
+    #[must_use]
+    pub fn histogram(&self, bins: NonZeroUsize) -> Vec<u32> {
+        let last_bin = bins.get() - 1;
+        let mut counts = vec![0; bins.get()];
+        for sample in self.pixels().filter_map(<[EscapeSample]>::first) {
+            if let EscapeSample::Escaped { escape_speed, .. } = sample {
+                // The escape speed is clamped to [0, 1] first, so the bin index is in range.
+                #[allow(
+                    clippy::cast_possible_truncation,
+                    clippy::cast_sign_loss,
+                    clippy::cast_precision_loss
+                )]
+                let bin =
+                    ((escape_speed.clamp(0.0, 1.0) * (last_bin + 1) as f64) as usize).min(last_bin);
+                counts[bin] += 1;
+            }
+        }
+        counts
+    }
+}
+
+/// Iterates the samples of every pixel of the image described by the render parameters and region,
+/// and keeps how fast they escape so that the image can be colored any number of times with [`colorize`].
+///
+/// Every pixel is computed and its samples are kept apart, so [`RenderParameters::quality`]
+/// and [`RenderParameters::reconstruction_filter`] are ignored.
+/// The image is mirrored in the real axis when [`render`](crate::render) would mirror it.
+///
+/// The buffer holds every sample of the image, so it takes up many times the memory of the image itself
+/// when it is supersampled.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{colorize, compute, render, Coloring, Frame, RenderParameters};
+/// # use color_space::{Palette, SupportedColorType};
+/// let mut params = RenderParameters::try_new(
+///     30.try_into().unwrap(),
+///     20.try_into().unwrap(),
+///     100.try_into().unwrap(),
+///     2.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+/// let buffer = compute(&params, frame);
+/// assert_eq!(colorize(&buffer, &Coloring::from(&params)), render(&params, frame, false));
+///
+/// // Changing the colors does not iterate any points again.
+/// params.color_type = SupportedColorType::L8;
+/// assert_eq!(colorize(&buffer, &Coloring::from(&params)), render(&params, frame, false));
+/// ```
+#[must_use]
+pub fn compute(render_parameters: &RenderParameters, render_region: Frame) -> EscapeBuffer {
+    compute_with_progress(render_parameters, render_region, &NoProgress)
+}
+
+/// Works like [`compute`], but reports the progress of the computation to `progress`, see [`Progress`].
+/// Every band of the image is a unit of work.
+/// If the progress cancels the computation the bands that were skipped have no samples,
+/// use [`try_compute_with_progress`] to get an error instead.
+#[must_use]
+pub fn compute_with_progress(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    progress: &dyn Progress,
+) -> EscapeBuffer {
+    compute_samples(
+        render_parameters,
+        render_region,
+        progress,
+        |_, _, c_re, c_im| (potential_in_precision(c_re, c_im, render_parameters), ()),
+    )
+    .0
+}
+
+/// Computes the samples of every pixel like [`compute_with_progress`] does, but with `escape`,
+/// which is given the index of the pixel in the buffer, the index of the sample in the pixel and c,
+/// and returns something to keep about the sample along with how it escapes.
+/// Those are returned in the same order as the samples of the buffer.
+pub(crate) fn compute_samples<T: Clone + Send>(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    progress: &dyn Progress,
+    escape: impl Fn(usize, usize, f64, f64) -> (Escape, T) + Sync,
+) -> (EscapeBuffer, Vec<T>) {
+    let grid = PixelGrid::new(render_parameters, render_region, None);
+    let gradient_field = GradientField::new(render_parameters, &grid);
+    let width = usize::from(render_parameters.x_resolution);
+    let height = usize::from(render_parameters.y_resolution);
+    let sqrt_samples = render_parameters.sqrt_samples_per_pixel.get();
+    progress.start(width as u64);
+
+    // The samples of every pixel of every band, counted from the bottom of the band.
+    let bands: Vec<Vec<Vec<(EscapeSample, T)>>> = (0..width)
+        .into_par_iter()
+        .map(|band_index| {
+            if progress.is_cancelled() {
+                progress.advance(1);
+                return vec![Vec::new(); height];
+            }
+            let band = render_parameters.cpu_limit.throttle(|| {
+                let mut band = vec![Vec::new(); height];
+                // The pixels are visited in the order of the computed grid,
+                // so that the pixels below the real axis are computed before they are mirrored.
+                for grid_index in 0..height {
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    if grid.mirror
+                        && grid_index as f64 > grid.mirror_axis / 2.0
+                        && grid_index as f64 <= grid.mirror_axis
+                    {
+                        let source = grid.grid_index(grid.mirror_axis as usize - grid_index);
+                        band[grid.grid_index(grid_index)] = band[source].clone();
+                        continue;
+                    }
+
+                    let direction = gradient_field
+                        .as_ref()
+                        .and_then(|field| field.direction(band_index, grid_index));
+                    let pixel = (height - 1 - grid.grid_index(grid_index)) * width + band_index;
+                    let mut samples = Vec::new();
+                    let mut kept = Vec::new();
+                    sample_escapes(
+                        grid.pixel_region(band_index, grid_index),
+                        sample_offsets(
+                            sqrt_samples,
+                            render_parameters.sample_placement,
+                            render_parameters.seed,
+                            direction,
+                            (band_index, grid.grid_index(grid_index)),
+                        ),
+                        render_parameters,
+                        |c_re, c_im| {
+                            let (escape, keep) = escape(pixel, kept.len(), c_re, c_im);
+                            kept.push(keep);
+                            escape
+                        },
+                        |_, sample| samples.push(sample),
+                    );
+                    band[grid.grid_index(grid_index)] = samples.into_iter().zip(kept).collect();
+                }
+                band
+            });
+            progress.advance(1);
+            band
+        })
+        .collect();
+    progress.finish();
+
+    let mut pixel_starts = Vec::with_capacity(width * height + 1);
+    let mut samples = Vec::new();
+    let mut kept = Vec::new();
+    for y in 0..height {
+        for band in &bands {
+            pixel_starts.push(samples.len());
+            for (sample, keep) in &band[height - 1 - y] {
+                samples.push(*sample);
+                kept.push(keep.clone());
+            }
+        }
+    }
+    pixel_starts.push(samples.len());
+
+    let buffer = EscapeBuffer {
+        width,
+        height,
+        max_iterations: render_parameters.max_iterations,
+        pixel_starts,
+        samples,
+    };
+    (buffer, kept)
+}
+
+/// Works like [`compute`], but checks that the frame can be rendered.
+///
+/// # Errors
+/// Returns an error if the center of the frame is not finite, or if its distances are not finite and positive.
+pub fn try_compute(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+) -> Result<EscapeBuffer, RenderError> {
+    check_frame(render_region)?;
+    Ok(compute(render_parameters, render_region))
+}
+
+/// Works like [`compute_with_progress`], but checks that the frame can be rendered.
+///
+/// # Errors
+/// Returns an error if the center of the frame is not finite, if its distances are not finite and positive,
+/// or if the progress cancelled the computation.
+pub fn try_compute_with_progress(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    progress: &dyn Progress,
+) -> Result<EscapeBuffer, RenderError> {
+    check_frame(render_region)?;
+    let buffer = compute_with_progress(render_parameters, render_region, progress);
+    if progress.is_cancelled() {
+        Err(RenderError::Cancelled)
+    } else {
+        Ok(buffer)
+    }
+}
+
+/// Colors the samples of every pixel in the buffer and averages them into an image.
+///
+/// Gives the same image as [`render`](crate::render) would with the same settings,
+/// except that [`GrayscaleMode::Equalized`] equalizes the escape speeds of the buffer itself
+/// rather than those of a low resolution version of the image.
+#[must_use]
+pub fn colorize(buffer: &EscapeBuffer, coloring: &Coloring) -> DynamicImage {
+    let grayscale_curve = GrayscaleCurve::for_buffer(coloring.grayscale_mode, buffer);
+    let colorizer = Colorizer::new(coloring.clone(), grayscale_curve);
+    let color_type = coloring.color_type;
+    let bytes_per_pixel = usize::from(color_type.bytes_per_pixel());
+
+    let mut bytes = vec![0; buffer.width * buffer.height * bytes_per_pixel];
+    bytes
+        .par_chunks_exact_mut(bytes_per_pixel)
+        .zip(buffer.pixel_starts.par_windows(2))
+        .for_each(|(pixel, bounds)| {
+            let samples = &buffer.samples[bounds[0]..bounds[1]];
+            let mut color = LinearRGB::default();
+            let mut inside = 0.0;
+            for sample in samples {
+                color += colorizer.color(sample);
+                inside += f64::from(u8::from(sample.is_inside()));
+            }
+            // There are at most u8::MAX^2 samples per pixel, which an f64 represents exactly.
+            #[allow(clippy::cast_precision_loss)]
+            let count = samples.len() as f64;
+            pixel.copy_from_slice(colorizer.pixel(color, count, inside).as_raw());
+        });
+
+    // The resolutions came from u32s, so they fit in them.
+    let (width, height) = (
+        u32::try_from(buffer.width).unwrap_or(u32::MAX),
+        u32::try_from(buffer.height).unwrap_or(u32::MAX),
+    );
+    let image =
+        match color_type {
+            SupportedColorType::L8 => ImageBuffer::<Luma<u8>, _>::from_raw(width, height, bytes)
+                .map(DynamicImage::ImageLuma8),
+            SupportedColorType::Rgb8 => ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, bytes)
+                .map(DynamicImage::ImageRgb8),
+            SupportedColorType::Rgba8 => ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, bytes)
+                .map(DynamicImage::ImageRgba8),
+        };
+    image.expect("the buffer has the length of the image")
+}
+
+#[cfg(test)]
+mod test_escape_buffer {
+    use super::*;
+    use crate::{render, InteriorShading};
+
+    fn parameters() -> RenderParameters {
+        RenderParameters::try_new(
+            40.try_into().unwrap(),
+            30.try_into().unwrap(),
+            200.try_into().unwrap(),
+            3.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn check_recoloring_matches_render() {
+        let mut params = parameters();
+        params.interior_shading = InteriorShading::Multiplier;
+        // The image is mirrored in the real axis, and flipped since its center is above it.
+        let frame = Frame::new(-0.75, 0.1, 3.0, 2.5);
+        let buffer = compute(&params, frame);
+
+        for exterior in [
+            Exterior::Opaque,
+            Exterior::BinaryDecomposition,
+            Exterior::Transparent {
+                interior_color: None,
+            },
+        ] {
+            params.exterior = exterior;
+            params.color_type = SupportedColorType::Rgba8;
+            params.escape_speed_range = EscapeSpeedRange::new(0.2, 0.9);
+            // Binary decomposition is not symmetric under conjugation, so it needs a new buffer.
+            let buffer = if exterior == Exterior::BinaryDecomposition {
+                compute(&params, frame)
+            } else {
+                buffer.clone()
+            };
+            assert_eq!(
+                colorize(&buffer, &Coloring::from(&params)),
+                render(&params, frame, false),
+                "{exterior:?}"
+            );
+        }
+
+        params.exterior = Exterior::Opaque;
+        params.color_type = SupportedColorType::L8;
+        params.grayscale_mode = GrayscaleMode::Log;
+        assert_eq!(
+            colorize(&buffer, &Coloring::from(&params)),
+            render(&params, frame, false)
+        );
+    }
+
+    #[test]
+    fn check_buffer() {
+        let params = parameters();
+        let buffer = compute(&params, Frame::new(-0.75, 0.0, 3.0, 2.0));
+        assert_eq!((buffer.width(), buffer.height()), (40, 30));
+        assert_eq!(buffer.pixels().count(), 40 * 30);
+
+        // The center of the image is inside the set, while its corners are so far from it
+        // that they are not supersampled.
+        assert_eq!(buffer.pixel(20, 15).len(), 9);
+        assert!(buffer.pixel(20, 15).iter().all(EscapeSample::is_inside));
+        assert_eq!(buffer.pixel(0, 0).len(), 1);
+        let speeds = buffer.mean_escape_speeds();
+        assert_eq!(speeds[15 * 40 + 20], None);
+        assert!(speeds[0].unwrap() > 0.9);
+
+        // The histogram counts every pixel with an escaping center once.
+        let histogram = buffer.histogram(NonZeroUsize::new(10).unwrap());
+        let escaping = buffer
+            .pixels()
+            .filter(|samples| !samples[0].is_inside())
+            .count();
+        assert_eq!(histogram.iter().sum::<u32>() as usize, escaping);
+
+        let frame = Frame::new(-0.75, 0.0, f64::INFINITY, 2.0);
+        assert_eq!(
+            try_compute(&params, frame),
+            Err(RenderError::InvalidFrame(frame))
+        );
+    }
+
+    #[test]
+    fn check_palette_offset() {
+        let mut params = parameters();
+        let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+        let buffer = compute(&params, frame);
+        let still = colorize(&buffer, &Coloring::from(&params));
+
+        params.palette_offset = 0.3;
+        let cycled = colorize(&buffer, &Coloring::from(&params));
+        assert_eq!(cycled, render(&params, frame, false));
+        assert_ne!(cycled, still);
+        // The interior keeps its color.
+        assert_eq!(cycled.to_rgb8()[(20, 15)], still.to_rgb8()[(20, 15)]);
+
+        // Offsets that differ by whole cycles of the palette give the same positions in it, up to rounding.
+        let colorizer = Colorizer::new(Coloring::from(&params), GrayscaleCurve::Linear);
+        params.palette_offset = -2.7;
+        let wrapped = Colorizer::new(Coloring::from(&params), GrayscaleCurve::Linear);
+        for position in [0.0, 0.25, 0.69, 0.9] {
+            assert!(
+                (colorizer.cycled(position, false) - wrapped.cycled(position, false)).abs() < 1e-12
+            );
+            assert_eq!(colorizer.cycled(position, true), position);
+        }
+    }
+}
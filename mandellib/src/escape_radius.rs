@@ -0,0 +1,194 @@
+use core::fmt;
+use core::num::ParseFloatError;
+use core::str::FromStr;
+
+use crate::Float;
+
+/// The iteration of a point is stopped once |z| exceeds this radius.
+/// Is known to be between [`EscapeRadius::MIN`] and [`EscapeRadius::MAX`].
+///
+/// A smaller radius lets points outside the set escape in fewer iterations,
+/// while a larger one makes the smoothed coloring more accurate.
+/// The coloring is normalized by the radius, so changing it keeps the colors of the image roughly the same.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EscapeRadius(f64);
+
+impl EscapeRadius {
+    /// The escape radius that images are rendered with by default.
+    pub const DEFAULT: Self = Self(6.0);
+
+    /// The smallest escape radius, points outside the disk of radius 2 are guaranteed to escape.
+    pub const MIN: f64 = 2.0;
+
+    /// The largest escape radius, which keeps |z|^2 finite in single precision
+    /// for another iteration after it has escaped.
+    pub const MAX: f64 = 1e9;
+
+    #[must_use]
+    pub const fn get(&self) -> f64 {
+        self.0
+    }
+
+    /// The square of the radius, which is what |z|^2 is compared against.
+    #[must_use]
+    pub fn squared(&self) -> f64 {
+        self.0 * self.0
+    }
+
+    /// The square of the radius in the given number type, lowered to [`Float::MAX_BAILOUT_SQR`]
+    /// if the type can not hold a |z|^2 that exceeds it.
+    pub(crate) fn bailout_sqr<F: Float>(&self) -> F {
+        F::from_f64(self.squared().min(F::MAX_BAILOUT_SQR))
+    }
+
+    /// The number of extra iterations that points are iterated for after they escape a radius
+    /// that is smaller than the default, so that |z| grows past the default radius.
+    /// The smoothed coloring assumes that |z| is much larger than |c| when the point escapes,
+    /// and is visibly banded for small radii without them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::EscapeRadius;
+    /// assert_eq!(EscapeRadius::DEFAULT.smoothing_iterations(), 0);
+    /// assert_eq!(EscapeRadius::try_from(3.0).unwrap().smoothing_iterations(), 1);
+    /// assert_eq!(EscapeRadius::try_from(2.0).unwrap().smoothing_iterations(), 2);
+    /// ```
+    #[must_use]
+    pub fn smoothing_iterations(&self) -> u32 {
+        let mut radius = self.0;
+        let mut iterations = 0;
+        while radius < Self::DEFAULT.0 {
+            radius *= radius;
+            iterations += 1;
+        }
+        iterations
+    }
+}
+
+impl Default for EscapeRadius {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl fmt::Display for EscapeRadius {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<f64> for EscapeRadius {
+    type Error = InvalidEscapeRadiusError;
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        if (Self::MIN..=Self::MAX).contains(&value) {
+            Ok(Self(value))
+        } else {
+            Err(InvalidEscapeRadiusError::OutOfRange)
+        }
+    }
+}
+
+impl From<EscapeRadius> for f64 {
+    fn from(value: EscapeRadius) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidEscapeRadiusError {
+    OutOfRange,
+    InvalidValue(ParseFloatError),
+}
+
+impl fmt::Display for InvalidEscapeRadiusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange => write!(
+                f,
+                "the escape radius must be between {} and {}",
+                EscapeRadius::MIN,
+                EscapeRadius::MAX
+            ),
+            Self::InvalidValue(e) => write!(f, "the escape radius could not be parsed: {e}"),
+        }
+    }
+}
+
+impl core::error::Error for InvalidEscapeRadiusError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::InvalidValue(e) => Some(e),
+            Self::OutOfRange => None,
+        }
+    }
+}
+
+impl FromStr for EscapeRadius {
+    type Err = InvalidEscapeRadiusError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<f64>()
+            .map_err(InvalidEscapeRadiusError::InvalidValue)?
+            .try_into()
+    }
+}
+
+#[cfg(test)]
+mod test_escape_radius {
+    use super::*;
+    use crate::renderer::potential;
+    use crate::{Escape, RenderParameters};
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_smoothing_is_continuous() {
+        let mut params = RenderParameters::try_new(
+            10.try_into().unwrap(),
+            10.try_into().unwrap(),
+            1000.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        for radius in [2.0, 3.0, 4.0, 6.0, 100.0] {
+            params.escape_radius = radius.try_into().unwrap();
+            // The smoothed iteration counts along a line through the set,
+            // which would jump where the iteration count changes if the coloring was banded.
+            let samples = 20_000;
+            let smoothed_iterations: Vec<Option<f64>> = (0..samples)
+                .map(|i| {
+                    let t = f64::from(i) / f64::from(samples);
+                    match potential(-2.5 + 2.0 * t, 0.3 + 0.6 * t, &params) {
+                        Escape::Escaped { escape_speed, .. } => Some(escape_speed * 1000.0),
+                        Escape::Inside(_) => None,
+                    }
+                })
+                .collect();
+            for pair in smoothed_iterations.windows(2) {
+                if let [Some(a), Some(b)] = pair {
+                    assert!(
+                        (a - b).abs() < 0.1,
+                        "banding with the escape radius {radius}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_parsing() {
+        assert_eq!("6".parse(), Ok(EscapeRadius::DEFAULT));
+        assert_eq!(
+            "1.5".parse::<EscapeRadius>(),
+            Err(InvalidEscapeRadiusError::OutOfRange)
+        );
+        assert_eq!(
+            "NaN".parse::<EscapeRadius>(),
+            Err(InvalidEscapeRadiusError::OutOfRange)
+        );
+        assert!(matches!(
+            "six".parse::<EscapeRadius>(),
+            Err(InvalidEscapeRadiusError::InvalidValue(_))
+        ));
+    }
+}
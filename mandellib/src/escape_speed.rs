@@ -0,0 +1,249 @@
+//! The distribution of escape speeds in an image, and the range of them that the palette is stretched over.
+//! Most images only use a small part of the palette, since the escape speeds of their pixels are
+//! bunched together. Stretching the palette over that part brings out detail that would otherwise be
+//! colored almost the same.
+
+use core::fmt;
+use core::num::{NonZeroUsize, ParseFloatError};
+use core::str::FromStr;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{potential_in_precision, Escape, Frame, RenderParameters};
+
+/// The range of escape speeds that the palette is stretched over.
+/// Escape speeds below `min` are colored like `min` and those above `max` like `max`.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::EscapeSpeedRange;
+/// let range = EscapeSpeedRange::new(0.25, 0.75);
+/// assert_eq!(range.normalize(0.5), 0.5);
+/// assert_eq!(range.normalize(0.9), 1.0);
+/// assert_eq!(EscapeSpeedRange::FULL.normalize(0.4), 0.4);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EscapeSpeedRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl EscapeSpeedRange {
+    /// The whole palette, which leaves escape speeds unchanged.
+    pub const FULL: Self = Self::new(0.0, 1.0);
+
+    #[must_use]
+    pub const fn new(min: f64, max: f64) -> Self {
+        Self { min, max }
+    }
+
+    /// Maps an escape speed in this range to the range \[0, 1\] that the palette is defined on.
+    /// If `min` is not below `max` every escape speed is mapped to either 0 or 1.
+    #[must_use]
+    pub fn normalize(self, escape_speed: f64) -> f64 {
+        if self == Self::FULL {
+            // Leave images rendered with the default range exactly as they were.
+            escape_speed
+        } else if self.min < self.max {
+            ((escape_speed - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+        } else if escape_speed < self.min {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
+
+impl Default for EscapeSpeedRange {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+impl fmt::Display for EscapeSpeedRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.min, self.max)
+    }
+}
+
+impl FromStr for EscapeSpeedRange {
+    type Err = ParseEscapeSpeedRangeError;
+    /// Parses the minimum and maximum separated by a comma, e.g. "0.2,0.9".
+    /// Both must be between 0 and 1, and the minimum must not be above the maximum.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (min, max) = s
+            .split_once(',')
+            .ok_or(ParseEscapeSpeedRangeError::MissingComma)?;
+        let min: f64 = min
+            .trim()
+            .parse()
+            .map_err(ParseEscapeSpeedRangeError::InvalidValue)?;
+        let max: f64 = max
+            .trim()
+            .parse()
+            .map_err(ParseEscapeSpeedRangeError::InvalidValue)?;
+        if (0.0..=1.0).contains(&min) && (0.0..=1.0).contains(&max) && min <= max {
+            Ok(Self::new(min, max))
+        } else {
+            Err(ParseEscapeSpeedRangeError::OutOfRange)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseEscapeSpeedRangeError {
+    MissingComma,
+    InvalidValue(ParseFloatError),
+    OutOfRange,
+}
+
+impl fmt::Display for ParseEscapeSpeedRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingComma => write!(
+                f,
+                "the escape speed range must be a minimum and a maximum separated by a comma"
+            ),
+            Self::InvalidValue(e) => write!(f, "the escape speed range could not be parsed: {e}"),
+            Self::OutOfRange => write!(
+                f,
+                "the escape speed range must lie between 0 and 1, with the minimum at most the maximum"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseEscapeSpeedRangeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidValue(e) => Some(e),
+            Self::MissingComma | Self::OutOfRange => None,
+        }
+    }
+}
+
+/// Counts the escape speeds of the centers of the pixels of the image given by the render parameters
+/// and region in `bins` bins of equal width that cover the range \[0, 1\].
+/// Points inside the set are not counted, and escape speeds outside the range are counted in the closest bin.
+///
+/// No supersampling is done, so a histogram of a large image can be computed quickly
+/// by lowering the resolution of the render parameters first.
+#[must_use]
+pub fn escape_speed_histogram(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    bins: NonZeroUsize,
+) -> Vec<u32> {
+    let x_resolution = u32::from(render_parameters.x_resolution);
+    let y_resolution = u32::from(render_parameters.y_resolution);
+    let real_delta = render_region.real_distance / f64::from(x_resolution);
+    let imag_delta = render_region.imag_distance / f64::from(y_resolution);
+    let start_real = render_region.center_real - (render_region.real_distance - real_delta) / 2.0;
+    let start_imag = render_region.center_imag - (render_region.imag_distance - imag_delta) / 2.0;
+    let last_bin = bins.get() - 1;
+
+    (0..y_resolution)
+        .into_par_iter()
+        .fold(
+            || vec![0; bins.get()],
+            |mut counts, y| {
+                let c_im = start_imag + f64::from(y) * imag_delta;
+                for x in 0..x_resolution {
+                    let c_re = start_real + f64::from(x) * real_delta;
+                    let escape = potential_in_precision(c_re, c_im, render_parameters);
+                    if let Escape::Escaped { escape_speed, .. } = escape {
+                        // The escape speed is clamped to [0, 1] first, so the bin index is in range.
+                        #[allow(
+                            clippy::cast_possible_truncation,
+                            clippy::cast_sign_loss,
+                            clippy::cast_precision_loss
+                        )]
+                        let bin = ((escape_speed.clamp(0.0, 1.0) * (last_bin + 1) as f64) as usize)
+                            .min(last_bin);
+                        counts[bin] += 1;
+                    }
+                }
+                counts
+            },
+        )
+        .reduce(
+            || vec![0; bins.get()],
+            |mut total, counts| {
+                for (total, count) in total.iter_mut().zip(counts) {
+                    *total += count;
+                }
+                total
+            },
+        )
+}
+
+#[cfg(test)]
+mod test_escape_speed {
+    use super::*;
+    use crate::renderer::potential;
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_normalization() {
+        let range = EscapeSpeedRange::new(0.25, 0.75);
+        assert_eq!(range.normalize(0.0), 0.0);
+        assert_eq!(range.normalize(0.5), 0.5);
+        assert_eq!(range.normalize(1.0), 1.0);
+        // The default range does not even clamp.
+        assert_eq!(EscapeSpeedRange::default().normalize(1.5), 1.5);
+        let degenerate = EscapeSpeedRange::new(0.5, 0.5);
+        assert_eq!(degenerate.normalize(0.4), 0.0);
+        assert_eq!(degenerate.normalize(0.6), 1.0);
+    }
+
+    #[test]
+    fn check_parsing() {
+        let range = EscapeSpeedRange::new(0.2, 0.9);
+        assert_eq!(range.to_string().parse(), Ok(range));
+        assert_eq!(" 0.5 , 0.5 ".parse(), Ok(EscapeSpeedRange::new(0.5, 0.5)));
+        assert_eq!(
+            "0.5".parse::<EscapeSpeedRange>(),
+            Err(ParseEscapeSpeedRangeError::MissingComma)
+        );
+        assert!(matches!(
+            "low,high".parse::<EscapeSpeedRange>(),
+            Err(ParseEscapeSpeedRangeError::InvalidValue(_))
+        ));
+        for out_of_range in ["0.9,0.2", "-0.1,0.5", "0.5,1.5", "NaN,1"] {
+            assert_eq!(
+                out_of_range.parse::<EscapeSpeedRange>(),
+                Err(ParseEscapeSpeedRangeError::OutOfRange),
+                "{out_of_range}"
+            );
+        }
+    }
+
+    #[test]
+    fn check_histogram() {
+        let params = RenderParameters::try_new(
+            60.try_into().unwrap(),
+            40.try_into().unwrap(),
+            100.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+        let histogram = escape_speed_histogram(&params, frame, NonZeroUsize::new(16).unwrap());
+        assert_eq!(histogram.len(), 16);
+
+        let inside = (0..40)
+            .flat_map(|y| (0..60).map(move |x| (x, y)))
+            .filter(|&(x, y)| {
+                let c_re = -2.25 + (f64::from(x) + 0.5) * 0.05;
+                let c_im = -1.0 + (f64::from(y) + 0.5) * 0.05;
+                matches!(potential(c_re, c_im, &params), Escape::Inside(_))
+            })
+            .count();
+        assert!(inside > 0);
+        let counted: u32 = histogram.iter().sum();
+        assert_eq!(counted as usize + inside, 60 * 40);
+    }
+}
@@ -0,0 +1,86 @@
+use core::fmt;
+use core::num::ParseIntError;
+use core::str::FromStr;
+
+/// The exponent `d` in the iteration function z -> z^d + c.
+/// Is known to be at least 2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Exponent(u8);
+
+impl Exponent {
+    /// The exponent of the Mandelbrot set.
+    pub const TWO: Self = Self(2);
+
+    #[must_use]
+    pub const fn get(&self) -> u8 {
+        self.0
+    }
+}
+
+impl Default for Exponent {
+    fn default() -> Self {
+        Self::TWO
+    }
+}
+
+impl fmt::Display for Exponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<u8> for Exponent {
+    type Error = InvalidExponentError;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        if value >= 2 {
+            Ok(Self(value))
+        } else {
+            Err(InvalidExponentError::TooSmall)
+        }
+    }
+}
+
+impl From<Exponent> for u8 {
+    fn from(value: Exponent) -> Self {
+        value.0
+    }
+}
+
+impl From<Exponent> for f64 {
+    fn from(value: Exponent) -> Self {
+        value.0.into()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidExponentError {
+    TooSmall,
+    InvalidValue(ParseIntError),
+}
+
+impl fmt::Display for InvalidExponentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooSmall => write!(f, "the exponent must be at least 2"),
+            Self::InvalidValue(e) => write!(f, "the exponent could not be parsed: {e}"),
+        }
+    }
+}
+
+impl core::error::Error for InvalidExponentError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::InvalidValue(e) => Some(e),
+            Self::TooSmall => None,
+        }
+    }
+}
+
+impl FromStr for Exponent {
+    type Err = InvalidExponentError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<u8>()
+            .map_err(InvalidExponentError::InvalidValue)?
+            .try_into()
+    }
+}
@@ -0,0 +1,455 @@
+//! External rays of the Mandelbrot and Multibrot sets, i.e. the curves that points outside the set
+//! fall along towards it, each of which is named by the angle it has far away from the set.
+//! Rays whose angles are rational fractions of a turn land on the set, and which of them land
+//! on the same point describes how the components of the set are connected to each other.
+//!
+//! A ray is traced inwards from far outside the set with Newton's method: the point on the ray
+//! where z_n(c) has a given radius and angle n-fold multiplied by the exponent is found
+//! by starting from the previous point on the ray, for smaller and smaller radii and larger n.
+
+use core::f64::consts::TAU;
+use core::fmt;
+use core::num::{NonZeroU32, NonZeroU64, ParseIntError};
+use core::str::FromStr;
+
+use image::DynamicImage;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::contours::draw_line;
+use crate::{Complex, Exponent, Formula, Frame, RenderParameters};
+
+/// The radius at which rays are started. Far enough from the set that the rays are straight there.
+const RAY_RADIUS: f64 = 65536.0;
+
+/// The number of points that are traced along a ray between each increase of the iteration count.
+const SHARPNESS: u32 = 8;
+
+/// The largest number of steps of Newton's method that are taken to find each point of a ray.
+const MAX_NEWTON_STEPS: u32 = 64;
+
+/// Newton's method stops once it moves a point less than this fraction of the resolution of the ray.
+const NEWTON_TOLERANCE: f64 = 1e-3;
+
+/// An angle as a fraction of a full turn, between 0 and 1.
+/// Stored as an exact fraction so that it stays exact when it is multiplied by the exponent
+/// once per iteration while a ray is traced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RayAngle {
+    numerator: u64,
+    denominator: NonZeroU64,
+}
+
+impl RayAngle {
+    /// Returns the angle `numerator/denominator` of a turn. Whole turns are removed,
+    /// and the fraction is reduced to its lowest terms.
+    #[must_use]
+    pub fn new(numerator: u64, denominator: NonZeroU64) -> Self {
+        let numerator = numerator % denominator;
+        let divisor = gcd(numerator, denominator.get());
+        Self {
+            numerator: numerator / divisor,
+            // The divisor divides the denominator, so the quotient is not zero.
+            denominator: NonZeroU64::new(denominator.get() / divisor).unwrap_or(denominator),
+        }
+    }
+
+    #[must_use]
+    pub const fn numerator(&self) -> u64 {
+        self.numerator
+    }
+
+    #[must_use]
+    pub const fn denominator(&self) -> NonZeroU64 {
+        self.denominator
+    }
+
+    /// The angle as a fraction of a turn.
+    #[must_use]
+    pub fn turns(&self) -> f64 {
+        self.numerator as f64 / self.denominator.get() as f64
+    }
+
+    /// Returns the angle multiplied by `factor`, with whole turns removed.
+    /// This is what iterating z -> z^factor + c does to the angle of a ray far from the set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::RayAngle;
+    /// let angle: RayAngle = "1/7".parse().unwrap();
+    /// assert_eq!(angle.multiplied(2).to_string(), "2/7");
+    /// assert_eq!(angle.multiplied(2).multiplied(2).multiplied(2), angle);
+    /// ```
+    #[must_use]
+    pub fn multiplied(self, factor: u8) -> Self {
+        let denominator = u128::from(self.denominator.get());
+        let numerator = u128::from(self.numerator) * u128::from(factor) % denominator;
+        Self {
+            // The remainder is smaller than the denominator, which fits in a u64.
+            numerator: u64::try_from(numerator).unwrap_or_default(),
+            denominator: self.denominator,
+        }
+    }
+}
+
+/// Returns the greatest common divisor of the numbers, or `b` if `a` is 0.
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while a != 0 {
+        (a, b) = (b % a, a);
+    }
+    b
+}
+
+impl fmt::Display for RayAngle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRayAngleError {
+    InvalidNumber(ParseIntError),
+    ZeroDenominator,
+    TooManyDecimals,
+}
+
+impl fmt::Display for ParseRayAngleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidNumber(e) => write!(
+                f,
+                "the angle must be a fraction such as \"1/3\" or a decimal number such as \"0.25\": {e}"
+            ),
+            Self::ZeroDenominator => write!(f, "the denominator of the angle can not be zero"),
+            Self::TooManyDecimals => write!(f, "the angle can have at most 19 decimals"),
+        }
+    }
+}
+
+impl std::error::Error for ParseRayAngleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidNumber(e) => Some(e),
+            Self::ZeroDenominator | Self::TooManyDecimals => None,
+        }
+    }
+}
+
+impl FromStr for RayAngle {
+    type Err = ParseRayAngleError;
+    /// Parses a fraction of a turn such as "1/3", or a decimal number such as "0.25".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some((numerator, denominator)) = s.split_once('/') {
+            let numerator = numerator
+                .trim()
+                .parse()
+                .map_err(ParseRayAngleError::InvalidNumber)?;
+            let denominator: u64 = denominator
+                .trim()
+                .parse()
+                .map_err(ParseRayAngleError::InvalidNumber)?;
+            let denominator =
+                NonZeroU64::new(denominator).ok_or(ParseRayAngleError::ZeroDenominator)?;
+            return Ok(Self::new(numerator, denominator));
+        }
+
+        let (whole, decimals) = s.split_once('.').unwrap_or((s, ""));
+        // Whole turns do not change the angle, but must still be a number.
+        if !whole.is_empty() || decimals.is_empty() {
+            whole
+                .parse::<u64>()
+                .map_err(ParseRayAngleError::InvalidNumber)?;
+        }
+        if decimals.is_empty() {
+            return Ok(Self::new(0, NonZeroU64::MIN));
+        }
+        let digits =
+            u32::try_from(decimals.len()).map_err(|_| ParseRayAngleError::TooManyDecimals)?;
+        let denominator = 10_u64
+            .checked_pow(digits)
+            .and_then(NonZeroU64::new)
+            .ok_or(ParseRayAngleError::TooManyDecimals)?;
+        let numerator = decimals
+            .parse()
+            .map_err(ParseRayAngleError::InvalidNumber)?;
+        Ok(Self::new(numerator, denominator))
+    }
+}
+
+/// Traces the external ray of the set of z -> z^exponent + c with the given angle,
+/// from far outside the set towards where it lands on it.
+/// The tracing stops once a full iteration moves the ray less than `resolution`,
+/// after `max_depth` iterations, or if the points can no longer be computed accurately.
+/// The points are returned in the order they were traced in.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{external_ray, Exponent};
+/// // The ray at half a turn runs along the negative real axis and lands on the tip of the set at -2.
+/// let ray = external_ray("1/2".parse().unwrap(), Exponent::TWO, 1e-4, 1000.try_into().unwrap());
+/// let end = ray[ray.len() - 1];
+/// assert!((end.re + 2.0).abs() < 1e-3 && end.im.abs() < 1e-9);
+/// ```
+#[must_use]
+pub fn external_ray(
+    angle: RayAngle,
+    exponent: Exponent,
+    resolution: f64,
+    max_depth: NonZeroU32,
+) -> Vec<Complex> {
+    let degree = exponent.get();
+    let power = i32::from(degree);
+    let degree_f = f64::from(degree);
+
+    let mut angle = angle;
+    let start = |radius: f64, angle: RayAngle| {
+        let (sin, cos) = (TAU * angle.turns()).sin_cos();
+        Complex::new(radius * cos, radius * sin)
+    };
+    let mut c = start(RAY_RADIUS, angle);
+    let mut points = vec![c];
+
+    for depth in 0..max_depth.get() {
+        let band_start = c;
+        for step in 0..SHARPNESS {
+            // Moves from just inside the ray radius to its exponent-th root over the band,
+            // which z^exponent then maps back out to the ray radius at the next depth.
+            let radius =
+                RAY_RADIUS.powf(degree_f.powf(-(f64::from(step) + 0.5) / f64::from(SHARPNESS)));
+            let target = start(radius, angle);
+
+            // Newton's method for z_(depth + 1)(c) = target.
+            for _ in 0..MAX_NEWTON_STEPS {
+                let (mut z, mut dz) = (Complex::ZERO, Complex::ZERO);
+                for _ in 0..=depth {
+                    dz = Complex::from(degree_f) * z.powi(power - 1) * dz + Complex::from(1.0);
+                    z = z.powi(power) + c;
+                }
+                let next = c - (z - target) / dz;
+                if !(next.re.is_finite() && next.im.is_finite()) {
+                    return points;
+                }
+                let moved = (next - c).abs();
+                c = next;
+                if moved <= resolution * NEWTON_TOLERANCE {
+                    break;
+                }
+            }
+            points.push(c);
+        }
+        if (c - band_start).abs() < resolution {
+            break;
+        }
+        angle = angle.multiplied(degree);
+    }
+    points
+}
+
+/// An external ray, as points in the complex plane.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExternalRay {
+    pub angle: RayAngle,
+    /// The points along the ray, from far outside the set towards where it lands.
+    pub points: Vec<Complex>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExternalRayError {
+    /// External rays are only traced for the Mandelbrot and Multibrot sets.
+    UnsupportedFormula,
+}
+
+impl fmt::Display for ExternalRayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormula => write!(
+                f,
+                "external rays can only be traced for the Mandelbrot and Multibrot sets"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExternalRayError {}
+
+/// Traces the external rays with the given angles in parallel, to the resolution of the image
+/// described by the render parameters and region. The rays are traced for at most as many iterations
+/// as the points of the image are iterated.
+///
+/// # Errors
+///
+/// Returns an error if the formula of the render parameters is not [`Formula::Mandelbrot`].
+pub fn external_rays(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    angles: &[RayAngle],
+) -> Result<Vec<ExternalRay>, ExternalRayError> {
+    if render_parameters.formula != Formula::Mandelbrot {
+        return Err(ExternalRayError::UnsupportedFormula);
+    }
+    // Half a pixel, so that the rays end within a pixel of where they land.
+    let resolution = (render_region.real_distance / f64::from(render_parameters.x_resolution))
+        .min(render_region.imag_distance / f64::from(render_parameters.y_resolution))
+        / 2.0;
+    Ok(angles
+        .par_iter()
+        .map(|&angle| ExternalRay {
+            angle,
+            points: external_ray(
+                angle,
+                render_parameters.exponent,
+                resolution,
+                // Rays are traced to a depth of at most one band per iteration.
+                NonZeroU32::try_from(render_parameters.max_iterations).unwrap_or(NonZeroU32::MAX),
+            ),
+        })
+        .collect())
+}
+
+/// Draws the external rays on the image, which shows the given region of the complex plane,
+/// with the given color. The lines are one pixel wide.
+pub fn draw_external_rays(
+    image: &mut DynamicImage,
+    rays: &[ExternalRay],
+    render_region: Frame,
+    color: [u8; 3],
+) {
+    let (width, height) = (f64::from(image.width()), f64::from(image.height()));
+    let left = render_region.center_real - render_region.real_distance / 2.0;
+    let top = render_region.center_imag + render_region.imag_distance / 2.0;
+    let to_pixel = |c: &Complex| {
+        (
+            (c.re - left) / render_region.real_distance * width,
+            (top - c.im) / render_region.imag_distance * height,
+        )
+    };
+    for ray in rays {
+        for pair in ray.points.windows(2) {
+            draw_line(image, to_pixel(&pair[0]), to_pixel(&pair[1]), color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_external_rays {
+    use super::*;
+    use color_space::SupportedColorType;
+
+    fn angle(s: &str) -> RayAngle {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn check_angle_parsing() {
+        assert_eq!(angle("2/6"), angle("1/3"));
+        assert_eq!(angle(" 4/3 ").to_string(), "1/3");
+        assert_eq!(angle("0.25").to_string(), "1/4");
+        assert_eq!(angle("1.5"), angle("1/2"));
+        assert_eq!(angle("0"), angle(".0"));
+        assert_eq!(angle("0").turns(), 0.0);
+        assert_eq!(
+            "1/0".parse::<RayAngle>(),
+            Err(ParseRayAngleError::ZeroDenominator)
+        );
+        assert_eq!(
+            "0.12345678901234567890".parse::<RayAngle>(),
+            Err(ParseRayAngleError::TooManyDecimals)
+        );
+        for invalid in ["", "third", "-1/3", "0.-5", "1/3/4", "."] {
+            assert!(
+                matches!(
+                    invalid.parse::<RayAngle>(),
+                    Err(ParseRayAngleError::InvalidNumber(_))
+                ),
+                "{invalid}"
+            );
+        }
+        // Doubling is exact, so periodic angles come back to themselves.
+        let mut doubled = angle("3/15");
+        for _ in 0..4 {
+            doubled = doubled.multiplied(2);
+        }
+        assert_eq!(doubled, angle("3/15"));
+    }
+
+    #[test]
+    fn check_landing_points() {
+        let max_depth = NonZeroU32::new(10_000).unwrap();
+        let end = |angle_: &str, exponent: Exponent| {
+            let ray = external_ray(angle(angle_), exponent, 1e-5, max_depth);
+            ray[ray.len() - 1]
+        };
+        let close = |a: Complex, b: Complex, tolerance: f64| (a - b).abs() < tolerance;
+
+        // The tip of the set, and the cusp of the main cardioid.
+        assert!(close(
+            end("1/2", Exponent::TWO),
+            Complex::new(-2.0, 0.0),
+            1e-3
+        ));
+        assert!(close(
+            end("0", Exponent::TWO),
+            Complex::new(0.25, 0.0),
+            1e-2
+        ));
+        // The rays at 1/3 and 2/3 of a turn land on the root of the period 2 bulb,
+        // and the ones at 1/7 and 2/7 on the root of the upper period 3 bulb.
+        assert!(close(
+            end("1/3", Exponent::TWO),
+            Complex::new(-0.75, 0.0),
+            1e-2
+        ));
+        assert!(close(
+            end("2/3", Exponent::TWO),
+            Complex::new(-0.75, 0.0),
+            1e-2
+        ));
+        let root = Complex::new(-0.125, 0.649_519_052_838_329);
+        assert!(close(end("1/7", Exponent::TWO), root, 1e-2));
+        assert!(close(end("2/7", Exponent::TWO), root, 1e-2));
+        // The set of z^3 + c is symmetric under c -> -c, which maps the ray at 1/4 of a turn
+        // onto the one at 3/4.
+        let upper = end("1/4", Exponent::try_from(3).unwrap());
+        let lower = end("3/4", Exponent::try_from(3).unwrap());
+        assert!(close(upper, -lower, 1e-3));
+
+        // A ray stays outside the set, and the conjugate angle traces the mirrored ray.
+        let ray = external_ray(angle("1/7"), Exponent::TWO, 1e-4, max_depth);
+        let mirrored = external_ray(angle("6/7"), Exponent::TWO, 1e-4, max_depth);
+        assert_eq!(ray.len(), mirrored.len());
+        for (a, b) in ray.iter().zip(&mirrored) {
+            assert!(close(*a, b.conj(), 1e-9));
+        }
+    }
+
+    #[test]
+    fn check_drawing() {
+        let mut params = RenderParameters::try_new(
+            30.try_into().unwrap(),
+            20.try_into().unwrap(),
+            200.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let region = Frame::new(-0.75, 0.0, 3.0, 2.0);
+        let rays = external_rays(&params, region, &[angle("1/2")]).unwrap();
+        let mut image = DynamicImage::new_rgb8(30, 20);
+        draw_external_rays(&mut image, &rays, region, [255, 0, 0]);
+        let image = image.to_rgb8();
+        // The ray runs along the real axis from the left edge of the image to the tip of the set
+        // at -2, a twelfth of the way in.
+        for x in 0..30 {
+            let drawn = image.get_pixel(x, 10)[0] == 255 || image.get_pixel(x, 9)[0] == 255;
+            assert_eq!(drawn, x <= 2, "{x}");
+        }
+
+        params.formula = Formula::Mandelbar;
+        assert_eq!(
+            external_rays(&params, region, &[angle("1/2")]),
+            Err(ExternalRayError::UnsupportedFormula)
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Fixed point numbers that the escape time iteration can be done in instead of floating point numbers,
+//! for targets whose floating point units are slow or missing.
+//!
+//! Only `core` is used, so that the iteration can be done in them without the standard library.
+
+use core::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub};
+
+use crate::math::round;
+use crate::{Float, Precision};
+
+/// A signed fixed point number with [`Fixed::FRACTIONAL_BITS`] fractional bits stored in an `i64`,
+/// i.e. a number in the Q7.56 format. It is between -128 and 128 with a resolution of 2^-56,
+/// which is finer than the resolution of an `f64` for numbers larger than 1/16 in magnitude.
+///
+/// The arithmetic saturates at the ends of the range instead of overflowing, so the orbit of a point
+/// that escapes keeps a large magnitude. |z|^2 can not exceed the range, so escape radii above
+/// the square root of [`Fixed::MAX_BAILOUT_SQR`], about 11.3, are lowered to that radius.
+/// Points may then escape an iteration earlier than they would in `f64`,
+/// and higher exponents can lose some of the smoothing of the colors.
+/// The threshold of the derivative bailout is below the resolution, so it never stops an iteration.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{iterate, Exponent, Fixed, Float};
+/// # use core::num::NonZeroU64;
+/// const MAXITERS: NonZeroU64 = NonZeroU64::new(100).unwrap();
+/// assert_eq!(
+///     iterate(Fixed::from_f64(0.3), Fixed::from_f64(0.6), Exponent::TWO, MAXITERS).0,
+///     iterate(0.3, 0.6, Exponent::TWO, MAXITERS).0,
+/// );
+/// assert_eq!((Fixed::from_f64(1.5) * Fixed::from_f64(-0.5)).to_f64(), -0.75);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed(i64);
+
+impl Fixed {
+    /// The number of bits after the binary point.
+    pub const FRACTIONAL_BITS: u32 = 56;
+
+    /// The largest number, just below 128.
+    pub const MAX: Self = Self(i64::MAX);
+
+    /// The smallest number, just above -128.
+    pub const MIN: Self = Self(-i64::MAX);
+
+    /// Stands in for the NaN that the iteration returns for points that are known to be inside the set.
+    /// It is only ever returned, never computed with.
+    const NOT_A_NUMBER: Self = Self(i64::MIN);
+
+    /// The number that the bits are the number times, 2^[`Fixed::FRACTIONAL_BITS`].
+    const SCALE: f64 = (1_u64 << Self::FRACTIONAL_BITS) as f64;
+
+    /// Returns the number whose bits are the given integer, i.e. `bits` / 2^[`Fixed::FRACTIONAL_BITS`].
+    #[must_use]
+    pub const fn from_bits(bits: i64) -> Self {
+        Self(saturate(bits))
+    }
+
+    /// Returns the bits of the number, i.e. the number times 2^[`Fixed::FRACTIONAL_BITS`] rounded to an integer.
+    #[must_use]
+    pub const fn to_bits(self) -> i64 {
+        self.0
+    }
+}
+
+/// Keeps the bits of a number within the range of [`Fixed`], away from the bits of [`Fixed::NOT_A_NUMBER`].
+const fn saturate(bits: i64) -> i64 {
+    if bits < -i64::MAX {
+        -i64::MAX
+    } else {
+        bits
+    }
+}
+
+impl Add for Fixed {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self(saturate(self.0.saturating_add(rhs.0)))
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self(saturate(self.0.saturating_sub(rhs.0)))
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        // The product of two numbers has twice the fractional bits, and is rounded to the nearest number.
+        let product = (i128::from(self.0) * i128::from(rhs.0) + (1 << (Self::FRACTIONAL_BITS - 1)))
+            >> Self::FRACTIONAL_BITS;
+        // The clamping makes sure that the cast does not truncate.
+        #[allow(clippy::cast_possible_truncation)]
+        Self(product.clamp(i128::from(Self::MIN.0), i128::from(Self::MAX.0)) as i64)
+    }
+}
+
+impl MulAssign for Fixed {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self(self.0.saturating_neg())
+    }
+}
+
+impl Float for Fixed {
+    const NAN: Self = Self::NOT_A_NUMBER;
+    const PRECISION: Precision = Precision::Fixed;
+    // Just below the largest number, which |z|^2 saturates to.
+    const MAX_BAILOUT_SQR: f64 = 127.0;
+
+    fn from_f64(x: f64) -> Self {
+        if x.is_nan() {
+            Self::NOT_A_NUMBER
+        } else {
+            // Casts from floats to integers saturate, which is what the arithmetic does as well.
+            #[allow(clippy::cast_possible_truncation)]
+            let bits = round(x * Self::SCALE) as i64;
+            Self(saturate(bits))
+        }
+    }
+
+    fn to_f64(self) -> f64 {
+        if self == Self::NOT_A_NUMBER {
+            f64::NAN
+        } else {
+            // The bits beyond the precision of an `f64` are rounded away.
+            #[allow(clippy::cast_precision_loss)]
+            let x = self.0 as f64 / Self::SCALE;
+            x
+        }
+    }
+
+    fn abs(self) -> Self {
+        Self(self.0.saturating_abs())
+    }
+}
+
+#[cfg(test)]
+mod test_fixed {
+    use super::*;
+    use crate::float::pixels_differing_from_double;
+    use crate::{iterate_with_escape_radius, EscapeRadius, Exponent};
+
+    #[test]
+    fn check_arithmetic() {
+        let x = Fixed::from_f64(3.25);
+        let y = Fixed::from_f64(-0.125);
+        assert_eq!((x + y).to_f64(), 3.125);
+        assert_eq!((x - y).to_f64(), 3.375);
+        assert_eq!((x * y).to_f64(), -0.40625);
+        assert_eq!((-x).abs(), x);
+
+        // The range saturates instead of wrapping around.
+        assert_eq!(Fixed::from_f64(1000.0), Fixed::MAX);
+        assert_eq!(Fixed::from_f64(-1000.0), Fixed::MIN);
+        assert_eq!(x * x * x * x * x, Fixed::MAX);
+        assert_eq!(-(x * x * x * x * x), Fixed::MIN);
+        assert_eq!(Fixed::MIN - x, Fixed::MIN);
+
+        // Products are rounded to the nearest number.
+        let smallest = Fixed::from_bits(1);
+        assert_eq!(smallest * Fixed::from_f64(0.5), smallest);
+        assert_eq!(smallest * Fixed::from_f64(0.25), Fixed::from_bits(0));
+
+        assert!(Fixed::NAN.to_f64().is_nan());
+        assert_eq!(Fixed::from_f64(f64::NAN), Fixed::NAN);
+    }
+
+    #[test]
+    fn check_escape_radius_beyond_range() {
+        let max_iterations = 100.try_into().unwrap();
+        for radius in [12.0, 50.0] {
+            let radius = EscapeRadius::try_from(radius).unwrap();
+            let fixed = iterate_with_escape_radius(
+                Fixed::from_f64(1.0),
+                Fixed::from_f64(1.0),
+                Exponent::TWO,
+                max_iterations,
+                radius,
+            );
+            let double =
+                iterate_with_escape_radius(1.0, 1.0, Exponent::TWO, max_iterations, radius);
+            assert_eq!(fixed.0, double.0, "escape radius {radius}");
+            assert_eq!(fixed.0, 4);
+        }
+    }
+
+    #[test]
+    fn check_fixed_point_render() {
+        let differing = pixels_differing_from_double::<Fixed>();
+        assert!(differing < 64 * 48 / 100, "{differing} pixels differ");
+    }
+}
@@ -0,0 +1,194 @@
+use core::fmt::Debug;
+use core::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub};
+
+use crate::Frame;
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+    impl Sealed for crate::Fixed {}
+}
+
+/// A number type that the escape time iteration can be done in.
+/// This trait is sealed and implemented for `f32`, `f64` and [`Fixed`](crate::Fixed).
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{iterate, Exponent};
+/// # use core::num::NonZeroU64;
+/// const MAXITERS: NonZeroU64 = NonZeroU64::new(100).unwrap();
+/// // Away from the boundary of the set the precision makes no difference.
+/// assert_eq!(
+///     iterate(0.3_f32, 0.6_f32, Exponent::TWO, MAXITERS).0,
+///     iterate(0.3_f64, 0.6_f64, Exponent::TWO, MAXITERS).0,
+/// );
+/// ```
+pub trait Float:
+    Copy
+    + Debug
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + MulAssign
+    + Send
+    + Sync
+    + private::Sealed
+{
+    const NAN: Self;
+
+    /// The precision that renders are iterated in this type with.
+    const PRECISION: Precision;
+
+    /// The largest |z|^2 that an orbit can be compared against in this type.
+    /// Larger squared escape radii are lowered to it, so that escaping orbits can still exceed them.
+    const MAX_BAILOUT_SQR: f64 = f64::INFINITY;
+
+    /// Converts from an `f64`, rounding to the nearest representable value.
+    fn from_f64(x: f64) -> Self;
+
+    /// Converts to an `f64`, without loss for the floating point types.
+    fn to_f64(self) -> f64;
+
+    /// Returns the absolute value.
+    fn abs(self) -> Self;
+}
+
+impl Float for f32 {
+    const NAN: Self = Self::NAN;
+    const PRECISION: Precision = Precision::Single;
+
+    fn from_f64(x: f64) -> Self {
+        // Rounding is the point of the conversion.
+        #[allow(clippy::cast_possible_truncation)]
+        let x = x as Self;
+        x
+    }
+
+    fn to_f64(self) -> f64 {
+        self.into()
+    }
+
+    fn abs(self) -> Self {
+        self.abs()
+    }
+}
+
+impl Float for f64 {
+    const NAN: Self = Self::NAN;
+    const PRECISION: Precision = Precision::Double;
+
+    fn from_f64(x: f64) -> Self {
+        x
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn abs(self) -> Self {
+        self.abs()
+    }
+}
+
+/// The precision of the numbers that an image is rendered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Precision {
+    /// Iterate in `f32`. Faster on some hardware, but the image becomes blocky
+    /// once the distance between pixels approaches the precision of an `f32`.
+    Single,
+    /// Iterate in `f64`.
+    #[default]
+    Double,
+    /// Iterate in [`Fixed`](crate::Fixed) point numbers, which only needs integer arithmetic.
+    /// Slower than `f64` on processors with a fast floating point unit, but resolves pixels
+    /// about as closely as `f64` does in the usual views. The coloring is still done in `f64`.
+    Fixed,
+}
+
+impl Precision {
+    /// Returns the lowest precision that can resolve the individual pixels of an image of the given frame
+    /// that is `y_resolution` pixels tall.
+    ///
+    /// Note that formulas given as [`Fractal`](crate::Fractal)s are always iterated in `f64`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::{Frame, Precision};
+    /// # use core::num::NonZeroU32;
+    /// let y_resolution = NonZeroU32::new(1080).unwrap();
+    /// let overview = Frame::new(-0.75, 0.0, 3.5, 2.0);
+    /// assert_eq!(Precision::lowest_sufficient(overview, y_resolution), Precision::Single);
+    ///
+    /// let deep_zoom = Frame::new(-0.75, 0.1, 3.5e-6, 2e-6);
+    /// assert_eq!(Precision::lowest_sufficient(deep_zoom, y_resolution), Precision::Double);
+    /// ```
+    #[must_use]
+    pub fn lowest_sufficient(frame: Frame, y_resolution: core::num::NonZeroU32) -> Self {
+        /// The number of distinct values that should fit between adjacent pixels,
+        /// so that supersamples and the iteration itself are also resolved.
+        const MARGIN: f32 = 256.0;
+
+        let pixel_size = frame.imag_distance / f64::from(y_resolution.get());
+        let largest_coordinate = (frame.center_real.abs() + frame.real_distance)
+            .max(frame.center_imag.abs() + frame.imag_distance);
+        if pixel_size > largest_coordinate * f64::from(f32::EPSILON * MARGIN) {
+            Self::Single
+        } else {
+            Self::Double
+        }
+    }
+}
+
+/// Renders a 64x48 image of a shallow view in `f64` and in `F`, and returns how many of its pixels
+/// differ by more than a little in some channel.
+#[cfg(test)]
+pub(crate) fn pixels_differing_from_double<F: Float>() -> usize {
+    use image::GenericImageView;
+
+    let mut params = crate::RenderParameters::try_new(
+        64.try_into().unwrap(),
+        48.try_into().unwrap(),
+        255.try_into().unwrap(),
+        2.try_into().unwrap(),
+        color_space::SupportedColorType::Rgb8,
+    )
+    .unwrap();
+    let frame = Frame::new(-0.75, 0.1, 3.0, 2.25);
+    assert_eq!(
+        Precision::lowest_sufficient(frame, params.y_resolution.into()),
+        Precision::Single
+    );
+
+    let double = crate::render(&params, frame, false);
+    params.precision = F::PRECISION;
+    let other = crate::render(&params, frame, false);
+
+    double
+        .pixels()
+        .zip(other.pixels())
+        .filter(|((_, _, a), (_, _, b))| a.0.iter().zip(b.0).any(|(&x, y)| x.abs_diff(y) > 2))
+        .count()
+}
+
+#[cfg(test)]
+mod test_float {
+    use super::*;
+
+    #[test]
+    fn check_single_precision_render() {
+        // At shallow zooms the two precisions should give nearly the same image.
+        let differing = pixels_differing_from_double::<f32>();
+        assert!(differing < 64 * 48 / 20, "{differing} pixels differ");
+    }
+}
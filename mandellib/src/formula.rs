@@ -0,0 +1,503 @@
+use core::fmt;
+use core::num::NonZeroU64;
+use core::str::FromStr;
+use std::sync::Arc;
+
+use crate::{
+    complex_powi, iterate_with_escape_radius, AbsVariant, Complex, EscapeRadius, Exponent, Float,
+    Fractal, Hybrid, Mandelbrot,
+};
+
+/// The iteration function that defines the fractal.
+#[derive(Debug, Clone, Default)]
+pub enum Formula {
+    /// z -> z^d + c, which gives the Mandelbrot set for d = 2 and Multibrot sets otherwise.
+    #[default]
+    Mandelbrot,
+    /// z -> conj(z)^d + c, which gives the Tricorn for d = 2 and Multicorns otherwise.
+    Mandelbar,
+    /// One of the quadratic fractals that take the absolute value of parts of z^2, like the Burning Ship.
+    /// Ignores the exponent.
+    Abs(AbsVariant),
+    /// A repeating sequence of the other built-in formulas, see [`Hybrid`].
+    Hybrid(Hybrid),
+    /// Any other fractal, e.g. a [`CustomFormula`](crate::CustomFormula) or one defined outside this crate.
+    /// Ignores the exponent.
+    Custom(Arc<dyn Fractal>),
+}
+
+// Custom fractals can not be compared, so they are only equal if they are the same instance.
+impl PartialEq for Formula {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Mandelbrot, Self::Mandelbrot) | (Self::Mandelbar, Self::Mandelbar) => true,
+            (Self::Abs(a), Self::Abs(b)) => a == b,
+            (Self::Hybrid(a), Self::Hybrid(b)) => a == b,
+            (Self::Custom(a), Self::Custom(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Formula {}
+
+impl Formula {
+    /// All the built-in formulas.
+    pub const ALL: [Self; 7] = [
+        Self::Mandelbrot,
+        Self::Mandelbar,
+        Self::Abs(AbsVariant::BurningShip),
+        Self::Abs(AbsVariant::Celtic),
+        Self::Abs(AbsVariant::PerpendicularMandelbrot),
+        Self::Abs(AbsVariant::PerpendicularBurningShip),
+        Self::Abs(AbsVariant::Buffalo),
+    ];
+
+    /// Iterates the formula on the given c in the same way as [`iterate`](crate::iterate) does for the Mandelbrot set.
+    /// Returns a tuple of `(iterations, final real part of z, final imaginary part of z)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::{Exponent, Formula};
+    /// # use core::num::NonZeroU64;
+    /// const MAXITERS: NonZeroU64 = NonZeroU64::new(100).unwrap();
+    /// // The point 0.5i is in the Mandelbrot set, but not in the Tricorn.
+    /// assert_eq!(Formula::Mandelbrot.iterate(0.0, 0.5, Exponent::TWO, MAXITERS).0, MAXITERS.get());
+    /// assert_ne!(Formula::Mandelbar.iterate(0.0, 0.5, Exponent::TWO, MAXITERS).0, MAXITERS.get());
+    /// ```
+    #[must_use]
+    pub fn iterate<F: Float>(
+        &self,
+        c_re: F,
+        c_im: F,
+        exponent: Exponent,
+        max_iterations: NonZeroU64,
+    ) -> (u64, F, F) {
+        self.iterate_with_escape_radius(c_re, c_im, exponent, max_iterations, EscapeRadius::DEFAULT)
+    }
+
+    /// Works like [`iterate`](Self::iterate), but stops once |z| exceeds the given escape radius.
+    /// Custom fractals ignore the radius and use their own [`bailout_sqr`](Fractal::bailout_sqr).
+    #[must_use]
+    pub fn iterate_with_escape_radius<F: Float>(
+        &self,
+        c_re: F,
+        c_im: F,
+        exponent: Exponent,
+        max_iterations: NonZeroU64,
+        escape_radius: EscapeRadius,
+    ) -> (u64, F, F) {
+        match self {
+            Self::Mandelbrot => {
+                iterate_with_escape_radius(c_re, c_im, exponent, max_iterations, escape_radius)
+            }
+            Self::Mandelbar => {
+                iterate_mandelbar(c_re, c_im, exponent, max_iterations, escape_radius)
+            }
+            Self::Abs(variant) => {
+                iterate_abs_variant(*variant, c_re, c_im, max_iterations, escape_radius)
+            }
+            Self::Hybrid(hybrid) => {
+                iterate_hybrid(hybrid, c_re, c_im, exponent, max_iterations, escape_radius)
+            }
+            // Custom fractals are always iterated in f64.
+            Self::Custom(fractal) => {
+                let (iterations, z) =
+                    fractal.iterate(Complex::new(c_re.to_f64(), c_im.to_f64()), max_iterations);
+                (iterations, F::from_f64(z.re), F::from_f64(z.im))
+            }
+        }
+    }
+
+    /// Returns the number of uncounted iterations that escaping points are iterated for
+    /// with the given escape radius, see [`EscapeRadius::smoothing_iterations`].
+    pub(crate) fn smoothing_iterations(&self, escape_radius: EscapeRadius) -> u32 {
+        match self {
+            Self::Mandelbrot | Self::Mandelbar | Self::Abs(_) | Self::Hybrid(_) => {
+                escape_radius.smoothing_iterations()
+            }
+            Self::Custom(_) => 0,
+        }
+    }
+
+    /// Returns the symmetries of the fractal defined by this formula with the given exponent.
+    #[must_use]
+    pub fn symmetry(&self, exponent: Exponent) -> Symmetry {
+        let d = exponent.get();
+        match self {
+            Self::Mandelbrot => Mandelbrot { exponent }.symmetry(),
+            // conj(z)^d + c is invariant under rotating c by a (d+1):th of a turn.
+            Self::Mandelbar => Symmetry {
+                conjugation: true,
+                rotational_order: d.saturating_add(1),
+            },
+            Self::Abs(variant) => Symmetry {
+                conjugation: variant.is_conjugation_symmetric(),
+                rotational_order: 1,
+            },
+            // A hybrid only has the symmetries that all of its formulas have.
+            Self::Hybrid(hybrid) => hybrid.formulas().iter().fold(
+                Symmetry {
+                    conjugation: true,
+                    rotational_order: 0,
+                },
+                |symmetry, formula| {
+                    let formula_symmetry = formula.symmetry(exponent);
+                    Symmetry {
+                        conjugation: symmetry.conjugation && formula_symmetry.conjugation,
+                        rotational_order: gcd(
+                            symmetry.rotational_order,
+                            formula_symmetry.rotational_order,
+                        ),
+                    }
+                },
+            ),
+            Self::Custom(fractal) => fractal.symmetry(),
+        }
+    }
+
+    /// Returns the rate at which |z| grows once it is large,
+    /// i.e. the degree of the iteration function as a polynomial in z.
+    #[must_use]
+    pub fn degree(&self, exponent: Exponent) -> f64 {
+        match self {
+            Self::Mandelbrot | Self::Mandelbar => exponent.into(),
+            Self::Abs(_) => 2.0,
+            // |z| grows with the product of the degrees over the whole sequence,
+            // so the average growth per iteration is their geometric mean.
+            Self::Hybrid(hybrid) => {
+                let formulas = hybrid.formulas();
+                // Hybrids are short, so the length is exact.
+                #[allow(clippy::cast_precision_loss)]
+                let length = formulas.len() as f64;
+                (formulas
+                    .iter()
+                    .map(|formula| formula.degree(exponent).ln())
+                    .sum::<f64>()
+                    / length)
+                    .exp()
+            }
+            Self::Custom(fractal) => fractal.degree(),
+        }
+    }
+}
+
+/// Describes the symmetries of a fractal in the complex plane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symmetry {
+    /// True if the fractal is symmetric under complex conjugation,
+    /// i.e. mirroring in the real axis.
+    pub conjugation: bool,
+    /// The fractal is symmetric under rotation around the origin
+    /// by a `rotational_order`:th of a turn.
+    /// A value of 1 means that there is no rotational symmetry.
+    pub rotational_order: u8,
+}
+
+/// Iterates the function z -> conj(z)^d + c.
+fn iterate_mandelbar<F: Float>(
+    c_re: F,
+    c_im: F,
+    exponent: Exponent,
+    max_iterations: NonZeroU64,
+    escape_radius: EscapeRadius,
+) -> (u64, F, F) {
+    let max_iterations = max_iterations.get();
+    let bailout_sqr = escape_radius.bailout_sqr();
+
+    let mut z_re = c_re;
+    let mut z_im = c_im;
+    let mut mag_sqr = c_re * c_re + c_im * c_im;
+
+    let mut iterations = 1;
+
+    if exponent == Exponent::TWO {
+        let mut z_re_sqr = c_re * c_re;
+        let mut z_im_sqr = c_im * c_im;
+        while iterations < max_iterations && mag_sqr <= bailout_sqr {
+            // Same as the Mandelbrot loop, except that the sign of the imaginary part is flipped.
+            z_im *= F::from_f64(-2.0) * z_re;
+            z_im += c_im;
+            z_re = z_re_sqr - z_im_sqr + c_re;
+            z_re_sqr = z_re * z_re;
+            z_im_sqr = z_im * z_im;
+            mag_sqr = z_re_sqr + z_im_sqr;
+            iterations += 1;
+        }
+    } else {
+        while iterations < max_iterations && mag_sqr <= bailout_sqr {
+            // conj(z)^d = conj(z^d)
+            let (w_re, w_im) = complex_powi(z_re, z_im, exponent.get());
+            z_re = w_re + c_re;
+            z_im = c_im - w_im;
+            mag_sqr = z_re * z_re + z_im * z_im;
+            iterations += 1;
+        }
+    }
+
+    if mag_sqr > bailout_sqr {
+        for _ in 0..escape_radius.smoothing_iterations() {
+            let (w_re, w_im) = complex_powi(z_re, z_im, exponent.get());
+            z_re = w_re + c_re;
+            z_im = c_im - w_im;
+        }
+    }
+
+    (iterations, z_re, z_im)
+}
+
+/// Iterates the quadratic fractal given by the variant.
+fn iterate_abs_variant<F: Float>(
+    variant: AbsVariant,
+    c_re: F,
+    c_im: F,
+    max_iterations: NonZeroU64,
+    escape_radius: EscapeRadius,
+) -> (u64, F, F) {
+    let max_iterations = max_iterations.get();
+    let bailout_sqr = escape_radius.bailout_sqr();
+
+    let mut z_re = c_re;
+    let mut z_im = c_im;
+    let mut mag_sqr = c_re * c_re + c_im * c_im;
+
+    let mut iterations = 1;
+
+    while iterations < max_iterations && mag_sqr <= bailout_sqr {
+        (z_re, z_im) = variant.step(z_re, z_im, c_re, c_im);
+        mag_sqr = z_re * z_re + z_im * z_im;
+        iterations += 1;
+    }
+
+    if mag_sqr > bailout_sqr {
+        for _ in 0..escape_radius.smoothing_iterations() {
+            (z_re, z_im) = variant.step(z_re, z_im, c_re, c_im);
+        }
+    }
+
+    (iterations, z_re, z_im)
+}
+
+/// Iterates the repeating sequence of formulas of the hybrid.
+fn iterate_hybrid<F: Float>(
+    hybrid: &Hybrid,
+    c_re: F,
+    c_im: F,
+    exponent: Exponent,
+    max_iterations: NonZeroU64,
+    escape_radius: EscapeRadius,
+) -> (u64, F, F) {
+    let max_iterations = max_iterations.get();
+    let bailout_sqr = escape_radius.bailout_sqr();
+
+    let mut z_re = c_re;
+    let mut z_im = c_im;
+    let mut mag_sqr = c_re * c_re + c_im * c_im;
+
+    let mut iterations = 1;
+
+    while iterations < max_iterations && mag_sqr <= bailout_sqr {
+        (z_re, z_im) = hybrid.step(iterations, z_re, z_im, c_re, c_im, exponent);
+        mag_sqr = z_re * z_re + z_im * z_im;
+        iterations += 1;
+    }
+
+    if mag_sqr > bailout_sqr {
+        for n in iterations..(iterations + u64::from(escape_radius.smoothing_iterations())) {
+            (z_re, z_im) = hybrid.step(n, z_re, z_im, c_re, c_im, exponent);
+        }
+    }
+
+    (iterations, z_re, z_im)
+}
+
+/// Returns the greatest common divisor of the numbers, where the divisor of 0 and n is n.
+fn gcd(mut a: u8, mut b: u8) -> u8 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mandelbrot => write!(f, "mandelbrot"),
+            Self::Mandelbar => write!(f, "mandelbar"),
+            Self::Abs(variant) => write!(f, "{variant}"),
+            Self::Hybrid(hybrid) => write!(f, "{hybrid}"),
+            Self::Custom(fractal) => write!(f, "{fractal}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFormulaError(String);
+
+impl fmt::Display for ParseFormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown formula \"{}\", expected one of {}",
+            self.0,
+            Formula::ALL
+                .iter()
+                .map(|formula| format!("\"{formula}\""))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ParseFormulaError {}
+
+impl FromStr for Formula {
+    type Err = ParseFormulaError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // A comma separated list of formulas is a hybrid.
+        if s.contains(',') {
+            let formulas = s
+                .split(',')
+                .map(|formula| formula.trim().parse())
+                .collect::<Result<Vec<_>, _>>()?;
+            return Hybrid::try_new(formulas)
+                .map(Self::Hybrid)
+                .map_err(|_| ParseFormulaError(s.to_owned()));
+        }
+        // "Burning Ship", "burning_ship" and "burning-ship" are all accepted.
+        match s.to_ascii_lowercase().replace([' ', '_'], "-").as_str() {
+            "mandelbrot" | "multibrot" => Ok(Self::Mandelbrot),
+            "mandelbar" | "tricorn" | "multicorn" => Ok(Self::Mandelbar),
+            name => AbsVariant::ALL
+                .into_iter()
+                .find(|variant| variant.to_string() == name)
+                .map(Self::Abs)
+                .ok_or_else(|| ParseFormulaError(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_formula {
+    use super::*;
+
+    #[test]
+    fn check_tricorn_symmetry() {
+        let max_iterations = NonZeroU64::new(255).unwrap();
+        let (sin, cos) = (2.0 * core::f64::consts::PI / 3.0).sin_cos();
+        for (c_re, c_im) in [(0.3, 0.5), (-1.2, 0.4), (0.1, -0.9)] {
+            // The Tricorn is symmetric under conjugation
+            assert_eq!(
+                Formula::Mandelbar
+                    .iterate(c_re, c_im, Exponent::TWO, max_iterations)
+                    .0,
+                Formula::Mandelbar
+                    .iterate(c_re, -c_im, Exponent::TWO, max_iterations)
+                    .0
+            );
+            // and under rotation by a third of a turn.
+            let (r_re, r_im) = (c_re * cos - c_im * sin, c_re * sin + c_im * cos);
+            assert_eq!(
+                Formula::Mandelbar
+                    .iterate(c_re, c_im, Exponent::TWO, max_iterations)
+                    .0,
+                Formula::Mandelbar
+                    .iterate(r_re, r_im, Exponent::TWO, max_iterations)
+                    .0
+            );
+        }
+        assert_eq!(
+            Formula::Mandelbar.symmetry(Exponent::TWO).rotational_order,
+            3
+        );
+    }
+
+    #[test]
+    fn check_abs_variants() {
+        let max_iterations = NonZeroU64::new(255).unwrap();
+        let burning_ship = Formula::Abs(AbsVariant::BurningShip);
+        // The same points as in the example of the `Fractal` trait.
+        assert_eq!(
+            burning_ship
+                .iterate(-0.5, -0.5, Exponent::TWO, max_iterations)
+                .0,
+            255
+        );
+        assert_ne!(
+            burning_ship
+                .iterate(1.0, 1.0, Exponent::TWO, max_iterations)
+                .0,
+            255
+        );
+
+        for variant in AbsVariant::ALL {
+            let formula = Formula::Abs(variant);
+            // The exponent is ignored.
+            assert_eq!(
+                formula.iterate(-1.2, 0.3, Exponent::TWO, max_iterations),
+                formula.iterate(-1.2, 0.3, Exponent::try_from(5).unwrap(), max_iterations)
+            );
+            // The claimed symmetry under conjugation holds.
+            if formula.symmetry(Exponent::TWO).conjugation {
+                for (c_re, c_im) in [(0.3, 0.5), (-1.2, 0.4), (0.1, -0.9), (-1.7, 0.02)] {
+                    assert_eq!(
+                        formula.iterate(c_re, c_im, Exponent::TWO, max_iterations).0,
+                        formula
+                            .iterate(c_re, -c_im, Exponent::TWO, max_iterations)
+                            .0
+                    );
+                }
+            }
+        }
+        // and the Burning Ship is not symmetric.
+        assert_ne!(
+            burning_ship
+                .iterate(-1.75, -0.03, Exponent::TWO, max_iterations)
+                .0,
+            burning_ship
+                .iterate(-1.75, 0.03, Exponent::TWO, max_iterations)
+                .0
+        );
+    }
+
+    #[test]
+    fn check_parsing() {
+        for formula in Formula::ALL {
+            assert_eq!(formula.to_string().parse(), Ok(formula));
+        }
+        assert_eq!(
+            "Burning Ship".parse(),
+            Ok(Formula::Abs(AbsVariant::BurningShip))
+        );
+        assert_eq!("tricorn".parse(), Ok(Formula::Mandelbar));
+        let error = "julia".parse::<Formula>().unwrap_err().to_string();
+        assert!(error.contains("\"buffalo\""), "{error}");
+    }
+
+    #[test]
+    fn check_generic_mandelbar_loop() {
+        // The specialized quadratic loop and the generic loop must agree.
+        let max_iterations = NonZeroU64::new(255).unwrap();
+        for (c_re, c_im) in [(0.3, 0.5), (-1.2, 0.4), (0.1, -0.9)] {
+            let mut z = (c_re, c_im);
+            let mut iterations = 1;
+            while iterations < 255 && z.0 * z.0 + z.1 * z.1 <= 36.0 {
+                let w = complex_powi(z.0, -z.1, 2);
+                z = (w.0 + c_re, w.1 + c_im);
+                iterations += 1;
+            }
+            assert_eq!(
+                iterate_mandelbar(
+                    c_re,
+                    c_im,
+                    Exponent::TWO,
+                    max_iterations,
+                    EscapeRadius::DEFAULT
+                )
+                .0,
+                iterations
+            );
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use core::fmt;
+use core::num::NonZeroU64;
+
+use crate::{in_main_cardioid_or_bulb, iterate, Complex, Exponent, Symmetry};
+
+/// An escape-time fractal defined by iterating a function z -> f(z, c).
+///
+/// Implement this trait and wrap it in [`Formula::Custom`](crate::Formula::Custom)
+/// to render your own fractals with [`render`](crate::render).
+/// Only [`step`](Fractal::step) is required, the other methods have defaults
+/// that are correct for any formula but may be overridden to make rendering faster.
+///
+/// # Example
+///
+/// ```
+/// # use core::fmt;
+/// # use core::num::NonZeroU64;
+/// # use mandellib::{Complex, Fractal};
+/// /// The "burning ship" fractal.
+/// #[derive(Debug)]
+/// struct BurningShip;
+///
+/// impl Fractal for BurningShip {
+///     fn step(&self, z: Complex, c: Complex) -> Complex {
+///         let z = Complex::new(z.re.abs(), z.im.abs());
+///         z * z + c
+///     }
+/// }
+///
+/// impl fmt::Display for BurningShip {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         write!(f, "burning ship")
+///     }
+/// }
+///
+/// const MAXITERS: NonZeroU64 = NonZeroU64::new(100).unwrap();
+/// assert_eq!(BurningShip.iterate(Complex::new(-0.5, -0.5), MAXITERS).0, MAXITERS.get());
+/// assert_ne!(BurningShip.iterate(Complex::new(1.0, 1.0), MAXITERS).0, MAXITERS.get());
+/// ```
+pub trait Fractal: fmt::Debug + fmt::Display + Send + Sync {
+    /// Computes the next value of z from the current one.
+    fn step(&self, z: Complex, c: Complex) -> Complex;
+
+    /// The iteration is stopped once |z|^2 exceeds this value.
+    /// The default of 36 is large enough to keep the smoothed coloring free from banding.
+    fn bailout_sqr(&self) -> f64 {
+        36.0
+    }
+
+    /// Returns true if c is known to be in the set without iterating it,
+    /// e.g. because it lies in a region whose shape is known analytically.
+    /// The default never skips any points.
+    fn is_known_interior(&self, _c: Complex) -> bool {
+        false
+    }
+
+    /// The symmetries of the fractal, used to avoid computing mirrored pixels twice.
+    /// The default claims no symmetries, which is always correct.
+    fn symmetry(&self) -> Symmetry {
+        Symmetry {
+            conjugation: false,
+            rotational_order: 1,
+        }
+    }
+
+    /// The rate at which |z| grows once it is large,
+    /// i.e. the degree of `step` as a polynomial in z. Used to smooth the coloring.
+    fn degree(&self) -> f64 {
+        2.0
+    }
+
+    /// Iterates the fractal starting from z = 0 until |z|^2 exceeds the bailout
+    /// or `max_iterations` iterations have been done.
+    /// Returns a tuple of `(iterations, final z)`, just like [`iterate`].
+    /// The final z is NaN for points that are in the known interior.
+    fn iterate(&self, c: Complex, max_iterations: NonZeroU64) -> (u64, Complex) {
+        let max_iterations = max_iterations.get();
+
+        if self.is_known_interior(c) {
+            return (max_iterations, Complex::new(f64::NAN, f64::NAN));
+        }
+
+        let bailout_sqr = self.bailout_sqr();
+        let mut z = Complex::ZERO;
+        let mut mag_sqr = 0.0;
+        let mut iterations = 0;
+
+        while iterations < max_iterations && mag_sqr <= bailout_sqr {
+            z = self.step(z, c);
+            mag_sqr = z.mag_sqr();
+            // NaN would otherwise never escape and be counted as inside the set.
+            if mag_sqr.is_nan() {
+                mag_sqr = f64::INFINITY;
+                z = Complex::new(f64::INFINITY, 0.0);
+            }
+            iterations += 1;
+        }
+
+        (iterations, z)
+    }
+}
+
+/// The Mandelbrot set, or a Multibrot set if the exponent is larger than 2.
+/// Iterates z -> z^d + c using the optimized [`iterate`] function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Mandelbrot {
+    pub exponent: Exponent,
+}
+
+impl Fractal for Mandelbrot {
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        z.powi(self.exponent.get().into()) + c
+    }
+
+    fn is_known_interior(&self, c: Complex) -> bool {
+        self.exponent == Exponent::TWO && in_main_cardioid_or_bulb(c.re, c.im)
+    }
+
+    fn symmetry(&self) -> Symmetry {
+        // z^d + c is invariant under rotating c by a (d-1):th of a turn.
+        Symmetry {
+            conjugation: true,
+            rotational_order: self.exponent.get() - 1,
+        }
+    }
+
+    fn degree(&self) -> f64 {
+        self.exponent.into()
+    }
+
+    fn iterate(&self, c: Complex, max_iterations: NonZeroU64) -> (u64, Complex) {
+        let (iterations, z_re, z_im) = iterate(c.re, c.im, self.exponent, max_iterations);
+        (iterations, Complex::new(z_re, z_im))
+    }
+}
+
+impl fmt::Display for Mandelbrot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.exponent == Exponent::TWO {
+            write!(f, "mandelbrot")
+        } else {
+            write!(f, "multibrot (d = {})", self.exponent)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_fractal {
+    use super::*;
+
+    #[test]
+    fn check_default_iterate_matches_optimized() {
+        // Iterating with only `step` must give the same result as the optimized loops.
+        #[derive(Debug)]
+        struct StepOnly(Mandelbrot);
+        impl Fractal for StepOnly {
+            fn step(&self, z: Complex, c: Complex) -> Complex {
+                self.0.step(z, c)
+            }
+        }
+        impl fmt::Display for StepOnly {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        let max_iterations = NonZeroU64::new(255).unwrap();
+        for exponent in [2, 3, 5] {
+            let mandelbrot = Mandelbrot {
+                exponent: exponent.try_into().unwrap(),
+            };
+            for c in [(0.3, 0.5), (-1.2, 0.4), (0.1, -0.9), (-0.8, 0.2)] {
+                let c = Complex::new(c.0, c.1);
+                assert_eq!(
+                    StepOnly(mandelbrot).iterate(c, max_iterations).0,
+                    mandelbrot.iterate(c, max_iterations).0
+                );
+            }
+        }
+    }
+}
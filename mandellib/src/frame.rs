@@ -0,0 +1,215 @@
+use core::num::NonZeroU32;
+
+#[cfg(feature = "std")]
+use crate::RenderParameters;
+use crate::{InvalidZoomError, Zoom};
+
+/// Contains information about a rectangle-shaped region in the complex plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub center_real: f64,
+    pub center_imag: f64,
+    pub real_distance: f64,
+    pub imag_distance: f64,
+}
+
+impl Frame {
+    #[must_use]
+    pub const fn new(
+        center_real: f64,
+        center_imag: f64,
+        real_distance: f64,
+        imag_distance: f64,
+    ) -> Self {
+        Self {
+            center_real,
+            center_imag,
+            real_distance,
+            imag_distance,
+        }
+    }
+
+    /// Returns the frame centered on the given point that is zoomed in to `zoom`
+    /// and has the given ratio of width to height.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::{Frame, Zoom};
+    /// let frame = Frame::from_zoom(-0.75, 0.0, Zoom::try_from(1.0).unwrap(), 1.5);
+    /// assert_eq!(frame.imag_distance, 4.0 / 3.0);
+    /// assert_eq!(frame.real_distance, 2.0);
+    /// ```
+    #[must_use]
+    pub fn from_zoom(center_real: f64, center_imag: f64, zoom: Zoom, aspect_ratio: f64) -> Self {
+        Self::new(
+            center_real,
+            center_imag,
+            zoom.real_distance(aspect_ratio),
+            zoom.imag_distance(),
+        )
+    }
+
+    /// Returns the smallest frame that has the two points, given as `(re, im)`,
+    /// at opposite corners. It does not matter which corners they are.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::Frame;
+    /// let frame = Frame::from_corners((-2.25, 1.0), (0.75, -1.0));
+    /// assert_eq!(frame, Frame::new(-0.75, 0.0, 3.0, 2.0));
+    /// assert_eq!(Frame::from_corners((0.75, 1.0), (-2.25, -1.0)), frame);
+    /// ```
+    #[must_use]
+    pub fn from_corners(a: (f64, f64), b: (f64, f64)) -> Self {
+        let (left, right) = (a.0.min(b.0), a.0.max(b.0));
+        let (bottom, top) = (a.1.min(b.1), a.1.max(b.1));
+        Self::new(
+            (left + right) / 2.0,
+            (bottom + top) / 2.0,
+            right - left,
+            top - bottom,
+        )
+    }
+
+    /// Returns the frame with the same center that is zoomed in by the given factor,
+    /// so that its distances are divided by it. Factors below one zoom out.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::Frame;
+    /// let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+    /// assert_eq!(frame.zoom_by(4.0), Frame::new(-0.75, 0.0, 0.75, 0.5));
+    /// assert_eq!(frame.zoom_by(0.5), Frame::new(-0.75, 0.0, 6.0, 4.0));
+    /// ```
+    #[must_use]
+    pub fn zoom_by(self, factor: f64) -> Self {
+        Self {
+            real_distance: self.real_distance / factor,
+            imag_distance: self.imag_distance / factor,
+            ..self
+        }
+    }
+
+    /// Returns the frame moved by the given distances along the real and imaginary axes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::Frame;
+    /// let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+    /// assert_eq!(frame.translate(0.5, -0.25), Frame::new(-0.25, -0.25, 3.0, 2.0));
+    /// ```
+    #[must_use]
+    pub fn translate(self, real_offset: f64, imag_offset: f64) -> Self {
+        Self {
+            center_real: self.center_real + real_offset,
+            center_imag: self.center_imag + imag_offset,
+            ..self
+        }
+    }
+
+    /// Returns the frame with the same center and imaginary distance that has the given ratio of width to height.
+    /// The zoom of a frame is based on its imaginary distance, so the zoom does not change either.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::Frame;
+    /// let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+    /// assert_eq!(frame.with_aspect_ratio(1.0), Frame::new(-0.75, 0.0, 2.0, 2.0));
+    /// ```
+    #[must_use]
+    pub fn with_aspect_ratio(self, aspect_ratio: f64) -> Self {
+        Self {
+            real_distance: self.imag_distance * aspect_ratio,
+            ..self
+        }
+    }
+
+    /// Returns the point, as `(re, im)`, that is at the given position in an image of the frame
+    /// with the resolution of the render parameters. The position is measured in pixels from the top left corner
+    /// of the image, so the center of the pixel in column `x` and row `y` is at `(x + 0.5, y + 0.5)`,
+    /// which is the point that the renderer samples for that pixel, up to rounding.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::{Frame, RenderParameters};
+    /// # use color_space::SupportedColorType;
+    /// let params = RenderParameters::try_new(
+    ///     30.try_into().unwrap(),
+    ///     20.try_into().unwrap(),
+    ///     100.try_into().unwrap(),
+    ///     1.try_into().unwrap(),
+    ///     SupportedColorType::Rgb8,
+    /// )
+    /// .unwrap();
+    /// let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+    /// // The corners of the image are the corners of the frame,
+    /// assert_eq!(frame.pixel_to_point(0.0, 0.0, &params), (-2.25, 1.0));
+    /// assert_eq!(frame.pixel_to_point(30.0, 20.0, &params), (0.75, -1.0));
+    /// // and the pixels are 0.1 wide.
+    /// assert_eq!(frame.pixel_to_point(0.5, 0.5, &params), (-2.2, 0.95));
+    /// ```
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn pixel_to_point(
+        &self,
+        x: f64,
+        y: f64,
+        render_parameters: &RenderParameters,
+    ) -> (f64, f64) {
+        let real_delta = self.real_distance / f64::from(render_parameters.x_resolution);
+        let imag_delta = self.imag_distance / f64::from(render_parameters.y_resolution);
+        (
+            self.center_real - self.real_distance / 2.0 + x * real_delta,
+            self.center_imag + self.imag_distance / 2.0 - y * imag_delta,
+        )
+    }
+
+    /// Returns how far the frame is zoomed in, based on its imaginary distance.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the imaginary distance of the frame is not positive and finite.
+    pub fn zoom(&self) -> Result<Zoom, InvalidZoomError> {
+        Zoom::from_imag_distance(self.imag_distance)
+    }
+
+    /// The most decimals that [`Frame::coordinate_decimals`] returns,
+    /// which is enough to tell apart any two `f64`s between -1 and 1 that are not subnormal.
+    pub const MAX_COORDINATE_DECIMALS: usize = 17;
+
+    /// Returns the number of decimals that the center of the frame needs to be given with
+    /// for an image of it that is `y_resolution` pixels tall to be placed to within a tenth of a pixel.
+    /// Further decimals only move the image by less than that, so coordinates can be rounded
+    /// to this many decimals when they are shown without misleading about how precisely they are known.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::Frame;
+    /// # use core::num::NonZeroU32;
+    /// let y_resolution = NonZeroU32::new(1000).unwrap();
+    /// // The pixels are 0.002 high, so coordinates are needed to within 0.0002.
+    /// let overview = Frame::new(-0.75, 0.0, 3.0, 2.0);
+    /// assert_eq!(overview.coordinate_decimals(y_resolution), 4);
+    ///
+    /// let deep_zoom = Frame::new(-0.75, 0.1, 3e-9, 2e-9);
+    /// assert_eq!(deep_zoom.coordinate_decimals(y_resolution), 13);
+    /// ```
+    #[must_use]
+    pub fn coordinate_decimals(&self, y_resolution: NonZeroU32) -> usize {
+        let tolerance = self.imag_distance / f64::from(y_resolution.get()) / 10.0;
+        let mut decimals = 0;
+        let mut step = 1.0;
+        while step > tolerance && decimals < Self::MAX_COORDINATE_DECIMALS {
+            step /= 10.0;
+            decimals += 1;
+        }
+        decimals
+    }
+}
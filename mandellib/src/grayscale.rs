@@ -0,0 +1,261 @@
+//! How escape speeds are mapped to brightness in grayscale images.
+//!
+//! The escape speeds of most views bunch up close to 1, since the smoothed iteration count of
+//! most of the exterior is a tiny fraction of the maximum number of iterations. Mapping them
+//! linearly to brightness leaves the faint structure far from the set indistinguishable,
+//! which is why the brightness can instead be spread out by how common every escape speed is,
+//! or by the logarithm of the iteration count. Both are spread evenly over perceived brightness,
+//! i.e. over the sRGB values of the pixels, rather than over the light they emit.
+
+use core::fmt;
+use core::num::NonZeroUsize;
+use core::str::FromStr;
+
+use color_space::srgb_to_linear_rgb;
+
+use crate::{escape_speed_histogram, iterations_to_f64, EscapeBuffer, Frame, RenderParameters};
+
+/// The number of bins of the histogram that grayscale images are equalized by.
+/// The escape speeds of the exterior are bunched so closely together that many bins
+/// are needed to tell them apart.
+const EQUALIZATION_BINS: NonZeroUsize = NonZeroUsize::new(1 << 16).unwrap();
+
+/// The largest number of pixels of the image that the histogram is computed from.
+const EQUALIZATION_PROBE_PIXELS: f64 = 250_000.0;
+
+/// How the escape speeds of a grayscale image are mapped to brightness.
+/// Has no effect on images in color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GrayscaleMode {
+    /// The brightness is the escape speed, stretched over the
+    /// [`EscapeSpeedRange`](crate::EscapeSpeedRange) of the render.
+    #[default]
+    Linear,
+    /// Every perceived brightness is used by equally many pixels, as estimated from a histogram
+    /// of the escape speeds at the centers of the pixels of a low resolution version of the image.
+    /// Brings out the structure of any view, but its brightness depends on the whole view,
+    /// so overlapping views do not match.
+    Equalized,
+    /// The brightness falls with the logarithm of the smoothed iteration count,
+    /// which spreads out the escape speeds far from the set while keeping the image
+    /// independent of the rest of the view.
+    Log,
+}
+
+impl GrayscaleMode {
+    pub const ALL: [Self; 3] = [Self::Linear, Self::Equalized, Self::Log];
+}
+
+impl fmt::Display for GrayscaleMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Linear => write!(f, "linear"),
+            Self::Equalized => write!(f, "equalized"),
+            Self::Log => write!(f, "log"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseGrayscaleModeError(String);
+
+impl fmt::Display for ParseGrayscaleModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown grayscale mode \"{}\", expected \"linear\", \"equalized\" or \"log\"",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseGrayscaleModeError {}
+
+impl FromStr for GrayscaleMode {
+    type Err = ParseGrayscaleModeError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "linear" => Ok(Self::Linear),
+            "equalized" | "equalised" => Ok(Self::Equalized),
+            "log" => Ok(Self::Log),
+            _ => Err(ParseGrayscaleModeError(s.to_owned())),
+        }
+    }
+}
+
+/// The mapping from escape speeds to brightness of a render, worked out from its [`GrayscaleMode`]
+/// before any pixels are computed.
+pub(crate) enum GrayscaleCurve {
+    Linear,
+    /// The fraction of the counted escape speeds that are below the start of every bin,
+    /// followed by 1.
+    Equalized(Vec<f64>),
+    Log {
+        max_iterations: f64,
+    },
+}
+
+impl GrayscaleCurve {
+    pub(crate) fn new(render_parameters: &RenderParameters, render_region: Frame) -> Self {
+        match render_parameters.grayscale_mode {
+            GrayscaleMode::Linear => Self::Linear,
+            GrayscaleMode::Log => Self::Log {
+                max_iterations: iterations_to_f64(render_parameters.max_iterations.get()),
+            },
+            GrayscaleMode::Equalized => {
+                let x_resolution = f64::from(render_parameters.x_resolution);
+                let y_resolution = f64::from(render_parameters.y_resolution);
+                let scale = (EQUALIZATION_PROBE_PIXELS / (x_resolution * y_resolution))
+                    .sqrt()
+                    .min(1.0);
+                let mut probe_parameters = render_parameters.clone();
+                // The scale is at most 1, so the scaled resolutions fit in the original types.
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                {
+                    probe_parameters.x_resolution = ((x_resolution * scale) as u32)
+                        .max(1)
+                        .try_into()
+                        .unwrap_or(render_parameters.x_resolution);
+                    probe_parameters.y_resolution = ((y_resolution * scale) as u32)
+                        .max(1)
+                        .try_into()
+                        .unwrap_or(render_parameters.y_resolution);
+                }
+                let counts =
+                    escape_speed_histogram(&probe_parameters, render_region, EQUALIZATION_BINS);
+                Self::equalizing(&counts)
+            }
+        }
+    }
+
+    /// Returns the curve of the given mode for the samples in the buffer.
+    /// Equalized curves are computed from the histogram of the buffer itself.
+    pub(crate) fn for_buffer(mode: GrayscaleMode, buffer: &EscapeBuffer) -> Self {
+        match mode {
+            GrayscaleMode::Linear => Self::Linear,
+            GrayscaleMode::Log => Self::Log {
+                max_iterations: iterations_to_f64(buffer.max_iterations().get()),
+            },
+            GrayscaleMode::Equalized => Self::equalizing(&buffer.histogram(EQUALIZATION_BINS)),
+        }
+    }
+
+    /// Returns the curve that equalizes the given histogram of escape speeds.
+    fn equalizing(counts: &[u32]) -> Self {
+        let total: f64 = counts.iter().map(|&count| f64::from(count)).sum();
+        if total == 0.0 {
+            // Without any escaping points there is nothing to equalize.
+            return Self::Linear;
+        }
+        let mut below = 0.0;
+        let mut cumulative = Vec::with_capacity(counts.len() + 1);
+        cumulative.push(0.0);
+        for &count in counts {
+            below += f64::from(count);
+            cumulative.push(below / total);
+        }
+        Self::Equalized(cumulative)
+    }
+
+    /// Returns the brightness in linear light of a point with the given escape speed,
+    /// which is at the given position of the palette.
+    pub(crate) fn brightness(&self, escape_speed: f64, palette_position: f64) -> f64 {
+        match self {
+            Self::Linear => palette_position,
+            Self::Equalized(cumulative) => {
+                // Escape speeds are assumed to be spread evenly within every bin.
+                let bins = cumulative.len() - 1;
+                let position = escape_speed.clamp(0.0, 1.0) * bins as f64;
+                // The position is clamped to the range of the bins first.
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let bin = (position as usize).min(bins - 1);
+                let fraction = position - bin as f64;
+                srgb_to_linear_rgb(
+                    cumulative[bin] + (cumulative[bin + 1] - cumulative[bin]) * fraction,
+                )
+            }
+            Self::Log { max_iterations } => {
+                let smoothed_iterations = (max_iterations * (1.0 - escape_speed)).max(0.0);
+                srgb_to_linear_rgb(
+                    (1.0 - smoothed_iterations.ln_1p() / max_iterations.ln_1p()).clamp(0.0, 1.0),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_grayscale {
+    use super::*;
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_curves() {
+        // Half of the escape speeds are in the last of four bins.
+        let curve = GrayscaleCurve::equalizing(&[1, 1, 0, 2]);
+        for (escape_speed, perceived) in [
+            (0.0, 0.0),
+            (0.25, 0.25),
+            (0.625, 0.5),
+            (0.875, 0.75),
+            (1.5, 1.0),
+        ] {
+            assert_eq!(
+                curve.brightness(escape_speed, 0.0),
+                srgb_to_linear_rgb(perceived)
+            );
+        }
+
+        let log = GrayscaleCurve::Log {
+            max_iterations: 1000.0,
+        };
+        assert_eq!(log.brightness(0.0, 0.0), 0.0);
+        assert_eq!(log.brightness(1.0, 0.0), 1.0);
+        // One iteration out of a thousand is far from the brightest in a linear mapping.
+        assert!(log.brightness(0.999, 0.999) < srgb_to_linear_rgb(0.95));
+
+        assert_eq!(GrayscaleCurve::Linear.brightness(0.3, 0.7), 0.7);
+    }
+
+    #[test]
+    fn check_equalized_render() {
+        use crate::render;
+
+        let mut params = RenderParameters::try_new(
+            90.try_into().unwrap(),
+            60.try_into().unwrap(),
+            2000.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::L8,
+        )
+        .unwrap();
+        let frame = Frame::new(-0.75, 0.1, 3.0, 2.0);
+        let mean_brightness = |params: &RenderParameters| {
+            let image = render(params, frame, false).into_luma8();
+            let pixels = f64::from(image.width() * image.height());
+            image
+                .pixels()
+                .map(|pixel| f64::from(pixel.0[0]))
+                .sum::<f64>()
+                / pixels
+        };
+
+        // Nearly the whole exterior is white with the linear mapping.
+        let linear = mean_brightness(&params);
+        params.grayscale_mode = GrayscaleMode::Equalized;
+        let equalized = mean_brightness(&params);
+        params.grayscale_mode = GrayscaleMode::Log;
+        let log = mean_brightness(&params);
+        assert!(equalized < linear - 20.0, "{equalized} vs {linear}");
+        assert!(log < linear - 20.0, "{log} vs {linear}");
+    }
+
+    #[test]
+    fn check_parsing() {
+        for mode in GrayscaleMode::ALL {
+            assert_eq!(mode.to_string().parse(), Ok(mode));
+        }
+        assert_eq!(" Equalised".parse(), Ok(GrayscaleMode::Equalized));
+        assert!("gamma".parse::<GrayscaleMode>().is_err());
+    }
+}
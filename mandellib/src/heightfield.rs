@@ -0,0 +1,435 @@
+//! Turns the escape speeds of an image into a 3D heightfield mesh
+//! that can be 3D printed or raytraced.
+//!
+//! Every pixel becomes a vertex whose height grows with the smoothed iteration count of the pixel,
+//! so the set itself is a plateau at the top with the exterior sloping down and away from it.
+//! The surface is closed off with walls and a flat floor, which makes the mesh a solid.
+
+use core::fmt;
+use core::str::FromStr;
+use std::io::{self, Write};
+
+use crate::EscapeSpeedField;
+
+/// How the smoothed iteration count of a pixel is turned into the height of its vertex.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HeightScale {
+    /// The height is proportional to the iteration count,
+    /// which leaves most of the exterior close to the floor.
+    Linear,
+    /// The height is proportional to the logarithm of one plus the iteration count,
+    /// which brings out the bands of the exterior.
+    #[default]
+    Logarithmic,
+}
+
+impl HeightScale {
+    pub const ALL: [Self; 2] = [Self::Linear, Self::Logarithmic];
+
+    /// Maps the smoothed iteration count to a height between 0 and 1,
+    /// where the maximum number of iterations is 1.
+    fn normalize(self, smoothed_iterations: f64, max_iterations: f64) -> f64 {
+        let smoothed_iterations = smoothed_iterations.clamp(0.0, max_iterations);
+        match self {
+            Self::Linear => smoothed_iterations / max_iterations,
+            Self::Logarithmic => smoothed_iterations.ln_1p() / max_iterations.ln_1p(),
+        }
+    }
+}
+
+impl fmt::Display for HeightScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Linear => write!(f, "linear"),
+            Self::Logarithmic => write!(f, "log"),
+        }
+    }
+}
+
+impl FromStr for HeightScale {
+    type Err = ParseHeightScaleError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "linear" => Ok(Self::Linear),
+            "log" | "logarithmic" => Ok(Self::Logarithmic),
+            _ => Err(ParseHeightScaleError(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseHeightScaleError(String);
+
+impl fmt::Display for ParseHeightScaleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown height scale \"{}\", expected one of {}",
+            self.0,
+            HeightScale::ALL.map(|scale| scale.to_string()).join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ParseHeightScaleError {}
+
+/// The settings of a heightfield mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeightfieldSettings {
+    /// The height of the points inside the set above the lowest points of the surface,
+    /// as a fraction of the width of the mesh.
+    pub relief: f64,
+    /// How the smoothed iteration counts are mapped to heights.
+    pub scale: HeightScale,
+    /// The thickness of the solid below the lowest points of the surface,
+    /// as a fraction of the width of the mesh.
+    pub base: f64,
+}
+
+impl Default for HeightfieldSettings {
+    fn default() -> Self {
+        Self {
+            relief: 0.1,
+            scale: HeightScale::Logarithmic,
+            base: 0.02,
+        }
+    }
+}
+
+/// A triangle mesh. The vertices of every triangle are ordered counterclockwise
+/// when seen from the outside of the solid.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<[f64; 3]>,
+    /// The indices into `vertices` of the corners of every triangle.
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    /// Creates a solid heightfield mesh with one vertex on its top surface for every pixel of the field.
+    ///
+    /// The mesh is in units of pixels: the vertex of the pixel in column x and row y counted
+    /// from the top left of the image is at (x, height - 1 - y), so the imaginary axis points along y,
+    /// and z is up. The floor of the solid is at z = 0.
+    ///
+    /// Fields less than two pixels wide or high have no area, and give an empty mesh.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::{EscapeSpeedField, Frame, HeightfieldSettings, Mesh, RenderParameters};
+    /// # use color_space::SupportedColorType;
+    /// let params = RenderParameters::try_new(
+    ///     60.try_into().unwrap(),
+    ///     40.try_into().unwrap(),
+    ///     100.try_into().unwrap(),
+    ///     1.try_into().unwrap(),
+    ///     SupportedColorType::Rgb8,
+    /// )
+    /// .unwrap();
+    /// let field = EscapeSpeedField::new(&params, Frame::new(-0.75, 0.0, 3.0, 2.0));
+    /// let mesh = Mesh::heightfield(&field, &HeightfieldSettings::default());
+    /// // Two triangles for every square between four pixels, and more for the walls and floor.
+    /// assert!(mesh.triangles.len() > 2 * 59 * 39);
+    /// ```
+    #[must_use]
+    pub fn heightfield(field: &EscapeSpeedField, settings: &HeightfieldSettings) -> Self {
+        let (width, height) = (field.width(), field.height());
+        if width < 2 || height < 2 {
+            return Self::default();
+        }
+
+        let span = (width - 1) as f64;
+        let base = settings.base * span;
+        let relief = settings.relief * span;
+        let mut vertices: Vec<[f64; 3]> = field
+            .smoothed_iterations()
+            .iter()
+            .enumerate()
+            .map(|(index, &smoothed_iterations)| {
+                let (x, y) = (index % width, index / width);
+                let elevation = settings
+                    .scale
+                    .normalize(smoothed_iterations, field.max_iterations());
+                [x as f64, (height - 1 - y) as f64, base + relief * elevation]
+            })
+            .collect();
+
+        let mut triangles =
+            Vec::with_capacity(2 * (width - 1) * (height - 1) + 4 * (width + height));
+        for y in 0..height - 1 {
+            for x in 0..width - 1 {
+                let top_left = y * width + x;
+                let bottom_left = top_left + width;
+                triangles.push([bottom_left, bottom_left + 1, top_left + 1]);
+                triangles.push([bottom_left, top_left + 1, top_left]);
+            }
+        }
+
+        // The vertices around the edge of the surface, clockwise when seen from above.
+        let perimeter: Vec<usize> = (0..width)
+            .chain((1..height).map(|y| y * width + width - 1))
+            .chain((0..width - 1).rev().map(|x| (height - 1) * width + x))
+            .chain((1..height - 1).rev().map(|y| y * width))
+            .collect();
+
+        // Every vertex on the edge gets a vertex straight below it on the floor.
+        let first_floor_vertex = vertices.len();
+        for &index in &perimeter {
+            let [x, y, _] = vertices[index];
+            vertices.push([x, y, 0.0]);
+        }
+        let floor_center = vertices.len();
+        vertices.push([span / 2.0, (height - 1) as f64 / 2.0, 0.0]);
+
+        for (i, &top) in perimeter.iter().enumerate() {
+            let next = (i + 1) % perimeter.len();
+            let next_top = perimeter[next];
+            let (bottom, next_bottom) = (first_floor_vertex + i, first_floor_vertex + next);
+            triangles.push([top, next_bottom, bottom]);
+            triangles.push([top, next_top, next_bottom]);
+            triangles.push([floor_center, bottom, next_bottom]);
+        }
+
+        Self {
+            vertices,
+            triangles,
+        }
+    }
+
+    /// Writes the mesh in the given format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the writer fails.
+    pub fn write(&self, mut writer: impl Write, format: MeshFormat) -> io::Result<()> {
+        match format {
+            MeshFormat::Obj => {
+                writeln!(writer, "# Heightfield of the escape speeds of a fractal")?;
+                for [x, y, z] in &self.vertices {
+                    writeln!(writer, "v {x} {y} {z}")?;
+                }
+                for [a, b, c] in &self.triangles {
+                    // The vertices are numbered from one.
+                    writeln!(writer, "f {} {} {}", a + 1, b + 1, c + 1)?;
+                }
+            }
+            MeshFormat::Stl => {
+                // The binary format, which is much smaller than the text one.
+                let mut header = [0; 80];
+                let title = b"Heightfield of the escape speeds of a fractal";
+                header[..title.len()].copy_from_slice(title);
+                writer.write_all(&header)?;
+                let count = u32::try_from(self.triangles.len()).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "the mesh has too many triangles for an STL file",
+                    )
+                })?;
+                writer.write_all(&count.to_le_bytes())?;
+                for triangle in &self.triangles {
+                    let corners = triangle.map(|index| self.vertices[index]);
+                    // STL files store single precision numbers.
+                    #[allow(clippy::cast_possible_truncation)]
+                    for [x, y, z] in core::iter::once(normal(corners)).chain(corners) {
+                        for coordinate in [x, y, z] {
+                            writer.write_all(&(coordinate as f32).to_le_bytes())?;
+                        }
+                    }
+                    // The unused attribute byte count.
+                    writer.write_all(&[0, 0])?;
+                }
+            }
+        }
+        writer.flush()
+    }
+}
+
+/// Returns the unit normal of the triangle, pointing to the side where its corners are counterclockwise.
+fn normal([a, b, c]: [[f64; 3]; 3]) -> [f64; 3] {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let n = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+    if length > 0.0 {
+        n.map(|component| component / length)
+    } else {
+        n
+    }
+}
+
+/// The file formats that a [`Mesh`] can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MeshFormat {
+    /// Wavefront OBJ, a text format that most 3D software can read.
+    Obj,
+    /// Binary STL, the usual format of 3D printing.
+    Stl,
+}
+
+impl MeshFormat {
+    pub const ALL: [Self; 2] = [Self::Obj, Self::Stl];
+
+    /// Determines the format from the extension of a file name.
+    #[must_use]
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        extension.parse().ok()
+    }
+}
+
+impl fmt::Display for MeshFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Obj => write!(f, "obj"),
+            Self::Stl => write!(f, "stl"),
+        }
+    }
+}
+
+impl FromStr for MeshFormat {
+    type Err = ParseMeshFormatError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "obj" => Ok(Self::Obj),
+            "stl" => Ok(Self::Stl),
+            _ => Err(ParseMeshFormatError(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMeshFormatError(String);
+
+impl fmt::Display for ParseMeshFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown mesh format \"{}\", expected one of {}",
+            self.0,
+            MeshFormat::ALL.map(|format| format.to_string()).join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ParseMeshFormatError {}
+
+#[cfg(test)]
+mod test_heightfield {
+    use super::*;
+    use crate::{Frame, RenderParameters};
+    use color_space::SupportedColorType;
+    use std::collections::HashMap;
+
+    fn field(width: u32, height: u32) -> EscapeSpeedField {
+        let params = RenderParameters::try_new(
+            width.try_into().unwrap(),
+            height.try_into().unwrap(),
+            100.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        EscapeSpeedField::new(&params, Frame::new(-0.75, 0.0, 3.0, 2.0))
+    }
+
+    #[test]
+    fn check_closed_solid() {
+        let mesh = Mesh::heightfield(&field(15, 10), &HeightfieldSettings::default());
+        assert_eq!(mesh.vertices.len(), 15 * 10 + 2 * (15 + 10) - 4 + 1);
+
+        // Every edge is shared by exactly two triangles that go along it in opposite directions,
+        // which makes the mesh a closed surface with consistently oriented triangles.
+        let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+        for &[a, b, c] in &mesh.triangles {
+            for edge in [(a, b), (b, c), (c, a)] {
+                *edges.entry(edge).or_default() += 1;
+            }
+        }
+        for (&(a, b), &count) in &edges {
+            assert_eq!(count, 1, "{a} -> {b}");
+            assert_eq!(edges.get(&(b, a)), Some(&1), "{a} -> {b}");
+        }
+
+        // The triangles face outwards, so the signed volume they enclose is positive.
+        let volume: f64 = mesh
+            .triangles
+            .iter()
+            .map(|triangle| {
+                let [a, b, c] = triangle.map(|index| mesh.vertices[index]);
+                a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+                    + a[2] * (b[0] * c[1] - b[1] * c[0])
+            })
+            .sum::<f64>()
+            / 6.0;
+        assert!(volume > 14.0 * 9.0 * 0.02 * 14.0, "{volume}");
+    }
+
+    #[test]
+    fn check_heights() {
+        let field = field(30, 20);
+        let settings = HeightfieldSettings {
+            relief: 0.5,
+            scale: HeightScale::Linear,
+            base: 0.1,
+        };
+        let mesh = Mesh::heightfield(&field, &settings);
+        let surface = &mesh.vertices[..30 * 20];
+        // The points inside the set are at the top, and the corners of the image far from it are low.
+        let top = 0.1 * 29.0 + 0.5 * 29.0;
+        assert!(surface.iter().any(|vertex| (vertex[2] - top).abs() < 1e-9));
+        assert!(surface.iter().all(|vertex| vertex[2] <= top + 1e-9));
+        assert!(surface.iter().all(|vertex| vertex[2] >= 0.1 * 29.0));
+        assert!(surface[0][2] < 0.25 * top);
+        // The first vertex is at the top left of the image.
+        assert_eq!(surface[0][..2], [0.0, 19.0]);
+        assert_eq!(surface[30 * 20 - 1][..2], [29.0, 0.0]);
+
+        // The logarithmic scale lifts the exterior.
+        let logarithmic = Mesh::heightfield(
+            &field,
+            &HeightfieldSettings {
+                scale: HeightScale::Logarithmic,
+                ..settings
+            },
+        );
+        assert!(logarithmic.vertices[0][2] > surface[0][2]);
+
+        assert_eq!(
+            Mesh::heightfield(&self::field(1, 20), &settings),
+            Mesh::default()
+        );
+    }
+
+    #[test]
+    fn check_writing() {
+        let mesh = Mesh::heightfield(&field(6, 4), &HeightfieldSettings::default());
+
+        let mut obj = Vec::new();
+        mesh.write(&mut obj, MeshFormat::Obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        assert_eq!(
+            obj.lines().filter(|line| line.starts_with("v ")).count(),
+            mesh.vertices.len()
+        );
+        assert_eq!(
+            obj.lines().filter(|line| line.starts_with("f ")).count(),
+            mesh.triangles.len()
+        );
+
+        let mut stl = Vec::new();
+        mesh.write(&mut stl, MeshFormat::Stl).unwrap();
+        assert_eq!(stl.len(), 84 + 50 * mesh.triangles.len());
+        assert_eq!(
+            u32::from_le_bytes(stl[80..84].try_into().unwrap()) as usize,
+            mesh.triangles.len()
+        );
+
+        assert_eq!(MeshFormat::from_extension("STL"), Some(MeshFormat::Stl));
+        assert_eq!(MeshFormat::from_extension("ply"), None);
+        assert_eq!("log".parse(), Ok(HeightScale::Logarithmic));
+    }
+}
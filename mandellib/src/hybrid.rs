@@ -0,0 +1,228 @@
+use core::fmt;
+use std::sync::Arc;
+
+use crate::{complex_powi, Exponent, Float, Formula};
+
+/// A fractal that iterates a repeating sequence of built-in formulas,
+/// e.g. two steps of the Mandelbrot formula followed by one of the Burning Ship.
+///
+/// z_1 = c like for the other formulas, and z_2 is computed from it with the first formula
+/// in the sequence, z_3 with the second one and so on, starting over after the last one.
+/// The Mandelbrot and Mandelbar formulas in the sequence use the exponent of the render.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{AbsVariant, Formula, Hybrid};
+/// let hybrid = Hybrid::try_new(vec![
+///     Formula::Mandelbrot,
+///     Formula::Mandelbrot,
+///     Formula::Abs(AbsVariant::BurningShip),
+/// ])
+/// .unwrap();
+/// assert_eq!(hybrid.to_string(), "mandelbrot,mandelbrot,burning-ship");
+/// assert_eq!(hybrid.to_string().parse(), Ok(Formula::Hybrid(hybrid)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hybrid(Arc<[Formula]>);
+
+impl Hybrid {
+    /// Creates a hybrid of the given sequence of formulas.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sequence is empty, or if it contains custom formulas or other hybrids.
+    pub fn try_new(formulas: Vec<Formula>) -> Result<Self, InvalidHybridError> {
+        if formulas.is_empty() {
+            return Err(InvalidHybridError::Empty);
+        }
+        if let Some(formula) = formulas
+            .iter()
+            .find(|formula| matches!(formula, Formula::Custom(_) | Formula::Hybrid(_)))
+        {
+            return Err(InvalidHybridError::Unsupported(formula.to_string()));
+        }
+        Ok(Self(formulas.into()))
+    }
+
+    /// Returns the sequence of formulas.
+    #[must_use]
+    pub fn formulas(&self) -> &[Formula] {
+        &self.0
+    }
+
+    /// Computes z_(n+1) from z_n.
+    #[inline]
+    pub(crate) fn step<F: Float>(
+        &self,
+        n: u64,
+        z_re: F,
+        z_im: F,
+        c_re: F,
+        c_im: F,
+        exponent: Exponent,
+    ) -> (F, F) {
+        // z_1 = c no matter which formula computes it from z_0 = 0.
+        let index = n.saturating_sub(1) as usize % self.0.len();
+        match &self.0[index] {
+            Formula::Mandelbrot if exponent == Exponent::TWO => (
+                z_re * z_re - z_im * z_im + c_re,
+                F::from_f64(2.0) * z_re * z_im + c_im,
+            ),
+            Formula::Mandelbar if exponent == Exponent::TWO => (
+                z_re * z_re - z_im * z_im + c_re,
+                F::from_f64(-2.0) * z_re * z_im + c_im,
+            ),
+            Formula::Mandelbrot => {
+                let (w_re, w_im) = complex_powi(z_re, z_im, exponent.get());
+                (w_re + c_re, w_im + c_im)
+            }
+            Formula::Mandelbar => {
+                let (w_re, w_im) = complex_powi(z_re, z_im, exponent.get());
+                (w_re + c_re, c_im - w_im)
+            }
+            Formula::Abs(variant) => variant.step(z_re, z_im, c_re, c_im),
+            Formula::Custom(_) | Formula::Hybrid(_) => {
+                unreachable!("hybrids of custom formulas can not be created")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Hybrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, formula) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{formula}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidHybridError {
+    Empty,
+    /// The named formula can not be part of a hybrid.
+    Unsupported(String),
+}
+
+impl fmt::Display for InvalidHybridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "a hybrid must contain at least one formula"),
+            Self::Unsupported(formula) => {
+                write!(f, "the formula \"{formula}\" can not be part of a hybrid")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvalidHybridError {}
+
+#[cfg(test)]
+mod test_hybrid {
+    use super::*;
+    use crate::{AbsVariant, CustomFormula};
+    use core::num::NonZeroU64;
+
+    const MAX_ITERATIONS: NonZeroU64 = NonZeroU64::new(255).unwrap();
+    const POINTS: [(f64, f64); 5] = [
+        (0.3, 0.5),
+        (-1.2, 0.4),
+        (0.1, -0.9),
+        (-1.76, 0.02),
+        (-0.5, -0.5),
+    ];
+
+    #[test]
+    fn check_iteration() {
+        // A hybrid of a single formula is the same as that formula.
+        for formula in Formula::ALL {
+            let hybrid = Formula::Hybrid(Hybrid::try_new(vec![formula.clone()]).unwrap());
+            for exponent in [Exponent::TWO, Exponent::try_from(3).unwrap()] {
+                for (c_re, c_im) in POINTS {
+                    let expected = formula.iterate(c_re, c_im, exponent, MAX_ITERATIONS);
+                    let result = hybrid.iterate(c_re, c_im, exponent, MAX_ITERATIONS);
+                    // The Mandelbrot formula skips the points in the main cardioid and bulb.
+                    if expected.0 < MAX_ITERATIONS.get() {
+                        assert_eq!(result, expected, "{formula} at {c_re} + {c_im}i");
+                    } else {
+                        assert_eq!(result.0, expected.0, "{formula} at {c_re} + {c_im}i");
+                    }
+                }
+            }
+        }
+
+        // The formulas are applied in order, starting with the first one on z_1 = c.
+        let hybrid: Formula = "mandelbrot,mandelbrot,burning-ship".parse().unwrap();
+        let mut differs_from_reversed = false;
+        for (c_re, c_im) in POINTS {
+            let mut z = (c_re, c_im);
+            let mut iterations = 1;
+            while iterations < MAX_ITERATIONS.get() && z.0 * z.0 + z.1 * z.1 <= 36.0 {
+                z = if iterations % 3 == 0 {
+                    AbsVariant::BurningShip.step(z.0, z.1, c_re, c_im)
+                } else {
+                    (z.0 * z.0 - z.1 * z.1 + c_re, 2.0 * z.0 * z.1 + c_im)
+                };
+                iterations += 1;
+            }
+            let result = hybrid.iterate(c_re, c_im, Exponent::TWO, MAX_ITERATIONS);
+            assert_eq!(result.0, iterations);
+            differs_from_reversed |= result
+                != "burning-ship,mandelbrot,mandelbrot"
+                    .parse::<Formula>()
+                    .unwrap()
+                    .iterate(c_re, c_im, Exponent::TWO, MAX_ITERATIONS);
+        }
+        assert!(differs_from_reversed);
+    }
+
+    #[test]
+    fn check_symmetry() {
+        let exponent = Exponent::try_from(3).unwrap();
+        let symmetry = |s: &str| s.parse::<Formula>().unwrap().symmetry(exponent);
+        assert!(symmetry("mandelbrot,celtic").conjugation);
+        assert!(!symmetry("mandelbrot,burning-ship").conjugation);
+        // z^3 + c is symmetric under half a turn and conj(z)^3 + c under a quarter turn.
+        assert_eq!(symmetry("mandelbrot,mandelbar").rotational_order, 2);
+        assert_eq!(symmetry("mandelbar,mandelbar").rotational_order, 4);
+        assert_eq!(symmetry("mandelbar,celtic").rotational_order, 1);
+
+        let hybrid: Formula = "mandelbrot,celtic".parse().unwrap();
+        for (c_re, c_im) in POINTS {
+            assert_eq!(
+                hybrid.iterate(c_re, c_im, exponent, MAX_ITERATIONS).0,
+                hybrid.iterate(c_re, -c_im, exponent, MAX_ITERATIONS).0
+            );
+        }
+    }
+
+    #[test]
+    fn check_construction() {
+        assert_eq!(Hybrid::try_new(Vec::new()), Err(InvalidHybridError::Empty));
+        let custom = Formula::Custom(Arc::new("z^2 + c".parse::<CustomFormula>().unwrap()));
+        assert!(matches!(
+            Hybrid::try_new(vec![Formula::Mandelbrot, custom]),
+            Err(InvalidHybridError::Unsupported(_))
+        ));
+        let hybrid = Formula::Hybrid(Hybrid::try_new(vec![Formula::Mandelbrot]).unwrap());
+        assert_eq!(
+            Hybrid::try_new(vec![hybrid]),
+            Err(InvalidHybridError::Unsupported("mandelbrot".to_owned()))
+        );
+
+        assert_eq!(
+            "Mandelbrot, Burning Ship"
+                .parse::<Formula>()
+                .unwrap()
+                .to_string(),
+            "mandelbrot,burning-ship"
+        );
+        for invalid in ["mandelbrot,julia", "mandelbrot,", ","] {
+            assert!(invalid.parse::<Formula>().is_err(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,666 @@
+//! Shading of the points inside the set, which would otherwise all get the same color.
+//!
+//! Every point inside the hyperbolic components of the set has an orbit that is attracted
+//! to a cycle. The shading is computed from that cycle, either from its multiplier,
+//! which goes from 0 at the center of a component to 1 at its boundary,
+//! or from the interior distance estimate, which approximates the distance to the boundary of the set.
+//! Points can also be shaded by where their orbits end up after the last iteration,
+//! or by the iteration at which their orbits come closest to the origin, which splits the set
+//! into the atom domains of its components.
+//!
+//! Some points are known to be inside the set without being iterated, see [`InteriorProvenance`].
+//! Every shading must give those points the same brightness as it would have if they were iterated,
+//! or the edge of the region that is skipped would show up as a seam in the image.
+
+use core::fmt;
+use core::num::NonZeroU32;
+use core::str::FromStr;
+
+use crate::{
+    iterations_to_f64, Complex, Formula, Fractal, Mandelbrot, RenderParameters,
+    INVERSE_GOLDEN_RATIO,
+};
+
+/// The longest cycle that is searched for. Points that are attracted to longer cycles are not shaded.
+const MAX_PERIOD: u32 = 1024;
+
+/// The squared distance within which an orbit is considered to have returned to where it was.
+const CYCLE_TOLERANCE_SQR: f64 = 1e-18;
+
+/// The number of Newton steps used to move the found point on to the cycle.
+const NEWTON_STEPS: u8 = 4;
+
+/// The number of iterations that the orbit is given to settle close to its attracting cycle
+/// before the period of the cycle is searched for by [`detect_period`].
+const PERIOD_SETTLE_ITERATIONS: u32 = 10_000;
+
+/// The largest number of Newton steps taken when solving for a point of a cycle of a given period.
+const MAX_PERIOD_NEWTON_STEPS: u8 = 64;
+
+/// The squared length of a Newton step below which the point is considered to be on the cycle.
+const PERIOD_NEWTON_TOLERANCE_SQR: f64 = 1e-24;
+
+/// The factor that |z|^2 must shrink by for a later iteration to count as closer to the origin
+/// when finding the atom domain of a point.
+const ATOM_DOMAIN_MARGIN: f64 = 0.9;
+
+/// How the points inside the set are colored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum InteriorShading {
+    /// Color every point inside the set like an escape speed of 0.
+    #[default]
+    Flat,
+    /// Shade points by the magnitude of the multiplier of the cycle their orbit is attracted to.
+    /// Brightest at the centers of the components of the set and dark at their boundaries.
+    Multiplier,
+    /// Shade points by the interior distance estimate, relative to the size of the image.
+    /// Dark close to the boundary of the set and brighter further in.
+    Distance,
+    /// Shade points by the magnitude of z after the last iteration, dark at the origin.
+    /// Unlike the multiplier and distance shadings this works for every formula.
+    Magnitude,
+    /// Shade points by the iteration at which their orbit comes closest to the origin.
+    /// The points with the same such iteration form the atom domain of a component of the set,
+    /// which is usually its period. Works for every formula.
+    AtomDomain,
+}
+
+impl InteriorShading {
+    /// All the interior shading modes.
+    pub const ALL: [Self; 5] = [
+        Self::Flat,
+        Self::Multiplier,
+        Self::Distance,
+        Self::Magnitude,
+        Self::AtomDomain,
+    ];
+}
+
+impl fmt::Display for InteriorShading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Flat => write!(f, "flat"),
+            Self::Multiplier => write!(f, "multiplier"),
+            Self::Distance => write!(f, "distance"),
+            Self::Magnitude => write!(f, "magnitude"),
+            Self::AtomDomain => write!(f, "atom-domain"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseInteriorShadingError(String);
+
+impl fmt::Display for ParseInteriorShadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown interior shading \"{}\", expected one of \"flat\", \"multiplier\", \"distance\", \"magnitude\" or \"atom-domain\"",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseInteriorShadingError {}
+
+impl FromStr for InteriorShading {
+    type Err = ParseInteriorShadingError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "flat" => Ok(Self::Flat),
+            "multiplier" => Ok(Self::Multiplier),
+            "distance" => Ok(Self::Distance),
+            "magnitude" => Ok(Self::Magnitude),
+            "atom-domain" | "atom" => Ok(Self::AtomDomain),
+            _ => Err(ParseInteriorShadingError(s.to_owned())),
+        }
+    }
+}
+
+/// How the points outside the set are colored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Exterior {
+    /// Color the points outside the set by their escape speed.
+    #[default]
+    Opaque,
+    /// Make the points outside the set transparent, so that only the inside of the set is visible.
+    /// The opacity of a pixel is the fraction of its samples that are inside the set.
+    /// The inside is colored like it otherwise would be, or with the given sRGB color.
+    ///
+    /// Only has an effect on images with an alpha channel.
+    Transparent { interior_color: Option<[u8; 3]> },
+    /// Color the points outside the set by their escape speed, but darken those whose orbits
+    /// escape below the real axis. This is known as binary decomposition, and splits the exterior
+    /// into cells that show how the external rays of the set are arranged.
+    BinaryDecomposition,
+}
+
+/// The factor that the colors of the darker half of the binary decomposition are scaled by.
+pub(crate) const BINARY_DECOMPOSITION_DARKENING: f64 = 0.4;
+
+/// How a point was found to be inside the set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum InteriorProvenance {
+    /// The point lies in a region that is known to be inside the set, like the main cardioid,
+    /// so it was never iterated and there is no final value of z.
+    KnownInterior,
+    /// The orbit of the point did not escape within the maximum number of iterations.
+    MaxIterations { mag_sqr: f64 },
+}
+
+/// The derivatives of the `period`:th iterate of z -> z^d + c at a point of the cycle.
+struct CycleDerivatives {
+    /// The point that the orbit ends up at.
+    z: Complex,
+    /// d/dz, the multiplier of the cycle.
+    dz: Complex,
+    /// d/dc
+    dc: Complex,
+    /// d^2/dz^2
+    dzdz: Complex,
+    /// d^2/dcdz
+    dcdz: Complex,
+}
+
+impl CycleDerivatives {
+    fn new(z: Complex, c: Complex, degree: i32, period: u32) -> Self {
+        let d = f64::from(degree);
+        let mut derivatives = Self {
+            z,
+            dz: Complex::new(1.0, 0.0),
+            dc: Complex::ZERO,
+            dzdz: Complex::ZERO,
+            dcdz: Complex::ZERO,
+        };
+        for _ in 0..period {
+            let Self {
+                z,
+                dz,
+                dc,
+                dzdz,
+                dcdz,
+            } = derivatives;
+            // The first and second derivatives of z^d.
+            let first = Complex::from(d) * z.powi(degree - 1);
+            let second = Complex::from(d * (d - 1.0)) * z.powi(degree - 2);
+            derivatives = Self {
+                z: z.powi(degree) + c,
+                dz: first * dz,
+                dc: first * dc + Complex::from(1.0),
+                dzdz: second * dz * dz + first * dzdz,
+                dcdz: second * dc * dz + first * dcdz,
+            };
+        }
+        derivatives
+    }
+}
+
+/// Returns the brightness in \[0, 1\] that the given point inside the set should be shaded with,
+/// or `None` if it should be colored like any other point inside the set.
+/// `provenance` is how the point was found to be inside the set,
+/// and `pixel_size` is the imaginary distance covered by a pixel.
+pub(crate) fn interior_brightness(
+    c: Complex,
+    provenance: InteriorProvenance,
+    pixel_size: f64,
+    render_parameters: &RenderParameters,
+) -> Option<f64> {
+    match render_parameters.interior_shading {
+        InteriorShading::Flat => None,
+        InteriorShading::Magnitude => {
+            let mag_sqr = match provenance {
+                InteriorProvenance::MaxIterations { mag_sqr } => mag_sqr,
+                // The point was never iterated, so its orbit is followed here instead.
+                InteriorProvenance::KnownInterior => final_mag_sqr(c, render_parameters),
+            };
+            // The orbits of points in the Mandelbrot set stay within |z| <= 2.
+            Some((mag_sqr.sqrt() / 2.0).clamp(0.0, 1.0))
+        }
+        // These do not depend on the provenance since they always follow the orbit themselves.
+        InteriorShading::Multiplier | InteriorShading::Distance => {
+            cycle_brightness(c, pixel_size, render_parameters)
+        }
+        // Consecutive domains get very different brightnesses, and none of them is black
+        // so that they stand out from the boundary of the set.
+        InteriorShading::AtomDomain => {
+            let domain = atom_domain(c, render_parameters);
+            Some(0.2 + 0.8 * (iterations_to_f64(domain) * INVERSE_GOLDEN_RATIO).fract())
+        }
+    }
+}
+
+/// Applies the formula of the render parameters to z = z_n once.
+fn step(n: u64, z: Complex, c: Complex, render_parameters: &RenderParameters) -> Complex {
+    let exponent = render_parameters.exponent;
+    match render_parameters.formula {
+        Formula::Mandelbrot => Mandelbrot { exponent }.step(z, c),
+        Formula::Mandelbar => z.conj().powi(exponent.get().into()) + c,
+        Formula::Abs(variant) => {
+            let (re, im) = variant.step(z.re, z.im, c.re, c.im);
+            Complex::new(re, im)
+        }
+        Formula::Hybrid(ref hybrid) => {
+            let (re, im) = hybrid.step(n, z.re, z.im, c.re, c.im, exponent);
+            Complex::new(re, im)
+        }
+        Formula::Custom(ref fractal) => fractal.step(z, c),
+    }
+}
+
+/// Returns |z|^2 after iterating the formula the maximum number of iterations from z = 0,
+/// which is what the iteration would have ended with if the point had not been skipped.
+fn final_mag_sqr(c: Complex, render_parameters: &RenderParameters) -> f64 {
+    let mut z = Complex::ZERO;
+    for n in 0..render_parameters.max_iterations.get() {
+        z = step(n, z, c, render_parameters);
+    }
+    z.mag_sqr()
+}
+
+/// Returns the first iteration, counted from 1, at which |z| is the smallest
+/// during the maximum number of iterations of the formula from z = 0.
+///
+/// An orbit that is attracted to a cycle comes back almost as close to the origin every period,
+/// so a later iteration only counts as closer if it is closer by more than [`ATOM_DOMAIN_MARGIN`].
+/// Otherwise the multiples of the period would speckle the domain.
+pub(crate) fn atom_domain(c: Complex, render_parameters: &RenderParameters) -> u64 {
+    let mut z = Complex::ZERO;
+    let mut domain = 1;
+    let mut min_mag_sqr = f64::INFINITY;
+    for iteration in 1..=render_parameters.max_iterations.get() {
+        z = step(iteration - 1, z, c, render_parameters);
+        let mag_sqr = z.mag_sqr();
+        if mag_sqr < min_mag_sqr * ATOM_DOMAIN_MARGIN {
+            min_mag_sqr = mag_sqr;
+            domain = iteration;
+        }
+    }
+    domain
+}
+
+/// Returns the brightness of the given point from the cycle that its orbit is attracted to,
+/// for the multiplier and distance shadings.
+///
+/// Only the Mandelbrot and Multibrot sets are shaded, and only points whose orbits settle on a cycle
+/// within the maximum number of iterations.
+fn cycle_brightness(
+    c: Complex,
+    pixel_size: f64,
+    render_parameters: &RenderParameters,
+) -> Option<f64> {
+    if render_parameters.formula != Formula::Mandelbrot {
+        return None;
+    }
+
+    let degree = i32::from(render_parameters.exponent.get());
+    let step = |z: Complex| z.powi(degree) + c;
+
+    // Let the orbit settle on the attracting cycle.
+    let mut z = c;
+    for _ in 0..render_parameters.max_iterations.get() {
+        z = step(z);
+    }
+
+    // The period is the number of steps it takes for the orbit to return.
+    let mut w = z;
+    let max_period = u32::try_from(render_parameters.max_iterations.get())
+        .map_or(MAX_PERIOD, |max_iterations| max_iterations.min(MAX_PERIOD));
+    let period = (1..=max_period).find(|_| {
+        w = step(w);
+        (w - z).mag_sqr() < CYCLE_TOLERANCE_SQR
+    })?;
+
+    // Refine the point with Newton's method on f^p(z) - z = 0,
+    // since the orbit may only be close to the cycle.
+    for _ in 0..NEWTON_STEPS {
+        let derivatives = CycleDerivatives::new(z, c, degree, period);
+        z = z - (derivatives.z - z) / (derivatives.dz - Complex::from(1.0));
+    }
+
+    let derivatives = CycleDerivatives::new(z, c, degree, period);
+    let multiplier = derivatives.dz.abs();
+    if multiplier.is_nan() || multiplier >= 1.0 {
+        // The cycle is not attracting, so the refinement went wrong.
+        return None;
+    }
+
+    match render_parameters.interior_shading {
+        InteriorShading::Flat | InteriorShading::Magnitude | InteriorShading::AtomDomain => None,
+        InteriorShading::Multiplier => Some(1.0 - multiplier),
+        InteriorShading::Distance => {
+            let distance = (1.0 - derivatives.dz.mag_sqr())
+                / (derivatives.dcdz
+                    + derivatives.dzdz * derivatives.dc / (Complex::from(1.0) - derivatives.dz))
+                    .abs();
+            // A logarithmic scale from the size of a pixel to the size of the image
+            // makes the shading look the same at every zoom level.
+            let y_resolution = f64::from(render_parameters.y_resolution);
+            let brightness = (distance / pixel_size).ln() / y_resolution.ln();
+            brightness.is_finite().then(|| brightness.clamp(0.0, 1.0))
+        }
+    }
+}
+
+/// Returns the period of the hyperbolic component of the Mandelbrot set that contains the point
+/// `c_re + c_im*i`, or `None` if the point is not inside a component of at most `max_period`.
+///
+/// The orbit of a point inside a hyperbolic component is attracted to a cycle whose period
+/// is the period of the component. The orbit is first allowed to settle close to that cycle,
+/// after which Newton's method is used to solve f^p(z) = z for every period p in turn,
+/// starting from where the orbit ended up. The period is the first p for which it converges
+/// to a cycle that is attracting. Points that are close to the boundary of their component
+/// settle slowly and may not be found.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::detect_period;
+/// # use core::num::NonZeroU32;
+/// let max_period = NonZeroU32::new(100).unwrap();
+/// // The main cardioid has period 1 and the large bulb to its left period 2.
+/// assert_eq!(detect_period(0.1, 0.1, max_period), NonZeroU32::new(1));
+/// assert_eq!(detect_period(-1.1, 0.05, max_period), NonZeroU32::new(2));
+/// // Points outside the set are not in any component.
+/// assert_eq!(detect_period(0.5, 0.5, max_period), None);
+/// ```
+#[must_use]
+pub fn detect_period(c_re: f64, c_im: f64, max_period: NonZeroU32) -> Option<NonZeroU32> {
+    let c = Complex::new(c_re, c_im);
+
+    // Let the orbit settle close to its attracting cycle, if it has one.
+    let mut z = Complex::ZERO;
+    for _ in 0..PERIOD_SETTLE_ITERATIONS {
+        z = z * z + c;
+        if z.mag_sqr() > 4.0 {
+            return None;
+        }
+    }
+
+    (1..=max_period.get())
+        .find(|&period| {
+            // Only one cycle can be attracting, so cycles of periods that are not
+            // a multiple of its period are repelling, and are not counted when Newton's method finds them.
+            let mut w = z;
+            for _ in 0..MAX_PERIOD_NEWTON_STEPS {
+                let derivatives = CycleDerivatives::new(w, c, 2, period);
+                let newton_step = (derivatives.z - w) / (derivatives.dz - Complex::from(1.0));
+                w = w - newton_step;
+                let length_sqr = newton_step.mag_sqr();
+                if !length_sqr.is_finite() {
+                    return false;
+                }
+                if length_sqr < PERIOD_NEWTON_TOLERANCE_SQR {
+                    return CycleDerivatives::new(w, c, 2, period).dz.mag_sqr() < 1.0;
+                }
+            }
+            false
+        })
+        .and_then(NonZeroU32::new)
+}
+
+#[cfg(test)]
+mod test_interior {
+    use super::*;
+    use color_space::SupportedColorType;
+
+    fn parameters(interior_shading: InteriorShading) -> RenderParameters {
+        let mut params = RenderParameters::try_new(
+            100.try_into().unwrap(),
+            100.try_into().unwrap(),
+            1000.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::L8,
+        )
+        .unwrap();
+        params.interior_shading = interior_shading;
+        params
+    }
+
+    #[test]
+    fn check_multiplier_shading() {
+        let params = parameters(InteriorShading::Multiplier);
+        // The centers of the main cardioid and the period 2 bulb are superattracting.
+        for center in [Complex::ZERO, Complex::new(-1.0, 0.0)] {
+            let brightness =
+                interior_brightness(center, InteriorProvenance::KnownInterior, 0.01, &params)
+                    .unwrap();
+            assert!((brightness - 1.0).abs() < 1e-9, "{brightness}");
+        }
+        // The multiplier of the fixed point is 2z = 1 - sqrt(1 - 4c), which is 0.5 at c = 0.1875.
+        let brightness = interior_brightness(
+            Complex::new(0.1875, 0.0),
+            InteriorProvenance::KnownInterior,
+            0.01,
+            &params,
+        )
+        .unwrap();
+        assert!((brightness - 0.5).abs() < 1e-9, "{brightness}");
+
+        assert_eq!(
+            interior_brightness(
+                Complex::ZERO,
+                InteriorProvenance::KnownInterior,
+                0.01,
+                &parameters(InteriorShading::Flat)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn check_distance_shading() {
+        let params = parameters(InteriorShading::Distance);
+        // Points further from the boundary are brighter.
+        let deep = interior_brightness(
+            Complex::new(-0.1, 0.0),
+            InteriorProvenance::KnownInterior,
+            0.01,
+            &params,
+        )
+        .unwrap();
+        let shallow = interior_brightness(
+            Complex::new(0.2, 0.0),
+            InteriorProvenance::KnownInterior,
+            0.01,
+            &params,
+        )
+        .unwrap();
+        assert!(deep > shallow, "{deep} <= {shallow}");
+        assert!(
+            0.0 < shallow && deep < 1.0,
+            "the shading should not be clamped"
+        );
+    }
+
+    #[test]
+    fn check_atom_domains() {
+        let params = parameters(InteriorShading::AtomDomain);
+        // The nuclei of the main cardioid and of components of periods 2, 3 and 4,
+        // whose orbits return to the origin once every period.
+        let nuclei = [
+            (Complex::ZERO, 1),
+            (Complex::new(-1.0, 0.0), 2),
+            (
+                Complex::new(-0.122_561_166_876_654, 0.744_861_766_619_744),
+                3,
+            ),
+            (Complex::new(-1.310_702_641_336_833, 0.0), 4),
+        ];
+        let mut brightnesses = Vec::new();
+        for (nucleus, period) in nuclei {
+            assert_eq!(atom_domain(nucleus, &params), period);
+            brightnesses.push(
+                interior_brightness(nucleus, InteriorProvenance::KnownInterior, 0.01, &params)
+                    .unwrap(),
+            );
+        }
+        // Points near a nucleus are in its domain as well.
+        assert_eq!(atom_domain(Complex::new(-1.01, 0.02), &params), 2);
+        // The domains of different periods are told apart.
+        for (i, a) in brightnesses.iter().enumerate() {
+            assert!(brightnesses[i + 1..].iter().all(|b| (a - b).abs() > 0.05));
+        }
+    }
+
+    #[test]
+    fn check_period_detection() {
+        let max_period = NonZeroU32::new(16).unwrap();
+        let nuclei = [
+            (Complex::ZERO, 1),
+            (Complex::new(-1.0, 0.0), 2),
+            (
+                Complex::new(-0.122_561_166_876_654, 0.744_861_766_619_744),
+                3,
+            ),
+            (Complex::new(-1.310_702_641_336_833, 0.0), 4),
+            // The nucleus of the largest minibrot on the real axis.
+            (Complex::new(-1.754_877_666_246_693, 0.0), 3),
+        ];
+        for (nucleus, period) in nuclei {
+            assert_eq!(
+                detect_period(nucleus.re, nucleus.im, max_period),
+                NonZeroU32::new(period),
+                "{nucleus:?}"
+            );
+            // Points that are further from the nucleus, but still in its component, have the same period.
+            let offset = Complex::new(0.001, 0.001);
+            let shifted = nucleus + offset;
+            assert_eq!(
+                detect_period(shifted.re, shifted.im, max_period),
+                NonZeroU32::new(period),
+                "{shifted:?}"
+            );
+        }
+        // The period 3 component is not found if shorter periods are searched for.
+        assert_eq!(
+            detect_period(
+                -0.122_561_166_876_654,
+                0.744_861_766_619_744,
+                NonZeroU32::new(2).unwrap()
+            ),
+            None
+        );
+        // Points in the exterior have no period, and neither do points on the boundary
+        // such as the tip of the antenna, whose orbit ends up on a repelling fixed point.
+        assert_eq!(detect_period(0.3, 0.6, max_period), None);
+        assert_eq!(detect_period(-2.0, 0.0, max_period), None);
+    }
+
+    #[test]
+    fn check_magnitude_shading_across_shortcut() {
+        use crate::{render, Frame};
+        use image::GenericImageView;
+        use std::sync::Arc;
+
+        /// The Mandelbrot set without the shortcut for its main cardioid and period 2 bulb.
+        #[derive(Debug)]
+        struct IteratedMandelbrot;
+
+        impl Fractal for IteratedMandelbrot {
+            fn step(&self, z: Complex, c: Complex) -> Complex {
+                z * z + c
+            }
+        }
+
+        impl fmt::Display for IteratedMandelbrot {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "iterated mandelbrot")
+            }
+        }
+
+        // A view of where the period 3 bulb meets the edge of the main cardioid,
+        // which is where the shortcut stops.
+        let frame = Frame::new(-0.125, 0.63, 0.16, 0.16);
+        let mut params = parameters(InteriorShading::Magnitude);
+        params.x_resolution = 40.try_into().unwrap();
+        params.y_resolution = 40.try_into().unwrap();
+        params.max_iterations = 200.try_into().unwrap();
+        let shortcut = render(&params, frame, false);
+        params.formula = Formula::Custom(Arc::new(IteratedMandelbrot));
+        let iterated = render(&params, frame, false);
+
+        for ((x, y, a), (_, _, b)) in shortcut.pixels().zip(iterated.pixels()) {
+            assert!(
+                a.0[0].abs_diff(b.0[0]) <= 1,
+                "pixel ({x}, {y}) is {} with the shortcut but {} without it",
+                a.0[0],
+                b.0[0]
+            );
+        }
+    }
+
+    #[test]
+    fn check_transparent_exterior() {
+        use crate::{render, Frame};
+        use image::GenericImageView;
+
+        let mut params = RenderParameters::try_new(
+            60.try_into().unwrap(),
+            40.try_into().unwrap(),
+            255.try_into().unwrap(),
+            3.try_into().unwrap(),
+            SupportedColorType::Rgba8,
+        )
+        .unwrap();
+        params.exterior = Exterior::Transparent {
+            interior_color: Some([10, 200, 30]),
+        };
+        let image = render(&params, Frame::new(-0.75, 0.0, 3.0, 2.0), false);
+
+        // Far outside the set the image is fully transparent
+        assert_eq!(image.get_pixel(0, 0).0[3], 0);
+        // and inside the main cardioid it has the interior color.
+        assert_eq!(image.get_pixel(41, 20).0, [10, 200, 30, 255]);
+        // Pixels on the boundary are partially covered.
+        assert!(image
+            .pixels()
+            .any(|(_, _, pixel)| (1..255).contains(&pixel.0[3])));
+    }
+
+    #[test]
+    fn check_binary_decomposition() {
+        use crate::{render, Frame};
+
+        let mut params = RenderParameters::try_new(
+            60.try_into().unwrap(),
+            40.try_into().unwrap(),
+            255.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::L8,
+        )
+        .unwrap();
+        let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+        let opaque = render(&params, frame, false).into_luma8();
+        params.exterior = Exterior::BinaryDecomposition;
+        let decomposed = render(&params, frame, false).into_luma8();
+
+        // Every pixel is either colored as usual or darkened,
+        for (a, b) in opaque.pixels().zip(decomposed.pixels()) {
+            assert!(b.0[0] <= a.0[0]);
+        }
+        // and about half of the exterior is darkened.
+        let exterior = opaque.pixels().filter(|pixel| pixel.0[0] > 0).count();
+        let darkened = opaque
+            .pixels()
+            .zip(decomposed.pixels())
+            .filter(|(a, b)| b.0[0] < a.0[0])
+            .count();
+        assert!(
+            (exterior / 4..exterior * 3 / 4).contains(&darkened),
+            "{darkened} of {exterior} pixels are darkened"
+        );
+    }
+
+    #[test]
+    fn check_parsing() {
+        for shading in InteriorShading::ALL {
+            assert_eq!(shading.to_string().parse(), Ok(shading));
+        }
+        assert!("glow".parse::<InteriorShading>().is_err());
+    }
+}
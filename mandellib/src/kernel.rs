@@ -0,0 +1,492 @@
+//! The escape time iteration and the smoothing of its result, which only need `core`
+//! and are available without the `std` feature.
+
+use core::num::NonZeroU64;
+
+use crate::math::{ln, log2};
+use crate::{EscapeRadius, Exponent, Float};
+
+/// The default `smoothing_offset` of [`smooth_escape_speed`] and the renderer.
+/// It is chosen because it makes the final image look nice with the classic palette.
+pub const DEFAULT_SMOOTHING_OFFSET: f64 = core::f64::consts::E + 1.0;
+
+/// Iterates the Multibrot function
+///
+/// ```math
+/// z_(n+1) = z_n^d + c
+/// ```
+///
+/// on the given c starting with z_0 = c until it either escapes the default [`EscapeRadius`]
+/// or the loop exceeds the maximum number of iterations.
+/// An exponent of d = 2 gives the Mandelbrot set.
+/// Returns a tuple of `(iterations, final real part of z, final imaginary part of z)`.
+/// The iteration is done in the precision of the given [`Float`] type.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{iterate, Exponent};
+/// # use core::num::NonZeroU64;
+/// const MAXITERS: NonZeroU64 = NonZeroU64::new(10).unwrap();
+/// // The origin is in the set
+/// assert_eq!(iterate(0.0, 0.0, Exponent::TWO, MAXITERS).0, MAXITERS.into());
+///
+/// // but 1 + i is not.
+/// assert_ne!(iterate(1.0, 1.0, Exponent::TWO, MAXITERS).0, MAXITERS.into());
+///
+/// // The orbit of -2 ends up at 2 and stays there.
+/// assert_eq!(iterate(-2.0, 0.0, Exponent::TWO, MAXITERS), (MAXITERS.into(), 2.0, 0.0));
+///
+/// // But with an exponent of 3 it escapes.
+/// let cubic = Exponent::try_from(3).unwrap();
+/// assert_ne!(iterate(-2.0, 0.0, cubic, MAXITERS).0, MAXITERS.into());
+/// ```
+///
+/// # Note
+///
+/// When the exponent is 2, points inside the main cardioid or period-2 bulb are not iterated
+/// but instead return immediately while reporting the maximum number of iterations.
+/// For those points the final value of z is not well defined and
+/// is currently returned as NaN to indicate that the value should not be used.
+///
+/// ```
+/// # use mandellib::{iterate, Exponent};
+/// # use core::num::NonZeroU64;
+/// # const MAXITERS: u64 = 100;
+/// # let maxiters = NonZeroU64::new(MAXITERS).unwrap();
+/// let (iters, broken_re, broken_im) = iterate(-1.0_f64, 0.0, Exponent::TWO, maxiters);
+/// assert_eq!(iters, MAXITERS);
+/// assert!(broken_re.is_nan() && broken_im.is_nan());
+/// ```
+#[must_use]
+pub fn iterate<F: Float>(
+    c_re: F,
+    c_im: F,
+    exponent: Exponent,
+    max_iterations: NonZeroU64,
+) -> (u64, F, F) {
+    iterate_with_escape_radius(c_re, c_im, exponent, max_iterations, EscapeRadius::DEFAULT)
+}
+
+/// Works like [`iterate`], but stops once |z| exceeds the given escape radius.
+/// If the radius is smaller than the default, points that escape are iterated
+/// [`EscapeRadius::smoothing_iterations`] more times without counting them,
+/// so that the returned z is large enough to color the point smoothly.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{iterate, iterate_with_escape_radius, EscapeRadius, Exponent};
+/// # use core::num::NonZeroU64;
+/// const MAXITERS: NonZeroU64 = NonZeroU64::new(100).unwrap();
+/// let radius = EscapeRadius::try_from(1000.0).unwrap();
+/// let (iterations, z_re, z_im) =
+///     iterate_with_escape_radius(1.0, 1.0, Exponent::TWO, MAXITERS, radius);
+/// // A larger radius takes more iterations to escape.
+/// assert!(iterations > iterate(1.0, 1.0, Exponent::TWO, MAXITERS).0);
+/// assert!(z_re * z_re + z_im * z_im > radius.squared());
+/// ```
+#[must_use]
+pub fn iterate_with_escape_radius<F: Float>(
+    c_re: F,
+    c_im: F,
+    exponent: Exponent,
+    max_iterations: NonZeroU64,
+    escape_radius: EscapeRadius,
+) -> (u64, F, F) {
+    iterate_mandelbrot(c_re, c_im, exponent, max_iterations, escape_radius, true)
+}
+
+/// Works like [`iterate_with_escape_radius`], but only skips the points in the main cardioid
+/// and period 2 bulb if `cardioid_check` is true.
+pub(crate) fn iterate_mandelbrot<F: Float>(
+    c_re: F,
+    c_im: F,
+    exponent: Exponent,
+    max_iterations: NonZeroU64,
+    escape_radius: EscapeRadius,
+    cardioid_check: bool,
+) -> (u64, F, F) {
+    let bailout_sqr = escape_radius.bailout_sqr();
+    let smoothing_iterations = escape_radius.smoothing_iterations();
+    if exponent == Exponent::TWO {
+        return iterate_quadratic(
+            c_re,
+            c_im,
+            max_iterations,
+            bailout_sqr,
+            smoothing_iterations,
+            cardioid_check,
+        );
+    }
+
+    // We have effectively performed one iteration of the function
+    // by starting with z = c.
+    iterate_power_from(
+        c_re,
+        c_im,
+        (1, c_re, c_im),
+        exponent.get(),
+        max_iterations.get(),
+        bailout_sqr,
+        smoothing_iterations,
+    )
+}
+
+/// Continues an iteration of [`iterate_mandelbrot`] that reached its maximum number of iterations,
+/// from the `(iterations, z_re, z_im)` that it returned, up to a larger maximum.
+/// Gives the same result as iterating the point from the start with the larger maximum.
+/// Points that were skipped by the cardioid check have no z to continue from, and must not be given.
+#[cfg(feature = "std")]
+pub(crate) fn resume_mandelbrot<F: Float>(
+    c_re: F,
+    c_im: F,
+    orbit: (u64, F, F),
+    exponent: Exponent,
+    max_iterations: NonZeroU64,
+    escape_radius: EscapeRadius,
+    cardioid_check: bool,
+) -> (u64, F, F) {
+    let (iterations, z_re, z_im) = orbit;
+    // The first iteration is not done in the same way as the rest, so it is simplest to start over.
+    if iterations <= 1 {
+        return iterate_mandelbrot(
+            c_re,
+            c_im,
+            exponent,
+            max_iterations,
+            escape_radius,
+            cardioid_check,
+        );
+    }
+    let bailout_sqr = escape_radius.bailout_sqr();
+    // The point escaped on the last iteration that it was allowed, and would stop there
+    // with a larger maximum as well. Its z has already been iterated further for smoothing.
+    if z_re * z_re + z_im * z_im > bailout_sqr {
+        return orbit;
+    }
+    let smoothing_iterations = escape_radius.smoothing_iterations();
+    if exponent == Exponent::TWO {
+        QuadraticOrbit::at(iterations, z_re, z_im).run(
+            c_re,
+            c_im,
+            max_iterations.get(),
+            bailout_sqr,
+            smoothing_iterations,
+        )
+    } else {
+        iterate_power_from(
+            c_re,
+            c_im,
+            orbit,
+            exponent.get(),
+            max_iterations.get(),
+            bailout_sqr,
+            smoothing_iterations,
+        )
+    }
+}
+
+/// Iterates z -> z^d + c from the given `(iterations, z_re, z_im)` until |z|^2 exceeds `bailout_sqr`
+/// or the maximum number of iterations is reached. Points that escape are then iterated
+/// `smoothing_iterations` more times without counting them.
+fn iterate_power_from<F: Float>(
+    c_re: F,
+    c_im: F,
+    (mut iterations, mut z_re, mut z_im): (u64, F, F),
+    exponent: u8,
+    max_iterations: u64,
+    bailout_sqr: F,
+    smoothing_iterations: u32,
+) -> (u64, F, F) {
+    let mut mag_sqr = z_re * z_re + z_im * z_im;
+
+    while iterations < max_iterations && mag_sqr <= bailout_sqr {
+        (z_re, z_im) = complex_powi(z_re, z_im, exponent);
+        z_re += c_re;
+        z_im += c_im;
+        mag_sqr = z_re * z_re + z_im * z_im;
+        iterations += 1;
+    }
+
+    if mag_sqr > bailout_sqr {
+        for _ in 0..smoothing_iterations {
+            (z_re, z_im) = complex_powi(z_re, z_im, exponent);
+            z_re += c_re;
+            z_im += c_im;
+        }
+    }
+
+    (iterations, z_re, z_im)
+}
+
+/// Returns true if the point is within the main cardioid or period 2 bulb of the Mandelbrot set.
+/// Such points are known to be inside the set, so they do not need to be iterated.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::in_main_cardioid_or_bulb;
+/// assert!(in_main_cardioid_or_bulb(0.0, 0.0));
+/// assert!(in_main_cardioid_or_bulb(-1.0, 0.1));
+/// // The tip of the antenna is in the set, but not in either of them.
+/// assert!(!in_main_cardioid_or_bulb(-2.0, 0.0));
+/// ```
+#[must_use]
+pub fn in_main_cardioid_or_bulb<F: Float>(c_re: F, c_im: F) -> bool {
+    let c_imag_sqr = c_im * c_im;
+    let mag_sqr = c_re * c_re + c_imag_sqr;
+    let one = F::from_f64(1.0);
+    (c_re + one) * (c_re + one) + c_imag_sqr <= F::from_f64(0.0625)
+        || mag_sqr * (F::from_f64(8.0) * mag_sqr - F::from_f64(3.0)) <= F::from_f64(0.09375) - c_re
+}
+
+/// Iterates the Mandelbrot function z -> z^2 + c.
+/// This is a special case of [`iterate`] that is optimized for the exponent 2.
+/// Points in the main cardioid and period 2 bulb are only skipped if `cardioid_check` is true.
+fn iterate_quadratic<F: Float>(
+    c_re: F,
+    c_im: F,
+    max_iterations: NonZeroU64,
+    bailout_sqr: F,
+    smoothing_iterations: u32,
+    cardioid_check: bool,
+) -> (u64, F, F) {
+    let c_imag_sqr = c_im * c_im;
+    let mag_sqr = c_re * c_re + c_imag_sqr;
+
+    let max_iterations = max_iterations.get();
+
+    if cardioid_check && in_main_cardioid_or_bulb(c_re, c_im) {
+        // We can unfortunately not know the final value of z in that case,
+        // so we return it as NAN.
+        return (max_iterations, F::NAN, F::NAN);
+    }
+
+    // We have effectively performed one iteration of the function
+    // by setting the starting values as below.
+    QuadraticOrbit {
+        iterations: 1,
+        z_re: c_re,
+        z_im: c_im,
+        z_re_sqr: mag_sqr - c_imag_sqr,
+        z_im_sqr: c_imag_sqr,
+        mag_sqr,
+    }
+    .run(
+        c_re,
+        c_im,
+        max_iterations,
+        bailout_sqr,
+        smoothing_iterations,
+    )
+}
+
+/// The state of the iteration of z -> z^2 + c between two steps,
+/// along with the squares of the parts of z that the next step reuses.
+struct QuadraticOrbit<F> {
+    iterations: u64,
+    z_re: F,
+    z_im: F,
+    z_re_sqr: F,
+    z_im_sqr: F,
+    mag_sqr: F,
+}
+
+impl<F: Float> QuadraticOrbit<F> {
+    /// The state of an orbit that has reached z after the given number of iterations.
+    #[cfg(feature = "std")]
+    fn at(iterations: u64, z_re: F, z_im: F) -> Self {
+        let z_re_sqr = z_re * z_re;
+        let z_im_sqr = z_im * z_im;
+        Self {
+            iterations,
+            z_re,
+            z_im,
+            z_re_sqr,
+            z_im_sqr,
+            mag_sqr: z_re_sqr + z_im_sqr,
+        }
+    }
+
+    /// Iterates the orbit until |z|^2 exceeds `bailout_sqr` or the maximum number of iterations is reached,
+    /// and returns a tuple of `(iterations, final real part of z, final imaginary part of z)`.
+    fn run(
+        self,
+        c_re: F,
+        c_im: F,
+        max_iterations: u64,
+        bailout_sqr: F,
+        smoothing_iterations: u32,
+    ) -> (u64, F, F) {
+        let Self {
+            mut iterations,
+            mut z_re,
+            mut z_im,
+            mut z_re_sqr,
+            mut z_im_sqr,
+            mut mag_sqr,
+        } = self;
+
+        // Iterates the mandelbrot function.
+        // This loop uses only 3 multiplications, which is the minimum.
+        // While it is common to abort when |z| > 2 since such a point is guaranteed
+        // to not be in the set, the default escape radius is 6 as this reduces
+        // color banding.
+        while iterations < max_iterations && mag_sqr <= bailout_sqr {
+            z_im *= z_re;
+            z_im += z_im;
+            z_im += c_im;
+            z_re = z_re_sqr - z_im_sqr + c_re;
+            z_re_sqr = z_re * z_re;
+            z_im_sqr = z_im * z_im;
+            mag_sqr = z_re_sqr + z_im_sqr;
+            iterations += 1;
+        }
+
+        if mag_sqr > bailout_sqr {
+            for _ in 0..smoothing_iterations {
+                (z_re, z_im) = (
+                    z_re * z_re - z_im * z_im + c_re,
+                    F::from_f64(2.0) * z_re * z_im + c_im,
+                );
+            }
+        }
+
+        (iterations, z_re, z_im)
+    }
+}
+
+/// Raises the complex number `re + i*im` to the given power by repeated squaring.
+pub(crate) fn complex_powi<F: Float>(re: F, im: F, mut exponent: u8) -> (F, F) {
+    let (mut result_re, mut result_im) = (F::from_f64(1.0), F::from_f64(0.0));
+    let (mut base_re, mut base_im) = (re, im);
+
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            (result_re, result_im) = (
+                result_re * base_re - result_im * base_im,
+                result_re * base_im + result_im * base_re,
+            );
+        }
+        (base_re, base_im) = (
+            base_re * base_re - base_im * base_im,
+            F::from_f64(2.0) * base_re * base_im,
+        );
+        exponent >>= 1;
+    }
+
+    (result_re, result_im)
+}
+
+/// Maps the result of an iteration that escaped smoothly to a number between 0 (close to the set) and 1 (far outside),
+/// the escape speed that images are colored by. This is kind of like the potential function of the set.
+///
+/// `iterations` and `mag_sqr`, the magnitude squared of the final z, are the result of the iteration,
+/// `degree` is how many times the logarithm of |z| is multiplied by in each iteration, i.e. the exponent,
+/// and `smoothing_iterations` is the number of iterations that were done after escaping without being counted,
+/// see [`EscapeRadius::smoothing_iterations`]. `smoothing_offset` is subtracted from the smoothed
+/// number of iterations, see [`DEFAULT_SMOOTHING_OFFSET`].
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{iterate, smooth_escape_speed, Exponent, DEFAULT_SMOOTHING_OFFSET};
+/// # use core::num::NonZeroU64;
+/// const MAXITERS: NonZeroU64 = NonZeroU64::new(100).unwrap();
+/// let speed = |c_re: f64| {
+///     let (iterations, z_re, z_im) = iterate(c_re, 0.0, Exponent::TWO, MAXITERS);
+///     let mag_sqr = z_re * z_re + z_im * z_im;
+///     smooth_escape_speed(iterations, MAXITERS, mag_sqr, 2.0, 0, DEFAULT_SMOOTHING_OFFSET)
+/// };
+/// // Points further from the set escape faster.
+/// assert!(speed(0.3) < speed(0.5));
+/// assert!(speed(0.5) < speed(1.0) && speed(1.0) < 1.0);
+/// ```
+#[must_use]
+pub fn smooth_escape_speed(
+    iterations: u64,
+    max_iterations: NonZeroU64,
+    mag_sqr: f64,
+    degree: f64,
+    smoothing_iterations: u32,
+    smoothing_offset: f64,
+) -> f64 {
+    // The magnitude grows as |z|^(d^n), so the logarithm base must match the exponent
+    // in order for the escape speed to be continuous across iteration counts.
+    let log_log_mag = if degree == 2.0 {
+        log2(ln(mag_sqr))
+    } else {
+        ln(ln(mag_sqr)) / ln(degree)
+    };
+    // Each uncounted iteration after escaping multiplies the logarithm of |z| by the degree.
+    let log_log_mag = log_log_mag - f64::from(smoothing_iterations);
+    let max_iterations = max_iterations.get();
+    // The number of iterations that were left is computed exactly before it is converted,
+    // so the smoothing is only lost to rounding once more than 2^53 iterations are left.
+    (iterations_to_f64(max_iterations - iterations) + log_log_mag - smoothing_offset)
+        / iterations_to_f64(max_iterations)
+}
+
+/// Converts a number of iterations to an `f64`.
+/// Numbers above 2^53 are rounded to a relative precision of about 10^-16.
+#[inline]
+pub(crate) fn iterations_to_f64(iterations: u64) -> f64 {
+    // The rounding is far finer than any difference that the colors can show.
+    #[allow(clippy::cast_precision_loss)]
+    let iterations = iterations as f64;
+    iterations
+}
+
+#[cfg(test)]
+mod test_kernel {
+    use super::*;
+
+    #[test]
+    fn check_some_iterations() {
+        let max_iterations = NonZeroU64::new(255).unwrap();
+        assert_eq!(iterate(0.0, 0.0, Exponent::TWO, max_iterations).0, 255);
+        assert_eq!(iterate(-2.0, 0.0, Exponent::TWO, max_iterations).0, 255);
+    }
+
+    #[test]
+    fn check_multibrot_iterations() {
+        let max_iterations = NonZeroU64::new(255).unwrap();
+        let cubic = Exponent::try_from(3).unwrap();
+        // The cubic Multibrot set is symmetric under c -> -c, which also negates the orbit.
+        let (iterations, z_re, z_im) = iterate(0.3, 0.5, cubic, max_iterations);
+        assert_eq!(
+            iterate(-0.3, -0.5, cubic, max_iterations),
+            (iterations, -z_re, -z_im)
+        );
+        assert_eq!(iterate(0.0, 0.0, cubic, max_iterations).0, 255);
+        assert_eq!(complex_powi(0.0, 1.0, 3), (0.0, -1.0));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn check_resumed_iterations() {
+        let radius = EscapeRadius::try_from(2.0).unwrap();
+        let points = [
+            (-0.75, 0.1),
+            (0.3, 0.5),
+            (-1.4, 0.0),
+            (0.26, 0.0015),
+            (-0.1, 0.9),
+        ];
+        for exponent in [Exponent::TWO, Exponent::try_from(3).unwrap()] {
+            for (c_re, c_im) in points {
+                for low in [1, 2, 7, 40] {
+                    let low = NonZeroU64::new(low).unwrap();
+                    let high = NonZeroU64::new(500).unwrap();
+                    let orbit = iterate_mandelbrot(c_re, c_im, exponent, low, radius, false);
+                    assert_eq!(
+                        resume_mandelbrot(c_re, c_im, orbit, exponent, high, radius, false),
+                        iterate_mandelbrot(c_re, c_im, exponent, high, radius, false),
+                        "c = {c_re} + {c_im}i with the exponent {exponent:?} from {low} iterations"
+                    );
+                }
+            }
+        }
+    }
+}
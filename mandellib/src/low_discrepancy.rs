@@ -0,0 +1,198 @@
+//! Two dimensional low discrepancy sequences, which spread points over the unit square
+//! more evenly than random points do, so that estimates from them converge faster.
+//!
+//! The sequences are fully deterministic, so the same settings always give the same image.
+
+use core::fmt;
+use core::str::FromStr;
+
+/// A sequence of points in the unit square [0, 1)^2 where every prefix is spread out evenly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LowDiscrepancySequence {
+    /// The additive recurrence of the plastic number, which is evenly spread for any number of points.
+    #[default]
+    R2,
+    /// The radical inverses of the index in base 2 and 3.
+    Halton,
+    /// The first two dimensions of the Sobol sequence. The first 2^m points of it have exactly
+    /// one point in every box of area 2^-m whose sides are powers of two,
+    /// so it is best used with a power of two number of points.
+    Sobol,
+}
+
+impl LowDiscrepancySequence {
+    pub const ALL: [Self; 3] = [Self::R2, Self::Halton, Self::Sobol];
+
+    /// Returns the point with the given index in the sequence.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::LowDiscrepancySequence;
+    /// assert_eq!(LowDiscrepancySequence::Halton.point(1), (0.5, 1.0 / 3.0));
+    /// assert_eq!(LowDiscrepancySequence::Sobol.point(2), (0.25, 0.75));
+    /// ```
+    #[must_use]
+    pub fn point(self, index: u64) -> (f64, f64) {
+        match self {
+            Self::R2 => {
+                /// The plastic number, the unique real solution of x^3 = x + 1.
+                const PLASTIC: f64 = 1.324_717_957_244_746;
+                // Precision is lost for very large indices, but the points are still spread out.
+                #[allow(clippy::cast_precision_loss)]
+                let n = index as f64;
+                (
+                    (0.5 + n / PLASTIC).fract(),
+                    (0.5 + n / (PLASTIC * PLASTIC)).fract(),
+                )
+            }
+            Self::Halton => (radical_inverse(index, 2), radical_inverse(index, 3)),
+            Self::Sobol => {
+                // The direction numbers of the second dimension come from the primitive polynomial x + 1.
+                let mut direction = 1 << 63;
+                let mut second = 0_u64;
+                let mut bits = index;
+                while bits != 0 {
+                    if bits & 1 == 1 {
+                        second ^= direction;
+                    }
+                    direction ^= direction >> 1;
+                    bits >>= 1;
+                }
+                (unit_fraction(index.reverse_bits()), unit_fraction(second))
+            }
+        }
+    }
+}
+
+/// Mirrors the digits of `index` in the given base around the decimal point.
+fn radical_inverse(mut index: u64, base: u64) -> f64 {
+    #[allow(clippy::cast_precision_loss)]
+    let inverse_base = 1.0 / base as f64;
+    let mut scale = inverse_base;
+    let mut result = 0.0;
+    while index != 0 {
+        #[allow(clippy::cast_precision_loss)]
+        let digit = (index % base) as f64;
+        result += digit * scale;
+        scale *= inverse_base;
+        index /= base;
+    }
+    result
+}
+
+/// Interprets the bits as a binary fraction in [0, 1), keeping as many of them as fit in an f64.
+fn unit_fraction(bits: u64) -> f64 {
+    // The shifted value has at most 53 bits, which an f64 represents exactly.
+    #[allow(clippy::cast_precision_loss)]
+    let mantissa = (bits >> 11) as f64;
+    mantissa / (1_u64 << 53) as f64
+}
+
+impl fmt::Display for LowDiscrepancySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::R2 => write!(f, "r2"),
+            Self::Halton => write!(f, "halton"),
+            Self::Sobol => write!(f, "sobol"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLowDiscrepancySequenceError(String);
+
+impl fmt::Display for ParseLowDiscrepancySequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown sequence \"{}\", expected \"r2\", \"halton\" or \"sobol\"",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseLowDiscrepancySequenceError {}
+
+impl FromStr for LowDiscrepancySequence {
+    type Err = ParseLowDiscrepancySequenceError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "r2" => Ok(Self::R2),
+            "halton" => Ok(Self::Halton),
+            "sobol" => Ok(Self::Sobol),
+            _ => Err(ParseLowDiscrepancySequenceError(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_low_discrepancy {
+    use super::*;
+
+    #[test]
+    fn check_known_points() {
+        let sobol: Vec<_> = (0..6)
+            .map(|i| LowDiscrepancySequence::Sobol.point(i))
+            .collect();
+        assert_eq!(
+            sobol,
+            [
+                (0.0, 0.0),
+                (0.5, 0.5),
+                (0.25, 0.75),
+                (0.75, 0.25),
+                (0.125, 0.625),
+                (0.625, 0.125)
+            ]
+        );
+        let halton: Vec<_> = (1..4)
+            .map(|i| LowDiscrepancySequence::Halton.point(i))
+            .collect();
+        assert_eq!(
+            halton,
+            [(0.5, 1.0 / 3.0), (0.25, 2.0 / 3.0), (0.75, 1.0 / 9.0)]
+        );
+    }
+
+    #[test]
+    fn check_stratification() {
+        // Every box of 2^-8 of the square with power of two sides gets one of the first 256 Sobol points.
+        let points: Vec<_> = (0..256)
+            .map(|i| LowDiscrepancySequence::Sobol.point(i))
+            .collect();
+        for columns in [1_u32, 2, 4, 16, 64, 256] {
+            let rows = 256 / columns;
+            let mut counts = vec![0; 256];
+            for &(x, y) in &points {
+                let column = (x * f64::from(columns)).floor() as usize;
+                let row = (y * f64::from(rows)).floor() as usize;
+                counts[row * columns as usize + column] += 1;
+            }
+            assert!(counts.iter().all(|&count| count == 1), "{columns} columns");
+        }
+
+        // Every sequence fills each quarter of the square with roughly a quarter of its points.
+        for sequence in LowDiscrepancySequence::ALL {
+            let mut quarters = [0; 4];
+            for i in 0..1000 {
+                let (x, y) = sequence.point(i);
+                assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+                quarters[usize::from(x >= 0.5) + 2 * usize::from(y >= 0.5)] += 1;
+            }
+            assert!(
+                quarters.iter().all(|&count| (240..=260).contains(&count)),
+                "{sequence}: {quarters:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn check_parsing() {
+        for sequence in LowDiscrepancySequence::ALL {
+            assert_eq!(sequence.to_string().parse(), Ok(sequence));
+        }
+        assert_eq!(" Sobol".parse(), Ok(LowDiscrepancySequence::Sobol));
+        assert!("random".parse::<LowDiscrepancySequence>().is_err());
+    }
+}
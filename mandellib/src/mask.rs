@@ -0,0 +1,212 @@
+use core::fmt;
+
+use color_space::SupportedColorType;
+use image::{imageops::FilterType, DynamicImage, Pixel as _, Rgb};
+
+use crate::U32AndUsize;
+
+// Mask pixels with a luminance at or above this value are considered active.
+const ACTIVE_THRESHOLD: u8 = 128;
+
+// The largest relative difference between the aspect ratio of the mask
+// and the aspect ratio of the image that is tolerated.
+const ASPECT_RATIO_TOLERANCE: f64 = 0.01;
+
+/// Restricts which pixels of an image are computed.
+///
+/// Pixels where the mask is white (or where it is black, if the mask is inverted)
+/// are rendered as usual, while the rest are filled with a constant color.
+/// This is useful for composited art, or for cheaply re-rendering only
+/// the part of an image that has changed.
+#[derive(Debug, Clone)]
+pub struct Mask {
+    // The mask data is stored in the same rotated fashion as the image
+    // during rendering, so that the values for one band lie contiguous in memory.
+    // The first element of every band is at the bottom of the image.
+    active: Vec<bool>,
+    // True if the corresponding band contains at least one active pixel.
+    band_is_active: Vec<bool>,
+    y_resolution: usize,
+    fill_color: Rgb<u8>,
+}
+
+impl Mask {
+    /// Creates a new mask for an image of the given resolution from the given mask image.
+    /// The mask image is resized to the resolution of the image if needed.
+    ///
+    /// If `invert` is true the pixels where the mask image is black are rendered
+    /// instead of the ones where it is white.
+    ///
+    /// # Errors
+    /// Returns an error if the aspect ratio of the mask image differs from
+    /// the aspect ratio of the image.
+    pub fn new(
+        mask_image: &DynamicImage,
+        x_resolution: U32AndUsize,
+        y_resolution: U32AndUsize,
+        fill_color: Rgb<u8>,
+        invert: bool,
+    ) -> Result<Self, MaskError> {
+        let mask_aspect_ratio = f64::from(mask_image.width()) / f64::from(mask_image.height());
+        let image_aspect_ratio = f64::from(x_resolution) / f64::from(y_resolution);
+        if (mask_aspect_ratio / image_aspect_ratio - 1.0).abs() > ASPECT_RATIO_TOLERANCE {
+            return Err(MaskError::AspectRatioMismatch {
+                mask_resolution: (mask_image.width(), mask_image.height()),
+                image_resolution: (x_resolution.into(), y_resolution.into()),
+            });
+        }
+
+        let luma = mask_image
+            .resize_exact(
+                x_resolution.into(),
+                y_resolution.into(),
+                FilterType::Nearest,
+            )
+            .into_luma8();
+
+        let x_resolution = usize::from(x_resolution);
+        let y_resolution = usize::from(y_resolution);
+
+        let mut active = Vec::with_capacity(x_resolution * y_resolution);
+        for x in 0..x_resolution {
+            for y in (0..y_resolution).rev() {
+                // The casts can not truncate since the values are smaller than the resolution,
+                // which fits in a u32.
+                let is_white = luma.get_pixel(x as u32, y as u32).0[0] >= ACTIVE_THRESHOLD;
+                active.push(is_white != invert);
+            }
+        }
+
+        let band_is_active = active
+            .chunks_exact(y_resolution)
+            .map(|band| band.iter().any(|&a| a))
+            .collect();
+
+        Ok(Self {
+            active,
+            band_is_active,
+            y_resolution,
+            fill_color,
+        })
+    }
+
+    /// Returns whether the pixel at the given position in the given band should be computed.
+    pub(crate) fn is_active(&self, band_index: usize, pixel_index: usize) -> bool {
+        self.active[band_index * self.y_resolution + pixel_index]
+    }
+
+    /// Returns whether any pixel in the given band should be computed.
+    pub(crate) fn band_is_active(&self, band_index: usize) -> bool {
+        self.band_is_active[band_index]
+    }
+
+    /// Returns the raw bytes of the color that pixels outside the mask are filled with
+    /// in the given format.
+    pub(crate) fn fill_bytes(&self, color_type: SupportedColorType) -> Vec<u8> {
+        match color_type {
+            SupportedColorType::L8 => self.fill_color.to_luma().0.to_vec(),
+            SupportedColorType::Rgb8 => self.fill_color.0.to_vec(),
+            SupportedColorType::Rgba8 => self.fill_color.to_rgba().0.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaskError {
+    AspectRatioMismatch {
+        mask_resolution: (u32, u32),
+        image_resolution: (u32, u32),
+    },
+}
+
+impl fmt::Display for MaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AspectRatioMismatch {
+                mask_resolution,
+                image_resolution,
+            } => write!(
+                f,
+                "the mask has a resolution of {}x{} which does not have the same aspect ratio as the image resolution of {}x{}",
+                mask_resolution.0, mask_resolution.1, image_resolution.0, image_resolution.1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MaskError {}
+
+#[cfg(test)]
+mod test_mask {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn check_mask_orientation() {
+        // A 2x2 mask where only the top left pixel is white.
+        let mut mask_image = GrayImage::new(2, 2);
+        mask_image.put_pixel(0, 0, Luma([255]));
+        let mask_image = DynamicImage::ImageLuma8(mask_image);
+
+        let res: U32AndUsize = 2.try_into().unwrap();
+        let mask = Mask::new(&mask_image, res, res, Rgb([0, 0, 0]), false).unwrap();
+
+        // The top left pixel is the last pixel in the first band.
+        assert!(mask.is_active(0, 1));
+        assert!(!mask.is_active(0, 0));
+        assert!(mask.band_is_active(0));
+        assert!(!mask.band_is_active(1));
+
+        let inverted = Mask::new(&mask_image, res, res, Rgb([0, 0, 0]), true).unwrap();
+        assert!(!inverted.is_active(0, 1));
+        assert!(inverted.band_is_active(1));
+    }
+
+    #[test]
+    fn check_masked_render() {
+        use crate::{render_masked, Frame, RenderParameters};
+
+        // Only render the top left quadrant of the image.
+        let mut mask_image = GrayImage::new(6, 4);
+        for x in 0..3 {
+            for y in 0..2 {
+                mask_image.put_pixel(x, y, Luma([255]));
+            }
+        }
+        let mask_image = DynamicImage::ImageLuma8(mask_image);
+
+        let params = RenderParameters::try_new(
+            12.try_into().unwrap(),
+            8.try_into().unwrap(),
+            255.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let mask = Mask::new(
+            &mask_image,
+            params.x_resolution,
+            params.y_resolution,
+            Rgb([255, 0, 0]),
+            false,
+        )
+        .unwrap();
+
+        let image =
+            render_masked(&params, Frame::new(-0.75, 0.1, 3.0, 2.0), &mask, false).into_rgb8();
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if x < 6 && y < 4 {
+                assert_ne!(*pixel, Rgb([255, 0, 0]));
+            } else {
+                assert_eq!(*pixel, Rgb([255, 0, 0]));
+            }
+        }
+    }
+
+    #[test]
+    fn check_aspect_ratio_mismatch() {
+        let mask_image = DynamicImage::ImageLuma8(GrayImage::new(4, 2));
+        let res: U32AndUsize = 2.try_into().unwrap();
+        assert!(Mask::new(&mask_image, res, res, Rgb([0, 0, 0]), false).is_err());
+    }
+}
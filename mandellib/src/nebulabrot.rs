@@ -0,0 +1,411 @@
+//! Renders the Buddhabrot, the density of the orbits of points outside the set,
+//! and its multi-channel variant the Nebulabrot, where each color channel shows the density
+//! of orbits that escape within a different maximum number of iterations.
+
+use core::num::{NonZeroU32, NonZeroU64};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use color_space::SupportedColorType;
+use image::{DynamicImage, GrayImage, Luma, Rgb, RgbImage};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::progress::verbose_progress;
+use crate::sample_placement::jitter;
+use crate::{
+    check_frame, complex_powi, in_main_cardioid_or_bulb, Exponent, Frame, LowDiscrepancySequence,
+    RenderError, RenderParameters,
+};
+
+/// The points c are sampled in the square with this half side length around the origin,
+/// which contains the Mandelbrot and Multibrot sets.
+const SAMPLING_RADIUS: f64 = 2.0;
+
+/// Orbits that leave the disk with this squared radius are considered to have escaped.
+const BAILOUT_SQR: f64 = 4.0;
+
+/// The number of samples that are traced by each parallel unit of work.
+const SAMPLES_PER_CHUNK: u64 = 1 << 14;
+
+/// The settings of a Buddhabrot or Nebulabrot render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nebulabrot {
+    /// The maximum number of iterations of the orbits that are counted
+    /// in the red, green and blue channels respectively.
+    pub channel_max_iterations: [NonZeroU32; 3],
+    /// The number of points c whose orbits are traced.
+    pub samples: NonZeroU64,
+    /// The sequence that the points c are taken from.
+    pub sequence: LowDiscrepancySequence,
+}
+
+impl Nebulabrot {
+    /// Creates the settings of a Nebulabrot whose points are taken from the default [`LowDiscrepancySequence`].
+    #[must_use]
+    pub const fn new(channel_max_iterations: [NonZeroU32; 3], samples: NonZeroU64) -> Self {
+        Self {
+            channel_max_iterations,
+            samples,
+            sequence: LowDiscrepancySequence::R2,
+        }
+    }
+
+    /// The Buddhabrot, which uses the same maximum number of iterations in every channel
+    /// and thus gives a grayscale image.
+    #[must_use]
+    pub const fn buddhabrot(max_iterations: NonZeroU32, samples: NonZeroU64) -> Self {
+        Self::new([max_iterations; 3], samples)
+    }
+}
+
+/// Renders the density of the orbits of points outside the set that pass through each pixel
+/// of the given region, with the settings in `nebulabrot`.
+/// The density in each channel is normalized so that the densest pixel is fully bright.
+///
+/// Only the resolution, the exponent, the color type and the seed of the render parameters are used,
+/// and the orbits are always those of z -> z^d + c.
+/// If the color type is grayscale the channels are averaged, otherwise the image is RGB.
+///
+/// The sampled points are spread evenly with the low discrepancy sequence in `nebulabrot`.
+/// All of them are shifted by the same pseudo-random amount that only depends on the
+/// [`seed`](RenderParameters::seed), wrapping around at the edges of the sampling square.
+/// The points stay as evenly spread as before, but renders with different seeds trace different orbits,
+/// so that their noise is independent. The points are not shifted when the seed is 0.
+/// The same settings always give the same image, however many threads render it.
+///
+/// If `verbose` is true the function will use prints to `stderr` to display a progress bar.
+///
+/// # Panics
+/// Panics if the memory for the orbit counts can not be allocated.
+/// Use [`try_render_nebulabrot`] to get an error instead.
+#[must_use]
+pub fn render_nebulabrot(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    nebulabrot: &Nebulabrot,
+    verbose: bool,
+) -> DynamicImage {
+    nebulabrot_image(render_parameters, render_region, nebulabrot, verbose)
+        .unwrap_or_else(|error| panic!("{error}"))
+}
+
+/// Works like [`render_nebulabrot`], but checks that the frame can be rendered
+/// and returns an error instead of panicking if the orbit counts can not be allocated.
+///
+/// # Errors
+/// Returns an error if the center of the frame is not finite, if its distances
+/// are not finite and positive, or if the memory for the orbit counts can not be allocated.
+pub fn try_render_nebulabrot(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    nebulabrot: &Nebulabrot,
+    verbose: bool,
+) -> Result<DynamicImage, RenderError> {
+    check_frame(render_region)?;
+    nebulabrot_image(render_parameters, render_region, nebulabrot, verbose)
+}
+
+/// Renders the density of the orbits, see [`render_nebulabrot`].
+fn nebulabrot_image(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    nebulabrot: &Nebulabrot,
+    verbose: bool,
+) -> Result<DynamicImage, RenderError> {
+    let _render_span = tracing::info_span!(
+        "nebulabrot",
+        channel_max_iterations = ?nebulabrot.channel_max_iterations,
+        samples = nebulabrot.samples.get(),
+    )
+    .entered();
+
+    let x_resolution = usize::from(render_parameters.x_resolution);
+    let y_resolution = usize::from(render_parameters.y_resolution);
+    let too_large = RenderError::ImageTooLarge {
+        x_resolution: render_parameters.x_resolution.into(),
+        y_resolution: render_parameters.y_resolution.into(),
+        color_type: render_parameters.color_type,
+    };
+    let pixels = x_resolution.checked_mul(y_resolution).ok_or(too_large)?;
+    let mut counts: Vec<[AtomicU32; 3]> = Vec::new();
+    counts.try_reserve_exact(pixels).map_err(|_| too_large)?;
+    counts.extend((0..pixels).map(|_| [AtomicU32::new(0), AtomicU32::new(0), AtomicU32::new(0)]));
+
+    let longest_orbit = nebulabrot
+        .channel_max_iterations
+        .iter()
+        .max()
+        .expect("there are three channels")
+        .get();
+    let left = render_region.center_real - render_region.real_distance / 2.0;
+    let top = render_region.center_imag + render_region.imag_distance / 2.0;
+    let real_delta = render_region.real_distance / x_resolution as f64;
+    let imag_delta = render_region.imag_distance / y_resolution as f64;
+
+    // Returns the index of the pixel that contains the given point, if it is in the image.
+    let pixel_index = |re: f64, im: f64| {
+        let x = ((re - left) / real_delta).floor();
+        let y = ((top - im) / imag_delta).floor();
+        // The range checks make sure that the casts do not truncate.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        ((0.0..x_resolution as f64).contains(&x) && (0.0..y_resolution as f64).contains(&y))
+            .then(|| y as usize * x_resolution + x as usize)
+    };
+
+    let shift = sequence_shift(render_parameters.seed);
+    let samples = nebulabrot.samples.get();
+    let chunks = samples.div_ceil(SAMPLES_PER_CHUNK);
+    let progress = verbose_progress(verbose);
+    progress.start(chunks);
+
+    (0..chunks)
+        .into_par_iter()
+        .for_each_init(Vec::new, |orbit, chunk| {
+            render_parameters.cpu_limit.throttle(|| {
+                let first = chunk * SAMPLES_PER_CHUNK;
+                for sample in first..(first + SAMPLES_PER_CHUNK).min(samples) {
+                    let (c_re, c_im) = sample_point(nebulabrot.sequence, shift, sample);
+                    let Some(escape_iterations) =
+                        trace_orbit(c_re, c_im, render_parameters.exponent, longest_orbit, orbit)
+                    else {
+                        continue;
+                    };
+                    for (channel, max_iterations) in
+                        nebulabrot.channel_max_iterations.iter().enumerate()
+                    {
+                        if escape_iterations <= max_iterations.get() {
+                            for &(re, im) in orbit.iter() {
+                                if let Some(index) = pixel_index(re, im) {
+                                    counts[index][channel].fetch_add(1, Ordering::Relaxed);
+                                }
+                            }
+                        }
+                    }
+                }
+            });
+            progress.advance(1);
+        });
+    progress.finish();
+
+    let counts: Vec<[u32; 3]> = counts
+        .into_iter()
+        .map(|pixel| pixel.map(AtomicU32::into_inner))
+        .collect();
+    let mut densest = [1; 3];
+    for pixel in &counts {
+        for (densest, &count) in densest.iter_mut().zip(pixel) {
+            *densest = count.max(*densest);
+        }
+    }
+    // The square root brings out the fainter orbits.
+    // The ratio is at most one, so the result fits in a u8.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let brightness = |count: u32, densest: u32| {
+        (f64::from(u8::MAX) * (f64::from(count) / f64::from(densest)).sqrt()).round() as u8
+    };
+    let channels = |x: u32, y: u32| {
+        let pixel = counts[y as usize * x_resolution + x as usize];
+        [0, 1, 2].map(|channel| brightness(pixel[channel], densest[channel]))
+    };
+
+    let (width, height) = (
+        render_parameters.x_resolution.into(),
+        render_parameters.y_resolution.into(),
+    );
+    Ok(match render_parameters.color_type {
+        SupportedColorType::L8 => {
+            DynamicImage::ImageLuma8(GrayImage::from_fn(width, height, |x, y| {
+                let [r, g, b] = channels(x, y).map(u16::from);
+                // The average of three u8s fits in a u8.
+                #[allow(clippy::cast_possible_truncation)]
+                Luma([((r + g + b) / 3) as u8])
+            }))
+        }
+        SupportedColorType::Rgb8 | SupportedColorType::Rgba8 => {
+            DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| Rgb(channels(x, y))))
+        }
+    })
+}
+
+/// Returns how far the points of the sequence are shifted along each axis of the unit square
+/// for the given seed, see [`render_nebulabrot`].
+fn sequence_shift(seed: u64) -> (f64, f64) {
+    if seed == 0 {
+        (0.0, 0.0)
+    } else {
+        (jitter(seed, (0, 0), 0), jitter(seed, (0, 0), 1))
+    }
+}
+
+/// Returns the point c of the given sample, from the low discrepancy sequence
+/// shifted by `shift` and scaled to the sampling square.
+fn sample_point(sequence: LowDiscrepancySequence, shift: (f64, f64), sample: u64) -> (f64, f64) {
+    let (u, v) = sequence.point(sample);
+    let (u, v) = ((u + shift.0).fract(), (v + shift.1).fract());
+    (
+        SAMPLING_RADIUS * (2.0 * u - 1.0),
+        SAMPLING_RADIUS * (2.0 * v - 1.0),
+    )
+}
+
+/// Iterates z -> z^d + c from z = 0 and stores the orbit in `orbit`.
+/// Returns the number of iterations it took to escape, or `None` if it did not escape
+/// within `max_iterations` iterations.
+fn trace_orbit(
+    c_re: f64,
+    c_im: f64,
+    exponent: Exponent,
+    max_iterations: u32,
+    orbit: &mut Vec<(f64, f64)>,
+) -> Option<u32> {
+    // These points never escape, so there is no need to iterate them.
+    if exponent == Exponent::TWO && in_main_cardioid_or_bulb(c_re, c_im) {
+        return None;
+    }
+
+    orbit.clear();
+    let (mut z_re, mut z_im) = (0.0, 0.0);
+    for iterations in 1..=max_iterations {
+        let (w_re, w_im) = complex_powi(z_re, z_im, exponent.get());
+        (z_re, z_im) = (w_re + c_re, w_im + c_im);
+        if z_re * z_re + z_im * z_im > BAILOUT_SQR {
+            return Some(iterations);
+        }
+        orbit.push((z_re, z_im));
+    }
+    None
+}
+
+#[cfg(test)]
+mod test_nebulabrot {
+    use super::*;
+
+    fn parameters(color_type: SupportedColorType) -> RenderParameters {
+        RenderParameters::try_new(
+            40.try_into().unwrap(),
+            40.try_into().unwrap(),
+            1.try_into().unwrap(),
+            1.try_into().unwrap(),
+            color_type,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn check_buddhabrot_and_nebulabrot() {
+        let frame = Frame::new(-0.5, 0.0, 3.0, 3.0);
+        let samples = NonZeroU64::new(200_000).unwrap();
+
+        let buddhabrot = render_nebulabrot(
+            &parameters(SupportedColorType::Rgb8),
+            frame,
+            &Nebulabrot::buddhabrot(100.try_into().unwrap(), samples),
+            false,
+        )
+        .into_rgb8();
+        // Every channel of the Buddhabrot is the same.
+        assert!(buddhabrot
+            .pixels()
+            .all(|pixel| pixel.0[0] == pixel.0[1] && pixel.0[1] == pixel.0[2]));
+        assert!(buddhabrot.pixels().any(|pixel| pixel.0[0] > 0));
+
+        let nebulabrot = render_nebulabrot(
+            &parameters(SupportedColorType::Rgb8),
+            frame,
+            &Nebulabrot::new(
+                [1000, 100, 10].map(|iterations| iterations.try_into().unwrap()),
+                samples,
+            ),
+            false,
+        )
+        .into_rgb8();
+        assert!(nebulabrot.pixels().any(|pixel| pixel.0[0] != pixel.0[2]));
+        // The densest pixel of every channel is fully bright.
+        for channel in 0..3 {
+            assert!(nebulabrot.pixels().any(|pixel| pixel.0[channel] == u8::MAX));
+        }
+
+        let grayscale = render_nebulabrot(
+            &parameters(SupportedColorType::L8),
+            frame,
+            &Nebulabrot::buddhabrot(100.try_into().unwrap(), samples),
+            false,
+        )
+        .into_luma8();
+        assert!(grayscale
+            .pixels()
+            .zip(buddhabrot.pixels())
+            .all(|(luma, rgb)| luma.0[0] == rgb.0[0]));
+    }
+
+    #[test]
+    fn check_invalid_frame() {
+        let frame = Frame::new(-0.5, 0.0, -3.0, 3.0);
+        assert_eq!(
+            try_render_nebulabrot(
+                &parameters(SupportedColorType::Rgb8),
+                frame,
+                &Nebulabrot::buddhabrot(100.try_into().unwrap(), NonZeroU64::MIN),
+                false,
+            ),
+            Err(RenderError::InvalidFrame(frame))
+        );
+    }
+
+    #[test]
+    fn check_sequences() {
+        let frame = Frame::new(-0.5, 0.0, 3.0, 3.0);
+        let parameters = parameters(SupportedColorType::L8);
+        let render = |sequence| {
+            let mut buddhabrot =
+                Nebulabrot::buddhabrot(50.try_into().unwrap(), NonZeroU64::new(1 << 16).unwrap());
+            buddhabrot.sequence = sequence;
+            render_nebulabrot(&parameters, frame, &buddhabrot, false).into_luma8()
+        };
+        let r2 = render(LowDiscrepancySequence::R2);
+        for sequence in LowDiscrepancySequence::ALL {
+            let image = render(sequence);
+            // The same settings always give the same image.
+            assert_eq!(image, render(sequence));
+            // The sequences sample the same density, so they give nearly the same image.
+            let difference: f64 = image
+                .pixels()
+                .zip(r2.pixels())
+                .map(|(a, b)| (f64::from(a.0[0]) - f64::from(b.0[0])).abs())
+                .sum::<f64>()
+                / f64::from(image.width() * image.height());
+            assert!(difference < 8.0, "{sequence}: {difference}");
+        }
+    }
+
+    #[test]
+    fn check_seeds() {
+        let frame = Frame::new(-0.5, 0.0, 3.0, 3.0);
+        let buddhabrot =
+            Nebulabrot::buddhabrot(50.try_into().unwrap(), NonZeroU64::new(1 << 16).unwrap());
+        let render = |seed, threads| {
+            let mut parameters = parameters(SupportedColorType::L8);
+            parameters.seed = seed;
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap()
+                .install(|| render_nebulabrot(&parameters, frame, &buddhabrot, false))
+                .into_luma8()
+        };
+        let unshifted = render(0, 2);
+        assert_eq!(
+            sample_point(LowDiscrepancySequence::R2, sequence_shift(0), 5),
+            {
+                let (u, v) = LowDiscrepancySequence::R2.point(5);
+                (2.0 * (2.0 * u - 1.0), 2.0 * (2.0 * v - 1.0))
+            }
+        );
+        for seed in [1, 42] {
+            // The same seed gives the same image, whichever threads trace the orbits.
+            let image = render(seed, 1);
+            assert_eq!(image, render(seed, 3));
+            assert_ne!(image, unshifted);
+            assert_ne!(image, render(seed + 1, 1));
+        }
+    }
+}
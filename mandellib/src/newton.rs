@@ -0,0 +1,742 @@
+//! Renders Newton fractals, the basins of attraction of the roots of a polynomial
+//! under Newton's method z -> z - p(z) / p'(z).
+//!
+//! Unlike the other fractals nothing escapes here: every point is iterated until it converges
+//! to one of the roots, and it is colored by which root that is and how long it took to get there.
+
+use core::fmt;
+use core::num::NonZeroU64;
+use core::str::FromStr;
+
+use color_space::{ColorMap, LinearRGB, Pixel, SupportedColorType};
+use image::DynamicImage;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::progress::verbose_progress;
+use crate::{
+    allocate_rotated_image, check_frame, iterations_to_f64, parallel_rotate270, sample_offsets,
+    split_into_work, Complex, Frame, RenderError, RenderParameters,
+};
+
+/// A point is considered to have converged to a root once it is this close to it.
+const CONVERGENCE_RADIUS: f64 = 1e-6;
+
+/// The highest degree of a polynomial that can be parsed.
+const MAX_DEGREE: usize = 64;
+
+/// The maximum number of rounds of the Durand-Kerner method that are used to find the roots.
+const MAX_ROOT_FINDING_ROUNDS: usize = 10_000;
+
+/// A polynomial with complex coefficients and a degree of at least one.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::Polynomial;
+/// let polynomial: Polynomial = "z^3 - 2z + 2".parse().unwrap();
+/// assert_eq!(polynomial.degree(), 3);
+/// assert_eq!(polynomial.roots().len(), 3);
+/// assert_eq!(polynomial.to_string(), "z^3 - 2z + 2");
+///
+/// // Constants have no roots to converge to.
+/// assert!("5".parse::<Polynomial>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polynomial {
+    /// The coefficients, starting with the constant term. The last one is not zero.
+    coefficients: Vec<Complex>,
+    /// The roots of the polynomial, sorted by their argument and then their magnitude.
+    roots: Vec<Complex>,
+}
+
+impl Polynomial {
+    /// Creates the polynomial with the given coefficients, starting with the constant term.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the polynomial is a constant, or if its degree is larger than 64.
+    pub fn try_new(mut coefficients: Vec<Complex>) -> Result<Self, ParsePolynomialError> {
+        while coefficients.last() == Some(&Complex::ZERO) {
+            coefficients.pop();
+        }
+        if coefficients.len() < 2 {
+            return Err(ParsePolynomialError::Constant);
+        }
+        if coefficients.len() > MAX_DEGREE + 1 {
+            return Err(ParsePolynomialError::DegreeTooHigh(coefficients.len() - 1));
+        }
+        let roots = find_roots(&coefficients);
+        Ok(Self {
+            coefficients,
+            roots,
+        })
+    }
+
+    /// The coefficients of the polynomial, starting with the constant term.
+    #[must_use]
+    pub fn coefficients(&self) -> &[Complex] {
+        &self.coefficients
+    }
+
+    #[must_use]
+    pub fn degree(&self) -> usize {
+        self.coefficients.len() - 1
+    }
+
+    /// The roots of the polynomial, repeated roots included,
+    /// sorted by their argument in (-pi, pi] and then by their magnitude.
+    #[must_use]
+    pub fn roots(&self) -> &[Complex] {
+        &self.roots
+    }
+
+    /// Returns the value of the polynomial and of its derivative at z.
+    fn evaluate(&self, z: Complex) -> (Complex, Complex) {
+        let mut value = Complex::ZERO;
+        let mut derivative = Complex::ZERO;
+        for &coefficient in self.coefficients.iter().rev() {
+            derivative = derivative * z + value;
+            value = value * z + coefficient;
+        }
+        (value, derivative)
+    }
+
+    /// Runs Newton's method from z = c until it gets close to one of the roots.
+    /// Returns the index of that root in [`roots`](Self::roots) and the smoothed number of iterations
+    /// it took, or `None` if it does not get there within `max_iterations` iterations.
+    ///
+    /// The smoothing assumes that the distance to the root is squared by every iteration,
+    /// which is the case close to roots that are not repeated.
+    #[must_use]
+    pub fn converge(&self, c: Complex, max_iterations: NonZeroU64) -> Option<(usize, f64)> {
+        let mut z = c;
+        let (root, mut previous_distance) = self.nearest_root(z);
+        if previous_distance < CONVERGENCE_RADIUS {
+            return Some((root, 0.0));
+        }
+        for iterations in 1..=max_iterations.get() {
+            let (value, derivative) = self.evaluate(z);
+            if derivative == Complex::ZERO {
+                return None;
+            }
+            z = z - value / derivative;
+            if !(z.re.is_finite() && z.im.is_finite()) {
+                return None;
+            }
+
+            let (root, distance) = self.nearest_root(z);
+            if distance < CONVERGENCE_RADIUS {
+                // How much of the last iteration was needed to get within the radius.
+                // It goes from 0 when the previous distance was right at the radius
+                // to 1 when it was the square root of the radius, which makes the count continuous.
+                let fraction = if previous_distance < 1.0 {
+                    (CONVERGENCE_RADIUS.ln() / previous_distance.ln())
+                        .log2()
+                        .clamp(0.0, 1.0)
+                } else {
+                    1.0
+                };
+                return Some((root, iterations_to_f64(iterations - 1) + fraction));
+            }
+            previous_distance = distance;
+        }
+        None
+    }
+
+    /// Returns the index of the root closest to z and the distance to it.
+    fn nearest_root(&self, z: Complex) -> (usize, f64) {
+        self.roots
+            .iter()
+            .map(|&root| (z - root).abs())
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .expect("a polynomial of degree one or more has roots")
+    }
+}
+
+/// Finds all the roots of the polynomial with the Durand-Kerner method.
+fn find_roots(coefficients: &[Complex]) -> Vec<Complex> {
+    let degree = coefficients.len() - 1;
+    let leading = coefficients[degree];
+    let monic: Vec<Complex> = coefficients.iter().map(|&c| c / leading).collect();
+    let evaluate = |z: Complex| {
+        monic
+            .iter()
+            .rev()
+            .fold(Complex::ZERO, |value, &coefficient| value * z + coefficient)
+    };
+
+    // The roots lie within this radius of the origin.
+    let bound = 1.0 + monic[..degree].iter().map(|c| c.abs()).fold(0.0, f64::max);
+    // Powers of a number that is neither real nor a root of unity are the customary starting guesses.
+    let seed = Complex::new(0.4, 0.9);
+    let mut roots: Vec<Complex> = (0..degree)
+        .scan(Complex::new(1.0, 0.0), |power, _| {
+            *power = *power * seed;
+            Some(*power * Complex::from(bound / 2.0))
+        })
+        .collect();
+
+    for _ in 0..MAX_ROOT_FINDING_ROUNDS {
+        let mut largest_step: f64 = 0.0;
+        for i in 0..degree {
+            let denominator = (0..degree)
+                .filter(|&j| j != i)
+                .fold(Complex::new(1.0, 0.0), |product, j| {
+                    product * (roots[i] - roots[j])
+                });
+            if denominator == Complex::ZERO {
+                // Two guesses coincide, so one of them is nudged apart.
+                roots[i] = roots[i] + Complex::new(f64::EPSILON * bound, 0.0);
+                largest_step = f64::INFINITY;
+                continue;
+            }
+            let step = evaluate(roots[i]) / denominator;
+            roots[i] = roots[i] - step;
+            largest_step = largest_step.max(step.abs());
+        }
+        if largest_step <= f64::EPSILON * bound {
+            break;
+        }
+    }
+
+    for root in &mut roots {
+        // Real roots of real polynomials are found with tiny imaginary parts,
+        // which would break the symmetry of their basins.
+        if root.im.abs() <= 1e-12 * root.re.abs().max(1.0) {
+            root.im = 0.0;
+        }
+    }
+    roots.sort_by(|a, b| {
+        a.arg()
+            .total_cmp(&b.arg())
+            .then(a.mag_sqr().total_cmp(&b.mag_sqr()))
+    });
+    roots
+}
+
+/// Displays the polynomial from its highest power down,
+/// in a form that can be parsed back into the same polynomial.
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for (power, &coefficient) in self.coefficients.iter().enumerate().rev() {
+            if coefficient == Complex::ZERO {
+                continue;
+            }
+            // Real and imaginary coefficients have a sign of their own,
+            // other complex numbers are written in parentheses after a plus.
+            let (negative, magnitude) = if coefficient.im == 0.0 {
+                (
+                    coefficient.re < 0.0,
+                    Complex::new(coefficient.re.abs(), 0.0),
+                )
+            } else if coefficient.re == 0.0 {
+                (
+                    coefficient.im < 0.0,
+                    Complex::new(0.0, coefficient.im.abs()),
+                )
+            } else {
+                (false, coefficient)
+            };
+            match (first, negative) {
+                (true, true) => write!(f, "-")?,
+                (true, false) => {}
+                (false, true) => write!(f, " - ")?,
+                (false, false) => write!(f, " + ")?,
+            }
+            first = false;
+
+            let is_one = magnitude == Complex::new(1.0, 0.0);
+            match (magnitude.re, magnitude.im) {
+                _ if is_one && power > 0 => {}
+                (re, 0.0) => write!(f, "{re}")?,
+                (0.0, 1.0) => write!(f, "i")?,
+                (0.0, im) => write!(f, "{im}i")?,
+                (re, im) if im < 0.0 => write!(f, "({re}-{}i)", -im)?,
+                (re, im) => write!(f, "({re}+{im}i)")?,
+            }
+            match power {
+                0 => {}
+                1 => write!(f, "z")?,
+                _ => write!(f, "z^{power}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses polynomials in z written as sums of terms such as "3z^2", "-z", "2iz" or "(1-2i)z^4",
+/// e.g. "z^3 - 2z + 2". Coefficients can be real or imaginary numbers,
+/// or complex numbers in parentheses, optionally followed by a "*".
+impl FromStr for Polynomial {
+    type Err = ParsePolynomialError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        Self::try_new(parse_terms(&s)?)
+    }
+}
+
+/// Parses a sum of terms into the coefficients of the polynomial they add up to.
+fn parse_terms(s: &str) -> Result<Vec<Complex>, ParsePolynomialError> {
+    let invalid = |term: &str| ParsePolynomialError::InvalidTerm(term.to_owned());
+
+    // Split the sum before every sign that is not inside parentheses or part of a number such as 1e-3.
+    let mut terms = Vec::new();
+    let mut depth = 0_usize;
+    let mut start = 0;
+    let mut previous = None;
+    for (index, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1).ok_or_else(|| invalid(s))?,
+            '+' | '-' if depth == 0 && index > 0 && !matches!(previous, Some('e' | 'E')) => {
+                terms.push(&s[start..index]);
+                start = index;
+            }
+            _ => {}
+        }
+        previous = Some(c);
+    }
+    if depth != 0 {
+        return Err(invalid(s));
+    }
+    terms.push(&s[start..]);
+
+    let mut coefficients = Vec::new();
+    for term in terms {
+        let (sign, body) = match term.as_bytes().first() {
+            Some(b'-') => (-1.0, &term[1..]),
+            Some(b'+') => (1.0, &term[1..]),
+            _ => (1.0, term),
+        };
+        if body.is_empty() {
+            return Err(invalid(term));
+        }
+        let (coefficient, power) = match body.find('z') {
+            Some(z) => {
+                let power = match &body[z + 1..] {
+                    "" => 1,
+                    exponent => exponent
+                        .strip_prefix('^')
+                        .and_then(|power| power.parse::<usize>().ok())
+                        .ok_or_else(|| invalid(term))?,
+                };
+                let coefficient = &body[..z];
+                let coefficient = coefficient.strip_suffix('*').unwrap_or(coefficient);
+                (coefficient, power)
+            }
+            None => (body, 0),
+        };
+        if power > MAX_DEGREE {
+            return Err(ParsePolynomialError::DegreeTooHigh(power));
+        }
+        let coefficient = parse_coefficient(coefficient).ok_or_else(|| invalid(term))?;
+
+        if coefficients.len() <= power {
+            coefficients.resize(power + 1, Complex::ZERO);
+        }
+        coefficients[power] = coefficients[power] + coefficient * Complex::from(sign);
+    }
+    Ok(coefficients)
+}
+
+/// Parses the coefficient in front of a power of z. An empty coefficient is one.
+fn parse_coefficient(coefficient: &str) -> Option<Complex> {
+    if coefficient.is_empty() {
+        return Some(Complex::new(1.0, 0.0));
+    }
+    if let Some(inner) = coefficient
+        .strip_prefix('(')
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        // A complex number in parentheses is a sum of terms without any z.
+        let terms = parse_terms(inner).ok()?;
+        return match terms.as_slice() {
+            [constant] => Some(*constant),
+            _ => None,
+        };
+    }
+    if let Some(imaginary) = coefficient.strip_suffix('i') {
+        let imaginary = imaginary.strip_suffix('*').unwrap_or(imaginary);
+        let factor = if imaginary.is_empty() {
+            1.0
+        } else {
+            imaginary.parse().ok()?
+        };
+        return Some(Complex::new(0.0, factor));
+    }
+    coefficient
+        .parse()
+        .ok()
+        .filter(|number: &f64| number.is_finite())
+        .map(Complex::from)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsePolynomialError {
+    /// The term could not be parsed.
+    InvalidTerm(String),
+    /// The polynomial is a constant, which has no roots to converge to.
+    Constant,
+    /// The polynomial has a higher degree than is supported.
+    DegreeTooHigh(usize),
+}
+
+impl fmt::Display for ParsePolynomialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTerm(term) => write!(
+                f,
+                "\"{term}\" is not a term of a polynomial in z, such as \"3z^2\", \"-iz\" or \"(1+2i)\""
+            ),
+            Self::Constant => write!(f, "the polynomial must contain z to have any roots"),
+            Self::DegreeTooHigh(degree) => write!(
+                f,
+                "the polynomial has a degree of {degree}, but at most {MAX_DEGREE} is supported"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParsePolynomialError {}
+
+/// The settings of a Newton fractal render.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Newton {
+    /// The polynomial whose roots Newton's method converges to.
+    pub polynomial: Polynomial,
+    /// How quickly the colors darken with the number of iterations it takes to converge.
+    /// A point that takes n iterations gets a brightness of e^(-shading * n).
+    pub shading: f64,
+}
+
+impl Newton {
+    /// The default [`shading`](Self::shading).
+    pub const DEFAULT_SHADING: f64 = 0.1;
+
+    #[must_use]
+    pub const fn new(polynomial: Polynomial) -> Self {
+        Self {
+            polynomial,
+            shading: Self::DEFAULT_SHADING,
+        }
+    }
+
+    /// Returns the color of the point c: the color of the root it converges to,
+    /// darkened by how many iterations it takes, or black if it does not converge.
+    fn color(
+        &self,
+        c: Complex,
+        render_parameters: &RenderParameters,
+        color_map: &dyn ColorMap,
+    ) -> LinearRGB {
+        match self
+            .polynomial
+            .converge(c, render_parameters.max_iterations)
+        {
+            Some((root, iterations)) => {
+                // Every root gets a color from an equally large share of the palette.
+                let position = (root as f64 + 0.5) / self.polynomial.roots.len() as f64;
+                color_map.color(render_parameters.escape_speed_range.normalize(position))
+                    * (-self.shading * iterations).exp()
+            }
+            None => LinearRGB::default(),
+        }
+    }
+}
+
+/// Renders the Newton fractal with the settings in `newton` in the given region.
+/// Every point is colored by the root of the polynomial that Newton's method converges to
+/// from it, with the roots spread evenly over the palette, and darkened the more iterations it takes.
+/// Points that do not converge within the maximum number of iterations are black.
+///
+/// Of the render parameters only the resolution, the maximum number of iterations, the supersampling,
+/// the palette and its range of escape speeds, the scheduling and the color type are used.
+/// Every pixel is supersampled, since the basins of the roots have sharp edges everywhere.
+///
+/// If `verbose` is true the function will use prints to `stderr` to display a progress bar.
+///
+/// # Panics
+/// Panics if the memory for the image can not be allocated.
+/// Use [`try_render_newton`] to get an error instead.
+#[must_use]
+pub fn render_newton(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    newton: &Newton,
+    verbose: bool,
+) -> DynamicImage {
+    newton_image(render_parameters, render_region, newton, verbose)
+        .unwrap_or_else(|error| panic!("{error}"))
+}
+
+/// Works like [`render_newton`], but checks that the frame can be rendered
+/// and returns an error instead of panicking if the image can not be allocated.
+///
+/// # Errors
+/// Returns an error if the center of the frame is not finite, if its distances
+/// are not finite and positive, or if the memory for the image can not be allocated.
+pub fn try_render_newton(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    newton: &Newton,
+    verbose: bool,
+) -> Result<DynamicImage, RenderError> {
+    check_frame(render_region)?;
+    newton_image(render_parameters, render_region, newton, verbose)
+}
+
+/// Renders the Newton fractal, see [`render_newton`].
+fn newton_image(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    newton: &Newton,
+    verbose: bool,
+) -> Result<DynamicImage, RenderError> {
+    let _render_span = tracing::info_span!(
+        "newton",
+        polynomial = %newton.polynomial,
+        shading = newton.shading,
+    )
+    .entered();
+
+    let color_type = render_parameters.color_type;
+    let mut image = allocate_rotated_image(
+        render_parameters.x_resolution,
+        render_parameters.y_resolution,
+        color_type,
+    )?;
+    let buffer: &mut [u8] = match &mut image {
+        DynamicImage::ImageLuma8(buffer) => buffer.as_mut(),
+        DynamicImage::ImageRgb8(buffer) => buffer.as_mut(),
+        DynamicImage::ImageRgba8(buffer) => buffer.as_mut(),
+        _ => unreachable!("we define the image so that it can only be one of the above"),
+    };
+
+    // Pixel (x, y), counted from the bottom left corner, samples the point at its center.
+    let real_delta = render_region.real_distance / f64::from(render_parameters.x_resolution);
+    let imag_delta = render_region.imag_distance / f64::from(render_parameters.y_resolution);
+    let start_real = render_region.center_real - (render_region.real_distance - real_delta) / 2.0;
+    let start_imag = render_region.center_imag - (render_region.imag_distance - imag_delta) / 2.0;
+
+    let bytes_per_pixel = usize::from(color_type.bytes_per_pixel());
+    let work = split_into_work(
+        buffer,
+        bytes_per_pixel * usize::from(render_parameters.y_resolution),
+        bytes_per_pixel,
+        render_parameters.scheduling,
+    );
+
+    let progress = verbose_progress(verbose);
+    progress.start(work.len() as u64);
+
+    let color_map = render_parameters.palette.color_map();
+    work.into_par_iter().for_each(|segments| {
+        render_parameters.cpu_limit.throttle(|| {
+            for (band_index, first_pixel, segment) in segments {
+                let c_re = start_real + real_delta * band_index as f64;
+                for (index, pixel) in segment.chunks_exact_mut(bytes_per_pixel).enumerate() {
+                    let c_im = start_imag + imag_delta * (first_pixel + index) as f64;
+
+                    let mut color = LinearRGB::default();
+                    let mut samples = 0_u16;
+                    for (real_offset, imag_offset) in sample_offsets(
+                        render_parameters.sqrt_samples_per_pixel.get(),
+                        render_parameters.sample_placement,
+                        render_parameters.seed,
+                        None,
+                        (band_index, first_pixel + index),
+                    ) {
+                        color += newton.color(
+                            Complex::new(
+                                c_re + real_offset * real_delta,
+                                c_im + imag_offset * imag_delta,
+                            ),
+                            render_parameters,
+                            color_map.as_ref(),
+                        );
+                        samples += 1;
+                    }
+                    color /= f64::from(samples);
+
+                    let color = match color_type {
+                        SupportedColorType::L8 => Pixel::Luma(color.into()),
+                        SupportedColorType::Rgb8 => Pixel::Rgb(color.into()),
+                        SupportedColorType::Rgba8 => Pixel::Rgba(color.into()),
+                    };
+                    pixel.copy_from_slice(color.as_raw());
+                }
+            }
+        });
+        progress.advance(1);
+    });
+    progress.finish();
+
+    // Undo the rotated state used during rendering.
+    Ok(parallel_rotate270(&image))
+}
+
+#[cfg(test)]
+mod test_newton {
+    use super::*;
+
+    const MAX_ITERATIONS: NonZeroU64 = NonZeroU64::new(100).unwrap();
+
+    fn assert_close(a: Complex, b: Complex) {
+        assert!((a - b).abs() < 1e-9, "{a:?} is not close to {b:?}");
+    }
+
+    #[test]
+    fn check_parsing() {
+        let polynomial: Polynomial = "z^3 - 1".parse().unwrap();
+        assert_eq!(
+            polynomial.coefficients(),
+            [-1.0, 0.0, 0.0, 1.0].map(Complex::from)
+        );
+        for (input, expected) in [
+            ("z^3-1", "z^3 - 1"),
+            ("-z^2 + 2*z", "-z^2 + 2z"),
+            ("2z^2 + (1-2i)z - i", "2z^2 + (1-2i)z - i"),
+            ("z^3 + z^3 + 0.5iz", "2z^3 + 0.5iz"),
+            ("1e-3 z^8 + 15z^4 - 16", "0.001z^8 + 15z^4 - 16"),
+            ("iz^2 + (2)", "iz^2 + 2"),
+        ] {
+            let polynomial: Polynomial = input.parse().unwrap();
+            assert_eq!(polynomial.to_string(), expected, "{input}");
+            assert_eq!(expected.parse(), Ok(polynomial), "{input}");
+        }
+
+        assert_eq!(
+            "z^2 - z^2 + 4".parse::<Polynomial>(),
+            Err(ParsePolynomialError::Constant)
+        );
+        assert_eq!(
+            "z^65".parse::<Polynomial>(),
+            Err(ParsePolynomialError::DegreeTooHigh(65))
+        );
+        for invalid in ["", "z^", "y^2", "(z-1)^2", "z^2 +", "z^-1", "(1+i", "2x"] {
+            assert!(
+                matches!(
+                    invalid.parse::<Polynomial>(),
+                    Err(ParsePolynomialError::InvalidTerm(_))
+                ),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn check_roots() {
+        // The cube roots of unity, sorted by their argument.
+        let half_sqrt_3 = 3.0_f64.sqrt() / 2.0;
+        let polynomial: Polynomial = "z^3 - 1".parse().unwrap();
+        for (root, expected) in polynomial.roots().iter().zip([
+            Complex::new(-0.5, -half_sqrt_3),
+            Complex::new(1.0, 0.0),
+            Complex::new(-0.5, half_sqrt_3),
+        ]) {
+            assert_close(*root, expected);
+        }
+        // Real roots are exactly real.
+        assert_eq!(polynomial.roots()[1].im, 0.0);
+
+        for polynomial in ["z^5 - 3z + 1", "(2+i)z^4 - iz + 3", "z^12 - 1"] {
+            let polynomial: Polynomial = polynomial.parse().unwrap();
+            assert_eq!(polynomial.roots().len(), polynomial.degree());
+            for &root in polynomial.roots() {
+                assert!(polynomial.evaluate(root).0.abs() < 1e-9, "{polynomial}");
+            }
+        }
+    }
+
+    #[test]
+    fn check_convergence() {
+        let polynomial: Polynomial = "z^3 - 1".parse().unwrap();
+        for (index, &root) in polynomial.roots().iter().enumerate() {
+            let nearby = root + Complex::new(0.01, 0.02);
+            let (converged, iterations) = polynomial.converge(nearby, MAX_ITERATIONS).unwrap();
+            assert_eq!(converged, index);
+            assert!(iterations < 5.0);
+        }
+
+        // The basins are symmetric under conjugation, since the coefficients are real.
+        let (upper, upper_iterations) = polynomial
+            .converge(Complex::new(-0.3, 0.4), MAX_ITERATIONS)
+            .unwrap();
+        let (lower, lower_iterations) = polynomial
+            .converge(Complex::new(-0.3, -0.4), MAX_ITERATIONS)
+            .unwrap();
+        assert_eq!(polynomial.roots()[upper], polynomial.roots()[lower].conj());
+        assert!((upper_iterations - lower_iterations).abs() < 1e-9);
+
+        // The smoothed iteration count changes continuously along the real axis,
+        // where every point converges to 1. Right next to the root it changes too quickly to check.
+        let mut previous = None;
+        for step in 0..3000 {
+            let c = Complex::new(0.25 + f64::from(step) * 0.00025, 0.0);
+            let (root, iterations) = polynomial.converge(c, MAX_ITERATIONS).unwrap();
+            assert_eq!(root, 1);
+            if let Some(previous) = previous {
+                let difference: f64 = iterations - previous;
+                assert!(difference.abs() < 0.2, "jump of {difference} at {c:?}");
+            }
+            previous = Some(iterations);
+        }
+
+        // z^2 + 1 has no real roots, so Newton's method hits the critical point at the origin.
+        let polynomial: Polynomial = "z^2 + 1".parse().unwrap();
+        assert_eq!(polynomial.converge(Complex::ZERO, MAX_ITERATIONS), None);
+    }
+
+    #[test]
+    fn check_render() {
+        let params = RenderParameters::try_new(
+            30.try_into().unwrap(),
+            20.try_into().unwrap(),
+            MAX_ITERATIONS,
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let newton = Newton::new("z^3 - 1".parse().unwrap());
+        let image =
+            render_newton(&params, Frame::new(0.0, 0.0, 3.0, 2.0), &newton, false).into_rgb8();
+        assert_eq!(image.dimensions(), (30, 20));
+
+        // Without supersampling every pixel has the color of the point at its center,
+        // with the top of the image at the largest imaginary part.
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let c = Complex::new(-1.45 + 0.1 * f64::from(x), 0.95 - 0.1 * f64::from(y));
+            let expected = newton
+                .color(c, &params, params.palette.color_map().as_ref())
+                .to_srgb();
+            for (a, b) in pixel.0.iter().zip(expected) {
+                assert!(a.abs_diff(b) <= 1, "({x}, {y})");
+            }
+        }
+
+        // The pixels around the root at 1 take the color of the middle of the palette
+        // and are darkened by a few iterations at most.
+        let full_color = params.palette.color(0.5);
+        let [bright, dark] = [full_color, full_color * (-0.5_f64).exp()].map(LinearRGB::to_srgb);
+        let at_root = image.get_pixel(25, 10).0;
+        for channel in 0..3 {
+            assert!(
+                (dark[channel]..=bright[channel]).contains(&at_root[channel]),
+                "{at_root:?}"
+            );
+        }
+
+        assert_eq!(
+            try_render_newton(&params, Frame::new(0.0, 0.0, 3.0, 2.0), &newton, false)
+                .map(DynamicImage::into_rgb8),
+            Ok(image)
+        );
+        let empty = Frame::new(0.0, 0.0, 0.0, 2.0);
+        assert_eq!(
+            try_render_newton(&params, empty, &newton, false),
+            Err(RenderError::InvalidFrame(empty))
+        );
+    }
+}
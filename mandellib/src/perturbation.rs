@@ -0,0 +1,427 @@
+//! Deep zooms using perturbation theory.
+//!
+//! The orbit of a single reference point C is computed with as many bits of precision as
+//! the zoom requires. Every other point c = C + δc is then iterated as a small deviation δz
+//! from the reference orbit Z, using δz -> 2Zδz + δz^2 + δc, which only needs `f64` precision
+//! as long as δc can be represented by an `f64`. This allows zooming to depths of around 2^1000
+//! instead of the around 2^45 that is possible when iterating c directly.
+//!
+//! When z comes much closer to zero than the reference orbit, δz has lost the precision
+//! that tells the point apart from its neighbours, and whole blobs of pixels get the same wrong
+//! value. Such glitches are detected with Pauldelbrot's criterion |Z + δz|^2 < ε|Z|^2,
+//! as is the point outliving an escaping reference orbit. Glitched points are iterated again
+//! with a secondary reference orbit computed at the center of a small cell of the image
+//! around them, which is shared by all glitched points in the cell.
+//! If the point glitches with that reference as well, it is iterated a final time
+//! with rebasing: whenever z is closer to zero than to the reference orbit, or the end of
+//! the reference orbit is reached, δz is replaced by the full value of z and the
+//! iteration continues from the start of the reference orbit.
+
+use core::fmt;
+use core::num::NonZeroU64;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use num_bigint::BigInt;
+
+use crate::precise_real::{f64_to_fixed_point, fixed_point_to_f64};
+use crate::{Complex, Fractal, PreciseReal};
+
+/// The number of bits of precision used beyond what is needed to resolve a single pixel.
+const GUARD_BITS: u32 = 64;
+
+/// The ε of Pauldelbrot's glitch criterion |Z + δz|^2 < ε|Z|^2.
+const GLITCH_TOLERANCE: f64 = 1e-6;
+
+/// The side of the square cells that share a secondary reference orbit, in pixels.
+const SECONDARY_REFERENCE_CELL_PIXELS: f64 = 64.0;
+
+/// The Mandelbrot set rendered with perturbation theory around a high precision reference point.
+///
+/// **Note:** the values of c given to [`iterate`](Fractal::iterate) are the offsets δc from the reference point,
+/// so the frame given to [`render`](crate::render) must be centered on the origin.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{Complex, Fractal, PerturbedMandelbrot, PreciseReal};
+/// # use core::num::NonZeroU64;
+/// const MAXITERS: NonZeroU64 = NonZeroU64::new(1000).unwrap();
+/// // i is on the boundary of the Mandelbrot set, so points arbitrarily close to it behave differently.
+/// let re: PreciseReal = "0".parse().unwrap();
+/// let im: PreciseReal = "1".parse().unwrap();
+/// let fractal = PerturbedMandelbrot::new(&re, &im, 1e-30, MAXITERS);
+///
+/// assert_ne!(
+///     fractal.iterate(Complex::new(1e-25, 0.0), MAXITERS),
+///     fractal.iterate(Complex::new(-1e-25, 1e-25), MAXITERS),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct PerturbedMandelbrot {
+    center_real: PreciseReal,
+    center_imag: PreciseReal,
+    fractional_bits: u32,
+    /// The reference orbit Z_0 = 0, Z_1 = C, Z_2 = C^2 + C, ... rounded to `f64`.
+    /// Ends when it escapes or has the maximum number of iterations.
+    reference_orbit: Vec<Complex>,
+    secondary_cell_size: f64,
+    /// The secondary reference orbits that have been computed so far, by the cell they are for.
+    /// Shared between clones, since they are all centered on the same point.
+    secondary_references: Arc<Mutex<SecondaryReferences>>,
+}
+
+/// Secondary reference orbits by the cell of the image that they are centered in.
+type SecondaryReferences = HashMap<(i64, i64), Arc<SecondaryReference>>;
+
+/// A reference orbit at an offset from the center of a [`PerturbedMandelbrot`].
+#[derive(Debug)]
+struct SecondaryReference {
+    offset: Complex,
+    orbit: Vec<Complex>,
+}
+
+/// The reason that a point could not be iterated accurately with a reference orbit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Glitch;
+
+impl PerturbedMandelbrot {
+    /// Computes the reference orbit of the given point with enough precision to resolve
+    /// features of size `pixel_size`, the distance between adjacent samples in the image.
+    #[must_use]
+    pub fn new(
+        center_real: &PreciseReal,
+        center_imag: &PreciseReal,
+        pixel_size: f64,
+        max_iterations: NonZeroU64,
+    ) -> Self {
+        let fractional_bits = precision_for(pixel_size);
+        Self {
+            reference_orbit: reference_orbit(
+                center_real.to_fixed_point(fractional_bits),
+                center_imag.to_fixed_point(fractional_bits),
+                fractional_bits,
+                max_iterations,
+            ),
+            center_real: center_real.clone(),
+            center_imag: center_imag.clone(),
+            fractional_bits,
+            secondary_cell_size: pixel_size.abs() * SECONDARY_REFERENCE_CELL_PIXELS,
+            secondary_references: Arc::default(),
+        }
+    }
+
+    /// The number of iterations that the reference orbit could be computed for.
+    /// If this is smaller than the maximum number of iterations the reference point escapes.
+    #[must_use]
+    pub fn reference_iterations(&self) -> usize {
+        self.reference_orbit.len() - 1
+    }
+
+    /// The number of secondary reference orbits that have been computed for glitched points.
+    #[must_use]
+    pub fn secondary_references(&self) -> usize {
+        self.secondary_references
+            .lock()
+            .map_or(0, |references| references.len())
+    }
+
+    /// Returns the secondary reference orbit of the cell that the given offset is in,
+    /// computing it if this is the first point in the cell that needs it.
+    fn secondary_reference(
+        &self,
+        delta_c: Complex,
+        max_iterations: NonZeroU64,
+    ) -> Arc<SecondaryReference> {
+        // The offsets are at most a few thousand cells from the center of the image.
+        #[allow(clippy::cast_possible_truncation)]
+        let cell = (
+            (delta_c.re / self.secondary_cell_size).floor() as i64,
+            (delta_c.im / self.secondary_cell_size).floor() as i64,
+        );
+        if let Some(reference) = self
+            .secondary_references
+            .lock()
+            .ok()
+            .and_then(|references| references.get(&cell).cloned())
+        {
+            return reference;
+        }
+
+        // The orbit is computed without holding the lock, so that other threads can use
+        // the existing references meanwhile. Any thread that computes the same orbit
+        // computes the same values, so it does not matter which one is kept.
+        #[allow(clippy::cast_precision_loss)]
+        let offset = Complex::new(
+            (cell.0 as f64 + 0.5) * self.secondary_cell_size,
+            (cell.1 as f64 + 0.5) * self.secondary_cell_size,
+        );
+        let reference = Arc::new(SecondaryReference {
+            offset,
+            orbit: reference_orbit(
+                self.center_real.to_fixed_point(self.fractional_bits)
+                    + f64_to_fixed_point(offset.re, self.fractional_bits),
+                self.center_imag.to_fixed_point(self.fractional_bits)
+                    + f64_to_fixed_point(offset.im, self.fractional_bits),
+                self.fractional_bits,
+                max_iterations,
+            ),
+        });
+        match self.secondary_references.lock() {
+            Ok(mut references) => Arc::clone(references.entry(cell).or_insert(reference)),
+            Err(_) => reference,
+        }
+    }
+}
+
+/// Returns the number of fractional bits needed to resolve details of the given size.
+fn precision_for(pixel_size: f64) -> u32 {
+    let needed = -pixel_size.abs().log2().ceil();
+    if needed.is_finite() && needed > 0.0 {
+        // The number of bits is at most around 1100 since the pixel size is an f64.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let needed = needed as u32;
+        needed + GUARD_BITS
+    } else {
+        GUARD_BITS
+    }
+}
+
+/// Iterates z -> z^2 + c for the given c, a fixed point number with the given number of
+/// fractional bits, and returns the orbit rounded to `f64`.
+fn reference_orbit(
+    c_re: BigInt,
+    c_im: BigInt,
+    fractional_bits: u32,
+    max_iterations: NonZeroU64,
+) -> Vec<Complex> {
+    let max_iterations = usize::try_from(max_iterations.get()).unwrap_or(usize::MAX);
+
+    let mut z_re = c_re.clone();
+    let mut z_im = c_im.clone();
+
+    let mut orbit = vec![
+        Complex::ZERO,
+        Complex::new(
+            fixed_point_to_f64(&z_re, fractional_bits),
+            fixed_point_to_f64(&z_im, fractional_bits),
+        ),
+    ];
+
+    while orbit.len() <= max_iterations && orbit[orbit.len() - 1].mag_sqr() <= 36.0 {
+        let z_re_sqr = (&z_re * &z_re) >> fractional_bits;
+        let z_im_sqr = (&z_im * &z_im) >> fractional_bits;
+        // 2 * z_re * z_im
+        z_im = ((&z_re * &z_im) >> (fractional_bits - 1)) + &c_im;
+        z_re = z_re_sqr - z_im_sqr + &c_re;
+        orbit.push(Complex::new(
+            fixed_point_to_f64(&z_re, fractional_bits),
+            fixed_point_to_f64(&z_im, fractional_bits),
+        ));
+    }
+
+    orbit
+}
+
+/// Iterates the point at the given offset from the start of the reference orbit
+/// and returns the number of iterations and the final value of z,
+/// or a [`Glitch`] if the reference orbit is not a good enough approximation of its orbit.
+fn perturb(
+    orbit: &[Complex],
+    delta_c: Complex,
+    max_iterations: u64,
+) -> Result<(u64, Complex), Glitch> {
+    let mut delta_z = Complex::ZERO;
+    let mut z = Complex::ZERO;
+    let mut mag_sqr = 0.0;
+    let mut iterations = 0;
+
+    while iterations < max_iterations && mag_sqr <= 36.0 {
+        let reference_index = usize::try_from(iterations).unwrap_or(usize::MAX);
+        // A point that outlives the reference orbit can not be iterated further with it.
+        let Some(&next_reference) = orbit.get(reference_index + 1) else {
+            return Err(Glitch);
+        };
+        let reference = orbit[reference_index];
+        delta_z = (reference + reference + delta_z) * delta_z + delta_c;
+
+        z = next_reference + delta_z;
+        mag_sqr = z.mag_sqr();
+        iterations += 1;
+
+        if mag_sqr < GLITCH_TOLERANCE * next_reference.mag_sqr() {
+            return Err(Glitch);
+        }
+    }
+
+    Ok((iterations, z))
+}
+
+/// Iterates the point at the given offset from the start of the reference orbit,
+/// rebasing to the start of the orbit whenever it is no longer a good approximation.
+/// Never glitches, but needs more iterations of the reference orbit.
+fn perturb_with_rebasing(
+    orbit: &[Complex],
+    delta_c: Complex,
+    max_iterations: u64,
+) -> (u64, Complex) {
+    let mut delta_z = Complex::ZERO;
+    let mut reference_index = 0;
+    let mut z = Complex::ZERO;
+    let mut mag_sqr = 0.0;
+    let mut iterations = 0;
+
+    while iterations < max_iterations && mag_sqr <= 36.0 {
+        let reference = orbit[reference_index];
+        delta_z = (reference + reference + delta_z) * delta_z + delta_c;
+        reference_index += 1;
+
+        z = orbit[reference_index] + delta_z;
+        mag_sqr = z.mag_sqr();
+        iterations += 1;
+
+        // Rebase when the reference orbit has run out,
+        // or when z is closer to zero than to the reference orbit.
+        if reference_index == orbit.len() - 1 || mag_sqr < delta_z.mag_sqr() {
+            delta_z = z;
+            reference_index = 0;
+        }
+    }
+
+    (iterations, z)
+}
+
+impl Fractal for PerturbedMandelbrot {
+    /// Iterates the full value of z, which is only accurate at shallow zooms.
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        z * z + self.reference_orbit[1] + c
+    }
+
+    // The image is centered on the reference point, not the real axis,
+    // so the default of no symmetry is correct.
+
+    fn iterate(&self, delta_c: Complex, max_iterations: NonZeroU64) -> (u64, Complex) {
+        perturb(&self.reference_orbit, delta_c, max_iterations.get())
+            .or_else(|Glitch| {
+                let secondary = self.secondary_reference(delta_c, max_iterations);
+                perturb(
+                    &secondary.orbit,
+                    delta_c - secondary.offset,
+                    max_iterations.get(),
+                )
+            })
+            .unwrap_or_else(|Glitch| {
+                perturb_with_rebasing(&self.reference_orbit, delta_c, max_iterations.get())
+            })
+    }
+}
+
+impl fmt::Display for PerturbedMandelbrot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "mandelbrot (perturbed around {} + {}i)",
+            self.center_real, self.center_imag
+        )
+    }
+}
+
+#[cfg(test)]
+mod test_perturbation {
+    use super::*;
+    use crate::{iterate, Exponent, Frame, RenderParameters};
+    use image::GenericImageView;
+    use std::sync::Arc;
+
+    #[test]
+    fn check_against_direct_iteration() {
+        // At shallow zooms the perturbed iteration must agree with iterating c directly.
+        let max_iterations = NonZeroU64::new(500).unwrap();
+        let re: PreciseReal = "-0.75".parse().unwrap();
+        let im: PreciseReal = "0.1".parse().unwrap();
+        let fractal = PerturbedMandelbrot::new(&re, &im, 1e-3, max_iterations);
+        for (d_re, d_im) in [
+            (0.0, 0.0),
+            (0.01, 0.02),
+            (-0.3, -0.05),
+            (0.5, 0.5),
+            (-1.0, 0.2),
+        ] {
+            assert_eq!(
+                fractal.iterate(Complex::new(d_re, d_im), max_iterations).0,
+                iterate(-0.75 + d_re, 0.1 + d_im, Exponent::TWO, max_iterations).0
+            );
+        }
+    }
+
+    #[test]
+    fn check_glitch_correction() {
+        let max_iterations = NonZeroU64::new(500).unwrap();
+        let re: PreciseReal = "-1.75".parse().unwrap();
+        let im: PreciseReal = "0".parse().unwrap();
+        let fractal = PerturbedMandelbrot::new(&re, &im, 1e-3, max_iterations);
+        // Points that follow the reference orbit closely do not glitch.
+        let delta_c = Complex::new(1e-4, 1e-4);
+        assert!(perturb(&fractal.reference_orbit, delta_c, max_iterations.get()).is_ok());
+        assert_eq!(fractal.secondary_references(), 0);
+
+        // The center of the period 3 component comes much closer to zero than the reference.
+        let center_of_period_3 = -1.754_877_666_246_693;
+        let delta_c = Complex::new(center_of_period_3 + 1.75, 0.0);
+        assert_eq!(
+            perturb(&fractal.reference_orbit, delta_c, max_iterations.get()),
+            Err(Glitch)
+        );
+        for delta_c in [delta_c, Complex::new(-0.05, 0.01)] {
+            assert_eq!(
+                fractal.iterate(delta_c, max_iterations).0,
+                iterate(
+                    -1.75 + delta_c.re,
+                    delta_c.im,
+                    Exponent::TWO,
+                    max_iterations
+                )
+                .0
+            );
+        }
+        assert_eq!(fractal.secondary_references(), 1);
+
+        // 0 does not escape, unlike the reference point 0.3.
+        let re: PreciseReal = "0.3".parse().unwrap();
+        let fractal = PerturbedMandelbrot::new(&re, &im, 1e-3, max_iterations);
+        let delta_c = Complex::new(-0.3, 0.0);
+        assert_eq!(
+            perturb(&fractal.reference_orbit, delta_c, max_iterations.get()),
+            Err(Glitch)
+        );
+        assert_eq!(
+            fractal.iterate(delta_c, max_iterations).0,
+            max_iterations.get()
+        );
+    }
+
+    #[test]
+    fn check_deep_render() {
+        // Beyond the precision of f64 a direct render would be a single flat color.
+        let resolution: u32 = 16;
+        let max_iterations = NonZeroU64::new(1000).unwrap();
+        let distance = 1e-25;
+        let mut params = RenderParameters::try_new(
+            resolution.try_into().unwrap(),
+            resolution.try_into().unwrap(),
+            max_iterations,
+            1.try_into().unwrap(),
+            color_space::SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        params.formula = crate::Formula::Custom(Arc::new(PerturbedMandelbrot::new(
+            &"0".parse().unwrap(),
+            &"1".parse().unwrap(),
+            distance / f64::from(resolution),
+            max_iterations,
+        )));
+        let image = crate::render(&params, Frame::new(0.0, 0.0, distance, distance), false);
+        let first = image.get_pixel(0, 0);
+        assert!(image.pixels().any(|(_, _, pixel)| pixel != first));
+    }
+}
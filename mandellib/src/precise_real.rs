@@ -0,0 +1,201 @@
+use core::fmt;
+use core::str::FromStr;
+
+use num_bigint::BigInt;
+use num_traits::{Float, ToPrimitive};
+
+/// A real number given in decimal notation that keeps all of its digits,
+/// so that it can be used as a coordinate beyond the precision of an `f64`.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::PreciseReal;
+/// let x: PreciseReal = "-0.7436438870371587047521".parse().unwrap();
+/// // Converting to an f64 rounds away most of the digits.
+/// assert_eq!(x.to_f64(), -0.7436438870371587);
+/// // but they are still shown when the number is displayed.
+/// assert_eq!(x.to_string(), "-0.7436438870371587047521");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreciseReal {
+    source: String,
+    /// The value of the number is `mantissa * 10^exponent`.
+    mantissa: BigInt,
+    exponent: i32,
+}
+
+/// Decimal exponents smaller than this are rejected, since converting
+/// such a number to fixed point would be very slow and the digits are useless anyway.
+const MIN_EXPONENT: i32 = -100_000;
+
+/// The largest absolute value that is accepted.
+const MAX_MAGNITUDE: f64 = 1e6;
+
+impl PreciseReal {
+    /// Returns the closest `f64` to the number.
+    #[must_use]
+    pub fn to_f64(&self) -> f64 {
+        // The standard library parser rounds correctly, and the source is known to be valid.
+        self.source.parse().unwrap_or(f64::NAN)
+    }
+
+    /// Returns the number as a fixed point number with the given number of fractional bits,
+    /// truncated towards zero.
+    pub(crate) fn to_fixed_point(&self, fractional_bits: u32) -> BigInt {
+        let ten = BigInt::from(10);
+        if self.exponent >= 0 {
+            (&self.mantissa * ten.pow(self.exponent.unsigned_abs())) << fractional_bits
+        } else {
+            (&self.mantissa << fractional_bits) / ten.pow(self.exponent.unsigned_abs())
+        }
+    }
+}
+
+/// Converts a fixed point number with the given number of fractional bits to the closest `f64`.
+pub(crate) fn fixed_point_to_f64(value: &BigInt, fractional_bits: u32) -> f64 {
+    // Only convert the most significant bits so that the conversion can not overflow.
+    let excess = value.bits().saturating_sub(64);
+    let leading = (value >> excess).to_f64().unwrap_or(0.0);
+    let scale = i32::try_from(excess).unwrap_or(i32::MAX)
+        - i32::try_from(fractional_bits).unwrap_or(i32::MAX);
+    leading * 2.0_f64.powi(scale)
+}
+
+/// Converts an `f64` to a fixed point number with the given number of fractional bits,
+/// truncated towards zero. Values that are not finite are converted to zero.
+pub(crate) fn f64_to_fixed_point(value: f64, fractional_bits: u32) -> BigInt {
+    if !value.is_finite() {
+        return BigInt::ZERO;
+    }
+    // The value is exactly mantissa * 2^exponent.
+    let (mantissa, exponent, sign) = value.integer_decode();
+    let shift = i64::from(exponent) + i64::from(fractional_bits);
+    let magnitude = BigInt::from(mantissa);
+    let magnitude = if shift >= 0 {
+        magnitude << shift.unsigned_abs()
+    } else {
+        magnitude >> shift.unsigned_abs()
+    };
+    if sign < 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+impl fmt::Display for PreciseReal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl FromStr for PreciseReal {
+    type Err = ParsePreciseRealError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParsePreciseRealError::InvalidFormat(s.to_owned());
+        let source = s.trim();
+
+        let (negative, unsigned) = match source.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, source.strip_prefix('+').unwrap_or(source)),
+        };
+
+        let (decimal, exponent) = match unsigned.split_once(['e', 'E']) {
+            Some((decimal, exponent)) => (decimal, exponent.parse::<i32>().map_err(|_| invalid())?),
+            None => (unsigned, 0),
+        };
+
+        let (integer_part, fractional_part) = decimal.split_once('.').unwrap_or((decimal, ""));
+        if integer_part.is_empty() && fractional_part.is_empty()
+            || !integer_part
+                .chars()
+                .chain(fractional_part.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let digits = format!("{integer_part}{fractional_part}");
+        let mut mantissa: BigInt = digits.parse().map_err(|_| invalid())?;
+        if negative {
+            mantissa = -mantissa;
+        }
+        let exponent = i32::try_from(fractional_part.len())
+            .ok()
+            .and_then(|fraction_length| exponent.checked_sub(fraction_length))
+            .filter(|&exponent| exponent >= MIN_EXPONENT)
+            .ok_or_else(|| ParsePreciseRealError::OutOfRange(s.to_owned()))?;
+
+        let result = Self {
+            source: source.to_owned(),
+            mantissa,
+            exponent,
+        };
+
+        if result.to_f64().abs() > MAX_MAGNITUDE {
+            Err(ParsePreciseRealError::OutOfRange(s.to_owned()))
+        } else {
+            Ok(result)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsePreciseRealError {
+    InvalidFormat(String),
+    OutOfRange(String),
+}
+
+impl fmt::Display for ParsePreciseRealError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFormat(s) => write!(f, "\"{s}\" is not a decimal number"),
+            Self::OutOfRange(s) => write!(
+                f,
+                "\"{s}\" is out of range, the magnitude must be at most {MAX_MAGNITUDE} and have at most {} decimals",
+                -MIN_EXPONENT
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParsePreciseRealError {}
+
+#[cfg(test)]
+mod test_precise_real {
+    use super::*;
+
+    #[test]
+    fn check_fixed_point_conversion() {
+        for (source, value) in [
+            ("1", 1.0),
+            ("-0.75", -0.75),
+            ("+2.5e-3", 0.0025),
+            (".5", 0.5),
+            ("3.", 3.0),
+            ("-1.25E2", -125.0),
+        ] {
+            let x: PreciseReal = source.parse().unwrap();
+            assert_eq!(x.to_f64(), value);
+            assert_eq!(fixed_point_to_f64(&x.to_fixed_point(100), 100), value);
+            assert_eq!(
+                fixed_point_to_f64(&f64_to_fixed_point(value, 100), 100),
+                value
+            );
+        }
+        assert_eq!(f64_to_fixed_point(-2.75, 1), BigInt::from(-5));
+        assert_eq!(f64_to_fixed_point(1e-300, 2000).bits(), 1004);
+
+        for invalid in ["", "-", ".", "1.2.3", "e5", "0x10", "1e", "nan", "inf"] {
+            assert!(matches!(
+                invalid.parse::<PreciseReal>(),
+                Err(ParsePreciseRealError::InvalidFormat(_))
+            ));
+        }
+        assert!(matches!(
+            "1e7".parse::<PreciseReal>(),
+            Err(ParsePreciseRealError::OutOfRange(_))
+        ));
+    }
+}
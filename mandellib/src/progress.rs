@@ -0,0 +1,269 @@
+//! Reporting how far along a render is, so that programs can show it in whichever way suits them
+//! without the renderer writing anything to the terminal itself, and cancelling renders that are no longer needed.
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use indicatif::ProgressBar;
+
+/// Receives the progress of a render as the units of work that the image is split into are completed.
+///
+/// The units are the bands of the image, or tiles of them, depending on the [`Scheduling`](crate::Scheduling).
+/// Renders that are not split into units report a single unit.
+///
+/// Closures that take the number of completed units implement the trait, as does an [`indicatif::ProgressBar`],
+/// which is what the `verbose` argument of the rendering functions uses.
+pub trait Progress: Sync {
+    /// Called once before any work is done, with the total number of units of work.
+    fn start(&self, _total: u64) {}
+
+    /// Called from the render threads every time units of work are completed,
+    /// with the number of units that were completed since the last call.
+    fn advance(&self, completed: u64);
+
+    /// Called once after all units of work have been completed.
+    fn finish(&self) {}
+
+    /// Polled by the render threads before every unit of work. Once it returns true the remaining units
+    /// are skipped, and the render returns [`RenderError::Cancelled`](crate::RenderError::Cancelled).
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// Ignores the progress of the render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn advance(&self, _completed: u64) {}
+}
+
+impl<F: Fn(u64) + Sync> Progress for F {
+    fn advance(&self, completed: u64) {
+        self(completed);
+    }
+}
+
+impl Progress for ProgressBar {
+    fn start(&self, total: u64) {
+        self.set_length(total);
+    }
+
+    fn advance(&self, completed: u64) {
+        self.inc(completed);
+    }
+
+    fn finish(&self) {
+        Self::finish(self);
+    }
+}
+
+/// Counts the completed units of work of a render, so that another thread can poll how far along it is
+/// and cancel it.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{try_render_with_progress, Frame, ProgressCounter, RenderParameters};
+/// # use color_space::SupportedColorType;
+/// let params = RenderParameters::try_new(
+///     30.try_into().unwrap(),
+///     20.try_into().unwrap(),
+///     100.try_into().unwrap(),
+///     1.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// let counter = ProgressCounter::new();
+/// try_render_with_progress(&params, Frame::new(-0.75, 0.0, 3.0, 2.0), &counter).unwrap();
+/// assert_eq!(counter.fraction(), 1.0);
+/// ```
+#[derive(Debug, Default)]
+pub struct ProgressCounter {
+    completed: AtomicU64,
+    total: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+impl ProgressCounter {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            completed: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    /// Makes the render that reports to the counter stop as soon as the units of work in progress are done.
+    /// A render that is started with the counter after it has been cancelled does no work at all.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns the number of units of work that have been completed.
+    #[must_use]
+    pub fn completed(&self) -> u64 {
+        self.completed.load(Ordering::Relaxed)
+    }
+
+    /// Returns the total number of units of work, or zero if the render has not started.
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Returns the fraction of the units of work that have been completed, from 0 to 1.
+    /// Before the render has started it is 0.
+    #[must_use]
+    pub fn fraction(&self) -> f64 {
+        let total = self.total();
+        if total == 0 {
+            0.0
+        } else {
+            (self.completed() as f64 / total as f64).min(1.0)
+        }
+    }
+}
+
+impl Progress for ProgressCounter {
+    fn start(&self, total: u64) {
+        self.completed.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    fn advance(&self, completed: u64) {
+        self.completed.fetch_add(completed, Ordering::Relaxed);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Returns the progress that the rendering functions report to when given the `verbose` argument:
+/// a progress bar on `stderr` if it is true, and nothing otherwise.
+pub(crate) fn verbose_progress(verbose: bool) -> Box<dyn Progress> {
+    if verbose {
+        Box::new(ProgressBar::no_length())
+    } else {
+        Box::new(NoProgress)
+    }
+}
+
+#[cfg(test)]
+mod test_progress {
+    use super::*;
+    use crate::{
+        compute_with_progress, Frame, RenderError, RenderParameters, RenderQuality, Scheduling,
+    };
+    use color_space::SupportedColorType;
+    use core::time::Duration;
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+
+    #[test]
+    fn check_reported_units() {
+        let mut params = RenderParameters::try_new(
+            40.try_into().unwrap(),
+            30.try_into().unwrap(),
+            100.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        params.scheduling = Scheduling::Bands;
+        let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+
+        let completed = AtomicU64::new(0);
+        crate::try_render_with_progress(&params, frame, &|units| {
+            completed.fetch_add(units, Ordering::Relaxed);
+        })
+        .unwrap();
+        // Every band is a unit of work.
+        assert_eq!(completed.into_inner(), 40);
+
+        // The counter is reset when a new render starts.
+        let counter = ProgressCounter::new();
+        _ = compute_with_progress(&params, frame, &counter);
+        _ = compute_with_progress(&params, frame, &counter);
+        assert_eq!((counter.completed(), counter.total()), (40, 40));
+    }
+
+    #[test]
+    fn check_cancel() {
+        let params = RenderParameters::try_new(
+            40.try_into().unwrap(),
+            30.try_into().unwrap(),
+            100.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+
+        let counter = ProgressCounter::new();
+        counter.cancel();
+        assert_eq!(
+            crate::try_render_with_progress(&params, frame, &counter),
+            Err(crate::RenderError::Cancelled)
+        );
+        assert_eq!(
+            crate::try_compute_with_progress(&params, frame, &counter).err(),
+            Some(crate::RenderError::Cancelled)
+        );
+        // Every skipped unit still counts as done, so that the progress ends at the total.
+        assert_eq!(counter.fraction(), 1.0);
+    }
+
+    #[test]
+    fn check_cancel_from_another_thread() {
+        let mut params = RenderParameters::try_new(
+            40.try_into().unwrap(),
+            30.try_into().unwrap(),
+            100.try_into().unwrap(),
+            2.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let frame = Frame::new(-0.75, 0.1, 3.0, 2.0);
+
+        for (scheduling, quality) in [
+            (Scheduling::Bands, RenderQuality::Full),
+            (Scheduling::Tiles, RenderQuality::Full),
+            (Scheduling::Interlaced, RenderQuality::Full),
+            (Scheduling::Automatic, RenderQuality::Draft),
+            (Scheduling::Automatic, RenderQuality::QuadTree),
+        ] {
+            params.scheduling = scheduling;
+            params.quality = quality;
+            let complete = crate::render(&params, frame, false);
+            // Cancel the renders at different points, from before they start to after they are done.
+            for delay in 0..40 {
+                let counter = Arc::new(ProgressCounter::new());
+                let (sender, receiver) = mpsc::channel();
+                {
+                    let (params, counter) = (params.clone(), Arc::clone(&counter));
+                    thread::spawn(move || {
+                        _ = sender.send(crate::try_render_with_progress(
+                            &params,
+                            frame,
+                            counter.as_ref(),
+                        ));
+                    });
+                }
+                thread::sleep(Duration::from_micros(50 * delay));
+                counter.cancel();
+                let case = format!("{scheduling:?}, {quality:?}, cancelled after {delay}");
+                // A cancelled render either stops or finishes the whole image, and never hangs.
+                match receiver
+                    .recv_timeout(Duration::from_secs(10))
+                    .unwrap_or_else(|_| panic!("the render did not return: {case}"))
+                {
+                    Ok(image) => assert_eq!(image, complete, "{case}"),
+                    Err(error) => assert_eq!(error, RenderError::Cancelled, "{case}"),
+                }
+            }
+        }
+    }
+}
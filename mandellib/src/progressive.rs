@@ -0,0 +1,141 @@
+//! Rendering an image coarse to fine, so that a rough version of it can be shown
+//! long before the full resolution render is done.
+
+use core::num::{NonZeroU32, NonZeroU8};
+
+use image::DynamicImage;
+
+use crate::{try_render, Frame, RenderError, RenderParameters, U32AndUsize};
+
+/// The factors that the resolution of the image is divided by in the refinements
+/// of a progressive render, in the order that they are rendered.
+pub const REFINEMENT_DIVISORS: [NonZeroU32; 3] = [
+    NonZeroU32::new(8).unwrap(),
+    NonZeroU32::new(4).unwrap(),
+    NonZeroU32::new(2).unwrap(),
+];
+
+/// Returns the parameters of a refinement of a progressive render:
+/// the resolution is divided by `divisor`, but is at least one pixel,
+/// and every pixel is sampled once since the refinement is soon replaced anyway.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{refinement_parameters, RenderParameters};
+/// # use color_space::SupportedColorType;
+/// let params = RenderParameters::try_new(
+///     1920.try_into().unwrap(),
+///     1080.try_into().unwrap(),
+///     255.try_into().unwrap(),
+///     3.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// let coarse = refinement_parameters(&params, 8.try_into().unwrap());
+/// assert_eq!(u32::from(coarse.x_resolution), 240);
+/// assert_eq!(u32::from(coarse.y_resolution), 135);
+/// assert_eq!(coarse.sqrt_samples_per_pixel.get(), 1);
+/// ```
+#[must_use]
+pub fn refinement_parameters(
+    render_parameters: &RenderParameters,
+    divisor: NonZeroU32,
+) -> RenderParameters {
+    let divide = |resolution: U32AndUsize| {
+        (u32::from(resolution) / divisor)
+            .max(1)
+            .try_into()
+            .expect("smaller than a valid resolution")
+    };
+    let mut coarse = render_parameters.clone();
+    coarse.x_resolution = divide(render_parameters.x_resolution);
+    coarse.y_resolution = divide(render_parameters.y_resolution);
+    coarse.sqrt_samples_per_pixel = NonZeroU8::MIN;
+    coarse
+}
+
+/// Renders the image at the resolutions given by [`REFINEMENT_DIVISORS`] before rendering it at full resolution,
+/// and calls `on_refinement` with each of those refinements as soon as it is done.
+/// Refinements that would be the same size as the one before them are skipped.
+///
+/// The refinements take about a third of the time of the full image if it is not supersampled,
+/// and less the more it is supersampled.
+///
+/// # Errors
+/// Returns an error if the center of the frame is not finite, if its distances
+/// are not finite and positive, or if the memory for an image can not be allocated.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{render, render_progressive, Frame, RenderParameters};
+/// # use color_space::SupportedColorType;
+/// let params = RenderParameters::try_new(
+///     64.try_into().unwrap(),
+///     40.try_into().unwrap(),
+///     100.try_into().unwrap(),
+///     2.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// let frame = Frame::new(-0.75, 0.0, 3.2, 2.0);
+///
+/// let mut heights = Vec::new();
+/// let image = render_progressive(&params, frame, |refinement| heights.push(refinement.height())).unwrap();
+/// assert_eq!(heights, [5, 10, 20]);
+/// assert_eq!(image, render(&params, frame, false));
+/// ```
+pub fn render_progressive(
+    render_parameters: &RenderParameters,
+    frame: Frame,
+    mut on_refinement: impl FnMut(&DynamicImage),
+) -> Result<DynamicImage, RenderError> {
+    let mut last_resolution = None;
+    for divisor in REFINEMENT_DIVISORS {
+        let coarse = refinement_parameters(render_parameters, divisor);
+        let resolution = Some((
+            u32::from(coarse.x_resolution),
+            u32::from(coarse.y_resolution),
+        ));
+        if resolution == last_resolution {
+            continue;
+        }
+        last_resolution = resolution;
+        on_refinement(&try_render(&coarse, frame, false)?);
+    }
+    try_render(render_parameters, frame, false)
+}
+
+#[cfg(test)]
+mod test_progressive {
+    use super::*;
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_small_images_skip_refinements() {
+        let params = RenderParameters::try_new(
+            6.try_into().unwrap(),
+            3.try_into().unwrap(),
+            50.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::L8,
+        )
+        .unwrap();
+        let mut sizes = Vec::new();
+        let image = render_progressive(&params, Frame::new(-0.75, 0.0, 3.0, 1.5), |refinement| {
+            sizes.push((refinement.width(), refinement.height()));
+        })
+        .unwrap();
+        // Dividing by 8 and by 4 both give a single pixel.
+        assert_eq!(sizes, [(1, 1), (3, 1)]);
+        assert_eq!((image.width(), image.height()), (6, 3));
+
+        assert!(matches!(
+            render_progressive(&params, Frame::new(f64::NAN, 0.0, 3.0, 1.5), |_| {
+                panic!("no refinement of an invalid frame can be rendered")
+            }),
+            Err(RenderError::InvalidFrame(_))
+        ));
+    }
+}
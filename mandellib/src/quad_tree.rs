@@ -0,0 +1,436 @@
+//! An experimental renderer that adapts its resolution to the detail of the image.
+//! The image is split into square cells whose corners are computed, and a cell is recursively split into four
+//! whenever the escape speeds at its corners differ. Cells whose corners agree are filled in by interpolating
+//! their escape speeds, so smooth parts of the image are only computed at the corners of large cells.
+//!
+//! Compared to [`RenderQuality::Draft`](crate::RenderQuality::Draft), which copies the color of agreeing
+//! neighbours, the interpolation also skips the smooth gradients outside the set, not just areas of a single color.
+//! The price is that the interpolated pixels are not supersampled, and that details which fit between the corners
+//! of a cell, like thin filaments or small minibrots, are missing if the corners happen to agree.
+//! The escape speeds are interpolated before they are colored, so palettes that cycle quickly can show
+//! the straight edges of the cells where the interpolation bends the bands of color.
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::renderer::pixel_color_and_first_sample;
+use crate::{Colorizer, EscapeSample, PixelGrid, Progress, RenderParameters};
+
+/// The size in pixels of the cells that the image is first split into. Must be a power of two.
+const CELL_SIZE: usize = 16;
+
+/// The largest difference between the escape speeds at the corners of a cell for it to be interpolated.
+const ESCAPE_SPEED_TOLERANCE: f64 = 2e-3;
+
+/// A pixel of a cell that has been computed, along with the sample closest to its center,
+/// which is what decides whether the cells that it is a corner of are split.
+#[derive(Debug, Clone, Copy)]
+struct Computed {
+    color: [u8; 4],
+    sample: EscapeSample,
+}
+
+/// A pixel of a cell that has been colored, with its band and index in the band.
+#[derive(Debug, Clone, Copy)]
+struct ColoredPixel {
+    band: usize,
+    pixel: usize,
+    color: [u8; 4],
+    /// Whether the pixel was computed rather than interpolated.
+    computed: bool,
+}
+
+/// The bands and pixel indices within the bands of the first and last pixels of a cell.
+/// Neighbouring cells share the pixels along their common edge.
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    bands: (usize, usize),
+    pixels: (usize, usize),
+}
+
+/// Fills the rotated `image` by subdividing it into cells where its escape speeds vary,
+/// where each band is `band_length` pixels long. Every cell of the first level is a unit of work.
+/// The pixels sample the same points as they would in a full render,
+/// but the image is never mirrored since most of the pixels that would be mirrored are interpolated anyway.
+pub(crate) fn color_by_subdivision(
+    render_parameters: &RenderParameters,
+    grid: &PixelGrid,
+    colorizer: &Colorizer,
+    image: &mut [u8],
+    band_length: usize,
+    progress: &dyn Progress,
+) {
+    let bytes_per_pixel = usize::from(render_parameters.color_type.bytes_per_pixel());
+    if band_length == 0 {
+        return;
+    }
+    let bands = image.len() / (band_length * bytes_per_pixel);
+
+    // The cells of the first level, which end at the last pixel of the image
+    // even when its size is not a multiple of the cell size.
+    let edges = |length: usize| {
+        (0..length.saturating_sub(1))
+            .step_by(CELL_SIZE)
+            .map(move |start| (start, (start + CELL_SIZE).min(length - 1)))
+            .collect::<Vec<_>>()
+    };
+    let (band_edges, pixel_edges) = (edges(bands), edges(band_length));
+    // Images that are a single pixel wide or high have cells without width or height.
+    let band_edges = if band_edges.is_empty() {
+        vec![(0, 0)]
+    } else {
+        band_edges
+    };
+    let pixel_edges = if pixel_edges.is_empty() {
+        vec![(0, 0)]
+    } else {
+        pixel_edges
+    };
+    let cells: Vec<Cell> = band_edges
+        .iter()
+        .flat_map(|&bands| {
+            pixel_edges
+                .iter()
+                .map(move |&pixels| Cell { bands, pixels })
+        })
+        .collect();
+
+    progress.start(cells.len() as u64);
+    // The cells are computed on their own, so the corners that neighbouring cells share are computed by each of them.
+    // Pixels that a cell computed take precedence over those that a neighbour interpolated.
+    let colored: Vec<Vec<ColoredPixel>> = cells
+        .into_par_iter()
+        .map(|cell| {
+            if progress.is_cancelled() {
+                progress.advance(1);
+                return Vec::new();
+            }
+            let pixels = render_parameters.cpu_limit.throttle(|| {
+                let mut subdivision = Subdivision::new(cell, grid, colorizer, render_parameters);
+                subdivision.refine(cell);
+                subdivision.into_pixels()
+            });
+            progress.advance(1);
+            pixels
+        })
+        .collect();
+    progress.finish();
+
+    for computed_pass in [false, true] {
+        for colored_pixel in colored.iter().flatten() {
+            if colored_pixel.computed == computed_pass {
+                let offset =
+                    (colored_pixel.band * band_length + colored_pixel.pixel) * bytes_per_pixel;
+                image[offset..offset + bytes_per_pixel]
+                    .copy_from_slice(&colored_pixel.color[..bytes_per_pixel]);
+            }
+        }
+    }
+}
+
+/// The pixels of a cell of the first level as it is subdivided.
+struct Subdivision<'a> {
+    cell: Cell,
+    grid: &'a PixelGrid,
+    colorizer: &'a Colorizer,
+    render_parameters: &'a RenderParameters,
+    /// The pixels of the cell that have been computed, band by band.
+    computed: Vec<Option<Computed>>,
+    /// The colors of the pixels of the cell that have been interpolated, band by band.
+    interpolated: Vec<Option<[u8; 4]>>,
+}
+
+impl<'a> Subdivision<'a> {
+    fn new(
+        cell: Cell,
+        grid: &'a PixelGrid,
+        colorizer: &'a Colorizer,
+        render_parameters: &'a RenderParameters,
+    ) -> Self {
+        let pixels = (cell.bands.1 - cell.bands.0 + 1) * (cell.pixels.1 - cell.pixels.0 + 1);
+        Self {
+            cell,
+            grid,
+            colorizer,
+            render_parameters,
+            computed: vec![None; pixels],
+            interpolated: vec![None; pixels],
+        }
+    }
+
+    /// The index in the pixels of the cell of the pixel with the given band and index in the band.
+    const fn index(&self, band: usize, pixel: usize) -> usize {
+        (band - self.cell.bands.0) * (self.cell.pixels.1 - self.cell.pixels.0 + 1)
+            + (pixel - self.cell.pixels.0)
+    }
+
+    /// Computes the pixel if it has not been already, and returns it.
+    fn compute(&mut self, band: usize, pixel: usize) -> Computed {
+        let index = self.index(band, pixel);
+        if let Some(computed) = self.computed[index] {
+            return computed;
+        }
+        let (color, sample) = pixel_color_and_first_sample(
+            self.grid,
+            (band, self.grid.grid_index(pixel)),
+            None,
+            self.colorizer,
+            self.render_parameters,
+        );
+        let mut bytes = [0; 4];
+        bytes[..color.as_raw().len()].copy_from_slice(color.as_raw());
+        let computed = Computed {
+            color: bytes,
+            sample,
+        };
+        self.computed[index] = Some(computed);
+        computed
+    }
+
+    /// Computes the corners of the part of the cell, and either interpolates it
+    /// if they agree or splits it into four and refines those.
+    fn refine(&mut self, part: Cell) {
+        let Cell {
+            bands: (left, right),
+            pixels: (bottom, top),
+        } = part;
+        let corners = [
+            self.compute(left, bottom).sample,
+            self.compute(right, bottom).sample,
+            self.compute(left, top).sample,
+            self.compute(right, top).sample,
+        ];
+        // Every pixel of the part is a corner.
+        if right - left <= 1 && top - bottom <= 1 {
+            return;
+        }
+        if agree(&corners) {
+            self.interpolate(part, &corners);
+            return;
+        }
+
+        let halves = |(start, end): (usize, usize)| {
+            let middle = (start + end) / 2;
+            if end - start >= 2 {
+                [Some((start, middle)), Some((middle, end))]
+            } else {
+                [Some((start, end)), None]
+            }
+        };
+        for bands in halves(part.bands).into_iter().flatten() {
+            for pixels in halves(part.pixels).into_iter().flatten() {
+                self.refine(Cell { bands, pixels });
+            }
+        }
+    }
+
+    /// Colors the pixels of the part of the cell that have not been computed
+    /// by interpolating the samples at its corners bilinearly.
+    fn interpolate(&mut self, part: Cell, corners: &[EscapeSample; 4]) {
+        let Cell {
+            bands: (left, right),
+            pixels: (bottom, top),
+        } = part;
+        let fraction = |position: usize, (start, end): (usize, usize)| {
+            if end == start {
+                0.0
+            } else {
+                // The cells are small, so the positions are represented exactly.
+                #[allow(clippy::cast_precision_loss)]
+                let fraction = (position - start) as f64 / (end - start) as f64;
+                fraction
+            }
+        };
+        for band in left..=right {
+            let x = fraction(band, part.bands);
+            for pixel in bottom..=top {
+                let index = self.index(band, pixel);
+                if self.computed[index].is_some() {
+                    continue;
+                }
+                let y = fraction(pixel, part.pixels);
+                let weights = [(1.0 - x) * (1.0 - y), x * (1.0 - y), (1.0 - x) * y, x * y];
+                let sample = blend(corners, weights);
+                let color = self.colorizer.pixel(
+                    self.colorizer.color(&sample),
+                    1.0,
+                    if sample.is_inside() { 1.0 } else { 0.0 },
+                );
+                let mut bytes = [0; 4];
+                bytes[..color.as_raw().len()].copy_from_slice(color.as_raw());
+                self.interpolated[index] = Some(bytes);
+            }
+        }
+    }
+
+    /// Returns every pixel of the cell that has been colored.
+    fn into_pixels(self) -> Vec<ColoredPixel> {
+        let Cell { bands, pixels } = self.cell;
+        (bands.0..=bands.1)
+            .flat_map(|band| (pixels.0..=pixels.1).map(move |pixel| (band, pixel)))
+            .zip(self.computed.iter().zip(&self.interpolated))
+            .filter_map(|((band, pixel), (computed, interpolated))| {
+                let (color, computed) = match computed {
+                    Some(computed) => (computed.color, true),
+                    None => ((*interpolated)?, false),
+                };
+                Some(ColoredPixel {
+                    band,
+                    pixel,
+                    color,
+                    computed,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Whether the samples at the corners of a cell are close enough for the cell to be interpolated:
+/// they must all have escaped with nearly the same escape speed on the same side of the real axis,
+/// or all be inside the set with nearly the same brightness.
+fn agree(corners: &[EscapeSample; 4]) -> bool {
+    let within_tolerance = |values: [f64; 4]| {
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        max - min <= ESCAPE_SPEED_TOLERANCE
+    };
+    match *corners {
+        [EscapeSample::Escaped {
+            below_real_axis, ..
+        }, ..] => {
+            corners.iter().all(|corner| {
+                matches!(corner, EscapeSample::Escaped { below_real_axis: below, .. } if *below == below_real_axis)
+            }) && within_tolerance(corners.map(|corner| corner.escape_speed()))
+        }
+        [EscapeSample::Inside { brightness: None }, ..] => corners
+            .iter()
+            .all(|corner| matches!(corner, EscapeSample::Inside { brightness: None })),
+        [EscapeSample::Inside {
+            brightness: Some(_),
+        }, ..] => {
+            let brightnesses = corners.map(|corner| match corner {
+                EscapeSample::Inside {
+                    brightness: Some(brightness),
+                } => brightness,
+                _ => f64::NAN,
+            });
+            // NaN is never within the tolerance, so corners without a brightness do not agree.
+            brightnesses.iter().all(|brightness| !brightness.is_nan())
+                && within_tolerance(brightnesses)
+        }
+    }
+}
+
+/// Returns the weighted sum of the samples at the corners of a cell, which must agree.
+fn blend(corners: &[EscapeSample; 4], weights: [f64; 4]) -> EscapeSample {
+    let weighted_sum = |value: fn(&EscapeSample) -> f64| {
+        corners
+            .iter()
+            .zip(weights)
+            .map(|(corner, weight)| weight * value(corner))
+            .sum()
+    };
+    match corners[0] {
+        EscapeSample::Escaped {
+            below_real_axis, ..
+        } => EscapeSample::Escaped {
+            escape_speed: weighted_sum(EscapeSample::escape_speed),
+            below_real_axis,
+        },
+        EscapeSample::Inside { brightness } => EscapeSample::Inside {
+            brightness: brightness.map(|_| {
+                weighted_sum(|corner| match corner {
+                    EscapeSample::Inside {
+                        brightness: Some(brightness),
+                    } => *brightness,
+                    _ => 0.0,
+                })
+            }),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test_quad_tree {
+    use super::*;
+    use crate::grayscale::GrayscaleCurve;
+    use crate::{render, Coloring, Frame, RenderQuality};
+
+    /// Subdivides the first cell of the image of the frame and returns its colored pixels.
+    fn subdivide(frame: Frame) -> Vec<ColoredPixel> {
+        let params = RenderParameters::test_image(RenderQuality::QuadTree, 2);
+        let grid = PixelGrid::new(&params, frame, None);
+        let colorizer =
+            Colorizer::new(Coloring::from(&params), GrayscaleCurve::new(&params, frame));
+        let cell = Cell {
+            bands: (0, CELL_SIZE),
+            pixels: (0, CELL_SIZE),
+        };
+        let mut subdivision = Subdivision::new(cell, &grid, &colorizer, &params);
+        subdivision.refine(cell);
+        subdivision.into_pixels()
+    }
+
+    #[test]
+    fn check_uniform_cells_are_filled() {
+        // Entirely inside the main cardioid, so the corners of the cell agree.
+        let pixels = subdivide(Frame::new(-0.1, 0.1, 0.15, 0.1));
+        assert_eq!(pixels.len(), (CELL_SIZE + 1).pow(2));
+        // Only the corners are computed, and the rest of the cell is filled in with their color.
+        assert_eq!(pixels.iter().filter(|pixel| pixel.computed).count(), 4);
+        assert!(pixels.iter().all(|pixel| pixel.color == pixels[0].color));
+    }
+
+    #[test]
+    fn check_cells_across_the_boundary_are_subdivided() {
+        // The first cell contains the cusp of the main cardioid.
+        let pixels = subdivide(Frame::new(0.4, 0.09, 0.3, 0.2));
+        assert_eq!(pixels.len(), (CELL_SIZE + 1).pow(2));
+        let computed = pixels.iter().filter(|pixel| pixel.computed).count();
+        // The cell is split where its corners differ, but the parts that agree are still filled in.
+        assert!(
+            4 < computed && computed < pixels.len(),
+            "{computed} pixels were computed"
+        );
+        // Both the inside and the outside of the set are in the cell.
+        assert!(pixels.iter().any(|pixel| pixel.color[..3] == [0, 0, 0]));
+        assert!(pixels.iter().any(|pixel| pixel.color[..3] != [0, 0, 0]));
+    }
+
+    #[test]
+    fn check_interpolation() {
+        let escaped = |escape_speed| EscapeSample::Escaped {
+            escape_speed,
+            below_real_axis: false,
+        };
+        let corners = [0.5, 0.5 + 1e-3, 0.5, 0.5 + 1e-3].map(escaped);
+        assert!(agree(&corners));
+        assert_eq!(
+            blend(&corners, [0.25; 4]).escape_speed(),
+            (0.5 + 0.5 + 1e-3) / 2.0
+        );
+        // Corners whose escape speeds are too far apart, that are on different sides of the real axis,
+        // or of which only some are inside the set are not interpolated.
+        assert!(!agree(&[0.5, 0.6, 0.5, 0.5].map(escaped)));
+        let mut below = corners;
+        below[3] = EscapeSample::Escaped {
+            escape_speed: 0.5,
+            below_real_axis: true,
+        };
+        assert!(!agree(&below));
+        let mut inside = corners;
+        inside[0] = EscapeSample::Inside { brightness: None };
+        assert!(!agree(&inside));
+        assert!(agree(&[EscapeSample::Inside { brightness: None }; 4]));
+    }
+
+    #[test]
+    fn check_tiny_images() {
+        for (width, height) in [(1, 1), (1, 40), (40, 1), (17, 33)] {
+            let mut params = RenderParameters::test_image(RenderQuality::QuadTree, 2);
+            params.x_resolution = width.try_into().unwrap();
+            params.y_resolution = height.try_into().unwrap();
+            let image = render(&params, Frame::new(-0.75, 0.0, 3.0, 2.0), false);
+            assert_eq!((image.width(), image.height()), (width, height));
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! Reconstructing pixels from samples that are shared with their neighbours.
+//!
+//! The samples of a pixel are spread over a square that reaches to the centers of the
+//! neighbouring pixels, and are normally averaged with equal weights. A reconstruction filter
+//! instead weights every sample by its distance to the center of each pixel less than a pixel
+//! away from it, so samples close to the edge of a pixel count less for it and also count for its
+//! neighbours. This gives smoother edges with the same number of samples.
+//!
+//! The pixels of a band segment are accumulated in a buffer of weighted sums, which every thread
+//! reuses for the segments it renders, see [`BufferPool`](crate::buffer_pool::BufferPool). The samples of
+//! the neighbouring bands that fall within reach of the segment are computed again, rather than
+//! shared between the threads that render the bands, so that the result does not depend on the
+//! scheduling.
+
+use core::cell::RefCell;
+use core::fmt;
+use core::str::FromStr;
+
+use color_space::LinearRGB;
+
+use crate::buffer_pool::BufferPool;
+use crate::sample_placement::sample_offsets;
+use crate::{sample_pixel, Mask, PixelGrid, RenderParameters, ViewPasses};
+
+thread_local! {
+    /// The buffers that the render threads accumulate the samples of band segments in.
+    static ACCUMULATORS: RefCell<BufferPool<(LinearRGB, f64, f64)>> = RefCell::new(BufferPool::new());
+}
+
+/// The standard deviation of the Gaussian filter, in pixels.
+const GAUSSIAN_SIGMA: f64 = 0.4;
+
+/// How the samples of the pixels are combined into their colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ReconstructionFilter {
+    /// Every pixel is the average of its own samples.
+    #[default]
+    Box,
+    /// Samples are weighted by how close they are to the center of the pixel,
+    /// falling linearly to zero one pixel away from it.
+    Tent,
+    /// Samples are weighted by a Gaussian of their distance to the center of the pixel,
+    /// cut off one pixel away from it. Softer than the tent filter.
+    Gaussian,
+}
+
+impl ReconstructionFilter {
+    pub const ALL: [Self; 3] = [Self::Box, Self::Tent, Self::Gaussian];
+
+    /// Returns the weight of a sample at the given distance from the center of a pixel
+    /// along one of the axes, in pixels.
+    fn weight(self, distance: f64) -> f64 {
+        let distance = distance.abs();
+        if distance >= 1.0 {
+            return 0.0;
+        }
+        match self {
+            // Box filtered pixels only use their own samples, see `color_band_segment`.
+            Self::Box => 1.0,
+            Self::Tent => 1.0 - distance,
+            // Shifted down to reach zero at the edge, so the weights do not jump there.
+            Self::Gaussian => {
+                let gaussian = |x: f64| (-x * x / (2.0 * GAUSSIAN_SIGMA * GAUSSIAN_SIGMA)).exp();
+                gaussian(distance) - gaussian(1.0)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ReconstructionFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Box => write!(f, "box"),
+            Self::Tent => write!(f, "tent"),
+            Self::Gaussian => write!(f, "gaussian"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseReconstructionFilterError(String);
+
+impl fmt::Display for ParseReconstructionFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown reconstruction filter \"{}\", expected \"box\", \"tent\" or \"gaussian\"",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseReconstructionFilterError {}
+
+impl FromStr for ReconstructionFilter {
+    type Err = ParseReconstructionFilterError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "box" => Ok(Self::Box),
+            "tent" | "triangle" => Ok(Self::Tent),
+            "gaussian" | "gauss" => Ok(Self::Gaussian),
+            _ => Err(ParseReconstructionFilterError(s.to_owned())),
+        }
+    }
+}
+
+/// Computes the colors of the pixels in a segment of a y-axis band of the image
+/// with the reconstruction filter of the render parameters,
+/// starting at the pixel with index `first_pixel` counted from the bottom of the band.
+/// The pixels are not mirrored.
+pub(crate) fn filter_band_segment(
+    render_parameters: &RenderParameters,
+    grid: &PixelGrid,
+    passes: &ViewPasses,
+    mask: Option<&Mask>,
+    band_index: usize,
+    first_pixel: usize,
+    segment: &mut [u8],
+) {
+    let filter = render_parameters.reconstruction_filter;
+    let ssaa = render_parameters.sqrt_samples_per_pixel.get();
+    let bytes_per_pixel = usize::from(render_parameters.color_type.bytes_per_pixel());
+    let pixels = segment.len() / bytes_per_pixel;
+    let last_band = usize::from(render_parameters.x_resolution) - 1;
+
+    // The pixels of the segment, as indices in the computed grid.
+    let targets = if grid.need_to_flip {
+        (grid.y_resolution - first_pixel - pixels)..(grid.y_resolution - first_pixel)
+    } else {
+        first_pixel..(first_pixel + pixels)
+    };
+    // The pixels whose samples can reach the segment.
+    let sources = targets.start.saturating_sub(1)..(targets.end + 1).min(grid.y_resolution);
+
+    let is_active = |band_index: usize, grid_index: usize| {
+        mask.is_none_or(|mask| mask.is_active(band_index, grid.grid_index(grid_index)))
+    };
+
+    // The weighted sum of the colors of the samples, the total weight, and the weight of
+    // the samples inside the set, of every pixel in the segment in the order of the computed grid.
+    let empty = (LinearRGB::default(), 0.0, 0.0);
+    BufferPool::with_buffer(&ACCUMULATORS, pixels, empty, |accumulated| {
+        for source_band in band_index.saturating_sub(1)..=(band_index + 1).min(last_band) {
+            // The bands are at most a few hundred thousand pixels wide, so this is exact.
+            #[allow(clippy::cast_precision_loss)]
+            let band_distance = source_band as f64 - band_index as f64;
+            for source in sources.clone() {
+                if !is_active(source_band, source) {
+                    continue;
+                }
+                let direction = passes
+                    .gradient_field
+                    .as_ref()
+                    .and_then(|field| field.direction(source_band, source));
+                // Only the samples of the neighbouring bands that reach this one are computed.
+                let offsets = sample_offsets(
+                    ssaa,
+                    render_parameters.sample_placement,
+                    render_parameters.seed,
+                    direction,
+                    (source_band, grid.grid_index(source)),
+                )
+                .filter(|&(real_offset, _)| filter.weight(band_distance + real_offset) > 0.0);
+                sample_pixel(
+                    grid.pixel_region(source_band, source),
+                    offsets,
+                    &passes.colorizer,
+                    render_parameters,
+                    |sample| {
+                        let (real_offset, imag_offset) = sample.offset;
+                        let real_weight = filter.weight(band_distance + real_offset);
+                        for target in source.saturating_sub(1)..=(source + 1) {
+                            if !targets.contains(&target) {
+                                continue;
+                            }
+                            #[allow(clippy::cast_precision_loss)]
+                            let weight = real_weight
+                                * filter.weight(source as f64 - target as f64 + imag_offset);
+                            if weight > 0.0 {
+                                let (color, total, inside) =
+                                    &mut accumulated[target - targets.start];
+                                *color += sample.color * weight;
+                                *total += weight;
+                                if sample.escape.is_inside() {
+                                    *inside += weight;
+                                }
+                            }
+                        }
+                    },
+                );
+            }
+        }
+
+        let fill = mask.map(|mask| mask.fill_bytes(render_parameters.color_type));
+        for (target, (color, weight, inside_weight)) in targets.zip(accumulated.iter().copied()) {
+            let offset = (grid.grid_index(target) - first_pixel) * bytes_per_pixel;
+            let pixel = &mut segment[offset..(offset + bytes_per_pixel)];
+            match &fill {
+                Some(fill) if !is_active(band_index, target) => pixel.copy_from_slice(fill),
+                _ => pixel.copy_from_slice(
+                    passes
+                        .colorizer
+                        .pixel(color, weight, inside_weight)
+                        .as_raw(),
+                ),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test_reconstruction {
+    use super::*;
+    use crate::{render, Frame};
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_weights() {
+        for filter in [ReconstructionFilter::Tent, ReconstructionFilter::Gaussian] {
+            assert_eq!(
+                filter.weight(0.0).max(filter.weight(0.5)),
+                filter.weight(0.0)
+            );
+            assert!(filter.weight(0.5) > filter.weight(-0.9));
+            assert_eq!(filter.weight(0.5), filter.weight(-0.5));
+            assert_eq!(filter.weight(1.0), 0.0);
+            assert_eq!(filter.weight(-1.5), 0.0);
+        }
+        assert_eq!(ReconstructionFilter::Tent.weight(0.25), 0.75);
+    }
+
+    #[test]
+    fn check_filtered_render() {
+        let mut params = RenderParameters::try_new(
+            48.try_into().unwrap(),
+            32.try_into().unwrap(),
+            200.try_into().unwrap(),
+            3.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let frame = Frame::new(-0.75, 0.1, 3.0, 2.0);
+        let boxed = render(&params, frame, false).into_rgb8();
+        params.reconstruction_filter = ReconstructionFilter::Tent;
+        let tent = render(&params, frame, false).into_rgb8();
+        params.reconstruction_filter = ReconstructionFilter::Gaussian;
+        let gaussian = render(&params, frame, false).into_rgb8();
+
+        // The filters only change the weights of the samples, so the images are close to each other
+        for image in [&tent, &gaussian] {
+            assert_ne!(image, &boxed);
+            let mean_difference = image
+                .as_raw()
+                .iter()
+                .zip(boxed.as_raw())
+                .map(|(&a, &b)| f64::from(a.abs_diff(b)))
+                .sum::<f64>()
+                / boxed.as_raw().len() as f64;
+            assert!(mean_difference < 10.0, "{mean_difference}");
+        }
+
+        // and the filtered images do not depend on how the work is split up.
+        for scheduling in [crate::Scheduling::Bands, crate::Scheduling::Tiles] {
+            params.scheduling = scheduling;
+            assert_eq!(render(&params, frame, false).into_rgb8(), gaussian);
+        }
+    }
+
+    #[test]
+    fn check_parsing() {
+        for filter in ReconstructionFilter::ALL {
+            assert_eq!(filter.to_string().parse(), Ok(filter));
+        }
+        assert_eq!(" Triangle".parse(), Ok(ReconstructionFilter::Tent));
+        assert!("lanczos".parse::<ReconstructionFilter>().is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use core::fmt;
+
+use color_space::SupportedColorType;
+
+use crate::{Frame, PixelRect};
+
+/// An error that prevents an image from being rendered, returned by [`try_render`](crate::try_render),
+/// [`try_render_masked`](crate::try_render_masked), [`try_render_newton`](crate::try_render_newton),
+/// [`try_render_nebulabrot`](crate::try_render_nebulabrot), [`try_render_timed`](crate::try_render_timed),
+/// [`render_into`](crate::render_into), [`render_tile`](crate::render_tile)
+/// and [`RenderParameters::try_new`](crate::RenderParameters::try_new).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderError {
+    /// The center of the frame is not finite, or its distances are not finite and positive.
+    InvalidFrame(Frame),
+    /// The memory needed to store the image could not be allocated.
+    ImageTooLarge {
+        x_resolution: u32,
+        y_resolution: u32,
+        color_type: SupportedColorType,
+    },
+    /// The buffer given to [`render_into`](crate::render_into) does not have the length of the image.
+    BufferLength { expected: usize, actual: usize },
+    /// The tile given to [`render_tile`](crate::render_tile) is empty or not inside the image.
+    TileOutsideImage(PixelRect),
+    /// The [`Progress`](crate::Progress) that the render reported to cancelled it.
+    Cancelled,
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFrame(frame) => write!(
+                f,
+                "can not render a {} by {} region centered on {} + {}i, the center must be finite and the size finite and positive",
+                frame.real_distance, frame.imag_distance, frame.center_real, frame.center_imag
+            ),
+            Self::ImageTooLarge {
+                x_resolution,
+                y_resolution,
+                color_type,
+            } => write!(
+                f,
+                "could not allocate the {} bytes needed for a {x_resolution}x{y_resolution} image",
+                u128::from(*x_resolution)
+                    * u128::from(*y_resolution)
+                    * u128::from(color_type.bytes_per_pixel())
+            ),
+            Self::BufferLength { expected, actual } => write!(
+                f,
+                "the buffer is {actual} bytes long, but the image needs {expected} bytes"
+            ),
+            Self::TileOutsideImage(tile) => write!(
+                f,
+                "the {}x{} tile at ({}, {}) is not inside the image",
+                tile.width, tile.height, tile.x, tile.y
+            ),
+            Self::Cancelled => write!(f, "the render was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
@@ -0,0 +1,205 @@
+//! Rendering straight into a buffer that the caller owns, e.g. the memory of a texture or a memory-mapped file,
+//! without allocating an image of its own or rotating it into place afterwards.
+
+use image::DynamicImage;
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use rayon::slice::ParallelSliceMut;
+
+use crate::escape_buffer::Colorizer;
+use crate::grayscale::GrayscaleCurve;
+use crate::sample_placement::GradientField;
+use crate::{
+    check_frame, pixel_color, progress::NoProgress, render_with_optional_mask, Coloring, Frame,
+    PixelGrid, ReconstructionFilter, RenderError, RenderParameters, RenderQuality,
+};
+
+/// Renders the image that [`render`](crate::render) would into `buffer`, which must hold exactly
+/// one image with the resolution and color type of the render parameters. The pixels are laid out
+/// like in the [`image`] crate: row by row from the top, with [`bytes_per_pixel`] bytes per pixel.
+///
+/// The rows of the image are computed in parallel. [`RenderQuality::Draft`] renders and
+/// reconstruction filters other than [`ReconstructionFilter::Box`] can not be computed a row at a time,
+/// so those images are rendered as usual and copied into the buffer.
+///
+/// [`bytes_per_pixel`]: color_space::SupportedColorType::bytes_per_pixel
+///
+/// # Errors
+/// Returns an error if the length of the buffer does not fit the image, if the center of the frame
+/// is not finite, or if its distances are not finite and positive.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{render, render_into, Frame, RenderError, RenderParameters};
+/// # use color_space::SupportedColorType;
+/// let params = RenderParameters::try_new(
+///     30.try_into().unwrap(),
+///     20.try_into().unwrap(),
+///     100.try_into().unwrap(),
+///     2.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+///
+/// let mut buffer = vec![0; 30 * 20 * 3];
+/// render_into(&params, frame, &mut buffer).unwrap();
+/// assert_eq!(buffer, render(&params, frame, false).into_bytes());
+///
+/// assert_eq!(
+///     render_into(&params, frame, &mut buffer[1..]),
+///     Err(RenderError::BufferLength { expected: 1800, actual: 1799 })
+/// );
+/// ```
+pub fn render_into(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    buffer: &mut [u8],
+) -> Result<(), RenderError> {
+    let width = usize::from(render_parameters.x_resolution);
+    let height = usize::from(render_parameters.y_resolution);
+    let bytes_per_pixel = usize::from(render_parameters.color_type.bytes_per_pixel());
+    let row_length = width * bytes_per_pixel;
+
+    let expected = row_length.checked_mul(height);
+    if expected != Some(buffer.len()) {
+        return Err(RenderError::BufferLength {
+            expected: expected.unwrap_or(usize::MAX),
+            actual: buffer.len(),
+        });
+    }
+    check_frame(render_region)?;
+
+    if render_parameters.quality != RenderQuality::Full
+        || render_parameters.reconstruction_filter != ReconstructionFilter::Box
+    {
+        let image =
+            render_with_optional_mask(render_parameters, render_region, None, &NoProgress, None)?;
+        buffer.copy_from_slice(image_bytes(&image));
+        return Ok(());
+    }
+
+    let _render_span = tracing::info_span!(
+        "render_into",
+        x_resolution = width,
+        y_resolution = height,
+        formula = %render_parameters.formula,
+    )
+    .entered();
+
+    let grid = PixelGrid::new(render_parameters, render_region, None);
+    let gradient_field = GradientField::new(render_parameters, &grid);
+    let colorizer = Colorizer::new(
+        Coloring::from(render_parameters),
+        GrayscaleCurve::new(render_parameters, render_region),
+    );
+
+    // The index in the computed grid of the pixels in the given row of the buffer, counted from the top.
+    let grid_index = |row: usize| grid.grid_index(height - 1 - row);
+    // The row that the given row is the mirror image of, if any.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let mirror_source = |row: usize| {
+        let index = grid_index(row);
+        (grid.mirror && index as f64 > grid.mirror_axis / 2.0 && index as f64 <= grid.mirror_axis)
+            .then(|| height - 1 - grid.grid_index(grid.mirror_axis as usize - index))
+    };
+
+    buffer
+        .par_chunks_exact_mut(row_length)
+        .enumerate()
+        .filter(|(row, _)| mirror_source(*row).is_none())
+        .for_each(|(row, pixels)| {
+            render_parameters.cpu_limit.throttle(|| {
+                let index = grid_index(row);
+                for (band_index, pixel) in pixels.chunks_exact_mut(bytes_per_pixel).enumerate() {
+                    let direction = gradient_field
+                        .as_ref()
+                        .and_then(|field| field.direction(band_index, index));
+                    let color = pixel_color(
+                        &grid,
+                        (band_index, index),
+                        direction,
+                        &colorizer,
+                        render_parameters,
+                    );
+                    pixel.copy_from_slice(color.as_raw());
+                }
+            });
+        });
+
+    // The mirrored rows are copied once all the rows they are copied from have been computed.
+    for row in 0..height {
+        if let Some(source) = mirror_source(row) {
+            buffer.copy_within(
+                source * row_length..(source + 1) * row_length,
+                row * row_length,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the raw bytes of an image made by the renderer.
+fn image_bytes(image: &DynamicImage) -> &[u8] {
+    match image {
+        DynamicImage::ImageLuma8(buffer) => buffer.as_raw(),
+        DynamicImage::ImageRgb8(buffer) => buffer.as_raw(),
+        DynamicImage::ImageRgba8(buffer) => buffer.as_raw(),
+        _ => unreachable!("the renderer only produces the images above"),
+    }
+}
+
+#[cfg(test)]
+mod test_render_into {
+    use super::*;
+    use crate::render;
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_same_as_render() {
+        let frames = [
+            // Mirrored in the real axis.
+            Frame::new(-0.75, 0.0, 3.0, 2.0),
+            // Mirrored and flipped.
+            Frame::new(-0.75, 0.3, 3.0, 2.0),
+            // Not containing the real axis.
+            Frame::new(-0.1, 0.8, 0.5, 0.4),
+        ];
+        for color_type in [SupportedColorType::L8, SupportedColorType::Rgba8] {
+            let mut params = RenderParameters::try_new(
+                31.try_into().unwrap(),
+                20.try_into().unwrap(),
+                200.try_into().unwrap(),
+                2.try_into().unwrap(),
+                color_type,
+            )
+            .unwrap();
+            for quality in RenderQuality::ALL {
+                params.quality = quality;
+                for frame in frames {
+                    let mut buffer = vec![0; 31 * 20 * usize::from(color_type.bytes_per_pixel())];
+                    render_into(&params, frame, &mut buffer).unwrap();
+                    assert_eq!(buffer, render(&params, frame, false).into_bytes());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_invalid_frame() {
+        let params = RenderParameters::try_new(
+            3.try_into().unwrap(),
+            2.try_into().unwrap(),
+            10.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::L8,
+        )
+        .unwrap();
+        let frame = Frame::new(f64::INFINITY, 0.0, 3.0, 2.0);
+        assert_eq!(
+            render_into(&params, frame, &mut [0; 6]),
+            Err(RenderError::InvalidFrame(frame))
+        );
+    }
+}
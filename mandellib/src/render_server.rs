@@ -0,0 +1,77 @@
+//! The location that `mandelbrot batch` listens for views on, and that the viewer sends them to.
+
+use std::path::PathBuf;
+
+/// The local socket that `mandelbrot batch --listen` listens on, and that the viewer sends views to, by default.
+///
+/// On Unix it is put in the runtime directory of the user if there is one. Otherwise it is put in a directory
+/// of the temporary directory that is named after the id of the user, which the render server creates
+/// so that only the user can enter it. On Windows it is a named pipe.
+#[must_use]
+pub fn default_render_server_socket() -> PathBuf {
+    #[cfg(windows)]
+    {
+        PathBuf::from(r"\\.\pipe\mandelbrot-batch")
+    }
+    #[cfg(unix)]
+    {
+        match std::env::var_os("XDG_RUNTIME_DIR") {
+            Some(runtime_directory) => {
+                PathBuf::from(runtime_directory).join("mandelbrot-batch.sock")
+            }
+            // The temporary directory is shared by every user, so the socket can not have a fixed name there.
+            None => std::env::temp_dir()
+                .join(format!(
+                    "mandelbrot-batch-{}",
+                    rustix::process::geteuid().as_raw()
+                ))
+                .join("socket"),
+        }
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        std::env::temp_dir().join("mandelbrot-batch.sock")
+    }
+}
+
+/// Returns an error unless the file at `path` belongs to the user that runs the program.
+/// A render server sees the views that are sent to it and decides what the viewer is told in reply,
+/// so nothing should be sent to a socket that another user has put at its path,
+/// and a server should not listen in a directory where another user can replace its socket.
+///
+/// # Errors
+/// Returns an error if the metadata of the file can not be read, or if it belongs to another user.
+#[cfg(unix)]
+pub fn check_render_server_owner(path: &std::path::Path) -> std::io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let owner = std::fs::symlink_metadata(path)?.uid();
+    if owner == rustix::process::geteuid().as_raw() {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("{} belongs to another user", path.display()),
+        ))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test_render_server {
+    use super::*;
+
+    #[test]
+    fn check_owner() {
+        let path = std::env::temp_dir().join(format!(
+            "mandelbrot-render-server-owner-test-{}",
+            std::process::id()
+        ));
+        assert_eq!(
+            check_render_server_owner(&path).unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+        std::fs::write(&path, "").unwrap();
+        check_render_server_owner(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
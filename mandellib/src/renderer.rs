@@ -0,0 +1,1555 @@
+use core::num::{NonZeroU32, NonZeroU64, NonZeroU8};
+use std::time::{Duration, Instant};
+
+use color_space::{LinearRGB, Palette, Pixel, SupportedColorType};
+use image::{DynamicImage, ImageBuffer, Luma, Rgb, Rgba};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::adaptive_supersampling::{SampleVariance, PILOT_SAMPLES};
+use crate::derivative_bailout::iterate_with_derivative_bailout;
+use crate::escape_buffer::Colorizer;
+use crate::grayscale::GrayscaleCurve;
+use crate::interior::{interior_brightness, InteriorProvenance};
+use crate::kernel::iterate_mandelbrot;
+use crate::sample_placement::{sample_offsets, GradientField};
+use crate::scheduling::split_into_work;
+use crate::watchdog::{time_if, SegmentTime};
+use crate::{
+    draft, parallel_rotate270, progress, quad_tree, reconstruction, BulbChecks, Coloring, Complex,
+    CpuLimit, EscapeRadius, EscapeSample, EscapeSpeedRange, Exponent, Exterior, Fixed, Float,
+    Formula, Frame, GrayscaleMode, InteriorShading, Mask, Precision, Progress,
+    ReconstructionFilter, RenderError, RenderQuality, RenderTimings, SamplePlacement, Scheduling,
+    Smoothing, SsaaCutoff, U32AndUsize, VarianceThreshold, DEFAULT_SMOOTHING_OFFSET,
+};
+
+/// Takes in variables describing where to render and at what resolution
+/// and produces an image of the Mandelbrot set.
+///
+/// `render_parameters` contains `x_resolution`, `y_resolution`, `max_iterations`, `sqrt_samples_per_pixel`,
+/// `formula`, `exponent`, `palette` and `grayscale`.
+///
+/// `render_region` contains `center_real`, `center_imag`, `real_distance` and `imag_distance`.
+///
+/// `x_resolution` and `y_resolution` is the resolution in pixels in the real
+/// and imaginary direction respectively.
+/// `sqrt_samples_per_pixel` is the number of supersampled points along one direction. If it
+/// is e.g. 3, then a supersampled pixel will be sampled 3^2 = 9 times.
+///
+/// `center_real` and `center_imag` are the real and imaginary parts of the
+/// point at the center of the image.
+///
+/// `real_distance` and `imag_distance` describe the size of the region in the
+/// complex plane to render.
+///
+/// ```text
+///           real_distance
+/// |-------------------------------|
+/// |                               |
+/// |              x                |  imag_distance
+/// |  center_real + center_imag*i  |
+/// |-------------------------------|
+/// ```
+/// If `real_distance` = `imag_distance` = 1,
+/// `x_resolution` = `y_resolution` = 100 and `center_real` = `center_imag` = 0 a square
+/// of size 1x1 centered on the origin will be computed and rendered as a
+/// 100x100 pixel image.
+///
+/// `max_iterations` is the maximum number of iterations to compute for each pixel sample before labeling
+/// a point as part of the set.
+///
+/// `formula` is the iteration function, see [`Formula`].
+///
+/// `exponent` is the exponent `d` in the iteration function z -> z^d + c.
+/// It is 2 for the Mandelbrot set, larger values give so called Multibrot sets.
+///
+/// `palette` determines how escape speeds are mapped to colors.
+///
+/// If `grayscale` is true the image is rendered in grayscale instead of color.
+///
+/// If `verbose` is true the function will use prints to `stderr` to display a progress bar.
+/// Use [`try_render_with_progress`] to report the progress in some other way.
+///
+/// The image can also be rendered in two stages with [`compute`](crate::compute) and [`colorize`](crate::colorize),
+/// which lets it be colored again without iterating its points again.
+///
+/// # Panics
+/// Panics if the memory for the image can not be allocated.
+/// Use [`try_render`] to get an error instead.
+#[must_use]
+pub fn render(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    verbose: bool,
+) -> DynamicImage {
+    render_with_optional_mask(
+        render_parameters,
+        render_region,
+        None,
+        progress::verbose_progress(verbose).as_ref(),
+        None,
+    )
+    .unwrap_or_else(|error| panic!("{error}"))
+}
+
+/// Works like [`render`], but checks that the frame can be rendered
+/// and returns an error instead of panicking if the image can not be allocated.
+///
+/// # Errors
+/// Returns an error if the center of the frame is not finite, if its distances
+/// are not finite and positive, or if the memory for the image can not be allocated.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{try_render, Frame, RenderError, RenderParameters};
+/// # use color_space::SupportedColorType;
+/// let params = RenderParameters::try_new(
+///     30.try_into().unwrap(),
+///     20.try_into().unwrap(),
+///     100.try_into().unwrap(),
+///     1.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// assert!(try_render(&params, Frame::new(-0.75, 0.0, 3.0, 2.0), false).is_ok());
+///
+/// // Zooming in by a factor of 2^2000 makes the frame too small to represent.
+/// let frame = Frame::new(-0.75, 0.0, 3.0 / 2.0_f64.powi(2000), 2.0 / 2.0_f64.powi(2000));
+/// assert_eq!(
+///     try_render(&params, frame, false),
+///     Err(RenderError::InvalidFrame(frame))
+/// );
+/// ```
+pub fn try_render(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    verbose: bool,
+) -> Result<DynamicImage, RenderError> {
+    try_render_with_progress(
+        render_parameters,
+        render_region,
+        progress::verbose_progress(verbose).as_ref(),
+    )
+}
+
+/// Works like [`try_render`], but reports the progress of the render to `progress`
+/// instead of optionally displaying a progress bar, see [`Progress`].
+///
+/// # Errors
+/// Returns an error if the center of the frame is not finite, if its distances
+/// are not finite and positive, if the memory for the image can not be allocated,
+/// or if the progress cancelled the render.
+pub fn try_render_with_progress(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    progress: &dyn Progress,
+) -> Result<DynamicImage, RenderError> {
+    check_frame(render_region)?;
+    render_with_optional_mask(render_parameters, render_region, None, progress, None)
+}
+
+/// Returns an error if the center of the frame is not finite, or if its distances are not finite and positive.
+pub(crate) fn check_frame(render_region: Frame) -> Result<(), RenderError> {
+    let is_valid_distance = |distance: f64| distance.is_finite() && distance > 0.0;
+    if render_region.center_real.is_finite()
+        && render_region.center_imag.is_finite()
+        && is_valid_distance(render_region.real_distance)
+        && is_valid_distance(render_region.imag_distance)
+    {
+        Ok(())
+    } else {
+        Err(RenderError::InvalidFrame(render_region))
+    }
+}
+
+/// Works like [`render`], but only computes the pixels that are active in the given [`Mask`].
+/// The rest of the pixels are filled with the fill color of the mask.
+/// Bands of the image that contain no active pixels are skipped entirely.
+///
+/// Since the mask is in general not symmetric under conjugation
+/// the image is never mirrored when a mask is used.
+///
+/// # Panics
+/// Panics if the memory for the image can not be allocated.
+/// Use [`try_render_masked`] to get an error instead.
+#[must_use]
+pub fn render_masked(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    mask: &Mask,
+    verbose: bool,
+) -> DynamicImage {
+    render_with_optional_mask(
+        render_parameters,
+        render_region,
+        Some(mask),
+        progress::verbose_progress(verbose).as_ref(),
+        None,
+    )
+    .unwrap_or_else(|error| panic!("{error}"))
+}
+
+/// Works like [`render_masked`], but checks that the frame can be rendered
+/// and returns an error instead of panicking if the image can not be allocated.
+///
+/// # Errors
+/// Returns an error if the center of the frame is not finite, if its distances
+/// are not finite and positive, or if the memory for the image can not be allocated.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{try_render_masked, Frame, Mask, RenderError, RenderParameters};
+/// # use color_space::SupportedColorType;
+/// # use image::{DynamicImage, GrayImage, Luma, Rgb};
+/// let params = RenderParameters::try_new(
+///     30.try_into().unwrap(),
+///     20.try_into().unwrap(),
+///     100.try_into().unwrap(),
+///     1.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// let mask = Mask::new(
+///     &DynamicImage::ImageLuma8(GrayImage::from_pixel(30, 20, Luma([255]))),
+///     params.x_resolution,
+///     params.y_resolution,
+///     Rgb([0, 0, 0]),
+///     false,
+/// )
+/// .unwrap();
+/// assert!(try_render_masked(&params, Frame::new(-0.75, 0.0, 3.0, 2.0), &mask, false).is_ok());
+///
+/// let frame = Frame::new(-0.75, 0.0, 0.0, 2.0);
+/// assert_eq!(
+///     try_render_masked(&params, frame, &mask, false),
+///     Err(RenderError::InvalidFrame(frame))
+/// );
+/// ```
+pub fn try_render_masked(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    mask: &Mask,
+    verbose: bool,
+) -> Result<DynamicImage, RenderError> {
+    check_frame(render_region)?;
+    render_with_optional_mask(
+        render_parameters,
+        render_region,
+        Some(mask),
+        progress::verbose_progress(verbose).as_ref(),
+        None,
+    )
+}
+
+/// Returns the name of the render worker thread with the given index, e.g. "mandel-worker-3".
+///
+/// Pass this to [`rayon::ThreadPoolBuilder::thread_name`] when building the thread pool that renders
+/// images, so that the threads can be recognized in profilers and debuggers.
+#[must_use]
+pub fn worker_thread_name(index: usize) -> String {
+    format!("mandel-worker-{index}")
+}
+
+/// The maximum number of iterations used by [`RenderParameters::fast_preview`].
+pub const FAST_PREVIEW_MAX_ITERATIONS: NonZeroU64 = NonZeroU64::new(64).unwrap();
+
+/// The inverse of the golden ratio. The fractional parts of its multiples by consecutive integers
+/// are spread evenly over \[0, 1\), with no two of them close together.
+pub(crate) const INVERSE_GOLDEN_RATIO: f64 = 0.618_033_988_749_894_8;
+
+/// The approximate number of pixels in the image rendered by [`estimate_render_time`].
+const PROBE_PIXELS: f64 = 10_000.0;
+
+/// The maximum number of iterations of the first of the images rendered by [`estimate_render_time`].
+/// The second one has twice as many.
+const PROBE_MAX_ITERATIONS: NonZeroU64 = NonZeroU64::new(512).unwrap();
+
+/// Estimates how long [`render`] would take with the given parameters by rendering a low resolution
+/// version of the same image with one sample per pixel, and scaling the time it took by the ratio
+/// of the number of samples. Is quick enough to run before every render.
+///
+/// If the maximum number of iterations is high, the version is rendered with two low maximums instead,
+/// and the time that the rest of the iterations would take is extrapolated from how much longer
+/// the higher maximum took.
+/// The estimate is only rough, since small features can make up a larger part of the low resolution
+/// image than of the full one, and supersampled pixels far from the set are often done after one sample.
+#[must_use]
+pub fn estimate_render_time(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+) -> Duration {
+    let x_resolution = f64::from(render_parameters.x_resolution);
+    let y_resolution = f64::from(render_parameters.y_resolution);
+    let scale = (PROBE_PIXELS / (x_resolution * y_resolution))
+        .sqrt()
+        .min(1.0);
+
+    let mut probe_parameters = render_parameters.clone();
+    // The scale is at most 1, so the scaled resolutions fit in the original types.
+    // At least two pixels are needed along each axis for the distance between them to be defined.
+    probe_parameters.x_resolution = ((x_resolution * scale) as u32)
+        .max(2)
+        .try_into()
+        .unwrap_or(render_parameters.x_resolution);
+    probe_parameters.y_resolution = ((y_resolution * scale) as u32)
+        .max(2)
+        .try_into()
+        .unwrap_or(render_parameters.y_resolution);
+    probe_parameters.sqrt_samples_per_pixel = NonZeroU8::MIN;
+
+    let mut probe_seconds = |max_iterations: NonZeroU64| {
+        probe_parameters.max_iterations = max_iterations;
+        let start = Instant::now();
+        _ = render(&probe_parameters, render_region, false);
+        start.elapsed().as_secs_f64()
+    };
+
+    let max_iterations = render_parameters.max_iterations;
+    let doubled_probe_iterations =
+        PROBE_MAX_ITERATIONS.saturating_mul(NonZeroU64::new(2).expect("2 is not zero"));
+    let seconds = if max_iterations <= doubled_probe_iterations {
+        probe_seconds(max_iterations)
+    } else {
+        let low = probe_seconds(PROBE_MAX_ITERATIONS);
+        let high = probe_seconds(doubled_probe_iterations);
+        // Only the points that did not escape within the lower maximum took longer with the higher one,
+        // and they keep iterating at the same rate until the full maximum. The difference between
+        // the times can be negative if the first render was slowed down by something else.
+        let seconds_per_iteration = (high - low).max(0.0) / PROBE_MAX_ITERATIONS.get() as f64;
+        let remaining_iterations = max_iterations.get() - doubled_probe_iterations.get();
+        high + remaining_iterations as f64 * seconds_per_iteration
+    };
+
+    let probe_pixels =
+        f64::from(probe_parameters.x_resolution) * f64::from(probe_parameters.y_resolution);
+    let samples_per_pixel = f64::from(render_parameters.sqrt_samples_per_pixel.get()).powi(2);
+    Duration::try_from_secs_f64(
+        seconds * samples_per_pixel * x_resolution * y_resolution / probe_pixels,
+    )
+    .unwrap_or(Duration::MAX)
+}
+
+/// Renders the image while reporting its progress, and if `timings` is given,
+/// times every band segment that is rendered and stores the times in it.
+pub(crate) fn render_with_optional_mask(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    mask: Option<&Mask>,
+    progress: &dyn Progress,
+    timings: Option<&mut RenderTimings>,
+) -> Result<DynamicImage, RenderError> {
+    let x_resolution = render_parameters.x_resolution;
+    let y_resolution = render_parameters.y_resolution;
+    let color_type = render_parameters.color_type;
+
+    let mut image = allocate_rotated_image(x_resolution, y_resolution, color_type)?;
+
+    // Spans are only recorded if the caller has installed a `tracing` subscriber,
+    // so this costs next to nothing otherwise.
+    let render_span = tracing::info_span!(
+        "render",
+        center_real = render_region.center_real,
+        center_imag = render_region.center_imag,
+        real_distance = render_region.real_distance,
+        imag_distance = render_region.imag_distance,
+        x_resolution = u32::from(x_resolution),
+        y_resolution = u32::from(y_resolution),
+        formula = %render_parameters.formula,
+    );
+    let _entered = render_span.enter();
+
+    let grid = PixelGrid::new(render_parameters, render_region, mask);
+    let colorizer = {
+        let _grayscale_span = tracing::info_span!(parent: &render_span, "grayscale").entered();
+        Colorizer::new(
+            Coloring::from(render_parameters),
+            GrayscaleCurve::new(render_parameters, render_region),
+        )
+    };
+    let bytes_per_pixel = usize::from(color_type.bytes_per_pixel());
+    let buffer: &mut [u8] = match &mut image {
+        DynamicImage::ImageLuma8(buffer) => buffer.as_mut(),
+        DynamicImage::ImageRgb8(buffer) => buffer.as_mut(),
+        DynamicImage::ImageRgba8(buffer) => buffer.as_mut(),
+        _ => unreachable!("we define the image so that it can only be one of the above"),
+    };
+
+    let timed = timings.is_some();
+    let mut segment_times = Vec::new();
+    let mut scheduling = render_parameters.scheduling;
+
+    if render_parameters.quality == RenderQuality::QuadTree && mask.is_none() {
+        let _subdivide_span = tracing::info_span!(parent: &render_span, "subdivide").entered();
+        quad_tree::color_by_subdivision(
+            render_parameters,
+            &grid,
+            &colorizer,
+            buffer,
+            y_resolution.into(),
+            progress,
+        );
+    } else if render_parameters.quality == RenderQuality::Draft && mask.is_none() {
+        let _guess_span = tracing::info_span!(parent: &render_span, "guess").entered();
+        // The guessing is not split into units of work, so the whole image is reported as one.
+        progress.start(1);
+        draft::color_by_guessing(
+            render_parameters,
+            &grid,
+            &colorizer,
+            buffer,
+            y_resolution.into(),
+        );
+        progress.advance(1);
+    } else {
+        let passes = ViewPasses {
+            gradient_field: {
+                let _gradient_span =
+                    tracing::info_span!(parent: &render_span, "gradient").entered();
+                GradientField::new(render_parameters, &grid)
+            },
+            colorizer,
+        };
+        scheduling =
+            render_parameters
+                .scheduling
+                .resolve(render_parameters, render_region, grid.mirror);
+
+        // Split the image up into vertical bands, and the bands into units of work.
+        let work = split_into_work(
+            buffer,
+            bytes_per_pixel * usize::from(y_resolution),
+            bytes_per_pixel,
+            scheduling,
+        );
+
+        progress.start(work.len() as u64);
+
+        // Iterate over the units of work in parallel.
+        segment_times = work
+            .into_par_iter()
+            .flat_map_iter(|segments| {
+                // The work is done on other threads, so the parent span must be given explicitly.
+                let _work_span = tracing::trace_span!(
+                    parent: &render_span,
+                    "work",
+                    ?scheduling,
+                    first_band = segments.first().map(|segment| segment.0),
+                )
+                .entered();
+                // The times are collected here, while the span is entered.
+                let mut times = Vec::new();
+                if progress.is_cancelled() {
+                    progress.advance(1);
+                    return times;
+                }
+                for (band_index, first_pixel, segment) in segments {
+                    let pixels = segment.len() / bytes_per_pixel;
+                    // The time spent resting is not part of the timings.
+                    let duration = render_parameters.cpu_limit.throttle(|| {
+                        time_if(timed, || {
+                            color_band_segment(
+                                render_parameters,
+                                &grid,
+                                &passes,
+                                mask,
+                                band_index,
+                                first_pixel,
+                                segment,
+                            );
+                        })
+                    });
+                    times.extend(duration.map(|duration| SegmentTime {
+                        band_index,
+                        first_pixel,
+                        pixels,
+                        duration,
+                    }));
+                }
+                progress.advance(1);
+                times
+            })
+            .collect();
+    }
+    progress.finish();
+    if progress.is_cancelled() {
+        return Err(RenderError::Cancelled);
+    }
+
+    if let Some(timings) = timings {
+        *timings = RenderTimings::new(render_parameters, render_region, scheduling, &segment_times);
+    }
+
+    // Undo the rotated state used during rendering.
+    let _rotate_span = tracing::info_span!(parent: &render_span, "rotate").entered();
+    Ok(parallel_rotate270(&image))
+}
+
+/// Allocates a black image with the given resolution and color type, rotated by 90 degrees.
+///
+/// We store the pixel data in a rotated fashion so that
+/// the data for pixels along the y-axis lie contiguous in memory.
+pub(crate) fn allocate_rotated_image(
+    x_resolution: U32AndUsize,
+    y_resolution: U32AndUsize,
+    color_type: SupportedColorType,
+) -> Result<DynamicImage, RenderError> {
+    let too_large = RenderError::ImageTooLarge {
+        x_resolution: x_resolution.into(),
+        y_resolution: y_resolution.into(),
+        color_type,
+    };
+
+    let length = usize::from(x_resolution)
+        .checked_mul(usize::from(y_resolution))
+        .and_then(|pixels| pixels.checked_mul(usize::from(color_type.bytes_per_pixel())))
+        .ok_or(too_large)?;
+    let mut buffer = Vec::new();
+    buffer.try_reserve_exact(length).map_err(|_| too_large)?;
+    buffer.resize(length, 0);
+
+    // That is the reason for the switched dimensions in these calls to `from_raw`.
+    let (width, height) = (y_resolution.into(), x_resolution.into());
+    let image = match color_type {
+        SupportedColorType::L8 => ImageBuffer::<Luma<u8>, Vec<u8>>::from_raw(width, height, buffer)
+            .map(DynamicImage::ImageLuma8),
+        SupportedColorType::Rgb8 => {
+            ImageBuffer::<Rgb<u8>, Vec<u8>>::from_raw(width, height, buffer)
+                .map(DynamicImage::ImageRgb8)
+        }
+        SupportedColorType::Rgba8 => {
+            ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(width, height, buffer)
+                .map(DynamicImage::ImageRgba8)
+        }
+    };
+    Ok(image.expect("the buffer has the length of the image"))
+}
+
+/// The mapping from pixels to points in the complex plane,
+/// together with the information needed to mirror and flip the image.
+pub(crate) struct PixelGrid {
+    pub(crate) start_real: f64,
+    pub(crate) start_imag: f64,
+    pub(crate) real_delta: f64,
+    pub(crate) imag_delta: f64,
+    pub(crate) y_resolution: usize,
+    /// Whether the grid is computed for the conjugate of the frame and flipped into place.
+    pub(crate) need_to_flip: bool,
+    /// Whether pixels on one side of the real axis are copied from the other side.
+    pub(crate) mirror: bool,
+    /// Twice the index of the pixel on the real axis, rounded to the nearest integer.
+    pub(crate) mirror_axis: f64,
+    /// Whether the real axis lies on the center or the edge of a pixel, rather than only near it.
+    pub(crate) axis_on_grid: bool,
+}
+
+impl PixelGrid {
+    pub(crate) fn new(
+        render_parameters: &RenderParameters,
+        render_region: Frame,
+        mask: Option<&Mask>,
+    ) -> Self {
+        // The distance between adjacent pixels.
+        // Pixel (x, y), counted from the bottom left corner, samples the point at its center,
+        // (start_real + x * real_delta) + (start_imag + y * imag_delta)i
+        // regardless of whether the image is mirrored or flipped,
+        // which keeps the sampled points consistent between frames that share a pixel grid
+        // and makes an image that is a single pixel wide or high sample the middle of the frame.
+        let real_delta = render_region.real_distance / f64::from(render_parameters.x_resolution);
+        let imag_delta = render_region.imag_distance / f64::from(render_parameters.y_resolution);
+
+        // Conjugating c also conjugates its orbit, which swaps the halves of the binary decomposition.
+        let symmetric_under_conjugation = render_parameters
+            .formula
+            .symmetry(render_parameters.exponent)
+            .conjugation
+            && render_parameters.exterior != Exterior::BinaryDecomposition;
+
+        // True if the image contains the real axis, false otherwise.
+        // If the image contains the real axis we want to mirror
+        // the result of the largest half on to the smallest.
+        // Pixels that share samples with their neighbours are not computed one at a time,
+        // so they can not be mirrored either.
+        let mirror = render_parameters.mirroring
+            && mask.is_none()
+            && render_parameters.reconstruction_filter == ReconstructionFilter::Box
+            && symmetric_under_conjugation
+            && render_region.center_imag.abs() < render_region.imag_distance / 2.0;
+
+        // One way of doing this is to always assume that the half with negative
+        // imaginary part is the larger one. If the assumption is false
+        // we only need to flip the image vertically to get the
+        // correct result since it is symmetric under conjugation.
+        let need_to_flip = symmetric_under_conjugation && render_region.center_imag > 0.0;
+        // The pixels are written to the band in reverse order when flipping,
+        // so the bottom pixel of the conjugated grid ends up at the top of the image.
+        let center_imag = if need_to_flip {
+            -render_region.center_imag
+        } else {
+            render_region.center_imag
+        };
+        let start_imag = center_imag - (render_region.imag_distance - imag_delta) / 2.0;
+
+        // Pixel y and pixel `mirror_axis - y` sample conjugate points, so we only
+        // compute the pixels below the axis and mirror them on to the ones above it.
+        // Working with indices rather than the sampled values means that rounding errors
+        // can not change which pixels are mirrored.
+        let exact_mirror_axis = -2.0 * start_imag / imag_delta;
+        let mirror_axis = exact_mirror_axis.round();
+        // If the real axis does not lie on or halfway between pixels the mirrored pixels
+        // would be shifted compared to computing them, which would make the image depend on
+        // where the frame starts, so then we compute everything.
+        let axis_on_grid = (exact_mirror_axis - mirror_axis).abs() < 1e-6;
+        let mirror = mirror && axis_on_grid;
+
+        Self {
+            start_real: render_region.center_real
+                - (render_region.real_distance - real_delta) / 2.0,
+            start_imag,
+            real_delta,
+            imag_delta,
+            y_resolution: render_parameters.y_resolution.into(),
+            need_to_flip,
+            mirror,
+            mirror_axis,
+            axis_on_grid,
+        }
+    }
+
+    /// Returns the index in the computed grid of the pixel with the given index
+    /// in its band, counted from the bottom of the band.
+    pub(crate) const fn grid_index(&self, pixel_index: usize) -> usize {
+        if self.need_to_flip {
+            self.y_resolution - 1 - pixel_index
+        } else {
+            pixel_index
+        }
+    }
+
+    /// Returns the region of the complex plane that is sampled by the pixel in the given band
+    /// with the given index in the computed grid.
+    pub(crate) fn pixel_region(&self, band_index: usize, grid_index: usize) -> Frame {
+        // This is the real value of c for this entire band.
+        let c_real = self.start_real + self.real_delta * (band_index as f64);
+
+        // The pixel on the real axis is snapped to it, since rounding errors
+        // would otherwise make it depend on where the frame starts.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let c_imag = if self.axis_on_grid
+            && self.mirror_axis >= 0.0
+            && 2 * grid_index == self.mirror_axis as usize
+        {
+            0.0
+        } else {
+            self.start_imag + self.imag_delta * (grid_index as f64)
+        };
+
+        Frame::new(c_real, c_imag, self.real_delta, self.imag_delta)
+    }
+}
+
+/// What is worked out about the whole view before its pixels are colored.
+pub(crate) struct ViewPasses {
+    pub(crate) gradient_field: Option<GradientField>,
+    pub(crate) colorizer: Colorizer,
+}
+
+/// Computes the colors of the pixels in a segment of a y-axis band of the image,
+/// starting at the pixel with index `first_pixel` counted from the bottom of the band.
+/// Pixels can only be copied from their mirror images in the same segment. The others compute the color
+/// of their mirror image instead, so any segment gets the same colors as the whole band would,
+/// but mirrored images are fastest to render in whole bands.
+pub(crate) fn color_band_segment(
+    render_parameters: &RenderParameters,
+    grid: &PixelGrid,
+    passes: &ViewPasses,
+    mask: Option<&Mask>,
+    band_index: usize,
+    first_pixel: usize,
+    segment: &mut [u8],
+) {
+    let bytes_per_pixel = usize::from(render_parameters.color_type.bytes_per_pixel());
+
+    let fill = mask.map(|mask| mask.fill_bytes(render_parameters.color_type));
+
+    if let (Some(mask), Some(fill)) = (mask, &fill) {
+        if !mask.band_is_active(band_index) {
+            // Nothing in this band should be computed, so we just fill it.
+            for pixel in segment.chunks_exact_mut(bytes_per_pixel) {
+                pixel.copy_from_slice(fill);
+            }
+            return;
+        }
+    }
+
+    if render_parameters.reconstruction_filter != ReconstructionFilter::Box {
+        reconstruction::filter_band_segment(
+            render_parameters,
+            grid,
+            passes,
+            mask,
+            band_index,
+            first_pixel,
+            segment,
+        );
+        return;
+    }
+
+    let pixels = segment.len() / bytes_per_pixel;
+    let segment_range = first_pixel..(first_pixel + pixels);
+
+    // Returns the index of a pixel in the segment from its index in the computed grid.
+    let position_in_segment = |grid_index: usize| {
+        // Flipping is its own inverse.
+        let pixel_index = grid.grid_index(grid_index);
+        segment_range
+            .contains(&pixel_index)
+            .then(|| (pixel_index - first_pixel) * bytes_per_pixel)
+    };
+
+    // The pixels are visited in the order of the computed grid,
+    // so that the pixels below the real axis are computed before they are mirrored.
+    let grid_indices = if grid.need_to_flip {
+        (grid.y_resolution - segment_range.end)..(grid.y_resolution - segment_range.start)
+    } else {
+        segment_range.clone()
+    };
+
+    for grid_index in grid_indices {
+        let Some(offset) = position_in_segment(grid_index) else {
+            unreachable!("the grid indices are chosen to be in the segment");
+        };
+
+        if let (Some(mask), Some(fill)) = (mask, &fill) {
+            // The mask is looked up at the pixel's final position.
+            if !mask.is_active(band_index, first_pixel + offset / bytes_per_pixel) {
+                segment[offset..(offset + bytes_per_pixel)].copy_from_slice(fill);
+                continue;
+            }
+        }
+
+        // The index in the computed grid of the pixel that this one is the mirror image of, if any.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let mirror_index = (grid.mirror
+            && grid_index as f64 > grid.mirror_axis / 2.0
+            && grid_index as f64 <= grid.mirror_axis)
+            .then(|| grid.mirror_axis as usize - grid_index);
+
+        // If this pixel is the mirror image of one that has already been computed
+        // we `memmove` the data from that pixel into this one.
+        if let Some(source) = mirror_index.and_then(position_in_segment) {
+            segment.copy_within(source..(source + bytes_per_pixel), offset);
+        } else {
+            // Compute the pixel color as normal by iteration. If the pixel it is the mirror image of
+            // is not in the segment, that pixel is computed instead, so that this one gets the same color
+            // as when it is mirrored.
+            let index = mirror_index.unwrap_or(grid_index);
+            let direction = passes
+                .gradient_field
+                .as_ref()
+                .and_then(|field| field.direction(band_index, index));
+            let color = pixel_color(
+                grid,
+                (band_index, index),
+                direction,
+                &passes.colorizer,
+                render_parameters,
+            );
+
+            // and `memcpy` it to the correct place.
+            segment[offset..(offset + bytes_per_pixel)].copy_from_slice(color.as_raw());
+        }
+    }
+}
+
+/// Computes the escape speed for samples in a grid inside
+/// the region of the pixel with the given band and grid index, works out the color of each sample and
+/// returns the average color as an sRGB value. If x is the center
+/// of the pixel region and `sqrt_samples_per_pixel` = 3,
+/// then the dots are also sampled:
+///
+/// ```text
+///  real_distance
+///    -------
+///    .  .  .  |
+///    .  x  .  | imag_distance
+///    .  .  .  |
+/// ```
+///
+/// The gap between the sample points at the edge and the
+/// edge of the pixel is the same as between the points.
+///
+/// N.B.: if `render_parameters.sqrt_samples_per_pixel` is even the center of
+/// the pixel is never sampled, and if it is 1 no super
+/// sampling is done (only the center is sampled).
+///
+/// If a direction is given the samples are instead spread along it,
+/// see [`SamplePlacement::Gradient`]. Other placements move the samples as well, see [`SamplePlacement`].
+///
+/// The samples are colored by the given colorizer.
+pub(crate) fn pixel_color(
+    grid: &PixelGrid,
+    pixel: (usize, usize),
+    gradient_direction: Option<(f64, f64)>,
+    colorizer: &Colorizer,
+    render_parameters: &RenderParameters,
+) -> Pixel<u8> {
+    pixel_color_and_first_sample(
+        grid,
+        pixel,
+        gradient_direction,
+        colorizer,
+        render_parameters,
+    )
+    .0
+}
+
+/// Works like [`pixel_color`], but also returns how the first sample of the pixel,
+/// the one closest to its center, escaped.
+pub(crate) fn pixel_color_and_first_sample(
+    grid: &PixelGrid,
+    pixel: (usize, usize),
+    gradient_direction: Option<(f64, f64)>,
+    colorizer: &Colorizer,
+    render_parameters: &RenderParameters,
+) -> (Pixel<u8>, EscapeSample) {
+    // Initialize the pixel color as black.
+    let mut color = LinearRGB::default();
+    // `samples` can be a u16 since the maximum number of samples is u8::MAX^2 which is less than u16::MAX
+    let mut samples: u16 = 0;
+    // The number of samples that are inside the set, only used if the exterior is transparent.
+    let mut inside_samples: u16 = 0;
+    let mut first_sample = None;
+
+    let aborted = sample_pixel(
+        grid.pixel_region(pixel.0, pixel.1),
+        sample_offsets(
+            render_parameters.sqrt_samples_per_pixel.get(),
+            render_parameters.sample_placement,
+            render_parameters.seed,
+            gradient_direction,
+            (pixel.0, grid.grid_index(pixel.1)),
+        ),
+        colorizer,
+        render_parameters,
+        |sample| {
+            color += sample.color;
+            inside_samples += u16::from(sample.escape.is_inside());
+            samples += 1;
+            first_sample.get_or_insert(sample.escape);
+        },
+    );
+
+    if render_parameters.show_ssaa_region && aborted {
+        color = [150.0 / 255.0, 75.0 / 255.0, 0.0].into();
+    }
+
+    (
+        colorizer.pixel(color, f64::from(samples), f64::from(inside_samples)),
+        first_sample.expect("every pixel has at least one sample"),
+    )
+}
+
+/// A colored sample of a pixel.
+pub(crate) struct Sample {
+    /// The offset of the sample from the center of the pixel, in units of the distance between pixels.
+    pub(crate) offset: (f64, f64),
+    pub(crate) color: LinearRGB,
+    pub(crate) escape: EscapeSample,
+}
+
+/// Computes the colors of the samples at the given offsets from the center of the pixel region
+/// and hands them to `add_sample` one at a time, see [`sample_escapes`]. Returns whether supersampling was aborted.
+pub(crate) fn sample_pixel(
+    pixel_region: Frame,
+    offsets: impl Iterator<Item = (f64, f64)>,
+    colorizer: &Colorizer,
+    render_parameters: &RenderParameters,
+    mut add_sample: impl FnMut(Sample),
+) -> bool {
+    sample_escapes(
+        pixel_region,
+        offsets,
+        render_parameters,
+        |c_re, c_im| potential_in_precision(c_re, c_im, render_parameters),
+        |offset, sample| {
+            add_sample(Sample {
+                offset,
+                color: colorizer.color(&sample),
+                escape: sample,
+            });
+        },
+    )
+}
+
+/// Iterates the samples at the given offsets from the center of the pixel region with `escape`,
+/// which is given the real and imaginary parts of c, and hands how they escape to `add_sample` one at a time. The samples closest to the center of the pixel
+/// should be given first, since supersampling is aborted once a sample is found to be far from the set,
+/// or with adaptive supersampling once the first samples are found to agree closely.
+/// Returns whether that happened.
+pub(crate) fn sample_escapes(
+    pixel_region: Frame,
+    offsets: impl Iterator<Item = (f64, f64)>,
+    render_parameters: &RenderParameters,
+    mut escape: impl FnMut(f64, f64) -> Escape,
+    mut add_sample: impl FnMut((f64, f64), EscapeSample),
+) -> bool {
+    let binary_decomposition = render_parameters.exterior == Exterior::BinaryDecomposition;
+    let mut variance = SampleVariance::default();
+
+    for (rowoffset, coloffset) in offsets {
+        // Compute escape speed of point.
+        // We use the potential instead of the number of
+        // iterations in order to reduce color banding.
+        let c_re = snap_to_axis(
+            pixel_region.center_real + rowoffset * pixel_region.real_distance,
+            pixel_region.real_distance,
+        );
+        let c_im = snap_to_axis(
+            pixel_region.center_imag + coloffset * pixel_region.imag_distance,
+            pixel_region.imag_distance,
+        );
+        // Points inside the set are shaded in grayscale if an interior shading is used.
+        let sample = match escape(c_re, c_im) {
+            Escape::Escaped { escape_speed, z } => EscapeSample::Escaped {
+                escape_speed,
+                below_real_axis: z.im < 0.0,
+            },
+            Escape::Inside(provenance) => EscapeSample::Inside {
+                brightness: interior_brightness(
+                    Complex::new(c_re, c_im),
+                    provenance,
+                    pixel_region.imag_distance,
+                    render_parameters,
+                ),
+            },
+        };
+        add_sample((rowoffset, coloffset), sample);
+
+        // The cells of the binary decomposition have sharp edges everywhere, so it is always supersampled.
+        if binary_decomposition {
+            continue;
+        }
+        // Both ways of deciding to stop use the escape speed before the palette is stretched.
+        if let Some(threshold) = render_parameters.adaptive_supersampling {
+            variance.add(sample.escape_speed());
+            if variance.count() == PILOT_SAMPLES && variance.get() <= threshold.get() {
+                return true;
+            }
+        } else if let Some(cutoff) = render_parameters.ssaa_cutoff {
+            // If we are far from the fractal we do not need to supersample.
+            if sample.escape_speed() > cutoff.get() {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Returns zero if the coordinate of a sample is within a tiny fraction of a pixel of it,
+/// and otherwise the coordinate unchanged.
+///
+/// Samples on the axes end up a few ulps to either side of them depending on where the frame starts,
+/// since their coordinates are differences of much larger numbers. Points on the axes such as
+/// -0.75 and ±i are among the most sensitive ones of the set, so that would make overlapping frames
+/// disagree about them, and break the symmetry of the image under conjugation.
+fn snap_to_axis(coordinate: f64, pixel_size: f64) -> f64 {
+    if coordinate.abs() < 1e-6 * pixel_size {
+        0.0
+    } else {
+        coordinate
+    }
+}
+
+/// Returns the orbit of the point `c_re + c_im*i` under the Mandelbrot function z -> z^2 + c,
+/// as the `(real, imaginary)` parts of z_1 = c, z_2, z_3 and so on.
+/// The orbit ends with the first point outside the default [`EscapeRadius`],
+/// or after `max_iterations` points if it never leaves it.
+///
+/// Unlike [`iterate`](crate::iterate) every point is iterated, including those inside the main cardioid
+/// and period 2 bulb, so the orbit has the same length as the number of iterations that
+/// [`iterate`](crate::iterate) reports.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::orbit;
+/// # use core::num::NonZeroU64;
+/// const MAXITERS: NonZeroU64 = NonZeroU64::new(10).unwrap();
+/// // The orbit of -2 ends up at 2 and stays there.
+/// let points = orbit(-2.0, 0.0, MAXITERS);
+/// assert_eq!(points.len(), 10);
+/// assert_eq!(points[..3], [(-2.0, 0.0), (2.0, 0.0), (2.0, 0.0)]);
+///
+/// // The orbit of 1 grows quickly and escapes.
+/// assert_eq!(orbit(1.0, 0.0, MAXITERS), [(1.0, 0.0), (2.0, 0.0), (5.0, 0.0), (26.0, 0.0)]);
+/// ```
+#[must_use]
+pub fn orbit(c_re: f64, c_im: f64, max_iterations: NonZeroU64) -> Vec<(f64, f64)> {
+    let bailout_sqr = EscapeRadius::DEFAULT.squared();
+    let mut points = Vec::new();
+    let (mut z_re, mut z_im) = (c_re, c_im);
+    for _ in 0..max_iterations.get() {
+        points.push((z_re, z_im));
+        if z_re * z_re + z_im * z_im > bailout_sqr {
+            break;
+        }
+        (z_re, z_im) = (z_re * z_re - z_im * z_im + c_re, 2.0 * z_re * z_im + c_im);
+    }
+    points
+}
+
+/// Calls [`potential`] with the point converted to the [`Precision`] of the render.
+pub(crate) fn potential_in_precision(
+    c_re: f64,
+    c_im: f64,
+    render_parameters: &RenderParameters,
+) -> Escape {
+    match render_parameters.precision {
+        Precision::Single => potential(f32::from_f64(c_re), f32::from_f64(c_im), render_parameters),
+        Precision::Double => potential(c_re, c_im, render_parameters),
+        Precision::Fixed => potential(
+            Fixed::from_f64(c_re),
+            Fixed::from_f64(c_im),
+            render_parameters,
+        ),
+    }
+}
+
+/// Whether a point escaped, and how it was found to be inside the set if it did not.
+pub(crate) enum Escape {
+    /// The point is outside the set, with the given escape speed and final value of z.
+    Escaped {
+        escape_speed: f64,
+        z: Complex,
+    },
+    Inside(InteriorProvenance),
+}
+
+/// Returns a value kind of like the potential function of the Mandelbrot set.
+/// Maps the result of [`iterate`] smoothly to a number between 0 (close to the set) and 1 (far outside),
+/// or returns how the point was found to be inside the set.
+/// The iteration is done in the precision of `F`, but the result is always an `f64`.
+#[must_use]
+pub(crate) fn potential<F: Float>(
+    c_re: F,
+    c_im: F,
+    render_parameters: &RenderParameters,
+) -> Escape {
+    let exponent = render_parameters.exponent;
+    if in_checked_bulb(c_re, c_im, render_parameters) {
+        return Escape::Inside(InteriorProvenance::KnownInterior);
+    }
+    let (iterations, z_re, z_im) = match &render_parameters.formula {
+        formula @ (Formula::Mandelbrot | Formula::Mandelbar)
+            if render_parameters.derivative_bailout =>
+        {
+            iterate_with_derivative_bailout(
+                c_re,
+                c_im,
+                *formula == Formula::Mandelbar,
+                exponent,
+                render_parameters.max_iterations,
+                render_parameters.escape_radius,
+            )
+        }
+        Formula::Mandelbrot => iterate_mandelbrot(
+            c_re,
+            c_im,
+            exponent,
+            render_parameters.max_iterations,
+            render_parameters.escape_radius,
+            render_parameters.shortcuts,
+        ),
+        formula => formula.iterate_with_escape_radius(
+            c_re,
+            c_im,
+            exponent,
+            render_parameters.max_iterations,
+            render_parameters.escape_radius,
+        ),
+    };
+
+    escape_of_orbit(
+        iterations,
+        Complex::new(z_re.to_f64(), z_im.to_f64()),
+        render_parameters,
+    )
+}
+
+/// Whether the point is in one of the [`BulbChecks`] of the render,
+/// so that it can be taken to be inside the set without iterating it.
+pub(crate) fn in_checked_bulb<F: Float>(
+    c_re: F,
+    c_im: F,
+    render_parameters: &RenderParameters,
+) -> bool {
+    render_parameters.shortcuts
+        && render_parameters.exponent == Exponent::TWO
+        && render_parameters.formula == Formula::Mandelbrot
+        && render_parameters
+            .bulb_checks
+            .contains(c_re.to_f64(), c_im.to_f64())
+}
+
+/// Returns how a point escaped from the number of iterations and final z
+/// that its iteration with the render parameters resulted in, see [`potential`].
+pub(crate) fn escape_of_orbit(
+    iterations: u64,
+    z: Complex,
+    render_parameters: &RenderParameters,
+) -> Escape {
+    let exponent = render_parameters.exponent;
+    let mag_sqr = z.mag_sqr();
+
+    if iterations == render_parameters.max_iterations.get() {
+        // We label all points that could not be excluded as inside the set
+        // This also avoids using the potentially undefined magnitude squared
+        // for numbers that can be computed without iteration.
+        Escape::Inside(if mag_sqr.is_nan() {
+            InteriorProvenance::KnownInterior
+        } else {
+            InteriorProvenance::MaxIterations { mag_sqr }
+        })
+    } else {
+        Escape::Escaped {
+            escape_speed: render_parameters.smoothing.escape_speed(
+                iterations,
+                render_parameters.max_iterations,
+                mag_sqr,
+                render_parameters.formula.degree(exponent),
+                render_parameters
+                    .formula
+                    .smoothing_iterations(render_parameters.escape_radius),
+                render_parameters.smoothing_offset,
+            ),
+            z,
+        }
+    }
+}
+
+/// Contains information about the mandelbrot image
+/// that is relevant to the rendering process.
+///
+/// With the `serde` feature it can be serialized, which fails for [`Formula::Custom`] fractals.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RenderParameters {
+    /// The width of the image in pixels.
+    pub x_resolution: U32AndUsize,
+    /// The height of the image in pixels.
+    pub y_resolution: U32AndUsize,
+    /// Points whose orbits have not escaped after this many iterations are taken to be inside the set.
+    pub max_iterations: NonZeroU64,
+    /// The square root of the number of samples that are taken in every pixel,
+    /// e.g. 3 takes a 3 by 3 grid of samples. 1 turns supersampling off.
+    pub sqrt_samples_per_pixel: NonZeroU8,
+    /// Whether the image is grayscale or in color, and whether it has an alpha channel.
+    pub color_type: SupportedColorType,
+    /// The iteration z -> f(z, c) whose set is rendered.
+    pub formula: Formula,
+    /// The power of z in the formula, e.g. 3 renders the Multibrot set z -> z^3 + c.
+    pub exponent: Exponent,
+    /// Maps the escape speeds of the points to colors. Not used by grayscale images.
+    pub palette: Palette,
+    /// The kind of numbers that the points are iterated in.
+    pub precision: Precision,
+    /// How the image is split into units of work for the threads that render it.
+    pub scheduling: Scheduling,
+    /// How the points inside the set are colored.
+    pub interior_shading: InteriorShading,
+    /// How the points outside the set are colored.
+    pub exterior: Exterior,
+    /// Which components of the Mandelbrot set besides the main cardioid and period 2 bulb
+    /// are checked before a point is iterated. Only used if `shortcuts` is true.
+    pub bulb_checks: BulbChecks,
+    /// The range of escape speeds that the palette is stretched over.
+    /// Escape speeds outside of it get the color at the nearest end of the palette.
+    pub escape_speed_range: EscapeSpeedRange,
+    /// How carefully the pixels are computed, trading accuracy for speed.
+    pub quality: RenderQuality,
+    /// Where the samples are placed within every pixel. Only used if `sqrt_samples_per_pixel` is above 1.
+    pub sample_placement: SamplePlacement,
+    /// Seeds the pseudo-random numbers of the render: where [`SamplePlacement::Jittered`] places the samples
+    /// within their pixels, and how far the points of a Nebulabrot are shifted, see
+    /// [`render_nebulabrot`](crate::render_nebulabrot). The same seed always gives the same image,
+    /// and images with different seeds have independent noise.
+    /// Renders without pseudo-random numbers are not affected by it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub seed: u64,
+    /// How the escape speeds of grayscale images are mapped to brightness.
+    pub grayscale_mode: GrayscaleMode,
+    /// How the samples are combined into pixels. Ignored by [`RenderQuality::Draft`] renders.
+    pub reconstruction_filter: ReconstructionFilter,
+    /// Orbits that leave the disk with this radius around the origin have escaped.
+    pub escape_radius: EscapeRadius,
+    /// How the iteration counts of escaping points are turned into escape speeds.
+    pub smoothing: Smoothing,
+    /// Subtracted from the smoothed iteration count of escaping points before it is turned into an escape speed,
+    /// which shifts the colors of the palette along the bands of the image. Only used by [`Smoothing::Classic`].
+    pub smoothing_offset: f64,
+    /// Added to the position in the palette of the escape speed of every escaping point, wrapping around
+    /// at the end of the palette. Changing it over the frames of an animation cycles the colors of the palette,
+    /// and since it only affects the coloring it does not require the image to be computed again.
+    pub palette_offset: f64,
+    /// Stop iterating points whose orbits have contracted enough to be attracted to a cycle,
+    /// and take them to be inside the set. Much faster for views that contain a lot of the interior,
+    /// but a few points right outside the set are also taken to be inside.
+    /// Only used by [`Formula::Mandelbrot`] and [`Formula::Mandelbar`].
+    pub derivative_bailout: bool,
+    /// The share of the time that the threads rendering the image spend working.
+    /// Renders with a lower limit take longer, but keep the processor cooler.
+    pub cpu_limit: CpuLimit,
+    /// If given, the first few samples of every pixel are taken, and the rest of them only if
+    /// the variance of their escape speeds is larger than the threshold.
+    /// Otherwise supersampling stops at the first sample that is far enough from the set, see `ssaa_cutoff`.
+    pub adaptive_supersampling: Option<VarianceThreshold>,
+    /// Supersampling of a pixel stops at the first sample whose escape speed is above the cutoff,
+    /// and every sample is taken if there is none. Ignored by adaptively supersampled renders.
+    /// At low resolutions the default cutoff can clip into the thinnest parts of the set.
+    pub ssaa_cutoff: Option<SsaaCutoff>,
+    /// Color the pixels whose supersampling was stopped early orange/brown, to show the region
+    /// that is not fully supersampled.
+    pub show_ssaa_region: bool,
+    /// Only compute the larger half of views that contain the real axis of fractals
+    /// that are symmetric under conjugation, and mirror it onto the other half.
+    pub mirroring: bool,
+    /// Skip iterating the points of the Mandelbrot set that are known to be inside it:
+    /// the main cardioid, the period 2 bulb and the bulbs of `bulb_checks`.
+    /// This does not change the image, only how long it takes to render, and neither does `mirroring`
+    /// unless `ssaa_cutoff` makes pixels stop supersampling at a sample that is not mirrored.
+    pub shortcuts: bool,
+}
+
+impl RenderParameters {
+    /// Creates the parameters of a full quality render of the Mandelbrot set in the classic palette
+    /// with the given resolution, maximum number of iterations, supersampling and color type.
+    ///
+    /// Every other field has the default of its type, or the value that changes the image the least:
+    /// the palette covers the full range of escape speeds and is not offset, the seed is 0,
+    /// the smoothing offset is [`DEFAULT_SMOOTHING_OFFSET`],
+    /// points are iterated until they escape or reach the maximum number of iterations,
+    /// and supersampling stops at [`SsaaCutoff::DEFAULT`] without showing where.
+    /// The image is mirrored and the points known to be inside the set are skipped where possible,
+    /// and the render threads are not throttled.
+    ///
+    /// # Errors
+    /// Returns [`RenderError::ImageTooLarge`] if
+    /// - `x_resolution` does not fit in a `usize`, or
+    /// - `y_resolution` does not fit in a `usize`.
+    pub fn try_new(
+        x_resolution: NonZeroU32,
+        y_resolution: NonZeroU32,
+        max_iterations: NonZeroU64,
+        sqrt_samples_per_pixel: NonZeroU8,
+        color_type: SupportedColorType,
+    ) -> Result<Self, RenderError> {
+        let too_large = |_| RenderError::ImageTooLarge {
+            x_resolution: x_resolution.get(),
+            y_resolution: y_resolution.get(),
+            color_type,
+        };
+        Ok(Self {
+            x_resolution: x_resolution.try_into().map_err(too_large)?,
+            y_resolution: y_resolution.try_into().map_err(too_large)?,
+            max_iterations,
+            sqrt_samples_per_pixel,
+            color_type,
+            formula: Formula::Mandelbrot,
+            exponent: Exponent::TWO,
+            palette: Palette::default(),
+            precision: Precision::Double,
+            scheduling: Scheduling::Automatic,
+            interior_shading: InteriorShading::Flat,
+            exterior: Exterior::Opaque,
+            bulb_checks: BulbChecks::Period2,
+            escape_speed_range: EscapeSpeedRange::FULL,
+            quality: RenderQuality::Full,
+            sample_placement: SamplePlacement::Grid,
+            seed: 0,
+            grayscale_mode: GrayscaleMode::Linear,
+            reconstruction_filter: ReconstructionFilter::Box,
+            escape_radius: EscapeRadius::DEFAULT,
+            smoothing: Smoothing::Classic,
+            smoothing_offset: DEFAULT_SMOOTHING_OFFSET,
+            palette_offset: 0.0,
+            derivative_bailout: false,
+            cpu_limit: CpuLimit::NONE,
+            adaptive_supersampling: None,
+            ssaa_cutoff: Some(SsaaCutoff::DEFAULT),
+            show_ssaa_region: false,
+            mirroring: true,
+            shortcuts: true,
+        })
+    }
+
+    /// Returns parameters that render the given region as fast as possible while keeping the resolution,
+    /// for use as a preview while the user is changing the view.
+    ///
+    /// The image is a grayscale potential field with no supersampling, at most
+    /// [`FAST_PREVIEW_MAX_ITERATIONS`] iterations and the lowest [`Precision`] that resolves its pixels,
+    /// rendered in [`RenderQuality::Draft`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::{Frame, Precision, RenderParameters, RenderQuality, FAST_PREVIEW_MAX_ITERATIONS};
+    /// # use color_space::SupportedColorType;
+    /// let params = RenderParameters::try_new(
+    ///     1920.try_into().unwrap(),
+    ///     1080.try_into().unwrap(),
+    ///     1000.try_into().unwrap(),
+    ///     3.try_into().unwrap(),
+    ///     SupportedColorType::Rgb8,
+    /// )
+    /// .unwrap();
+    /// let preview = params.fast_preview(Frame::new(-0.75, 0.0, 3.5, 2.0));
+    /// assert_eq!(preview.color_type, SupportedColorType::L8);
+    /// assert_eq!(preview.sqrt_samples_per_pixel.get(), 1);
+    /// assert_eq!(preview.max_iterations, FAST_PREVIEW_MAX_ITERATIONS);
+    /// assert_eq!(preview.precision, Precision::Single);
+    /// assert_eq!(preview.quality, RenderQuality::Draft);
+    /// ```
+    #[must_use]
+    pub fn fast_preview(&self, render_region: Frame) -> Self {
+        let mut preview = self.clone();
+        preview.color_type = SupportedColorType::L8;
+        preview.sqrt_samples_per_pixel = NonZeroU8::MIN;
+        preview.max_iterations = self.max_iterations.min(FAST_PREVIEW_MAX_ITERATIONS);
+        preview.precision = Precision::lowest_sufficient(render_region, self.y_resolution.into());
+        preview.quality = RenderQuality::Draft;
+        // Equalizing the brightness would cost an extra pass over the view.
+        preview.grayscale_mode = GrayscaleMode::Linear;
+        preview
+    }
+
+    /// The parameters of the small RGB images that the tests of the render qualities
+    /// compare with full renders.
+    #[cfg(test)]
+    pub(crate) fn test_image(quality: RenderQuality, sqrt_samples_per_pixel: u8) -> Self {
+        let mut params = Self::try_new(
+            150.try_into().unwrap(),
+            100.try_into().unwrap(),
+            200.try_into().unwrap(),
+            sqrt_samples_per_pixel.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        params.quality = quality;
+        params
+    }
+}
+
+#[cfg(test)]
+mod test_iteration {
+    use super::*;
+    use crate::iterate;
+
+    #[test]
+    fn check_orbits() {
+        let max_iterations = NonZeroU64::new(500).unwrap();
+        for (c_re, c_im) in [
+            (0.3, 0.5),
+            (-0.75, 0.1),
+            (-1.5, 0.0),
+            (0.25, 0.0),
+            (-0.1, 0.2),
+        ] {
+            let points = orbit(c_re, c_im, max_iterations);
+            let (iterations, z_re, z_im) = iterate(c_re, c_im, Exponent::TWO, max_iterations);
+            assert_eq!(points.len(), iterations as usize, "{c_re} + {c_im}i");
+            // The last point of an escaping orbit is where the iteration stopped.
+            if iterations < max_iterations.get() {
+                assert_eq!(points.last(), Some(&(z_re, z_im)));
+            }
+        }
+    }
+
+    #[test]
+    fn check_more_iterations_than_fit_in_u32() {
+        let max_iterations = NonZeroU64::new(4 * u64::from(u32::MAX)).unwrap();
+        let mut params = RenderParameters::try_new(
+            NonZeroU32::MIN,
+            NonZeroU32::MIN,
+            max_iterations,
+            NonZeroU8::MIN,
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        params.escape_radius = EscapeRadius::try_from(2.0).unwrap();
+
+        let mut previous_escape_speed = 1.0;
+        for (c_re, c_im) in [(-2.5, 0.0), (0.5, 0.5), (0.4, 0.3), (-0.75, 0.05)] {
+            // Only points that escape quickly are iterated, the rest would take minutes.
+            let (iterations, ..) = iterate(c_re, c_im, Exponent::TWO, 255.try_into().unwrap());
+            assert!(iterations < 255, "{c_re} + {c_im}i");
+            assert_eq!(
+                iterate(c_re, c_im, Exponent::TWO, max_iterations).0,
+                iterations
+            );
+
+            // Points that need more iterations to escape still get lower escape speeds,
+            // even though they differ by less than 10^-8.
+            let Escape::Escaped { escape_speed, .. } = potential(c_re, c_im, &params) else {
+                panic!("{c_re} + {c_im}i did not escape");
+            };
+            assert!(escape_speed < previous_escape_speed, "{c_re} + {c_im}i");
+            assert!(previous_escape_speed - escape_speed < 1e-8);
+            previous_escape_speed = escape_speed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_pixel_grid {
+    use super::*;
+    use image::GenericImageView;
+
+    fn parameters(x_resolution: u32, y_resolution: u32) -> RenderParameters {
+        RenderParameters::try_new(
+            x_resolution.try_into().unwrap(),
+            y_resolution.try_into().unwrap(),
+            255.try_into().unwrap(),
+            2.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn check_one_pixel_dimensions() {
+        // The pixels are a quarter wide, so every sampled point is exact and images that
+        // share pixels must agree on them, whether they are mirrored or not.
+        let square = render(
+            &parameters(9, 9),
+            Frame::new(-0.75, 0.125, 2.25, 2.25),
+            false,
+        );
+        for (x_resolution, y_resolution) in [(1, 1), (1, 9), (9, 1)] {
+            let frame = Frame::new(
+                -0.75,
+                0.125,
+                0.25 * f64::from(x_resolution),
+                0.25 * f64::from(y_resolution),
+            );
+            let image = render(&parameters(x_resolution, y_resolution), frame, false);
+            assert_eq!(image.dimensions(), (x_resolution, y_resolution));
+            // A single pixel samples the middle of the frame, so it is the middle pixel of the square.
+            let middle = square.crop_imm(
+                (9 - x_resolution) / 2,
+                (9 - y_resolution) / 2,
+                x_resolution,
+                y_resolution,
+            );
+            assert_eq!(image, middle, "{x_resolution}x{y_resolution}");
+        }
+    }
+
+    #[test]
+    fn check_disabled_mirroring_and_shortcuts() {
+        // The sampled points are exact, so the mirrored half is the same as the computed one
+        // as long as every sample is taken, rather than stopping at the first one that escapes quickly.
+        let mut params = parameters(9, 9);
+        params.ssaa_cutoff = None;
+        let frame = Frame::new(-0.75, 0.0, 2.25, 2.25);
+        assert!(PixelGrid::new(&params, frame, None).mirror);
+        let image = render(&params, frame, false);
+
+        params.mirroring = false;
+        assert!(!PixelGrid::new(&params, frame, None).mirror);
+        assert_eq!(render(&params, frame, false), image);
+
+        params.shortcuts = false;
+        assert_eq!(render(&params, frame, false), image);
+    }
+
+    #[test]
+    fn check_mirroring_at_pixel_boundaries() {
+        // The pixels are a quarter high, so the sampled points are exact when the real axis lies
+        // on the center or the edge of a pixel, and mirroring must not change the image.
+        // When it lies a little off them the image is not mirrored.
+        for (x_resolution, y_resolution) in [(9, 7), (9, 8), (10, 9), (10, 10), (7, 15), (8, 16)] {
+            let mut params = parameters(x_resolution, y_resolution);
+            params.ssaa_cutoff = None;
+            let imag_distance = 0.25 * f64::from(y_resolution);
+            let half = 0.5 * f64::from(y_resolution);
+            // How far above the bottom edge of the frame the real axis is, in pixels.
+            for axis in [0.5, 1.0, 1.5, half - 0.5, half, half + 0.5, half + 1.0] {
+                for (shift, mirrored) in [(0.0, true), (1e-3, false), (-1e-3, false)] {
+                    let axis = axis + shift;
+                    let frame = Frame::new(
+                        -0.75,
+                        imag_distance / 2.0 - 0.25 * axis,
+                        0.25 * f64::from(x_resolution),
+                        imag_distance,
+                    );
+                    let case = format!("{x_resolution}x{y_resolution}, axis at {axis}");
+                    params.mirroring = true;
+                    assert_eq!(
+                        PixelGrid::new(&params, frame, None).mirror,
+                        mirrored,
+                        "{case}"
+                    );
+                    let image = render(&params, frame, false);
+                    params.mirroring = false;
+                    assert_eq!(render(&params, frame, false), image, "{case}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_pixels_near_the_axis_are_not_snapped_to_it() {
+        // The real axis is 0.4 pixels above the center of the only pixel, which is rounded
+        // to the pixel itself, but its single sample must still be -0.75 - 0.05i, which escapes.
+        let mut params = parameters(1, 1);
+        params.sqrt_samples_per_pixel = 1.try_into().unwrap();
+        let off_axis = Frame::new(-0.75, -0.05, 0.25, 0.25);
+        let grid = PixelGrid::new(&params, off_axis, None);
+        assert!(!grid.axis_on_grid);
+        assert_eq!(grid.pixel_region(0, 0).center_imag, -0.05);
+        let image = render(&params, off_axis, false);
+        assert_ne!(
+            image,
+            render(&params, Frame::new(-0.75, 0.0, 0.25, 0.25), false)
+        );
+        params.mirroring = false;
+        assert_eq!(render(&params, off_axis, false), image);
+    }
+
+    #[test]
+    fn check_panorama() {
+        // 32:1, only a few pixels high.
+        let params = parameters(320, 10);
+        let on_axis = Frame::new(-0.75, 0.0, 3.2, 0.1);
+        let grid = PixelGrid::new(&params, on_axis, None);
+        assert!(grid.mirror);
+        assert_eq!(grid.mirror_axis, 9.0);
+        let image = render(&params, on_axis, false);
+        assert_eq!(image, image.flipv());
+
+        // The real axis is just below the frame, so nothing can be mirrored.
+        let above_axis = Frame::new(-0.75, 0.06, 3.2, 0.1);
+        assert!(!PixelGrid::new(&params, above_axis, None).mirror);
+        let image = render(&params, above_axis, false);
+        let all_active = Mask::new(
+            &DynamicImage::ImageLuma8(image::GrayImage::from_pixel(32, 1, image::Luma([255]))),
+            params.x_resolution,
+            params.y_resolution,
+            image::Rgb([0, 0, 0]),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            image,
+            render_masked(&params, above_axis, &all_active, false)
+        );
+
+        // Panoramas that are a single pixel high or wide work as well.
+        for (x_resolution, y_resolution) in [(320, 1), (1, 320)] {
+            let image = render(&parameters(x_resolution, y_resolution), on_axis, false);
+            assert_eq!(image.dimensions(), (x_resolution, y_resolution));
+        }
+    }
+}
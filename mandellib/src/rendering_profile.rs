@@ -0,0 +1,159 @@
+//! Frozen versions of the default settings that decide the colors of an image,
+//! so that an image can be rendered again pixel for pixel after the defaults have changed,
+//! e.g. at a higher resolution years later.
+//!
+//! The settings of a profile must never change once it has been released.
+//! Changing a default instead means adding a new profile with it and making that the latest one.
+
+use core::f64::consts::E;
+use core::fmt;
+use core::str::FromStr;
+
+use color_space::{BuiltinPalette, Palette};
+
+use crate::{EscapeRadius, RenderParameters, Smoothing};
+
+/// A version of the default smoothing, smoothing offset, escape radius and palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RenderingProfile {
+    /// Classic smoothing with an offset of e + 1, an escape radius of 6 and the classic palette.
+    #[default]
+    V1,
+}
+
+impl RenderingProfile {
+    pub const ALL: [Self; 1] = [Self::V1];
+
+    /// The profile whose settings are the defaults of [`RenderParameters::try_new`].
+    pub const LATEST: Self = Self::V1;
+
+    #[must_use]
+    pub const fn smoothing(self) -> Smoothing {
+        match self {
+            Self::V1 => Smoothing::Classic,
+        }
+    }
+
+    #[must_use]
+    pub const fn smoothing_offset(self) -> f64 {
+        match self {
+            Self::V1 => E + 1.0,
+        }
+    }
+
+    #[must_use]
+    pub fn escape_radius(self) -> EscapeRadius {
+        match self {
+            Self::V1 => EscapeRadius::try_from(6.0).expect("6 is a valid escape radius"),
+        }
+    }
+
+    #[must_use]
+    pub const fn palette(self) -> Palette {
+        match self {
+            Self::V1 => Palette::Builtin(BuiltinPalette::Classic),
+        }
+    }
+
+    /// Sets the smoothing, smoothing offset, escape radius and palette of the parameters to those of the profile.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::{RenderParameters, RenderingProfile};
+    /// # use color_space::SupportedColorType;
+    /// let mut params = RenderParameters::try_new(
+    ///     30.try_into().unwrap(),
+    ///     20.try_into().unwrap(),
+    ///     100.try_into().unwrap(),
+    ///     1.try_into().unwrap(),
+    ///     SupportedColorType::Rgb8,
+    /// )
+    /// .unwrap();
+    /// params.smoothing_offset = 0.0;
+    /// RenderingProfile::V1.apply(&mut params);
+    /// assert_eq!(params.smoothing_offset, std::f64::consts::E + 1.0);
+    /// ```
+    pub fn apply(self, render_parameters: &mut RenderParameters) {
+        render_parameters.smoothing = self.smoothing();
+        render_parameters.smoothing_offset = self.smoothing_offset();
+        render_parameters.escape_radius = self.escape_radius();
+        render_parameters.palette = self.palette();
+    }
+}
+
+impl fmt::Display for RenderingProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V1 => write!(f, "v1"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRenderingProfileError(String);
+
+impl fmt::Display for ParseRenderingProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = RenderingProfile::ALL
+            .iter()
+            .map(|profile| format!("\"{profile}\""))
+            .collect();
+        write!(
+            f,
+            "unknown rendering profile \"{}\", expected one of {}",
+            self.0,
+            names.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ParseRenderingProfileError {}
+
+impl FromStr for RenderingProfile {
+    type Err = ParseRenderingProfileError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|profile| profile.to_string() == s.trim().to_lowercase())
+            .ok_or_else(|| ParseRenderingProfileError(s.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod test_rendering_profile {
+    use super::*;
+
+    #[test]
+    fn check_latest_is_default() {
+        // If this fails a default has changed, which must be done by adding a new profile
+        // with the new default and making it the latest, so that older images can still be rendered.
+        let mut params = RenderParameters::try_new(
+            30.try_into().unwrap(),
+            20.try_into().unwrap(),
+            100.try_into().unwrap(),
+            1.try_into().unwrap(),
+            color_space::SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let defaults = params.clone();
+        params.smoothing = Smoothing::Banded;
+        params.smoothing_offset = 0.0;
+        params.escape_radius = EscapeRadius::try_from(1e3).unwrap();
+        params.palette = Palette::Curves(std::sync::Arc::new("r=s, g=s, b=s".parse().unwrap()));
+        RenderingProfile::LATEST.apply(&mut params);
+        assert_eq!(params.smoothing, defaults.smoothing);
+        assert_eq!(params.smoothing_offset, defaults.smoothing_offset);
+        assert_eq!(params.escape_radius, defaults.escape_radius);
+        assert_eq!(params.palette, defaults.palette);
+    }
+
+    #[test]
+    fn check_parsing() {
+        for profile in RenderingProfile::ALL {
+            assert_eq!(profile.to_string().parse(), Ok(profile));
+        }
+        assert_eq!(" V1".parse(), Ok(RenderingProfile::V1));
+        assert!("v0".parse::<RenderingProfile>().is_err());
+    }
+}
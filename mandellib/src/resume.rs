@@ -0,0 +1,329 @@
+//! Computing the escape data of an image again with a larger maximum number of iterations
+//! by continuing the orbits of its samples from where they stopped, instead of iterating them from the start.
+
+use core::mem::size_of;
+use core::num::NonZeroU64;
+
+use crate::escape_buffer::compute_samples;
+use crate::interior::InteriorProvenance;
+use crate::kernel::{iterate_mandelbrot, resume_mandelbrot};
+use crate::progress::NoProgress;
+use crate::renderer::{escape_of_orbit, in_checked_bulb};
+use crate::{
+    check_frame, compute_with_progress, Complex, Escape, EscapeBuffer, EscapeSample, Float,
+    Formula, Frame, Precision, Progress, RenderError, RenderParameters, SamplePlacement,
+};
+
+/// Where the orbit of a sample stopped.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Orbit {
+    /// The number of iterations and the final z that the iteration of the point resulted in.
+    Iterated { iterations: u64, z: Complex },
+    /// The point was found to be inside the set without iterating it,
+    /// by the cardioid check or the [`BulbChecks`](crate::BulbChecks) of the render.
+    KnownInterior,
+}
+
+/// The escape data of every sample of an image along with where the orbits of the samples stopped,
+/// so that it can be computed again with a larger maximum number of iterations without starting over,
+/// see [`compute_resumable`].
+#[derive(Debug, Clone)]
+pub struct ResumableEscapeBuffer {
+    buffer: EscapeBuffer,
+    /// Where the orbit of every sample of the buffer stopped, in the same order as the samples.
+    /// Empty if the orbits of the render can not be continued, see [`is_resumable`].
+    orbits: Vec<Orbit>,
+    render_parameters: RenderParameters,
+    render_region: Frame,
+}
+
+impl ResumableEscapeBuffer {
+    /// The number of bytes that each sample of the image takes up in the buffer at most.
+    pub const BYTES_PER_SAMPLE: usize = size_of::<EscapeSample>() + size_of::<Orbit>();
+
+    #[must_use]
+    pub const fn escape_buffer(&self) -> &EscapeBuffer {
+        &self.buffer
+    }
+
+    #[must_use]
+    pub fn into_escape_buffer(self) -> EscapeBuffer {
+        self.buffer
+    }
+
+    /// Computes the buffer again with the given maximum number of iterations.
+    ///
+    /// If the maximum is at least as large as the one that the buffer was computed with, the orbits of the samples
+    /// that had not escaped are continued from where they stopped, and the escape speeds of the rest are
+    /// recomputed from their final z, which gives the same result as computing the buffer from the start.
+    /// Otherwise, or if the orbits of the render can not be continued, the buffer is computed from the start.
+    /// The orbits can only be continued for [`Formula::Mandelbrot`] without the derivative bailout,
+    /// in single or double precision, and with samples that are not placed by the gradient of the image.
+    #[must_use]
+    pub fn resume(&self, max_iterations: NonZeroU64) -> Self {
+        self.try_resume_with_progress(max_iterations, &NoProgress)
+            .expect("the frame was checked when the buffer was computed, and the computation can not be cancelled")
+    }
+
+    /// Works like [`resume`](Self::resume), but reports the progress of the computation to `progress`,
+    /// see [`Progress`]. Every band of the image is a unit of work.
+    ///
+    /// # Errors
+    /// Returns an error if the progress cancelled the computation.
+    pub fn try_resume_with_progress(
+        &self,
+        max_iterations: NonZeroU64,
+        progress: &dyn Progress,
+    ) -> Result<Self, RenderError> {
+        let mut render_parameters = self.render_parameters.clone();
+        render_parameters.max_iterations = max_iterations;
+        if self.orbits.is_empty() || max_iterations < self.buffer.max_iterations() {
+            return try_compute_resumable_with_progress(
+                &render_parameters,
+                self.render_region,
+                progress,
+            );
+        }
+
+        let (buffer, orbits) = compute_samples(
+            &render_parameters,
+            self.render_region,
+            progress,
+            |pixel, sample, c_re, c_im| {
+                // The samples are placed the same way as before, so the first ones of every pixel
+                // are the ones that it had before. Pixels that are supersampled further get new ones.
+                let previous = self
+                    .buffer
+                    .sample_indices(pixel)
+                    .nth(sample)
+                    .map(|index| self.orbits[index]);
+                resumed_escape_in_precision(c_re, c_im, previous, &render_parameters)
+            },
+        );
+        if progress.is_cancelled() {
+            return Err(RenderError::Cancelled);
+        }
+        Ok(Self {
+            buffer,
+            orbits,
+            render_parameters,
+            render_region: self.render_region,
+        })
+    }
+}
+
+/// Works like [`compute`](crate::compute), but also keeps where the orbits of the samples stopped,
+/// so that the image can be computed with a larger maximum number of iterations
+/// by continuing them with [`ResumableEscapeBuffer::resume`].
+/// That takes up about twice the memory of an [`EscapeBuffer`].
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{compute, compute_resumable, Frame, RenderParameters};
+/// # use color_space::SupportedColorType;
+/// let mut params = RenderParameters::try_new(
+///     30.try_into().unwrap(),
+///     20.try_into().unwrap(),
+///     50.try_into().unwrap(),
+///     2.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+/// let buffer = compute_resumable(&params, frame);
+///
+/// // Only the points that had not escaped are iterated further.
+/// params.max_iterations = 200.try_into().unwrap();
+/// let resumed = buffer.resume(params.max_iterations);
+/// assert_eq!(resumed.escape_buffer(), &compute(&params, frame));
+/// ```
+#[must_use]
+pub fn compute_resumable(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+) -> ResumableEscapeBuffer {
+    let (buffer, orbits) = compute_orbits(render_parameters, render_region, &NoProgress);
+    ResumableEscapeBuffer {
+        buffer,
+        orbits,
+        render_parameters: render_parameters.clone(),
+        render_region,
+    }
+}
+
+/// Works like [`compute_resumable`], but checks that the frame can be rendered
+/// and reports the progress of the computation to `progress`, see [`Progress`].
+///
+/// # Errors
+/// Returns an error if the center of the frame is not finite, if its distances are not finite and positive,
+/// or if the progress cancelled the computation.
+pub fn try_compute_resumable_with_progress(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    progress: &dyn Progress,
+) -> Result<ResumableEscapeBuffer, RenderError> {
+    check_frame(render_region)?;
+    let (buffer, orbits) = compute_orbits(render_parameters, render_region, progress);
+    if progress.is_cancelled() {
+        return Err(RenderError::Cancelled);
+    }
+    Ok(ResumableEscapeBuffer {
+        buffer,
+        orbits,
+        render_parameters: render_parameters.clone(),
+        render_region,
+    })
+}
+
+/// Computes the escape data of the image along with where the orbits of its samples stopped,
+/// or without them if they can not be continued.
+fn compute_orbits(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    progress: &dyn Progress,
+) -> (EscapeBuffer, Vec<Orbit>) {
+    if is_resumable(render_parameters) {
+        compute_samples(
+            render_parameters,
+            render_region,
+            progress,
+            |_, _, c_re, c_im| resumed_escape_in_precision(c_re, c_im, None, render_parameters),
+        )
+    } else {
+        (
+            compute_with_progress(render_parameters, render_region, progress),
+            Vec::new(),
+        )
+    }
+}
+
+/// Whether the orbits of the samples of a render with the parameters can be continued.
+/// Other formulas and the derivative bailout keep more state than z, fixed point numbers can not be stored
+/// as an `f64` without rounding, and the samples that are placed by the gradient move when the
+/// maximum number of iterations changes.
+fn is_resumable(render_parameters: &RenderParameters) -> bool {
+    render_parameters.formula == Formula::Mandelbrot
+        && !render_parameters.derivative_bailout
+        && render_parameters.precision != Precision::Fixed
+        && render_parameters.sample_placement != SamplePlacement::Gradient
+}
+
+/// Works like [`resumed_escape`], but in the precision of the render parameters.
+fn resumed_escape_in_precision(
+    c_re: f64,
+    c_im: f64,
+    previous: Option<Orbit>,
+    render_parameters: &RenderParameters,
+) -> (Escape, Orbit) {
+    match render_parameters.precision {
+        Precision::Single => resumed_escape(
+            f32::from_f64(c_re),
+            f32::from_f64(c_im),
+            previous,
+            render_parameters,
+        ),
+        Precision::Double | Precision::Fixed => {
+            resumed_escape(c_re, c_im, previous, render_parameters)
+        }
+    }
+}
+
+/// Iterates the point c, or continues its orbit from where it stopped if it is given,
+/// and returns how it escaped along with where its orbit stopped this time.
+/// Gives the same escape as [`potential`](crate::renderer::potential) for the render parameters.
+fn resumed_escape<F: Float>(
+    c_re: F,
+    c_im: F,
+    previous: Option<Orbit>,
+    render_parameters: &RenderParameters,
+) -> (Escape, Orbit) {
+    let (iterations, z_re, z_im) = match previous {
+        Some(Orbit::KnownInterior) => {
+            return (
+                Escape::Inside(InteriorProvenance::KnownInterior),
+                Orbit::KnownInterior,
+            )
+        }
+        // The z of a single precision orbit is stored as an `f64` without rounding,
+        // so it converts back to exactly the same value.
+        Some(Orbit::Iterated { iterations, z }) => resume_mandelbrot(
+            c_re,
+            c_im,
+            (iterations, F::from_f64(z.re), F::from_f64(z.im)),
+            render_parameters.exponent,
+            render_parameters.max_iterations,
+            render_parameters.escape_radius,
+            render_parameters.shortcuts,
+        ),
+        None if in_checked_bulb(c_re, c_im, render_parameters) => {
+            return (
+                Escape::Inside(InteriorProvenance::KnownInterior),
+                Orbit::KnownInterior,
+            )
+        }
+        None => iterate_mandelbrot(
+            c_re,
+            c_im,
+            render_parameters.exponent,
+            render_parameters.max_iterations,
+            render_parameters.escape_radius,
+            render_parameters.shortcuts,
+        ),
+    };
+
+    let z = Complex::new(z_re.to_f64(), z_im.to_f64());
+    // Points that were skipped by the cardioid check have no final z.
+    let orbit = if z.re.is_nan() {
+        Orbit::KnownInterior
+    } else {
+        Orbit::Iterated { iterations, z }
+    };
+    (escape_of_orbit(iterations, z, render_parameters), orbit)
+}
+
+#[cfg(test)]
+mod test_resume {
+    use super::*;
+    use crate::{compute, Exponent, VarianceThreshold};
+    use color_space::SupportedColorType;
+
+    fn check_resumed_matches_computed(params: &RenderParameters, frame: Frame) {
+        let mut params = params.clone();
+        let buffer = compute_resumable(&params, frame);
+        assert_eq!(buffer.escape_buffer(), &compute(&params, frame));
+        for max_iterations in [120, 120, 1000, 30] {
+            params.max_iterations = NonZeroU64::new(max_iterations).unwrap();
+            let resumed = buffer.resume(params.max_iterations);
+            assert_eq!(resumed.escape_buffer(), &compute(&params, frame));
+        }
+    }
+
+    #[test]
+    fn check_resumed_buffers() {
+        let mut params = RenderParameters::try_new(
+            24.try_into().unwrap(),
+            16.try_into().unwrap(),
+            40.try_into().unwrap(),
+            3.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let frame = Frame::new(-0.75, 0.1, 3.0, 2.0);
+        check_resumed_matches_computed(&params, frame);
+        // The samples close to the set are supersampled further once they escape.
+        check_resumed_matches_computed(&params, Frame::new(-0.745, 0.11, 0.01, 0.007));
+
+        params.shortcuts = false;
+        check_resumed_matches_computed(&params, frame);
+        params.adaptive_supersampling = Some(VarianceThreshold::DEFAULT);
+        check_resumed_matches_computed(&params, frame);
+        params.precision = Precision::Single;
+        check_resumed_matches_computed(&params, frame);
+        params.exponent = Exponent::try_from(3).unwrap();
+        check_resumed_matches_computed(&params, frame);
+        // Orbits that can not be continued are computed from the start.
+        params.precision = Precision::Fixed;
+        check_resumed_matches_computed(&params, frame);
+    }
+}
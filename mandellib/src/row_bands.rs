@@ -0,0 +1,157 @@
+//! Rendering an image a band of rows at a time from the top, so that each band can be written
+//! to a file as soon as it is done and the whole image never has to be held in memory.
+
+use core::iter::FusedIterator;
+use core::num::NonZeroU32;
+
+use image::DynamicImage;
+
+use crate::tile::TileRenderer;
+use crate::{Frame, PixelRect, RenderError, RenderParameters};
+
+/// A band of whole rows of an image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowBand {
+    /// The index of the top row of the band, counted from the top of the image.
+    pub first_row: u32,
+    /// The rows of the band, which are as wide as the image.
+    pub image: DynamicImage,
+}
+
+/// An iterator over the bands of rows of an image, from the top down, which renders each band when it is asked for.
+/// Created by [`render_rows`].
+pub struct RowBands<'a> {
+    renderer: TileRenderer<'a>,
+    rows_per_band: u32,
+    next_row: u32,
+}
+
+/// Returns an iterator over the image that [`render`](crate::render) would render, in bands of
+/// `rows_per_band` rows from the top down. The last band is smaller if `rows_per_band`
+/// does not divide the vertical resolution.
+///
+/// Only one band is rendered at a time, and the pixels of every band are rendered in parallel.
+/// Every pixel is identical to the same pixel of the full image, except that
+/// [`RenderQuality::Draft`](crate::RenderQuality::Draft) renders compute every pixel,
+/// just like [`render_tile`](crate::render_tile).
+///
+/// # Errors
+/// Returns an error if the center of the frame is not finite, or if its distances are not finite and positive.
+/// Each band is an error if the memory for it can not be allocated.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{render, render_rows, Frame, RenderParameters};
+/// # use color_space::SupportedColorType;
+/// let params = RenderParameters::try_new(
+///     30.try_into().unwrap(),
+///     20.try_into().unwrap(),
+///     100.try_into().unwrap(),
+///     2.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+///
+/// let mut rows = Vec::new();
+/// for band in render_rows(&params, frame, 8.try_into().unwrap()).unwrap() {
+///     let band = band.unwrap();
+///     assert_eq!(band.first_row as usize, rows.len() / (30 * 3));
+///     rows.extend_from_slice(band.image.as_bytes());
+/// }
+/// assert_eq!(rows, render(&params, frame, false).as_bytes());
+/// ```
+pub fn render_rows(
+    render_parameters: &RenderParameters,
+    frame: Frame,
+    rows_per_band: NonZeroU32,
+) -> Result<RowBands<'_>, RenderError> {
+    Ok(RowBands {
+        renderer: TileRenderer::new(render_parameters, frame)?,
+        rows_per_band: rows_per_band.get(),
+        next_row: 0,
+    })
+}
+
+impl RowBands<'_> {
+    fn y_resolution(&self) -> u32 {
+        self.renderer.render_parameters().y_resolution.into()
+    }
+}
+
+impl Iterator for RowBands<'_> {
+    type Item = Result<RowBand, RenderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining_rows = self.y_resolution() - self.next_row;
+        if remaining_rows == 0 {
+            return None;
+        }
+        let first_row = self.next_row;
+        let tile = PixelRect {
+            x: 0,
+            y: first_row,
+            width: self.renderer.render_parameters().x_resolution.into(),
+            height: self.rows_per_band.min(remaining_rows),
+        };
+        self.next_row += tile.height;
+        Some(
+            self.renderer
+                .render(tile)
+                .map(|image| RowBand { first_row, image }),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let bands = (self.y_resolution() - self.next_row).div_ceil(self.rows_per_band);
+        // The number of rows fits in a usize.
+        (bands as usize, Some(bands as usize))
+    }
+}
+
+impl ExactSizeIterator for RowBands<'_> {}
+
+impl FusedIterator for RowBands<'_> {}
+
+#[cfg(test)]
+mod test_row_bands {
+    use super::*;
+    use crate::{render, GrayscaleMode, ReconstructionFilter, SamplePlacement};
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_bands_match_full_image() {
+        let mut params = RenderParameters::try_new(
+            24.try_into().unwrap(),
+            17.try_into().unwrap(),
+            150.try_into().unwrap(),
+            2.try_into().unwrap(),
+            SupportedColorType::L8,
+        )
+        .unwrap();
+        params.sample_placement = SamplePlacement::Gradient;
+        params.reconstruction_filter = ReconstructionFilter::Tent;
+        params.grayscale_mode = GrayscaleMode::Equalized;
+        for frame in [
+            Frame::new(-0.75, 0.0, 3.0, 2.0),
+            Frame::new(-0.75, 0.4, 3.0, 2.0),
+        ] {
+            let full = render(&params, frame, false);
+            let bands = render_rows(&params, frame, 5.try_into().unwrap()).unwrap();
+            assert_eq!(bands.len(), 4);
+
+            let mut next_row = 0;
+            for band in bands {
+                let band = band.unwrap();
+                assert_eq!(band.first_row, next_row);
+                assert_eq!(
+                    band.image,
+                    full.crop_imm(0, band.first_row, 24, band.image.height())
+                );
+                next_row += band.image.height();
+            }
+            assert_eq!(next_row, 17);
+        }
+    }
+}
@@ -0,0 +1,540 @@
+//! Where the supersamples of a pixel are placed within it.
+//!
+//! Most pixels of an image only contain a smooth change in color, and the ones that need supersampling
+//! are crossed by an edge, where the color changes quickly in one direction and barely along the edge.
+//! A uniform grid of n by n samples only resolves such an edge into n steps. Placing every sample
+//! at a different distance along the direction that the escape speed changes in resolves it into
+//! n^2 steps instead, for the same number of samples.
+//!
+//! The direction is estimated from the escape speeds at the centers of the neighboring pixels,
+//! which are computed in a first pass over the image.
+
+use core::fmt;
+use core::ops::Range;
+use core::str::FromStr;
+
+use itertools::{Either, Itertools};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    potential_in_precision, Escape, LowDiscrepancySequence, PixelGrid, RenderParameters,
+    INVERSE_GOLDEN_RATIO,
+};
+
+/// The smallest change in escape speed between neighboring pixels that is treated as an edge.
+/// Pixels where it changes less are supersampled with the uniform grid, which is at least as good
+/// for smooth changes in color.
+const MIN_GRADIENT: f64 = 0.05;
+
+/// How the supersamples of a pixel are placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SamplePlacement {
+    /// A uniform grid of samples.
+    #[default]
+    Grid,
+    /// Spread the samples along the direction that the escape speed changes in the most,
+    /// estimated from a first pass over the centers of the pixels, wherever it changes quickly.
+    /// Resolves straight edges into more steps for the same number of samples,
+    /// at the cost of one extra sample per pixel. How much that improves the image depends on the view,
+    /// since thin filaments do not have a direction that can be estimated from the neighboring pixels.
+    /// Has no effect on renders without supersampling or in [`RenderQuality::Draft`](crate::RenderQuality::Draft).
+    Gradient,
+    /// Take the samples at the first points of a low discrepancy sequence, shifted so that the first
+    /// sample is at the center of the pixel. Unlike the rows of a grid the samples never line up with
+    /// an edge, and every pixel is sampled at the same points so the image is the same every time.
+    Sequence(LowDiscrepancySequence),
+    /// Move every sample of the grid to a random point in its cell of the grid, which is different
+    /// for every pixel. Edges along the axes are then resolved into more steps than by the grid,
+    /// and patterns that line up with the grid turn into fine noise instead of moiré.
+    /// The random points only depend on the [`seed`](RenderParameters::seed) of the render
+    /// and the position of the pixel in the image, so the same seed always gives the same image,
+    /// however many threads render it. Pixels that are mirrored across the real axis are copies
+    /// of the pixels that they mirror, so their points are the mirror images of those pixels' points.
+    /// Has no effect on renders without supersampling.
+    Jittered,
+}
+
+impl SamplePlacement {
+    pub const ALL: [Self; 6] = [
+        Self::Grid,
+        Self::Gradient,
+        Self::Sequence(LowDiscrepancySequence::R2),
+        Self::Sequence(LowDiscrepancySequence::Halton),
+        Self::Sequence(LowDiscrepancySequence::Sobol),
+        Self::Jittered,
+    ];
+}
+
+impl fmt::Display for SamplePlacement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Grid => write!(f, "grid"),
+            Self::Gradient => write!(f, "gradient"),
+            Self::Sequence(sequence) => write!(f, "{sequence}"),
+            Self::Jittered => write!(f, "jittered"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSamplePlacementError(String);
+
+impl fmt::Display for ParseSamplePlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown sample placement \"{}\", expected \"grid\", \"gradient\", \"r2\", \"halton\", \"sobol\" or \"jittered\"",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseSamplePlacementError {}
+
+impl FromStr for SamplePlacement {
+    type Err = ParseSamplePlacementError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "grid" => Ok(Self::Grid),
+            "gradient" => Ok(Self::Gradient),
+            "jittered" => Ok(Self::Jittered),
+            other => other
+                .parse()
+                .map(Self::Sequence)
+                .map_err(|_| ParseSamplePlacementError(s.to_owned())),
+        }
+    }
+}
+
+/// The escape speeds at the centers of the pixels of a window of the computed grid,
+/// stored band by band.
+pub(crate) struct GradientField {
+    escape_speeds: Vec<f32>,
+    /// The bands and the indices in the computed grid that the window covers.
+    window_bands: Range<usize>,
+    window_indices: Range<usize>,
+    bands: usize,
+    band_length: usize,
+}
+
+impl GradientField {
+    /// Computes the escape speed at the center of every pixel of the grid
+    /// if the render places its samples by the gradient, and returns `None` otherwise.
+    pub(crate) fn new(render_parameters: &RenderParameters, grid: &PixelGrid) -> Option<Self> {
+        Self::new_window(
+            render_parameters,
+            grid,
+            0..usize::from(render_parameters.x_resolution),
+            0..usize::from(render_parameters.y_resolution),
+        )
+    }
+
+    /// Works like [`GradientField::new`], but only computes the escape speeds that are needed
+    /// for the directions at the given bands and indices in the computed grid.
+    pub(crate) fn new_window(
+        render_parameters: &RenderParameters,
+        grid: &PixelGrid,
+        bands: Range<usize>,
+        indices: Range<usize>,
+    ) -> Option<Self> {
+        if render_parameters.sample_placement != SamplePlacement::Gradient
+            || render_parameters.sqrt_samples_per_pixel.get() == 1
+        {
+            return None;
+        }
+        let band_count = usize::from(render_parameters.x_resolution);
+        let band_length = usize::from(render_parameters.y_resolution);
+        // The directions also need the escape speeds of the neighbouring pixels.
+        let window_bands = bands.start.saturating_sub(1)..(bands.end + 1).min(band_count);
+        let window_indices = indices.start.saturating_sub(1)..(indices.end + 1).min(band_length);
+        let escape_speeds = window_bands
+            .clone()
+            .into_par_iter()
+            .flat_map_iter(|band_index| {
+                window_indices.clone().map(move |grid_index| {
+                    let center = grid.pixel_region(band_index, grid_index);
+                    let escape = potential_in_precision(
+                        center.center_real,
+                        center.center_imag,
+                        render_parameters,
+                    );
+                    // The inside of the set is colored as if its escape speed was zero.
+                    #[allow(clippy::cast_possible_truncation)]
+                    match escape {
+                        Escape::Escaped { escape_speed, .. } => escape_speed as f32,
+                        Escape::Inside(_) => 0.0,
+                    }
+                })
+            })
+            .collect();
+        Some(Self {
+            escape_speeds,
+            window_bands,
+            window_indices,
+            bands: band_count,
+            band_length,
+        })
+    }
+
+    /// Returns the direction that the escape speed increases the most in at the given pixel,
+    /// as a unit vector of (real, imaginary) parts in units of pixels,
+    /// or `None` if it barely changes there.
+    pub(crate) fn direction(&self, band_index: usize, grid_index: usize) -> Option<(f64, f64)> {
+        let speed = |band: usize, index: usize| {
+            let band = band - self.window_bands.start;
+            let index = index - self.window_indices.start;
+            f64::from(self.escape_speeds[band * self.window_indices.len() + index])
+        };
+        // Central differences, or one-sided ones at the edges of the image.
+        let (left, right) = (
+            band_index.saturating_sub(1),
+            (band_index + 1).min(self.bands - 1),
+        );
+        let (below, above) = (
+            grid_index.saturating_sub(1),
+            (grid_index + 1).min(self.band_length - 1),
+        );
+        let real =
+            (speed(right, grid_index) - speed(left, grid_index)) / (right - left).max(1) as f64;
+        let imag =
+            (speed(band_index, above) - speed(band_index, below)) / (above - below).max(1) as f64;
+        let length = real.hypot(imag);
+        (length >= MIN_GRADIENT).then(|| (real / length, imag / length))
+    }
+}
+
+/// Returns the offsets of the samples of a pixel from its center, as (real, imaginary) parts
+/// in units of the distance between pixels, in the order they are taken.
+/// The samples closest to the center are taken first, so that the pixels that stop supersampling early
+/// are colored by them.
+///
+/// Without a direction the samples form a uniform grid. With one they are placed at
+/// `sqrt_samples_per_pixel^2` different distances along it, such that each of them covers an equal share
+/// of the square that the grid covers, and are spread across the square by the golden ratio.
+/// If the placement is a low discrepancy sequence the direction is ignored and the samples
+/// are the first points of the sequence instead, see [`SamplePlacement::Sequence`].
+/// Jittered samples are moved within their cells of the grid by amounts that depend on `seed` and `pixel`,
+/// the band of the pixel and its index in the band counted from the bottom of the image,
+/// see [`SamplePlacement::Jittered`].
+pub(crate) fn sample_offsets(
+    sqrt_samples_per_pixel: u8,
+    placement: SamplePlacement,
+    seed: u64,
+    direction: Option<(f64, f64)>,
+    pixel: (usize, usize),
+) -> impl Iterator<Item = (f64, f64)> {
+    let ssaa = f64::from(sqrt_samples_per_pixel);
+    let samples = usize::from(sqrt_samples_per_pixel) * usize::from(sqrt_samples_per_pixel);
+    if let SamplePlacement::Sequence(sequence) = placement {
+        // Shifting every point by the same amount around the square keeps them spread out,
+        // and this shift moves the first one to the center.
+        let (first_x, first_y) = sequence.point(0);
+        return Either::Right(Either::Right((0..samples as u64).map(move |k| {
+            let (x, y) = sequence.point(k);
+            (
+                2.0 * (x - first_x + 1.5).fract() - 1.0,
+                2.0 * (y - first_y + 1.5).fract() - 1.0,
+            )
+        })));
+    }
+    // A single sample stays at the center, so images without supersampling are not noisy.
+    let jitter_seed =
+        (placement == SamplePlacement::Jittered && sqrt_samples_per_pixel > 1).then_some(seed);
+    match direction {
+        None => Either::Left(
+            (1..=sqrt_samples_per_pixel)
+                .cartesian_product(1..=sqrt_samples_per_pixel)
+                .cycle()
+                .skip(samples / 2)
+                .take(samples)
+                .enumerate()
+                .map(move |(k, (i, j))| {
+                    let imag_offset = (2.0 * f64::from(i) - ssaa - 1.0) / ssaa;
+                    let real_offset = (2.0 * f64::from(j) - ssaa - 1.0) / ssaa;
+                    match jitter_seed {
+                        // Anywhere in the cell of side 2 / ssaa around the point of the grid.
+                        Some(seed) => (
+                            real_offset + (jitter(seed, pixel, 2 * k) - 0.5) * 2.0 / ssaa,
+                            imag_offset + (jitter(seed, pixel, 2 * k + 1) - 0.5) * 2.0 / ssaa,
+                        ),
+                        None => (real_offset, imag_offset),
+                    }
+                }),
+        ),
+        Some((along_real, along_imag)) => {
+            let count = samples as f64;
+            Either::Right(Either::Left(
+                (0..samples)
+                    .cycle()
+                    .skip(samples / 2)
+                    .take(samples)
+                    .map(move |k| {
+                        let k = k as f64;
+                        // Each sample covers an equal share of the square, ordered along the direction.
+                        let along = projected_quantile((k + 0.5) / count, along_real, along_imag);
+                        let (first, last) = chord(along, along_real, along_imag);
+                        let across =
+                            first + (last - first) * (k * INVERSE_GOLDEN_RATIO + 0.5).fract();
+                        (
+                            along * along_real - across * along_imag,
+                            along * along_imag + across * along_real,
+                        )
+                    }),
+            ))
+        }
+    }
+}
+
+/// Returns a pseudo-random number in [0, 1) that only depends on its arguments,
+/// where `pixel` is the position of a pixel in the image and `index` tells its random numbers apart.
+/// Since nothing depends on the order that the pixels are computed in, seeded images are the same
+/// whichever threads compute them.
+pub(crate) fn jitter(seed: u64, pixel: (usize, usize), index: usize) -> f64 {
+    // The arguments are mixed into one number by odd constants and then scrambled
+    // by the finalizer of SplitMix64, which spreads every bit of the input over the output.
+    let mut x = seed
+        ^ (pixel.0 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (pixel.1 as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+        ^ (index as u64).wrapping_mul(0x1656_67b1_9e37_79f9);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^= x >> 31;
+    // The top 53 bits fill the mantissa of an f64.
+    #[allow(clippy::cast_precision_loss)]
+    let fraction = (x >> 11) as f64 / (1_u64 << 53) as f64;
+    fraction
+}
+
+/// Returns the distance along the unit vector `(real, imag)` that the given fraction
+/// of the square [-1, 1]^2 lies behind.
+/// The area of the square is spread along the direction as a trapezoid that is flat in the middle.
+fn projected_quantile(fraction: f64, real: f64, imag: f64) -> f64 {
+    if fraction > 0.5 {
+        return -projected_quantile(1.0 - fraction, real, imag);
+    }
+    let (outer, inner) = (real.abs() + imag.abs(), (real.abs() - imag.abs()).abs());
+    let height = 1.0 / (outer + inner);
+    let ramp_area = height * (outer - inner) / 2.0;
+    if fraction < ramp_area {
+        -outer + (2.0 * fraction * (outer - inner) / height).sqrt()
+    } else {
+        -inner + (fraction - ramp_area) / height
+    }
+}
+
+/// Returns the range of distances along the perpendicular of the unit vector `(real, imag)`
+/// that the line at the given distance along it is inside the square [-1, 1]^2 for.
+fn chord(along: f64, real: f64, imag: f64) -> (f64, f64) {
+    // A point is `along * (real, imag) + across * (-imag, real)`, and each of its coordinates
+    // limits `across` to an interval unless the perpendicular is parallel to that axis.
+    let limits = |center: f64, slope: f64| {
+        if slope == 0.0 {
+            (f64::NEG_INFINITY, f64::INFINITY)
+        } else {
+            let (a, b) = ((-1.0 - center) / slope, (1.0 - center) / slope);
+            (a.min(b), a.max(b))
+        }
+    };
+    let (real_first, real_last) = limits(along * real, -imag);
+    let (imag_first, imag_last) = limits(along * imag, real);
+    (real_first.max(imag_first), real_last.min(imag_last))
+}
+
+#[cfg(test)]
+mod test_sample_placement {
+    use super::*;
+    use crate::{render, render_tile, Frame, PixelRect};
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_offsets() {
+        for ssaa in [1, 2, 3, 8] {
+            let grid: Vec<_> =
+                sample_offsets(ssaa, SamplePlacement::Grid, 0, None, (0, 0)).collect();
+            assert_eq!(grid.len(), usize::from(ssaa).pow(2));
+            // The grid is taken from the center out.
+            if ssaa % 2 == 1 {
+                assert_eq!(grid[0], (0.0, 0.0));
+            }
+
+            let (sin, cos) = 0.3_f64.sin_cos();
+            let guided: Vec<_> =
+                sample_offsets(ssaa, SamplePlacement::Gradient, 0, Some((cos, sin)), (0, 0))
+                    .collect();
+            assert_eq!(guided.len(), grid.len());
+            // Every sample is inside the pixel, and at a different distance along the direction.
+            assert!(guided
+                .iter()
+                .all(|(real, imag)| real.abs() <= 1.0 && imag.abs() <= 1.0));
+            let mut distances: Vec<f64> = guided
+                .iter()
+                .map(|(real, imag)| real * cos + imag * sin)
+                .collect();
+            distances.sort_by(f64::total_cmp);
+            assert!(distances.windows(2).all(|pair| pair[1] - pair[0] > 1e-9));
+        }
+    }
+
+    #[test]
+    fn check_straight_edges() {
+        let exact: Vec<(f64, f64)> =
+            sample_offsets(200, SamplePlacement::Grid, 0, None, (0, 0)).collect();
+        for angle in [0.0, 0.3, core::f64::consts::FRAC_PI_4] {
+            let (sin, cos) = f64::sin_cos(angle);
+            // The fraction of the samples behind a straight edge at the given distance from the center.
+            let coverage = |offsets: &[(f64, f64)], distance: f64| {
+                offsets
+                    .iter()
+                    .filter(|(real, imag)| real * cos + imag * sin < distance)
+                    .count() as f64
+                    / offsets.len() as f64
+            };
+            for ssaa in [2, 3, 4] {
+                let grid: Vec<_> =
+                    sample_offsets(ssaa, SamplePlacement::Grid, 0, None, (0, 0)).collect();
+                let guided: Vec<_> =
+                    sample_offsets(ssaa, SamplePlacement::Gradient, 0, Some((cos, sin)), (0, 0))
+                        .collect();
+                let (mut grid_error, mut guided_error) = (0.0, 0.0);
+                for step in -120..=120 {
+                    let distance = f64::from(step) / 100.0;
+                    let exact = coverage(&exact, distance);
+                    grid_error += (coverage(&grid, distance) - exact).abs();
+                    guided_error += (coverage(&guided, distance) - exact).abs();
+                }
+                assert!(
+                    guided_error < grid_error,
+                    "{ssaa}x{ssaa} samples at {angle} radians"
+                );
+                // The rows of the grid line up with edges along the axes, which are then
+                // only resolved into `ssaa` steps.
+                if angle == 0.0 {
+                    assert!(guided_error < grid_error / 2.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_jittered_offsets() {
+        let placement = SamplePlacement::Jittered;
+        assert_eq!(
+            sample_offsets(1, placement, 7, None, (3, 4)).collect::<Vec<_>>(),
+            [(0.0, 0.0)]
+        );
+        for ssaa in [2, 3, 4] {
+            let cell = 1.0 / f64::from(ssaa);
+            let grid: Vec<_> =
+                sample_offsets(ssaa, SamplePlacement::Grid, 0, None, (3, 4)).collect();
+            let jittered: Vec<_> = sample_offsets(ssaa, placement, 7, None, (3, 4)).collect();
+            // Every sample stays in its cell of the grid, but is moved within it.
+            for (point, moved) in grid.iter().zip(&jittered) {
+                assert!((point.0 - moved.0).abs() <= cell && (point.1 - moved.1).abs() <= cell);
+                assert_ne!(point, moved);
+            }
+            // The samples are the same for the same seed and pixel, and differ otherwise.
+            let offsets = |seed, pixel| {
+                sample_offsets(ssaa, placement, seed, None, pixel).collect::<Vec<_>>()
+            };
+            assert_eq!(offsets(7, (3, 4)), jittered);
+            assert_ne!(offsets(7, (4, 3)), jittered);
+            assert_ne!(offsets(8, (3, 4)), jittered);
+            // The seed has no effect on the other placements.
+            assert_eq!(
+                sample_offsets(ssaa, SamplePlacement::Grid, 7, None, (3, 4)).collect::<Vec<_>>(),
+                grid
+            );
+        }
+    }
+
+    #[test]
+    fn check_jittered_tiles_match_full_image() {
+        let mut params = RenderParameters::try_new(
+            30.try_into().unwrap(),
+            20.try_into().unwrap(),
+            100.try_into().unwrap(),
+            3.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        params.sample_placement = SamplePlacement::Jittered;
+        params.seed = 42;
+        // The view contains the real axis, so the image is mirrored.
+        let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+        let full = render(&params, frame, false);
+        let tile = PixelRect {
+            x: 5,
+            y: 3,
+            width: 12,
+            height: 14,
+        };
+        assert_eq!(
+            render_tile(&params, frame, tile).unwrap(),
+            full.crop_imm(tile.x, tile.y, tile.width, tile.height)
+        );
+        params.seed = 43;
+        assert_ne!(render(&params, frame, false), full);
+        params.sample_placement = SamplePlacement::Grid;
+        assert_ne!(render(&params, frame, false), full);
+
+        // The number of threads that render the image does not change it.
+        params.sample_placement = SamplePlacement::Jittered;
+        params.seed = 42;
+        for threads in [1, 3] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            assert_eq!(pool.install(|| render(&params, frame, false)), full);
+        }
+    }
+
+    #[test]
+    fn check_parsing() {
+        for placement in SamplePlacement::ALL {
+            assert_eq!(placement.to_string().parse(), Ok(placement));
+        }
+        assert_eq!(" Jittered".parse(), Ok(SamplePlacement::Jittered));
+        // The seed is a setting of the render, not of the placement.
+        assert!("jittered:42".parse::<SamplePlacement>().is_err());
+        assert_eq!(" Gradient".parse(), Ok(SamplePlacement::Gradient));
+        assert_eq!(
+            "Sobol".parse(),
+            Ok(SamplePlacement::Sequence(LowDiscrepancySequence::Sobol))
+        );
+        assert!("random".parse::<SamplePlacement>().is_err());
+    }
+
+    #[test]
+    fn check_sequence_offsets() {
+        for sequence in LowDiscrepancySequence::ALL {
+            let placement = SamplePlacement::Sequence(sequence);
+            // Without supersampling only the center is sampled.
+            assert_eq!(
+                sample_offsets(1, placement, 0, None, (0, 0)).collect::<Vec<_>>(),
+                [(0.0, 0.0)]
+            );
+            for ssaa in [2, 3, 4] {
+                let offsets: Vec<_> =
+                    sample_offsets(ssaa, placement, 0, Some((1.0, 0.0)), (0, 0)).collect();
+                assert_eq!(offsets.len(), usize::from(ssaa).pow(2));
+                assert_eq!(offsets[0], (0.0, 0.0));
+                assert!(offsets
+                    .iter()
+                    .all(|(real, imag)| (-1.0..1.0).contains(real) && (-1.0..1.0).contains(imag)));
+                // No two samples share a row or a column, unlike the samples of a grid.
+                for axis in [0, 1] {
+                    let mut coordinates: Vec<f64> = offsets
+                        .iter()
+                        .map(|&(real, imag)| if axis == 0 { real } else { imag })
+                        .collect();
+                    coordinates.sort_by(f64::total_cmp);
+                    assert!(
+                        coordinates.windows(2).all(|pair| pair[1] - pair[0] > 1e-9),
+                        "{sequence} with {ssaa}x{ssaa} samples"
+                    );
+                }
+            }
+        }
+    }
+}
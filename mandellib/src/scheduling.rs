@@ -0,0 +1,173 @@
+//! Different ways of dividing the work of rendering an image between threads.
+
+use crate::{Frame, RenderParameters};
+
+/// The width and height in pixels of the tiles used by [`Scheduling::Tiles`].
+pub(crate) const TILE_SIZE: usize = 64;
+
+/// Frames whose imaginary distance is smaller than this are considered deep zooms
+/// by [`Scheduling::Automatic`].
+const DEEP_ZOOM_DISTANCE: f64 = 1e-3;
+
+/// How the work of rendering an image is divided between threads.
+///
+/// All strategies give identical images, but differ in how evenly the work is spread over the threads
+/// and in how well the renderer can reuse the computed colors of mirrored pixels.
+/// Run the `scheduling` benchmarks to compare them on your machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Scheduling {
+    /// Pick one of the other strategies based on the shape of the image and the zoom.
+    /// Images that contain the real axis or are wide are split into bands, deep zooms into tiles.
+    #[default]
+    Automatic,
+    /// Every column of the image is a separate unit of work.
+    /// This allows the most mirroring, since every column is computed by a single thread.
+    Bands,
+    /// The image is split into square tiles that are separate units of work. Spreads the work
+    /// more evenly than bands when expensive regions are concentrated in a few rows.
+    /// Images that are mirrored in the real axis are split into bands instead,
+    /// since mirroring needs whole bands.
+    Tiles,
+    /// Every thread is assigned every n:th column of the image up front, where n is the number of threads.
+    /// Expensive regions are spread out over all threads without any work stealing.
+    Interlaced,
+}
+
+impl Scheduling {
+    /// Returns the strategy that is used to render the given frame.
+    /// `mirrored` is whether the image will be mirrored in the real axis.
+    pub(crate) fn resolve(
+        self,
+        render_parameters: &RenderParameters,
+        render_region: Frame,
+        mirrored: bool,
+    ) -> Self {
+        // Mirroring only works within a single band segment, and the supersampling
+        // of a computed pixel does not give exactly the same color as its mirror image.
+        if mirrored && matches!(self, Self::Automatic | Self::Tiles) {
+            return Self::Bands;
+        }
+        if self != Self::Automatic {
+            return self;
+        }
+
+        let x_resolution = u32::from(render_parameters.x_resolution);
+        let y_resolution = u32::from(render_parameters.y_resolution);
+
+        if x_resolution >= 2 * y_resolution {
+            // Wide images already have many bands to spread over the threads.
+            Self::Bands
+        } else if render_region.imag_distance < DEEP_ZOOM_DISTANCE {
+            // Deep zooms are dominated by a few expensive regions of the image,
+            // which are balanced better by many small tiles.
+            Self::Tiles
+        } else {
+            Self::Bands
+        }
+    }
+}
+
+/// A segment of a band of the image: `(band_index, first_pixel, pixel_data)`.
+pub(crate) type Segment<'a> = (usize, usize, &'a mut [u8]);
+
+/// Splits the rotated image buffer into units of work according to the given strategy,
+/// where each unit of work is a list of band segments.
+/// `band_length` is the number of bytes in a band.
+pub(crate) fn split_into_work(
+    buffer: &mut [u8],
+    band_length: usize,
+    bytes_per_pixel: usize,
+    scheduling: Scheduling,
+) -> Vec<Vec<Segment<'_>>> {
+    let bands = buffer.chunks_exact_mut(band_length).enumerate();
+    match scheduling {
+        Scheduling::Automatic | Scheduling::Bands => {
+            bands.map(|(index, band)| vec![(index, 0, band)]).collect()
+        }
+        Scheduling::Tiles => {
+            let tiles_per_band = band_length.div_ceil(TILE_SIZE * bytes_per_pixel);
+            let mut tiles: Vec<Vec<Segment>> = Vec::new();
+            for (index, band) in bands {
+                if index % TILE_SIZE == 0 {
+                    tiles.extend((0..tiles_per_band).map(|_| Vec::with_capacity(TILE_SIZE)));
+                }
+                let first_tile = tiles.len() - tiles_per_band;
+                for (row, segment) in band.chunks_mut(TILE_SIZE * bytes_per_pixel).enumerate() {
+                    tiles[first_tile + row].push((index, row * TILE_SIZE, segment));
+                }
+            }
+            tiles
+        }
+        Scheduling::Interlaced => {
+            let threads = rayon::current_num_threads();
+            let mut groups: Vec<Vec<Segment>> = (0..threads).map(|_| Vec::new()).collect();
+            for (index, band) in bands {
+                groups[index % threads].push((index, 0, band));
+            }
+            groups
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_scheduling {
+    use super::*;
+    use crate::{render, render_masked, Mask};
+    use color_space::SupportedColorType;
+    use image::{DynamicImage, GrayImage, Luma, Rgb};
+
+    #[test]
+    fn check_strategies_give_identical_images() {
+        // Taller than the tiles, so that the bands are split into several segments.
+        let mut params = RenderParameters::try_new(
+            150.try_into().unwrap(),
+            100.try_into().unwrap(),
+            255.try_into().unwrap(),
+            2.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let frames = [
+            // Mirrored.
+            Frame::new(-0.75, 0.0, 3.0, 2.0),
+            // Mirrored and flipped.
+            Frame::new(-0.75, 0.5, 3.0, 2.0),
+            // Neither.
+            Frame::new(-0.2345, -0.7178, 0.003, 0.002),
+        ];
+        let mask = Mask::new(
+            &DynamicImage::ImageLuma8(GrayImage::from_fn(150, 100, |x, y| {
+                Luma([if (x / 7 + y / 5) % 3 == 0 { 0 } else { 255 }])
+            })),
+            params.x_resolution,
+            params.y_resolution,
+            Rgb([1, 2, 3]),
+            false,
+        )
+        .unwrap();
+
+        for frame in frames {
+            params.scheduling = Scheduling::Bands;
+            let image = render(&params, frame, false);
+            let masked_image = render_masked(&params, frame, &mask, false);
+            for scheduling in [
+                Scheduling::Automatic,
+                Scheduling::Tiles,
+                Scheduling::Interlaced,
+            ] {
+                params.scheduling = scheduling;
+                assert_eq!(render(&params, frame, false), image, "{scheduling:?}");
+                assert_eq!(
+                    render_masked(&params, frame, &mask, false),
+                    masked_image,
+                    "{scheduling:?}"
+                );
+            }
+        }
+    }
+}
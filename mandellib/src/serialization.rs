@@ -0,0 +1,255 @@
+//! Serialization of frames and render parameters with serde.
+//!
+//! The settings that can be parsed from text are serialized as the text that they are displayed as,
+//! e.g. `"sobol"` for a [`SamplePlacement`], and are parsed from it when deserialized.
+//! Every value is checked when it is deserialized in the same way as when it is created,
+//! so a deserialized frame can be rendered and deserialized settings are within their ranges.
+
+use core::fmt;
+use core::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::renderer::check_frame;
+use crate::{
+    BulbChecks, CpuLimit, EscapeRadius, Exponent, Formula, Frame, GrayscaleMode, InteriorShading,
+    ReconstructionFilter, RenderQuality, SamplePlacement, Smoothing, SsaaCutoff, U32AndUsize,
+    VarianceThreshold,
+};
+
+/// Deserializes a value from the text that it is parsed from.
+fn deserialize_from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(de::Error::custom)
+}
+
+/// Implements `Serialize` and `Deserialize` for types that are displayed as the text that they are parsed from.
+macro_rules! serialize_as_text {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Serialize for $ty {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $ty {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    deserialize_from_str(deserializer)
+                }
+            }
+        )*
+    };
+}
+
+serialize_as_text!(
+    BulbChecks,
+    CpuLimit,
+    EscapeRadius,
+    Exponent,
+    GrayscaleMode,
+    InteriorShading,
+    ReconstructionFilter,
+    RenderQuality,
+    SamplePlacement,
+    Smoothing,
+    SsaaCutoff,
+    VarianceThreshold,
+);
+
+/// Custom fractals are defined by code, so they can not be serialized.
+impl Serialize for Formula {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Custom(fractal) => Err(serde::ser::Error::custom(format!(
+                "the custom fractal {fractal} can not be serialized"
+            ))),
+            Self::Mandelbrot | Self::Mandelbar | Self::Abs(_) | Self::Hybrid(_) => {
+                serializer.collect_str(self)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Formula {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_from_str(deserializer)
+    }
+}
+
+impl Serialize for U32AndUsize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(u32::from(*self))
+    }
+}
+
+/// Checks that the number is not zero and fits in a `usize`.
+impl<'de> Deserialize<'de> for U32AndUsize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = u32::deserialize(deserializer)?;
+        Self::try_from(value).map_err(|_| {
+            de::Error::invalid_value(
+                de::Unexpected::Unsigned(value.into()),
+                &"a positive number that fits in a usize",
+            )
+        })
+    }
+}
+
+/// The fields of a [`Frame`] as they are serialized.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Frame")]
+struct FrameFields {
+    center_real: f64,
+    center_imag: f64,
+    real_distance: f64,
+    imag_distance: f64,
+}
+
+impl Serialize for Frame {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        FrameFields {
+            center_real: self.center_real,
+            center_imag: self.center_imag,
+            real_distance: self.real_distance,
+            imag_distance: self.imag_distance,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Checks that the frame can be rendered: its center must be finite and its distances finite and positive.
+impl<'de> Deserialize<'de> for Frame {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = FrameFields::deserialize(deserializer)?;
+        let frame = Self::new(
+            fields.center_real,
+            fields.center_imag,
+            fields.real_distance,
+            fields.imag_distance,
+        );
+        check_frame(frame).map_err(de::Error::custom)?;
+        Ok(frame)
+    }
+}
+
+#[cfg(test)]
+mod test_serialization {
+    use super::*;
+    use crate::{
+        AbsVariant, CustomFormula, EscapeSpeedRange, Exterior, LowDiscrepancySequence, Precision,
+        RenderParameters, Scheduling,
+    };
+    use color_space::{Palette, SupportedColorType};
+    use std::sync::Arc;
+
+    #[test]
+    fn check_render_parameters() {
+        let mut params = RenderParameters::try_new(
+            1920.try_into().unwrap(),
+            1080.try_into().unwrap(),
+            5000.try_into().unwrap(),
+            3.try_into().unwrap(),
+            SupportedColorType::Rgba8,
+        )
+        .unwrap();
+        let json = serde_json::to_string(&params).unwrap();
+        let deserialized: RenderParameters = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), json);
+
+        params.formula = Formula::Abs(AbsVariant::ALL[1]);
+        params.exponent = Exponent::try_from(3).unwrap();
+        params.palette = Palette::Curves(Arc::new("r=s, g=s^2, b=1-s".parse().unwrap()));
+        params.precision = Precision::Single;
+        params.scheduling = Scheduling::Tiles;
+        params.interior_shading = InteriorShading::ALL[1];
+        params.exterior = Exterior::Transparent {
+            interior_color: Some([10, 20, 30]),
+        };
+        params.escape_speed_range = EscapeSpeedRange::new(0.2, 0.9);
+        params.quality = RenderQuality::Draft;
+        params.sample_placement = SamplePlacement::Jittered;
+        params.seed = 7;
+        params.grayscale_mode = GrayscaleMode::Log;
+        params.reconstruction_filter = ReconstructionFilter::ALL[1];
+        params.escape_radius = EscapeRadius::try_from(100.0).unwrap();
+        params.smoothing = Smoothing::Banded;
+        params.palette_offset = 0.25;
+        params.cpu_limit = CpuLimit::try_from(1.5).unwrap();
+        params.adaptive_supersampling = Some(VarianceThreshold::DEFAULT);
+        params.ssaa_cutoff = None;
+        let json = serde_json::to_string(&params).unwrap();
+        assert!(
+            json.contains(r#""sample_placement":"jittered","seed":7"#),
+            "{json}"
+        );
+        let deserialized: RenderParameters = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), json);
+
+        params.sample_placement = SamplePlacement::Sequence(LowDiscrepancySequence::default());
+        params.formula = "mandelbrot, burning ship".parse().unwrap();
+        let json = serde_json::to_string(&params).unwrap();
+        let deserialized: RenderParameters = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), json);
+
+        // Parameters that were saved before renders had a seed get the default seed.
+        let mut value_json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value_json.as_object_mut().unwrap().remove("seed");
+        assert_eq!(
+            serde_json::from_value::<RenderParameters>(value_json)
+                .unwrap()
+                .seed,
+            0
+        );
+
+        // The ranges of the settings are checked.
+        for (field, value) in [
+            ("x_resolution", "0"),
+            ("max_iterations", "0"),
+            ("sqrt_samples_per_pixel", "0"),
+            ("exponent", r#""1""#),
+            ("escape_radius", r#""1""#),
+            ("formula", r#""mandelbrotte""#),
+            ("smoothing", r#""smooth""#),
+        ] {
+            let mut value_json: serde_json::Value = serde_json::from_str(&json).unwrap();
+            value_json[field] = serde_json::from_str(value).unwrap();
+            assert!(
+                serde_json::from_value::<RenderParameters>(value_json).is_err(),
+                "{field}: {value}"
+            );
+        }
+
+        params.formula = Formula::Custom(Arc::new("z^2 + c".parse::<CustomFormula>().unwrap()));
+        assert!(serde_json::to_string(&params).is_err());
+    }
+
+    #[test]
+    fn check_frame() {
+        let frame = Frame::new(-0.75, 0.1, 3.0, 2.0);
+        let json = serde_json::to_string(&frame).unwrap();
+        assert_eq!(
+            json,
+            r#"{"center_real":-0.75,"center_imag":0.1,"real_distance":3.0,"imag_distance":2.0}"#
+        );
+        assert_eq!(serde_json::from_str::<Frame>(&json).unwrap(), frame);
+        assert!(serde_json::from_str::<Frame>(
+            r#"{"center_real":-0.75,"center_imag":0.1,"real_distance":0.0,"imag_distance":2.0}"#
+        )
+        .is_err());
+
+        assert_eq!(
+            serde_json::from_str::<U32AndUsize>("640")
+                .map(u32::from)
+                .unwrap(),
+            640
+        );
+        assert!(serde_json::from_str::<U32AndUsize>("0").is_err());
+    }
+}
@@ -0,0 +1,278 @@
+//! Slope shading, which lights the exterior of the set as if it was a surface
+//! whose height grows with the escape speed, giving the image an embossed look.
+//!
+//! The height of the surface at a pixel is the logarithm of its smoothed iteration count,
+//! and the normal of the surface is estimated from the heights of the neighboring pixels.
+//! Each pixel is then lit by a light that is infinitely far away with Lambert's cosine law,
+//! and its color is darkened or brightened by how much more or less light it gets than a flat surface would.
+
+use core::fmt;
+
+use color_space::LinearRGB;
+use image::{DynamicImage, GenericImage, GenericImageView, Rgba};
+
+use crate::EscapeSpeedField;
+
+/// How the exterior of an image is lit by slope shading.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlopeShading {
+    azimuth: f64,
+    elevation: f64,
+    relief: f64,
+    strength: f64,
+}
+
+impl SlopeShading {
+    /// The default light comes from the top left.
+    pub const DEFAULT_AZIMUTH: f64 = 135.0;
+    pub const DEFAULT_ELEVATION: f64 = 45.0;
+    pub const DEFAULT_RELIEF: f64 = 10.0;
+    pub const DEFAULT_STRENGTH: f64 = 0.75;
+
+    /// Creates the settings of slope shading.
+    ///
+    /// - `azimuth` is the direction that the light comes from, in degrees counter-clockwise from the positive real axis.
+    /// - `elevation` is the angle of the light above the image, in degrees from 0, which only lights
+    ///   the steepest slopes, to 90, where it shines straight down onto the image.
+    /// - `relief` is the height of the surface where the smoothed iteration count is e - 1, in pixels.
+    ///   Higher reliefs give steeper slopes.
+    /// - `strength` is how much the lighting changes the colors, from 0 for not at all to 1 for fully.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the azimuth is not finite, the elevation is not in (0, 90],
+    /// the relief is not positive and finite, or the strength is not in \[0, 1\].
+    pub fn new(
+        azimuth: f64,
+        elevation: f64,
+        relief: f64,
+        strength: f64,
+    ) -> Result<Self, SlopeShadingError> {
+        if !(elevation > 0.0 && elevation <= 90.0) {
+            return Err(SlopeShadingError::InvalidElevation(elevation));
+        }
+        if !(relief > 0.0 && relief.is_finite()) {
+            return Err(SlopeShadingError::InvalidRelief(relief));
+        }
+        if !(0.0..=1.0).contains(&strength) {
+            return Err(SlopeShadingError::InvalidStrength(strength));
+        }
+        if !azimuth.is_finite() {
+            return Err(SlopeShadingError::InvalidAzimuth(azimuth));
+        }
+        Ok(Self {
+            azimuth,
+            elevation,
+            relief,
+            strength,
+        })
+    }
+
+    /// Lights every pixel of the image outside the set by the slope of the escape speed field at it.
+    /// The field should be the one of the image, and only the pixels that both cover are shaded.
+    /// The inside of the set is flat and left as it is.
+    pub fn shade(&self, image: &mut DynamicImage, field: &EscapeSpeedField) {
+        let factors = self.brightness_factors(field);
+        let width = u32::try_from(field.width()).unwrap_or(u32::MAX);
+        let height = u32::try_from(field.height()).unwrap_or(u32::MAX);
+        for y in 0..height.min(image.height()) {
+            for x in 0..width.min(image.width()) {
+                let Some(factor) = factors[y as usize * field.width() + x as usize] else {
+                    continue;
+                };
+                let pixel = image.get_pixel(x, y);
+                let [r, g, b] =
+                    (LinearRGB::from_srgb([pixel[0], pixel[1], pixel[2]]) * factor).to_srgb();
+                image.put_pixel(x, y, Rgba([r, g, b, pixel[3]]));
+            }
+        }
+    }
+
+    /// Returns what the color of every pixel of the field is multiplied by, row by row from the top left.
+    /// A flat surface keeps its color, slopes that face the light are brightened and the others are darkened.
+    /// Pixels inside the set are not shaded and are `None`.
+    fn brightness_factors(&self, field: &EscapeSpeedField) -> Vec<Option<f64>> {
+        let (width, height) = (field.width(), field.height());
+        let heights: Vec<Option<f64>> = field
+            .smoothed_iterations()
+            .iter()
+            .map(|&iterations| {
+                (iterations < field.max_iterations())
+                    .then(|| self.relief * iterations.max(0.0).ln_1p())
+            })
+            .collect();
+        let height_at = |x: usize, y: usize| heights[y * width + x];
+
+        let (azimuth, elevation) = (self.azimuth.to_radians(), self.elevation.to_radians());
+        let light = [
+            elevation.cos() * azimuth.cos(),
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+        ];
+        // The slope along an axis from the neighbors on either side that are outside the set,
+        // or from the pixel itself and one neighbor at the edges of the set and the image.
+        let slope = |center: f64, before: Option<f64>, after: Option<f64>| match (before, after) {
+            (Some(before), Some(after)) => (after - before) / 2.0,
+            (Some(before), None) => center - before,
+            (None, Some(after)) => after - center,
+            (None, None) => 0.0,
+        };
+
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let center = height_at(x, y)?;
+                let real_slope = slope(
+                    center,
+                    x.checked_sub(1).and_then(|x| height_at(x, y)),
+                    (x + 1 < width).then(|| height_at(x + 1, y)).flatten(),
+                );
+                // The rows go down the image while the imaginary axis goes up.
+                let imag_slope = slope(
+                    center,
+                    (y + 1 < height).then(|| height_at(x, y + 1)).flatten(),
+                    y.checked_sub(1).and_then(|y| height_at(x, y)),
+                );
+                let normal = [-real_slope, -imag_slope, 1.0];
+                let length = normal.iter().map(|n| n * n).sum::<f64>().sqrt();
+                let lambert = normal
+                    .iter()
+                    .zip(light)
+                    .map(|(n, l)| n * l)
+                    .sum::<f64>()
+                    .max(0.0)
+                    / length;
+                // A flat surface is lit by the sine of the elevation, and keeps its color.
+                Some(1.0 + self.strength * (lambert / light[2] - 1.0))
+            })
+            .collect()
+    }
+}
+
+impl Default for SlopeShading {
+    fn default() -> Self {
+        Self {
+            azimuth: Self::DEFAULT_AZIMUTH,
+            elevation: Self::DEFAULT_ELEVATION,
+            relief: Self::DEFAULT_RELIEF,
+            strength: Self::DEFAULT_STRENGTH,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlopeShadingError {
+    InvalidAzimuth(f64),
+    InvalidElevation(f64),
+    InvalidRelief(f64),
+    InvalidStrength(f64),
+}
+
+impl fmt::Display for SlopeShadingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidAzimuth(azimuth) => {
+                write!(
+                    f,
+                    "the light azimuth must be a finite angle, but it was {azimuth}"
+                )
+            }
+            Self::InvalidElevation(elevation) => write!(
+                f,
+                "the light elevation must be above 0 and at most 90 degrees, but it was {elevation}"
+            ),
+            Self::InvalidRelief(relief) => write!(
+                f,
+                "the slope relief must be a positive number, but it was {relief}"
+            ),
+            Self::InvalidStrength(strength) => write!(
+                f,
+                "the slope shading strength must be between 0 and 1, but it was {strength}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SlopeShadingError {}
+
+#[cfg(test)]
+mod test_slope_shading {
+    use super::*;
+    use crate::{Frame, RenderParameters};
+    use color_space::SupportedColorType;
+    use image::RgbImage;
+
+    fn field() -> EscapeSpeedField {
+        let parameters = RenderParameters::try_new(
+            60.try_into().unwrap(),
+            40.try_into().unwrap(),
+            100.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        EscapeSpeedField::new(&parameters, Frame::new(-0.5, 0.0, 3.0, 2.0))
+    }
+
+    #[test]
+    fn check_lighting() {
+        let field = field();
+        let from_left = SlopeShading::new(180.0, 30.0, 10.0, 1.0)
+            .unwrap()
+            .brightness_factors(&field);
+        let from_right = SlopeShading::new(0.0, 30.0, 10.0, 1.0)
+            .unwrap()
+            .brightness_factors(&field);
+        let (width, height) = (field.width(), field.height());
+
+        // The middle of the image is inside the set, and is not shaded.
+        assert_eq!(from_left[height / 2 * width + width * 2 / 3], None);
+        // The escape speed increases towards the set in the middle of the image,
+        // so the left edge of the image slopes up to the right and faces a light from the left.
+        let left_edge = height / 2 * width;
+        assert!(from_left[left_edge].unwrap() > 1.0);
+        assert!(from_right[left_edge].unwrap() < 1.0);
+        // No pixel gets negative light.
+        assert!(from_left.iter().flatten().all(|&factor| factor >= 0.0));
+
+        // Light from straight above with no strength changes nothing.
+        let unchanged = SlopeShading::new(0.0, 90.0, 10.0, 0.0)
+            .unwrap()
+            .brightness_factors(&field);
+        assert!(unchanged.iter().flatten().all(|&factor| factor == 1.0));
+    }
+
+    #[test]
+    fn check_shading() {
+        let field = field();
+        let gray = RgbImage::from_pixel(60, 40, image::Rgb([128, 128, 128]));
+        let mut image = DynamicImage::ImageRgb8(gray.clone());
+        SlopeShading::default().shade(&mut image, &field);
+        let image = image.into_rgb8();
+        assert_ne!(image, gray);
+        // The inside of the set keeps its color.
+        assert_eq!(image.get_pixel(40, 20), gray.get_pixel(40, 20));
+        // The shading only changes the brightness of a color.
+        assert!(image
+            .pixels()
+            .all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2]));
+    }
+
+    #[test]
+    fn check_validation() {
+        assert!(SlopeShading::new(45.0, 45.0, 1.0, 0.5).is_ok());
+        assert_eq!(
+            SlopeShading::new(45.0, 0.0, 1.0, 0.5),
+            Err(SlopeShadingError::InvalidElevation(0.0))
+        );
+        assert_eq!(
+            SlopeShading::new(45.0, 45.0, -1.0, 0.5),
+            Err(SlopeShadingError::InvalidRelief(-1.0))
+        );
+        assert_eq!(
+            SlopeShading::new(45.0, 45.0, 1.0, 1.5),
+            Err(SlopeShadingError::InvalidStrength(1.5))
+        );
+        assert!(SlopeShading::new(f64::NAN, 45.0, 1.0, 0.5).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+use core::fmt;
+use core::num::NonZeroU64;
+use core::str::FromStr;
+
+use crate::kernel::{iterations_to_f64, smooth_escape_speed};
+use crate::math::ln;
+
+/// How the number of iterations it took a point to escape is turned into its escape speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Smoothing {
+    /// The iteration count is smoothed by how far past the escape radius the point got,
+    /// and shifted by the smoothing offset of the render, see [`smooth_escape_speed`].
+    #[default]
+    Classic,
+    /// The normalized iteration count n + 1 - log_d(ln|z|), where d is the degree of the formula.
+    /// Does not depend on the smoothing offset, so the escape speeds of different renders
+    /// can be compared with each other.
+    Normalized,
+    /// The iteration count is not smoothed, which colors the image in bands of equal iteration count.
+    Banded,
+}
+
+impl Smoothing {
+    pub const ALL: [Self; 3] = [Self::Classic, Self::Normalized, Self::Banded];
+
+    /// Returns the escape speed of a point that escaped, between 0 (close to the set) and 1 (far outside).
+    /// The arguments are those of [`smooth_escape_speed`], but `smoothing_offset` is only used by
+    /// [`Smoothing::Classic`] and only the number of iterations is used by [`Smoothing::Banded`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::{iterate, Exponent, Smoothing, DEFAULT_SMOOTHING_OFFSET};
+    /// # use core::num::NonZeroU64;
+    /// const MAXITERS: NonZeroU64 = NonZeroU64::new(100).unwrap();
+    /// let speed = |smoothing: Smoothing, c_re: f64| {
+    ///     let (iterations, z_re, z_im) = iterate(c_re, 0.0, Exponent::TWO, MAXITERS);
+    ///     let mag_sqr = z_re * z_re + z_im * z_im;
+    ///     smoothing.escape_speed(iterations, MAXITERS, mag_sqr, 2.0, 0, DEFAULT_SMOOTHING_OFFSET)
+    /// };
+    /// // Both 0.6 and 0.7 escape after 5 iterations.
+    /// assert_eq!(speed(Smoothing::Banded, 0.6), 0.95);
+    /// assert_eq!(speed(Smoothing::Banded, 0.7), 0.95);
+    /// assert!(speed(Smoothing::Normalized, 0.6) < speed(Smoothing::Normalized, 0.7));
+    /// ```
+    #[must_use]
+    pub fn escape_speed(
+        self,
+        iterations: u64,
+        max_iterations: NonZeroU64,
+        mag_sqr: f64,
+        degree: f64,
+        smoothing_iterations: u32,
+        smoothing_offset: f64,
+    ) -> f64 {
+        match self.offset(smoothing_offset, degree) {
+            Some(offset) => smooth_escape_speed(
+                iterations,
+                max_iterations,
+                mag_sqr,
+                degree,
+                smoothing_iterations,
+                offset,
+            ),
+            None => {
+                iterations_to_f64(max_iterations.get() - iterations)
+                    / iterations_to_f64(max_iterations.get())
+            }
+        }
+    }
+
+    /// Returns the offset that [`smooth_escape_speed`] must be called with to smooth escape speeds in this way,
+    /// or `None` if they are not smoothed.
+    pub(crate) fn offset(self, smoothing_offset: f64, degree: f64) -> Option<f64> {
+        match self {
+            Self::Classic => Some(smoothing_offset),
+            // `smooth_escape_speed` adds log_d(ln|z|^2) = log_d(2) + log_d(ln|z|) to the number of iterations left,
+            // which is one more than the normalized iteration count when the offset is 1 + log_d(2).
+            Self::Normalized => Some(1.0 + ln(2.0) / ln(degree)),
+            Self::Banded => None,
+        }
+    }
+}
+
+impl fmt::Display for Smoothing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Classic => write!(f, "classic"),
+            Self::Normalized => write!(f, "normalized"),
+            Self::Banded => write!(f, "banded"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseSmoothingError;
+
+impl fmt::Display for ParseSmoothingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown smoothing, expected \"classic\", \"normalized\" or \"banded\""
+        )
+    }
+}
+
+impl core::error::Error for ParseSmoothingError {}
+
+impl FromStr for Smoothing {
+    type Err = ParseSmoothingError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("classic") {
+            Ok(Self::Classic)
+        } else if s.eq_ignore_ascii_case("normalized")
+            || s.eq_ignore_ascii_case("normalised")
+            || s.eq_ignore_ascii_case("log-log")
+        {
+            Ok(Self::Normalized)
+        } else if s.eq_ignore_ascii_case("banded") || s.eq_ignore_ascii_case("none") {
+            Ok(Self::Banded)
+        } else {
+            Err(ParseSmoothingError)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_smoothing {
+    use super::*;
+    use crate::{iterate, Exponent};
+
+    #[test]
+    fn check_parsing() {
+        for smoothing in Smoothing::ALL {
+            assert_eq!(smoothing.to_string().parse(), Ok(smoothing));
+        }
+        assert_eq!("None".parse(), Ok(Smoothing::Banded));
+        assert_eq!(" log-log".parse(), Ok(Smoothing::Normalized));
+        assert_eq!("smooth".parse::<Smoothing>(), Err(ParseSmoothingError));
+    }
+
+    #[test]
+    fn check_normalized_iteration_count() {
+        const MAXITERS: NonZeroU64 = NonZeroU64::new(100).unwrap();
+        for (c_re, exponent, degree) in [(0.4, 2, 2.0), (0.7, 3, 3.0)] {
+            let (iterations, z_re, z_im) =
+                iterate(c_re, 0.0, Exponent::try_from(exponent).unwrap(), MAXITERS);
+            let mag_sqr: f64 = z_re * z_re + z_im * z_im;
+            let normalized_count =
+                iterations as f64 + 1.0 - (0.5 * mag_sqr.ln()).ln() / f64::ln(degree);
+            let escape_speed =
+                Smoothing::Normalized.escape_speed(iterations, MAXITERS, mag_sqr, degree, 0, 0.0);
+            assert!(
+                (100.0 * (1.0 - escape_speed) - normalized_count).abs() < 1e-9,
+                "{c_re}"
+            );
+        }
+    }
+}
@@ -0,0 +1,382 @@
+//! Rendering a part of an image that is too large to render at once, so that it can be rendered
+//! tile by tile and stitched together.
+//!
+//! The pixels of a tile sample exactly the same points, and are colored exactly the same way,
+//! as the corresponding pixels of the full image, so the tiles fit together without seams.
+
+use image::DynamicImage;
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use rayon::slice::ParallelSliceMut;
+
+use crate::escape_buffer::Colorizer;
+use crate::grayscale::GrayscaleCurve;
+use crate::sample_placement::GradientField;
+use crate::{
+    allocate_rotated_image, check_frame, color_band_segment, parallel_rotate270, Coloring, Frame,
+    PixelGrid, ReconstructionFilter, RenderError, RenderParameters, U32AndUsize, ViewPasses,
+};
+
+/// A rectangle of pixels in an image, with the origin in the top left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PixelRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Renders the pixels in the rectangle `tile` of the image that [`render`](crate::render) would render
+/// of `full_frame`, whose resolution is that of the render parameters.
+///
+/// Every pixel of the tile is identical to the same pixel of the full image, including mirrored pixels
+/// and pixels whose colors depend on their neighbours or on the whole view. The exceptions are
+/// [`RenderQuality::Draft`](crate::RenderQuality::Draft) renders, which guess their colors from the whole image,
+/// and [`RenderQuality::QuadTree`](crate::RenderQuality::QuadTree) renders, which interpolate them over cells
+/// that can span several tiles. Tiles of those qualities compute every pixel, so they differ from the full image
+/// wherever it guessed or interpolated a pixel.
+///
+/// # Errors
+/// Returns an error if the tile is empty or not inside the image, if the center of the frame
+/// is not finite, if its distances are not finite and positive, or if the memory for the tile
+/// can not be allocated.
+///
+/// # Example
+///
+/// ```
+/// # use image::GenericImageView;
+/// # use mandellib::{render, render_tile, Frame, PixelRect, RenderError, RenderParameters};
+/// # use color_space::SupportedColorType;
+/// let params = RenderParameters::try_new(
+///     30.try_into().unwrap(),
+///     20.try_into().unwrap(),
+///     100.try_into().unwrap(),
+///     2.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+/// let tile = PixelRect { x: 10, y: 4, width: 8, height: 12 };
+/// assert_eq!(
+///     render_tile(&params, frame, tile).unwrap(),
+///     render(&params, frame, false).crop_imm(10, 4, 8, 12),
+/// );
+///
+/// let outside = PixelRect { x: 25, ..tile };
+/// assert_eq!(render_tile(&params, frame, outside), Err(RenderError::TileOutsideImage(outside)));
+/// ```
+pub fn render_tile(
+    render_parameters: &RenderParameters,
+    full_frame: Frame,
+    tile: PixelRect,
+) -> Result<DynamicImage, RenderError> {
+    check_tile(render_parameters, tile)?;
+    TileRenderer::new(render_parameters, full_frame)?.render(tile)
+}
+
+/// Returns an error if the tile is empty or not inside the image.
+fn check_tile(render_parameters: &RenderParameters, tile: PixelRect) -> Result<(), RenderError> {
+    let inside = |start: u32, length: u32, resolution: U32AndUsize| {
+        length > 0
+            && start
+                .checked_add(length)
+                .is_some_and(|end| end <= u32::from(resolution))
+    };
+    if inside(tile.x, tile.width, render_parameters.x_resolution)
+        && inside(tile.y, tile.height, render_parameters.y_resolution)
+    {
+        Ok(())
+    } else {
+        Err(RenderError::TileOutsideImage(tile))
+    }
+}
+
+/// Renders tiles of the same image, working out what is shared between them once.
+pub(crate) struct TileRenderer<'a> {
+    render_parameters: &'a RenderParameters,
+    grid: PixelGrid,
+    /// The gradient field only covers the pixels that the latest tile samples.
+    passes: ViewPasses,
+}
+
+impl<'a> TileRenderer<'a> {
+    /// # Errors
+    /// Returns an error if the center of the frame is not finite, or if its distances are not finite and positive.
+    pub(crate) fn new(
+        render_parameters: &'a RenderParameters,
+        full_frame: Frame,
+    ) -> Result<Self, RenderError> {
+        check_frame(full_frame)?;
+        let grid = PixelGrid::new(render_parameters, full_frame, None);
+        Ok(Self {
+            render_parameters,
+            grid,
+            passes: ViewPasses {
+                gradient_field: None,
+                // The grayscale curve is worked out from the full frame, so that every tile is colored the same way.
+                colorizer: Colorizer::new(
+                    Coloring::from(render_parameters),
+                    GrayscaleCurve::new(render_parameters, full_frame),
+                ),
+            },
+        })
+    }
+
+    pub(crate) const fn render_parameters(&self) -> &'a RenderParameters {
+        self.render_parameters
+    }
+
+    /// # Errors
+    /// Returns an error if the tile is empty or not inside the image, or if the memory for it can not be allocated.
+    pub(crate) fn render(&mut self, tile: PixelRect) -> Result<DynamicImage, RenderError> {
+        check_tile(self.render_parameters, tile)?;
+        let render_parameters = self.render_parameters;
+
+        let _render_span = tracing::info_span!(
+            "tile",
+            x = tile.x,
+            y = tile.y,
+            width = tile.width,
+            height = tile.height,
+        )
+        .entered();
+
+        // The tile is inside the image, whose resolution fits in a usize.
+        let (width, height) = (tile.width as usize, tile.height as usize);
+        let first_band = tile.x as usize;
+        let y_resolution = usize::from(render_parameters.y_resolution);
+        // The index of the bottom pixel of the tile in its bands, counted from the bottom of the image.
+        let first_pixel = y_resolution - tile.y as usize - height;
+
+        let mut image = allocate_rotated_image(
+            tile.width
+                .try_into()
+                .expect("the width of the tile is not zero"),
+            tile.height
+                .try_into()
+                .expect("the height of the tile is not zero"),
+            render_parameters.color_type,
+        )?;
+        let buffer: &mut [u8] = match &mut image {
+            DynamicImage::ImageLuma8(buffer) => buffer.as_mut(),
+            DynamicImage::ImageRgb8(buffer) => buffer.as_mut(),
+            DynamicImage::ImageRgba8(buffer) => buffer.as_mut(),
+            _ => unreachable!("we define the image so that it can only be one of the above"),
+        };
+
+        let grid = &self.grid;
+        self.passes.gradient_field = {
+            let (bands, indices) = sampled_window(render_parameters, grid, tile, first_pixel);
+            GradientField::new_window(render_parameters, grid, bands, indices)
+        };
+        let passes = &self.passes;
+
+        let bytes_per_pixel = usize::from(render_parameters.color_type.bytes_per_pixel());
+        buffer
+            .par_chunks_exact_mut(height * bytes_per_pixel)
+            .enumerate()
+            .for_each(|(column, segment)| {
+                render_parameters.cpu_limit.throttle(|| {
+                    color_band_segment(
+                        render_parameters,
+                        grid,
+                        passes,
+                        None,
+                        first_band + column,
+                        first_pixel,
+                        segment,
+                    );
+                });
+            });
+        debug_assert_eq!(buffer.len(), width * height * bytes_per_pixel);
+
+        Ok(parallel_rotate270(&image))
+    }
+}
+
+/// Returns the bands and the indices in the computed grid of the pixels whose samples are taken
+/// to color the tile: the pixels of the tile, the ones that they are mirror images of,
+/// and their neighbours if the samples are shared between pixels.
+fn sampled_window(
+    render_parameters: &RenderParameters,
+    grid: &PixelGrid,
+    tile: PixelRect,
+    first_pixel: usize,
+) -> (core::ops::Range<usize>, core::ops::Range<usize>) {
+    let mut bands = tile.x as usize..(tile.x + tile.width) as usize;
+    let pixels = first_pixel..(first_pixel + tile.height as usize);
+    let mut indices = if grid.need_to_flip {
+        (grid.y_resolution - pixels.end)..(grid.y_resolution - pixels.start)
+    } else {
+        pixels
+    };
+
+    if grid.mirror {
+        // The mirror axis is inside the image when it is mirrored.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let axis = grid.mirror_axis as usize;
+        let sources = indices
+            .clone()
+            .filter(|&index| index as f64 > grid.mirror_axis / 2.0 && index <= axis)
+            .map(|index| axis - index);
+        if let (Some(lowest), Some(highest)) = (sources.clone().min(), sources.max()) {
+            indices = indices.start.min(lowest)..indices.end.max(highest + 1);
+        }
+    }
+
+    if render_parameters.reconstruction_filter != ReconstructionFilter::Box {
+        bands = bands.start.saturating_sub(1)
+            ..(bands.end + 1).min(usize::from(render_parameters.x_resolution));
+        indices = indices.start.saturating_sub(1)..(indices.end + 1).min(grid.y_resolution);
+    }
+
+    (bands, indices)
+}
+
+#[cfg(test)]
+mod test_tile {
+    use super::*;
+    use crate::{render, GrayscaleMode, RenderQuality, SamplePlacement};
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_tiles_match_full_image() {
+        let frames = [
+            // Mirrored in the real axis.
+            Frame::new(-0.75, 0.0, 3.0, 2.0),
+            // Mirrored and flipped.
+            Frame::new(-0.75, 0.3, 3.0, 2.0),
+            // Not containing the real axis.
+            Frame::new(-0.1, 0.8, 0.5, 0.4),
+        ];
+        let tiles = [
+            PixelRect {
+                x: 0,
+                y: 0,
+                width: 40,
+                height: 30,
+            },
+            // Only above the real axis, so every pixel is the mirror image of one outside the tile.
+            PixelRect {
+                x: 5,
+                y: 2,
+                width: 13,
+                height: 9,
+            },
+            PixelRect {
+                x: 33,
+                y: 12,
+                width: 7,
+                height: 18,
+            },
+        ];
+        let mut params = RenderParameters::try_new(
+            40.try_into().unwrap(),
+            30.try_into().unwrap(),
+            200.try_into().unwrap(),
+            2.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let settings = [
+            (
+                SamplePlacement::Grid,
+                ReconstructionFilter::Box,
+                GrayscaleMode::Linear,
+            ),
+            (
+                SamplePlacement::Gradient,
+                ReconstructionFilter::Box,
+                GrayscaleMode::Linear,
+            ),
+            (
+                SamplePlacement::Gradient,
+                ReconstructionFilter::Tent,
+                GrayscaleMode::Linear,
+            ),
+            (
+                SamplePlacement::Grid,
+                ReconstructionFilter::Box,
+                GrayscaleMode::Equalized,
+            ),
+        ];
+        for (sample_placement, filter, grayscale_mode) in settings {
+            params.sample_placement = sample_placement;
+            params.reconstruction_filter = filter;
+            params.grayscale_mode = grayscale_mode;
+            params.color_type = if grayscale_mode == GrayscaleMode::Linear {
+                SupportedColorType::Rgb8
+            } else {
+                SupportedColorType::L8
+            };
+            for frame in frames {
+                let full = render(&params, frame, false);
+                for tile in tiles {
+                    assert_eq!(
+                        render_tile(&params, frame, tile).unwrap(),
+                        full.crop_imm(tile.x, tile.y, tile.width, tile.height),
+                        "{tile:?} of {frame:?} with {sample_placement}, {filter} and {grayscale_mode}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_tiles_compute_every_pixel() {
+        let mut params = RenderParameters::test_image(RenderQuality::Full, 1);
+        let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+        let tile = PixelRect {
+            x: 20,
+            y: 10,
+            width: 50,
+            height: 40,
+        };
+        let full = render_tile(&params, frame, tile).unwrap();
+        for quality in [RenderQuality::Draft, RenderQuality::QuadTree] {
+            params.quality = quality;
+            assert_eq!(
+                render_tile(&params, frame, tile).unwrap(),
+                full,
+                "{quality}"
+            );
+        }
+    }
+
+    #[test]
+    fn check_tile_outside_image() {
+        let params = RenderParameters::try_new(
+            4.try_into().unwrap(),
+            3.try_into().unwrap(),
+            10.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::L8,
+        )
+        .unwrap();
+        let frame = Frame::new(-0.75, 0.0, 3.0, 2.0);
+        for tile in [
+            PixelRect {
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 3,
+            },
+            PixelRect {
+                x: 1,
+                y: 1,
+                width: 3,
+                height: 3,
+            },
+            PixelRect {
+                x: u32::MAX,
+                y: 0,
+                width: 2,
+                height: 1,
+            },
+        ] {
+            assert_eq!(
+                render_tile(&params, frame, tile),
+                Err(RenderError::TileOutsideImage(tile))
+            );
+        }
+    }
+}
@@ -0,0 +1,425 @@
+//! Checks for combinations of render parameters that are valid, but are probably not what the user wants,
+//! such as a render that takes far longer than it needs to or an image that shows nothing of interest.
+
+use core::fmt;
+use core::num::{NonZeroU32, NonZeroU64, NonZeroU8, ParseFloatError};
+use core::str::FromStr;
+
+use image::DynamicImage;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::interior::InteriorProvenance;
+use crate::{
+    auto_max_iterations, in_main_cardioid_or_bulb, potential_in_precision, try_render, Escape,
+    Exponent, Formula, Frame, RenderError, RenderParameters,
+};
+
+/// Supersampling more than this many samples along each axis of a pixel is warned about in large images.
+const MAX_SENSIBLE_SSAA: u8 = 6;
+
+/// The number of pixels of an 8K image, from which the supersampling factor is warned about.
+const LARGE_IMAGE_PIXELS: u64 = 7680 * 4320;
+
+/// Images with fewer than this fraction of the iterations given by [`auto_max_iterations`] are warned about.
+const MIN_ITERATION_FRACTION: u64 = 4;
+
+/// The number of points along each edge of the frame that are checked to lie inside the main cardioid or bulb.
+const EDGE_CHECKS: u32 = 256;
+
+/// The number of points along each axis of the frame that [`dark_frame_warning`] iterates.
+const DARK_FRAME_CHECKS: u32 = 64;
+
+/// A combination of render parameters that is probably a mistake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderWarning {
+    /// The image is large enough that supersampling it this much takes much longer
+    /// than it needs to for the edges to look smooth.
+    ExcessiveSupersampling {
+        sqrt_samples_per_pixel: NonZeroU8,
+        x_resolution: u32,
+        y_resolution: u32,
+    },
+    /// The view is zoomed in so far that most of the details are lost with this few iterations.
+    TooFewIterations {
+        max_iterations: NonZeroU64,
+        suggested: NonZeroU64,
+    },
+    /// The whole view is inside the main cardioid or period 2 bulb of the Mandelbrot set,
+    /// so every pixel is inside the set.
+    InsideCardioid,
+    /// More than a [`DarkFrameThreshold`] of the view did not escape within the maximum number of iterations,
+    /// so it is colored like the inside of the set. Some of it may escape with more iterations.
+    /// The fraction is `reached_max_iterations` out of `checked_points` points spread over the view.
+    DarkFrame {
+        max_iterations: NonZeroU64,
+        reached_max_iterations: u32,
+        checked_points: u32,
+    },
+}
+
+impl fmt::Display for RenderWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ExcessiveSupersampling {
+                sqrt_samples_per_pixel,
+                x_resolution,
+                y_resolution,
+            } => write!(
+                f,
+                "an SSAA factor of {sqrt_samples_per_pixel} takes {} samples per pixel, which is more than a {x_resolution}x{y_resolution} image needs",
+                u16::from(sqrt_samples_per_pixel.get()).pow(2)
+            ),
+            Self::TooFewIterations {
+                max_iterations,
+                suggested,
+            } => write!(
+                f,
+                "{max_iterations} iterations lose most of the details at this zoom, try around {suggested}"
+            ),
+            Self::InsideCardioid => write!(
+                f,
+                "the whole view is inside the main cardioid or period 2 bulb, so every pixel is inside the set"
+            ),
+            Self::DarkFrame {
+                max_iterations,
+                reached_max_iterations,
+                checked_points,
+            } => write!(
+                f,
+                "{:.0}% of the view reached the maximum of {max_iterations} iterations, try doubling them to {}",
+                100.0 * f64::from(*reached_max_iterations) / f64::from(*checked_points),
+                max_iterations.saturating_mul(NonZeroU64::new(2).expect("2 is not zero"))
+            ),
+        }
+    }
+}
+
+/// An image along with the warnings about the parameters it was rendered with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOutput {
+    pub image: DynamicImage,
+    pub warnings: Vec<RenderWarning>,
+}
+
+/// Returns the warnings about rendering the given frame with the given parameters, see [`RenderWarning`].
+/// Is fast enough to check the parameters before every render.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{render_warnings, Frame, RenderParameters, RenderWarning};
+/// # use color_space::SupportedColorType;
+/// let params = RenderParameters::try_new(
+///     1920.try_into().unwrap(),
+///     1080.try_into().unwrap(),
+///     255.try_into().unwrap(),
+///     3.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// assert!(render_warnings(&params, Frame::new(-0.75, 0.0, 3.5, 2.0)).is_empty());
+/// assert_eq!(
+///     render_warnings(&params, Frame::new(-0.1, 0.1, 0.02, 0.01)),
+///     [RenderWarning::InsideCardioid]
+/// );
+/// ```
+#[must_use]
+pub fn render_warnings(render_parameters: &RenderParameters, frame: Frame) -> Vec<RenderWarning> {
+    let mut warnings = Vec::new();
+
+    let x_resolution = u32::from(render_parameters.x_resolution);
+    let y_resolution = u32::from(render_parameters.y_resolution);
+    let sqrt_samples_per_pixel = render_parameters.sqrt_samples_per_pixel;
+    if sqrt_samples_per_pixel.get() > MAX_SENSIBLE_SSAA
+        && u64::from(x_resolution) * u64::from(y_resolution) >= LARGE_IMAGE_PIXELS
+    {
+        warnings.push(RenderWarning::ExcessiveSupersampling {
+            sqrt_samples_per_pixel,
+            x_resolution,
+            y_resolution,
+        });
+    }
+
+    let suggested = auto_max_iterations(
+        frame,
+        NonZeroU32::new(y_resolution).expect("the resolution is not zero"),
+    );
+    let max_iterations = render_parameters.max_iterations;
+    if max_iterations.get() < suggested.get() / MIN_ITERATION_FRACTION {
+        warnings.push(RenderWarning::TooFewIterations {
+            max_iterations,
+            suggested,
+        });
+    }
+
+    if matches!(render_parameters.formula, Formula::Mandelbrot)
+        && render_parameters.exponent == Exponent::TWO
+        && inside_cardioid_or_bulb(frame)
+    {
+        warnings.push(RenderWarning::InsideCardioid);
+    }
+
+    warnings
+}
+
+/// Works like [`try_render`], but also returns the warnings about the parameters, see [`render_warnings`].
+///
+/// # Errors
+/// Returns an error if the center of the frame is not finite, if its distances
+/// are not finite and positive, or if the memory for the image can not be allocated.
+pub fn try_render_with_warnings(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    verbose: bool,
+) -> Result<RenderOutput, RenderError> {
+    Ok(RenderOutput {
+        image: try_render(render_parameters, render_region, verbose)?,
+        warnings: render_warnings(render_parameters, render_region),
+    })
+}
+
+/// A [`RenderWarning::DarkFrame`] is given when more than this fraction of the view
+/// reaches the maximum number of iterations. Is known to be between 0 and 1.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{DarkFrameThreshold, InvalidDarkFrameThresholdError};
+/// assert_eq!("0.75".parse(), Ok(DarkFrameThreshold::DEFAULT));
+/// assert_eq!(
+///     DarkFrameThreshold::try_from(-0.5),
+///     Err(InvalidDarkFrameThresholdError::OutOfRange)
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DarkFrameThreshold(f64);
+
+impl DarkFrameThreshold {
+    /// Views with this much of the inside of the set in them are rare once the iterations suit the zoom.
+    pub const DEFAULT: Self = Self(0.75);
+
+    #[must_use]
+    pub const fn get(&self) -> f64 {
+        self.0
+    }
+}
+
+impl Default for DarkFrameThreshold {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl fmt::Display for DarkFrameThreshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<f64> for DarkFrameThreshold {
+    type Error = InvalidDarkFrameThresholdError;
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        if (0.0..=1.0).contains(&value) {
+            Ok(Self(value))
+        } else {
+            Err(InvalidDarkFrameThresholdError::OutOfRange)
+        }
+    }
+}
+
+impl From<DarkFrameThreshold> for f64 {
+    fn from(value: DarkFrameThreshold) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidDarkFrameThresholdError {
+    OutOfRange,
+    InvalidValue(ParseFloatError),
+}
+
+impl fmt::Display for InvalidDarkFrameThresholdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange => write!(f, "the dark frame threshold must be between 0 and 1"),
+            Self::InvalidValue(e) => {
+                write!(f, "the dark frame threshold could not be parsed: {e}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvalidDarkFrameThresholdError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidValue(e) => Some(e),
+            Self::OutOfRange => None,
+        }
+    }
+}
+
+impl FromStr for DarkFrameThreshold {
+    type Err = InvalidDarkFrameThresholdError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<f64>()
+            .map_err(InvalidDarkFrameThresholdError::InvalidValue)?
+            .try_into()
+    }
+}
+
+/// Returns a [`RenderWarning::DarkFrame`] if more than `threshold` of the points of a grid spread over the frame
+/// reach the maximum number of iterations. Points that are known to be inside the set without being iterated,
+/// like those in the main cardioid, are not counted, since more iterations would not change them.
+///
+/// Unlike [`render_warnings`] this iterates points, so it takes about as long as rendering a 64x64 image.
+/// It is meant to be called once an image has been rendered, to suggest raising the iterations for the next one.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{dark_frame_warning, DarkFrameThreshold, Frame, RenderParameters, RenderWarning};
+/// # use color_space::SupportedColorType;
+/// let mut params = RenderParameters::try_new(
+///     1920.try_into().unwrap(),
+///     1080.try_into().unwrap(),
+///     10.try_into().unwrap(),
+///     3.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// // Most of the points around this minibrot need far more than 10 iterations to escape.
+/// let minibrot = Frame::new(-1.768_6, 0.0, 0.04, 0.0225);
+/// let threshold = DarkFrameThreshold::DEFAULT;
+/// assert!(matches!(
+///     dark_frame_warning(&params, minibrot, threshold),
+///     Some(RenderWarning::DarkFrame { .. })
+/// ));
+///
+/// params.max_iterations = 1000.try_into().unwrap();
+/// assert_eq!(dark_frame_warning(&params, minibrot, threshold), None);
+/// ```
+#[must_use]
+pub fn dark_frame_warning(
+    render_parameters: &RenderParameters,
+    frame: Frame,
+    threshold: DarkFrameThreshold,
+) -> Option<RenderWarning> {
+    let left = frame.center_real - frame.real_distance / 2.0;
+    let bottom = frame.center_imag - frame.imag_distance / 2.0;
+    let checked_points = DARK_FRAME_CHECKS * DARK_FRAME_CHECKS;
+    let reached_max_iterations = (0..checked_points)
+        .into_par_iter()
+        .filter(|i| {
+            // The points are in the middle of the cells of a grid, like the pixels of a small image.
+            let (x, y) = (i % DARK_FRAME_CHECKS, i / DARK_FRAME_CHECKS);
+            let re =
+                left + (f64::from(x) + 0.5) / f64::from(DARK_FRAME_CHECKS) * frame.real_distance;
+            let im =
+                bottom + (f64::from(y) + 0.5) / f64::from(DARK_FRAME_CHECKS) * frame.imag_distance;
+            matches!(
+                potential_in_precision(re, im, render_parameters),
+                Escape::Inside(InteriorProvenance::MaxIterations { .. })
+            )
+        })
+        .count();
+    // The count is at most `checked_points`, which is a `u32`.
+    let reached_max_iterations = u32::try_from(reached_max_iterations).expect("fits in a u32");
+
+    (f64::from(reached_max_iterations) > threshold.get() * f64::from(checked_points)).then_some(
+        RenderWarning::DarkFrame {
+            max_iterations: render_parameters.max_iterations,
+            reached_max_iterations,
+            checked_points,
+        },
+    )
+}
+
+/// Returns whether the edges of the frame lie inside the main cardioid or period 2 bulb.
+/// Neither of them have holes, so the rest of the frame does as well.
+fn inside_cardioid_or_bulb(frame: Frame) -> bool {
+    let left = frame.center_real - frame.real_distance / 2.0;
+    let bottom = frame.center_imag - frame.imag_distance / 2.0;
+    (0..=EDGE_CHECKS).all(|i| {
+        let t = f64::from(i) / f64::from(EDGE_CHECKS);
+        let (re, im) = (
+            left + t * frame.real_distance,
+            bottom + t * frame.imag_distance,
+        );
+        in_main_cardioid_or_bulb(re, bottom)
+            && in_main_cardioid_or_bulb(re, bottom + frame.imag_distance)
+            && in_main_cardioid_or_bulb(left, im)
+            && in_main_cardioid_or_bulb(left + frame.real_distance, im)
+    })
+}
+
+#[cfg(test)]
+mod test_warnings {
+    use super::*;
+    use color_space::SupportedColorType;
+
+    #[test]
+    fn check_warnings() {
+        let mut params = RenderParameters::try_new(
+            7680.try_into().unwrap(),
+            4320.try_into().unwrap(),
+            1000.try_into().unwrap(),
+            7.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let overview = Frame::new(-0.75, 0.0, 3.5, 2.0);
+        assert_eq!(
+            render_warnings(&params, overview),
+            [RenderWarning::ExcessiveSupersampling {
+                sqrt_samples_per_pixel: 7.try_into().unwrap(),
+                x_resolution: 7680,
+                y_resolution: 4320,
+            }]
+        );
+        params.sqrt_samples_per_pixel = 6.try_into().unwrap();
+        assert!(render_warnings(&params, overview).is_empty());
+
+        let deep = Frame::new(-0.745, 0.1, 1.75e-40, 1e-40);
+        assert!(matches!(
+            render_warnings(&params, deep)[..],
+            [RenderWarning::TooFewIterations { suggested, .. }] if suggested.get() > 10_000
+        ));
+
+        // The view straddles the edge of the cardioid, or its pinch where it meets the period 2 bulb.
+        assert!(render_warnings(&params, Frame::new(0.2, 0.0, 0.2, 0.1)).is_empty());
+        assert!(render_warnings(&params, Frame::new(-0.75, 0.0, 0.1, 0.04)).is_empty());
+        // Other fractals have no cardioid.
+        params.formula = Formula::Mandelbar;
+        assert!(render_warnings(&params, Frame::new(-0.1, 0.1, 0.02, 0.01)).is_empty());
+    }
+
+    #[test]
+    fn check_dark_frame_warning() {
+        let params = RenderParameters::try_new(
+            100.try_into().unwrap(),
+            100.try_into().unwrap(),
+            20.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        let near_boundary = Frame::new(-0.75, 0.1, 0.01, 0.01);
+        let none = DarkFrameThreshold::try_from(0.0).unwrap();
+        let all = DarkFrameThreshold::try_from(1.0).unwrap();
+        let Some(RenderWarning::DarkFrame {
+            reached_max_iterations,
+            checked_points,
+            ..
+        }) = dark_frame_warning(&params, near_boundary, none)
+        else {
+            panic!("no point reached the maximum number of iterations");
+        };
+        assert!(reached_max_iterations > checked_points / 2);
+        assert_eq!(dark_frame_warning(&params, near_boundary, all), None);
+
+        // Points that are known to be inside the set are not iterated, so they never reach the maximum.
+        let inside_cardioid = Frame::new(-0.1, 0.1, 0.02, 0.01);
+        assert_eq!(dark_frame_warning(&params, inside_cardioid, none), None);
+    }
+}
@@ -0,0 +1,379 @@
+//! Timing how long every tile or band of an image takes to render, in order to find the regions
+//! of the complex plane that are unusually slow, e.g. because many of their points escape
+//! after almost the maximum number of iterations.
+
+use std::time::{Duration, Instant};
+
+use image::{Rgb, RgbImage};
+
+use crate::scheduling::TILE_SIZE;
+use crate::{
+    check_frame, render_with_optional_mask, Frame, RenderError, RenderParameters, Scheduling,
+};
+
+/// How long it took to render one segment of a band of the image,
+/// starting at the pixel with index `first_pixel` counted from the bottom of the band.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SegmentTime {
+    pub(crate) band_index: usize,
+    pub(crate) first_pixel: usize,
+    pub(crate) pixels: usize,
+    pub(crate) duration: Duration,
+}
+
+/// How long it took to render a rectangle of pixels in an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileTime {
+    /// The column of the leftmost pixel of the tile.
+    pub x: u32,
+    /// The row of the topmost pixel of the tile, counted from the top of the image.
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// The region of the complex plane that is covered by the tile.
+    pub region: Frame,
+    pub duration: Duration,
+}
+
+impl TileTime {
+    /// Returns the average number of seconds it took to render a pixel of the tile.
+    #[must_use]
+    pub fn seconds_per_pixel(&self) -> f64 {
+        self.duration.as_secs_f64() / (f64::from(self.width) * f64::from(self.height))
+    }
+}
+
+/// The render times of all the tiles or bands of an image, as they were split up between the threads.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderTimings {
+    pub x_resolution: u32,
+    pub y_resolution: u32,
+    pub tiles: Vec<TileTime>,
+}
+
+impl RenderTimings {
+    /// Sums up the times of the rendered segments into the tiles of the given scheduling strategy.
+    /// Strategies that do not use tiles are timed band by band.
+    pub(crate) fn new(
+        render_parameters: &RenderParameters,
+        render_region: Frame,
+        scheduling: Scheduling,
+        segment_times: &[SegmentTime],
+    ) -> Self {
+        let x_resolution = u32::from(render_parameters.x_resolution);
+        let y_resolution = u32::from(render_parameters.y_resolution);
+        let mut tiles: Vec<((usize, usize), TileTime)> = Vec::new();
+        for time in segment_times {
+            let key = if scheduling == Scheduling::Tiles {
+                (time.band_index / TILE_SIZE, time.first_pixel / TILE_SIZE)
+            } else {
+                (time.band_index, 0)
+            };
+            // The pixel indices fit in a u32 since the resolutions do.
+            let band = u32::try_from(time.band_index).unwrap_or(u32::MAX);
+            let bottom = u32::try_from(time.first_pixel).unwrap_or(u32::MAX);
+            let pixels = u32::try_from(time.pixels).unwrap_or(u32::MAX);
+            let segment = TileTime {
+                x: band,
+                y: y_resolution.saturating_sub(bottom.saturating_add(pixels)),
+                width: 1,
+                height: pixels,
+                region: render_region,
+                duration: time.duration,
+            };
+            // The segments of a tile are rendered by the same unit of work, so they are next to each other.
+            match tiles.last_mut().filter(|(k, _)| *k == key) {
+                Some((_, tile)) => {
+                    let right = (tile.x + tile.width).max(segment.x + 1);
+                    let bottom = (tile.y + tile.height).max(segment.y + segment.height);
+                    tile.x = tile.x.min(segment.x);
+                    tile.y = tile.y.min(segment.y);
+                    tile.width = right - tile.x;
+                    tile.height = bottom - tile.y;
+                    tile.duration += segment.duration;
+                }
+                None => tiles.push((key, segment)),
+            }
+        }
+
+        let mut tiles: Vec<TileTime> = tiles.into_iter().map(|(_, tile)| tile).collect();
+        for tile in &mut tiles {
+            let (x, y) = (f64::from(tile.x), f64::from(tile.y));
+            tile.region = Frame::from_corners(
+                render_region.pixel_to_point(x, y, render_parameters),
+                render_region.pixel_to_point(
+                    x + f64::from(tile.width),
+                    y + f64::from(tile.height),
+                    render_parameters,
+                ),
+            );
+        }
+        tiles.sort_by_key(|tile| (tile.y, tile.x));
+
+        Self {
+            x_resolution,
+            y_resolution,
+            tiles,
+        }
+    }
+
+    /// Returns the median of the times it took to render a pixel of each tile, in seconds,
+    /// or zero if no tiles were timed.
+    #[must_use]
+    pub fn median_seconds_per_pixel(&self) -> f64 {
+        let mut times: Vec<f64> = self.tiles.iter().map(TileTime::seconds_per_pixel).collect();
+        times.sort_unstable_by(f64::total_cmp);
+        match times.len() {
+            0 => 0.0,
+            n if n % 2 == 0 => (times[n / 2 - 1] + times[n / 2]) / 2.0,
+            n => times[n / 2],
+        }
+    }
+
+    /// Returns the tiles that took more than `factor` times the median time per pixel to render,
+    /// slowest first. Time per pixel is compared rather than the total time, so that the smaller
+    /// tiles at the edges of the image are judged the same way as the others.
+    #[must_use]
+    pub fn slow_tiles(&self, factor: f64) -> Vec<&TileTime> {
+        let limit = factor * self.median_seconds_per_pixel();
+        let mut slow: Vec<&TileTime> = self
+            .tiles
+            .iter()
+            .filter(|tile| tile.seconds_per_pixel() > limit)
+            .collect();
+        slow.sort_unstable_by(|a, b| b.seconds_per_pixel().total_cmp(&a.seconds_per_pixel()));
+        slow
+    }
+
+    /// Returns an image with the resolution of the rendered one where every tile is colored
+    /// by how long it took to render a pixel of it: black for the fastest tile, through red and yellow,
+    /// to white for the slowest one. The times are compared on a logarithmic scale, so that a single
+    /// very slow tile does not make all the others look the same. Pixels that were not timed are black.
+    #[must_use]
+    pub fn heat_map(&self) -> RgbImage {
+        let mut image = RgbImage::new(self.x_resolution, self.y_resolution);
+        let log_times: Vec<f64> = self
+            .tiles
+            .iter()
+            .map(|tile| tile.seconds_per_pixel().ln())
+            .collect();
+        let fastest = log_times.iter().copied().fold(f64::INFINITY, f64::min);
+        let slowest = log_times.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        for (tile, log_time) in self.tiles.iter().zip(log_times) {
+            // Tiles that took no measurable time have a logarithm of minus infinity,
+            // and are as cold as can be.
+            let heat = if slowest > fastest {
+                (log_time - fastest) / (slowest - fastest)
+            } else {
+                0.0
+            };
+            let color = heat_color(heat);
+            for y in tile.y..(tile.y + tile.height).min(self.y_resolution) {
+                for x in tile.x..(tile.x + tile.width).min(self.x_resolution) {
+                    image.put_pixel(x, y, color);
+                }
+            }
+        }
+        image
+    }
+}
+
+/// Maps a heat in \[0, 1\] to a color that goes from black through red and yellow to white.
+fn heat_color(heat: f64) -> Rgb<u8> {
+    // Each channel ramps up from 0 to 255 over its own third of the range.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let channel = |third: f64| (255.0 * (3.0 * heat - third).clamp(0.0, 1.0)).round() as u8;
+    Rgb([channel(0.0), channel(1.0), channel(2.0)])
+}
+
+/// Works like [`render`](crate::render), but also times how long every tile or band of the image
+/// takes to render. The image is split up as given by [`RenderParameters::scheduling`],
+/// and images that are split into tiles are timed tile by tile, while the others are timed band by band.
+/// Draft renders are not split up, and have no timings.
+///
+/// # Panics
+/// Panics if the memory for the image can not be allocated.
+/// Use [`try_render_timed`] to get an error instead.
+///
+/// # Example
+///
+/// ```
+/// # use mandellib::{render_timed, Frame, RenderParameters};
+/// # use color_space::SupportedColorType;
+/// let params = RenderParameters::try_new(
+///     30.try_into().unwrap(),
+///     20.try_into().unwrap(),
+///     100.try_into().unwrap(),
+///     1.try_into().unwrap(),
+///     SupportedColorType::Rgb8,
+/// )
+/// .unwrap();
+/// let (image, timings) = render_timed(&params, Frame::new(-0.75, 0.0, 3.0, 2.0), false);
+/// assert_eq!(timings.heat_map().dimensions(), (image.width(), image.height()));
+/// ```
+#[must_use]
+pub fn render_timed(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    verbose: bool,
+) -> (image::DynamicImage, RenderTimings) {
+    let mut timings = RenderTimings::default();
+    let image = render_with_optional_mask(
+        render_parameters,
+        render_region,
+        None,
+        crate::progress::verbose_progress(verbose).as_ref(),
+        Some(&mut timings),
+    )
+    .unwrap_or_else(|error| panic!("{error}"));
+    (image, timings)
+}
+
+/// Works like [`render_timed`], but checks that the frame can be rendered
+/// and returns an error instead of panicking if the image can not be allocated.
+///
+/// # Errors
+/// Returns an error if the center of the frame is not finite, if its distances
+/// are not finite and positive, or if the memory for the image can not be allocated.
+pub fn try_render_timed(
+    render_parameters: &RenderParameters,
+    render_region: Frame,
+    verbose: bool,
+) -> Result<(image::DynamicImage, RenderTimings), RenderError> {
+    check_frame(render_region)?;
+    let mut timings = RenderTimings::default();
+    let image = render_with_optional_mask(
+        render_parameters,
+        render_region,
+        None,
+        crate::progress::verbose_progress(verbose).as_ref(),
+        Some(&mut timings),
+    )?;
+    Ok((image, timings))
+}
+
+/// Returns the time that it takes to run `f`, or `None` if `timed` is false.
+pub(crate) fn time_if(timed: bool, f: impl FnOnce()) -> Option<Duration> {
+    let start = timed.then(Instant::now);
+    f();
+    start.map(|start| start.elapsed())
+}
+
+#[cfg(test)]
+mod test_watchdog {
+    use super::*;
+    use color_space::SupportedColorType;
+
+    fn parameters(scheduling: Scheduling) -> RenderParameters {
+        let mut parameters = RenderParameters::try_new(
+            200.try_into().unwrap(),
+            100.try_into().unwrap(),
+            100.try_into().unwrap(),
+            1.try_into().unwrap(),
+            SupportedColorType::Rgb8,
+        )
+        .unwrap();
+        parameters.scheduling = scheduling;
+        parameters
+    }
+
+    fn segment(band_index: usize, first_pixel: usize, pixels: usize, micros: u64) -> SegmentTime {
+        SegmentTime {
+            band_index,
+            first_pixel,
+            pixels,
+            duration: Duration::from_micros(micros),
+        }
+    }
+
+    #[test]
+    fn check_tiles() {
+        let frame = Frame::new(0.0, 0.0, 2.0, 1.0);
+        let times = [
+            segment(0, 0, 64, 10),
+            segment(1, 0, 64, 10),
+            segment(0, 64, 36, 30),
+            segment(64, 0, 64, 40),
+        ];
+        let timings = RenderTimings::new(
+            &parameters(Scheduling::Tiles),
+            frame,
+            Scheduling::Tiles,
+            &times,
+        );
+
+        // The two segments at the bottom left of the image are in the same tile.
+        assert_eq!(timings.tiles.len(), 3);
+        let (top, bottom) = (timings.tiles[0], timings.tiles[1]);
+        assert_eq!((top.x, top.y, top.width, top.height), (0, 0, 1, 36));
+        assert_eq!(
+            (bottom.x, bottom.y, bottom.width, bottom.height),
+            (0, 36, 2, 64)
+        );
+        assert_eq!(bottom.duration, Duration::from_micros(20));
+        // The tile in the top left corner of the image covers the top left of the frame.
+        assert!((top.region.center_real - (-1.0 + 0.005)).abs() < 1e-12);
+        assert!((top.region.center_imag - (0.5 - 0.18)).abs() < 1e-12);
+        assert!((top.region.imag_distance - 0.36).abs() < 1e-12);
+
+        // The top tile takes 30 / 36 µs per pixel, the median 40 / 64 µs and the bottom left 20 / 128 µs.
+        assert_eq!(timings.slow_tiles(1.0), [&top]);
+        assert!(timings.slow_tiles(2.0).is_empty());
+
+        let heat_map = timings.heat_map();
+        assert_eq!(heat_map.get_pixel(0, 0), &Rgb([255, 255, 255]));
+        assert_eq!(heat_map.get_pixel(1, 99), &Rgb([0, 0, 0]));
+        let heat =
+            ((40.0 / 64.0) / (20.0 / 128.0_f64)).ln() / ((30.0 / 36.0) / (20.0 / 128.0_f64)).ln();
+        assert_eq!(heat_map.get_pixel(64, 99), &heat_color(heat));
+        // Pixels that were not timed are black.
+        assert_eq!(heat_map.get_pixel(100, 50), &Rgb([0, 0, 0]));
+    }
+
+    #[test]
+    fn check_bands() {
+        let frame = Frame::new(0.0, 0.0, 2.0, 1.0);
+        // Bands are timed as a whole regardless of how they are split up.
+        let times = [
+            segment(0, 0, 100, 10),
+            segment(3, 0, 100, 40),
+            segment(7, 0, 100, 10),
+        ];
+        let timings = RenderTimings::new(
+            &parameters(Scheduling::Bands),
+            frame,
+            Scheduling::Interlaced,
+            &times,
+        );
+        assert_eq!(timings.tiles.len(), 3);
+        assert!((timings.median_seconds_per_pixel() - 1e-7).abs() < 1e-15);
+        let slow = timings.slow_tiles(2.0);
+        assert_eq!(slow.len(), 1);
+        assert_eq!((slow[0].x, slow[0].height), (3, 100));
+        assert_eq!(RenderTimings::default().median_seconds_per_pixel(), 0.0);
+    }
+
+    #[test]
+    fn check_render_timed() {
+        for scheduling in [Scheduling::Bands, Scheduling::Tiles, Scheduling::Interlaced] {
+            let params = parameters(scheduling);
+            let frame = Frame::new(-0.75, 0.5, 3.0, 1.5);
+            let (image, timings) = render_timed(&params, frame, false);
+            assert_eq!(image, crate::render(&params, frame, false));
+            // Every pixel is in exactly one tile.
+            let pixels: u32 = timings
+                .tiles
+                .iter()
+                .map(|tile| tile.width * tile.height)
+                .sum();
+            assert_eq!(pixels, 200 * 100, "{scheduling:?}");
+        }
+
+        let params = parameters(Scheduling::Automatic);
+        let frame = Frame::new(-0.75, f64::INFINITY, 3.0, 1.5);
+        assert!(matches!(
+            try_render_timed(&params, frame, false),
+            Err(RenderError::InvalidFrame(_))
+        ));
+    }
+}
@@ -0,0 +1,274 @@
+//! Stamping a logo or signature onto a rendered image.
+
+use core::fmt;
+use core::str::FromStr;
+
+use color_space::LinearRGB;
+use image::{DynamicImage, GenericImage, GenericImageView, Rgba};
+
+/// The distance between a watermark and the edges of the image it is placed in the corner of,
+/// as a fraction of the shortest side of the image.
+const MARGIN_FRACTION: f64 = 0.02;
+
+/// Where in an image a watermark is placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+impl WatermarkPosition {
+    pub const ALL: [Self; 5] = [
+        Self::TopLeft,
+        Self::TopRight,
+        Self::BottomLeft,
+        Self::BottomRight,
+        Self::Center,
+    ];
+
+    /// Returns the pixel coordinates of the top left corner of a watermark of the given size
+    /// in an image of the given size. The coordinates are negative if the watermark
+    /// does not fit in the image.
+    fn top_left(
+        self,
+        (width, height): (u32, u32),
+        (image_width, image_height): (u32, u32),
+    ) -> (i64, i64) {
+        // The margin is at most a few percent of the image, so the cast does not truncate.
+        #[allow(clippy::cast_possible_truncation)]
+        let margin = (f64::from(image_width.min(image_height)) * MARGIN_FRACTION).round() as i64;
+        let (width, height) = (i64::from(width), i64::from(height));
+        let (image_width, image_height) = (i64::from(image_width), i64::from(image_height));
+        let left = margin;
+        let right = image_width - width - margin;
+        let top = margin;
+        let bottom = image_height - height - margin;
+        match self {
+            Self::TopLeft => (left, top),
+            Self::TopRight => (right, top),
+            Self::BottomLeft => (left, bottom),
+            Self::BottomRight => (right, bottom),
+            Self::Center => ((image_width - width) / 2, (image_height - height) / 2),
+        }
+    }
+}
+
+impl fmt::Display for WatermarkPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TopLeft => write!(f, "tl"),
+            Self::TopRight => write!(f, "tr"),
+            Self::BottomLeft => write!(f, "bl"),
+            Self::BottomRight => write!(f, "br"),
+            Self::Center => write!(f, "center"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWatermarkPositionError(String);
+
+impl fmt::Display for ParseWatermarkPositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown watermark position \"{}\", expected one of \"tl\", \"tr\", \"bl\", \"br\" or \"center\"",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseWatermarkPositionError {}
+
+impl FromStr for WatermarkPosition {
+    type Err = ParseWatermarkPositionError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "tl" | "top-left" => Ok(Self::TopLeft),
+            "tr" | "top-right" => Ok(Self::TopRight),
+            "bl" | "bottom-left" => Ok(Self::BottomLeft),
+            "br" | "bottom-right" => Ok(Self::BottomRight),
+            "center" | "centre" => Ok(Self::Center),
+            _ => Err(ParseWatermarkPositionError(s.to_owned())),
+        }
+    }
+}
+
+/// A logo or signature that is composited onto rendered images.
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    logo: DynamicImage,
+    pub position: WatermarkPosition,
+    opacity: f64,
+}
+
+impl Watermark {
+    /// Creates a watermark from the given logo, which is placed at the given position
+    /// and drawn with its own transparency multiplied by `opacity`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the opacity is not in the range \[0, 1\].
+    pub fn new(
+        logo: DynamicImage,
+        position: WatermarkPosition,
+        opacity: f64,
+    ) -> Result<Self, WatermarkError> {
+        if (0.0..=1.0).contains(&opacity) {
+            Ok(Self {
+                logo,
+                position,
+                opacity,
+            })
+        } else {
+            Err(WatermarkError::InvalidOpacity(opacity))
+        }
+    }
+
+    /// The opacity that the watermark is drawn with.
+    #[must_use]
+    pub const fn opacity(&self) -> f64 {
+        self.opacity
+    }
+
+    /// Composites the watermark onto the image in linear color space.
+    /// The parts of the watermark that do not fit in the image are left out.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use image::{DynamicImage, Rgb, RgbImage, Rgba, RgbaImage};
+    /// # use mandellib::{Watermark, WatermarkPosition};
+    /// let logo = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([255, 255, 255, 255])));
+    /// let watermark = Watermark::new(logo, WatermarkPosition::TopLeft, 1.0).unwrap();
+    /// let mut image = DynamicImage::ImageRgb8(RgbImage::new(4, 4));
+    /// watermark.stamp(&mut image);
+    /// let image = image.into_rgb8();
+    /// assert_eq!(image[(1, 1)], Rgb([255, 255, 255]));
+    /// assert_eq!(image[(2, 2)], Rgb([0, 0, 0]));
+    /// ```
+    pub fn stamp(&self, image: &mut DynamicImage) {
+        let (left, top) = self
+            .position
+            .top_left(self.logo.dimensions(), image.dimensions());
+        for (x, y, logo_pixel) in self.logo.pixels() {
+            let (Ok(image_x), Ok(image_y)) = (
+                u32::try_from(left + i64::from(x)),
+                u32::try_from(top + i64::from(y)),
+            ) else {
+                continue;
+            };
+            if image_x >= image.width() || image_y >= image.height() {
+                continue;
+            }
+            let stamped = self.composite(logo_pixel, image.get_pixel(image_x, image_y));
+            image.put_pixel(image_x, image_y, stamped);
+        }
+    }
+
+    /// Returns the color of the logo pixel drawn over the image pixel.
+    fn composite(&self, logo: Rgba<u8>, image: Rgba<u8>) -> Rgba<u8> {
+        let logo_alpha = f64::from(logo[3]) / f64::from(u8::MAX) * self.opacity;
+        if logo_alpha == 0.0 {
+            return image;
+        }
+        let image_alpha = f64::from(image[3]) / f64::from(u8::MAX);
+        let alpha = logo_alpha + image_alpha * (1.0 - logo_alpha);
+        let logo_color = LinearRGB::from_srgb([logo[0], logo[1], logo[2]]);
+        let image_color = LinearRGB::from_srgb([image[0], image[1], image[2]]);
+        let [r, g, b] =
+            ((logo_color * logo_alpha + image_color * (image_alpha * (1.0 - logo_alpha))) / alpha)
+                .to_srgb();
+        // The alpha is in the range [0, 1], so the cast does not truncate.
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        Rgba([r, g, b, (alpha * f64::from(u8::MAX)).round() as u8])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatermarkError {
+    InvalidOpacity(f64),
+}
+
+impl fmt::Display for WatermarkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidOpacity(opacity) => write!(
+                f,
+                "the watermark opacity must be between 0 and 1, but it was {opacity}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WatermarkError {}
+
+#[cfg(test)]
+mod test_watermark {
+    use super::*;
+    use image::{GrayImage, Luma, RgbaImage};
+
+    #[test]
+    fn check_positions() {
+        // A 100x50 image has a margin of one pixel.
+        for (position, expected) in [
+            (WatermarkPosition::TopLeft, (1, 1)),
+            (WatermarkPosition::TopRight, (89, 1)),
+            (WatermarkPosition::BottomLeft, (1, 39)),
+            (WatermarkPosition::BottomRight, (89, 39)),
+            (WatermarkPosition::Center, (45, 20)),
+        ] {
+            assert_eq!(position.top_left((10, 10), (100, 50)), expected);
+        }
+    }
+
+    #[test]
+    fn check_stamping() {
+        let mut logo = RgbaImage::from_pixel(3, 2, Rgba([255, 255, 255, 255]));
+        // A transparent pixel in the logo leaves the image as it was.
+        logo.put_pixel(0, 0, Rgba([255, 255, 255, 0]));
+        let watermark = Watermark::new(
+            DynamicImage::ImageRgba8(logo),
+            WatermarkPosition::BottomRight,
+            0.5,
+        )
+        .unwrap();
+
+        let mut image = DynamicImage::ImageLuma8(GrayImage::new(8, 6));
+        watermark.stamp(&mut image);
+        let image = image.into_luma8();
+        // Half of the light of white in sRGB is 188, not 128.
+        let stamped = LinearRGB::new(0.5, 0.5, 0.5).to_srgb()[0];
+        assert_eq!(stamped, 188);
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let expected = if (5..8).contains(&x) && (4..6).contains(&y) && (x, y) != (5, 4) {
+                stamped
+            } else {
+                0
+            };
+            assert_eq!(*pixel, Luma([expected]), "at ({x}, {y})");
+        }
+
+        // A watermark that is larger than the image is cut off.
+        let mut small = DynamicImage::ImageLuma8(GrayImage::new(2, 1));
+        watermark.stamp(&mut small);
+        assert_eq!(small.into_luma8().into_raw(), vec![stamped, stamped]);
+    }
+
+    #[test]
+    fn check_parsing() {
+        for position in WatermarkPosition::ALL {
+            assert_eq!(position.to_string().parse(), Ok(position));
+        }
+        assert_eq!(" Top-Left".parse(), Ok(WatermarkPosition::TopLeft));
+        assert!("middle".parse::<WatermarkPosition>().is_err());
+        let logo = DynamicImage::ImageRgba8(RgbaImage::new(1, 1));
+        for opacity in [-0.1, 1.5, f64::NAN] {
+            assert!(Watermark::new(logo.clone(), WatermarkPosition::Center, opacity).is_err());
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use core::fmt;
+use core::num::ParseFloatError;
+use core::str::FromStr;
+
+use crate::math::{exp2, log2};
+
+/// How far a view is zoomed in, on an exponential scale.
+/// A level of 0 shows the whole set, and every increase of the level by one
+/// halves the width and height of the view. Negative levels zoom out.
+/// Is known to be finite.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Zoom(f64);
+
+impl Zoom {
+    /// The zoom level that shows the whole set.
+    pub const NONE: Self = Self(0.0);
+
+    /// The imaginary distance covered by a view at zoom level 0.
+    pub const UNZOOMED_IMAG_DISTANCE: f64 = 8.0 / 3.0;
+
+    /// The zoom level.
+    #[must_use]
+    pub const fn level(&self) -> f64 {
+        self.0
+    }
+
+    /// How many times smaller the view is than at zoom level 0, i.e. 2^level.
+    #[must_use]
+    pub fn factor(&self) -> f64 {
+        exp2(self.0)
+    }
+
+    /// The imaginary distance covered by a view at this zoom level.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::Zoom;
+    /// assert_eq!(Zoom::NONE.imag_distance(), 8.0 / 3.0);
+    /// assert_eq!(Zoom::try_from(2.0).unwrap().imag_distance(), 2.0 / 3.0);
+    /// ```
+    #[must_use]
+    pub fn imag_distance(&self) -> f64 {
+        Self::UNZOOMED_IMAG_DISTANCE / self.factor()
+    }
+
+    /// The real distance, i.e. the width, covered by a view at this zoom level
+    /// with the given ratio of width to height.
+    #[must_use]
+    pub fn real_distance(&self, aspect_ratio: f64) -> f64 {
+        self.imag_distance() * aspect_ratio
+    }
+
+    /// Returns the zoom level of a view that covers the given imaginary distance.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the distance is not positive and finite.
+    pub fn from_imag_distance(imag_distance: f64) -> Result<Self, InvalidZoomError> {
+        if imag_distance > 0.0 {
+            log2(Self::UNZOOMED_IMAG_DISTANCE / imag_distance).try_into()
+        } else {
+            Err(InvalidZoomError::NonFinite)
+        }
+    }
+
+    /// Returns the zoom level of a view that covers the given real distance,
+    /// i.e. is that wide, with the given ratio of width to height.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the resulting imaginary distance is not positive and finite.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use mandellib::Zoom;
+    /// let zoom = Zoom::try_from(5.5).unwrap();
+    /// let width = zoom.real_distance(16.0 / 9.0);
+    /// let level = Zoom::from_real_distance(width, 16.0 / 9.0).unwrap().level();
+    /// assert!((level - 5.5).abs() < 1e-12);
+    /// ```
+    pub fn from_real_distance(
+        real_distance: f64,
+        aspect_ratio: f64,
+    ) -> Result<Self, InvalidZoomError> {
+        Self::from_imag_distance(real_distance / aspect_ratio)
+    }
+}
+
+impl fmt::Display for Zoom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl TryFrom<f64> for Zoom {
+    type Error = InvalidZoomError;
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        if value.is_finite() {
+            Ok(Self(value))
+        } else {
+            Err(InvalidZoomError::NonFinite)
+        }
+    }
+}
+
+impl From<Zoom> for f64 {
+    fn from(value: Zoom) -> Self {
+        value.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidZoomError {
+    NonFinite,
+    InvalidValue(ParseFloatError),
+}
+
+impl fmt::Display for InvalidZoomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonFinite => write!(f, "the zoom level must be finite"),
+            Self::InvalidValue(e) => write!(f, "the zoom level could not be parsed: {e}"),
+        }
+    }
+}
+
+impl core::error::Error for InvalidZoomError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::InvalidValue(e) => Some(e),
+            Self::NonFinite => None,
+        }
+    }
+}
+
+impl FromStr for Zoom {
+    type Err = InvalidZoomError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim()
+            .parse::<f64>()
+            .map_err(InvalidZoomError::InvalidValue)?
+            .try_into()
+    }
+}
+
+#[cfg(test)]
+mod test_zoom {
+    use super::*;
+    use crate::Frame;
+
+    #[test]
+    fn check_distances() {
+        for level in [-2.0, 0.0, 0.5, 3.0, 47.25] {
+            let zoom = Zoom::try_from(level).unwrap();
+            for aspect_ratio in [1.0, 1.5, 16.0 / 9.0, 0.25] {
+                let frame = Frame::from_zoom(-0.75, 0.1, zoom, aspect_ratio);
+                let expected = 8.0 / (3.0 * 2.0_f64.powf(level));
+                assert!((frame.imag_distance / expected - 1.0).abs() < 1e-15);
+                assert!((frame.real_distance / frame.imag_distance - aspect_ratio).abs() < 1e-12);
+                let recovered = frame.zoom().unwrap().level();
+                assert!((recovered - level).abs() < 1e-12, "{recovered} != {level}");
+            }
+        }
+        assert!(Zoom::from_imag_distance(0.0).is_err());
+        assert!(Zoom::from_imag_distance(-1.0).is_err());
+    }
+
+    #[test]
+    fn check_parsing() {
+        assert_eq!("0".parse(), Ok(Zoom::NONE));
+        assert_eq!(" -1.5".parse::<Zoom>().map(|z| z.level()), Ok(-1.5));
+        assert_eq!("inf".parse::<Zoom>(), Err(InvalidZoomError::NonFinite));
+        assert!(matches!(
+            "deep".parse::<Zoom>(),
+            Err(InvalidZoomError::InvalidValue(_))
+        ));
+        let zoom = Zoom::try_from(12.125).unwrap();
+        assert_eq!(zoom.to_string().parse(), Ok(zoom));
+    }
+}
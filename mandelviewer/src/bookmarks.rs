@@ -0,0 +1,49 @@
+//! Saving views as bookmarks with notes and tags, and going back to them later.
+//!
+//! The bookmarks are stored in the same file that `mandelbrot bookmarks` reads,
+//! so that every view with a given tag can be rendered from the command line.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use mandellib::{Bookmark, BookmarkLibrary, Zoom};
+
+/// Reads the bookmarks in the file at the path.
+/// A file that does not exist holds no bookmarks, since none have been saved yet.
+pub fn load_library(path: &Path) -> Result<BookmarkLibrary, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => text
+            .parse()
+            .map_err(|e| format!("could not read the bookmarks in {}: {e}", path.display())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BookmarkLibrary::default()),
+        Err(e) => Err(format!("could not open {}: {e}", path.display())),
+    }
+}
+
+/// Writes all the bookmarks to the file at the path, replacing what was there.
+pub fn save_library(library: &BookmarkLibrary, path: &Path) -> Result<(), String> {
+    fs::write(path, library.to_string())
+        .map_err(|e| format!("could not save the bookmarks to {}: {e}", path.display()))
+}
+
+/// Returns the real and imaginary parts of the center of the view of the bookmark, and its zoom level.
+/// Coordinates that the bookmark does not store are those of the default view of `mandelbrot`.
+pub fn bookmarked_view(bookmark: &Bookmark) -> Result<(f64, f64, Zoom), String> {
+    let center = |name: &str, default: f64| -> Result<f64, String> {
+        bookmark.argument(name).map_or(Ok(default), |value| {
+            value
+                .parse()
+                .map_err(|_| format!("the {name} of \"{}\" is not a number", bookmark.name))
+        })
+    };
+    let zoom = bookmark
+        .argument("zoom-level")
+        .map_or(Ok(Zoom::NONE), str::parse)
+        .map_err(|e| format!("the zoom level of \"{}\" is invalid: {e}", bookmark.name))?;
+    Ok((
+        center("real-center", -0.75)?,
+        center("imag-center", 0.0)?,
+        zoom,
+    ))
+}
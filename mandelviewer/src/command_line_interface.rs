@@ -1,4 +1,5 @@
 use clap::Parser;
+use mandellib::DarkFrameThreshold;
 
 #[derive(Parser)]
 #[clap(author, version, about)]
@@ -9,4 +10,14 @@ pub struct Cli {
     /// If this is not given the program lets the parallelism library decide.
     #[arg(short, long)]
     pub jobs: Option<core::num::NonZeroUsize>,
+
+    /// Ask for confirmation before starting a full resolution render that is estimated
+    /// to take longer than this many seconds. Set to 0 to never ask.
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    pub render_time_warning: u64,
+
+    /// Warn after a full resolution render if more than this fraction, between 0 and 1,
+    /// of the view reached the maximum number of iterations, and offer to double them.
+    #[arg(long, value_name = "FRACTION", default_value_t = DarkFrameThreshold::DEFAULT)]
+    pub dark_frame_threshold: DarkFrameThreshold,
 }
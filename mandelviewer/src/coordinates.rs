@@ -0,0 +1,67 @@
+//! How the coordinates of the center of the view are shown and copied.
+//! The view itself always keeps them in full precision, but most of their 17 digits
+//! only move the image by a fraction of a pixel at shallow zooms, so they can be cut down
+//! to the number of decimals that [`Frame::coordinate_decimals`] finds for the current view.
+
+use core::fmt;
+
+use mandellib::Frame;
+
+/// How the coordinates of the view are shortened when they are shown or copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoordinateRounding {
+    /// Show every digit of the coordinates.
+    #[default]
+    Full,
+    /// Round the coordinates to the decimals that the zoom needs.
+    Round,
+    /// Cut off the decimals that the zoom does not need.
+    Truncate,
+}
+
+impl CoordinateRounding {
+    pub const ALL: [Self; 3] = [Self::Full, Self::Round, Self::Truncate];
+
+    /// Returns the coordinate as it is shown with `decimals` decimals.
+    pub fn format(self, coordinate: f64, decimals: usize) -> String {
+        match self {
+            Self::Full => coordinate.to_string(),
+            Self::Round => format!("{coordinate:.decimals$}"),
+            Self::Truncate => {
+                // Formatting rounds, so the digits that are cut off are formatted as well
+                // to make sure that the rounding never carries into the ones that are kept.
+                const EXTRA_DIGITS: usize = 20;
+                let mut text = format!("{coordinate:.*}", decimals + EXTRA_DIGITS);
+                text.truncate(text.len() - EXTRA_DIGITS);
+                text.trim_end_matches('.').to_owned()
+            }
+        }
+    }
+
+    /// Returns the coordinate with the digits that are not shown removed.
+    pub fn apply(self, coordinate: f64, decimals: usize) -> f64 {
+        self.format(coordinate, decimals)
+            .parse()
+            .unwrap_or(coordinate)
+    }
+
+    /// Returns the frame with its center shortened like it is shown.
+    pub fn apply_to_center(self, frame: Frame, decimals: usize) -> Frame {
+        Frame {
+            center_real: self.apply(frame.center_real, decimals),
+            center_imag: self.apply(frame.center_imag, decimals),
+            ..frame
+        }
+    }
+}
+
+impl fmt::Display for CoordinateRounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Full => "full precision",
+            Self::Round => "rounded to the zoom",
+            Self::Truncate => "truncated to the zoom",
+        };
+        f.write_str(name)
+    }
+}
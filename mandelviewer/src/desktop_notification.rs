@@ -0,0 +1,20 @@
+//! Showing a desktop notification when a long render or export finishes,
+//! so that the user can do something else while it runs without forgetting about it.
+
+use core::time::Duration;
+
+use notify_rust::Notification;
+
+/// Renders and exports that take at least this long show a desktop notification when they finish.
+pub const NOTIFY_AFTER: Duration = Duration::from_secs(10);
+
+/// Shows a desktop notification with the given text.
+pub fn show(text: &str) -> Result<(), String> {
+    Notification::new()
+        .appname("Mandelviewer")
+        .summary("Mandelviewer")
+        .body(text)
+        .show()
+        .map(|_| ())
+        .map_err(|e| format!("could not show a desktop notification: {e}"))
+}
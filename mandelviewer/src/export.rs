@@ -0,0 +1,276 @@
+//! Exporting the current view at several resolutions in one go.
+//!
+//! The renders of a batch are done one after the other, since each of them already uses every core.
+//! The progress of the batch is weighed by the number of samples of each render,
+//! so that a small render finishing does not look like a large part of the work.
+
+use core::fmt;
+use core::num::{NonZeroU32, NonZeroU8};
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use image::error::{ImageFormatHint, UnsupportedErrorKind};
+use image::{DynamicImage, ImageError};
+use mandellib::{try_render_with_progress, Frame, Progress, RenderParameters, Watermark};
+
+/// A resolution and supersampling factor that the current view can be exported with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportPreset {
+    /// Shown in the UI, and put in the names of the exported files.
+    pub name: &'static str,
+    pub y_resolution: NonZeroU32,
+    pub ssaa: NonZeroU8,
+}
+
+pub const EXPORT_PRESETS: [ExportPreset; 3] = [
+    ExportPreset {
+        name: "1080p",
+        y_resolution: NonZeroU32::new(1080).unwrap(),
+        ssaa: NonZeroU8::new(3).unwrap(),
+    },
+    ExportPreset {
+        name: "4K",
+        y_resolution: NonZeroU32::new(2160).unwrap(),
+        ssaa: NonZeroU8::new(3).unwrap(),
+    },
+    ExportPreset {
+        name: "8K print",
+        y_resolution: NonZeroU32::new(4320).unwrap(),
+        ssaa: NonZeroU8::new(4).unwrap(),
+    },
+];
+
+/// The extensions of the image formats that images can be saved in.
+pub const IMAGE_EXTENSIONS: [&str; 11] = [
+    "png", "jpg", "gif", "webp", "bmp", "tiff", "tga", "qoi", "ico", "ppm", "pam",
+];
+
+/// One render of a batch export.
+#[derive(Debug, Clone)]
+pub struct ExportJob {
+    pub params: RenderParameters,
+    pub view_region: Frame,
+    pub path: PathBuf,
+    /// Stamped onto the image before it is saved.
+    pub watermark: Option<Watermark>,
+}
+
+impl ExportJob {
+    /// The number of samples that the render computes at most.
+    pub fn samples(&self) -> f64 {
+        f64::from(self.params.x_resolution)
+            * f64::from(self.params.y_resolution)
+            * f64::from(self.params.sqrt_samples_per_pixel.get()).powi(2)
+    }
+
+    /// The name of the file that the image is saved as, for showing to the user.
+    pub fn file_name(&self) -> String {
+        self.path.file_name().map_or_else(
+            || self.path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    }
+
+    /// Renders the view while reporting its progress and saves it at the path of the job,
+    /// and returns the path or a description of what went wrong.
+    pub fn run(self, progress: &dyn Progress) -> Result<PathBuf, String> {
+        let mut img = try_render_with_progress(&self.params, self.view_region, progress)
+            .map_err(|e| format!("could not export {}: {e}", self.path.display()))?;
+        if let Some(ref watermark) = self.watermark {
+            watermark.stamp(&mut img);
+        }
+        save_image(&img, &self.path, self.params.color_type.has_color())
+            .map_err(|e| format!("could not save {}: {e}", self.path.display()))?;
+        Ok(self.path)
+    }
+}
+
+/// The renders of a batch export that are left, and how far it has come.
+#[derive(Debug)]
+pub struct ExportBatch {
+    queue: VecDeque<ExportJob>,
+    total_samples: f64,
+    finished_samples: f64,
+    pub finished_jobs: usize,
+    pub total_jobs: usize,
+    started: Instant,
+}
+
+impl ExportBatch {
+    pub fn new(jobs: Vec<ExportJob>) -> Self {
+        Self {
+            total_samples: jobs.iter().map(ExportJob::samples).sum(),
+            finished_samples: 0.0,
+            finished_jobs: 0,
+            total_jobs: jobs.len(),
+            queue: jobs.into(),
+            started: Instant::now(),
+        }
+    }
+
+    /// When the export was started.
+    pub const fn started(&self) -> Instant {
+        self.started
+    }
+
+    /// Takes the next render out of the queue.
+    pub fn next_job(&mut self) -> Option<ExportJob> {
+        self.queue.pop_front()
+    }
+
+    /// The renders that have not been started yet, in the order they will be.
+    pub fn queued(&self) -> impl Iterator<Item = &ExportJob> {
+        self.queue.iter()
+    }
+
+    /// Removes the render with the given index in [`ExportBatch::queued`] from the batch,
+    /// so that it no longer counts towards the progress.
+    pub fn remove_queued(&mut self, index: usize) {
+        if let Some(job) = self.queue.remove(index) {
+            self.total_samples -= job.samples();
+            self.total_jobs -= 1;
+        }
+    }
+
+    /// Records that a render with the given number of samples is done, whether it succeeded or not.
+    pub fn job_finished(&mut self, samples: f64) {
+        self.finished_samples += samples;
+        self.finished_jobs += 1;
+    }
+
+    /// The fraction of the samples of the batch that have been rendered.
+    pub fn progress(&self) -> f32 {
+        if self.total_samples > 0.0 {
+            (self.finished_samples / self.total_samples) as f32
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Returns the path that the view is exported to with the given preset and resolution,
+/// based on the path that the user picked: "view.png" exported with the "8K print" preset
+/// becomes "view_8k_print_7680x4320.png".
+pub fn templated_path(base: &Path, preset: &ExportPreset, width: u32, height: u32) -> PathBuf {
+    let stem = base
+        .file_stem()
+        .map_or_else(|| "mandelbrot_set".into(), |stem| stem.to_string_lossy());
+    let name = preset.name.to_lowercase().replace(' ', "_");
+    let extension = base
+        .extension()
+        .map_or_else(|| "png".into(), |extension| extension.to_string_lossy());
+    base.with_file_name(format!("{stem}_{name}_{width}x{height}.{extension}"))
+}
+
+/// Saves the image in color or in grayscale, since the viewer always shows it with an alpha channel.
+pub fn save_image(img: &DynamicImage, path: &Path, has_color: bool) -> Result<(), SaveError> {
+    if has_color {
+        img.to_rgb8().save(path)
+    } else {
+        img.to_luma8().save(path)
+    }
+    .map_err(SaveError::from)
+}
+
+/// Why an image could not be saved, sorted into the cases that the user can do something about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SaveError {
+    PermissionDenied,
+    StorageFull,
+    MissingFolder,
+    /// The file extension of the path is not an image format that images can be saved in,
+    /// or the path has no extension. Contains the extension, if any.
+    UnsupportedFormat(Option<String>),
+    /// The image format can not store the image, e.g. because it is too large for it.
+    UnsupportedImage(String),
+    Other(String),
+}
+
+impl SaveError {
+    /// What the user can do to save the image anyway.
+    pub fn suggestion(&self) -> &'static str {
+        match self {
+            Self::PermissionDenied => "Choose a folder that you are allowed to write to.",
+            Self::StorageFull => "Free up some space on the disk or save the image on another one.",
+            Self::MissingFolder => "Choose a folder that exists.",
+            Self::UnsupportedFormat(_) => "Save the image as e.g. a PNG file instead.",
+            Self::UnsupportedImage(_) => "Save the image in another format, e.g. PNG.",
+            Self::Other(_) => "Try again, or save the image somewhere else.",
+        }
+    }
+
+    /// Returns the path to suggest to the user instead of the one that the image could not be saved at:
+    /// the same path as a PNG file if the format was the problem, and the same path otherwise.
+    pub fn suggested_path(&self, path: &Path) -> PathBuf {
+        match self {
+            Self::UnsupportedFormat(_) | Self::UnsupportedImage(_) => path.with_extension("png"),
+            _ => path.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PermissionDenied => write!(f, "permission to write the file was denied"),
+            Self::StorageFull => write!(f, "the disk is full"),
+            Self::MissingFolder => write!(f, "the folder does not exist"),
+            Self::UnsupportedFormat(Some(extension)) => {
+                write!(f, "images can not be saved as \".{extension}\" files")
+            }
+            Self::UnsupportedFormat(None) => {
+                write!(
+                    f,
+                    "the file name must end with the extension of an image format"
+                )
+            }
+            Self::UnsupportedImage(e) => write!(f, "the image format can not store the image: {e}"),
+            Self::Other(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<ImageError> for SaveError {
+    fn from(error: ImageError) -> Self {
+        match error {
+            ImageError::IoError(e) => match e.kind() {
+                ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem => {
+                    Self::PermissionDenied
+                }
+                ErrorKind::StorageFull | ErrorKind::QuotaExceeded => Self::StorageFull,
+                ErrorKind::NotFound => Self::MissingFolder,
+                _ => Self::Other(e.to_string()),
+            },
+            ImageError::Unsupported(e) => match e.kind() {
+                UnsupportedErrorKind::Format(hint) => Self::UnsupportedFormat(match hint {
+                    ImageFormatHint::PathExtension(extension) => {
+                        Some(extension.to_string_lossy().into_owned())
+                    }
+                    ImageFormatHint::Name(name) => Some(name),
+                    ImageFormatHint::Exact(format) => {
+                        format.extensions_str().first().map(|&e| e.to_owned())
+                    }
+                    _ => None,
+                }),
+                _ => Self::UnsupportedImage(e.to_string()),
+            },
+            ImageError::Parameter(e) => Self::UnsupportedImage(e.to_string()),
+            ImageError::Encoding(e) => Self::UnsupportedImage(e.to_string()),
+            e => Self::Other(e.to_string()),
+        }
+    }
+}
+
+/// An image that could not be saved, kept so that saving it can be retried.
+#[derive(Debug, Clone)]
+pub struct UnsavedImage {
+    pub image: DynamicImage,
+    pub has_color: bool,
+    /// Where the image was last attempted to be saved.
+    pub path: PathBuf,
+    pub error: SaveError,
+}
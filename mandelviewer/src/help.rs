@@ -0,0 +1,127 @@
+//! The overlay that lists the keyboard shortcuts, and the tour of the main controls
+//! that it shows the first time the viewer is started.
+
+use iced::{event, keyboard, Event};
+
+/// What the help overlay is showing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelpOverlay {
+    Hidden,
+    Shortcuts,
+    /// A step of the tour, whose control is highlighted.
+    Tour(TourStep),
+}
+
+impl HelpOverlay {
+    /// Returns the overlay after the user asked for the list of shortcuts,
+    /// which hides the list if it is already shown.
+    #[must_use]
+    pub const fn toggle_shortcuts(self) -> Self {
+        match self {
+            Self::Shortcuts => Self::Hidden,
+            Self::Hidden | Self::Tour(_) => Self::Shortcuts,
+        }
+    }
+
+    /// Returns the overlay after the user pressed "Next", which goes on to the next step of the tour
+    /// and hides the overlay after its last step.
+    #[must_use]
+    pub fn next(self) -> Self {
+        match self {
+            Self::Tour(step) => step.next().map_or(Self::Hidden, Self::Tour),
+            Self::Hidden | Self::Shortcuts => Self::Hidden,
+        }
+    }
+
+    /// Returns the step of the tour that is shown, if any.
+    #[must_use]
+    pub const fn tour_step(self) -> Option<TourStep> {
+        match self {
+            Self::Tour(step) => Some(step),
+            Self::Hidden | Self::Shortcuts => None,
+        }
+    }
+}
+
+/// The controls that the tour shows, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TourStep {
+    Zoom,
+    Iterations,
+    Supersampling,
+    Save,
+}
+
+impl TourStep {
+    pub const ALL: [Self; 4] = [
+        Self::Zoom,
+        Self::Iterations,
+        Self::Supersampling,
+        Self::Save,
+    ];
+
+    /// Returns the step after this one, or `None` if this is the last step.
+    #[must_use]
+    pub fn next(self) -> Option<Self> {
+        Self::ALL.get(self as usize + 1).copied()
+    }
+
+    /// Returns a description of the control that is highlighted in this step.
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Zoom => "Zoom factor: every step of +1 zooms in twice as far.\nSet the point to zoom in on with Re(c) and Im(c).",
+            Self::Iterations => "Iterations: how many times every point is iterated before it is taken to be in the set.\nRaise it when the edge of the set looks blurry after zooming in, or press \"auto\".",
+            Self::Supersampling => "Supersampling: how many samples are taken in every pixel to smooth the image.\nMore samples look better but take longer to render.",
+            Self::Save => "Save current view: renders the view at the full resolution and saves it.\nPress ? at any time to see the keyboard shortcuts.",
+        }
+    }
+}
+
+/// The actions that can be taken with the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shortcut {
+    ToggleHelp,
+    /// Hide the help overlay, or end the tour.
+    Dismiss,
+    ZoomIn,
+    ZoomOut,
+    Render,
+    Save,
+}
+
+/// The keys of every shortcut along with what they do, in the order they are listed in the overlay.
+pub const SHORTCUTS: [(&str, &str); 6] = [
+    ("?", "Show or hide this list"),
+    ("Esc", "Hide this list or end the tour"),
+    ("+", "Zoom in one step"),
+    ("-", "Zoom out one step"),
+    ("R", "Render the view at full resolution"),
+    ("Ctrl+S", "Save the current view"),
+];
+
+/// Returns the shortcut of a key press that no widget handled.
+/// Keys that are typed into a text field are handled by it, so they are never shortcuts.
+pub fn shortcut(event: Event, status: event::Status) -> Option<Shortcut> {
+    if status == event::Status::Captured {
+        return None;
+    }
+    match event {
+        Event::Keyboard(keyboard::Event::CharacterReceived(character)) => match character {
+            '?' => Some(Shortcut::ToggleHelp),
+            '+' => Some(Shortcut::ZoomIn),
+            '-' => Some(Shortcut::ZoomOut),
+            'r' | 'R' => Some(Shortcut::Render),
+            _ => None,
+        },
+        Event::Keyboard(keyboard::Event::KeyPressed {
+            key_code,
+            modifiers,
+        }) => match key_code {
+            keyboard::KeyCode::Escape => Some(Shortcut::Dismiss),
+            keyboard::KeyCode::S if modifiers.command() => Some(Shortcut::Save),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,59 @@
+//! Copying the rendered image to the clipboard of the system, so that it can be pasted
+//! directly into other programs without saving it to a file first.
+//!
+//! Many programs refuse or choke on very large images in the clipboard, so images with
+//! more than [`MAX_CLIPBOARD_PIXELS`] pixels are downscaled before they are copied.
+
+use std::borrow::Cow;
+
+use arboard::{Clipboard, ImageData};
+use image::{imageops::FilterType, DynamicImage};
+
+/// The largest number of pixels in an image that is copied without downscaling it, that of a 4K image.
+const MAX_CLIPBOARD_PIXELS: u64 = 3840 * 2160;
+
+/// Copies the image to the clipboard, downscaled to at most [`MAX_CLIPBOARD_PIXELS`] pixels.
+///
+/// The clipboard is opened the first time an image is copied, and is kept open since
+/// the copied image is only available to other programs as long as it is on some platforms.
+///
+/// Returns the resolution that the image was downscaled to, if it was.
+pub fn copy_image(
+    clipboard: &mut Option<Clipboard>,
+    image: DynamicImage,
+) -> Result<Option<(u32, u32)>, String> {
+    let clipboard = match clipboard {
+        Some(clipboard) => clipboard,
+        None => clipboard
+            .insert(Clipboard::new().map_err(|e| format!("could not open the clipboard: {e}"))?),
+    };
+
+    let (image, downscaled) = clipboard_sized(image);
+    let (width, height) = (image.width(), image.height());
+    clipboard
+        .set_image(ImageData {
+            width: width as usize,
+            height: height as usize,
+            bytes: Cow::Owned(image.into_rgba8().into_raw()),
+        })
+        .map_err(|e| format!("could not copy the image to the clipboard: {e}"))?;
+    Ok(downscaled.then_some((width, height)))
+}
+
+/// Downscales the image to at most [`MAX_CLIPBOARD_PIXELS`] pixels while keeping its aspect ratio,
+/// and returns whether it had to.
+fn clipboard_sized(image: DynamicImage) -> (DynamicImage, bool) {
+    let pixels = u64::from(image.width()) * u64::from(image.height());
+    if pixels <= MAX_CLIPBOARD_PIXELS {
+        return (image, false);
+    }
+    let scale = (MAX_CLIPBOARD_PIXELS as f64 / pixels as f64).sqrt();
+    let width = ((f64::from(image.width()) * scale) as u32).max(1);
+    let height = ((f64::from(image.height()) * scale) as u32).max(1);
+    // `resize_exact` since `resize` would round the resolution to keep the aspect ratio,
+    // which could put it above the limit.
+    (
+        image.resize_exact(width, height, FilterType::Triangle),
+        true,
+    )
+}
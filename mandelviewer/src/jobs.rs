@@ -0,0 +1,90 @@
+//! The renders that the viewer is running, which are listed in the jobs panel with their progress
+//! so that the user can see what the viewer is busy with and cancel the renders they no longer need.
+//!
+//! Every render reports to its own [`ProgressCounter`], which the panel polls while any render is running.
+
+use core::fmt;
+use core::time::Duration;
+use std::sync::Arc;
+
+use mandellib::ProgressCounter;
+
+/// How often the progress of the running renders is redrawn.
+pub const JOB_REFRESH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Identifies a render in the [`JobList`], and is sent along with its result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobId(u64);
+
+/// What a render is for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobKind {
+    Preview,
+    /// The render of the current view at full resolution.
+    FullRender,
+    /// The render of an export preset, with the name of the file it is saved as.
+    Export(String),
+    /// The frames of a recorded zoom, with the name of the file it is saved as.
+    Recording(String),
+}
+
+impl fmt::Display for JobKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Preview => write!(f, "Preview"),
+            Self::FullRender => write!(f, "Full render"),
+            Self::Export(name) => write!(f, "Export of {name}"),
+            Self::Recording(name) => write!(f, "Recording of {name}"),
+        }
+    }
+}
+
+/// A render that is running.
+#[derive(Debug)]
+pub struct Job {
+    pub id: JobId,
+    pub kind: JobKind,
+    pub progress: Arc<ProgressCounter>,
+}
+
+/// The renders that are running, in the order they were started.
+#[derive(Debug, Default)]
+pub struct JobList {
+    jobs: Vec<Job>,
+    next_id: u64,
+}
+
+impl JobList {
+    /// Adds a render to the list, and returns its id and the counter that it should report its progress to.
+    pub fn start(&mut self, kind: JobKind) -> (JobId, Arc<ProgressCounter>) {
+        let id = JobId(self.next_id);
+        self.next_id += 1;
+        let progress = Arc::new(ProgressCounter::new());
+        self.jobs.push(Job {
+            id,
+            kind,
+            progress: Arc::clone(&progress),
+        });
+        (id, progress)
+    }
+
+    /// Removes the render from the list once it has finished, been cancelled or failed.
+    pub fn finish(&mut self, id: JobId) {
+        self.jobs.retain(|job| job.id != id);
+    }
+
+    /// Makes the render stop as soon as possible. It stays in the list until its result arrives.
+    pub fn cancel(&self, id: JobId) {
+        if let Some(job) = self.jobs.iter().find(|job| job.id == id) {
+            job.progress.cancel();
+        }
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+}
@@ -0,0 +1,91 @@
+//! Which changes to the settings make the viewer render a preview of the view by itself,
+//! and how long it waits after the last of them before it does.
+//! Waiting lets the user type a coordinate digit by digit or drag a slider
+//! without starting a render for every step along the way.
+
+use core::fmt;
+use core::time::Duration;
+
+/// The kinds of changes to the settings that can start a preview.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewTrigger {
+    /// The center and zoom of the view.
+    View,
+    Iterations,
+    Formula,
+    /// Grayscale, interior shading and the range of escape speeds that the palette covers.
+    Coloring,
+    Supersampling,
+}
+
+impl PreviewTrigger {
+    pub const ALL: [Self; 5] = [
+        Self::View,
+        Self::Iterations,
+        Self::Formula,
+        Self::Coloring,
+        Self::Supersampling,
+    ];
+}
+
+impl fmt::Display for PreviewTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::View => "view",
+            Self::Iterations => "iterations",
+            Self::Formula => "formula",
+            Self::Coloring => "coloring",
+            Self::Supersampling => "supersampling",
+        };
+        f.write_str(name)
+    }
+}
+
+/// When previews are rendered without the user asking for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LivePreview {
+    /// How long the settings must stay unchanged before a preview is rendered.
+    /// Zero renders a preview for every change, and a fast preview for every keystroke in the view.
+    pub delay: Duration,
+    /// Whether each of the [`PreviewTrigger::ALL`] starts a preview.
+    triggers: [bool; PreviewTrigger::ALL.len()],
+}
+
+impl LivePreview {
+    pub const DEFAULT_DELAY: Duration = Duration::from_millis(300);
+    /// The longest delay that can be chosen in the UI, in milliseconds.
+    pub const MAX_DELAY_MILLIS: u32 = 2000;
+
+    /// The delay in whole milliseconds, at most [`MAX_DELAY_MILLIS`](Self::MAX_DELAY_MILLIS).
+    #[must_use]
+    pub fn delay_millis(&self) -> u32 {
+        u32::try_from(self.delay.as_millis())
+            .unwrap_or(Self::MAX_DELAY_MILLIS)
+            .min(Self::MAX_DELAY_MILLIS)
+    }
+
+    /// Returns whether changes of the given kind start a preview.
+    #[must_use]
+    pub const fn is_triggered_by(&self, trigger: PreviewTrigger) -> bool {
+        self.triggers[trigger as usize]
+    }
+
+    pub fn set_triggered_by(&mut self, trigger: PreviewTrigger, triggered: bool) {
+        self.triggers[trigger as usize] = triggered;
+    }
+
+    /// Returns whether any change starts a preview.
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.triggers.contains(&true)
+    }
+}
+
+impl Default for LivePreview {
+    fn default() -> Self {
+        Self {
+            delay: Self::DEFAULT_DELAY,
+            triggers: [true; PreviewTrigger::ALL.len()],
+        }
+    }
+}
@@ -2,66 +2,134 @@
 
 use core::{
     fmt::Write,
-    num::{NonZeroU32, NonZeroU8, TryFromIntError},
+    num::{NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize, TryFromIntError},
     time::Duration,
     writeln,
 };
-use std::error::Error;
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
 
+mod bookmarks;
 mod command_line_interface;
+mod coordinates;
+#[cfg(feature = "notify")]
+mod desktop_notification;
 mod embedded_resources;
+mod export;
+mod help;
+#[cfg(feature = "clipboard")]
+mod image_clipboard;
+mod jobs;
+mod live_preview;
+mod palette_histogram;
+mod preview;
+mod recolor;
+mod recording;
+mod render_server;
+mod share;
+mod viewer_config;
+use bookmarks::{bookmarked_view, load_library, save_library};
 use color_space::SupportedColorType;
 use command_line_interface::Cli;
+use coordinates::CoordinateRounding;
 use embedded_resources::{ICON, RENDERING_IN_PROGRESS};
-use mandellib::{render, Frame, RenderParameters};
+use export::{
+    save_image, templated_path, ExportBatch, ExportJob, UnsavedImage, EXPORT_PRESETS,
+    IMAGE_EXTENSIONS,
+};
+use help::{HelpOverlay, Shortcut, TourStep, SHORTCUTS};
+use jobs::{JobId, JobKind, JobList, JOB_REFRESH_INTERVAL};
+use live_preview::{LivePreview, PreviewTrigger};
+use mandellib::{
+    auto_max_iterations, colorize, dark_frame_warning, default_render_server_socket,
+    escape_speed_histogram, estimate_render_time, refinement_parameters, render_warnings,
+    try_compute_resumable_with_progress, try_render, try_render_with_progress, Bookmark,
+    BookmarkLibrary, Coloring, CpuLimit, DarkFrameThreshold, EscapeSpeedRange, Formula, Frame,
+    InteriorShading, Precision, Progress, ProgressCounter, ReconstructionFilter, RenderError,
+    RenderParameters, RenderQuality, RenderWarning, ResumableEscapeBuffer, SamplePlacement,
+    U32AndUsize, Watermark, WatermarkPosition, Zoom, DEFAULT_BOOKMARKS_FILE, REFINEMENT_DIVISORS,
+};
+use palette_histogram::{palette_histogram, HISTOGRAM_BINS};
+use preview::Preview;
+use recolor::RecolorCache;
+use recording::{
+    ZoomRecording, RECORDING_EXTENSIONS, RECORDING_FRAMES, RECORDING_Y_RESOLUTION,
+    RECORDING_ZOOM_LEVELS,
+};
+use render_server::{send_view, view_arguments};
+use share::{save_shared, stamp_qr_code, view_string};
+use viewer_config::{ViewerConfig, DEFAULT_CONFIG_FILE};
 
 use clap::Parser;
 
 use rayon::ThreadPoolBuilder;
 
 use iced::{
-    self, executor,
+    self, clipboard, executor, subscription,
     widget::{
         button::Button,
         checkbox::Checkbox,
         column,
-        image::{Handle, Viewer},
+        image::{Handle, Image, Viewer},
+        pick_list::PickList,
+        progress_bar::ProgressBar,
         row,
         text::Text,
         text_input::TextInput,
         tooltip::{Position, Tooltip},
-        Slider, Space,
+        Column, Slider, Space,
     },
-    window, Application, Command, Element, Length, Theme,
+    window, Application, Color, Command, Element, Length, Subscription, Theme,
 };
 use image::DynamicImage;
-use rfd::FileDialog;
+use rfd::{FileDialog, MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
 
 // Initial view settings
 const INITIAL_SSAA_FACTOR: NonZeroU8 = NonZeroU8::new(3).unwrap();
-const INITIAL_MAX_ITERATIONS: NonZeroU32 = NonZeroU32::new(256).unwrap();
+const INITIAL_MAX_ITERATIONS: NonZeroU64 = NonZeroU64::new(256).unwrap();
 const INITIAL_X_RES: NonZeroU32 = NonZeroU32::new(1920).unwrap();
 const INITIAL_Y_RES: NonZeroU32 = NonZeroU32::new(1080).unwrap();
-const INITIAL_IMAG_DISTANCE: f64 = 8.0 / 3.0;
 const INITIAL_REAL_CENTER: f64 = -0.75;
 const INITIAL_IMAG_CENTER: f64 = 0.0;
-const INITIAL_ZOOM: f64 = 0.0;
+const INITIAL_ZOOM: Zoom = Zoom::NONE;
 
 // Program settings
 const PROGRAM_NAME: &str = "Mandelviewer";
+const RENDER_ANYWAY: &str = "Render anyway";
+const LOWER_SSAA: &str = "Lower SSAA";
+const CANCEL: &str = "Cancel";
+const RETRY: &str = "Retry";
+const SAVE_ELSEWHERE: &str = "Save elsewhere";
+/// How long the view must stay unchanged after the user stops typing in it
+/// before the fast preview is replaced by one with the configured quality,
+/// when previews are rendered without a delay.
+const INTERACTION_SETTLE_TIME: Duration = Duration::from_millis(400);
+/// The color of the label of the control that the tour is showing.
+const TOUR_HIGHLIGHT: Color = Color::from_rgb(1.0, 0.5, 0.0);
+/// The vertical resolution that the histogram of escape speeds is computed at.
+/// It only needs to show the rough shape of the distribution, so this can be low.
+const HISTOGRAM_Y_RES: NonZeroU32 = NonZeroU32::new(120).unwrap();
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Cli::parse();
-    if let Some(jobs) = args.jobs {
-        ThreadPoolBuilder::new()
-            .num_threads(jobs.into())
-            .build_global()?;
-    }
+    ThreadPoolBuilder::new()
+        // Zero lets the parallelism library decide.
+        .num_threads(args.jobs.map_or(0, NonZeroUsize::get))
+        .thread_name(mandellib::worker_thread_name)
+        .build_global()?;
 
     let program_settings = iced::Settings {
         window: window::Settings {
             ..Default::default()
         },
+        flags: (
+            Duration::from_secs(args.render_time_warning),
+            args.dark_frame_threshold,
+        ),
         ..Default::default()
     };
 
@@ -76,41 +144,235 @@ fn main() -> Result<(), Box<dyn Error>> {
 struct UIValues {
     slider_ssaa_factor: NonZeroU8,
     do_ssaa: bool,
-    live_preview: bool,
+    /// Which changes render a preview of the view, and how long after them.
+    live_preview: LivePreview,
     // Parsing these to  directly to float and storing them in the view_region would
     // prevent the user from e.g. ever going through the string state "0." while inputting "0.2",
     center_real: String,
     center_imag: String,
     zoom: String,
+    /// How the coordinates of the view are shortened in the sidebar and when they are copied or shared.
+    coordinate_rounding: CoordinateRounding,
+    /// Which of the [`EXPORT_PRESETS`] are rendered when the user exports the view.
+    export_presets: [bool; EXPORT_PRESETS.len()],
+    watermark_position: WatermarkPosition,
+    watermark_opacity: f64,
+    /// The number of frames of a recorded zoom.
+    recording_frames: u32,
+    /// The number of times that a recorded zoom zooms in by a factor of two.
+    recording_zoom_levels: f64,
+    /// Whether shared images get a QR code of the command that renders them.
+    share_qr_code: bool,
+    /// Whether renders and exports that take a while show a desktop notification when they finish.
+    #[cfg(feature = "notify")]
+    desktop_notifications: bool,
+    /// The local socket of the `mandelbrot batch` process that the view is sent to.
+    render_server: String,
+    /// The name, notes and tags that the current view is bookmarked with.
+    bookmark_name: String,
+    bookmark_notes: String,
+    bookmark_tags: String,
+    /// Only the bookmarks that match this are listed.
+    bookmark_search: String,
 }
 
 struct MandelViewer {
-    image: Option<DynamicImage>,
+    /// The latest rendered image, kept in the form that the image viewer shows
+    /// so that it does not have to be converted every time the view is drawn.
+    image: Option<Preview>,
     params: RenderParameters,
     aspect_ratio: f64,
-    zoom: f64,
+    zoom: Zoom,
     view_region: Frame,
     render_in_progress: bool,
+    /// When the full resolution render in progress was started, if there is one.
+    render_started: Option<Instant>,
+    /// Incremented whenever a full resolution render is started,
+    /// so that the coarse refinements of an earlier render are not shown.
+    render_generation: u64,
+    /// The vertical resolution of the image that is shown, or 0 if the placeholder is shown.
+    shown_y_resolution: u32,
     notifications: Vec<String>,
+    /// The last error returned by the renderer, which is shown until the user dismisses it.
+    render_error: Option<String>,
+    /// The warnings about the settings of the latest full render that the user has not dismissed.
+    render_warnings: Vec<RenderWarning>,
+    /// The settings of the latest view that was rendered without errors,
+    /// which the viewer goes back to if a render fails.
+    last_good_view: RenderedView,
     ui_values: UIValues,
+    /// Full resolution renders that are estimated to take longer than this must be confirmed.
+    /// Zero means that renders never need to be confirmed.
+    render_time_warning: Duration,
+    /// Whether the time that a full resolution render would take is being estimated,
+    /// so that it is only started once when the user asks for it several times.
+    estimating_render_time: bool,
+    /// Full resolution renders with more than this fraction of the view at the maximum number of iterations
+    /// are warned about, see [`RenderWarning::DarkFrame`].
+    dark_frame_threshold: DarkFrameThreshold,
+    /// Incremented whenever the view is changed or a preview is started,
+    /// so that outdated requests to render a preview of the configured quality can be ignored.
+    interaction_generation: u64,
+    /// The escape speeds of the latest rendered view, counted in [`HISTOGRAM_BINS`] bins.
+    escape_speed_counts: Vec<u32>,
+    /// The palette strip and histogram of escape speeds, redrawn whenever they change.
+    palette_histogram: Handle,
+    /// The export of the view at several resolutions that is in progress, if any.
+    export: Option<ExportBatch>,
+    /// When the recording of a zoom into the view that is in progress was started, if there is one.
+    recording_started: Option<Instant>,
+    /// The renders that are running, which are listed in the jobs panel.
+    jobs: JobList,
+    /// Whether the jobs panel is redrawn periodically to show the progress of the running renders.
+    refreshing_jobs: bool,
+    /// The logo that is stamped onto saved and exported images, if the user has chosen one.
+    watermark_logo: Option<DynamicImage>,
+    /// The image that the user gave up on saving after it failed, which is kept
+    /// until it is saved or discarded.
+    unsaved_image: Option<UnsavedImage>,
+    /// The escape data of the latest full render, which is colored again
+    /// instead of rendering a preview when only the colors of the view change.
+    recolor_cache: Option<RecolorCache>,
+    /// The views that the user has bookmarked, which are kept in [`DEFAULT_BOOKMARKS_FILE`].
+    bookmarks: BookmarkLibrary,
+    /// The clipboard that rendered images are copied to, opened the first time one is copied.
+    #[cfg(feature = "clipboard")]
+    image_clipboard: Option<arboard::Clipboard>,
+    /// The list of keyboard shortcuts or the step of the tour that is shown, if any.
+    help_overlay: HelpOverlay,
+    /// The settings that are kept in [`DEFAULT_CONFIG_FILE`] between runs.
+    config: ViewerConfig,
+}
+
+/// The settings that a render was started with.
+#[derive(Debug, Clone)]
+struct RenderedView {
+    params: RenderParameters,
+    view_region: Frame,
+    zoom: Zoom,
 }
 
 #[derive(Debug, Clone)]
 enum NotificationAction {
     Push(String),
     Pop,
+    WarningDismissed(RenderWarning),
 }
 
 #[derive(Debug, Clone)]
 enum SSAAAction {
     Toggled(bool),
     NumSamplesUpdated(NonZeroU8),
+    PlacementSelected(SamplePlacement),
+    FilterSelected(ReconstructionFilter),
+}
+
+#[derive(Debug, Clone)]
+enum LivePreviewAction {
+    TriggerToggled(PreviewTrigger, bool),
+    /// The delay in milliseconds.
+    DelayUpdated(u32),
 }
 
 #[derive(Debug, Clone)]
 enum RenderAction {
     Started,
-    Finished(DynamicImage),
+    /// The time that a full resolution render of the view is estimated to take,
+    /// which must be confirmed if it is longer than the warning threshold.
+    Estimated(Duration),
+    Finished(JobId, Box<RenderedView>, Result<DynamicImage, RenderError>),
+    /// A full render finished, along with its escape data if it was small enough to be kept.
+    Computed(
+        JobId,
+        Box<RenderedView>,
+        Result<(DynamicImage, Option<Arc<ResumableEscapeBuffer>>), RenderError>,
+    ),
+    /// The escape data of the latest full render was colored with the colors of the view.
+    Recolored(Box<RenderedView>, DynamicImage),
+    /// The full render with the given generation was checked for a [`RenderWarning::DarkFrame`].
+    DarkFrameChecked(u64, Option<RenderWarning>),
+    /// Double the maximum number of iterations and render the view again, as the dark frame warning suggests.
+    IterationsDoubled,
+    /// A coarse version of the full render with the given generation finished,
+    /// along with the index of its divisor in [`REFINEMENT_DIVISORS`].
+    Refined(u64, usize, Result<DynamicImage, RenderError>),
+    ErrorDismissed,
+}
+
+#[derive(Debug, Clone)]
+enum SaveAction {
+    Pressed,
+    /// Try to save the image that could not be saved at the same path again.
+    Retried,
+    /// Ask for another path to save the image that could not be saved at.
+    ElsewherePressed,
+    Discarded,
+}
+
+#[derive(Debug, Clone)]
+enum ShareAction {
+    Pressed,
+    QrCodeToggled(bool),
+}
+
+#[derive(Debug, Clone)]
+enum ExportAction {
+    PresetToggled(usize, bool),
+    Started,
+    /// Ask for a logo to stamp onto saved and exported images, or stop stamping it.
+    WatermarkToggled(bool),
+    WatermarkPositionSelected(WatermarkPosition),
+    WatermarkOpacityUpdated(f64),
+    /// A render with the given number of samples was saved at the path, or failed.
+    JobFinished(JobId, f64, Result<PathBuf, String>),
+}
+
+#[derive(Debug, Clone)]
+enum RecordingAction {
+    FramesUpdated(u32),
+    ZoomLevelsUpdated(f64),
+    Started,
+    /// The recording was saved at the path, or failed.
+    Finished(JobId, Result<PathBuf, String>),
+}
+
+#[derive(Debug, Clone)]
+enum JobsAction {
+    /// Time to redraw the progress of the running renders.
+    Refreshed,
+    Cancelled(JobId),
+    /// Remove the export with the given index in the queue of the export in progress.
+    QueuedExportRemoved(usize),
+}
+
+#[derive(Debug, Clone)]
+enum RenderServerAction {
+    SocketUpdated(String),
+    SendPressed,
+    /// The server will save the image at the path, or the view could not be sent.
+    Answered(Result<String, String>),
+}
+
+#[derive(Debug, Clone)]
+enum BookmarkAction {
+    NameUpdated(String),
+    NotesUpdated(String),
+    TagsUpdated(String),
+    SearchUpdated(String),
+    /// Bookmark the current view, or update the bookmark with the same name.
+    SavePressed,
+    /// Go to the view of the bookmark with the given index.
+    Selected(usize),
+    Removed(usize),
+}
+
+#[derive(Debug, Clone)]
+enum HelpAction {
+    Shortcut(Shortcut),
+    TourStarted,
+    NextPressed,
+    /// Hide the overlay, or skip the rest of the tour.
+    Dismissed,
 }
 
 #[derive(Debug, Clone)]
@@ -131,12 +393,31 @@ enum UIAction {
 #[derive(Debug, Clone)]
 enum Message {
     Render(RenderAction),
-    MaxItersUpdated(NonZeroU32),
+    MaxItersUpdated(NonZeroU64),
     Notification(NotificationAction),
-    LiveCheckboxToggled(bool),
+    LivePreview(LivePreviewAction),
     GrayscaleToggled(bool),
-    SavePressed,
+    FormulaSelected(Formula),
+    InteriorShadingSelected(InteriorShading),
+    EscapeSpeedRangeUpdated(EscapeSpeedRange),
+    HistogramComputed(Vec<u32>),
+    Save(SaveAction),
+    Share(ShareAction),
+    CoordinateRoundingSelected(CoordinateRounding),
+    CopyCoordinatesPressed,
+    #[cfg(feature = "clipboard")]
+    CopyImagePressed,
+    #[cfg(feature = "notify")]
+    DesktopNotificationsToggled(bool),
+    Export(ExportAction),
+    Recording(RecordingAction),
+    Jobs(JobsAction),
+    RenderServer(RenderServerAction),
+    Bookmark(BookmarkAction),
+    Help(HelpAction),
+    InteractionSettled(u64),
     VerticalResolutionUpdated(NonZeroU32),
+    CpuLimitUpdated(CpuLimit),
     SuperSampling(SSAAAction),
     Frame(FrameAction),
     UI(UIAction),
@@ -149,13 +430,43 @@ impl MandelViewer {
     /// If the vertical resolution results in an invalid horizontal resolution or does not fit in all the
     /// necessary types this returns an error.
     fn with_new_resolution(&self, y_res: NonZeroU32) -> Result<RenderParameters, TryFromIntError> {
-        let mut new_params = self.params;
+        let mut new_params = self.params.clone();
         new_params.y_resolution = y_res.try_into()?;
         new_params.x_resolution =
             ((f64::from(y_res.get()) * self.aspect_ratio) as u32).try_into()?;
         Ok(new_params)
     }
 
+    /// Asynchronously count the escape speeds of the given view at a low resolution.
+    fn compute_histogram(view: &RenderedView) -> Command<<Self as Application>::Message> {
+        let mut params = view.params.clone();
+        let aspect_ratio = f64::from(params.x_resolution) / f64::from(params.y_resolution);
+        let (Ok(x_resolution), Ok(y_resolution)) = (
+            U32AndUsize::try_from((f64::from(HISTOGRAM_Y_RES.get()) * aspect_ratio).max(1.0) as u32),
+            U32AndUsize::try_from(HISTOGRAM_Y_RES),
+        ) else {
+            return Command::none();
+        };
+        params.x_resolution = x_resolution;
+        params.y_resolution = y_resolution;
+        let view_region = view.view_region;
+        let bins = NonZeroUsize::new(HISTOGRAM_BINS).expect("there is more than one bin");
+        Command::perform(
+            async move { escape_speed_histogram(&params, view_region, bins) },
+            Message::HistogramComputed,
+        )
+    }
+
+    /// Redraw the palette strip and histogram with the current palette, range and counts.
+    fn redraw_palette_histogram(&mut self) {
+        self.palette_histogram = palette_histogram(
+            &self.params.palette,
+            self.params.escape_speed_range,
+            !self.params.color_type.has_color(),
+            &self.escape_speed_counts,
+        );
+    }
+
     /// Push the given message to the notification queue.
     /// It will dissapear after a hard-coded delay.
     fn push_notification(&mut self, text: String) -> Command<<Self as Application>::Message> {
@@ -165,38 +476,922 @@ impl MandelViewer {
         })
     }
 
+    /// Asynchronously render the current view at full resolution.
+    fn start_render(&mut self) -> Command<<Self as Application>::Message> {
+        self.render_in_progress = true;
+        self.render_started = Some(Instant::now());
+        self.render_generation += 1;
+        self.render_warnings = render_warnings(&self.params, self.view_region);
+        let params = self.params.clone();
+        let view_region = self.view_region;
+        let view = self.current_view();
+        let previous = self
+            .recolor_cache
+            .as_ref()
+            .and_then(|cache| cache.resumable(&self.params, self.view_region, self.zoom));
+        let (job, progress, refresh) = self.start_job(JobKind::FullRender);
+        Command::batch([
+            refresh,
+            Self::refine(params.clone(), view_region, self.render_generation, 0),
+            Command::perform(
+                async move {
+                    Self::render_recolorable(&params, view_region, previous, progress.as_ref())
+                },
+                move |result| Message::Render(RenderAction::Computed(job, view, result)),
+            ),
+        ])
+    }
+
+    /// Adds a render to the jobs panel, and returns its id, the counter that it should report its progress to
+    /// and the command that starts redrawing the panel if it is not already being redrawn.
+    fn start_job(
+        &mut self,
+        kind: JobKind,
+    ) -> (
+        JobId,
+        Arc<ProgressCounter>,
+        Command<<Self as Application>::Message>,
+    ) {
+        let (id, progress) = self.jobs.start(kind);
+        let refresh = if self.refreshing_jobs {
+            Command::none()
+        } else {
+            self.refreshing_jobs = true;
+            Self::refresh_jobs()
+        };
+        (id, progress, refresh)
+    }
+
+    /// Redraw the jobs panel after a short while, see [`JobsAction::Refreshed`].
+    fn refresh_jobs() -> Command<<Self as Application>::Message> {
+        Command::perform(async { std::thread::sleep(JOB_REFRESH_INTERVAL) }, |()| {
+            Message::Jobs(JobsAction::Refreshed)
+        })
+    }
+
+    /// Asynchronously render the view at the resolution divided by the divisor
+    /// with the given index in [`REFINEMENT_DIVISORS`], so that it can be shown while the full render is in progress.
+    /// Each refinement starts the next one when it is done, see [`RenderAction::Refined`].
+    fn refine(
+        params: RenderParameters,
+        view_region: Frame,
+        generation: u64,
+        step: usize,
+    ) -> Command<<Self as Application>::Message> {
+        let Some(&divisor) = REFINEMENT_DIVISORS.get(step) else {
+            return Command::none();
+        };
+        let coarse = refinement_parameters(&params, divisor);
+        Command::perform(
+            async move { try_render(&coarse, view_region, false) },
+            move |result| Message::Render(RenderAction::Refined(generation, step, result)),
+        )
+    }
+
+    /// Render the view while reporting its progress, and keep its escape data if it is small enough
+    /// so that the view can be colored again without iterating it again.
+    /// The escape data is colored by averaging the samples of every pixel, so renders with other
+    /// reconstruction filters do not keep it.
+    /// If the escape data of the previous render is given, its orbits are continued instead of starting over.
+    fn render_recolorable(
+        params: &RenderParameters,
+        view_region: Frame,
+        previous: Option<Arc<ResumableEscapeBuffer>>,
+        progress: &dyn Progress,
+    ) -> Result<(DynamicImage, Option<Arc<ResumableEscapeBuffer>>), RenderError> {
+        if RecolorCache::fits(params) && params.reconstruction_filter == ReconstructionFilter::Box {
+            let buffer = match previous {
+                Some(previous) => {
+                    previous.try_resume_with_progress(params.max_iterations, progress)?
+                }
+                None => try_compute_resumable_with_progress(params, view_region, progress)?,
+            };
+            Ok((
+                colorize(buffer.escape_buffer(), &Coloring::from(params)),
+                Some(Arc::new(buffer)),
+            ))
+        } else {
+            try_render_with_progress(params, view_region, progress).map(|image| (image, None))
+        }
+    }
+
+    /// Asynchronously color the escape data of the latest full render with the current colors,
+    /// if it shows the current view. Returns `None` if the view has to be rendered again instead.
+    fn recolor(&self) -> Option<Command<<Self as Application>::Message>> {
+        let buffer = self
+            .recolor_cache
+            .as_ref()
+            .filter(|cache| cache.shows(&self.params, self.view_region, self.zoom))?
+            .buffer();
+        let coloring = Coloring::from(&self.params);
+        let view = self.current_view();
+        Some(Command::perform(
+            async move { colorize(buffer.escape_buffer(), &coloring) },
+            move |image| Message::Render(RenderAction::Recolored(view, image)),
+        ))
+    }
+
+    /// Show the result of a render, or the error that stopped it, and remove it from the jobs panel.
+    /// The image that is shown is kept if the render was cancelled.
+    fn finish_render(
+        &mut self,
+        job: JobId,
+        view: Box<RenderedView>,
+        result: Result<DynamicImage, RenderError>,
+    ) -> Command<<Self as Application>::Message> {
+        self.render_in_progress = false;
+        self.jobs.finish(job);
+        match result {
+            Ok(img) => {
+                self.shown_y_resolution = img.height();
+                self.image = Some(Preview::new(img));
+                let histogram = Self::compute_histogram(&view);
+                self.last_good_view = *view;
+                histogram
+            }
+            Err(RenderError::Cancelled) => self.push_notification("render cancelled".into()),
+            Err(error) => self.handle_render_error(&error),
+        }
+    }
+
+    /// Checks the rendered view for a [`RenderWarning::DarkFrame`] in the background.
+    /// The check iterates a few thousand points, which takes too long to wait for between frames at high iterations.
+    fn check_dark_frame(&self, view: &RenderedView) -> Command<<Self as Application>::Message> {
+        let params = view.params.clone();
+        let view_region = view.view_region;
+        let threshold = self.dark_frame_threshold;
+        let generation = self.render_generation;
+        Command::perform(
+            async move { dark_frame_warning(&params, view_region, threshold) },
+            move |warning| Message::Render(RenderAction::DarkFrameChecked(generation, warning)),
+        )
+    }
+
+    /// Returns the settings of the current view, to be sent along with a render.
+    fn current_view(&self) -> Box<RenderedView> {
+        Box::new(RenderedView {
+            params: self.params.clone(),
+            view_region: self.view_region,
+            zoom: self.zoom,
+        })
+    }
+
+    /// Shows the error from a failed render until the user dismisses it, and goes back
+    /// to the settings of the last view that rendered without errors. The image of that view
+    /// is kept on screen.
+    fn handle_render_error(
+        &mut self,
+        error: &RenderError,
+    ) -> Command<<Self as Application>::Message> {
+        let suggestion = match error {
+            RenderError::InvalidFrame(_) => {
+                "Check that the center coordinates are finite numbers and zoom out if the view is too small to represent."
+            }
+            RenderError::ImageTooLarge { .. } => {
+                "Lower the vertical resolution or render in grayscale to use less memory."
+            }
+            // The viewer lets the renderer allocate its images and handles cancelled renders
+            // before they get here, so this should not happen.
+            RenderError::BufferLength { .. }
+            | RenderError::TileOutsideImage(_)
+            | RenderError::Cancelled => {
+                "This is a bug in the viewer."
+            }
+        };
+        self.render_error = Some(format!(
+            "{error}\n{suggestion}\nThe settings of the last successful render have been restored."
+        ));
+
+        let RenderedView {
+            params,
+            view_region,
+            zoom,
+        } = self.last_good_view.clone();
+        self.params = params;
+        self.view_region = view_region;
+        self.zoom = zoom;
+        self.show_coordinates();
+        self.ui_values.zoom = zoom.to_string();
+        let ssaa_factor = self.params.sqrt_samples_per_pixel;
+        self.ui_values.do_ssaa = ssaa_factor.get() > 1;
+        if self.ui_values.do_ssaa {
+            self.ui_values.slider_ssaa_factor = ssaa_factor;
+        }
+        // Any preview that was scheduled for the failed view is outdated.
+        self.interaction_generation += 1;
+        Command::none()
+    }
+
+    /// Ask the user whether to go through with a render that is estimated to take
+    /// longer than the warning threshold, and offer to lower the SSAA factor to make it faster.
+    fn confirm_slow_render(
+        &mut self,
+        estimate: Duration,
+    ) -> Command<<Self as Application>::Message> {
+        let ssaa_factor = self.params.sqrt_samples_per_pixel.get();
+        // The render time is roughly proportional to the number of samples per pixel.
+        let time_ratio = self.render_time_warning.as_secs_f64() / estimate.as_secs_f64();
+        let lowered_ssaa_factor = (f64::from(ssaa_factor) * time_ratio.sqrt())
+            .floor()
+            .max(1.0) as u8;
+
+        let mut description = format!(
+            "This render is estimated to take {:.1} seconds.",
+            estimate.as_secs_f64()
+        );
+        let buttons = if lowered_ssaa_factor < ssaa_factor {
+            write!(
+                description,
+                "\nWith {} instead of {} samples per pixel it would take around {:.1} seconds.",
+                lowered_ssaa_factor.pow(2),
+                ssaa_factor.pow(2),
+                estimate.as_secs_f64() * f64::from(lowered_ssaa_factor.pow(2))
+                    / f64::from(ssaa_factor.pow(2))
+            )
+            .unwrap_or(());
+            MessageButtons::YesNoCancelCustom(
+                RENDER_ANYWAY.to_owned(),
+                LOWER_SSAA.to_owned(),
+                CANCEL.to_owned(),
+            )
+        } else {
+            MessageButtons::OkCancelCustom(RENDER_ANYWAY.to_owned(), CANCEL.to_owned())
+        };
+
+        // Depending on the platform the dialog reports either the standard result or the label of the button.
+        match MessageDialog::new()
+            .set_level(MessageLevel::Warning)
+            .set_title(PROGRAM_NAME)
+            .set_description(description)
+            .set_buttons(buttons)
+            .show()
+        {
+            MessageDialogResult::Yes | MessageDialogResult::Ok => self.start_render(),
+            MessageDialogResult::Custom(label) if label == RENDER_ANYWAY => self.start_render(),
+            MessageDialogResult::No => self.lower_ssaa_and_render(lowered_ssaa_factor),
+            MessageDialogResult::Custom(label) if label == LOWER_SSAA => {
+                self.lower_ssaa_and_render(lowered_ssaa_factor)
+            }
+            _ => self.push_notification("render cancelled".into()),
+        }
+    }
+
+    /// Set the SSAA factor to the given value and start a full resolution render.
+    fn lower_ssaa_and_render(
+        &mut self,
+        ssaa_factor: u8,
+    ) -> Command<<Self as Application>::Message> {
+        let ssaa_factor = NonZeroU8::new(ssaa_factor).unwrap_or(NonZeroU8::MIN);
+        self.params.sqrt_samples_per_pixel = ssaa_factor;
+        if ssaa_factor.get() == 1 {
+            self.ui_values.do_ssaa = false;
+        } else {
+            self.ui_values.slider_ssaa_factor = ssaa_factor;
+        }
+        Command::batch([
+            self.push_notification(format!(
+                "lowered SSAA to {} samples per pixel",
+                ssaa_factor.get().pow(2)
+            )),
+            self.start_render(),
+        ])
+    }
+
     /// Asynchronously render a low-resolution image.
     fn render_preview(&mut self) -> Command<<Self as Application>::Message> {
-        let new_params = self
+        self.interaction_generation += 1;
+        let mut new_params = self
             .with_new_resolution(480.try_into().expect("480 is not 0"))
             .expect("480 is a valid resolution");
+        // The preview is only a rough guide, so use f32 if it can resolve the pixels
+        new_params.precision =
+            Precision::lowest_sufficient(self.view_region, new_params.y_resolution.into());
+        // and guess the colors of the areas where they do not change.
+        new_params.quality = RenderQuality::Draft;
+        // The user is waiting for the preview, and it is quick to render at full speed.
+        new_params.cpu_limit = CpuLimit::NONE;
         let view_region = self.view_region;
+        let view = self.current_view();
         self.render_in_progress = true;
+        let (job, progress, refresh) = self.start_job(JobKind::Preview);
+        Command::batch([
+            refresh,
+            Command::perform(
+                async move { try_render_with_progress(&new_params, view_region, progress.as_ref()) },
+                move |result| Message::Render(RenderAction::Finished(job, view, result)),
+            ),
+        ])
+    }
+
+    /// Render a preview of the view if changes of the given kind trigger previews.
+    /// If previews are delayed it is only rendered once the user has not changed anything
+    /// for the delay, so that a series of changes results in a single preview.
+    fn schedule_preview(
+        &mut self,
+        trigger: PreviewTrigger,
+    ) -> Command<<Self as Application>::Message> {
+        let live_preview = self.ui_values.live_preview;
+        if !live_preview.is_triggered_by(trigger) {
+            return Command::none();
+        }
+        if live_preview.delay.is_zero() {
+            return self.render_preview();
+        }
+        self.interaction_generation += 1;
+        let generation = self.interaction_generation;
         Command::perform(
-            async move { render(new_params, view_region, false) },
-            |img| Message::Render(RenderAction::Finished(img)),
+            async move { std::thread::sleep(live_preview.delay) },
+            move |()| Message::InteractionSettled(generation),
         )
     }
 
-    /// Modifies the current view to be zoomed to 2^(the given factor).
-    /// Adding one to the factor halves the dimensions of the view.
+    /// Asynchronously render a low-resolution grayscale potential field of the view
+    /// that is fast enough to keep up with the user changing it,
+    /// and schedule a preview of the configured quality for when they stop.
+    fn render_fast_preview(&mut self) -> Command<<Self as Application>::Message> {
+        self.interaction_generation += 1;
+        let generation = self.interaction_generation;
+        let view_region = self.view_region;
+        let mut new_params = self
+            .with_new_resolution(480.try_into().expect("480 is not 0"))
+            .expect("480 is a valid resolution")
+            .fast_preview(view_region);
+        new_params.cpu_limit = CpuLimit::NONE;
+        let view = self.current_view();
+        self.render_in_progress = true;
+        let (job, progress, refresh) = self.start_job(JobKind::Preview);
+        Command::batch([
+            refresh,
+            Command::perform(
+                async move { try_render_with_progress(&new_params, view_region, progress.as_ref()) },
+                move |result| Message::Render(RenderAction::Finished(job, view, result)),
+            ),
+            Command::perform(
+                async { std::thread::sleep(INTERACTION_SETTLE_TIME) },
+                move |()| Message::InteractionSettled(generation),
+            ),
+        ])
+    }
+
+    /// Asks the user where to save an image, starting from the folder and file name of the given path.
+    fn ask_save_path(suggested: &Path) -> Option<PathBuf> {
+        let mut dialog = FileDialog::new().add_filter("image", &IMAGE_EXTENSIONS);
+        if let Some(file_name) = suggested.file_name() {
+            dialog = dialog.set_file_name(file_name.to_string_lossy());
+        }
+        if let Some(folder) = suggested.parent().filter(|folder| folder.is_dir()) {
+            dialog = dialog.set_directory(folder);
+        }
+        dialog.save_file()
+    }
+
+    /// Saves the image at the path. If that fails the user is asked whether to try again,
+    /// to save it at another path or in another format, or to give up on it.
+    /// An image that the user gives up on is kept, so that saving it can be retried later.
+    fn save_with_recovery(
+        &mut self,
+        image: DynamicImage,
+        has_color: bool,
+        mut path: PathBuf,
+    ) -> Command<<Self as Application>::Message> {
+        loop {
+            let error = match save_image(&image, &path, has_color) {
+                Ok(()) => return self.push_notification(format!("saved {}", path.display())),
+                Err(error) => error,
+            };
+
+            // Depending on the platform the dialog reports either the standard result or the label of the button.
+            let choice = match MessageDialog::new()
+                .set_level(MessageLevel::Error)
+                .set_title(PROGRAM_NAME)
+                .set_description(format!(
+                    "Could not save {}: {error}.\n{}",
+                    path.display(),
+                    error.suggestion()
+                ))
+                .set_buttons(MessageButtons::YesNoCancelCustom(
+                    RETRY.to_owned(),
+                    SAVE_ELSEWHERE.to_owned(),
+                    CANCEL.to_owned(),
+                ))
+                .show()
+            {
+                MessageDialogResult::Yes => RETRY.to_owned(),
+                MessageDialogResult::No => SAVE_ELSEWHERE.to_owned(),
+                MessageDialogResult::Custom(label) => label,
+                _ => CANCEL.to_owned(),
+            };
+            let next_path = match choice.as_str() {
+                RETRY => Some(path.clone()),
+                SAVE_ELSEWHERE => Self::ask_save_path(&error.suggested_path(&path)),
+                _ => None,
+            };
+            match next_path {
+                Some(next_path) => path = next_path,
+                None => {
+                    self.unsaved_image = Some(UnsavedImage {
+                        image,
+                        has_color,
+                        path,
+                        error,
+                    });
+                    return self.push_notification(
+                        "the image was not saved, it is kept until it is saved or discarded".into(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Saves the current image as a PNG with the command that renders it stored in it,
+    /// optionally with a QR code of the command in its corner, and copies the command to the clipboard.
+    fn share(&mut self) -> Command<<Self as Application>::Message> {
+        let Some(mut img) = self.image.as_ref().map(Preview::to_image) else {
+            return self.push_notification("no image to share".into());
+        };
+        let Some(path) = FileDialog::new()
+            .add_filter("png", &["png"])
+            .set_file_name("mandelbrot_share.png")
+            .save_file()
+        else {
+            return self.push_notification("share cancelled".into());
+        };
+
+        // The shared command has the coordinates shortened like they are shown.
+        let shared_region = self
+            .ui_values
+            .coordinate_rounding
+            .apply_to_center(self.view_region, self.coordinate_decimals());
+        let arguments = view_arguments(&self.params, shared_region, self.zoom);
+        let command = view_string(&arguments);
+        if self.ui_values.share_qr_code {
+            if let Err(e) = stamp_qr_code(&mut img, &command) {
+                return self.push_notification(e);
+            }
+        }
+        match save_shared(&img, &path, self.params.color_type.has_color(), &arguments) {
+            Ok(()) => Command::batch([
+                clipboard::write(command),
+                self.push_notification(format!(
+                    "saved {} and copied the command that renders it",
+                    path.display()
+                )),
+            ]),
+            Err(e) => self.push_notification(format!("could not save {}: {e}", path.display())),
+        }
+    }
+
+    /// Copy the current image to the clipboard, with the watermark stamped onto it like when it is saved.
+    #[cfg(feature = "clipboard")]
+    fn copy_image(&mut self) -> Command<<Self as Application>::Message> {
+        let Some(mut img) = self.image.as_ref().map(Preview::to_image) else {
+            return self.push_notification("no image to copy".into());
+        };
+        if let Some(watermark) = self.watermark() {
+            watermark.stamp(&mut img);
+        }
+        match image_clipboard::copy_image(&mut self.image_clipboard, img) {
+            Ok(None) => self.push_notification("copied the image to the clipboard".into()),
+            Ok(Some((width, height))) => self.push_notification(format!(
+                "copied the image to the clipboard, downscaled to {width}x{height}"
+            )),
+            Err(e) => self.push_notification(e),
+        }
+    }
+
+    /// Returns a button that copies the current image to the clipboard.
+    #[cfg(feature = "clipboard")]
+    fn copy_image_button(&self) -> Element<'_, <Self as Application>::Message> {
+        Tooltip::new(
+            Button::new("Copy image").on_press(Message::CopyImagePressed),
+            "Copy the image to the clipboard,\ndownscaled if it is larger than 4K".to_owned(),
+            Position::FollowCursor,
+        )
+        .into()
+    }
+
+    /// The viewer is compiled without the `clipboard` feature, so there is no button.
+    #[cfg(not(feature = "clipboard"))]
+    fn copy_image_button(&self) -> Element<'_, <Self as Application>::Message> {
+        Space::new(Length::Shrink, Length::Shrink).into()
+    }
+
+    /// Show a desktop notification that a render or export started at the given time has finished,
+    /// if it took long enough for the user to have turned to something else and they want notifications.
+    #[cfg(feature = "notify")]
+    fn notify_finished(
+        &mut self,
+        what: &str,
+        started: Instant,
+    ) -> Command<<Self as Application>::Message> {
+        let elapsed = started.elapsed();
+        if !self.ui_values.desktop_notifications || elapsed < desktop_notification::NOTIFY_AFTER {
+            return Command::none();
+        }
+        match desktop_notification::show(&format!(
+            "{what} after {:.0} seconds",
+            elapsed.as_secs_f64()
+        )) {
+            Ok(()) => Command::none(),
+            Err(e) => self.push_notification(e),
+        }
+    }
+
+    /// The viewer is compiled without the `notify` feature, so there are no desktop notifications.
+    #[cfg(not(feature = "notify"))]
+    fn notify_finished(
+        &mut self,
+        _what: &str,
+        _started: Instant,
+    ) -> Command<<Self as Application>::Message> {
+        Command::none()
+    }
+
+    /// Returns the jobs panel: the renders that are running with their progress and a button for cancelling them,
+    /// followed by the exports that are queued with a button for removing them from the queue.
+    fn jobs_panel(&self) -> Element<'_, <Self as Application>::Message> {
+        if self.jobs.is_empty() {
+            return Text::new("No renders are running").into();
+        }
+        let running = self.jobs.jobs().iter().map(|job| {
+            column![
+                row![
+                    Text::new(job.kind.to_string()).width(Length::Fill),
+                    if job.progress.is_cancelled() {
+                        Button::new("Cancelling...")
+                    } else {
+                        Button::new(CANCEL).on_press(Message::Jobs(JobsAction::Cancelled(job.id)))
+                    },
+                ],
+                ProgressBar::new(0.0..=1.0, job.progress.fraction() as f32),
+            ]
+            .into()
+        });
+        let queued = self
+            .export
+            .iter()
+            .flat_map(ExportBatch::queued)
+            .enumerate()
+            .map(|(index, export_job)| {
+                row![
+                    Text::new(format!("Queued export of {}", export_job.file_name()))
+                        .width(Length::Fill),
+                    Button::new("Remove")
+                        .on_press(Message::Jobs(JobsAction::QueuedExportRemoved(index))),
+                ]
+                .into()
+            });
+        Column::with_children(running.chain(queued).collect()).into()
+    }
+
+    /// Returns a checkbox for whether long renders and exports show a desktop notification when they finish.
+    #[cfg(feature = "notify")]
+    fn desktop_notifications_checkbox(&self) -> Element<'_, <Self as Application>::Message> {
+        Tooltip::new(
+            Checkbox::new(
+                "Notify when done",
+                self.ui_values.desktop_notifications,
+                Message::DesktopNotificationsToggled,
+            ),
+            format!(
+                "Show a desktop notification when a render or export\nthat takes more than {} seconds finishes",
+                desktop_notification::NOTIFY_AFTER.as_secs()
+            ),
+            Position::FollowCursor,
+        )
+        .into()
+    }
+
+    /// The viewer is compiled without the `notify` feature, so there is no checkbox.
+    #[cfg(not(feature = "notify"))]
+    fn desktop_notifications_checkbox(&self) -> Element<'_, <Self as Application>::Message> {
+        Space::new(Length::Shrink, Length::Shrink).into()
+    }
+
+    /// Returns the watermark that is stamped onto saved and exported images, if any.
+    fn watermark(&self) -> Option<Watermark> {
+        self.watermark_logo.as_ref().and_then(|logo| {
+            Watermark::new(
+                logo.clone(),
+                self.ui_values.watermark_position,
+                self.ui_values.watermark_opacity,
+            )
+            .ok()
+        })
+    }
+
+    /// Ask the user where to save the images and queue a render of the current view
+    /// with each of the selected export presets.
+    fn start_export(&mut self) -> Command<<Self as Application>::Message> {
+        if self.export.is_some() {
+            return self.push_notification("an export is already in progress".into());
+        }
+        let Some(base) = Self::ask_save_path(Path::new("mandelbrot_set.png")) else {
+            return self.push_notification("export cancelled".into());
+        };
+
+        let jobs: Result<Vec<ExportJob>, TryFromIntError> = EXPORT_PRESETS
+            .iter()
+            .zip(self.ui_values.export_presets)
+            .filter(|(_, selected)| *selected)
+            .map(|(preset, _)| {
+                let mut params = self.with_new_resolution(preset.y_resolution)?;
+                params.sqrt_samples_per_pixel = preset.ssaa;
+                let path = templated_path(
+                    &base,
+                    preset,
+                    params.x_resolution.into(),
+                    params.y_resolution.into(),
+                );
+                Ok(ExportJob {
+                    params,
+                    view_region: self.view_region,
+                    path,
+                    watermark: self.watermark(),
+                })
+            })
+            .collect();
+        match jobs {
+            Ok(jobs) if jobs.is_empty() => {
+                self.push_notification("no export presets are selected".into())
+            }
+            Ok(jobs) => {
+                self.export = Some(ExportBatch::new(jobs));
+                self.next_export()
+            }
+            Err(e) => self.push_notification(e.to_string()),
+        }
+    }
+
+    /// Asynchronously render and save the next image of the export in progress,
+    /// or end the export if every image is done.
+    fn next_export(&mut self) -> Command<<Self as Application>::Message> {
+        let Some(batch) = self.export.as_mut() else {
+            return Command::none();
+        };
+        match batch.next_job() {
+            Some(export_job) => {
+                let samples = export_job.samples();
+                let (job, progress, refresh) =
+                    self.start_job(JobKind::Export(export_job.file_name()));
+                Command::batch([
+                    refresh,
+                    Command::perform(
+                        async move { export_job.run(progress.as_ref()) },
+                        move |result| {
+                            Message::Export(ExportAction::JobFinished(job, samples, result))
+                        },
+                    ),
+                ])
+            }
+            None => {
+                let notification = match self.export.take() {
+                    Some(batch) => {
+                        self.notify_finished("Finished exporting the view", batch.started())
+                    }
+                    None => Command::none(),
+                };
+                Command::batch([
+                    self.push_notification("export finished".into()),
+                    notification,
+                ])
+            }
+        }
+    }
+
+    /// Ask the user where to save the recording and asynchronously record a zoom into the current view,
+    /// see [`ZoomRecording`].
+    fn start_recording(&mut self) -> Command<<Self as Application>::Message> {
+        if self.recording_started.is_some() {
+            return self.push_notification("a zoom is already being recorded".into());
+        }
+        let Some(path) = FileDialog::new()
+            .add_filter("animation", &RECORDING_EXTENSIONS)
+            .set_file_name("mandelbrot_zoom.gif")
+            .save_file()
+        else {
+            return self.push_notification("recording cancelled".into());
+        };
+        let frames = NonZeroU32::new(self.ui_values.recording_frames)
+            .expect("the frame slider starts above zero");
+        let recording = match ZoomRecording::new(
+            &self.params,
+            self.view_region,
+            self.ui_values.recording_zoom_levels,
+            frames,
+            path,
+        ) {
+            Ok(recording) => recording,
+            Err(e) => return self.push_notification(e),
+        };
+        self.recording_started = Some(Instant::now());
+        let (job, progress, refresh) = self.start_job(JobKind::Recording(recording.file_name()));
+        Command::batch([
+            refresh,
+            Command::perform(
+                async move { recording.run(progress.as_ref()) },
+                move |result| Message::Recording(RecordingAction::Finished(job, result)),
+            ),
+        ])
+    }
+
+    /// Modifies the current view to be zoomed to the given level.
+    /// Adding one to the level halves the dimensions of the view.
     /// 0 means no zoom relative the the initial state of the application,
     /// so calling this function twice with the same input has no effect.
-    fn zoom_to(&mut self, factor: f64) {
-        self.zoom = factor;
-        self.ui_values.zoom = factor.to_string();
-        self.view_region.imag_distance = INITIAL_IMAG_DISTANCE / 2.0_f64.powf(factor);
-        self.view_region.real_distance = self.view_region.imag_distance * self.aspect_ratio;
+    fn zoom_to(&mut self, zoom: Zoom) {
+        self.zoom = zoom;
+        self.ui_values.zoom = zoom.to_string();
+        self.view_region = Frame::from_zoom(
+            self.view_region.center_real,
+            self.view_region.center_imag,
+            zoom,
+            self.aspect_ratio,
+        );
+        // Zooming changes how many decimals the coordinates are shown with. They are left as they were typed
+        // when they are shown in full, so that zooming does not undo the user's formatting.
+        if self.ui_values.coordinate_rounding != CoordinateRounding::Full {
+            self.show_coordinates();
+        }
+    }
+
+    /// The number of decimals that the coordinates of the current view need at its zoom and resolution.
+    fn coordinate_decimals(&self) -> usize {
+        self.view_region
+            .coordinate_decimals(self.params.y_resolution.into())
+    }
+
+    /// Shows the center of the view in the sidebar, shortened as the user has chosen.
+    fn show_coordinates(&mut self) {
+        let rounding = self.ui_values.coordinate_rounding;
+        let decimals = self.coordinate_decimals();
+        self.ui_values.center_real = rounding.format(self.view_region.center_real, decimals);
+        self.ui_values.center_imag = rounding.format(self.view_region.center_imag, decimals);
+    }
+
+    /// Copies the center of the view to the clipboard as "real, imaginary", shortened like in the sidebar.
+    fn copy_coordinates(&mut self) -> Command<<Self as Application>::Message> {
+        let rounding = self.ui_values.coordinate_rounding;
+        let decimals = self.coordinate_decimals();
+        let coordinates = format!(
+            "{}, {}",
+            rounding.format(self.view_region.center_real, decimals),
+            rounding.format(self.view_region.center_imag, decimals)
+        );
+        Command::batch([
+            clipboard::write(coordinates),
+            self.push_notification("copied the coordinates of the view".into()),
+        ])
+    }
+
+    /// Bookmark the current view with the name, notes and tags in the sidebar,
+    /// replacing any bookmark with the same name, and save the bookmarks.
+    fn save_bookmark(&mut self) -> Command<<Self as Application>::Message> {
+        let name = self.ui_values.bookmark_name.trim();
+        if name.is_empty() {
+            return self.push_notification("give the bookmark a name first".into());
+        }
+        let bookmark = Bookmark {
+            name: name.to_owned(),
+            arguments: view_arguments(&self.params, self.view_region, self.zoom),
+            notes: self.ui_values.bookmark_notes.trim().to_owned(),
+            tags: Bookmark::parse_tags(&self.ui_values.bookmark_tags),
+        };
+        let text = match self
+            .bookmarks
+            .bookmarks
+            .iter_mut()
+            .find(|b| b.name == bookmark.name)
+        {
+            Some(existing) => {
+                *existing = bookmark;
+                format!("updated the bookmark \"{name}\"")
+            }
+            None => {
+                let text = format!("bookmarked the view as \"{name}\"");
+                self.bookmarks.bookmarks.push(bookmark);
+                text
+            }
+        };
+        match save_library(&self.bookmarks, Path::new(DEFAULT_BOOKMARKS_FILE)) {
+            Ok(()) => self.push_notification(text),
+            Err(e) => self.push_notification(e),
+        }
+    }
+
+    /// Go to the view of the bookmark with the given index and show its name, notes and tags
+    /// in the sidebar so that they can be edited.
+    fn go_to_bookmark(&mut self, index: usize) -> Command<<Self as Application>::Message> {
+        let Some(bookmark) = self.bookmarks.bookmarks.get(index) else {
+            return Command::none();
+        };
+        let (center_real, center_imag, zoom) = match bookmarked_view(bookmark) {
+            Ok(view) => view,
+            Err(e) => return self.push_notification(e),
+        };
+        self.ui_values.bookmark_name.clone_from(&bookmark.name);
+        self.ui_values.bookmark_notes.clone_from(&bookmark.notes);
+        self.ui_values.bookmark_tags = bookmark.tags.join(" ");
+        self.view_region.center_real = center_real;
+        self.view_region.center_imag = center_imag;
+        self.zoom_to(zoom);
+        self.show_coordinates();
+        self.schedule_preview(PreviewTrigger::View)
+    }
+
+    /// Show the given help overlay. The first time the tour is left, by finishing or skipping it,
+    /// that is saved so that the tour is not shown again when the viewer is started.
+    fn show_help(&mut self, overlay: HelpOverlay) -> Command<<Self as Application>::Message> {
+        let tour_left = self.help_overlay.tour_step().is_some() && overlay.tour_step().is_none();
+        self.help_overlay = overlay;
+        if !tour_left || self.config.tour_dismissed {
+            return Command::none();
+        }
+        self.config.tour_dismissed = true;
+        match self.config.save(Path::new(DEFAULT_CONFIG_FILE)) {
+            Ok(()) => Command::none(),
+            Err(e) => self.push_notification(e),
+        }
+    }
+
+    fn handle_shortcut(&mut self, shortcut: Shortcut) -> Command<<Self as Application>::Message> {
+        match shortcut {
+            Shortcut::ToggleHelp => self.show_help(self.help_overlay.toggle_shortcuts()),
+            Shortcut::Dismiss => self.show_help(HelpOverlay::Hidden),
+            Shortcut::ZoomIn => self.update(Message::Frame(FrameAction::ZoomSubmittedWith(
+                self.zoom.level() + 1.0,
+            ))),
+            Shortcut::ZoomOut => self.update(Message::Frame(FrameAction::ZoomSubmittedWith(
+                self.zoom.level() - 1.0,
+            ))),
+            // Like the button, which can not be pressed while a render is in progress.
+            Shortcut::Render if self.render_in_progress => Command::none(),
+            Shortcut::Render => self.update(Message::Render(RenderAction::Started)),
+            Shortcut::Save => self.update(Message::Save(SaveAction::Pressed)),
+        }
+    }
+
+    /// Returns the label of a control, highlighted if the tour is showing it.
+    fn tour_label(&self, step: TourStep, label: &'static str) -> Text<'static> {
+        let text = Text::new(label);
+        if self.help_overlay.tour_step() == Some(step) {
+            text.style(TOUR_HIGHLIGHT)
+        } else {
+            text
+        }
+    }
+
+    /// Returns the list of keyboard shortcuts or the current step of the tour,
+    /// with buttons for moving through the tour or closing the overlay.
+    fn help_overlay(&self) -> Element<'_, <Self as Application>::Message> {
+        match self.help_overlay {
+            HelpOverlay::Hidden => Space::new(Length::Shrink, Length::Shrink).into(),
+            HelpOverlay::Shortcuts => row![
+                Text::new(SHORTCUTS.iter().fold(
+                    String::from("Keyboard shortcuts\n"),
+                    |mut text, (keys, action)| {
+                        writeln!(text, "{keys}: {action}").unwrap_or(());
+                        text
+                    }
+                ))
+                .width(Length::Fill),
+                Button::new("Take the tour").on_press(Message::Help(HelpAction::TourStarted)),
+                Button::new("Close").on_press(Message::Help(HelpAction::Dismissed)),
+            ]
+            .spacing(10)
+            .into(),
+            HelpOverlay::Tour(step) => row![
+                Text::new(format!(
+                    "Tour {} of {}\n{}",
+                    step as usize + 1,
+                    TourStep::ALL.len(),
+                    step.description()
+                ))
+                .style(TOUR_HIGHLIGHT)
+                .width(Length::Fill),
+                Button::new(if step.next().is_some() {
+                    "Next"
+                } else {
+                    "Done"
+                })
+                .on_press(Message::Help(HelpAction::NextPressed)),
+                Button::new("Skip tour").on_press(Message::Help(HelpAction::Dismissed)),
+            ]
+            .spacing(10)
+            .into(),
+        }
     }
 }
 
 impl Application for MandelViewer {
     type Executor = executor::Default;
     type Message = Message;
-    type Flags = ();
+    type Flags = (Duration, DarkFrameThreshold);
     type Theme = Theme;
 
-    fn new(_flags: ()) -> (MandelViewer, Command<Self::Message>) {
+    fn new(
+        (render_time_warning, dark_frame_threshold): (Duration, DarkFrameThreshold),
+    ) -> (MandelViewer, Command<Self::Message>) {
         let params = RenderParameters::try_new(
             INITIAL_X_RES,
             INITIAL_Y_RES,
@@ -205,13 +1400,33 @@ impl Application for MandelViewer {
             SupportedColorType::Rgba8,
         )
         .unwrap();
-        let view_region = Frame::new(
+        let view_region = Frame::from_zoom(
             INITIAL_REAL_CENTER,
             INITIAL_IMAG_CENTER,
-            f64::from(INITIAL_X_RES.get()) / f64::from(INITIAL_Y_RES.get()) * INITIAL_IMAG_DISTANCE,
-            INITIAL_IMAG_DISTANCE,
+            INITIAL_ZOOM,
+            f64::from(INITIAL_X_RES.get()) / f64::from(INITIAL_Y_RES.get()),
         );
 
+        // The viewer still works without bookmarks, so an unreadable file is only reported.
+        let (bookmarks, notifications) = match load_library(Path::new(DEFAULT_BOOKMARKS_FILE)) {
+            Ok(bookmarks) => (bookmarks, Vec::new()),
+            Err(e) => (BookmarkLibrary::default(), vec![e]),
+        };
+        // Unreadable settings are reported the same way, and the defaults are used instead.
+        let (config, notifications) = match ViewerConfig::load(Path::new(DEFAULT_CONFIG_FILE)) {
+            Ok(config) => (config, notifications),
+            Err(e) => (ViewerConfig::default(), [notifications, vec![e]].concat()),
+        };
+
+        let initial_params = params.clone();
+        let mut jobs = JobList::default();
+        let (initial_job, initial_progress) = jobs.start(JobKind::FullRender);
+        let initial_view = RenderedView {
+            params: params.clone(),
+            view_region,
+            zoom: INITIAL_ZOOM,
+        };
+
         (
             MandelViewer {
                 image: None,
@@ -220,21 +1435,85 @@ impl Application for MandelViewer {
                 aspect_ratio: f64::from(INITIAL_X_RES.get()) / f64::from(INITIAL_Y_RES.get()),
                 zoom: INITIAL_ZOOM,
                 render_in_progress: true,
-                notifications: Vec::new(),
+                render_started: None,
+                render_generation: 0,
+                shown_y_resolution: 0,
+                notifications,
+                render_error: None,
+                render_warnings: render_warnings(&initial_params, view_region),
+                last_good_view: initial_view.clone(),
                 ui_values: UIValues {
                     slider_ssaa_factor: INITIAL_SSAA_FACTOR,
                     do_ssaa: true,
-                    live_preview: true,
+                    live_preview: LivePreview::default(),
                     center_real: view_region.center_real.to_string(),
                     center_imag: view_region.center_imag.to_string(),
                     zoom: INITIAL_ZOOM.to_string(),
+                    coordinate_rounding: CoordinateRounding::default(),
+                    export_presets: [true, true, false],
+                    watermark_position: WatermarkPosition::default(),
+                    watermark_opacity: 0.5,
+                    recording_frames: 50,
+                    recording_zoom_levels: 4.0,
+                    share_qr_code: true,
+                    #[cfg(feature = "notify")]
+                    desktop_notifications: true,
+                    render_server: default_render_server_socket().display().to_string(),
+                    bookmark_name: String::new(),
+                    bookmark_notes: String::new(),
+                    bookmark_tags: String::new(),
+                    bookmark_search: String::new(),
                 },
+                render_time_warning,
+                estimating_render_time: false,
+                dark_frame_threshold,
+                interaction_generation: 0,
+                escape_speed_counts: Vec::new(),
+                palette_histogram: palette_histogram(
+                    &initial_params.palette,
+                    initial_params.escape_speed_range,
+                    false,
+                    &[],
+                ),
+                export: None,
+                recording_started: None,
+                jobs,
+                refreshing_jobs: true,
+                watermark_logo: None,
+                unsaved_image: None,
+                recolor_cache: None,
+                bookmarks,
+                #[cfg(feature = "clipboard")]
+                image_clipboard: None,
+                // The tour is shown until the user has finished or skipped it once.
+                help_overlay: if config.tour_dismissed {
+                    HelpOverlay::Hidden
+                } else {
+                    HelpOverlay::Tour(TourStep::ALL[0])
+                },
+                config,
             },
             Command::batch([
                 window::maximize(true),
-                Command::perform(async move { render(params, view_region, false) }, |img| {
-                    Message::Render(RenderAction::Finished(img))
-                }),
+                Self::refresh_jobs(),
+                Self::refine(initial_params.clone(), view_region, 0, 0),
+                Command::perform(
+                    async move {
+                        Self::render_recolorable(
+                            &initial_params,
+                            view_region,
+                            None,
+                            initial_progress.as_ref(),
+                        )
+                    },
+                    move |result| {
+                        Message::Render(RenderAction::Computed(
+                            initial_job,
+                            Box::new(initial_view),
+                            result,
+                        ))
+                    },
+                ),
             ]),
         )
     }
@@ -248,28 +1527,126 @@ impl Application for MandelViewer {
         // + "i"
     }
 
+    fn subscription(&self) -> Subscription<Self::Message> {
+        subscription::events_with(|event, status| {
+            help::shortcut(event, status)
+                .map(|shortcut| Message::Help(HelpAction::Shortcut(shortcut)))
+        })
+    }
+
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
         match message {
+            Message::Help(action) => match action {
+                HelpAction::Shortcut(shortcut) => self.handle_shortcut(shortcut),
+                HelpAction::TourStarted => self.show_help(HelpOverlay::Tour(TourStep::ALL[0])),
+                HelpAction::NextPressed => self.show_help(self.help_overlay.next()),
+                HelpAction::Dismissed => self.show_help(HelpOverlay::Hidden),
+            },
+            Message::CpuLimitUpdated(cpu_limit) => {
+                self.params.cpu_limit = cpu_limit;
+                Command::none()
+            }
             Message::MaxItersUpdated(max_iters) => {
                 self.params.max_iterations = max_iters;
-                if self.ui_values.live_preview {
-                    self.render_preview()
-                } else {
-                    Command::none()
-                }
+                self.schedule_preview(PreviewTrigger::Iterations)
             }
             Message::Render(action) => match action {
                 RenderAction::Started => {
-                    self.render_in_progress = true;
-                    let params = self.params;
+                    if self.render_time_warning.is_zero() {
+                        return self.start_render();
+                    }
+                    if self.estimating_render_time {
+                        return Command::none();
+                    }
+                    // The estimate renders a small version of the view, which takes too long to wait for between frames.
+                    self.estimating_render_time = true;
+                    let params = self.params.clone();
                     let view_region = self.view_region;
-                    Command::perform(async move { render(params, view_region, false) }, |img| {
-                        Message::Render(RenderAction::Finished(img))
-                    })
+                    Command::perform(
+                        async move { estimate_render_time(&params, view_region) },
+                        |estimate| Message::Render(RenderAction::Estimated(estimate)),
+                    )
+                }
+                RenderAction::Estimated(estimate) => {
+                    self.estimating_render_time = false;
+                    if estimate <= self.render_time_warning {
+                        self.start_render()
+                    } else {
+                        self.confirm_slow_render(estimate)
+                    }
+                }
+                RenderAction::Finished(job, view, result) => self.finish_render(job, view, result),
+                RenderAction::Computed(job, view, result) => {
+                    let notification = match self.render_started.take() {
+                        Some(started) if !matches!(result, Err(RenderError::Cancelled)) => {
+                            self.notify_finished("Finished rendering the view", started)
+                        }
+                        _ => Command::none(),
+                    };
+                    let result = result.map(|(img, buffer)| {
+                        self.recolor_cache = buffer.map(|buffer| {
+                            RecolorCache::new(buffer, &view.params, view.view_region, view.zoom)
+                        });
+                        img
+                    });
+                    let dark_frame_check = if result.is_ok() {
+                        self.check_dark_frame(&view)
+                    } else {
+                        Command::none()
+                    };
+                    Command::batch([
+                        notification,
+                        self.finish_render(job, view, result),
+                        dark_frame_check,
+                    ])
+                }
+                RenderAction::DarkFrameChecked(generation, warning) => {
+                    if let Some(warning) = warning.filter(|warning| {
+                        generation == self.render_generation
+                            && !self.render_warnings.contains(warning)
+                    }) {
+                        self.render_warnings.push(warning);
+                    }
+                    Command::none()
                 }
-                RenderAction::Finished(img) => {
-                    self.render_in_progress = false;
-                    self.image = Some(img);
+                RenderAction::IterationsDoubled => {
+                    self.params.max_iterations = self
+                        .params
+                        .max_iterations
+                        .saturating_mul(NonZeroU64::new(2).expect("2 is not zero"));
+                    self.update(Message::Render(RenderAction::Started))
+                }
+                RenderAction::Recolored(view, img) => {
+                    // Dragging a slider starts a recoloring for every step,
+                    // so only the one with the current colors of the current view is shown.
+                    let is_current = Coloring::from(&view.params) == Coloring::from(&self.params)
+                        && self.recolor_cache.as_ref().is_some_and(|cache| {
+                            cache.shows(&self.params, self.view_region, self.zoom)
+                        });
+                    if is_current {
+                        self.shown_y_resolution = img.height();
+                        self.image = Some(Preview::new(img));
+                        self.last_good_view = *view;
+                    }
+                    Command::none()
+                }
+                RenderAction::Refined(generation, step, result) => {
+                    if !self.render_in_progress || generation != self.render_generation {
+                        return Command::none();
+                    }
+                    // A failed refinement is not reported, since the full render will fail the same way.
+                    if let Ok(img) = result {
+                        // Refinements that are smaller than the image that is already shown,
+                        // such as a preview, would only make the view blurrier.
+                        if img.height() > self.shown_y_resolution {
+                            self.shown_y_resolution = img.height();
+                            self.image = Some(Preview::new(img));
+                        }
+                    }
+                    Self::refine(self.params.clone(), self.view_region, generation, step + 1)
+                }
+                RenderAction::ErrorDismissed => {
+                    self.render_error = None;
                     Command::none()
                 }
             },
@@ -279,59 +1656,265 @@ impl Application for MandelViewer {
                     self.notifications.drain(..=0);
                     Command::none()
                 }
+                NotificationAction::WarningDismissed(warning) => {
+                    self.render_warnings.retain(|shown| *shown != warning);
+                    Command::none()
+                }
             },
-            Message::LiveCheckboxToggled(state) => {
-                self.ui_values.live_preview = state;
-                if state {
-                    self.render_preview()
-                } else {
+            Message::LivePreview(action) => match action {
+                LivePreviewAction::TriggerToggled(trigger, state) => {
+                    self.ui_values.live_preview.set_triggered_by(trigger, state);
+                    if state {
+                        self.render_preview()
+                    } else {
+                        Command::none()
+                    }
+                }
+                LivePreviewAction::DelayUpdated(millis) => {
+                    self.ui_values.live_preview.delay = Duration::from_millis(u64::from(millis));
                     Command::none()
                 }
-            }
+            },
             Message::GrayscaleToggled(state) => {
                 self.params.color_type = if state {
                     SupportedColorType::L8
                 } else {
                     SupportedColorType::Rgba8
                 };
-                if self.ui_values.live_preview {
-                    self.render_preview()
-                } else {
-                    Command::none()
+                self.redraw_palette_histogram();
+                match self.recolor() {
+                    Some(recolor) => recolor,
+                    None => self.schedule_preview(PreviewTrigger::Coloring),
                 }
             }
-            Message::SavePressed => {
-                if let Some(ref img) = self.image {
-                    match FileDialog::new()
-                        .set_file_name("mandelbrot_set.png")
-                        .add_filter(
-                            "image",
-                            &[
-                                "png", "jpg", "gif", "webp", "bmp", "tiff", "tga", "qoi", "ico",
-                                "ppm", "pam",
-                            ],
-                        )
-                        .save_file()
-                    {
-                        Some(out_path) => {
-                            if self.params.color_type.has_color() {
-                                if let Err(e) = img.to_rgb8().save(out_path) {
-                                    self.push_notification(e.to_string())
-                                } else {
-                                    self.push_notification("save operation successful".into())
-                                }
-                            } else if let Err(e) = img.to_luma8().save(out_path) {
-                                self.push_notification(e.to_string())
-                            } else {
-                                self.push_notification("save operation successful".into())
+            Message::FormulaSelected(formula) => {
+                self.params.formula = formula;
+                self.schedule_preview(PreviewTrigger::Formula)
+            }
+            Message::InteriorShadingSelected(interior_shading) => {
+                self.params.interior_shading = interior_shading;
+                self.schedule_preview(PreviewTrigger::Coloring)
+            }
+            Message::EscapeSpeedRangeUpdated(range) => {
+                self.params.escape_speed_range = range;
+                self.redraw_palette_histogram();
+                match self.recolor() {
+                    Some(recolor) => recolor,
+                    None => self.schedule_preview(PreviewTrigger::Coloring),
+                }
+            }
+            Message::HistogramComputed(counts) => {
+                self.escape_speed_counts = counts;
+                self.redraw_palette_histogram();
+                Command::none()
+            }
+            Message::Save(action) => match action {
+                SaveAction::Pressed => {
+                    if let Some(mut img) = self.image.as_ref().map(Preview::to_image) {
+                        if let Some(watermark) = self.watermark() {
+                            watermark.stamp(&mut img);
+                        }
+                        match Self::ask_save_path(Path::new("mandelbrot_set.png")) {
+                            Some(out_path) => self.save_with_recovery(
+                                img,
+                                self.params.color_type.has_color(),
+                                out_path,
+                            ),
+                            None => self.push_notification("save operation cancelled".into()),
+                        }
+                    } else {
+                        self.push_notification("no image to save".into())
+                    }
+                }
+                SaveAction::Retried => match self.unsaved_image.take() {
+                    Some(unsaved) => {
+                        self.save_with_recovery(unsaved.image, unsaved.has_color, unsaved.path)
+                    }
+                    None => Command::none(),
+                },
+                SaveAction::ElsewherePressed => match self.unsaved_image.take() {
+                    Some(unsaved) => {
+                        match Self::ask_save_path(&unsaved.error.suggested_path(&unsaved.path)) {
+                            Some(path) => {
+                                self.save_with_recovery(unsaved.image, unsaved.has_color, path)
+                            }
+                            None => {
+                                self.unsaved_image = Some(unsaved);
+                                Command::none()
                             }
                         }
-                        None => self.push_notification("save operation cancelled".into()),
                     }
-                } else {
-                    self.push_notification("no image to save".into())
+                    None => Command::none(),
+                },
+                SaveAction::Discarded => {
+                    self.unsaved_image = None;
+                    Command::none()
+                }
+            },
+            Message::Share(action) => match action {
+                ShareAction::Pressed => self.share(),
+                ShareAction::QrCodeToggled(status) => {
+                    self.ui_values.share_qr_code = status;
+                    Command::none()
                 }
+            },
+            Message::CoordinateRoundingSelected(rounding) => {
+                self.ui_values.coordinate_rounding = rounding;
+                self.show_coordinates();
+                Command::none()
+            }
+            Message::CopyCoordinatesPressed => self.copy_coordinates(),
+            #[cfg(feature = "clipboard")]
+            Message::CopyImagePressed => self.copy_image(),
+            #[cfg(feature = "notify")]
+            Message::DesktopNotificationsToggled(status) => {
+                self.ui_values.desktop_notifications = status;
+                Command::none()
             }
+            Message::Export(action) => match action {
+                ExportAction::PresetToggled(index, selected) => {
+                    self.ui_values.export_presets[index] = selected;
+                    Command::none()
+                }
+                ExportAction::Started => self.start_export(),
+                ExportAction::WatermarkToggled(false) => {
+                    self.watermark_logo = None;
+                    Command::none()
+                }
+                ExportAction::WatermarkToggled(true) => {
+                    let Some(path) = FileDialog::new()
+                        .add_filter("image", &IMAGE_EXTENSIONS)
+                        .pick_file()
+                    else {
+                        return Command::none();
+                    };
+                    match image::open(&path) {
+                        Ok(logo) => {
+                            self.watermark_logo = Some(logo);
+                            Command::none()
+                        }
+                        Err(e) => self.push_notification(format!(
+                            "could not open the watermark {}: {e}",
+                            path.display()
+                        )),
+                    }
+                }
+                ExportAction::WatermarkPositionSelected(position) => {
+                    self.ui_values.watermark_position = position;
+                    Command::none()
+                }
+                ExportAction::WatermarkOpacityUpdated(opacity) => {
+                    self.ui_values.watermark_opacity = opacity;
+                    Command::none()
+                }
+                ExportAction::JobFinished(job, samples, result) => {
+                    self.jobs.finish(job);
+                    if let Some(batch) = self.export.as_mut() {
+                        batch.job_finished(samples);
+                    }
+                    let notification = match result {
+                        Ok(path) => self.push_notification(format!("saved {}", path.display())),
+                        Err(e) => self.push_notification(e),
+                    };
+                    Command::batch([notification, self.next_export()])
+                }
+            },
+            Message::Recording(action) => match action {
+                RecordingAction::FramesUpdated(frames) => {
+                    self.ui_values.recording_frames = frames;
+                    Command::none()
+                }
+                RecordingAction::ZoomLevelsUpdated(levels) => {
+                    self.ui_values.recording_zoom_levels = levels;
+                    Command::none()
+                }
+                RecordingAction::Started => self.start_recording(),
+                RecordingAction::Finished(job, result) => {
+                    self.jobs.finish(job);
+                    let desktop_notification = match self.recording_started.take() {
+                        Some(started) if result.is_ok() => {
+                            self.notify_finished("Finished recording the zoom", started)
+                        }
+                        _ => Command::none(),
+                    };
+                    let notification = match result {
+                        Ok(path) => self.push_notification(format!("saved {}", path.display())),
+                        Err(e) => self.push_notification(e),
+                    };
+                    Command::batch([notification, desktop_notification])
+                }
+            },
+            Message::Jobs(action) => match action {
+                JobsAction::Refreshed => {
+                    // Nothing changes but the progress of the renders, which is read when the panel is drawn.
+                    if self.jobs.is_empty() {
+                        self.refreshing_jobs = false;
+                        Command::none()
+                    } else {
+                        Self::refresh_jobs()
+                    }
+                }
+                JobsAction::Cancelled(job) => {
+                    self.jobs.cancel(job);
+                    Command::none()
+                }
+                JobsAction::QueuedExportRemoved(index) => {
+                    if let Some(batch) = self.export.as_mut() {
+                        batch.remove_queued(index);
+                    }
+                    Command::none()
+                }
+            },
+            Message::RenderServer(action) => match action {
+                RenderServerAction::SocketUpdated(socket) => {
+                    self.ui_values.render_server = socket;
+                    Command::none()
+                }
+                RenderServerAction::SendPressed => {
+                    let socket = PathBuf::from(&self.ui_values.render_server);
+                    let arguments = view_arguments(&self.params, self.view_region, self.zoom);
+                    Command::perform(async move { send_view(&socket, &arguments) }, |result| {
+                        Message::RenderServer(RenderServerAction::Answered(result))
+                    })
+                }
+                RenderServerAction::Answered(result) => match result {
+                    Ok(path) => self.push_notification(format!("the server will save {path}")),
+                    Err(e) => self.push_notification(e),
+                },
+            },
+            Message::Bookmark(action) => match action {
+                BookmarkAction::NameUpdated(name) => {
+                    self.ui_values.bookmark_name = name;
+                    Command::none()
+                }
+                BookmarkAction::NotesUpdated(notes) => {
+                    self.ui_values.bookmark_notes = notes;
+                    Command::none()
+                }
+                BookmarkAction::TagsUpdated(tags) => {
+                    self.ui_values.bookmark_tags = tags;
+                    Command::none()
+                }
+                BookmarkAction::SearchUpdated(query) => {
+                    self.ui_values.bookmark_search = query;
+                    Command::none()
+                }
+                BookmarkAction::SavePressed => self.save_bookmark(),
+                BookmarkAction::Selected(index) => self.go_to_bookmark(index),
+                BookmarkAction::Removed(index) => {
+                    if index >= self.bookmarks.bookmarks.len() {
+                        return Command::none();
+                    }
+                    let removed = self.bookmarks.bookmarks.remove(index);
+                    match save_library(&self.bookmarks, Path::new(DEFAULT_BOOKMARKS_FILE)) {
+                        Ok(()) => self.push_notification(format!(
+                            "removed the bookmark \"{}\"",
+                            removed.name
+                        )),
+                        Err(e) => self.push_notification(e),
+                    }
+                }
+            },
             Message::VerticalResolutionUpdated(y_res) => match self.with_new_resolution(y_res) {
                 Ok(params) => {
                     if u32::from(params.x_resolution) * u32::from(params.y_resolution) * 4
@@ -348,9 +1931,9 @@ impl Application for MandelViewer {
             Message::SuperSampling(action) => match action {
                 SSAAAction::NumSamplesUpdated(ssaa_factor) => {
                     self.ui_values.slider_ssaa_factor = ssaa_factor;
-                    if self.ui_values.live_preview && self.ui_values.do_ssaa {
+                    if self.ui_values.do_ssaa {
                         self.params.sqrt_samples_per_pixel = self.ui_values.slider_ssaa_factor;
-                        self.render_preview()
+                        self.schedule_preview(PreviewTrigger::Supersampling)
                     } else {
                         Command::none()
                     }
@@ -363,8 +1946,21 @@ impl Application for MandelViewer {
                         self.params.sqrt_samples_per_pixel = 1.try_into().expect("1 is not zero");
                     };
 
-                    if self.ui_values.live_preview {
-                        self.render_preview()
+                    self.schedule_preview(PreviewTrigger::Supersampling)
+                }
+                SSAAAction::PlacementSelected(placement) => {
+                    self.params.sample_placement = placement;
+                    if self.ui_values.do_ssaa {
+                        self.schedule_preview(PreviewTrigger::Supersampling)
+                    } else {
+                        Command::none()
+                    }
+                }
+                SSAAAction::FilterSelected(filter) => {
+                    self.params.reconstruction_filter = filter;
+                    // With a single sample in the center of every pixel the filters give the same image.
+                    if self.ui_values.do_ssaa {
+                        self.schedule_preview(PreviewTrigger::Supersampling)
                     } else {
                         Command::none()
                     }
@@ -374,75 +1970,138 @@ impl Application for MandelViewer {
                 FrameAction::CenterRealSubmitted => match self.ui_values.center_real.parse() {
                     Ok(center_real) => {
                         self.view_region.center_real = center_real;
-                        if self.ui_values.live_preview {
-                            self.render_preview()
-                        } else {
-                            Command::none()
-                        }
+                        self.schedule_preview(PreviewTrigger::View)
                     }
                     Err(e) => self.push_notification(e.to_string()),
                 },
                 FrameAction::CenterImagSubmitted => match self.ui_values.center_imag.parse() {
                     Ok(center_imag) => {
                         self.view_region.center_imag = center_imag;
-                        if self.ui_values.live_preview {
-                            self.render_preview()
-                        } else {
-                            Command::none()
-                        }
+                        self.schedule_preview(PreviewTrigger::View)
                     }
                     Err(e) => self.push_notification(e.to_string()),
                 },
                 FrameAction::ZoomSubmitted => match self.ui_values.zoom.parse() {
-                    Ok(factor) => {
-                        self.zoom_to(factor);
-                        if self.ui_values.live_preview {
-                            self.render_preview()
-                        } else {
-                            Command::none()
-                        }
+                    Ok(zoom) => {
+                        self.zoom_to(zoom);
+                        self.schedule_preview(PreviewTrigger::View)
                     }
                     Err(e) => self.push_notification(e.to_string()),
                 },
-                FrameAction::ZoomSubmittedWith(factor) => {
-                    self.zoom_to(factor);
-                    if self.ui_values.live_preview {
-                        self.render_preview()
-                    } else {
-                        Command::none()
+                FrameAction::ZoomSubmittedWith(level) => match Zoom::try_from(level) {
+                    Ok(zoom) => {
+                        self.zoom_to(zoom);
+                        self.schedule_preview(PreviewTrigger::View)
                     }
-                }
+                    Err(e) => self.push_notification(e.to_string()),
+                },
             },
             Message::UI(action) => {
-                match action {
+                let view_changed = match action {
                     UIAction::CenterReal(val) => {
-                        if let Ok(center_real) = val.parse::<f64>() {
+                        let parsed = val.parse::<f64>();
+                        if let Ok(center_real) = parsed {
                             self.view_region.center_real = center_real;
                         }
                         self.ui_values.center_real = val;
+                        parsed.is_ok()
                     }
                     UIAction::CenterImag(val) => {
-                        if let Ok(center_imag) = val.parse::<f64>() {
+                        let parsed = val.parse::<f64>();
+                        if let Ok(center_imag) = parsed {
                             self.view_region.center_imag = center_imag;
                         }
                         self.ui_values.center_imag = val;
+                        parsed.is_ok()
                     }
                     UIAction::Zoom(val) => {
-                        if let Ok(factor) = val.parse::<f64>() {
-                            self.zoom_to(factor);
+                        let parsed = val.parse::<Zoom>();
+                        if let Ok(zoom) = parsed {
+                            self.zoom_to(zoom);
                         }
                         self.ui_values.zoom = val;
+                        parsed.is_ok()
                     }
+                };
+                let live_preview = self.ui_values.live_preview;
+                if !view_changed || !live_preview.is_triggered_by(PreviewTrigger::View) {
+                    Command::none()
+                } else if live_preview.delay.is_zero() {
+                    self.render_fast_preview()
+                } else {
+                    // A fast preview for every keystroke would mostly show views that the user
+                    // is only passing through, so wait for them to stop typing instead.
+                    self.schedule_preview(PreviewTrigger::View)
+                }
+            }
+            Message::InteractionSettled(generation) => {
+                // Only render if nothing has happened since the interaction that scheduled this.
+                if generation == self.interaction_generation
+                    && self.ui_values.live_preview.is_enabled()
+                {
+                    self.render_preview()
+                } else {
+                    Command::none()
                 }
-                Command::none()
             }
         }
     }
 
-    fn view(&self) -> Element<Self::Message> {
+    fn view(&self) -> Element<'_, Self::Message> {
         row![
             // An image viewer with an expanding notification field above it.
             column![
+                // The list of keyboard shortcuts or the step of the tour, if either is shown.
+                self.help_overlay(),
+                // The error from the last failed render, if any, with a button for dismissing it.
+                match &self.render_error {
+                    Some(error) => Element::from(row![
+                        Text::new(error).width(Length::Fill),
+                        Button::new("Dismiss")
+                            .on_press(Message::Render(RenderAction::ErrorDismissed)),
+                    ]),
+                    None => Space::new(Length::Shrink, Length::Shrink).into(),
+                },
+                // The warnings about the settings of the latest full render, each with a button for dismissing it.
+                Column::with_children(
+                    self.render_warnings
+                        .iter()
+                        .map(|&warning| {
+                            // Too dark a view comes with a button that does what the warning suggests.
+                            let suggestion = match warning {
+                                RenderWarning::DarkFrame { .. } => Element::from(
+                                    Button::new("Double iterations and re-render")
+                                        .on_press(Message::Render(RenderAction::IterationsDoubled)),
+                                ),
+                                _ => Space::new(Length::Shrink, Length::Shrink).into(),
+                            };
+                            row![
+                                Text::new(format!("Warning: {warning}.")).width(Length::Fill),
+                                suggestion,
+                                Button::new("Dismiss").on_press(Message::Notification(
+                                    NotificationAction::WarningDismissed(warning)
+                                )),
+                            ]
+                            .into()
+                        })
+                        .collect(),
+                ),
+                // The image that could not be saved, if any, with buttons for saving it again.
+                match &self.unsaved_image {
+                    Some(unsaved) => Element::from(row![
+                        Text::new(format!(
+                            "Could not save {}: {}.\nThe image is kept until it is saved or discarded.",
+                            unsaved.path.display(),
+                            unsaved.error
+                        ))
+                        .width(Length::Fill),
+                        Button::new(RETRY).on_press(Message::Save(SaveAction::Retried)),
+                        Button::new(SAVE_ELSEWHERE)
+                            .on_press(Message::Save(SaveAction::ElsewherePressed)),
+                        Button::new("Discard").on_press(Message::Save(SaveAction::Discarded)),
+                    ]),
+                    None => Space::new(Length::Shrink, Length::Shrink).into(),
+                },
                 Text::new(
                     self.notifications
                         .iter()
@@ -453,8 +2112,7 @@ impl Application for MandelViewer {
                         })
                 ),
                 Viewer::new(match &self.image {
-                    Some(img) =>
-                        Handle::from_pixels(img.width(), img.height(), img.to_rgba8().into_raw()),
+                    Some(preview) => preview.handle().clone(),
                     None =>
                         if self.render_in_progress {
                             Handle::from_memory(RENDERING_IN_PROGRESS)
@@ -494,8 +2152,9 @@ impl Application for MandelViewer {
                             .saturating_mul(NonZeroU32::new(2).expect("2 is not zero"))
                     ))
                 ],
-                // A text input field for the number of iterations with buttons on either side to halve or double it.
-                Text::new("Iterations"),
+                // A text input field for the number of iterations with buttons on either side to halve or double it,
+                // and one to choose it automatically.
+                self.tour_label(TourStep::Iterations, "Iterations"),
                 row![
                     Button::new("÷2").on_press(Message::MaxItersUpdated(
                         self.params
@@ -519,8 +2178,12 @@ impl Application for MandelViewer {
                     Button::new("·2").on_press(Message::MaxItersUpdated(
                         self.params
                             .max_iterations
-                            .saturating_mul(NonZeroU32::new(2).expect("2 is not zero"))
+                            .saturating_mul(NonZeroU64::new(2).expect("2 is not zero"))
                     )),
+                    Button::new("auto").on_press(Message::MaxItersUpdated(auto_max_iterations(
+                        self.view_region,
+                        NonZeroU32::from(self.params.y_resolution)
+                    ))),
                 ],
                 Text::new("Re(c)"),
                 TextInput::new("Re(c)", &self.ui_values.center_real)
@@ -530,24 +2193,81 @@ impl Application for MandelViewer {
                 TextInput::new("Im(c)", &self.ui_values.center_imag)
                     .on_input(|val| Message::UI(UIAction::CenterImag(val)))
                     .on_submit(Message::Frame(FrameAction::CenterImagSubmitted)),
-                Text::new("Zoom factor"),
+                // How the coordinates are shown, and a button that copies them.
+                row![
+                    Tooltip::new(
+                        PickList::new(
+                            CoordinateRounding::ALL.to_vec(),
+                            Some(self.ui_values.coordinate_rounding),
+                            Message::CoordinateRoundingSelected
+                        ),
+                        "Show, copy and share the coordinates with only the decimals\nthat the zoom and resolution need, or with every digit.\nThe view itself always keeps every digit"
+                            .to_owned(),
+                        Position::FollowCursor
+                    ),
+                    Button::new("Copy").on_press(Message::CopyCoordinatesPressed),
+                ]
+                .spacing(10),
+                self.tour_label(TourStep::Zoom, "Zoom factor"),
                 row![
                     Button::new("-1").on_press(Message::Frame(FrameAction::ZoomSubmittedWith(
-                        self.zoom - 1.0
+                        self.zoom.level() - 1.0
                     ))),
                     TextInput::new("Zoom factor", &self.ui_values.zoom)
                         .on_input(|val| Message::UI(UIAction::Zoom(val)))
                         .on_submit(Message::Frame(FrameAction::ZoomSubmitted)),
                     Button::new("+1").on_press(Message::Frame(FrameAction::ZoomSubmittedWith(
-                        self.zoom + 1.0
+                        self.zoom.level() + 1.0
                     ))),
                 ],
+                // A drop down list for selecting the fractal.
+                Text::new("Fractal"),
+                PickList::new(
+                    Formula::ALL.to_vec(),
+                    Some(self.params.formula.clone()),
+                    Message::FormulaSelected
+                ),
+                // A drop down list for selecting how the inside of the set is colored.
+                Text::new("Interior"),
+                PickList::new(
+                    InteriorShading::ALL.to_vec(),
+                    Some(self.params.interior_shading),
+                    Message::InteriorShadingSelected
+                ),
                 // A checkbox for rendering the image in grayscale.
                 Checkbox::new("Grayscale", !self.params.color_type.has_color(), |status| {
                     Message::GrayscaleToggled(status)
                 }),
+                // The palette with a histogram of the escape speeds in the current image below it,
+                // and sliders for the range of escape speeds that the palette is stretched over.
+                // Moving one end of the range past the other pushes it along.
+                row![
+                    Text::new("Palette range").width(Length::Fill),
+                    Button::new("Reset")
+                        .on_press(Message::EscapeSpeedRangeUpdated(EscapeSpeedRange::FULL)),
+                ],
+                Image::new(self.palette_histogram.clone()).width(Length::Fill),
+                Tooltip::new(
+                    Slider::new(0.0..=1.0, self.params.escape_speed_range.min, |min| {
+                        let max = self.params.escape_speed_range.max.max(min);
+                        Message::EscapeSpeedRangeUpdated(EscapeSpeedRange::new(min, max))
+                    })
+                    .step(0.001),
+                    "Escape speeds below this get the first color of the palette".to_owned(),
+                    Position::FollowCursor
+                ),
+                Tooltip::new(
+                    Slider::new(0.0..=1.0, self.params.escape_speed_range.max, |max| {
+                        let min = self.params.escape_speed_range.min.min(max);
+                        Message::EscapeSpeedRangeUpdated(EscapeSpeedRange::new(min, max))
+                    })
+                    .step(0.001),
+                    "Escape speeds above this get the last color of the palette".to_owned(),
+                    Position::FollowCursor
+                ),
                 // A slider for determining the number of samples per pixels when doing SSAA,
                 // as well as a toggle for enabling or disabling SSAA.
+                self.tour_label(TourStep::Supersampling, "Supersampling"),
                 row![
                     Tooltip::new(
                         Slider::new(
@@ -571,10 +2291,30 @@ impl Application for MandelViewer {
                     })
                     .spacing(5),
                 ],
+                // A drop down list for where the SSAA samples are placed within each pixel.
+                Tooltip::new(
+                    PickList::new(
+                        SamplePlacement::ALL.to_vec(),
+                        Some(self.params.sample_placement),
+                        |placement| Message::SuperSampling(SSAAAction::PlacementSelected(placement))
+                    ),
+                    "Where the samples are placed in each pixel.\n\"r2\", \"halton\" and \"sobol\" take them from\na low discrepancy sequence, which never lines them up with an edge",
+                    Position::FollowCursor
+                ),
+                // A drop down list for how the samples are combined into pixels.
+                Tooltip::new(
+                    PickList::new(
+                        ReconstructionFilter::ALL.to_vec(),
+                        Some(self.params.reconstruction_filter),
+                        |filter| Message::SuperSampling(SSAAAction::FilterSelected(filter))
+                    ),
+                    "How the samples are combined into pixels.\n\"tent\" and \"gaussian\" weight them by their distance\nto the center of the pixel, which smooths edges with the same\nnumber of samples but takes around twice as long",
+                    Position::FollowCursor
+                ),
                 Space::new(Length::Shrink, Length::Fixed(40.0)),
                 // A button for re-rendering the current view at full resolution,
-                // as well as a checkbox for whether the user wants the image to be re-rendered
-                // whenever they change a setting.
+                // as well as the settings for which changes render a preview of the view
+                // and how long after the last change the preview is rendered.
                 Tooltip::new(
                     if self.render_in_progress {
                         Button::new("rendering...")
@@ -585,25 +2325,249 @@ impl Application for MandelViewer {
                     "Render the current view at full resolution".to_owned(),
                     Position::FollowCursor
                 ),
+                Text::new("Jobs"),
+                self.jobs_panel(),
+                self.desktop_notifications_checkbox(),
+                Text::new(format!("Max CPU: {}%", self.params.cpu_limit.percent())),
                 Tooltip::new(
-                    Checkbox::new("Live preview", self.ui_values.live_preview, |status| {
-                        Message::LiveCheckboxToggled(status)
-                    }),
-                    "Render a low-resolution version\nof the image whenever settings are changed"
-                        .to_owned(),
+                    Slider::new(
+                        10u8..=100,
+                        self.params.cpu_limit.percent() as u8,
+                        |percent| Message::CpuLimitUpdated(
+                            CpuLimit::try_from(f64::from(percent))
+                                .expect("10..=100 is a valid CPU limit")
+                        )
+                    )
+                    .step(10u8),
+                    "Let full renders and exports only use this share of the processor,\nso that they can run in the background without the fans at full speed".to_owned(),
+                    Position::FollowCursor
+                ),
+                Text::new("Live preview"),
+                Column::with_children(
+                    PreviewTrigger::ALL
+                        .into_iter()
+                        .map(|trigger| {
+                            Checkbox::new(
+                                trigger.to_string(),
+                                self.ui_values.live_preview.is_triggered_by(trigger),
+                                move |status| {
+                                    Message::LivePreview(LivePreviewAction::TriggerToggled(
+                                        trigger, status,
+                                    ))
+                                },
+                            )
+                            .into()
+                        })
+                        .collect(),
+                ),
+                Tooltip::new(
+                    Slider::new(
+                        0..=LivePreview::MAX_DELAY_MILLIS,
+                        self.ui_values.live_preview.delay_millis(),
+                        |millis| Message::LivePreview(LivePreviewAction::DelayUpdated(millis))
+                    )
+                    .step(50u32),
+                    format!(
+                        "Render a low-resolution version of the image {} ms\nafter the last change to the checked settings",
+                        self.ui_values.live_preview.delay_millis()
+                    ),
                     Position::FollowCursor
                 ),
                 Space::new(Length::Shrink, Length::Fill),
-                // Finally a button for saving the current view.
+                // Finally a button for saving the current view, and one for copying it to the clipboard.
+                row![
+                    Tooltip::new(
+                        Button::new(self.tour_label(TourStep::Save, "Save current view"))
+                            .on_press(Message::Save(SaveAction::Pressed)),
+                        if !self.params.color_type.has_color()
+                            && !self
+                                .ui_values
+                                .live_preview
+                                .is_triggered_by(PreviewTrigger::Coloring)
+                        {
+                            "WARNING: SAVING IN GRAYSCALE"
+                        } else {
+                            ""
+                        },
+                        Position::FollowCursor
+                    ),
+                    self.copy_image_button(),
+                ]
+                .spacing(10),
+                // A button for sharing the current view, and whether to put a QR code in the shared image.
+                row![
+                    Tooltip::new(
+                        Button::new("Share").on_press(Message::Share(ShareAction::Pressed)),
+                        "Save the view with the command that renders it\nand copy the command to the clipboard"
+                            .to_owned(),
+                        Position::FollowCursor
+                    ),
+                    Checkbox::new("QR code", self.ui_values.share_qr_code, |status| {
+                        Message::Share(ShareAction::QrCodeToggled(status))
+                    }),
+                ]
+                .spacing(10),
+                // Checkboxes for the resolutions to export the view at, and a button that renders
+                // and saves it at all of them. The button is replaced by the progress of the export
+                // while it is running.
+                Text::new("Export presets"),
+                Column::with_children(
+                    EXPORT_PRESETS
+                        .iter()
+                        .zip(self.ui_values.export_presets)
+                        .enumerate()
+                        .map(|(index, (preset, selected))| {
+                            Checkbox::new(
+                                format!(
+                                    "{} ({}x{}, {} samples per pixel)",
+                                    preset.name,
+                                    (f64::from(preset.y_resolution.get()) * self.aspect_ratio)
+                                        as u32,
+                                    preset.y_resolution,
+                                    preset.ssaa.get().pow(2)
+                                ),
+                                selected,
+                                move |status| {
+                                    Message::Export(ExportAction::PresetToggled(index, status))
+                                },
+                            )
+                            .into()
+                        })
+                        .collect(),
+                ),
+                // A checkbox for stamping a logo onto saved and exported images,
+                // and where and how opaque to stamp it once one has been chosen.
+                Checkbox::new("Watermark", self.watermark_logo.is_some(), |status| {
+                    Message::Export(ExportAction::WatermarkToggled(status))
+                }),
+                if self.watermark_logo.is_some() {
+                    Element::from(row![
+                        PickList::new(
+                            WatermarkPosition::ALL.to_vec(),
+                            Some(self.ui_values.watermark_position),
+                            |position| Message::Export(ExportAction::WatermarkPositionSelected(
+                                position
+                            ))
+                        ),
+                        Tooltip::new(
+                            Slider::new(0.0..=1.0, self.ui_values.watermark_opacity, |opacity| {
+                                Message::Export(ExportAction::WatermarkOpacityUpdated(opacity))
+                            })
+                            .step(0.05),
+                            "The opacity of the watermark".to_owned(),
+                            Position::FollowCursor
+                        ),
+                    ])
+                } else {
+                    Space::new(Length::Shrink, Length::Shrink).into()
+                },
+                match &self.export {
+                    Some(batch) => Element::from(column![
+                        Text::new(format!(
+                            "Exporting {} of {}",
+                            batch.finished_jobs + 1,
+                            batch.total_jobs
+                        )),
+                        ProgressBar::new(0.0..=1.0, batch.progress()),
+                    ]),
+                    None => Button::new("Export view")
+                        .on_press(Message::Export(ExportAction::Started))
+                        .into(),
+                },
+                // Sliders for the number of frames of a recorded zoom into the view and how far it zooms in,
+                // and a button that records it and saves it as an animated image.
+                Text::new(format!(
+                    "Zoom recording ({} frames, zooming in {:.0} times)",
+                    self.ui_values.recording_frames,
+                    self.ui_values.recording_zoom_levels.exp2()
+                )),
                 Tooltip::new(
-                    Button::new("Save current view").on_press(Message::SavePressed),
-                    if !self.params.color_type.has_color() && !self.ui_values.live_preview {
-                        "WARNING: SAVING IN GRAYSCALE"
+                    Slider::new(
+                        RECORDING_FRAMES,
+                        self.ui_values.recording_frames,
+                        |frames| Message::Recording(RecordingAction::FramesUpdated(frames))
+                    ),
+                    "The number of frames, which are shown 25 per second".to_owned(),
+                    Position::FollowCursor
+                ),
+                Tooltip::new(
+                    Slider::new(
+                        RECORDING_ZOOM_LEVELS,
+                        self.ui_values.recording_zoom_levels,
+                        |levels| Message::Recording(RecordingAction::ZoomLevelsUpdated(levels))
+                    )
+                    .step(0.5),
+                    "How many times the view is halved in size over the recording".to_owned(),
+                    Position::FollowCursor
+                ),
+                Tooltip::new(
+                    if self.recording_started.is_some() {
+                        Button::new("Recording...")
                     } else {
-                        ""
+                        Button::new("Record zoom")
+                            .on_press(Message::Recording(RecordingAction::Started))
                     },
+                    format!(
+                        "Render a short zoom into the center of the view at {}p\nand save it as an animated GIF or WebP image",
+                        RECORDING_Y_RESOLUTION
+                    ),
+                    Position::FollowCursor
+                ),
+                // The socket of a `mandelbrot batch` process of the same user
+                // and a button that sends the current view to it to be rendered in the background.
+                Text::new("Render server"),
+                TextInput::new("socket of \"mandelbrot batch\"", &self.ui_values.render_server)
+                    .on_input(
+                        |socket| Message::RenderServer(RenderServerAction::SocketUpdated(socket))
+                    )
+                    .on_submit(Message::RenderServer(RenderServerAction::SendPressed)),
+                Tooltip::new(
+                    Button::new("Send to render server")
+                        .on_press(Message::RenderServer(RenderServerAction::SendPressed)),
+                    "Render the current view with the \"mandelbrot batch\" process listening on the socket above",
+                    Position::FollowCursor
+                ),
+                // Fields for bookmarking the current view with a name, notes and tags,
+                // and the bookmarks that match the search, which go to their view when pressed.
+                Text::new("Bookmarks"),
+                TextInput::new("Name", &self.ui_values.bookmark_name)
+                    .on_input(|name| Message::Bookmark(BookmarkAction::NameUpdated(name)))
+                    .on_submit(Message::Bookmark(BookmarkAction::SavePressed)),
+                TextInput::new("Notes", &self.ui_values.bookmark_notes)
+                    .on_input(|notes| Message::Bookmark(BookmarkAction::NotesUpdated(notes))),
+                TextInput::new("Tags, e.g. \"spiral deep\"", &self.ui_values.bookmark_tags)
+                    .on_input(|tags| Message::Bookmark(BookmarkAction::TagsUpdated(tags))),
+                Tooltip::new(
+                    Button::new("Bookmark view")
+                        .on_press(Message::Bookmark(BookmarkAction::SavePressed)),
+                    "A bookmark with the same name is replaced.\nRender every bookmark with a tag with \"mandelbrot bookmarks render TAG\"",
                     Position::FollowCursor
                 ),
+                TextInput::new("Search bookmarks", &self.ui_values.bookmark_search)
+                    .on_input(|query| Message::Bookmark(BookmarkAction::SearchUpdated(query))),
+                Text::new(format!("Tags: {}", self.bookmarks.tags().join(", "))),
+                Column::with_children(
+                    self.bookmarks
+                        .bookmarks
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, bookmark)| bookmark.matches(&self.ui_values.bookmark_search))
+                        .map(|(index, bookmark)| {
+                            row![
+                                Tooltip::new(
+                                    Button::new(Text::new(&bookmark.name))
+                                        .on_press(Message::Bookmark(BookmarkAction::Selected(index))),
+                                    format!("{}\nTags: {}", bookmark.notes, bookmark.tags.join(", ")),
+                                    Position::FollowCursor
+                                ),
+                                Button::new("Remove")
+                                    .on_press(Message::Bookmark(BookmarkAction::Removed(index))),
+                            ]
+                            .spacing(10)
+                            .into()
+                        })
+                        .collect(),
+                ),
                 Space::new(Length::Shrink, Length::FillPortion(1))
             ]
             .width(Length::FillPortion(1)),
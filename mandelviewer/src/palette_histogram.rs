@@ -0,0 +1,80 @@
+//! Draws the palette strip with a histogram of the escape speeds of the current view below it,
+//! so that the user can see which part of the palette the image uses and stretch the palette over it.
+
+use color_space::{LinearRGB, Palette};
+use iced::widget::image::Handle;
+use mandellib::EscapeSpeedRange;
+
+/// The number of bins the escape speeds are counted in, which is also the width of the image in pixels.
+pub const HISTOGRAM_BINS: usize = 256;
+const STRIP_HEIGHT: usize = 16;
+const BARS_HEIGHT: usize = 48;
+
+const BACKGROUND: [u8; 4] = [32, 32, 32, 255];
+const BAR: [u8; 4] = [200, 200, 200, 255];
+/// The color of the bars of escape speeds outside the range, which are all colored like its ends.
+const CLAMPED_BAR: [u8; 4] = [100, 100, 100, 255];
+const HANDLE: [u8; 4] = [255, 64, 64, 255];
+
+/// Returns an image with the color that every escape speed gets with the given palette and range on top,
+/// and the histogram in `counts` below it, with lines marking the ends of the range.
+///
+/// The bars are scaled logarithmically, since the escape speeds are often bunched up in a few bins.
+pub fn palette_histogram(
+    palette: &Palette,
+    range: EscapeSpeedRange,
+    grayscale: bool,
+    counts: &[u32],
+) -> Handle {
+    let width = HISTOGRAM_BINS;
+    let height = STRIP_HEIGHT + BARS_HEIGHT;
+    let mut pixels = vec![0; width * height * 4];
+
+    let position = |x: usize| x as f64 / (width - 1) as f64;
+    let strip: Vec<[u8; 4]> = (0..width)
+        .map(|x| {
+            let escape_speed = range.normalize(position(x));
+            let color = if grayscale {
+                LinearRGB::new(escape_speed, escape_speed, escape_speed)
+            } else {
+                palette.color(escape_speed)
+            };
+            let [r, g, b] = color.to_srgb();
+            [r, g, b, 255]
+        })
+        .collect();
+
+    let max_count = counts.iter().copied().max().unwrap_or(0);
+    let bar_height = |x: usize| {
+        let count = counts.get(x * counts.len() / width).copied().unwrap_or(0);
+        if max_count == 0 {
+            0
+        } else {
+            (f64::from(count).ln_1p() / f64::from(max_count).ln_1p() * BARS_HEIGHT as f64).round()
+                as usize
+        }
+    };
+    let handles = [range.min, range.max]
+        .map(|end| (end.clamp(0.0, 1.0) * (width - 1) as f64).round() as usize);
+
+    for (y, row) in pixels.chunks_exact_mut(width * 4).enumerate() {
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let color = if handles.contains(&x) {
+                HANDLE
+            } else if y < STRIP_HEIGHT {
+                strip[x]
+            } else if height - y <= bar_height(x) {
+                if (range.min..=range.max).contains(&position(x)) {
+                    BAR
+                } else {
+                    CLAMPED_BAR
+                }
+            } else {
+                BACKGROUND
+            };
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    Handle::from_pixels(width as u32, height as u32, pixels)
+}
@@ -0,0 +1,55 @@
+//! The rendered image together with the handle shown by the image viewer widget.
+//!
+//! The handle stores its pixels behind a reference count, so it can be cloned for every redraw
+//! of the view without copying the image. The pixels are shared between the handle and the preview,
+//! so that they can be read back, e.g. in order to save them, without decoding the handle.
+//! The only copy made is the one needed to convert the rendered image into the RGBA layout
+//! that the widget expects, and no copy is made at all when the image is rendered in that layout to begin with.
+
+use std::sync::Arc;
+
+use iced::widget::image::Handle;
+use image::{DynamicImage, RgbaImage};
+
+/// The pixels of a rendered image, shared between a [`Preview`] and its handle.
+#[derive(Debug)]
+struct SharedPixels(Arc<RgbaImage>);
+
+impl AsRef<[u8]> for SharedPixels {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_raw()
+    }
+}
+
+/// A rendered image and the handle that shows it in the image viewer.
+#[derive(Debug, Clone)]
+pub struct Preview {
+    pixels: Arc<RgbaImage>,
+    handle: Handle,
+}
+
+impl Preview {
+    /// Moves a rendered image into a preview.
+    ///
+    /// Images with the [`Rgba8`](color_space::SupportedColorType::Rgba8) color type are moved into
+    /// the preview without copying the pixel data, other color types are converted with a single copy.
+    pub fn new(image: DynamicImage) -> Self {
+        let pixels = Arc::new(image.into_rgba8());
+        let handle = Handle::from_pixels(
+            pixels.width(),
+            pixels.height(),
+            SharedPixels(Arc::clone(&pixels)),
+        );
+        Self { pixels, handle }
+    }
+
+    /// The handle that shows the image in the image viewer.
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// Copies the pixels of the image, e.g. in order to stamp a watermark on them before saving them.
+    pub fn to_image(&self) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::clone(&self.pixels))
+    }
+}
@@ -0,0 +1,75 @@
+//! Keeping the escape data of the latest full render, so that changing only the colors of the view
+//! colors it again instead of iterating every point of it again, and raising only its maximum number
+//! of iterations continues the orbits of the points from where they stopped.
+
+use std::sync::Arc;
+
+use mandellib::{Frame, RenderParameters, ResumableEscapeBuffer, Zoom};
+
+use crate::render_server::view_arguments;
+
+/// The largest amount of memory in bytes that the escape data of a render may take up for it to be kept.
+/// The data holds every sample of the image, so larger renders are rendered without keeping it.
+const MAX_CACHE_BYTES: u64 = 1 << 30;
+
+/// The escape data of a full render along with the view that it shows.
+#[derive(Debug, Clone)]
+pub struct RecolorCache {
+    buffer: Arc<ResumableEscapeBuffer>,
+    /// The arguments that render the view apart from its colors.
+    view: Vec<String>,
+}
+
+impl RecolorCache {
+    pub fn new(
+        buffer: Arc<ResumableEscapeBuffer>,
+        params: &RenderParameters,
+        view_region: Frame,
+        zoom: Zoom,
+    ) -> Self {
+        Self {
+            buffer,
+            view: uncolored_view(params, view_region, zoom),
+        }
+    }
+
+    /// Returns whether the escape data is that of the given view, so that the view only needs to be colored.
+    pub fn shows(&self, params: &RenderParameters, view_region: Frame, zoom: Zoom) -> bool {
+        self.view == uncolored_view(params, view_region, zoom)
+    }
+
+    pub fn buffer(&self) -> Arc<ResumableEscapeBuffer> {
+        Arc::clone(&self.buffer)
+    }
+
+    /// Returns the escape data if the given view only differs from it by a larger maximum number of iterations,
+    /// so that it can be computed by continuing the orbits of its points.
+    pub fn resumable(
+        &self,
+        params: &RenderParameters,
+        view_region: Frame,
+        zoom: Zoom,
+    ) -> Option<Arc<ResumableEscapeBuffer>> {
+        let max_iterations = self.buffer.escape_buffer().max_iterations();
+        let mut same_iterations = params.clone();
+        same_iterations.max_iterations = max_iterations;
+        (params.max_iterations > max_iterations && self.shows(&same_iterations, view_region, zoom))
+            .then(|| self.buffer())
+    }
+
+    /// Returns whether the escape data of a render with the given parameters is small enough to be kept.
+    pub fn fits(params: &RenderParameters) -> bool {
+        let samples = u64::from(u32::from(params.x_resolution))
+            * u64::from(u32::from(params.y_resolution))
+            * u64::from(params.sqrt_samples_per_pixel.get()).pow(2);
+        samples.saturating_mul(ResumableEscapeBuffer::BYTES_PER_SAMPLE as u64) <= MAX_CACHE_BYTES
+    }
+}
+
+/// Returns the arguments that render the view, without the ones that only change its colors.
+/// The escape speed range and palette are not part of the arguments to begin with.
+fn uncolored_view(params: &RenderParameters, view_region: Frame, zoom: Zoom) -> Vec<String> {
+    let mut arguments = view_arguments(params, view_region, zoom);
+    arguments.retain(|argument| argument != "--grayscale");
+    arguments
+}
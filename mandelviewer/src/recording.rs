@@ -0,0 +1,239 @@
+//! Recording a short zoom into the current view as an animated GIF or WebP image.
+//!
+//! The frames are rendered at a low resolution, so that a recording is done in moments
+//! and small enough to share. The view zooms in by the same factor every frame,
+//! which looks like a steady zoom, and every frame gets enough iterations for its depth.
+
+use core::num::{NonZeroU32, NonZeroU8};
+use core::ops::RangeInclusive;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::codecs::webp::WebPEncoder;
+use image::{Delay, ExtendedColorType, ImageError, RgbaImage};
+use mandellib::{
+    auto_max_iterations, try_render_with_progress, Frame, Progress, RenderParameters, U32AndUsize,
+};
+
+/// The height of the frames of a recording in pixels.
+pub const RECORDING_Y_RESOLUTION: NonZeroU32 = NonZeroU32::new(240).unwrap();
+
+/// The frames are supersampled a little, since the edges of the set flicker from frame to frame otherwise.
+const RECORDING_SSAA: NonZeroU8 = NonZeroU8::new(2).unwrap();
+
+/// How long every frame is shown, which plays the recording at 25 frames per second.
+const FRAME_DURATION_MS: u32 = 40;
+
+/// The number of frames that a recording can have.
+pub const RECORDING_FRAMES: RangeInclusive<u32> = 10..=250;
+
+/// The number of times that a recording can zoom in by a factor of two.
+pub const RECORDING_ZOOM_LEVELS: RangeInclusive<f64> = 1.0..=30.0;
+
+/// The extensions of the formats that recordings can be saved in.
+pub const RECORDING_EXTENSIONS: [&str; 2] = ["gif", "webp"];
+
+/// A zoom into a view that is rendered frame by frame and saved as an animated image.
+#[derive(Debug, Clone)]
+pub struct ZoomRecording {
+    /// The parameters that the first frame is rendered with.
+    pub params: RenderParameters,
+    /// The view of the first frame.
+    pub view_region: Frame,
+    /// The number of times that the view is zoomed in by a factor of two over the whole recording.
+    pub zoom_levels: f64,
+    pub frames: NonZeroU32,
+    pub path: PathBuf,
+}
+
+impl ZoomRecording {
+    /// Returns a recording of a zoom into the view that renders its frames with the parameters,
+    /// but at the resolution of a recording.
+    ///
+    /// # Errors
+    /// Returns an error if the resolution of the frames does not fit in the types that it must.
+    pub fn new(
+        params: &RenderParameters,
+        view_region: Frame,
+        zoom_levels: f64,
+        frames: NonZeroU32,
+        path: PathBuf,
+    ) -> Result<Self, String> {
+        let aspect_ratio = f64::from(params.x_resolution) / f64::from(params.y_resolution);
+        let x_resolution = (f64::from(RECORDING_Y_RESOLUTION.get()) * aspect_ratio).max(1.0) as u32;
+        let mut params = params.clone();
+        params.x_resolution = U32AndUsize::try_from(x_resolution).map_err(|e| e.to_string())?;
+        params.y_resolution =
+            U32AndUsize::try_from(RECORDING_Y_RESOLUTION).map_err(|e| e.to_string())?;
+        params.sqrt_samples_per_pixel = RECORDING_SSAA;
+        Ok(Self {
+            params,
+            view_region,
+            zoom_levels,
+            frames,
+            path,
+        })
+    }
+
+    /// The name of the file that the recording is saved as, for showing to the user.
+    pub fn file_name(&self) -> String {
+        self.path.file_name().map_or_else(
+            || self.path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    }
+
+    /// Returns the view of the frame with the given index.
+    fn frame_region(&self, frame: u32) -> Frame {
+        let progress = if self.frames.get() > 1 {
+            f64::from(frame) / f64::from(self.frames.get() - 1)
+        } else {
+            0.0
+        };
+        self.view_region
+            .zoom_by((self.zoom_levels * progress).exp2())
+    }
+
+    /// Renders every frame while reporting how many are done, and saves them as an animation
+    /// at the path of the recording in the format given by its extension.
+    /// Returns the path or a description of what went wrong.
+    pub fn run(self, progress: &dyn Progress) -> Result<PathBuf, String> {
+        let is_webp = match self
+            .path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .as_deref()
+        {
+            Some("gif") => false,
+            Some("webp") => true,
+            _ => {
+                return Err(format!(
+                    "could not record {}: zooms can only be saved as \".gif\" or \".webp\" files",
+                    self.path.display()
+                ))
+            }
+        };
+
+        progress.start(self.frames.get().into());
+        let mut images = Vec::new();
+        for frame in 0..self.frames.get() {
+            let view_region = self.frame_region(frame);
+            let mut params = self.params.clone();
+            // Deeper frames need more iterations to show the edge of the set as sharply.
+            params.max_iterations = params
+                .max_iterations
+                .max(auto_max_iterations(view_region, params.y_resolution.into()));
+            let image = try_render_with_progress(&params, view_region, &FrameProgress(progress))
+                .map_err(|e| format!("could not record {}: {e}", self.path.display()))?;
+            images.push(image.into_rgba8());
+            progress.advance(1);
+        }
+
+        let save_error =
+            |e: &dyn core::fmt::Display| format!("could not save {}: {e}", self.path.display());
+        let mut writer = BufWriter::new(File::create(&self.path).map_err(|e| save_error(&e))?);
+        if is_webp {
+            write_webp(&images, &mut writer).map_err(|e| save_error(&e))?;
+        } else {
+            write_gif(images, &mut writer).map_err(|e| save_error(&e))?;
+        }
+        writer.flush().map_err(|e| save_error(&e))?;
+        Ok(self.path)
+    }
+}
+
+/// Passes the cancellation of a recording on to the render of one of its frames,
+/// whose progress is not reported since the recording counts whole frames.
+struct FrameProgress<'a>(&'a dyn Progress);
+
+impl Progress for FrameProgress<'_> {
+    fn advance(&self, _completed: u64) {}
+
+    fn is_cancelled(&self) -> bool {
+        self.0.is_cancelled()
+    }
+}
+
+/// Writes the images as the frames of an animated GIF image that loops forever.
+fn write_gif(images: Vec<RgbaImage>, writer: impl Write) -> Result<(), ImageError> {
+    let mut encoder = GifEncoder::new_with_speed(writer, 10);
+    encoder.set_repeat(Repeat::Infinite)?;
+    encoder.encode_frames(images.into_iter().map(|image| {
+        image::Frame::from_parts(
+            image,
+            0,
+            0,
+            Delay::from_numer_denom_ms(FRAME_DURATION_MS, 1),
+        )
+    }))
+}
+
+/// Writes the images as the frames of an animated lossless WebP image that loops forever.
+/// All images must have the same size, and there must be at least one.
+///
+/// The image crate can only encode still WebP images, so every frame is encoded as one,
+/// and its image data is put in an animation frame chunk of a WebP file with an animation header,
+/// as laid out in the WebP container specification.
+fn write_webp(images: &[RgbaImage], mut writer: impl Write) -> Result<(), ImageError> {
+    let (width, height) = images[0].dimensions();
+
+    // The header of the animation: that the file is animated and its frames have an alpha channel,
+    // the size of the canvas, and that the animation loops forever over a transparent background.
+    let mut chunks = Vec::new();
+    let mut header = vec![0b1_0010, 0, 0, 0];
+    header.extend(u24(width - 1));
+    header.extend(u24(height - 1));
+    push_chunk(&mut chunks, b"VP8X", &header);
+    push_chunk(&mut chunks, b"ANIM", &[0, 0, 0, 0, 0, 0]);
+
+    for image in images {
+        let mut still = Vec::new();
+        WebPEncoder::new_lossless(&mut still).encode(
+            image.as_raw(),
+            width,
+            height,
+            ExtendedColorType::Rgba8,
+        )?;
+        // Every frame covers the whole canvas, is shown for the frame duration,
+        // and replaces the frame before it instead of being blended onto it.
+        let mut frame = vec![0; 6];
+        frame.extend(u24(width - 1));
+        frame.extend(u24(height - 1));
+        frame.extend(u24(FRAME_DURATION_MS));
+        frame.push(0b10);
+        // The chunks of the image data follow the "RIFF" tag, file size and "WEBP" tag of the still image.
+        frame.extend_from_slice(&still[12..]);
+        push_chunk(&mut chunks, b"ANMF", &frame);
+    }
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&chunk_size(4 + chunks.len()).to_le_bytes())?;
+    writer.write_all(b"WEBP")?;
+    writer.write_all(&chunks)?;
+    Ok(())
+}
+
+/// Appends a chunk of a RIFF file with the given tag and contents to the bytes,
+/// padded to an even number of bytes.
+fn push_chunk(bytes: &mut Vec<u8>, tag: &[u8; 4], contents: &[u8]) {
+    bytes.extend_from_slice(tag);
+    bytes.extend_from_slice(&chunk_size(contents.len()).to_le_bytes());
+    bytes.extend_from_slice(contents);
+    if contents.len() % 2 == 1 {
+        bytes.push(0);
+    }
+}
+
+/// The size of a chunk as it is written in a RIFF file. Frames of recordings are far smaller than 4 GiB.
+fn chunk_size(size: usize) -> u32 {
+    u32::try_from(size).unwrap_or(u32::MAX)
+}
+
+/// The three least significant bytes of the number in little endian order,
+/// which is how the sizes and durations of animations are stored in WebP files.
+fn u24(value: u32) -> [u8; 3] {
+    let [a, b, c, _] = value.to_le_bytes();
+    [a, b, c]
+}
@@ -0,0 +1,106 @@
+//! Sending the current view to a `mandelbrot batch` process, which renders it in the background.
+//!
+//! The view is sent over the local socket that the process listens on, which only the user who started it
+//! can connect to, as one line of the command line arguments that render it, separated by tabs.
+//! The process answers with where it will save the image or why it rejected the view.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use mandellib::{
+    EscapeSpeedRange, Exponent, Frame, ReconstructionFilter, RenderParameters, RenderingProfile,
+    SamplePlacement, Zoom,
+};
+
+/// Connects to the socket of a render server, see the [module documentation](self).
+#[cfg(unix)]
+fn connect(socket: &Path) -> std::io::Result<std::os::unix::net::UnixStream> {
+    /// How long to wait for the render server to answer.
+    const SERVER_TIMEOUT: core::time::Duration = core::time::Duration::from_secs(5);
+
+    mandellib::check_render_server_owner(socket)?;
+    let stream = std::os::unix::net::UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(SERVER_TIMEOUT))?;
+    Ok(stream)
+}
+
+/// Connects to the named pipe of a render server, see the [module documentation](self).
+#[cfg(windows)]
+fn connect(socket: &Path) -> std::io::Result<std::fs::File> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(socket)
+}
+
+/// Returns the command line arguments of `mandelbrot` that render the given view.
+pub fn view_arguments(params: &RenderParameters, view_region: Frame, zoom: Zoom) -> Vec<String> {
+    let mut arguments = vec![
+        format!("--real-center={}", view_region.center_real),
+        format!("--imag-center={}", view_region.center_imag),
+        format!("--zoom-level={zoom}"),
+        format!(
+            "--resolution={}x{}",
+            u32::from(params.x_resolution),
+            u32::from(params.y_resolution)
+        ),
+        format!("--ssaa={}", params.sqrt_samples_per_pixel),
+        format!("--max-iterations={}", params.max_iterations),
+        format!("--fractal={}", params.formula),
+        format!("--interior={}", params.interior_shading),
+        // The viewer renders with the defaults of the latest profile.
+        format!("--profile={}", RenderingProfile::LATEST),
+    ];
+    if params.sample_placement != SamplePlacement::default() {
+        arguments.push(format!("--sample-placement={}", params.sample_placement));
+    }
+    if params.seed != 0 {
+        arguments.push(format!("--seed={}", params.seed));
+    }
+    if params.escape_speed_range != EscapeSpeedRange::FULL {
+        arguments.push(format!(
+            "--escape-speed-range={}",
+            params.escape_speed_range
+        ));
+    }
+    if params.reconstruction_filter != ReconstructionFilter::default() {
+        arguments.push(format!("--filter={}", params.reconstruction_filter));
+    }
+    if params.exponent != Exponent::default() {
+        arguments.push(format!("--exponent={}", params.exponent));
+    }
+    if !params.color_type.has_color() {
+        arguments.push("--grayscale".to_owned());
+    }
+    arguments
+}
+
+/// Sends the view described by the arguments to the render server listening on `socket`,
+/// and returns the path that the server will save the image at,
+/// or a description of what went wrong.
+pub fn send_view(socket: &Path, arguments: &[String]) -> Result<String, String> {
+    let connection = connect(socket).map_err(|e| {
+        format!(
+            "could not connect to the render server at {}: {e}",
+            socket.display()
+        )
+    })?;
+
+    // The server answers every line, so there is no need to close our half of the connection.
+    let mut answer = String::new();
+    writeln!(&connection, "{}", arguments.join("\t"))
+        .and_then(|()| BufReader::new(&connection).read_line(&mut answer))
+        .map_err(|e| format!("could not send the view to {}: {e}", socket.display()))?;
+
+    let answer = answer.trim_end();
+    match (
+        answer.strip_prefix("queued "),
+        answer.strip_prefix("error: "),
+    ) {
+        (Some(path), _) => Ok(path.to_owned()),
+        (None, Some(e)) => Err(format!("the render server rejected the view: {e}")),
+        (None, None) => Err(format!(
+            "unexpected answer from the render server: \"{answer}\""
+        )),
+    }
+}
@@ -0,0 +1,95 @@
+//! Sharing the current view as a PNG image that can be rendered again by anyone who receives it.
+//!
+//! The image carries the command line arguments of `mandelbrot` that render the view in the same
+//! PNG text chunk that `mandelbrot` records its own arguments in, so `mandelbrot --seed-from`
+//! can render it again at any resolution. The arguments are also given as a line of text
+//! that can be pasted anywhere, and can be stamped onto the image as a QR code.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use image::{DynamicImage, GrayImage, Luma};
+use mandellib::{Watermark, WatermarkPosition};
+use qrcode::{Color, QrCode};
+
+/// The keyword of the PNG text chunk that `mandelbrot` reads the arguments from.
+const ARGUMENTS_KEYWORD: &str = "Mandelbrot arguments";
+
+/// The width of the light border around a QR code, in modules, that scanners need to find it.
+const QR_QUIET_ZONE: u32 = 4;
+
+/// The largest fraction of the shortest side of the image that the QR code covers.
+const QR_SIZE_FRACTION: u32 = 4;
+
+/// Returns the command that renders the view described by the arguments.
+pub fn view_string(arguments: &[String]) -> String {
+    format!("mandelbrot {}", arguments.join(" "))
+}
+
+/// Stamps a QR code of the text into the bottom right corner of the image.
+pub fn stamp_qr_code(image: &mut DynamicImage, text: &str) -> Result<(), String> {
+    let code =
+        QrCode::new(text.as_bytes()).map_err(|e| format!("could not make a QR code: {e}"))?;
+    // The width of a QR code is at most 177 modules, so it fits in a u32.
+    let modules = code.width() as u32;
+    let colors = code.to_colors();
+
+    let size_with_border = modules + 2 * QR_QUIET_ZONE;
+    let module_size =
+        (image.width().min(image.height()) / QR_SIZE_FRACTION / size_with_border).max(1);
+    let qr_image = GrayImage::from_fn(
+        size_with_border * module_size,
+        size_with_border * module_size,
+        |x, y| {
+            let (column, row) = (x / module_size, y / module_size);
+            let is_dark = (QR_QUIET_ZONE..QR_QUIET_ZONE + modules).contains(&column)
+                && (QR_QUIET_ZONE..QR_QUIET_ZONE + modules).contains(&row)
+                && colors[((row - QR_QUIET_ZONE) * modules + column - QR_QUIET_ZONE) as usize]
+                    == Color::Dark;
+            Luma([if is_dark { 0 } else { u8::MAX }])
+        },
+    );
+
+    Watermark::new(
+        DynamicImage::ImageLuma8(qr_image),
+        WatermarkPosition::BottomRight,
+        1.0,
+    )
+    .map_err(|e| e.to_string())?
+    .stamp(image);
+    Ok(())
+}
+
+/// Saves the image as a PNG at the given path with the arguments that render it stored in it,
+/// in grayscale unless `has_color` is true.
+pub fn save_shared(
+    image: &DynamicImage,
+    path: &Path,
+    has_color: bool,
+    arguments: &[String],
+) -> Result<(), String> {
+    let (color_type, pixels) = if has_color {
+        (png::ColorType::Rgb, image.to_rgb8().into_raw())
+    } else {
+        (png::ColorType::Grayscale, image.to_luma8().into_raw())
+    };
+
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), image.width(), image.height());
+    encoder.set_color(color_type);
+    encoder.set_depth(png::BitDepth::Eight);
+    let encoding_error = |e: png::EncodingError| format!("could not encode the image: {e}");
+    encoder
+        .add_text_chunk(
+            "Software".to_owned(),
+            format!("mandelviewer {}", env!("CARGO_PKG_VERSION")),
+        )
+        .map_err(encoding_error)?;
+    encoder
+        .add_itxt_chunk(ARGUMENTS_KEYWORD.to_owned(), arguments.join("\n"))
+        .map_err(encoding_error)?;
+    let mut writer = encoder.write_header().map_err(encoding_error)?;
+    writer.write_image_data(&pixels).map_err(encoding_error)?;
+    writer.finish().map_err(encoding_error)
+}
@@ -0,0 +1,52 @@
+//! The settings of the viewer that are kept between runs.
+//!
+//! They are stored as one `key = value` pair per line. Keys that are not known are ignored,
+//! so that the file can be shared between versions of the viewer.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The file that the settings are kept in.
+pub const DEFAULT_CONFIG_FILE: &str = "mandelviewer_settings.txt";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ViewerConfig {
+    /// Whether the tour of the main controls has been finished or skipped,
+    /// so that it is not shown when the viewer is started.
+    pub tour_dismissed: bool,
+}
+
+impl ViewerConfig {
+    /// Reads the settings in the file at the path.
+    /// A file that does not exist holds the default settings, since none have been saved yet.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("could not open {}: {e}", path.display())),
+        };
+        let mut config = Self::default();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("\"{line}\" in {} is not a setting", path.display()))?;
+            if key.trim() == "tour_dismissed" {
+                config.tour_dismissed = value.trim().parse().map_err(|_| {
+                    format!(
+                        "tour_dismissed in {} must be true or false, not \"{}\"",
+                        path.display(),
+                        value.trim()
+                    )
+                })?;
+            }
+        }
+        Ok(config)
+    }
+
+    /// Writes the settings to the file at the path, replacing what was there.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, format!("tour_dismissed = {}\n", self.tour_dismissed))
+            .map_err(|e| format!("could not save the settings to {}: {e}", path.display()))
+    }
+}